@@ -0,0 +1,413 @@
+//! Reading and writing overlapping AMR hierarchies (`.vthb` and `.vth` files).
+//!
+//! An overlapping AMR (adaptive mesh refinement) data set is a hierarchy of refinement levels.
+//! Each level has a uniform grid spacing and holds a number of boxes, which are image data sets
+//! covering the refined parts of the domain. Finer levels overlap the coarser ones. The
+//! hierarchy file only describes the levels and boxes, while the data of each box is stored in a
+//! separate `.vti` file referenced by it.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::amr::OverlappingAmr;
+//!
+//! let mut amr = OverlappingAmr::import("output/amr.vthb").expect("Failed to load hierarchy");
+//! for (level, amr_level) in amr.levels.iter().enumerate() {
+//!     println!(
+//!         "level {}: {} boxes, refinement ratio {:?}",
+//!         level,
+//!         amr_level.boxes.len(),
+//!         amr.refinement_ratio(level)
+//!     );
+//! }
+//! amr.load_all().expect("Failed to load boxes");
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::model::{self, DataSet, Vtk};
+use crate::pvd::escape;
+use crate::xml::{self, vector3, Extent};
+use crate::Error;
+
+/// A box of an AMR level.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AmrBox {
+    /// The cell index ranges covered by this box given by `[x0, x1, y0, y1, z0, z1]`.
+    ///
+    /// Indices are relative to the grid of the level containing this box, and both ends are
+    /// inclusive.
+    pub amr_box: [i32; 6],
+    /// The path to the referenced `.vti` file, if any.
+    ///
+    /// Relative paths in the hierarchy file are resolved against the directory containing it.
+    /// Boxes without a file and without data are empty.
+    pub file: Option<PathBuf>,
+    /// The loaded or inline image data, if any.
+    pub data: Option<Vtk>,
+}
+
+impl AmrBox {
+    /// Constructs a box covering `amr_box` holding the given image data.
+    pub fn inline(amr_box: [i32; 6], vtk: Vtk) -> AmrBox {
+        AmrBox {
+            amr_box,
+            file: None,
+            data: Some(vtk),
+        }
+    }
+
+    /// Constructs a box covering `amr_box` referencing the image data stored at the given path.
+    pub fn source(amr_box: [i32; 6], file: impl Into<PathBuf>) -> AmrBox {
+        AmrBox {
+            amr_box,
+            file: Some(file.into()),
+            data: None,
+        }
+    }
+
+    /// Loads the referenced image data if it hasn't been loaded already.
+    ///
+    /// Returns `None` if this box is empty.
+    pub fn load(&mut self) -> Result<Option<&Vtk>, Error> {
+        if self.data.is_none() {
+            if let Some(file) = &self.file {
+                self.data = Some(Vtk::import(file)?);
+            }
+        }
+        Ok(self.data.as_ref())
+    }
+}
+
+/// A single refinement level of an AMR hierarchy.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct AmrLevel {
+    /// The grid spacing of all boxes in this level.
+    pub spacing: [f32; 3],
+    /// Boxes in this level.
+    pub boxes: Vec<AmrBox>,
+}
+
+impl AmrLevel {
+    /// Constructs an empty level with the given spacing.
+    pub fn new(spacing: [f32; 3]) -> AmrLevel {
+        AmrLevel {
+            spacing,
+            boxes: Vec::new(),
+        }
+    }
+}
+
+/// An overlapping AMR hierarchy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlappingAmr {
+    /// The origin of the coarsest level.
+    pub origin: [f32; 3],
+    /// The axes spanned by the grids, such as `"XYZ"` or `"XY"`.
+    pub grid_description: String,
+    /// Levels ordered from coarsest to finest.
+    pub levels: Vec<AmrLevel>,
+}
+
+impl Default for OverlappingAmr {
+    fn default() -> OverlappingAmr {
+        OverlappingAmr {
+            origin: [0.0; 3],
+            grid_description: String::from("XYZ"),
+            levels: Vec::new(),
+        }
+    }
+}
+
+impl OverlappingAmr {
+    /// Reads the given hierarchy file without loading any of the referenced boxes.
+    ///
+    /// Boxes can be loaded later using [`AmrBox::load`] or [`OverlappingAmr::load_all`].
+    pub fn import(file_path: impl AsRef<Path>) -> Result<OverlappingAmr, Error> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let f = std::io::BufReader::new(std::fs::File::open(file_path)?);
+        let mut reader = quick_xml::Reader::from_reader(f);
+        reader.expand_empty_elements(true).trim_text(true);
+        let vthb = VthbFile::deserialize(&mut quick_xml::de::Deserializer::new(reader))
+            .map_err(xml::Error::from)?;
+        let VthbAmr {
+            origin,
+            grid_description,
+            mut blocks,
+        } = vthb.amr;
+
+        // Levels may be listed in any order.
+        blocks.sort_by_key(|block| block.level);
+        let levels = blocks
+            .into_iter()
+            .map(|block| AmrLevel {
+                spacing: block.spacing,
+                boxes: block
+                    .data_sets
+                    .into_iter()
+                    .map(|data_set| {
+                        let [x, y, z] = model::Extent::from(data_set.amr_box).into_ranges();
+                        AmrBox {
+                            amr_box: [
+                                *x.start(),
+                                *x.end(),
+                                *y.start(),
+                                *y.end(),
+                                *z.start(),
+                                *z.end(),
+                            ],
+                            file: data_set
+                                .file
+                                .filter(|file| !file.is_empty())
+                                .map(|file| dir.join(file)),
+                            data: None,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(OverlappingAmr {
+            origin,
+            grid_description,
+            levels,
+        })
+    }
+
+    /// Reads the given hierarchy file and loads all of the referenced boxes.
+    pub fn import_loaded(file_path: impl AsRef<Path>) -> Result<OverlappingAmr, Error> {
+        let mut amr = OverlappingAmr::import(file_path)?;
+        amr.load_all()?;
+        Ok(amr)
+    }
+
+    /// Loads all boxes that haven't been loaded yet.
+    pub fn load_all(&mut self) -> Result<(), Error> {
+        for level in self.levels.iter_mut() {
+            for amr_box in level.boxes.iter_mut() {
+                amr_box.load()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the refinement ratio between the given level and the next coarser one.
+    ///
+    /// The ratio is determined by the spacing of the two levels along the first axis. Returns
+    /// `None` for the coarsest level and for levels that don't exist.
+    pub fn refinement_ratio(&self, level: usize) -> Option<u32> {
+        let coarse = self.levels.get(level.checked_sub(1)?)?;
+        let fine = self.levels.get(level)?;
+        Some((coarse.spacing[0] / fine.spacing[0]).round() as u32)
+    }
+
+    /// Writes this hierarchy to the given `.vthb` or `.vth` file.
+    ///
+    /// Boxes holding data are written in XML format to `<stem>/<stem>_<level>_<n>.vti` next to
+    /// the hierarchy file, where `n` is the index of the box within its level. Their data must
+    /// be image data. Boxes without data only reference their file, which is written relative to
+    /// the hierarchy file where possible.
+    pub fn export(&self, file_path: impl AsRef<Path>) -> Result<(), Error> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let stem = file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or(Error::UnknownFileExtension(None))?;
+        // Older `.vth` files describe the same hierarchy under a different name.
+        let data_type = match file_path.extension().and_then(|s| s.to_str()) {
+            Some("vth") => "vtkHierarchicalBoxDataSet",
+            _ => "vtkOverlappingAMR",
+        };
+
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\"?>\n");
+        out.push_str(&format!(
+            "<VTKFile type=\"{}\" version=\"1.1\" byte_order=\"LittleEndian\">\n",
+            data_type
+        ));
+        let [x, y, z] = self.origin;
+        out.push_str(&format!(
+            "  <{} origin=\"{} {} {}\" grid_description=\"{}\">\n",
+            data_type,
+            x,
+            y,
+            z,
+            escape(&self.grid_description)
+        ));
+        for (level, amr_level) in self.levels.iter().enumerate() {
+            let [x, y, z] = amr_level.spacing;
+            out.push_str(&format!(
+                "    <Block level=\"{}\" spacing=\"{} {} {}\">\n",
+                level, x, y, z
+            ));
+            for (index, amr_box) in amr_level.boxes.iter().enumerate() {
+                let [x0, x1, y0, y1, z0, z1] = amr_box.amr_box;
+                out.push_str(&format!(
+                    "      <DataSet index=\"{}\" amr_box=\"{} {} {} {} {} {}\"",
+                    index, x0, x1, y0, y1, z0, z1
+                ));
+                let file = if let Some(vtk) = &amr_box.data {
+                    if !matches!(vtk.data, DataSet::ImageData { .. }) {
+                        return Err(model::Error::PieceDataMismatch.into());
+                    }
+                    let file = format!("{}/{}_{}_{}.vti", stem, stem, level, index);
+                    let path = dir.join(&file);
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    vtk.clone().export(&path)?;
+                    Some(file)
+                } else {
+                    amr_box.file.as_ref().map(|file| {
+                        file.strip_prefix(dir)
+                            .unwrap_or(file)
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                };
+                if let Some(file) = file {
+                    out.push_str(&format!(" file=\"{}\"", escape(&file)));
+                }
+                out.push_str("/>\n");
+            }
+            out.push_str("    </Block>\n");
+        }
+        out.push_str(&format!("  </{}>\n", data_type));
+        out.push_str("</VTKFile>\n");
+        std::fs::write(file_path, out)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VthbFile {
+    #[serde(rename = "vtkOverlappingAMR", alias = "vtkHierarchicalBoxDataSet")]
+    amr: VthbAmr,
+}
+
+#[derive(Debug, Deserialize)]
+struct VthbAmr {
+    #[serde(deserialize_with = "vector3::deserialize", default)]
+    origin: [f32; 3],
+    #[serde(default = "default_grid_description")]
+    grid_description: String,
+    #[serde(rename = "Block", default)]
+    blocks: Vec<VthbBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VthbBlock {
+    level: u32,
+    #[serde(deserialize_with = "vector3::deserialize")]
+    spacing: [f32; 3],
+    #[serde(rename = "DataSet", default)]
+    data_sets: Vec<VthbDataSet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VthbDataSet {
+    amr_box: Extent,
+    file: Option<String>,
+}
+
+fn default_grid_description() -> String {
+    String::from("XYZ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Attribute, Attributes, ByteOrder, ImageDataPiece, Version};
+
+    fn image(extent: [i32; 6], spacing: f32, value: f32) -> Vtk {
+        let extent = model::Extent::Ranges([
+            extent[0]..=extent[1] + 1,
+            extent[2]..=extent[3] + 1,
+            extent[4]..=extent[5] + 1,
+        ]);
+        let num_cells = extent.num_cells() as usize;
+        Vtk {
+            version: Version::new((1, 0)),
+            byte_order: ByteOrder::LittleEndian,
+            title: String::new(),
+            file_path: None,
+            data: DataSet::ImageData {
+                extent: extent.clone(),
+                origin: [0.0; 3],
+                spacing: [spacing; 3],
+                meta: None,
+                pieces: vec![model::Piece::Inline(Box::new(ImageDataPiece {
+                    extent,
+                    data: Attributes {
+                        point: vec![],
+                        cell: vec![Attribute::scalars("c", 1).with_data(vec![value; num_cells])],
+                        field: Vec::new(),
+                    },
+                }))],
+            },
+        }
+    }
+
+    #[test]
+    fn hierarchy_round_trip() -> Result<(), Error> {
+        let coarse = image([0, 3, 0, 3, 0, 3], 1.0, 1.0);
+        let fine = image([2, 5, 2, 5, 2, 5], 0.5, 2.0);
+        let amr = OverlappingAmr {
+            origin: [1.0, 2.0, 3.0],
+            grid_description: String::from("XYZ"),
+            levels: vec![
+                AmrLevel {
+                    spacing: [1.0; 3],
+                    boxes: vec![AmrBox::inline([0, 3, 0, 3, 0, 3], coarse.clone())],
+                },
+                AmrLevel {
+                    spacing: [0.5; 3],
+                    boxes: vec![
+                        AmrBox::inline([2, 5, 2, 5, 2, 5], fine.clone()),
+                        AmrBox::default(),
+                    ],
+                },
+            ],
+        };
+
+        let dir = std::env::temp_dir().join("vtkio_amr_hierarchy_round_trip");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("amr.vthb");
+        amr.export(&path)?;
+
+        let mut result = OverlappingAmr::import(&path)?;
+        assert_eq!(result.origin, [1.0, 2.0, 3.0]);
+        assert_eq!(result.grid_description, "XYZ");
+        assert_eq!(result.levels.len(), 2);
+        assert_eq!(result.refinement_ratio(0), None);
+        assert_eq!(result.refinement_ratio(1), Some(2));
+        assert_eq!(result.levels[1].spacing, [0.5; 3]);
+        assert_eq!(result.levels[1].boxes[0].amr_box, [2, 5, 2, 5, 2, 5]);
+        assert_eq!(
+            result.levels[1].boxes[0].file,
+            Some(dir.join("amr/amr_1_0.vti"))
+        );
+        assert_eq!(result.levels[1].boxes[1], AmrBox::default());
+
+        result.load_all()?;
+        let data = |amr: &OverlappingAmr, level: usize| {
+            amr.levels[level].boxes[0]
+                .data
+                .as_ref()
+                .map(|vtk| vtk.data.clone())
+        };
+        assert_eq!(data(&result, 0), Some(coarse.data));
+        assert_eq!(data(&result, 1), Some(fine.data));
+
+        // Only image data can be stored in boxes.
+        let mut amr = amr;
+        amr.levels[0].boxes[0].data = Some(Vtk::import("assets/hexahedron.vtu")?);
+        assert!(amr.export(dir.join("invalid.vthb")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Coarsening of coordinates for sharing sensitive geometry.
+//!
+//! Meshes derived from proprietary CAD models can reveal more than their shape: exact coordinates
+//! expose design tolerances, and the position of a part gives away where it sits in a larger
+//! assembly. [`anonymize`] snaps all coordinates to a regular grid and optionally moves the model
+//! to the origin, returning the removed offset so that it can be kept separately.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::anonymize::{anonymize, Options};
+//! use vtkio::model::*;
+//!
+//! let vtk = Vtk {
+//!     version: Version::new((4, 2)),
+//!     byte_order: ByteOrder::BigEndian,
+//!     title: String::from("Part"),
+//!     file_path: None,
+//!     data: DataSet::inline(PolyDataPiece {
+//!         points: vec![10.12f64, 0.04, 0.0, 12.31, 1.96, 0.0].into(),
+//!         ..Default::default()
+//!     }),
+//! };
+//!
+//! let options = Options::new(0.5).with_recentering(true);
+//! let (vtk, offset) = anonymize(vtk, &options).expect("Failed to load pieces");
+//! assert_eq!(offset, [11.0, 1.0, 0.0]);
+//! let piece = PolyDataPiece::from_data_set(vtk.data, None).unwrap();
+//! assert_eq!(piece.points, vec![-1.0f64, -1.0, 0.0, 1.5, 1.0, 0.0].into());
+//! ```
+
+use num_traits::ToPrimitive;
+
+use crate::match_buf;
+use crate::model::*;
+use crate::Error;
+
+/// Options determining how coordinates are changed by [`anonymize`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Options {
+    grid_spacing: f64,
+    recenter: bool,
+}
+
+impl Options {
+    /// Creates options snapping coordinates to a grid with the given spacing.
+    ///
+    /// A spacing that is not positive leaves coordinates unchanged.
+    pub fn new(grid_spacing: f64) -> Self {
+        Options {
+            grid_spacing,
+            recenter: false,
+        }
+    }
+
+    /// Sets whether the model is moved so that the center of its bounding box is at the origin.
+    ///
+    /// The offset is rounded to the grid, so recentering doesn't change distances between the
+    /// snapped points.
+    pub fn with_recentering(self, recenter: bool) -> Self {
+        Options { recenter, ..self }
+    }
+
+    /// Rounds the given coordinate to the nearest grid point.
+    fn snap(&self, x: f64) -> f64 {
+        if self.grid_spacing > 0.0 {
+            (x / self.grid_spacing).round() * self.grid_spacing
+        } else {
+            x
+        }
+    }
+}
+
+/// Snaps all coordinates of the given data set to a grid, and moves it to the origin if
+/// requested.
+///
+/// Returns the changed data set along with the offset that was subtracted from all coordinates,
+/// which is zero unless recentering is enabled. Pieces stored in other files are loaded first.
+///
+/// Points of poly data, unstructured and structured grids as well as the coordinates of
+/// rectilinear grids are snapped. Image data is only moved and has its origin snapped, since
+/// changing the spacing would change the number of grid cells covering the model. Attributes
+/// are left untouched. Integer coordinates are rounded back to integers.
+pub fn anonymize(mut vtk: Vtk, options: &Options) -> Result<(Vtk, [f64; 3]), Error> {
+    vtk.load_all_pieces()?;
+    let offset = match bounds(&vtk.data) {
+        Some((min, max)) if options.recenter => {
+            let center = |i: usize| options.snap(0.5 * (min[i] + max[i]));
+            [center(0), center(1), center(2)]
+        }
+        _ => [0.0; 3],
+    };
+    transform(&mut vtk.data, |axis, x| options.snap(x - offset[axis]));
+    Ok((vtk, offset))
+}
+
+/// Applies `f` to each coordinate in the buffer, given the axis it belongs to.
+///
+/// `stride` is the number of values per point, which is 3 for points and 1 for coordinates
+/// along a single axis, in which case `first_axis` is the axis of the buffer.
+fn transform_buf(
+    buf: &mut IOBuffer,
+    stride: usize,
+    first_axis: usize,
+    f: impl Fn(usize, f64) -> f64,
+) {
+    match_buf!(buf, v => {
+        for (i, x) in v.iter_mut().enumerate() {
+            let y = x.to_f64().map(|x| f(first_axis + i % stride, x)).and_then(num_traits::cast);
+            if let Some(y) = y {
+                *x = y;
+            }
+        }
+    });
+}
+
+/// Applies `f` to the inline pieces of the given data set.
+fn for_each_piece<P>(pieces: &mut [Piece<P>], mut f: impl FnMut(&mut P)) {
+    for piece in pieces.iter_mut() {
+        if let Piece::Inline(piece) = piece {
+            f(piece);
+        }
+    }
+}
+
+/// Replaces each coordinate `x` along `axis` in the data set with `f(axis, x)`.
+fn transform(data: &mut DataSet, f: impl Fn(usize, f64) -> f64) {
+    match data {
+        DataSet::ImageData { origin, .. } => {
+            for (axis, x) in origin.iter_mut().enumerate() {
+                *x = f(axis, f64::from(*x)) as f32;
+            }
+        }
+        DataSet::StructuredGrid { pieces, .. } => {
+            for_each_piece(pieces, |p| transform_buf(&mut p.points, 3, 0, &f))
+        }
+        DataSet::RectilinearGrid { pieces, .. } => for_each_piece(pieces, |p| {
+            transform_buf(&mut p.coords.x, 1, 0, &f);
+            transform_buf(&mut p.coords.y, 1, 1, &f);
+            transform_buf(&mut p.coords.z, 1, 2, &f);
+        }),
+        DataSet::UnstructuredGrid { pieces, .. } => {
+            for_each_piece(pieces, |p| transform_buf(&mut p.points, 3, 0, &f))
+        }
+        DataSet::PolyData { pieces, .. } => {
+            for_each_piece(pieces, |p| transform_buf(&mut p.points, 3, 0, &f))
+        }
+        DataSet::Field { .. } => {}
+    }
+}
+
+/// Computes the bounding box of all coordinates in the data set, if there are any.
+fn bounds(data: &DataSet) -> Option<([f64; 3], [f64; 3])> {
+    let mut bounds = None;
+    match data {
+        DataSet::ImageData {
+            extent,
+            origin,
+            spacing,
+            ..
+        } => {
+            let ranges = extent.clone().into_ranges();
+            for axis in 0..3 {
+                for &i in [ranges[axis].start(), ranges[axis].end()].iter() {
+                    let x = f64::from(origin[axis]) + f64::from(spacing[axis]) * f64::from(*i);
+                    extend_bounds(&mut bounds, axis, x);
+                }
+            }
+        }
+        DataSet::StructuredGrid { pieces, .. } => {
+            for piece in pieces.iter().filter_map(inline) {
+                extend_bounds_buf(&mut bounds, &piece.points, 3, 0);
+            }
+        }
+        DataSet::RectilinearGrid { pieces, .. } => {
+            for piece in pieces.iter().filter_map(inline) {
+                extend_bounds_buf(&mut bounds, &piece.coords.x, 1, 0);
+                extend_bounds_buf(&mut bounds, &piece.coords.y, 1, 1);
+                extend_bounds_buf(&mut bounds, &piece.coords.z, 1, 2);
+            }
+        }
+        DataSet::UnstructuredGrid { pieces, .. } => {
+            for piece in pieces.iter().filter_map(inline) {
+                extend_bounds_buf(&mut bounds, &piece.points, 3, 0);
+            }
+        }
+        DataSet::PolyData { pieces, .. } => {
+            for piece in pieces.iter().filter_map(inline) {
+                extend_bounds_buf(&mut bounds, &piece.points, 3, 0);
+            }
+        }
+        DataSet::Field { .. } => {}
+    }
+    bounds
+}
+
+fn inline<P>(piece: &Piece<P>) -> Option<&P> {
+    match piece {
+        Piece::Inline(piece) => Some(piece),
+        _ => None,
+    }
+}
+
+fn extend_bounds(bounds: &mut Option<([f64; 3], [f64; 3])>, axis: usize, x: f64) {
+    let (min, max) = bounds.get_or_insert(([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]));
+    min[axis] = min[axis].min(x);
+    max[axis] = max[axis].max(x);
+}
+
+/// Extends the bounds by the coordinates in the buffer, laid out as in [`transform_buf`].
+fn extend_bounds_buf(
+    bounds: &mut Option<([f64; 3], [f64; 3])>,
+    buf: &IOBuffer,
+    stride: usize,
+    first_axis: usize,
+) {
+    if let Some(values) = buf.cast_into::<f64>() {
+        for (i, &x) in values.iter().enumerate() {
+            extend_bounds(bounds, first_axis + i % stride, x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snaps_and_recenters_grids() -> Result<(), Error> {
+        let grid = Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::from("Grid"),
+            file_path: None,
+            data: DataSet::inline(RectilinearGridPiece {
+                extent: Extent::Dims([2, 2, 1]),
+                coords: Coordinates {
+                    x: vec![100.3f32, 102.6].into(),
+                    y: vec![-4i32, 7].into(),
+                    z: vec![0.2f64].into(),
+                },
+                data: Attributes::new(),
+            }),
+        };
+
+        // Without recentering only the coordinates are snapped.
+        let (snapped, offset) = anonymize(grid.clone(), &Options::new(1.0))?;
+        assert_eq!(offset, [0.0; 3]);
+        let piece = RectilinearGridPiece::from_data_set(snapped.data, None).unwrap();
+        assert_eq!(piece.coords.x, vec![100.0f32, 103.0].into());
+        assert_eq!(piece.coords.z, vec![0.0f64].into());
+
+        let options = Options::new(2.0).with_recentering(true);
+        let (centered, offset) = anonymize(grid, &options)?;
+        assert_eq!(offset, [102.0, 2.0, 0.0]);
+        let piece = RectilinearGridPiece::from_data_set(centered.data, None).unwrap();
+        assert_eq!(piece.coords.x, vec![-2.0f32, 0.0].into());
+        assert_eq!(piece.coords.y, vec![-6i32, 6].into());
+        Ok(())
+    }
+}
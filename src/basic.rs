@@ -13,7 +13,7 @@ use crate::model::IOBuffer;
 /// immediately after the "newline" (`\n`) character from the previous ASCII
 /// keyword and parameter sequence. For example point positions and cell indices
 /// and types can be saved in Binary in VTK files.
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Eq, Hash)]
 pub enum FileType {
     Binary,
     ASCII,
@@ -31,15 +31,23 @@ macro_rules! impl_real_parser {
                 recognize!(tuple!(
                     opt!(alt!(tag!("+") | tag!("-"))),
                     alt_complete!(
-                        delimited!(digit, tag!("."), opt!(digit))
-                            | delimited!(opt!(digit), tag!("."), digit)
-                            | digit
-                    ),
-                    opt!(complete!(tuple!(
-                        alt!(tag!("e") | tag!("E")),
-                        opt!(alt!(tag!("+") | tag!("-"))),
-                        digit
-                    )))
+                        tuple!(
+                            alt_complete!(
+                                delimited!(digit, tag!("."), opt!(digit))
+                                    | delimited!(opt!(digit), tag!("."), digit)
+                                    | digit
+                            ),
+                            opt!(complete!(tuple!(
+                                alt!(tag!("e") | tag!("E")),
+                                opt!(alt!(tag!("+") | tag!("-"))),
+                                digit
+                            )))
+                        ) => { |_| () }
+                        // Non-finite values as written by VTK and the round trip ASCII format.
+                        | tag_no_case!("nan") => { |_| () }
+                        | tag_no_case!("infinity") => { |_| () }
+                        | tag_no_case!("inf") => { |_| () }
+                    )
                 )),
                 parse_to!(T)
             )
@@ -271,6 +279,10 @@ mod tests {
         assert_eq!(real::<f32>(&b"-.3"[..]).unwrap().1, -0.3);
         assert_eq!(real::<f32>(&b"3e3"[..]).unwrap().1, 3000.0);
         assert_eq!(real::<f32>(&b"-3.2e2"[..]).unwrap().1, -320.0);
+        assert!(real::<f32>(&b"nan"[..]).unwrap().1.is_nan());
+        assert!(real::<f64>(&b"NaN"[..]).unwrap().1.is_nan());
+        assert_eq!(real::<f32>(&b"inf"[..]).unwrap().1, f32::INFINITY);
+        assert_eq!(real::<f64>(&b"-Infinity"[..]).unwrap().1, f64::NEG_INFINITY);
     }
     #[test]
     fn can_parse_int() {
@@ -0,0 +1,224 @@
+//! Writing and reading time series with a static mesh.
+//!
+//! Many solvers produce a mesh once and then a new set of fields at every time step. Writing the
+//! full data set at each step is wasteful, so a [`CheckpointWriter`] writes the mesh to a single
+//! file and each time step to a "field only" XML file, which stores the attributes but omits all
+//! points and cells. A `.pvd` collection file lists all the steps along with their time values and
+//! the mesh file they belong to.
+//!
+//! The resulting series can be read back with [`read_checkpoint`], which reassembles a full data
+//! set for each time step by attaching the stored fields to the mesh.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::checkpoint::*;
+//! use vtkio::model::*;
+//!
+//! let mesh = Vtk::import("assets/hexahedron.vtu").expect("Failed to load mesh");
+//! let mut writer = CheckpointWriter::new("output", "sim", mesh.clone()).unwrap();
+//! for step in 0..10 {
+//!     let mut vtk = mesh.clone();
+//!     // Update the attributes in `vtk` here.
+//!     writer.write_step(0.1 * step as f64, vtk).unwrap();
+//! }
+//!
+//! let steps = read_checkpoint("output/sim.pvd").unwrap();
+//! assert_eq!(steps.len(), 10);
+//! ```
+
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+use crate::model::Vtk;
+use crate::pvd::{self, PvdDataSet};
+use crate::xml;
+use crate::Error;
+
+/// Writes a static mesh once along with a field only file for each time step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointWriter {
+    dir: PathBuf,
+    base_name: String,
+    mesh_file: String,
+    steps: Vec<PvdDataSet>,
+}
+
+impl CheckpointWriter {
+    /// Creates a new writer for a series of files in `dir` named after `base_name`.
+    ///
+    /// The directory is created if it doesn't already exist, and `mesh` is immediately written
+    /// to `<base_name>_mesh.<ext>`, where the extension is determined by the type of data set.
+    pub fn new(
+        dir: impl AsRef<Path>,
+        base_name: impl Into<String>,
+        mesh: Vtk,
+    ) -> Result<CheckpointWriter, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        let base_name = base_name.into();
+        std::fs::create_dir_all(&dir)?;
+        let mesh_file = format!(
+            "{}_mesh.{}",
+            base_name,
+            pvd::data_set_extension(&mesh.data)?
+        );
+        mesh.export(dir.join(&mesh_file))?;
+        Ok(CheckpointWriter {
+            dir,
+            base_name,
+            mesh_file,
+            steps: Vec::new(),
+        })
+    }
+
+    /// Writes the attributes of the given data set as the fields at time `time`.
+    ///
+    /// The points and cells of `vtk` are not written. Instead, the stored fields are attached to
+    /// the mesh given in [`CheckpointWriter::new`] when read back, so `vtk` must be of the same type
+    /// and have the same number of points, cells and pieces as the mesh.
+    ///
+    /// The field file is written to `<base_name>_<step>.<ext>`, and the `<base_name>.pvd`
+    /// collection file is rewritten to include the new step.
+    pub fn write_step(&mut self, time: f64, vtk: Vtk) -> Result<(), Error> {
+        let file = format!(
+            "{}_{}.{}",
+            self.base_name,
+            self.steps.len(),
+            pvd::data_set_extension(&vtk.data)?
+        );
+        let vtk_file = xml::VTKFile::try_from(vtk)?.into_field_only();
+        xml::export(&vtk_file, self.dir.join(&file))?;
+        self.steps.push(PvdDataSet {
+            timestep: time,
+            group: String::new(),
+            part: 0,
+            file,
+            mesh: Some(self.mesh_file.clone()),
+        });
+        pvd::write(&self.pvd_path(), &self.steps)
+    }
+
+    /// The path to the `.pvd` collection file listing all written steps.
+    pub fn pvd_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.pvd", self.base_name))
+    }
+}
+
+/// Reads a series written by a [`CheckpointWriter`].
+///
+/// Each data set in the given `.pvd` file is loaded and, if it references a mesh, its fields are
+/// attached to that mesh to produce a complete data set. Meshes are only loaded once. The
+/// returned steps are in the order listed in the collection file.
+///
+/// An error is returned if the stored fields don't match the size of the mesh.
+pub fn read_checkpoint(pvd_path: impl AsRef<Path>) -> Result<Vec<(f64, Vtk)>, Error> {
+    let pvd_path = pvd_path.as_ref();
+    let dir = pvd_path.parent().unwrap_or_else(|| Path::new(""));
+    let data_sets = pvd::parse(pvd_path)?;
+
+    let mut meshes: Vec<(String, Vtk)> = Vec::new();
+    let mut steps = Vec::with_capacity(data_sets.len());
+    for PvdDataSet {
+        timestep,
+        file,
+        mesh,
+        ..
+    } in data_sets
+    {
+        let vtk = match mesh {
+            None => Vtk::import(dir.join(&file))?,
+            Some(mesh_file) => {
+                let mesh = match meshes.iter().find(|(name, _)| *name == mesh_file) {
+                    Some((_, mesh)) => mesh.clone(),
+                    None => {
+                        let mesh = Vtk::import(dir.join(&mesh_file))?;
+                        meshes.push((mesh_file, mesh.clone()));
+                        mesh
+                    }
+                };
+                mesh.with_fields_from(dir.join(&file))?
+            }
+        };
+        steps.push((timestep, vtk));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        Attribute, Attributes, ByteOrder, DataSet, Piece, PolyDataPiece, Version, VertexNumbers,
+    };
+
+    fn triangle(pressure: f32) -> Vtk {
+        Vtk {
+            version: Version::new((1, 0)),
+            byte_order: ByteOrder::LittleEndian,
+            title: String::new(),
+            file_path: None,
+            data: DataSet::inline(PolyDataPiece {
+                points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+                polys: Some(VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2],
+                    offsets: vec![3],
+                }),
+                data: Attributes {
+                    point: vec![Attribute::scalars("pressure", 1).with_data(vec![pressure; 3])],
+                    cell: vec![Attribute::scalars("id", 1).with_data(vec![7i32])],
+                    field: Vec::new(),
+                },
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("vtkio_checkpoint_round_trip");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut writer = CheckpointWriter::new(&dir, "tri", triangle(0.0))?;
+        writer.write_step(0.0, triangle(1.0))?;
+        writer.write_step(0.5, triangle(2.0))?;
+
+        // Field files must not contain any geometry.
+        let field_file = std::fs::read_to_string(dir.join("tri_1.vtp"))?;
+        assert!(!field_file.contains("<Points>"));
+        assert!(!field_file.contains("<Polys>"));
+
+        let steps = read_checkpoint(writer.pvd_path())?;
+        assert_eq!(steps.len(), 2);
+        for ((time, vtk), (expected_time, pressure)) in
+            steps.into_iter().zip([(0.0, 1.0), (0.5, 2.0)])
+        {
+            assert_eq!(time, expected_time);
+            assert_eq!(vtk.data, triangle(pressure).data);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn mismatched_fields() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("vtkio_checkpoint_mismatched_fields");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut writer = CheckpointWriter::new(&dir, "tri", triangle(0.0))?;
+        let mut fields = triangle(1.0);
+        if let DataSet::PolyData { pieces, .. } = &mut fields.data {
+            if let Piece::Inline(piece) = &mut pieces[0] {
+                piece.points = vec![0.0f32; 12].into();
+                piece.data.point =
+                    vec![Attribute::scalars("pressure", 1).with_data(vec![1.0f32; 4])];
+            }
+        }
+        writer.write_step(0.0, fields)?;
+        assert!(matches!(
+            read_checkpoint(writer.pvd_path()),
+            Err(Error::Load(
+                crate::model::Error::AttributeSizeMismatch { .. }
+            ))
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,866 @@
+//! Reading Exodus II databases into unstructured grids.
+//!
+//! [Exodus II](https://sandialabs.github.io/seacas-docs/) is the mesh format of many finite
+//! element codes in high performance computing. A [`Database`] reads the mesh of an Exodus file
+//! into one [`UnstructuredGridPiece`] per element block, which can then be exported to any VTK
+//! format with [`Database::into_vtk`].
+//!
+//! Each piece only holds the nodes used by its block. The global ids of nodes and elements (taken
+//! from the node and element number maps if present) are stored in the [`NODE_ID`] and
+//! [`ELEMENT_ID`] attributes, so that pieces can be stitched back together. Named sets are
+//! preserved as attributes:
+//!
+//!  - Each node set becomes an unsigned char point attribute, which is 1 for nodes in the set and
+//!    0 otherwise.
+//!  - Each side set becomes an unsigned char cell attribute holding a bit mask of the sides of
+//!    the element in the set, where bit `i` stands for side `i + 1`.
+//!
+//! Set attributes are named after the set, or `node_set_<id>` and `side_set_<id>` if it has no
+//! name. Element blocks are identified by their piece as well as the [`BLOCK_ID`] attribute.
+//!
+//! Exodus files are stored in netCDF. Only the classic netCDF formats (including the 64-bit offset
+//! and 64-bit data variants) are supported, since files in the HDF5 based netCDF-4 format would
+//! require the HDF5 library. For the same reason CGNS files can't be read. Results stored in
+//! time steps are not read.
+//!
+//! This module is available with the `exodus` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::exodus::Database;
+//!
+//! let db = Database::import("bracket.exo").expect("Failed to read Exodus file");
+//! for block in &db.blocks {
+//!     println!("{} ({}): {} cells", block.name, block.id, block.piece.cells.num_cells());
+//! }
+//! db.into_vtk().export("bracket.vtu").expect("Failed to export mesh");
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::model::{self, *};
+
+/// Name of the point attribute holding the global id of each node.
+pub const NODE_ID: &str = "exodus:node_id";
+
+/// Name of the cell attribute holding the global id of each element.
+pub const ELEMENT_ID: &str = "exodus:element_id";
+
+/// Name of the cell attribute holding the id of the element block of each cell.
+pub const BLOCK_ID: &str = "exodus:block_id";
+
+/// Error produced when reading Exodus files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file is not a netCDF file.
+    NotNetCdf,
+    /// The file is in the HDF5 based netCDF-4 format.
+    NetCdf4,
+    /// The netCDF header or the data of the given variable is truncated or malformed.
+    Malformed(String),
+    /// A variable required to read the mesh is missing.
+    MissingVariable(String),
+    /// An element block has an element type with no corresponding cell type.
+    UnsupportedElementType(String),
+    /// An element or set refers to a node or element that doesn't exist.
+    IndexOutOfBounds(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotNetCdf => write!(f, "Not a netCDF file"),
+            Error::NetCdf4 => write!(f, "netCDF-4 files are not supported"),
+            Error::Malformed(what) => write!(f, "Malformed netCDF {}", what),
+            Error::MissingVariable(name) => write!(f, "Missing variable: {}", name),
+            Error::UnsupportedElementType(ty) => write!(f, "Unsupported element type: {}", ty),
+            Error::IndexOutOfBounds(name) => write!(f, "Index out of bounds in {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An element block read from an Exodus file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementBlock {
+    /// The id of the block.
+    pub id: i64,
+    /// The name of the block, which is empty if it has none.
+    pub name: String,
+    /// The nodes and elements of the block, with attributes for ids and sets.
+    pub piece: UnstructuredGridPiece,
+}
+
+/// The mesh stored in an Exodus file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Database {
+    /// The title of the database.
+    pub title: String,
+    /// The element blocks in the order they are stored.
+    pub blocks: Vec<ElementBlock>,
+}
+
+impl Database {
+    /// Imports the Exodus file at the given path.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<Database, crate::Error> {
+        Database::parse(File::open(file_path)?)
+    }
+
+    /// Parses an Exodus file from the given reader.
+    pub fn parse(mut reader: impl Read) -> Result<Database, crate::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(read_database(&NetCdf::parse(&bytes)?)?)
+    }
+
+    /// Converts the mesh into a VTK file with one unstructured grid piece per element block.
+    pub fn into_vtk(self) -> Vtk {
+        Vtk {
+            version: Version::new((4, 2)),
+            byte_order: model::ByteOrder::BigEndian,
+            title: self.title,
+            file_path: None,
+            data: DataSet::UnstructuredGrid {
+                meta: None,
+                pieces: self
+                    .blocks
+                    .into_iter()
+                    .map(|block| Piece::Inline(Box::new(block.piece)))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Returns the cell type of the given Exodus element type with the given number of nodes.
+fn cell_type(elem_type: &str, num_nodes: usize) -> Option<CellType> {
+    let ty = elem_type.trim().to_ascii_uppercase();
+    let family = |prefixes: &[&str]| prefixes.iter().any(|p| ty.starts_with(p));
+    Some(if family(&["TET"]) {
+        match num_nodes {
+            4 => CellType::Tetra,
+            10 => CellType::QuadraticTetra,
+            _ => return None,
+        }
+    } else if family(&["HEX"]) {
+        match num_nodes {
+            8 => CellType::Hexahedron,
+            20 => CellType::QuadraticHexahedron,
+            _ => return None,
+        }
+    } else if family(&["WEDGE"]) && num_nodes == 6 {
+        CellType::Wedge
+    } else if family(&["PYR"]) && num_nodes == 5 {
+        CellType::Pyramid
+    } else if family(&["TRI", "QUAD", "SHELL"]) {
+        match num_nodes {
+            3 => CellType::Triangle,
+            4 => CellType::Quad,
+            6 => CellType::QuadraticTriangle,
+            8 => CellType::QuadraticQuad,
+            _ => return None,
+        }
+    } else if family(&["BAR", "BEAM", "TRUSS", "EDGE"]) {
+        match num_nodes {
+            2 => CellType::Line,
+            3 => CellType::QuadraticEdge,
+            _ => return None,
+        }
+    } else if family(&["SPHERE", "CIRCLE"]) && num_nodes == 1 {
+        CellType::Vertex
+    } else {
+        return None;
+    })
+}
+
+/// Returns the position in Exodus node order of each node in VTK node order.
+///
+/// Exodus numbers the mid-edge nodes of quadratic hexahedra on vertical edges before those on
+/// the top face, while VTK does the opposite.
+fn node_order(cell_type: CellType, num_nodes: usize) -> Vec<usize> {
+    match cell_type {
+        CellType::QuadraticHexahedron => (0..12).chain(16..20).chain(12..16).collect(),
+        _ => (0..num_nodes).collect(),
+    }
+}
+
+fn read_database(nc: &NetCdf) -> Result<Database, Error> {
+    let num_nodes = nc.dim("num_nodes").unwrap_or(0);
+    let coords = match nc.values("coord")? {
+        // Old files store all coordinates in a single variable.
+        Some(coord) => {
+            let axis = |i: usize| {
+                let start = i.checked_mul(num_nodes)?;
+                coord
+                    .get(start..start.checked_add(num_nodes)?)
+                    .map(<[f64]>::to_vec)
+            };
+            [axis(0), axis(1), axis(2)]
+        }
+        None => [
+            nc.values("coordx")?,
+            nc.values("coordy")?,
+            nc.values("coordz")?,
+        ],
+    };
+    let coords: Vec<Vec<f64>> = coords
+        .iter()
+        .map(|c| c.clone().unwrap_or_else(|| vec![0.0; num_nodes]))
+        .collect();
+    if coords.iter().any(|c| c.len() != num_nodes) {
+        return Err(Error::Malformed(String::from("coordinates")));
+    }
+    let ids = |name: &str, n: usize| -> Result<Vec<i64>, Error> {
+        let map = nc
+            .values(name)?
+            .map(|ids| ids.into_iter().map(|x| x as i64).collect());
+        Ok(map.unwrap_or_else(|| (1..=n as i64).collect()))
+    };
+    let node_ids = ids("node_num_map", num_nodes)?;
+    if node_ids.len() < num_nodes {
+        return Err(Error::IndexOutOfBounds(String::from("node_num_map")));
+    }
+    let elem_ids = ids("elem_num_map", nc.dim("num_elem").unwrap_or(0))?;
+
+    // Node sets as lists of flags, and side sets as maps from elements to side masks.
+    let mut node_sets = Vec::new();
+    let num_node_sets = nc.dim("num_node_sets").unwrap_or(0);
+    let set_ids = ids("ns_prop1", num_node_sets)?;
+    let set_names = nc.strings("ns_names")?;
+    for (i, id) in set_ids.into_iter().enumerate() {
+        let var = format!("node_ns{}", i + 1);
+        let mut flags = vec![0u8; num_nodes];
+        for node in nc.values(&var)?.unwrap_or_default() {
+            *flags
+                .get_mut((node as usize).wrapping_sub(1))
+                .ok_or_else(|| Error::IndexOutOfBounds(var.clone()))? = 1;
+        }
+        let name = set_name(&set_names, i, || format!("node_set_{}", id));
+        node_sets.push((name, flags));
+    }
+    let mut side_sets = Vec::new();
+    let num_side_sets = nc.dim("num_side_sets").unwrap_or(0);
+    let set_ids = ids("ss_prop1", num_side_sets)?;
+    let set_names = nc.strings("ss_names")?;
+    for (i, id) in set_ids.into_iter().enumerate() {
+        let elems = nc.values(&format!("elem_ss{}", i + 1))?.unwrap_or_default();
+        let sides = nc.values(&format!("side_ss{}", i + 1))?.unwrap_or_default();
+        let mut masks = HashMap::new();
+        for (&elem, &side) in elems.iter().zip(&sides) {
+            if elem >= 1.0 && (1.0..=8.0).contains(&side) {
+                *masks.entry(elem as usize - 1).or_insert(0u8) |= 1 << (side as u8 - 1);
+            }
+        }
+        let name = set_name(&set_names, i, || format!("side_set_{}", id));
+        side_sets.push((name, masks));
+    }
+
+    let num_blocks = nc.dim("num_el_blk").unwrap_or(0);
+    let block_ids = ids("eb_prop1", num_blocks)?;
+    let block_names = nc.strings("eb_names")?;
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut first_elem = 0;
+    for (b, id) in block_ids.into_iter().enumerate() {
+        let var = format!("connect{}", b + 1);
+        let num_elems = nc.dim(&format!("num_el_in_blk{}", b + 1)).unwrap_or(0);
+        let nodes_per_elem = nc.dim(&format!("num_nod_per_el{}", b + 1)).unwrap_or(0);
+        let connect = if num_elems == 0 {
+            Vec::new()
+        } else {
+            nc.values(&var)?
+                .ok_or_else(|| Error::MissingVariable(var.clone()))?
+        };
+        if num_elems.checked_mul(nodes_per_elem) != Some(connect.len()) {
+            return Err(Error::Malformed(var));
+        }
+        let elem_type = nc.text_attribute(&var, "elem_type").unwrap_or_default();
+        let cell_type = cell_type(&elem_type, nodes_per_elem)
+            .ok_or_else(|| Error::UnsupportedElementType(elem_type.clone()))?;
+        let order = node_order(cell_type, nodes_per_elem);
+
+        // Number the nodes used by the block in the order they are first referenced.
+        let mut local = HashMap::new();
+        let mut used = Vec::new();
+        let mut connectivity = Vec::with_capacity(connect.len());
+        for elem in connect.chunks(nodes_per_elem.max(1)) {
+            for &i in &order {
+                let node = elem[i] as usize;
+                if node == 0 || node > num_nodes {
+                    return Err(Error::IndexOutOfBounds(var));
+                }
+                let index = *local.entry(node - 1).or_insert_with(|| {
+                    used.push(node - 1);
+                    used.len() as u64 - 1
+                });
+                connectivity.push(index);
+            }
+        }
+
+        let mut points = Vec::with_capacity(3 * used.len());
+        for &node in &used {
+            points.extend(coords.iter().map(|c| c[node]));
+        }
+        let mut data = Attributes::new();
+        let node_id: Vec<i64> = used.iter().map(|&n| node_ids[n]).collect();
+        data.point
+            .push(Attribute::generic(NODE_ID, 1).with_data(node_id));
+        for (name, flags) in &node_sets {
+            let flags: Vec<u8> = used.iter().map(|&n| flags[n]).collect();
+            data.point
+                .push(Attribute::generic(name.as_str(), 1).with_data(flags));
+        }
+        let elems = first_elem..first_elem + num_elems;
+        let elem_id = elem_ids
+            .get(elems.clone())
+            .ok_or_else(|| Error::IndexOutOfBounds(String::from("elem_num_map")))?;
+        data.cell
+            .push(Attribute::generic(ELEMENT_ID, 1).with_data(elem_id.to_vec()));
+        data.cell
+            .push(Attribute::generic(BLOCK_ID, 1).with_data(vec![id; num_elems]));
+        for (name, masks) in &side_sets {
+            let masks: Vec<u8> = elems
+                .clone()
+                .map(|e| masks.get(&e).copied().unwrap_or(0))
+                .collect();
+            data.cell
+                .push(Attribute::generic(name.as_str(), 1).with_data(masks));
+        }
+        first_elem += num_elems;
+
+        blocks.push(ElementBlock {
+            id,
+            name: block_names.get(b).cloned().unwrap_or_default(),
+            piece: UnstructuredGridPiece {
+                points: points.into(),
+                cells: Cells {
+                    cell_verts: VertexNumbers::XML {
+                        offsets: (1..=num_elems as u64)
+                            .map(|i| i * nodes_per_elem as u64)
+                            .collect(),
+                        connectivity,
+                    },
+                    types: vec![cell_type; num_elems],
+                },
+                data,
+            },
+        });
+    }
+
+    Ok(Database {
+        title: nc.global_text("title").unwrap_or_default(),
+        blocks,
+    })
+}
+
+/// Returns the name of the `i`-th set, or the given default if it has none.
+fn set_name(names: &[String], i: usize, default: impl FnOnce() -> String) -> String {
+    match names.get(i) {
+        Some(name) if !name.is_empty() => name.clone(),
+        _ => default(),
+    }
+}
+
+/// An attribute value in a netCDF file.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Text(String),
+    Numbers(Vec<f64>),
+}
+
+/// A variable in a netCDF file.
+struct Variable {
+    name: String,
+    dims: Vec<usize>,
+    attributes: Vec<(String, Value)>,
+    nc_type: u32,
+    begin: u64,
+}
+
+/// A minimal reader of the classic netCDF formats (CDF-1, CDF-2 and CDF-5).
+///
+/// Only the header is parsed upfront, while variables are decoded on demand. Record variables
+/// are not supported.
+struct NetCdf<'a> {
+    data: &'a [u8],
+    dims: Vec<(String, usize)>,
+    attributes: Vec<(String, Value)>,
+    vars: Vec<Variable>,
+}
+
+/// Tags of the lists in the netCDF header.
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+/// Returns the size in bytes of values of the given netCDF type.
+fn type_size(nc_type: u32) -> Option<usize> {
+    match nc_type {
+        1 | 2 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 5 | 9 => Some(4),
+        6 | 10 | 11 => Some(8),
+        _ => None,
+    }
+}
+
+/// Decodes big endian values of the given netCDF type as floats.
+fn decode(bytes: &[u8], nc_type: u32) -> Vec<f64> {
+    let size = type_size(nc_type).unwrap_or(1);
+    bytes
+        .chunks_exact(size)
+        .map(|v| match nc_type {
+            1 => f64::from(v[0] as i8),
+            3 => f64::from(BigEndian::read_i16(v)),
+            4 => f64::from(BigEndian::read_i32(v)),
+            5 => f64::from(BigEndian::read_f32(v)),
+            6 => BigEndian::read_f64(v),
+            8 => f64::from(BigEndian::read_u16(v)),
+            9 => f64::from(BigEndian::read_u32(v)),
+            10 => BigEndian::read_i64(v) as f64,
+            11 => BigEndian::read_u64(v) as f64,
+            _ => f64::from(v[0]),
+        })
+        .collect()
+}
+
+/// Decodes a null padded string.
+fn decode_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end])
+        .trim_end()
+        .to_string()
+}
+
+/// A cursor over the netCDF header.
+struct Header<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Whether counts are stored in 64 bits, as in CDF-5.
+    wide: bool,
+}
+
+impl<'a> Header<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .pos
+            .checked_add(n)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| Error::Malformed(String::from("header")))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(BigEndian::read_u32(self.bytes(4)?))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(BigEndian::read_u64(self.bytes(8)?))
+    }
+
+    /// Reads a count, which is 64 bits wide in CDF-5.
+    fn count(&mut self) -> Result<usize, Error> {
+        let count = if self.wide {
+            self.u64()?
+        } else {
+            u64::from(self.u32()?)
+        };
+        usize::try_from(count).map_err(|_| Error::Malformed(String::from("header")))
+    }
+
+    /// Reads bytes padded to a multiple of four.
+    fn padded(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.bytes(n)?;
+        self.bytes((4 - n % 4) % 4)?;
+        Ok(bytes)
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let n = self.count()?;
+        Ok(String::from_utf8_lossy(self.padded(n)?).into_owned())
+    }
+
+    /// Reads the tag and number of elements of a list, which is zero if the list is absent.
+    fn list(&mut self, tag: u32) -> Result<usize, Error> {
+        let found = self.u32()?;
+        let n = self.count()?;
+        if found != tag && (found != 0 || n != 0) {
+            return Err(Error::Malformed(String::from("header")));
+        }
+        Ok(n)
+    }
+
+    fn attributes(&mut self) -> Result<Vec<(String, Value)>, Error> {
+        let n = self.list(NC_ATTRIBUTE)?;
+        let mut attributes = Vec::with_capacity(n.min(1024));
+        for _ in 0..n {
+            let name = self.name()?;
+            let nc_type = self.u32()?;
+            let len = self.count()?;
+            let size = type_size(nc_type).ok_or_else(|| Error::Malformed(name.clone()))?;
+            let len = len
+                .checked_mul(size)
+                .ok_or_else(|| Error::Malformed(name.clone()))?;
+            let bytes = self.padded(len)?;
+            let value = if nc_type == 2 {
+                Value::Text(decode_text(bytes))
+            } else {
+                Value::Numbers(decode(bytes, nc_type))
+            };
+            attributes.push((name, value));
+        }
+        Ok(attributes)
+    }
+}
+
+impl<'a> NetCdf<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.starts_with(b"\x89HDF") {
+            return Err(Error::NetCdf4);
+        }
+        let version = match data {
+            [b'C', b'D', b'F', version, ..] if [1, 2, 5].contains(version) => *version,
+            _ => return Err(Error::NotNetCdf),
+        };
+        let mut header = Header {
+            data,
+            pos: 4,
+            wide: version == 5,
+        };
+        // The number of records is irrelevant, since record variables aren't read.
+        header.count()?;
+
+        let num_dims = header.list(NC_DIMENSION)?;
+        let mut dims = Vec::with_capacity(num_dims.min(1024));
+        for _ in 0..num_dims {
+            let name = header.name()?;
+            dims.push((name, header.count()?));
+        }
+        let attributes = header.attributes()?;
+
+        let num_vars = header.list(NC_VARIABLE)?;
+        let mut vars = Vec::with_capacity(num_vars.min(1024));
+        for _ in 0..num_vars {
+            let name = header.name()?;
+            let ndims = header.count()?;
+            let var_dims = (0..ndims)
+                .map(|_| header.count())
+                .collect::<Result<Vec<_>, _>>()?;
+            if var_dims.iter().any(|&d| d >= dims.len()) {
+                return Err(Error::Malformed(name));
+            }
+            let attributes = header.attributes()?;
+            let nc_type = header.u32()?;
+            // The size of the variable is recomputed from its dimensions.
+            header.count()?;
+            let begin = match version {
+                1 => u64::from(header.u32()?),
+                _ => header.u64()?,
+            };
+            vars.push(Variable {
+                name,
+                dims: var_dims,
+                attributes,
+                nc_type,
+                begin,
+            });
+        }
+        Ok(NetCdf {
+            data,
+            dims,
+            attributes,
+            vars,
+        })
+    }
+
+    /// Returns the length of the dimension with the given name.
+    fn dim(&self, name: &str) -> Option<usize> {
+        self.dims
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|&(_, len)| len)
+    }
+
+    fn var(&self, name: &str) -> Option<&Variable> {
+        self.vars.iter().find(|v| v.name == name)
+    }
+
+    /// Returns the raw bytes of the variable with the given name, if it exists.
+    ///
+    /// Record variables, whose first dimension has unlimited length, are treated as missing.
+    fn bytes(&self, name: &str) -> Result<Option<(&Variable, &'a [u8])>, Error> {
+        let var = match self.var(name) {
+            Some(var) => var,
+            None => return Ok(None),
+        };
+        let lengths: Vec<usize> = var.dims.iter().map(|&d| self.dims[d].1).collect();
+        if lengths.first() == Some(&0) {
+            return Ok(None);
+        }
+        let malformed = || Error::Malformed(String::from(name));
+        let size = lengths
+            .iter()
+            .try_fold(type_size(var.nc_type).ok_or_else(malformed)?, |n, &len| {
+                n.checked_mul(len)
+            })
+            .ok_or_else(malformed)?;
+        let begin = usize::try_from(var.begin).map_err(|_| malformed())?;
+        let bytes = begin
+            .checked_add(size)
+            .and_then(|end| self.data.get(begin..end))
+            .ok_or_else(malformed)?;
+        Ok(Some((var, bytes)))
+    }
+
+    /// Returns the values of the numeric variable with the given name, if it exists.
+    fn values(&self, name: &str) -> Result<Option<Vec<f64>>, Error> {
+        Ok(self
+            .bytes(name)?
+            .map(|(var, bytes)| decode(bytes, var.nc_type)))
+    }
+
+    /// Returns the strings stored in the rows of the two dimensional character variable with the
+    /// given name, or an empty list if it doesn't exist.
+    fn strings(&self, name: &str) -> Result<Vec<String>, Error> {
+        Ok(match self.bytes(name)? {
+            Some((var, bytes)) if var.nc_type == 2 && var.dims.len() == 2 => {
+                let len = self.dims[var.dims[1]].1.max(1);
+                bytes.chunks(len).map(decode_text).collect()
+            }
+            _ => Vec::new(),
+        })
+    }
+
+    /// Returns the text attribute with the given name of the given variable.
+    fn text_attribute(&self, var: &str, name: &str) -> Option<String> {
+        text(&self.var(var)?.attributes, name)
+    }
+
+    /// Returns the global text attribute with the given name.
+    fn global_text(&self, name: &str) -> Option<String> {
+        text(&self.attributes, name)
+    }
+}
+
+fn text(attributes: &[(String, Value)], name: &str) -> Option<String> {
+    attributes.iter().find_map(|(n, value)| match value {
+        Value::Text(text) if n == name => Some(text.clone()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A variable given by name, dimension ids, text attributes, type and data.
+    type TestVariable = (
+        &'static str,
+        Vec<u32>,
+        Vec<(&'static str, &'static str)>,
+        u32,
+        Vec<u8>,
+    );
+
+    /// Writes classic netCDF (CDF-1) files for testing.
+    #[derive(Default)]
+    struct Writer {
+        dims: Vec<(&'static str, u32)>,
+        vars: Vec<TestVariable>,
+    }
+
+    fn name(out: &mut Vec<u8>, name: &str) {
+        out.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.resize(out.len() + (4 - name.len() % 4) % 4, 0);
+    }
+
+    impl Writer {
+        fn dim(&mut self, name: &'static str, len: u32) -> u32 {
+            self.dims.push((name, len));
+            self.dims.len() as u32 - 1
+        }
+
+        fn ints(&mut self, name: &'static str, dims: Vec<u32>, values: &[i32]) {
+            let data = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+            self.vars.push((name, dims, Vec::new(), 4, data));
+        }
+
+        fn write(&self, title: &str) -> Vec<u8> {
+            let mut out = b"CDF\x01\0\0\0\0".to_vec();
+            out.extend_from_slice(&NC_DIMENSION.to_be_bytes());
+            out.extend_from_slice(&(self.dims.len() as u32).to_be_bytes());
+            for (n, len) in &self.dims {
+                name(&mut out, n);
+                out.extend_from_slice(&len.to_be_bytes());
+            }
+            out.extend_from_slice(&NC_ATTRIBUTE.to_be_bytes());
+            out.extend_from_slice(&1u32.to_be_bytes());
+            name(&mut out, "title");
+            out.extend_from_slice(&2u32.to_be_bytes());
+            name(&mut out, title);
+
+            // Offsets of the data are patched in once the header is complete.
+            let mut begins = Vec::new();
+            out.extend_from_slice(&NC_VARIABLE.to_be_bytes());
+            out.extend_from_slice(&(self.vars.len() as u32).to_be_bytes());
+            for (n, dims, attributes, nc_type, data) in &self.vars {
+                name(&mut out, n);
+                out.extend_from_slice(&(dims.len() as u32).to_be_bytes());
+                for d in dims {
+                    out.extend_from_slice(&d.to_be_bytes());
+                }
+                out.extend_from_slice(&NC_ATTRIBUTE.to_be_bytes());
+                out.extend_from_slice(&(attributes.len() as u32).to_be_bytes());
+                for (n, value) in attributes {
+                    name(&mut out, n);
+                    out.extend_from_slice(&2u32.to_be_bytes());
+                    name(&mut out, value);
+                }
+                out.extend_from_slice(&nc_type.to_be_bytes());
+                out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                begins.push(out.len());
+                out.extend_from_slice(&[0; 4]);
+            }
+            for ((_, _, _, _, data), begin) in self.vars.iter().zip(begins) {
+                let offset = out.len() as u32;
+                out[begin..begin + 4].copy_from_slice(&offset.to_be_bytes());
+                out.extend_from_slice(data);
+                out.resize(out.len() + (4 - data.len() % 4) % 4, 0);
+            }
+            out
+        }
+    }
+
+    #[test]
+    fn blocks_and_sets() -> Result<(), crate::Error> {
+        // Two tetrahedra sharing a face, and a triangle shell on top.
+        let mut w = Writer::default();
+        let num_nodes = w.dim("num_nodes", 5);
+        let len_name = w.dim("len_name", 8);
+        let num_blocks = w.dim("num_el_blk", 2);
+        w.dim("num_elem", 3);
+        let num_elem1 = w.dim("num_el_in_blk1", 2);
+        let nodes1 = w.dim("num_nod_per_el1", 4);
+        let num_elem2 = w.dim("num_el_in_blk2", 1);
+        let nodes2 = w.dim("num_nod_per_el2", 3);
+        let num_ns = w.dim("num_node_sets", 1);
+        let ns1 = w.dim("num_nod_ns1", 2);
+        w.dim("num_side_sets", 1);
+        let ss1 = w.dim("num_side_ss1", 2);
+
+        let coord = |values: &[f64]| values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        w.vars.push((
+            "coordx",
+            vec![num_nodes],
+            Vec::new(),
+            6,
+            coord(&[0., 1., 0., 0., 1.]),
+        ));
+        w.vars.push((
+            "coordy",
+            vec![num_nodes],
+            Vec::new(),
+            6,
+            coord(&[0., 0., 1., 0., 1.]),
+        ));
+        w.vars.push((
+            "coordz",
+            vec![num_nodes],
+            Vec::new(),
+            6,
+            coord(&[0., 0., 0., 1., 1.]),
+        ));
+        w.ints("eb_prop1", vec![num_blocks], &[10, 20]);
+        let mut names = b"solid\0\0\0".to_vec();
+        names.extend_from_slice(&[0; 8]);
+        w.vars
+            .push(("eb_names", vec![num_blocks, len_name], Vec::new(), 2, names));
+        w.ints(
+            "connect1",
+            vec![num_elem1, nodes1],
+            &[1, 2, 3, 4, 2, 3, 4, 5],
+        );
+        w.vars[5].2.push(("elem_type", "TETRA4"));
+        w.ints("connect2", vec![num_elem2, nodes2], &[2, 3, 5]);
+        w.vars[6].2.push(("elem_type", "TRISHELL3"));
+        w.ints("ns_prop1", vec![num_ns], &[7]);
+        w.ints("node_ns1", vec![ns1], &[1, 5]);
+        w.ints("elem_ss1", vec![ss1], &[2, 2]);
+        w.ints("side_ss1", vec![ss1], &[1, 3]);
+        let db = Database::parse(w.write("Bracket").as_slice())?;
+
+        assert_eq!(db.title, "Bracket");
+        assert_eq!(db.blocks.len(), 2);
+        let (solid, shell) = (&db.blocks[0], &db.blocks[1]);
+        assert_eq!((solid.id, solid.name.as_str()), (10, "solid"));
+        assert_eq!((shell.id, shell.name.as_str()), (20, ""));
+        assert_eq!(solid.piece.cells.types, vec![CellType::Tetra; 2]);
+        assert_eq!(shell.piece.cells.types, vec![CellType::Triangle]);
+
+        // The shell only holds the three nodes it uses.
+        assert_eq!(
+            shell.piece.points,
+            vec![1.0f64, 0., 0., 0., 1., 0., 1., 1., 1.].into()
+        );
+        let attribute = |piece: &UnstructuredGridPiece, name: &str| {
+            let attribs = piece.data.point.iter().chain(&piece.data.cell);
+            let attrib = attribs.into_iter().find(|a| a.name() == name).unwrap();
+            attrib.data_array().unwrap().data.clone()
+        };
+        assert_eq!(attribute(&shell.piece, NODE_ID), vec![2i64, 3, 5].into());
+        assert_eq!(
+            attribute(&shell.piece, "node_set_7"),
+            vec![0u8, 0, 1].into()
+        );
+        assert_eq!(attribute(&shell.piece, ELEMENT_ID), vec![3i64].into());
+        assert_eq!(attribute(&shell.piece, BLOCK_ID), vec![20i64].into());
+        // The second tetrahedron has sides 1 and 3 in the side set.
+        assert_eq!(
+            attribute(&solid.piece, "side_set_1"),
+            vec![0u8, 0b101].into()
+        );
+
+        let vtk = db.into_vtk();
+        assert_eq!(vtk.data.num_pieces(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_short_node_map() {
+        let mut w = Writer::default();
+        w.dim("num_nodes", 3);
+        let len_map = w.dim("len_map", 2);
+        w.ints("node_num_map", vec![len_map], &[4, 5]);
+        match Database::parse(w.write("Short").as_slice()) {
+            Err(crate::Error::Exodus(Error::IndexOutOfBounds(var))) => {
+                assert_eq!(var, "node_num_map")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let err = |input: &[u8]| match Database::parse(input) {
+            Err(crate::Error::Exodus(err)) => err,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(err(b"\x89HDF\r\n\x1a\n"), Error::NetCdf4);
+        assert_eq!(err(b"# vtk DataFile"), Error::NotNetCdf);
+        assert_eq!(
+            err(b"CDF\x01\0\0"),
+            Error::Malformed(String::from("header"))
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Extension points for reading and writing custom file formats.
+//!
+//! A file format is added by implementing [`Deserializer`] to read it into the [`Vtk`] model and
+//! [`Serializer`] to write the model out again. Formats registered for a file extension with
+//! [`register_deserializer`] and [`register_serializer`] are then used by [`import`](crate::import)
+//! and [`export`](crate::export) for files with that extension, so the rest of an application
+//! doesn't need to know about them.
+//!
+//! The built-in formats are available as [`Legacy`] and [`Xml`], which can be used to wrap or
+//! convert between formats, or registered for extensions that vtkio doesn't know about.
+//! Registered formats take precedence over the built-in ones.
+//!
+//! # Examples
+//!
+//! Reading and writing point clouds stored as whitespace separated coordinates:
+//!
+//! ```
+//! use std::io::{BufRead, Write};
+//! use vtkio::format::{self, Deserializer, Serializer};
+//! use vtkio::model::*;
+//! use vtkio::Error;
+//!
+//! struct Xyz;
+//!
+//! impl Deserializer for Xyz {
+//!     fn deserialize(&self, reader: &mut dyn BufRead) -> Result<Vtk, Error> {
+//!         let mut points = Vec::new();
+//!         for line in reader.lines() {
+//!             for word in line?.split_whitespace() {
+//!                 points.push(word.parse::<f64>().map_err(|_| Error::Unknown)?);
+//!             }
+//!         }
+//!         Ok(Vtk {
+//!             version: Version::new((4, 2)),
+//!             byte_order: ByteOrder::native(),
+//!             title: String::from("Point cloud"),
+//!             file_path: None,
+//!             data: DataSet::inline(PolyDataPiece {
+//!                 points: points.into(),
+//!                 ..Default::default()
+//!             }),
+//!         })
+//!     }
+//! }
+//!
+//! impl Serializer for Xyz {
+//!     fn serialize(&self, vtk: Vtk, writer: &mut dyn Write) -> Result<(), Error> {
+//!         let piece = PolyDataPiece::from_data_set(vtk.data, None)?;
+//!         let points = piece.points.cast_into::<f64>().ok_or(Error::Unknown)?;
+//!         for p in points.chunks(3) {
+//!             writeln!(writer, "{} {} {}", p[0], p[1], p[2])?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! format::register_deserializer("xyz", Xyz);
+//! format::register_serializer("xyz", Xyz);
+//!
+//! let dir = std::env::temp_dir().join("vtkio_format_doc");
+//! std::fs::create_dir_all(&dir).unwrap();
+//! let path = dir.join("cloud.xyz");
+//! std::fs::write(&path, "0 0 0\n1 0.5 2\n").unwrap();
+//!
+//! let vtk = vtkio::import(&path).expect("Failed to import point cloud");
+//! vtkio::export(vtk, &path).expect("Failed to export point cloud");
+//! assert_eq!(std::fs::read_to_string(&path).unwrap(), "0 0 0\n1 0.5 2\n");
+//! ```
+
+use std::io::{BufRead, Write};
+use std::sync::{Arc, RwLock};
+
+use crate::model::Vtk;
+use crate::Error;
+
+/// A file format that can be read into the [`Vtk`] model.
+pub trait Deserializer: Send + Sync {
+    /// Reads a data set from the given reader.
+    ///
+    /// The file path of the returned data set is set by [`import`](crate::import) if it is
+    /// left empty.
+    fn deserialize(&self, reader: &mut dyn BufRead) -> Result<Vtk, Error>;
+}
+
+/// A file format that the [`Vtk`] model can be written to.
+pub trait Serializer: Send + Sync {
+    /// Writes the given data set to the given writer.
+    fn serialize(&self, vtk: Vtk, writer: &mut dyn Write) -> Result<(), Error>;
+}
+
+/// The legacy `.vtk` format.
+///
+/// Data sets are written in binary, and the byte order of binary data is detected when reading
+/// as in [`Vtk::parse_legacy`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Legacy;
+
+impl Deserializer for Legacy {
+    fn deserialize(&self, reader: &mut dyn BufRead) -> Result<Vtk, Error> {
+        Vtk::parse_legacy(reader)
+    }
+}
+
+impl Serializer for Legacy {
+    fn serialize(&self, vtk: Vtk, writer: &mut dyn Write) -> Result<(), Error> {
+        vtk.write_legacy(writer)
+    }
+}
+
+/// The modern XML format.
+///
+/// Pieces stored in other files are not loaded when reading, since their paths are relative to
+/// a file that the reader knows nothing about.
+#[cfg(feature = "xml")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Xml;
+
+#[cfg(feature = "xml")]
+impl Deserializer for Xml {
+    fn deserialize(&self, reader: &mut dyn BufRead) -> Result<Vtk, Error> {
+        Vtk::parse_xml(reader)
+    }
+}
+
+#[cfg(feature = "xml")]
+impl Serializer for Xml {
+    fn serialize(&self, vtk: Vtk, writer: &mut dyn Write) -> Result<(), Error> {
+        vtk.write_xml(writer)
+    }
+}
+
+static DESERIALIZERS: RwLock<Vec<(String, Arc<dyn Deserializer>)>> = RwLock::new(Vec::new());
+static SERIALIZERS: RwLock<Vec<(String, Arc<dyn Serializer>)>> = RwLock::new(Vec::new());
+
+/// Registers a format used to import files with the given extension.
+///
+/// The extension is given without the leading dot and is matched exactly. A format registered
+/// earlier for the same extension is replaced.
+pub fn register_deserializer(extension: &str, deserializer: impl Deserializer + 'static) {
+    register(&DESERIALIZERS, extension, Arc::new(deserializer));
+}
+
+/// Registers a format used to export files with the given extension.
+///
+/// The extension is given without the leading dot and is matched exactly. A format registered
+/// earlier for the same extension is replaced.
+pub fn register_serializer(extension: &str, serializer: impl Serializer + 'static) {
+    register(&SERIALIZERS, extension, Arc::new(serializer));
+}
+
+fn register<T: ?Sized>(registry: &RwLock<Vec<(String, Arc<T>)>>, extension: &str, format: Arc<T>) {
+    // A panic while holding the lock can't leave the list in an inconsistent state.
+    let mut formats = registry.write().unwrap_or_else(|e| e.into_inner());
+    formats.retain(|(ext, _)| ext != extension);
+    formats.push((extension.to_string(), format));
+}
+
+fn lookup<T: ?Sized>(registry: &RwLock<Vec<(String, Arc<T>)>>, extension: &str) -> Option<Arc<T>> {
+    let formats = registry.read().unwrap_or_else(|e| e.into_inner());
+    formats
+        .iter()
+        .find(|(ext, _)| ext == extension)
+        .map(|(_, format)| Arc::clone(format))
+}
+
+/// Returns the format registered to import files with the given extension.
+pub(crate) fn deserializer(extension: &str) -> Option<Arc<dyn Deserializer>> {
+    lookup(&DESERIALIZERS, extension)
+}
+
+/// Returns the format registered to export files with the given extension.
+pub(crate) fn serializer(extension: &str) -> Option<Arc<dyn Serializer>> {
+    lookup(&SERIALIZERS, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Legacy files prefixed with a tag line.
+    struct Tagged;
+
+    impl Serializer for Tagged {
+        fn serialize(&self, vtk: Vtk, writer: &mut dyn Write) -> Result<(), Error> {
+            writer.write_all(b"TAGGED\n")?;
+            Legacy.serialize(vtk, writer)
+        }
+    }
+
+    impl Deserializer for Tagged {
+        fn deserialize(&self, reader: &mut dyn BufRead) -> Result<Vtk, Error> {
+            let mut tag = String::new();
+            reader.read_line(&mut tag)?;
+            if tag != "TAGGED\n" {
+                return Err(Error::Unknown);
+            }
+            Legacy.deserialize(reader)
+        }
+    }
+
+    #[test]
+    fn registered_formats_are_used_by_import_and_export() -> Result<(), Error> {
+        let vtk = crate::import("assets/tet.vtk")?;
+        let dir = std::env::temp_dir().join("vtkio_format_test");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("tet.tagged");
+
+        // Unregistered extensions are rejected.
+        assert!(crate::export(vtk.clone(), &path).is_err());
+
+        register_serializer("tagged", Tagged);
+        register_deserializer("tagged", Tagged);
+        crate::export(vtk.clone(), &path)?;
+        assert!(std::fs::read(&path)?.starts_with(b"TAGGED\n# vtk DataFile"));
+
+        let imported = crate::import(&path)?;
+        assert_eq!(imported.data, vtk.data);
+        assert_eq!(imported.file_path.as_deref(), Some(path.as_path()));
+        Ok(())
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn built_in_formats_convert() -> Result<(), Error> {
+        let vtk = crate::import("assets/tet.vtk")?;
+        let mut xml = Vec::new();
+        Xml.serialize(vtk.clone(), &mut xml)?;
+        let mut converted = Xml.deserialize(&mut xml.as_slice())?;
+        // XML files have no title, and legacy files can't have an empty one.
+        converted.title = vtk.title.clone();
+        let mut legacy = Vec::new();
+        Legacy.serialize(converted, &mut legacy)?;
+        assert_eq!(Legacy.deserialize(&mut legacy.as_slice())?.data, vtk.data);
+        Ok(())
+    }
+}
@@ -0,0 +1,672 @@
+//! Conversion between Gmsh meshes and unstructured grids.
+//!
+//! [Gmsh](https://gmsh.info) is a common mesh generator for finite element codes, while results
+//! are often visualized from VTK files. A [`Mesh`] reads and writes ASCII `.msh` files in
+//! version 4.1 of the Gmsh format, and converts to and from
+//! [`DataSet::UnstructuredGrid`](crate::model::DataSet::UnstructuredGrid).
+//!
+//! Gmsh assigns each element to a geometrical entity, and entities to physical groups, which
+//! usually mark materials or boundary conditions. These are stored in the integer cell
+//! attributes named [`PHYSICAL`] and [`GEOMETRICAL`], holding the first physical tag of the
+//! element's entity (or 0 if it has none) and the entity tag. The names of physical groups are
+//! kept in [`Mesh::physical_names`].
+//!
+//! Only the common linear and quadratic element types are supported, and sections other than
+//! `$MeshFormat`, `$PhysicalNames`, `$Entities`, `$Nodes` and `$Elements` are ignored.
+//!
+//! This module is available with the `gmsh` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::gmsh::{self, Mesh};
+//! use vtkio::model::*;
+//!
+//! let input: &[u8] = b"$MeshFormat
+//! 4.1 0 8
+//! $EndMeshFormat
+//! $PhysicalNames
+//! 1
+//! 2 7 \"plate\"
+//! $EndPhysicalNames
+//! $Entities
+//! 0 0 1 0
+//! 1 0 0 0 1 1 0 1 7 0
+//! $EndEntities
+//! $Nodes
+//! 1 3 1 3
+//! 2 1 0 3
+//! 1
+//! 2
+//! 3
+//! 0 0 0
+//! 1 0 0
+//! 0 1 0
+//! $EndNodes
+//! $Elements
+//! 1 1 1 1
+//! 2 1 2 1
+//! 1 1 2 3
+//! $EndElements
+//! ";
+//!
+//! let mesh = Mesh::parse(input).expect("Failed to parse mesh");
+//! assert_eq!(mesh.physical_names[0].name, "plate");
+//! assert_eq!(mesh.piece.cells.types, vec![CellType::Triangle]);
+//!
+//! let physical = mesh.piece.data.cell.iter().find(|a| a.name() == gmsh::PHYSICAL).unwrap();
+//! assert_eq!(physical.data_array().unwrap().data, vec![7i32].into());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::model::*;
+
+/// Name of the cell attribute holding the physical group of each cell.
+pub const PHYSICAL: &str = "gmsh:physical";
+
+/// Name of the cell attribute holding the geometrical entity of each cell.
+pub const GEOMETRICAL: &str = "gmsh:geometrical";
+
+/// Supported Gmsh element types along with the corresponding cell types and node counts.
+const ELEMENT_TYPES: &[(i32, CellType, usize)] = &[
+    (1, CellType::Line, 2),
+    (2, CellType::Triangle, 3),
+    (3, CellType::Quad, 4),
+    (4, CellType::Tetra, 4),
+    (5, CellType::Hexahedron, 8),
+    (6, CellType::Wedge, 6),
+    (7, CellType::Pyramid, 5),
+    (8, CellType::QuadraticEdge, 3),
+    (9, CellType::QuadraticTriangle, 6),
+    (11, CellType::QuadraticTetra, 10),
+    (15, CellType::Vertex, 1),
+    (16, CellType::QuadraticQuad, 8),
+];
+
+/// Error produced when reading or writing Gmsh files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file is in a version of the format other than 4.1.
+    UnsupportedVersion(String),
+    /// The file is in the binary format.
+    Binary,
+    /// The given line (starting at 1) is malformed or ends a section prematurely.
+    Syntax(usize),
+    /// The section with the given name is missing.
+    MissingSection(&'static str),
+    /// An element has a type that has no corresponding cell type.
+    UnsupportedElementType(i32),
+    /// An element refers to a node tag that isn't defined.
+    UnknownNode(u64),
+    /// A cell has a type that has no corresponding Gmsh element type.
+    UnsupportedCellType(CellType),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnsupportedVersion(version) => {
+                write!(f, "Unsupported Gmsh format version: {}", version)
+            }
+            Error::Binary => write!(f, "Binary Gmsh files are not supported"),
+            Error::Syntax(line) => write!(f, "Syntax error at line {}", line),
+            Error::MissingSection(name) => write!(f, "Missing section: ${}", name),
+            Error::UnsupportedElementType(ty) => write!(f, "Unsupported element type: {}", ty),
+            Error::UnknownNode(tag) => write!(f, "Unknown node tag: {}", tag),
+            Error::UnsupportedCellType(ty) => write!(f, "Unsupported cell type: {:?}", ty),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The name of a physical group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PhysicalName {
+    /// The dimension of the entities in the group.
+    pub dim: u8,
+    /// The tag of the group, as stored in the [`PHYSICAL`] cell attribute.
+    pub tag: i32,
+    /// The name of the group.
+    pub name: String,
+}
+
+/// A Gmsh mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    /// The nodes and elements of the mesh, with cell attributes identifying their physical
+    /// groups and geometrical entities.
+    pub piece: UnstructuredGridPiece,
+    /// The names of the physical groups.
+    pub physical_names: Vec<PhysicalName>,
+}
+
+impl Mesh {
+    /// Imports the Gmsh file at the given path.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<Mesh, crate::Error> {
+        Mesh::parse(BufReader::new(File::open(file_path)?))
+    }
+
+    /// Parses a Gmsh file from the given reader.
+    ///
+    /// Nodes are numbered in the order they appear in the file, and cells are ordered as the
+    /// elements in the file.
+    pub fn parse(mut reader: impl BufRead) -> Result<Mesh, crate::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Parser::new(&text).parse()?)
+    }
+
+    /// Converts an unstructured grid into a mesh without physical names.
+    ///
+    /// Only the first piece of the data set is used, and it is loaded first if stored in another
+    /// file.
+    pub fn from_data_set(data_set: DataSet) -> Result<Mesh, crate::Error> {
+        Ok(Mesh {
+            piece: UnstructuredGridPiece::from_data_set(data_set, None)?,
+            physical_names: Vec::new(),
+        })
+    }
+
+    /// Converts this mesh into an unstructured grid with a single piece.
+    ///
+    /// The names of physical groups are dropped, since data sets have no place for them.
+    pub fn into_data_set(self) -> DataSet {
+        DataSet::inline(self.piece)
+    }
+
+    /// Writes this mesh in ASCII format to the given writer.
+    ///
+    /// Cells are grouped into entities by their dimension and the [`GEOMETRICAL`] attribute,
+    /// and each entity belongs to the physical groups given by the [`PHYSICAL`] attribute of
+    /// its cells. Without these attributes all cells of one dimension form a single entity
+    /// that belongs to no physical group. Attributes with a size other than the number of cells
+    /// are ignored, as are all other attributes.
+    pub fn write(&self, writer: impl Write) -> Result<(), crate::Error> {
+        write_mesh(self, writer)
+    }
+
+    /// Exports this mesh in ASCII format to the given path.
+    pub fn export(&self, file_path: impl AsRef<Path>) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write(&mut writer)?;
+        // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns the permutation taking Gmsh node order to VTK node order and back.
+///
+/// Gmsh numbers the last two edges of quadratic tetrahedra the other way around.
+fn node_order(cell_type: CellType) -> &'static [usize] {
+    match cell_type {
+        CellType::QuadraticTetra => &[0, 1, 2, 3, 4, 5, 6, 7, 9, 8],
+        _ => &[0, 1, 2, 3, 4, 5, 6, 7],
+    }
+}
+
+/// Returns the dimension of the given supported cell type.
+fn cell_dim(cell_type: CellType) -> u8 {
+    match cell_type {
+        CellType::Vertex => 0,
+        CellType::Line | CellType::QuadraticEdge => 1,
+        CellType::Triangle
+        | CellType::Quad
+        | CellType::QuadraticTriangle
+        | CellType::QuadraticQuad => 2,
+        _ => 3,
+    }
+}
+
+/// A line based reader of ASCII Gmsh files.
+struct Parser<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            lines: text.lines().enumerate(),
+            line: 0,
+        }
+    }
+
+    /// Returns the next non-empty line, or `None` at the end of the file.
+    fn try_next_line(&mut self) -> Option<&'a str> {
+        for (i, line) in &mut self.lines {
+            self.line = i + 1;
+            let line = line.trim();
+            if !line.is_empty() {
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    fn next_line(&mut self) -> Result<&'a str, Error> {
+        self.try_next_line().ok_or(Error::Syntax(self.line + 1))
+    }
+
+    /// Parses all whitespace separated numbers on the next line.
+    fn numbers<T: FromStr>(&mut self) -> Result<Vec<T>, Error> {
+        let line = self.next_line()?;
+        line.split_whitespace()
+            .map(|word| word.parse().map_err(|_| Error::Syntax(self.line)))
+            .collect()
+    }
+
+    /// Parses the next line, which must have at least `n` numbers.
+    fn at_least<T: FromStr>(&mut self, n: usize) -> Result<Vec<T>, Error> {
+        let numbers = self.numbers()?;
+        if numbers.len() < n {
+            return Err(Error::Syntax(self.line));
+        }
+        Ok(numbers)
+    }
+
+    /// Consumes the line closing the section with the given name.
+    fn end(&mut self, name: &str) -> Result<(), Error> {
+        match self.next_line()?.strip_prefix("$End") {
+            Some(end) if end == name => Ok(()),
+            _ => Err(Error::Syntax(self.line)),
+        }
+    }
+
+    fn parse(mut self) -> Result<Mesh, Error> {
+        let mut has_format = false;
+        let mut physical_names = Vec::new();
+        let mut entity_groups = HashMap::new();
+        let mut nodes = None;
+        let mut elements = None;
+        while let Some(line) = self.try_next_line() {
+            let name = line.strip_prefix('$').ok_or(Error::Syntax(self.line))?;
+            match name {
+                "MeshFormat" => {
+                    let line = self.next_line()?;
+                    let mut words = line.split_whitespace();
+                    let version = words.next().unwrap_or_default();
+                    if version != "4.1" {
+                        return Err(Error::UnsupportedVersion(version.to_string()));
+                    }
+                    if words.next() != Some("0") {
+                        return Err(Error::Binary);
+                    }
+                    has_format = true;
+                }
+                _ if !has_format => return Err(Error::MissingSection("MeshFormat")),
+                "PhysicalNames" => physical_names = self.physical_names()?,
+                "Entities" => entity_groups = self.entities()?,
+                "Nodes" => nodes = Some(self.nodes()?),
+                "Elements" => elements = Some(self.elements()?),
+                _ => {
+                    // Skip unknown sections, which may contain arbitrary content.
+                    let end = format!("$End{}", name);
+                    while self.next_line()? != end {}
+                    continue;
+                }
+            }
+            self.end(name)?;
+        }
+        if !has_format {
+            return Err(Error::MissingSection("MeshFormat"));
+        }
+        let (points, node_index) = nodes.ok_or(Error::MissingSection("Nodes"))?;
+        let blocks = elements.ok_or(Error::MissingSection("Elements"))?;
+
+        let mut connectivity = Vec::new();
+        let mut offsets = Vec::new();
+        let mut types = Vec::new();
+        let mut physical = Vec::new();
+        let mut geometrical = Vec::new();
+        for block in blocks {
+            let groups: Option<&Vec<i32>> = entity_groups.get(&(block.dim, block.entity));
+            let group = groups.and_then(|g| g.first()).copied().unwrap_or(0);
+            for nodes in block.elements.chunks(block.num_nodes) {
+                for &i in &node_order(block.cell_type)[..nodes.len()] {
+                    let index = node_index.get(&nodes[i]);
+                    connectivity.push(*index.ok_or(Error::UnknownNode(nodes[i]))?);
+                }
+                offsets.push(connectivity.len() as u64);
+                types.push(block.cell_type);
+                physical.push(group);
+                geometrical.push(block.entity);
+            }
+        }
+
+        let mut data = Attributes::new();
+        data.cell
+            .push(Attribute::scalars(PHYSICAL, 1).with_data(physical));
+        data.cell
+            .push(Attribute::scalars(GEOMETRICAL, 1).with_data(geometrical));
+        Ok(Mesh {
+            piece: UnstructuredGridPiece {
+                points: points.into(),
+                cells: Cells {
+                    cell_verts: VertexNumbers::XML {
+                        connectivity,
+                        offsets,
+                    },
+                    types,
+                },
+                data,
+            },
+            physical_names,
+        })
+    }
+
+    fn physical_names(&mut self) -> Result<Vec<PhysicalName>, Error> {
+        let count: usize = self.at_least(1)?[0];
+        let mut names = Vec::new();
+        for _ in 0..count {
+            let line = self.next_line()?;
+            let mut words = line.splitn(3, char::is_whitespace);
+            let (dim, tag) = match (words.next(), words.next()) {
+                (Some(dim), Some(tag)) => (dim.parse().ok(), tag.parse().ok()),
+                _ => (None, None),
+            };
+            let name = words.next().map(|name| name.trim().trim_matches('"'));
+            match (dim, tag, name) {
+                (Some(dim), Some(tag), Some(name)) => names.push(PhysicalName {
+                    dim,
+                    tag,
+                    name: name.to_string(),
+                }),
+                _ => return Err(Error::Syntax(self.line)),
+            }
+        }
+        Ok(names)
+    }
+
+    /// Parses the physical tags of all entities, keyed by their dimension and tag.
+    fn entities(&mut self) -> Result<HashMap<(u8, i32), Vec<i32>>, Error> {
+        let counts: Vec<usize> = self.at_least(4)?;
+        let mut groups = HashMap::new();
+        for dim in 0..4u8 {
+            // Points only store a position, while other entities store a bounding box.
+            let num_coords = if dim == 0 { 3 } else { 6 };
+            for _ in 0..counts[usize::from(dim)] {
+                let line = self.next_line()?;
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let tag = |i: usize| words.get(i).and_then(|w| w.parse::<i32>().ok());
+                let num_groups = words.get(num_coords + 1).and_then(|w| w.parse().ok());
+                let tags = num_groups
+                    .and_then(|n: usize| (0..n).map(|j| tag(num_coords + 2 + j)).collect());
+                match (tag(0), tags) {
+                    (Some(entity), Some(tags)) => groups.insert((dim, entity), tags),
+                    _ => return Err(Error::Syntax(self.line)),
+                };
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Parses all node coordinates along with a map from node tags to indices.
+    fn nodes(&mut self) -> Result<(Vec<f64>, HashMap<u64, u64>), Error> {
+        let header: Vec<usize> = self.at_least(4)?;
+        // Counts come from the file, so storage grows as data is read.
+        let mut points = Vec::new();
+        let mut index = HashMap::new();
+        for _ in 0..header[0] {
+            let block: Vec<usize> = self.at_least(4)?;
+            let mut tags = Vec::new();
+            for _ in 0..block[3] {
+                tags.push(self.at_least::<u64>(1)?[0]);
+            }
+            for tag in tags {
+                // Parametric coordinates may follow the position.
+                points.extend_from_slice(&self.at_least::<f64>(3)?[..3]);
+                index.insert(tag, index.len() as u64);
+            }
+        }
+        Ok((points, index))
+    }
+
+    fn elements(&mut self) -> Result<Vec<ElementBlock>, Error> {
+        let header: Vec<usize> = self.at_least(4)?;
+        let mut blocks = Vec::new();
+        for _ in 0..header[0] {
+            let block: Vec<i32> = self.at_least(4)?;
+            let &(_, cell_type, num_nodes) = ELEMENT_TYPES
+                .iter()
+                .find(|(ty, _, _)| *ty == block[2])
+                .ok_or(Error::UnsupportedElementType(block[2]))?;
+            let num_elements = block[3].max(0) as usize;
+            let mut elements = Vec::new();
+            for _ in 0..num_elements {
+                // The element tag is followed by the node tags.
+                let numbers = self.at_least::<u64>(num_nodes + 1)?;
+                elements.extend_from_slice(&numbers[1..=num_nodes]);
+            }
+            blocks.push(ElementBlock {
+                dim: block[0] as u8,
+                entity: block[1],
+                cell_type,
+                num_nodes,
+                elements,
+            });
+        }
+        Ok(blocks)
+    }
+}
+
+/// Elements of a single type belonging to one entity.
+struct ElementBlock {
+    dim: u8,
+    entity: i32,
+    cell_type: CellType,
+    num_nodes: usize,
+    /// The node tags of all elements.
+    elements: Vec<u64>,
+}
+
+/// Returns the values of the cell attribute with the given name, if it has one value per cell.
+fn cell_tags(piece: &UnstructuredGridPiece, name: &str) -> Option<Vec<i32>> {
+    let attrib = piece.data.cell.iter().find(|a| a.name() == name)?;
+    let tags = attrib.data_array()?.data.cast_into::<i32>()?;
+    Some(tags).filter(|tags| tags.len() == piece.cells.types.len())
+}
+
+fn write_mesh(mesh: &Mesh, mut w: impl Write) -> Result<(), crate::Error> {
+    let piece = &mesh.piece;
+    let points = piece.points.cast_into::<f64>().unwrap_or_default();
+    let (connectivity, offsets) = piece.cells.cell_verts.clone().into_xml();
+    let physical = cell_tags(piece, PHYSICAL);
+    let geometrical = cell_tags(piece, GEOMETRICAL);
+
+    // Group consecutive cells into blocks by entity and type, and collect the groups and bounds
+    // of entities.
+    let mut blocks: Vec<((u8, i32, CellType), Vec<usize>)> = Vec::new();
+    let mut entities: BTreeMap<(u8, i32), (BTreeSet<i32>, [f64; 6])> = BTreeMap::new();
+    let mut start = 0;
+    for (i, (&cell_type, &end)) in piece.cells.types.iter().zip(&offsets).enumerate() {
+        if !ELEMENT_TYPES.iter().any(|(_, ty, _)| *ty == cell_type) {
+            return Err(Error::UnsupportedCellType(cell_type).into());
+        }
+        let dim = cell_dim(cell_type);
+        let entity = geometrical.as_ref().map_or(1, |tags| tags[i]);
+        match blocks.last_mut() {
+            Some((key, cells)) if *key == (dim, entity, cell_type) => cells.push(i),
+            _ => blocks.push(((dim, entity, cell_type), vec![i])),
+        }
+        let (groups, bounds) = entities.entry((dim, entity)).or_insert_with(|| {
+            let inf = f64::INFINITY;
+            (BTreeSet::new(), [inf, inf, inf, -inf, -inf, -inf])
+        });
+        if let Some(&tag) = physical.as_ref().map(|tags| &tags[i]).filter(|&&t| t != 0) {
+            groups.insert(tag);
+        }
+        for &v in &connectivity[start..end as usize] {
+            let p = points
+                .get(3 * v as usize..3 * v as usize + 3)
+                .ok_or(Error::UnknownNode(v + 1))?;
+            for axis in 0..3 {
+                bounds[axis] = bounds[axis].min(p[axis]);
+                bounds[axis + 3] = bounds[axis + 3].max(p[axis]);
+            }
+        }
+        start = end as usize;
+    }
+
+    writeln!(w, "$MeshFormat\n4.1 0 8\n$EndMeshFormat")?;
+
+    if !mesh.physical_names.is_empty() {
+        writeln!(w, "$PhysicalNames\n{}", mesh.physical_names.len())?;
+        for name in &mesh.physical_names {
+            writeln!(w, "{} {} \"{}\"", name.dim, name.tag, name.name)?;
+        }
+        writeln!(w, "$EndPhysicalNames")?;
+    }
+
+    writeln!(w, "$Entities")?;
+    let count = |dim: u8| entities.keys().filter(|(d, _)| *d == dim).count();
+    writeln!(w, "{} {} {} {}", count(0), count(1), count(2), count(3))?;
+    for (&(dim, tag), (groups, bounds)) in &entities {
+        let bounds = if dim == 0 { &bounds[..3] } else { &bounds[..] };
+        write!(w, "{}", tag)?;
+        for x in bounds {
+            write!(w, " {}", x)?;
+        }
+        write!(w, " {}", groups.len())?;
+        for group in groups {
+            write!(w, " {}", group)?;
+        }
+        // Points have no bounding entities.
+        writeln!(w, "{}", if dim == 0 { "" } else { " 0" })?;
+    }
+    writeln!(w, "$EndEntities")?;
+
+    // All nodes are written in a single block attached to the first entity of highest dimension.
+    let num_nodes = points.len() / 3;
+    let &(dim, tag) = entities
+        .keys()
+        .max_by_key(|(dim, _)| *dim)
+        .unwrap_or(&(0, 1));
+    writeln!(w, "$Nodes\n1 {0} 1 {0}", num_nodes)?;
+    writeln!(w, "{} {} 0 {}", dim, tag, num_nodes)?;
+    for i in 1..=num_nodes {
+        writeln!(w, "{}", i)?;
+    }
+    for p in points.chunks_exact(3) {
+        writeln!(w, "{} {} {}", p[0], p[1], p[2])?;
+    }
+    writeln!(w, "$EndNodes")?;
+
+    let num_cells = piece.cells.types.len();
+    writeln!(
+        w,
+        "$Elements\n{} {} 1 {}",
+        blocks.len(),
+        num_cells,
+        num_cells
+    )?;
+    let mut element_tag = 0;
+    for &((dim, entity, cell_type), ref cells) in &blocks {
+        let &(ty, _, _) = ELEMENT_TYPES.iter().find(|e| e.1 == cell_type).unwrap();
+        writeln!(w, "{} {} {} {}", dim, entity, ty, cells.len())?;
+        for &i in cells {
+            let start = if i == 0 { 0 } else { offsets[i - 1] as usize };
+            let nodes = &connectivity[start..offsets[i] as usize];
+            element_tag += 1;
+            write!(w, "{}", element_tag)?;
+            for &j in &node_order(cell_type)[..nodes.len()] {
+                write!(w, " {}", nodes[j] + 1)?;
+            }
+            writeln!(w)?;
+        }
+    }
+    writeln!(w, "$EndElements")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_with_groups() -> Result<(), crate::Error> {
+        let mut data = Attributes::new();
+        data.cell
+            .push(Attribute::scalars(PHYSICAL, 1).with_data(vec![1i32, 2, 2]));
+        data.cell
+            .push(Attribute::scalars(GEOMETRICAL, 1).with_data(vec![1i32, 1, 3]));
+        let piece = UnstructuredGridPiece {
+            points: vec![
+                0.0f64, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.5, 0.0, 0.0, 0.5,
+                0.5, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.5, 0.0, 0.5, 0.5,
+            ]
+            .into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2],
+                    offsets: vec![4, 14, 17],
+                },
+                types: vec![
+                    CellType::Tetra,
+                    CellType::QuadraticTetra,
+                    CellType::Triangle,
+                ],
+            },
+            data,
+        };
+        let mesh = Mesh {
+            piece,
+            physical_names: vec![PhysicalName {
+                dim: 3,
+                tag: 1,
+                name: String::from("solid body"),
+            }],
+        };
+
+        let mut output = Vec::new();
+        mesh.write(&mut output)?;
+        let text = String::from_utf8(output.clone()).unwrap();
+        // Both tetrahedra share an entity, so it belongs to both physical groups.
+        assert!(text.contains("\n1 0 0 0 1 1 1 2 1 2 0\n"));
+        // The last two edge nodes of quadratic tetrahedra are swapped.
+        assert!(text.contains("\n2 1 2 3 4 5 6 7 8 10 9\n"));
+
+        let parsed = Mesh::parse(output.as_slice())?;
+        assert_eq!(parsed.physical_names, mesh.physical_names);
+        assert_eq!(parsed.piece.points, mesh.piece.points);
+        assert_eq!(parsed.piece.cells, mesh.piece.cells);
+        // Cells only keep the first physical group of their entity.
+        assert_eq!(cell_tags(&parsed.piece, PHYSICAL), Some(vec![1, 1, 2]));
+        assert_eq!(cell_tags(&parsed.piece, GEOMETRICAL), Some(vec![1, 1, 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        let err = |input: &str| match Mesh::parse(input.as_bytes()) {
+            Err(crate::Error::Gmsh(err)) => err,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(
+            err("$MeshFormat\n2.2 0 8\n$EndMeshFormat\n"),
+            Error::UnsupportedVersion(String::from("2.2"))
+        );
+        assert_eq!(err("$MeshFormat\n4.1 1 8\n$EndMeshFormat\n"), Error::Binary);
+        assert_eq!(
+            err("$MeshFormat\n4.1 0 8\n$EndMeshFormat\n$Nodes\n1 1 1 1\n"),
+            Error::Syntax(6)
+        );
+        assert_eq!(
+            err("$MeshFormat\n4.1 0 8\n$EndMeshFormat\n$Nodes\n0 0 0 0\n$EndNodes\n"),
+            Error::MissingSection("Elements")
+        );
+        // Huge counts are not trusted before the data is read.
+        assert_eq!(
+            err("$MeshFormat\n4.1 0 8\n$EndMeshFormat\n$Nodes\n1 9999999999999999 1 9999999999999999\n1 1 0 9999999999999999\n"),
+            Error::Syntax(7)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Export of graphs and networks as poly data.
+//!
+//! A [`Graph`] stores nodes as points and edges as two point lines, with node attributes stored as
+//! point data and edge attributes as cell data. The resulting `PolyData` can be loaded into
+//! ParaView directly, where the nodes are typically shown with the `Points` representation or a
+//! `Glyph` filter. Note that nodes are not referenced by any cell other than their edges, so
+//! isolated nodes are only visible as points.
+//!
+//! Graphs can be built from edge lists, adjacency lists or, with the `petgraph` feature, from
+//! [`petgraph::Graph`](https://docs.rs/petgraph/0.6/petgraph/graph/struct.Graph.html)s.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::graph::Graph;
+//!
+//! let nodes = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+//! let graph = Graph::from_edges(nodes, vec![(0, 1), (1, 2), (2, 0)])
+//!     .with_node_attribute(Attribute::scalars("degree", 1).with_data(vec![2u32, 2, 2]))
+//!     .unwrap()
+//!     .with_edge_attribute(Attribute::scalars("weight", 1).with_data(vec![0.5f32, 1.0, 2.0]))
+//!     .unwrap();
+//!
+//! let piece = graph.into_piece();
+//! assert_eq!(piece.num_points(), 3);
+//! assert_eq!(piece.num_lines(), 3);
+//! ```
+
+use crate::model::{self, *};
+
+/// A graph with node positions and attributes for both nodes and edges.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Graph {
+    /// Positions of the nodes.
+    pub nodes: Vec<[f64; 3]>,
+    /// Pairs of node indices connected by an edge.
+    pub edges: Vec<[u64; 2]>,
+    /// Node attributes, each with one tuple per node.
+    pub node_attributes: Vec<Attribute>,
+    /// Edge attributes, each with one tuple per edge.
+    pub edge_attributes: Vec<Attribute>,
+}
+
+impl Graph {
+    /// Constructs a graph with the given node positions and no edges.
+    pub fn new(nodes: Vec<[f64; 3]>) -> Self {
+        Graph {
+            nodes,
+            ..Default::default()
+        }
+    }
+
+    /// Constructs a graph from node positions and a list of edges given by node indices.
+    pub fn from_edges(
+        nodes: Vec<[f64; 3]>,
+        edges: impl IntoIterator<Item = (usize, usize)>,
+    ) -> Self {
+        Graph {
+            edges: edges
+                .into_iter()
+                .map(|(a, b)| [a as u64, b as u64])
+                .collect(),
+            ..Graph::new(nodes)
+        }
+    }
+
+    /// Constructs a graph from node positions and an adjacency list.
+    ///
+    /// Each entry `j` in `adjacency[i]` produces an edge from node `i` to node `j`, ordered by `i`
+    /// and then by the position of `j` in the list. Undirected graphs that list each edge at both
+    /// of its nodes thus produce two overlapping lines per edge.
+    pub fn from_adjacency(nodes: Vec<[f64; 3]>, adjacency: &[Vec<usize>]) -> Self {
+        let edges = adjacency
+            .iter()
+            .enumerate()
+            .flat_map(|(i, neighbours)| neighbours.iter().map(move |&j| (i, j)));
+        Graph::from_edges(nodes, edges)
+    }
+
+    /// Returns the number of nodes in this graph.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of edges in this graph.
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Adds a node attribute.
+    ///
+    /// An error is returned if the attribute doesn't have exactly one tuple per node.
+    pub fn with_node_attribute(mut self, attribute: Attribute) -> Result<Self, Error> {
+        model::validate_attribute_sizes(std::slice::from_ref(&attribute), self.num_nodes())?;
+        self.node_attributes.push(attribute);
+        Ok(self)
+    }
+
+    /// Adds an edge attribute.
+    ///
+    /// An error is returned if the attribute doesn't have exactly one tuple per edge.
+    pub fn with_edge_attribute(mut self, attribute: Attribute) -> Result<Self, Error> {
+        model::validate_attribute_sizes(std::slice::from_ref(&attribute), self.num_edges())?;
+        self.edge_attributes.push(attribute);
+        Ok(self)
+    }
+
+    /// Converts this graph into a poly data piece with one line per edge.
+    pub fn into_piece(self) -> PolyDataPiece {
+        let points: Vec<f64> = self.nodes.iter().flatten().copied().collect();
+        let num_edges = self.edges.len() as u64;
+        PolyDataPiece {
+            points: points.into(),
+            lines: Some(VertexNumbers::XML {
+                connectivity: self.edges.into_iter().flatten().collect(),
+                offsets: (1..=num_edges).map(|n| 2 * n).collect(),
+            }),
+            data: Attributes {
+                point: self.node_attributes,
+                cell: self.edge_attributes,
+                field: Vec::new(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Converts this graph into a one piece poly data set.
+    pub fn into_data_set(self) -> DataSet {
+        DataSet::inline(self.into_piece())
+    }
+}
+
+#[cfg(feature = "petgraph")]
+impl Graph {
+    /// Constructs a graph from a `petgraph` graph, computing the position of each node from its
+    /// weight.
+    ///
+    /// Nodes and edges keep their indices, so attributes can be built by iterating over
+    /// `node_weights` and `edge_weights` of `graph`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::graph::Graph;
+    ///
+    /// let mut network = petgraph::Graph::<[f64; 3], f32>::new();
+    /// let a = network.add_node([0.0, 0.0, 0.0]);
+    /// let b = network.add_node([1.0, 1.0, 0.0]);
+    /// network.add_edge(a, b, 4.5);
+    ///
+    /// let capacity: Vec<f32> = network.edge_weights().copied().collect();
+    /// let graph = Graph::from_petgraph(&network, |&pos| pos)
+    ///     .with_edge_attribute(Attribute::scalars("capacity", 1).with_data(capacity))
+    ///     .unwrap();
+    /// assert_eq!(graph.edges, vec![[0, 1]]);
+    /// ```
+    pub fn from_petgraph<N, E, Ty, Ix>(
+        graph: &petgraph::Graph<N, E, Ty, Ix>,
+        position: impl FnMut(&N) -> [f64; 3],
+    ) -> Self
+    where
+        Ty: petgraph::EdgeType,
+        Ix: petgraph::graph::IndexType,
+    {
+        use petgraph::visit::EdgeRef;
+        Graph::from_edges(
+            graph.node_weights().map(position).collect(),
+            graph
+                .edge_references()
+                .map(|edge| (edge.source().index(), edge.target().index())),
+        )
+    }
+}
+
+impl From<Graph> for PolyDataPiece {
+    fn from(graph: Graph) -> PolyDataPiece {
+        graph.into_piece()
+    }
+}
+
+impl From<Graph> for DataSet {
+    fn from(graph: Graph) -> DataSet {
+        graph.into_data_set()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjacency() {
+        let nodes = vec![[0.0; 3], [1.0; 3], [2.0; 3]];
+        let graph = Graph::from_adjacency(nodes, &[vec![1, 2], vec![], vec![1]]);
+        assert_eq!(graph.edges, vec![[0, 1], [0, 2], [2, 1]]);
+
+        let piece = graph.into_piece();
+        assert_eq!(
+            piece.lines,
+            Some(VertexNumbers::XML {
+                connectivity: vec![0, 1, 0, 2, 2, 1],
+                offsets: vec![2, 4, 6],
+            })
+        );
+    }
+
+    #[test]
+    fn attribute_sizes() {
+        let graph = Graph::from_edges(vec![[0.0; 3]; 3], vec![(0, 1)]);
+        assert!(graph
+            .clone()
+            .with_node_attribute(Attribute::scalars("n", 1).with_data(vec![0u8; 1]))
+            .is_err());
+        assert!(graph
+            .clone()
+            .with_edge_attribute(Attribute::scalars("e", 1).with_data(vec![0u8; 3]))
+            .is_err());
+
+        let piece = graph
+            .with_edge_attribute(Attribute::scalars("e", 1).with_data(vec![0u8; 1]))
+            .unwrap()
+            .into_piece();
+        assert_eq!(piece.data.cell.len(), 1);
+        assert!(piece.data.point.is_empty());
+    }
+}
@@ -0,0 +1,755 @@
+//! Spatial indices over the cells of a data set, stored in sidecar files.
+//!
+//! Interactive tools often only need the cells in a region of interest, but finding them requires
+//! a spatial data structure that is expensive to build for large data sets. [`build`] loads a file,
+//! builds a bounding volume hierarchy (BVH) over the bounding boxes of all its cells and writes it
+//! next to the file, so later runs can [`open`] the index without loading or processing the data.
+//!
+//! The sidecar file is stored at [`sidecar_path`] and records the size and modification time of
+//! the data file it was built from. [`open`] rebuilds the index if the data file has changed since.
+//!
+//! Cells are identified by the index of their piece along with their index within the piece. For
+//! poly data, cells are numbered in the order vertices, lines, polygons and triangle strips, as
+//! in VTK. Blanked cells of structured data sets (see
+//! [`StructuredGridPiece::is_cell_visible`]) are not indexed.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::index::{self, BoundingBox};
+//!
+//! let index = index::open("assets/hexahedron.vtu").expect("Failed to open index");
+//! let roi = BoundingBox::new([0.0, 0.0, 0.0], [0.5, 0.5, 0.5]);
+//! for cell in index.query(&roi) {
+//!     println!("piece {} cell {}", cell.piece, cell.cell);
+//! }
+//! ```
+//!
+//! # File format
+//!
+//! The sidecar file starts with the magic bytes `VTKIOBVH` followed by a format version, the
+//! size and modification time of the data file, and the number of nodes and cells, all stored as
+//! little endian integers. The nodes follow in depth first order, each given by its bounding box
+//! and two integers locating its children or cells, and finally the bounding box along with the
+//! piece and cell index of each cell.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::model::{self, *};
+use crate::Error;
+
+/// Identifies the sidecar file format.
+const MAGIC: &[u8; 8] = b"VTKIOBVH";
+/// The version of the sidecar file format written by this module.
+const FORMAT_VERSION: u32 = 1;
+/// The maximum number of cells stored in a single leaf of the hierarchy.
+const LEAF_SIZE: usize = 8;
+
+/// An axis aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BoundingBox {
+    /// Constructs a bounding box from its minimum and maximum corners.
+    pub fn new(min: [f64; 3], max: [f64; 3]) -> Self {
+        BoundingBox { min, max }
+    }
+
+    /// Computes the bounding box of the given points, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = [f64; 3]>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(BoundingBox::new(first, first), |bbox, p| {
+            bbox.union(&BoundingBox::new(p, p))
+        }))
+    }
+
+    /// Returns `true` if this box and `other` overlap, including when they only touch.
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// Returns `true` if the given point lies inside this box or on its boundary.
+    pub fn contains(&self, point: [f64; 3]) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+
+    /// Returns the smallest box containing both this box and `other`.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: [0, 1, 2].map(|i| self.min[i].min(other.min[i])),
+            max: [0, 1, 2].map(|i| self.max[i].max(other.max[i])),
+        }
+    }
+
+    fn center(&self) -> [f64; 3] {
+        [0, 1, 2].map(|i| 0.5 * (self.min[i] + self.max[i]))
+    }
+}
+
+/// The geometry of an image data set, as computed by [`fit_image_extent`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageGeometry {
+    pub extent: Extent,
+    pub origin: [f32; 3],
+    pub spacing: [f32; 3],
+}
+
+impl ImageGeometry {
+    /// Constructs an image data set with this geometry from a single piece with the given data.
+    pub fn into_data_set(self, data: Attributes) -> DataSet {
+        DataSet::ImageData {
+            extent: self.extent.clone(),
+            origin: self.origin,
+            spacing: self.spacing,
+            meta: None,
+            pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                extent: self.extent,
+                data,
+            }))],
+        }
+    }
+}
+
+/// Computes the origin and spacing of an image with `target_dims` points along each axis whose
+/// first and last points lie on the faces of `bounds`.
+///
+/// Images sample data at their points, so the outermost points are placed on the bounds rather
+/// than half a voxel inside them. Cell data with `n` cells along an axis therefore needs `n + 1`
+/// points. The origin and spacing are rounded outwards when converted to `f32`, so the image
+/// always covers the bounds.
+///
+/// Axes with a single point, or where the bounds are flat, have that point or the middle of the
+/// points at the center of the bounds. Zero dimensions are treated as one.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::index::{fit_image_extent, BoundingBox};
+/// use vtkio::model::Extent;
+///
+/// let bounds = BoundingBox::new([-1.0, 0.0, 2.0], [1.0, 10.0, 2.0]);
+/// let image = fit_image_extent(&bounds, [5, 11, 1]);
+/// assert_eq!(image.extent, Extent::Dims([5, 11, 1]));
+/// assert_eq!(image.origin, [-1.0, 0.0, 2.0]);
+/// assert_eq!(image.spacing, [0.5, 1.0, 1.0]);
+/// ```
+pub fn fit_image_extent(bounds: &BoundingBox, target_dims: [u32; 3]) -> ImageGeometry {
+    let dims = target_dims.map(|n| n.max(1));
+    let mut origin = [0.0; 3];
+    let mut spacing = [1.0; 3];
+    for i in 0..3 {
+        let (min, max) = (bounds.min[i], bounds.max[i]);
+        let steps = f64::from(dims[i] - 1);
+        if steps == 0.0 || max <= min {
+            let width = max - min;
+            if width > 0.0 {
+                spacing[i] = width as f32;
+            }
+            origin[i] = (0.5 * (min + max) - 0.5 * steps * f64::from(spacing[i])) as f32;
+            continue;
+        }
+        origin[i] = min as f32;
+        if f64::from(origin[i]) > min {
+            origin[i] = origin[i].next_down();
+        }
+        spacing[i] = ((max - min) / steps) as f32;
+        while f64::from(origin[i]) + f64::from(spacing[i]) * steps < max {
+            spacing[i] = spacing[i].next_up();
+        }
+    }
+    ImageGeometry {
+        extent: Extent::Dims(dims),
+        origin,
+        spacing,
+    }
+}
+
+/// Identifies a cell within a data set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CellRef {
+    /// The index of the piece containing the cell.
+    pub piece: usize,
+    /// The index of the cell within its piece.
+    pub cell: usize,
+}
+
+/// A node of the bounding volume hierarchy.
+///
+/// Leaves refer to `len` consecutive cells starting at `start`. Interior nodes have a `len` of
+/// zero, their first child immediately follows them and `start` is the index of the second child.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Node {
+    bounds: BoundingBox,
+    start: u64,
+    len: u32,
+}
+
+/// A bounding volume hierarchy over the cells of a data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpatialIndex {
+    nodes: Vec<Node>,
+    /// The cells referenced by the leaves along with their bounding boxes.
+    cells: Vec<(BoundingBox, CellRef)>,
+    /// The size and modification time of the data file this index was built from.
+    stamp: (u64, u64),
+}
+
+impl SpatialIndex {
+    /// Builds an index over the cells of the given data set.
+    ///
+    /// Pieces stored in other files are loaded relative to `vtk.file_path`. Cells without any
+    /// points are skipped.
+    pub fn from_vtk(vtk: Vtk) -> Result<SpatialIndex, Error> {
+        let source_path = vtk.file_path.as_deref();
+        let mut entries = Vec::new();
+        match vtk.data {
+            DataSet::ImageData {
+                origin,
+                spacing,
+                pieces,
+                ..
+            } => {
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    let piece = piece.into_loaded_piece_data(source_path)?;
+                    let coords = piece.extent.clone().into_ranges().map(|r| {
+                        (*r.start()..=*r.end())
+                            .map(|x| x as f64)
+                            .collect::<Vec<_>>()
+                    });
+                    let coords: [Vec<f64>; 3] = [0, 1, 2].map(|a| {
+                        let (o, s) = (origin[a] as f64, spacing[a] as f64);
+                        coords[a].iter().map(|&x| o + s * x).collect()
+                    });
+                    grid_cells(
+                        i,
+                        &piece.extent,
+                        &coords,
+                        |c| piece.is_cell_visible(c),
+                        &mut entries,
+                    );
+                }
+            }
+            DataSet::RectilinearGrid { pieces, .. } => {
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    let piece = piece.into_loaded_piece_data(source_path)?;
+                    let Coordinates { x, y, z } = &piece.coords;
+                    let coords = [x, y, z].map(|c| c.cast_into::<f64>().unwrap_or_default());
+                    grid_cells(i, &piece.extent, &coords, |_| true, &mut entries);
+                }
+            }
+            DataSet::StructuredGrid { pieces, .. } => {
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    let piece = piece.into_loaded_piece_data(source_path)?;
+                    let points = points_f64(&piece.points);
+                    let dims = piece.extent.clone().into_dims();
+                    for c in 0..piece.extent.num_cells() as usize {
+                        if !piece.is_cell_visible(c) {
+                            continue;
+                        }
+                        let bounds = BoundingBox::from_points(
+                            structured_cell_points(dims, c).filter_map(|p| points.get(p).copied()),
+                        );
+                        push_cell(&mut entries, i, c, bounds);
+                    }
+                }
+            }
+            DataSet::UnstructuredGrid { pieces, .. } => {
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    let piece = piece.into_loaded_piece_data(source_path)?;
+                    let points = points_f64(&piece.points);
+                    let cells = checked_cells(&piece.cells.cell_verts)?;
+                    for (c, verts) in cells.iter().enumerate() {
+                        let bounds = if piece.cells.types.get(c) == Some(&CellType::Polyhedron) {
+                            cell_bounds(&points, c, &face_stream_verts(verts))?
+                        } else {
+                            cell_bounds(&points, c, verts)?
+                        };
+                        push_cell(&mut entries, i, c, bounds);
+                    }
+                }
+            }
+            DataSet::PolyData { pieces, .. } => {
+                for (i, piece) in pieces.into_iter().enumerate() {
+                    let piece = piece.into_loaded_piece_data(source_path)?;
+                    let points = points_f64(&piece.points);
+                    let topologies = [&piece.verts, &piece.lines, &piece.polys, &piece.strips];
+                    let mut c = 0;
+                    for topo in topologies.iter().copied().flatten() {
+                        for verts in checked_cells(topo)? {
+                            let bounds = cell_bounds(&points, c, &verts)?;
+                            push_cell(&mut entries, i, c, bounds);
+                            c += 1;
+                        }
+                    }
+                }
+            }
+            DataSet::Field { .. } => return Err(Error::UnsupportedDataSet("Field")),
+        }
+
+        let mut nodes = Vec::new();
+        if !entries.is_empty() {
+            build_node(&mut nodes, &mut entries, 0);
+        }
+        Ok(SpatialIndex {
+            nodes,
+            cells: entries,
+            stamp: (0, 0),
+        })
+    }
+
+    /// Returns the number of indexed cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns `true` if no cells are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Returns the bounding box of all indexed cells, or `None` if the index is empty.
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    /// Returns all cells whose bounding boxes intersect the given region, sorted by piece and cell
+    /// index.
+    ///
+    /// Since cells are represented by their bounding boxes, some of the returned cells may not
+    /// actually intersect the region.
+    pub fn query(&self, roi: &BoundingBox) -> Vec<CellRef> {
+        let mut result = Vec::new();
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let node = match self.nodes.get(i) {
+                Some(node) if node.bounds.intersects(roi) => node,
+                _ => continue,
+            };
+            if node.len == 0 {
+                stack.push(node.start as usize);
+                stack.push(i + 1);
+            } else {
+                let start = node.start as usize;
+                let cells = &self.cells[start..start + node.len as usize];
+                result.extend(
+                    cells
+                        .iter()
+                        .filter(|(bounds, _)| bounds.intersects(roi))
+                        .map(|&(_, cell)| cell),
+                );
+            }
+        }
+        result.sort_unstable();
+        result
+    }
+
+    /// Writes this index in the sidecar file format.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(FORMAT_VERSION)?;
+        writer.write_u64::<LE>(self.stamp.0)?;
+        writer.write_u64::<LE>(self.stamp.1)?;
+        writer.write_u64::<LE>(self.nodes.len() as u64)?;
+        writer.write_u64::<LE>(self.cells.len() as u64)?;
+        for node in &self.nodes {
+            write_bounds(&mut writer, &node.bounds)?;
+            writer.write_u64::<LE>(node.start)?;
+            writer.write_u32::<LE>(node.len)?;
+        }
+        for (bounds, cell) in &self.cells {
+            write_bounds(&mut writer, bounds)?;
+            writer.write_u64::<LE>(cell.piece as u64)?;
+            writer.write_u64::<LE>(cell.cell as u64)?;
+        }
+        Ok(())
+    }
+
+    /// Reads an index in the sidecar file format.
+    ///
+    /// An error of kind `InvalidData` is returned if the data is not a valid index.
+    pub fn read(mut reader: impl Read) -> io::Result<SpatialIndex> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a spatial index file"));
+        }
+        if reader.read_u32::<LE>()? != FORMAT_VERSION {
+            return Err(invalid_data("unsupported spatial index version"));
+        }
+        let stamp = (reader.read_u64::<LE>()?, reader.read_u64::<LE>()?);
+        let num_nodes = reader.read_u64::<LE>()?;
+        let num_cells = reader.read_u64::<LE>()?;
+        let mut nodes = Vec::new();
+        for _ in 0..num_nodes {
+            nodes.push(Node {
+                bounds: read_bounds(&mut reader)?,
+                start: reader.read_u64::<LE>()?,
+                len: reader.read_u32::<LE>()?,
+            });
+        }
+        let mut cells = Vec::new();
+        for _ in 0..num_cells {
+            let bounds = read_bounds(&mut reader)?;
+            let cell = CellRef {
+                piece: reader.read_u64::<LE>()? as usize,
+                cell: reader.read_u64::<LE>()? as usize,
+            };
+            cells.push((bounds, cell));
+        }
+        // Make sure queries can't index out of bounds.
+        let valid = nodes.iter().enumerate().all(|(i, node)| {
+            if node.len == 0 {
+                node.start > i as u64 + 1 && node.start < nodes.len() as u64
+            } else {
+                node.start + u64::from(node.len) <= cells.len() as u64
+            }
+        });
+        if !valid {
+            return Err(invalid_data("corrupt spatial index"));
+        }
+        Ok(SpatialIndex {
+            nodes,
+            cells,
+            stamp,
+        })
+    }
+}
+
+/// Returns the path of the sidecar index file for the given data file.
+///
+/// The sidecar is stored next to the data file, with `.bvh` appended to its name.
+pub fn sidecar_path(file_path: impl AsRef<Path>) -> PathBuf {
+    let mut path = file_path.as_ref().as_os_str().to_owned();
+    path.push(".bvh");
+    PathBuf::from(path)
+}
+
+/// Builds a spatial index for the VTK file at the given path and writes it to the sidecar file.
+///
+/// Any existing sidecar file is replaced.
+pub fn build(file_path: impl AsRef<Path>) -> Result<SpatialIndex, Error> {
+    let path = file_path.as_ref();
+    let stamp = file_stamp(path)?;
+    let mut index = SpatialIndex::from_vtk(crate::import(path)?)?;
+    index.stamp = stamp;
+    let mut writer = BufWriter::new(File::create(sidecar_path(path))?);
+    index.write(&mut writer)?;
+    writer.flush()?;
+    Ok(index)
+}
+
+/// Loads the spatial index for the VTK file at the given path.
+///
+/// The index is read from the sidecar file if it exists and the data file hasn't changed since
+/// the index was built. Otherwise the index is rebuilt with [`build`].
+pub fn open(file_path: impl AsRef<Path>) -> Result<SpatialIndex, Error> {
+    let path = file_path.as_ref();
+    if let Ok(file) = File::open(sidecar_path(path)) {
+        if let Ok(index) = SpatialIndex::read(BufReader::new(file)) {
+            if index.stamp == file_stamp(path)? {
+                return Ok(index);
+            }
+        }
+    }
+    build(path)
+}
+
+/// Returns the size and modification time in nanoseconds of the given file.
+fn file_stamp(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
+
+fn write_bounds(writer: &mut impl Write, bounds: &BoundingBox) -> io::Result<()> {
+    for &x in bounds.min.iter().chain(bounds.max.iter()) {
+        writer.write_f64::<LE>(x)?;
+    }
+    Ok(())
+}
+
+fn read_bounds(reader: &mut impl Read) -> io::Result<BoundingBox> {
+    let mut x = [0.0; 6];
+    reader.read_f64_into::<LE>(&mut x)?;
+    Ok(BoundingBox::new([x[0], x[1], x[2]], [x[3], x[4], x[5]]))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn points_f64(points: &IOBuffer) -> Vec<[f64; 3]> {
+    points
+        .cast_into::<f64>()
+        .unwrap_or_default()
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect()
+}
+
+fn checked_cells(cell_verts: &VertexNumbers) -> Result<Vec<Vec<u64>>, Error> {
+    match split_cells(cell_verts) {
+        (cells, None) => Ok(cells),
+        (_, Some(cell)) => Err(Error::Load(model::Error::InvalidCells(
+            CellIssue::MalformedConnectivity { cell },
+        ))),
+    }
+}
+
+/// Extracts the vertex indices from a polyhedron face stream.
+fn face_stream_verts(stream: &[u64]) -> Vec<u64> {
+    let mut verts = Vec::new();
+    let mut rest = stream.get(1..).unwrap_or_default();
+    while let Some((&n, tail)) = rest.split_first() {
+        let n = (n as usize).min(tail.len());
+        verts.extend_from_slice(&tail[..n]);
+        rest = &tail[n..];
+    }
+    verts
+}
+
+fn cell_bounds(
+    points: &[[f64; 3]],
+    cell: usize,
+    verts: &[u64],
+) -> Result<Option<BoundingBox>, Error> {
+    let points = verts
+        .iter()
+        .map(|&v| {
+            points
+                .get(v as usize)
+                .copied()
+                .ok_or(Error::Load(model::Error::InvalidCells(
+                    CellIssue::IndexOutOfBounds {
+                        cell,
+                        index: v,
+                        num_points: points.len(),
+                    },
+                )))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(BoundingBox::from_points(points))
+}
+
+fn push_cell(
+    entries: &mut Vec<(BoundingBox, CellRef)>,
+    piece: usize,
+    cell: usize,
+    bounds: Option<BoundingBox>,
+) {
+    if let Some(bounds) = bounds {
+        entries.push((bounds, CellRef { piece, cell }));
+    }
+}
+
+/// Adds the cells of a grid whose points lie at the given coordinates along each axis.
+fn grid_cells(
+    piece: usize,
+    extent: &Extent,
+    coords: &[Vec<f64>; 3],
+    is_visible: impl Fn(usize) -> bool,
+    entries: &mut Vec<(BoundingBox, CellRef)>,
+) {
+    let dims = extent.clone().into_dims();
+    if coords
+        .iter()
+        .zip(dims.iter())
+        .any(|(c, &n)| c.len() < n as usize)
+    {
+        return;
+    }
+    for c in 0..extent.num_cells() as usize {
+        if !is_visible(c) {
+            continue;
+        }
+        let [nx, ny, nz] = dims.map(|n| n as usize);
+        let cx = nx.saturating_sub(1).max(1);
+        let cy = ny.saturating_sub(1).max(1);
+        let ijk = [c % cx, c / cx % cy, c / (cx * cy)];
+        let corner = |hi: bool| {
+            [0, 1, 2].map(|a| {
+                let i = if hi && [nx, ny, nz][a] > 1 {
+                    ijk[a] + 1
+                } else {
+                    ijk[a]
+                };
+                coords[a][i]
+            })
+        };
+        let bounds = BoundingBox::from_points(vec![corner(false), corner(true)]);
+        push_cell(entries, piece, c, bounds);
+    }
+}
+
+/// Recursively builds the hierarchy over `entries`, which start at `offset` among all cells.
+fn build_node(nodes: &mut Vec<Node>, entries: &mut [(BoundingBox, CellRef)], offset: usize) {
+    let bounds = entries
+        .iter()
+        .map(|(b, _)| *b)
+        .reduce(|a, b| a.union(&b))
+        .expect("empty node");
+    let node = nodes.len();
+    nodes.push(Node {
+        bounds,
+        start: offset as u64,
+        len: entries.len() as u32,
+    });
+    if entries.len() <= LEAF_SIZE {
+        return;
+    }
+
+    // Split at the median along the axis over which cell centers are spread the most.
+    let centers = BoundingBox::from_points(entries.iter().map(|(b, _)| b.center())).unwrap();
+    let axis = (0..3)
+        .max_by(|&a, &b| {
+            let extent = |i: usize| centers.max[i] - centers.min[i];
+            extent(a).total_cmp(&extent(b))
+        })
+        .unwrap();
+    let mid = entries.len() / 2;
+    entries.select_nth_unstable_by(mid, |(a, _), (b, _)| {
+        a.center()[axis].total_cmp(&b.center()[axis])
+    });
+    let (left, right) = entries.split_at_mut(mid);
+    nodes[node].len = 0;
+    build_node(nodes, left, offset);
+    nodes[node].start = nodes.len() as u64;
+    build_node(nodes, right, offset + mid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(n: u32) -> Vtk {
+        Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::from("Grid"),
+            file_path: None,
+            data: DataSet::ImageData {
+                extent: Extent::Dims([n, n, n]),
+                origin: [0.0; 3],
+                spacing: [1.0; 3],
+                meta: None,
+                pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                    extent: Extent::Dims([n, n, n]),
+                    data: Attributes::new(),
+                }))],
+            },
+        }
+    }
+
+    #[test]
+    fn query_image_cells() -> Result<(), Error> {
+        let index = SpatialIndex::from_vtk(grid(11))?;
+        assert_eq!(index.len(), 1000);
+        assert_eq!(index.bounds(), Some(BoundingBox::new([0.0; 3], [10.0; 3])));
+
+        // The region touches the 8 cells around the point (5, 5, 5).
+        let cells = index.query(&BoundingBox::new([4.5; 3], [5.0; 3]));
+        let expected: Vec<_> = [444, 445, 454, 455, 544, 545, 554, 555]
+            .iter()
+            .map(|&cell| CellRef { piece: 0, cell })
+            .collect();
+        assert_eq!(cells, expected);
+        assert!(index
+            .query(&BoundingBox::new([11.0; 3], [12.0; 3]))
+            .is_empty());
+
+        // The hierarchy survives a round trip through the sidecar format.
+        let mut bytes = Vec::new();
+        index.write(&mut bytes)?;
+        assert_eq!(SpatialIndex::read(bytes.as_slice())?, index);
+        bytes[8] = 2;
+        assert!(SpatialIndex::read(bytes.as_slice()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn query_poly_data_cells() -> Result<(), Error> {
+        let vtk = Vtk {
+            data: DataSet::inline(PolyDataPiece {
+                points: vec![0.0f32, 0., 0., 1., 0., 0., 0., 1., 0., 5., 5., 5.].into(),
+                verts: Some(VertexNumbers::Legacy {
+                    num_cells: 1,
+                    vertices: vec![1, 3],
+                }),
+                polys: Some(VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2],
+                    offsets: vec![3],
+                }),
+                ..Default::default()
+            }),
+            ..grid(1)
+        };
+        let index = SpatialIndex::from_vtk(vtk)?;
+        let query = |min, max| index.query(&BoundingBox::new(min, max));
+        assert_eq!(query([0.4; 3], [0.6; 3]), vec![]);
+        assert_eq!(
+            query([0.2, 0.2, 0.0], [0.3, 0.3, 0.0]),
+            vec![CellRef { piece: 0, cell: 1 }]
+        );
+        assert_eq!(
+            query([4.0; 3], [6.0; 3]),
+            vec![CellRef { piece: 0, cell: 0 }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sidecar_file() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("vtkio_index_sidecar_file");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("grid.vtk");
+        grid(3).export(&path)?;
+
+        let built = build(&path)?;
+        assert!(sidecar_path(&path).exists());
+        assert_eq!(built.len(), 8);
+        assert_eq!(open(&path)?, built);
+
+        // A stale sidecar is rebuilt.
+        grid(4).export(&path)?;
+        assert_eq!(open(&path)?.len(), 27);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn fit_image_covers_bounds() {
+        let bounds = BoundingBox::new([0.1, -0.3, 5.0], [0.7, 1e7 + 1.0, 5.0]);
+        let image = fit_image_extent(&bounds, [7, 3, 4]);
+        assert_eq!(image.extent, Extent::Dims([7, 3, 4]));
+        let dims = [7.0, 3.0, 4.0];
+        for (i, dim) in dims.iter().enumerate().take(2) {
+            let first = f64::from(image.origin[i]);
+            let last = first + f64::from(image.spacing[i]) * (dim - 1.0);
+            assert!(first <= bounds.min[i] && bounds.max[i] <= last);
+            assert!(bounds.min[i] - first < 1e-6 * bounds.max[i].abs().max(1.0));
+            assert!(last - bounds.max[i] < 1e-5 * bounds.max[i].abs().max(1.0));
+        }
+        // Points along a flat axis are centered on it.
+        assert_eq!(image.spacing[2], 1.0);
+        assert_eq!(image.origin[2], 3.5);
+
+        // A single point sits at the center of the bounds.
+        let image = fit_image_extent(&BoundingBox::new([0.0; 3], [2.0; 3]), [1, 0, 3]);
+        assert_eq!(image.extent, Extent::Dims([1, 1, 3]));
+        assert_eq!(image.origin, [1.0, 1.0, 0.0]);
+        assert_eq!(image.spacing, [2.0, 2.0, 1.0]);
+    }
+}
@@ -0,0 +1,537 @@
+//! Importing LAS and LAZ lidar files as point clouds.
+//!
+//! LAS is the standard exchange format for airborne and terrestrial lidar surveys. A [`LasFile`]
+//! reads the points of a LAS file (versions 1.0 to 1.4, point formats 0 to 10) into a
+//! [`PointCloud`], which converts into vertex poly data ready for ParaView. Coordinates are
+//! scaled and offset into `f64` positions, and the per-point measurements are stored in the
+//! attributes [`INTENSITY`], [`RETURN_NUMBER`], [`NUMBER_OF_RETURNS`] and [`CLASSIFICATION`],
+//! along with [`GPS_TIME`] and the 16-bit RGB [`COLOR`] for point formats that record them.
+//!
+//! LAZ files, which store the same data compressed with LASzip, are decompressed with the `laz`
+//! crate while reading.
+//!
+//! This module is available with the `las` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::las::{self, LasFile};
+//!
+//! let las = LasFile::import("survey.las").expect("Failed to import LAS file");
+//! println!("{} points", las.header.num_points);
+//! assert!(las.cloud.attribute(las::CLASSIFICATION).is_some());
+//! las.into_vtk().export("survey.vtp").expect("Failed to export point cloud");
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::model::{self, *};
+use crate::point_cloud::PointCloud;
+
+/// Name of the point attribute holding the return pulse intensities.
+pub const INTENSITY: &str = "Intensity";
+/// Name of the point attribute holding the return number of each point within its pulse.
+pub const RETURN_NUMBER: &str = "ReturnNumber";
+/// Name of the point attribute holding the number of returns of the pulse of each point.
+pub const NUMBER_OF_RETURNS: &str = "NumberOfReturns";
+/// Name of the point attribute holding the ASPRS classification codes.
+pub const CLASSIFICATION: &str = "Classification";
+/// Name of the point attribute holding the GPS times.
+pub const GPS_TIME: &str = "GpsTime";
+/// Name of the point attribute holding the 16-bit red, green and blue components.
+pub const COLOR: &str = "Color";
+
+/// The signature at the start of every LAS file.
+const SIGNATURE: &[u8] = b"LASF";
+
+/// The size of the header of each variable length record.
+const VLR_HEADER_SIZE: usize = 54;
+
+/// Error produced when reading LAS files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The file doesn't start with the `LASF` signature.
+    NotLas,
+    /// The file has an unsupported major and minor version.
+    UnsupportedVersion(u8, u8),
+    /// The points are stored in an unsupported point data record format.
+    UnsupportedPointFormat(u8),
+    /// The compressed points of a LAZ file lack their LASzip record or can't be decompressed.
+    Compressed,
+    /// The file is shorter than its header declares, or the header is inconsistent.
+    Truncated,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotLas => write!(f, "Missing LASF signature"),
+            Error::UnsupportedVersion(major, minor) => {
+                write!(f, "Unsupported LAS version: {}.{}", major, minor)
+            }
+            Error::UnsupportedPointFormat(format) => {
+                write!(f, "Unsupported point data record format: {}", format)
+            }
+            Error::Compressed => write!(f, "Invalid LAZ compressed point data"),
+            Error::Truncated => write!(f, "Truncated or inconsistent LAS file"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The public header block of a LAS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// Major and minor version of the file.
+    pub version: (u8, u8),
+    /// The system that produced the data, typically the scanner.
+    pub system_identifier: String,
+    /// The software that wrote the file.
+    pub generating_software: String,
+    /// The point data record format.
+    pub point_format: u8,
+    /// The number of points in the file.
+    pub num_points: u64,
+    /// Factors applied to the stored integer coordinates.
+    pub scale: [f64; 3],
+    /// Offsets added to the scaled coordinates.
+    pub offset: [f64; 3],
+    /// The bounds of the points as `[min x, max x, min y, max y, min z, max z]`.
+    pub bounds: [f64; 6],
+}
+
+impl Header {
+    /// Returns `true` if the point format records GPS times.
+    fn has_gps_time(&self) -> bool {
+        self.point_format != 0 && self.point_format != 2
+    }
+
+    /// Returns `true` if the point format records colors.
+    fn has_color(&self) -> bool {
+        [2, 3, 5, 7, 8, 10].contains(&self.point_format)
+    }
+}
+
+/// The points of a LAS file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LasFile {
+    pub header: Header,
+    pub cloud: PointCloud,
+}
+
+impl LasFile {
+    /// Imports the LAS file at the given path.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<LasFile, crate::Error> {
+        LasFile::parse(BufReader::new(File::open(file_path)?))
+    }
+
+    /// Parses a LAS or LAZ file from the given reader.
+    pub fn parse(mut reader: impl Read) -> Result<LasFile, crate::Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let (header, point_offset, record_len, compressed) = parse_header(&data)?;
+        let num_points = usize::try_from(header.num_points).map_err(|_| Error::Truncated)?;
+        let len = num_points.checked_mul(record_len).ok_or(Error::Truncated)?;
+        let cloud = if compressed {
+            let records = decompress(&data, point_offset, record_len, num_points)?;
+            read_points(&records, &header, record_len)?
+        } else {
+            let records = data
+                .get(point_offset..)
+                .and_then(|points| points.get(..len))
+                .ok_or(Error::Truncated)?;
+            read_points(records, &header, record_len)?
+        };
+        Ok(LasFile { header, cloud })
+    }
+
+    /// Converts the points into a model with a single vertex poly data piece.
+    ///
+    /// The title is taken from the system identifier in the header.
+    pub fn into_vtk(self) -> Vtk {
+        Vtk {
+            version: Version::new((4, 2)),
+            byte_order: model::ByteOrder::BigEndian,
+            title: self.header.system_identifier,
+            file_path: None,
+            data: self.cloud.into_data_set(),
+        }
+    }
+}
+
+/// Reads a fixed size, nul padded string.
+fn read_string(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len])
+        .trim_end()
+        .to_string()
+}
+
+fn read_f64s<const N: usize>(bytes: &[u8]) -> [f64; N] {
+    let mut values = [0.0; N];
+    LittleEndian::read_f64_into(&bytes[..8 * N], &mut values);
+    values
+}
+
+/// Parses the public header block, returning it along with the offset to the point data, the
+/// size of each point record and whether the points are compressed.
+fn parse_header(data: &[u8]) -> Result<(Header, usize, usize, bool), Error> {
+    if !data.starts_with(SIGNATURE) {
+        return Err(Error::NotLas);
+    }
+    // The header of version 1.0 is the smallest at 227 bytes.
+    if data.len() < 227 {
+        return Err(Error::Truncated);
+    }
+    let version = (data[24], data[25]);
+    if version.0 != 1 || version.1 > 4 {
+        return Err(Error::UnsupportedVersion(version.0, version.1));
+    }
+    let header_size = usize::from(LittleEndian::read_u16(&data[94..]));
+    let point_offset = LittleEndian::read_u32(&data[96..]) as usize;
+    // LAZ files flag the point format with the high bit, the next bit is reserved.
+    let compressed = data[104] & 0x80 != 0;
+    let point_format = match data[104] & 0x7f {
+        format if format > 10 => return Err(Error::UnsupportedPointFormat(format)),
+        format => format,
+    };
+    let record_len = usize::from(LittleEndian::read_u16(&data[105..]));
+    let mut num_points = u64::from(LittleEndian::read_u32(&data[107..]));
+    // Version 1.4 adds a 64-bit point count, required for the new point formats.
+    if version.1 >= 4 && header_size >= 375 && data.len() >= 375 {
+        num_points = LittleEndian::read_u64(&data[247..]);
+    }
+    // Bounds are stored with the maximum first.
+    let [max_x, min_x, max_y, min_y, max_z, min_z]: [f64; 6] = read_f64s(&data[179..]);
+    let header = Header {
+        version,
+        system_identifier: read_string(&data[26..58]),
+        generating_software: read_string(&data[58..90]),
+        point_format,
+        num_points,
+        scale: read_f64s(&data[131..]),
+        offset: read_f64s(&data[155..]),
+        bounds: [min_x, max_x, min_y, max_y, min_z, max_z],
+    };
+    if record_len < min_record_len(point_format) || point_offset < header_size {
+        return Err(Error::Truncated);
+    }
+    Ok((header, point_offset, record_len, compressed))
+}
+
+/// Finds the LASzip record among the variable length records following the header.
+fn laszip_vlr(data: &[u8]) -> Option<&[u8]> {
+    let num_vlrs = LittleEndian::read_u32(&data[100..]);
+    let mut offset = usize::from(LittleEndian::read_u16(&data[94..]));
+    for _ in 0..num_vlrs {
+        let header = data.get(offset..offset.checked_add(VLR_HEADER_SIZE)?)?;
+        let len = usize::from(LittleEndian::read_u16(&header[20..]));
+        let start = offset + VLR_HEADER_SIZE;
+        let record = data.get(start..start.checked_add(len)?)?;
+        if read_string(&header[2..18]) == laz::LazVlr::USER_ID
+            && LittleEndian::read_u16(&header[18..]) == laz::LazVlr::RECORD_ID
+        {
+            return Some(record);
+        }
+        offset = start + len;
+    }
+    None
+}
+
+/// Decompresses the point records of a LAZ file.
+fn decompress(
+    data: &[u8],
+    point_offset: usize,
+    record_len: usize,
+    num_points: usize,
+) -> Result<Vec<u8>, Error> {
+    let vlr = laszip_vlr(data)
+        .and_then(|record| laz::LazVlr::from_buffer(record).ok())
+        .ok_or(Error::Compressed)?;
+    if vlr.items_size() != record_len as u64 {
+        return Err(Error::Compressed);
+    }
+    // The chunk table offset stored with the points is relative to the start of the file.
+    let mut source = Cursor::new(data);
+    source.set_position(point_offset as u64);
+    let mut decompressor =
+        laz::LasZipDecompressor::new(source, vlr).map_err(|_| Error::Compressed)?;
+    // Grow the buffer one record at a time, so a bogus point count fails on the missing data
+    // instead of allocating up front.
+    let mut records = Vec::new();
+    for _ in 0..num_points {
+        let start = records.len();
+        records.resize(start + record_len, 0);
+        decompressor
+            .decompress_one(&mut records[start..])
+            .map_err(|_| Error::Compressed)?;
+    }
+    Ok(records)
+}
+
+/// The size of the standard fields of each point data record format.
+fn min_record_len(point_format: u8) -> usize {
+    match point_format {
+        0 => 20,
+        1 => 28,
+        2 => 26,
+        3 => 34,
+        4 => 57,
+        5 => 63,
+        6 => 30,
+        7 => 36,
+        8 => 38,
+        9 => 59,
+        _ => 67,
+    }
+}
+
+/// Reads the uncompressed point records into a point cloud.
+fn read_points(
+    records: &[u8],
+    header: &Header,
+    record_len: usize,
+) -> Result<PointCloud, crate::Error> {
+    let num_points = records.len() / record_len;
+    let mut points = Vec::with_capacity(num_points);
+    let mut intensity = Vec::with_capacity(num_points);
+    let mut return_number = Vec::with_capacity(num_points);
+    let mut number_of_returns = Vec::with_capacity(num_points);
+    let mut classification = Vec::with_capacity(num_points);
+    let mut gps_time = Vec::new();
+    let mut color = Vec::new();
+    let extended = header.point_format >= 6;
+    for record in records.chunks_exact(record_len) {
+        let mut pos = [0.0; 3];
+        for (axis, x) in pos.iter_mut().enumerate() {
+            let stored = LittleEndian::read_i32(&record[4 * axis..]);
+            *x = f64::from(stored) * header.scale[axis] + header.offset[axis];
+        }
+        points.push(pos);
+        intensity.push(LittleEndian::read_u16(&record[12..]));
+        // Extended formats widen the return fields to 4 bits and the classification to a byte.
+        let (gps_offset, color_offset) = if extended {
+            return_number.push(record[14] & 0x0f);
+            number_of_returns.push(record[14] >> 4);
+            classification.push(record[16]);
+            (22, 30)
+        } else {
+            return_number.push(record[14] & 0x07);
+            number_of_returns.push((record[14] >> 3) & 0x07);
+            classification.push(record[15] & 0x1f);
+            (20, if header.point_format == 2 { 20 } else { 28 })
+        };
+        if header.has_gps_time() {
+            gps_time.push(LittleEndian::read_f64(&record[gps_offset..]));
+        }
+        if header.has_color() {
+            for c in 0..3 {
+                color.push(LittleEndian::read_u16(&record[color_offset + 2 * c..]));
+            }
+        }
+    }
+
+    let mut cloud = PointCloud::new(points)
+        .with_attribute(Attribute::scalars(INTENSITY, 1).with_data(intensity))?
+        .with_attribute(Attribute::scalars(RETURN_NUMBER, 1).with_data(return_number))?
+        .with_attribute(Attribute::scalars(NUMBER_OF_RETURNS, 1).with_data(number_of_returns))?
+        .with_attribute(Attribute::scalars(CLASSIFICATION, 1).with_data(classification))?;
+    if header.has_gps_time() {
+        cloud.push_attribute(Attribute::scalars(GPS_TIME, 1).with_data(gps_time))?;
+    }
+    if header.has_color() {
+        cloud.push_attribute(Attribute::generic(COLOR, 3).with_data(color))?;
+    }
+    Ok(cloud)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a LAS file with the given points, each given by its stored coordinates, intensity,
+    /// classification and the remaining bytes of its record.
+    fn las_file(version: u8, point_format: u8, points: &[([i32; 3], u16, u8, Vec<u8>)]) -> Vec<u8> {
+        let header_size = if version == 4 { 375 } else { 227 };
+        let record_len = min_record_len(point_format);
+        let mut data = vec![0; header_size];
+        data[..4].copy_from_slice(SIGNATURE);
+        data[24] = 1;
+        data[25] = version;
+        data[26..32].copy_from_slice(b"Survey");
+        LittleEndian::write_u16(&mut data[94..], header_size as u16);
+        LittleEndian::write_u32(&mut data[96..], header_size as u32);
+        data[104] = point_format;
+        LittleEndian::write_u16(&mut data[105..], record_len as u16);
+        if version == 4 {
+            LittleEndian::write_u64(&mut data[247..], points.len() as u64);
+        } else {
+            LittleEndian::write_u32(&mut data[107..], points.len() as u32);
+        }
+        LittleEndian::write_f64_into(&[0.01, 0.01, 0.1], &mut data[131..155]);
+        LittleEndian::write_f64_into(&[1000.0, 2000.0, 0.0], &mut data[155..179]);
+        LittleEndian::write_f64_into(&[1.0, -1.0, 2.0, -2.0, 3.0, -3.0], &mut data[179..227]);
+        for (coords, intensity, classification, rest) in points {
+            let mut record = vec![0; 14];
+            LittleEndian::write_i32_into(coords, &mut record[..12]);
+            LittleEndian::write_u16(&mut record[12..], *intensity);
+            if point_format >= 6 {
+                // Second of three returns.
+                record.extend(&[0x32, 0, *classification, 0, 0, 0, 0, 0]);
+            } else {
+                record.extend(&[0x1a, *classification | 0x80, 0, 0, 0, 0]);
+            }
+            record.extend(rest);
+            record.resize(record_len, 0);
+            data.extend(record);
+        }
+        data
+    }
+
+    #[test]
+    fn legacy_point_format() {
+        let mut rest = 12.5f64.to_le_bytes().to_vec();
+        rest.extend(&[1, 0, 2, 0, 3, 0]);
+        let data = las_file(
+            2,
+            3,
+            &[
+                ([100, 200, 30], 512, 2, rest.clone()),
+                ([-100, 0, 0], 7, 6, rest),
+            ],
+        );
+        let las = LasFile::parse(data.as_slice()).unwrap();
+        assert_eq!(las.header.version, (1, 2));
+        assert_eq!(las.header.system_identifier, "Survey");
+        assert_eq!(las.header.num_points, 2);
+        assert_eq!(las.header.bounds, [-1.0, 1.0, -2.0, 2.0, -3.0, 3.0]);
+
+        let cloud = &las.cloud;
+        assert_eq!(
+            cloud.points,
+            vec![[1001.0, 2002.0, 3.0], [999.0, 2000.0, 0.0]]
+        );
+        let attribute = |name| cloud.attribute(name).unwrap().clone();
+        assert_eq!(
+            attribute(INTENSITY),
+            Attribute::scalars(INTENSITY, 1).with_data(vec![512u16, 7])
+        );
+        assert_eq!(
+            attribute(CLASSIFICATION),
+            Attribute::scalars(CLASSIFICATION, 1).with_data(vec![2u8, 6])
+        );
+        assert_eq!(
+            attribute(RETURN_NUMBER),
+            Attribute::scalars(RETURN_NUMBER, 1).with_data(vec![2u8, 2])
+        );
+        assert_eq!(
+            attribute(NUMBER_OF_RETURNS),
+            Attribute::scalars(NUMBER_OF_RETURNS, 1).with_data(vec![3u8, 3])
+        );
+        assert_eq!(
+            attribute(GPS_TIME),
+            Attribute::scalars(GPS_TIME, 1).with_data(vec![12.5f64, 12.5])
+        );
+        assert_eq!(
+            attribute(COLOR),
+            Attribute::generic(COLOR, 3).with_data(vec![1u16, 2, 3, 1, 2, 3])
+        );
+
+        let vtk = las.into_vtk();
+        assert_eq!(vtk.title, "Survey");
+        assert_eq!(vtk.data.num_pieces(), 1);
+    }
+
+    #[test]
+    fn extended_point_format() {
+        let data = las_file(4, 6, &[([0, 0, 10], 1, 9, Vec::new())]);
+        let las = LasFile::parse(data.as_slice()).unwrap();
+        assert_eq!(las.cloud.points, vec![[1000.0, 2000.0, 1.0]]);
+        let attribute = |name| las.cloud.attribute(name).unwrap().clone();
+        assert_eq!(
+            attribute(CLASSIFICATION),
+            Attribute::scalars(CLASSIFICATION, 1).with_data(vec![9u8])
+        );
+        assert_eq!(
+            attribute(RETURN_NUMBER),
+            Attribute::scalars(RETURN_NUMBER, 1).with_data(vec![2u8])
+        );
+        assert!(las.cloud.attribute(GPS_TIME).is_some());
+        assert!(las.cloud.attribute(COLOR).is_none());
+    }
+
+    /// Compresses the points of a LAS file built by `las_file` into a LAZ file.
+    fn laz_file(las: &[u8], header_size: usize, point_format: u8) -> Vec<u8> {
+        let items =
+            laz::LazItemRecordBuilder::default_for_point_format_id(point_format, 0).unwrap();
+        let vlr = laz::LazVlr::from_laz_items(items);
+        let mut record = Vec::new();
+        vlr.write_to(&mut record).unwrap();
+
+        let mut data = las[..header_size].to_vec();
+        let mut vlr_header = [0; VLR_HEADER_SIZE];
+        vlr_header[2..16].copy_from_slice(laz::LazVlr::USER_ID.as_bytes());
+        LittleEndian::write_u16(&mut vlr_header[18..], laz::LazVlr::RECORD_ID);
+        LittleEndian::write_u16(&mut vlr_header[20..], record.len() as u16);
+        data.extend(&vlr_header);
+        data.extend(record);
+        let point_offset = data.len() as u32;
+        LittleEndian::write_u32(&mut data[96..], point_offset);
+        LittleEndian::write_u32(&mut data[100..], 1);
+        data[104] |= 0x80;
+
+        let mut dst = Cursor::new(data);
+        dst.set_position(dst.get_ref().len() as u64);
+        laz::compress_buffer(&mut dst, &las[header_size..], vlr).unwrap();
+        dst.into_inner()
+    }
+
+    #[test]
+    fn compressed_point_format() {
+        let mut rest = 12.5f64.to_le_bytes().to_vec();
+        rest.extend(&[1, 0, 2, 0, 3, 0]);
+        let points: Vec<_> = (0..100)
+            .map(|i| ([i, 2 * i, -i], i as u16, (i % 7) as u8, rest.clone()))
+            .collect();
+        let las = las_file(2, 3, &points);
+        let laz = laz_file(&las, 227, 3);
+        assert_ne!(laz[227..], las[227..]);
+        let expected = LasFile::parse(las.as_slice()).unwrap();
+        assert_eq!(LasFile::parse(laz.as_slice()).unwrap(), expected);
+        assert_eq!(expected.header.num_points, 100);
+
+        let las = las_file(4, 6, &[([0, 0, 10], 1, 9, Vec::new())]);
+        let laz = laz_file(&las, 375, 6);
+        assert_eq!(
+            LasFile::parse(laz.as_slice()).unwrap(),
+            LasFile::parse(las.as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |data: Vec<u8>| match LasFile::parse(data.as_slice()) {
+            Err(crate::Error::Las(e)) => e,
+            result => panic!("Unexpected result: {:?}", result),
+        };
+        let mut data = las_file(2, 0, &[([0, 0, 0], 0, 0, Vec::new())]);
+        assert_eq!(error(data[..data.len() - 1].to_vec()), Error::Truncated);
+        // Compressed points without a LASzip record.
+        data[104] |= 0x80;
+        assert_eq!(error(data.clone()), Error::Compressed);
+        data[0] = b'X';
+        assert_eq!(error(data), Error::NotLas);
+        assert_eq!(
+            error(las_file(2, 11, &[])),
+            Error::UnsupportedPointFormat(11)
+        );
+    }
+}
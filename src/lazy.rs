@@ -0,0 +1,917 @@
+//! Lazy, thread safe access to the data arrays of XML VTK files.
+//!
+//! Importing a file with [`Vtk::import`] decodes every data array up front. For large files it
+//! is often preferable to only read the arrays that are actually needed. A [`LazyReader`] parses
+//! the XML structure of a file once and decodes individual arrays on demand. Arrays stored in the
+//! `AppendedData` section are read directly from the underlying source without touching the rest
+//! of the file.
+//!
+//! A `LazyReader` is `Sync` whenever its source is `Send`. Access to the source is synchronized
+//! internally, so a single reader can be shared between threads, which may then load different
+//! arrays and pieces concurrently. The source is only locked while the raw bytes of an array are
+//! read; decoding and decompression happen in parallel.
+//!
+//! Interactive tools repeatedly accessing the same arrays can enable a cache of decoded arrays
+//! with [`LazyReader::with_cache`], which avoids decoding and decompressing arrays again.
+//!
+//! Time series described by `.pvd` collections can be accessed through a [`LazyTimeSeries`],
+//! which opens a cached reader for each time step on demand. Viewers playing back a time series
+//! can [prefetch](LazyTimeSeries::prefetch) upcoming time steps on a background thread.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::sync::Arc;
+//! use vtkio::lazy::LazyReader;
+//!
+//! let reader = Arc::new(LazyReader::open("assets/RectilinearGridRawBinary.vtr").expect("Failed to open file"));
+//! let handles: Vec<_> = reader
+//!     .cell_array_names(0)
+//!     .into_iter()
+//!     .map(|name| {
+//!         let reader = Arc::clone(&reader);
+//!         std::thread::spawn(move || reader.cell_array(0, &name))
+//!     })
+//!     .collect();
+//! for handle in handles {
+//!     let array = handle.join().unwrap().expect("Failed to load array");
+//!     assert!(array.is_some());
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::model::{self, IOBuffer, Vtk};
+use crate::pvd::Collection;
+use crate::xml::{self, AppendedData, AttributeInfo, DataArray, DataArrayFormat, Encoding};
+use crate::Error;
+
+/// The size of the chunks in which the XML structure of a file is read.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Location of the `AppendedData` section within the source.
+#[derive(Clone, Debug)]
+struct AppendedSection {
+    encoding: Encoding,
+    /// Position of the first byte following the leading underscore.
+    start: u64,
+    /// Sorted offsets of all arrays stored in this section.
+    offsets: Vec<u64>,
+}
+
+/// Identifies a data array within a file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ArrayId {
+    /// The points of the piece with the given index.
+    Points(usize),
+    /// The named point attribute of the piece with the given index.
+    PointData(usize, String),
+    /// The named cell attribute of the piece with the given index.
+    CellData(usize, String),
+}
+
+/// A decoded array stored in the cache.
+#[derive(Clone, Debug)]
+enum CachedArray {
+    Buffer(IOBuffer),
+    Attribute(model::DataArray),
+}
+
+impl CachedArray {
+    fn num_bytes(&self) -> usize {
+        match self {
+            CachedArray::Buffer(buf) => buf.num_bytes(),
+            CachedArray::Attribute(data_array) => data_array.data.num_bytes(),
+        }
+    }
+}
+
+/// A least recently used cache of decoded arrays.
+#[derive(Debug)]
+struct ArrayCache {
+    /// Maximum total size of all cached arrays in bytes.
+    capacity: usize,
+    /// Current total size of all cached arrays in bytes.
+    size: usize,
+    /// Counter used to determine the least recently used array.
+    tick: u64,
+    entries: HashMap<ArrayId, (CachedArray, u64)>,
+}
+
+impl ArrayCache {
+    fn new(capacity: usize) -> Self {
+        ArrayCache {
+            capacity,
+            size: 0,
+            tick: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, id: &ArrayId) -> Option<CachedArray> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(id).map(|(array, last_used)| {
+            *last_used = tick;
+            array.clone()
+        })
+    }
+
+    fn insert(&mut self, id: ArrayId, array: CachedArray) {
+        let num_bytes = array.num_bytes();
+        if num_bytes > self.capacity {
+            return;
+        }
+        self.tick += 1;
+        if let Some((old, _)) = self.entries.insert(id, (array, self.tick)) {
+            self.size -= old.num_bytes();
+        }
+        self.size += num_bytes;
+        while self.size > self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(id, _)| id.clone());
+            match lru.and_then(|id| self.entries.remove(&id)) {
+                Some((evicted, _)) => self.size -= evicted.num_bytes(),
+                None => break,
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
+}
+
+/// A reader decoding the data arrays of an XML VTK file on demand.
+///
+/// Pieces and arrays are identified by their index and name respectively. Only inline pieces are
+/// supported; parallel files referencing pieces in other files contain no pieces.
+#[derive(Debug)]
+pub struct LazyReader<R> {
+    /// The XML structure of the file without any appended data.
+    file: xml::VTKFile,
+    appended: Option<AppendedSection>,
+    source: Mutex<R>,
+    cache: Option<Mutex<ArrayCache>>,
+}
+
+impl LazyReader<BufReader<File>> {
+    /// Opens the XML VTK file at the given path.
+    pub fn open(file_path: impl AsRef<Path>) -> Result<Self, Error> {
+        LazyReader::new(BufReader::new(File::open(file_path)?))
+    }
+}
+
+impl<R: Read + Seek> LazyReader<R> {
+    /// Constructs a reader for the XML VTK file stored in the given source.
+    ///
+    /// Only the XML structure preceding the `AppendedData` section is read here, along with any
+    /// inline data arrays.
+    pub fn new(mut source: R) -> Result<Self, Error> {
+        source.seek(SeekFrom::Start(0))?;
+
+        // Read up to and including the underscore marking the start of the appended data.
+        let mut header = Vec::new();
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let mut tag_start = None;
+        let mut underscore = None;
+        loop {
+            let n = source.read(&mut chunk)?;
+            let searched = header.len().saturating_sub(b"<AppendedData".len());
+            header.extend_from_slice(&chunk[..n]);
+            if tag_start.is_none() {
+                tag_start = find(&header[searched..], b"<AppendedData").map(|i| i + searched);
+            }
+            if let Some(start) = tag_start {
+                underscore = header[start..]
+                    .iter()
+                    .position(|&b| b == b'_')
+                    .map(|i| i + start);
+                if underscore.is_some() {
+                    break;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+        }
+
+        let (file, appended) = match underscore {
+            Some(underscore) => {
+                header.truncate(underscore + 1);
+                header.extend_from_slice(b"</AppendedData></VTKFile>");
+                let mut file = xml::parse(header.as_slice())?;
+                let encoding = file
+                    .appended_data
+                    .take()
+                    .map_or(Encoding::Raw, |appended| appended.encoding);
+                let mut offsets: Vec<u64> = file
+                    .data_set
+                    .pieces()
+                    .iter()
+                    .flat_map(|piece| piece.data_arrays())
+                    .filter(|data_array| data_array.format == DataArrayFormat::Appended)
+                    .map(|data_array| data_array.offset.unwrap_or(0))
+                    .collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                let appended = AppendedSection {
+                    encoding,
+                    start: underscore as u64 + 1,
+                    offsets,
+                };
+                (file, Some(appended))
+            }
+            None => (xml::parse(header.as_slice())?, None),
+        };
+
+        Ok(LazyReader {
+            file,
+            appended,
+            source: Mutex::new(source),
+            cache: None,
+        })
+    }
+
+    /// Enables caching of decoded arrays using at most `capacity` bytes.
+    ///
+    /// Once the cache is full, the least recently used arrays are evicted. Arrays larger than
+    /// the capacity are never cached. Cached arrays are returned as copies, so they remain valid
+    /// after being evicted.
+    pub fn with_cache(self, capacity: usize) -> Self {
+        LazyReader {
+            cache: Some(Mutex::new(ArrayCache::new(capacity))),
+            ..self
+        }
+    }
+
+    /// Returns the total size of all cached arrays in bytes.
+    pub fn cached_bytes(&self) -> usize {
+        self.cache
+            .as_ref()
+            .map_or(0, |cache| cache.lock().unwrap().size)
+    }
+
+    /// Returns `true` if the given array is currently cached.
+    pub fn is_cached(&self, id: &ArrayId) -> bool {
+        self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.lock().unwrap().entries.contains_key(id))
+    }
+
+    /// Removes all arrays from the cache.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().clear();
+        }
+    }
+
+    /// Returns the type of data set stored in this file.
+    pub fn data_set_type(&self) -> xml::DataSetType {
+        self.file.data_set_type
+    }
+
+    /// Returns the number of inline pieces in this file.
+    pub fn num_pieces(&self) -> usize {
+        self.file.data_set.pieces().len()
+    }
+
+    /// Returns the number of points and cells in the given piece.
+    pub fn piece_size(&self, piece: usize) -> Option<(usize, usize)> {
+        self.file.piece_size(piece)
+    }
+
+    /// Returns the names of all point attributes in the given piece.
+    pub fn point_array_names(&self, piece: usize) -> Vec<String> {
+        self.file
+            .data_set
+            .pieces()
+            .get(piece)
+            .map(|piece| array_names(&piece.point_data))
+            .unwrap_or_default()
+    }
+
+    /// Returns the names of all cell attributes in the given piece.
+    pub fn cell_array_names(&self, piece: usize) -> Vec<String> {
+        self.file
+            .data_set
+            .pieces()
+            .get(piece)
+            .map(|piece| array_names(&piece.cell_data))
+            .unwrap_or_default()
+    }
+
+    /// Returns the identifiers of the points and all attributes stored in this file.
+    pub fn array_ids(&self) -> Vec<ArrayId> {
+        let mut ids = Vec::new();
+        for (i, piece) in self.file.data_set.pieces().iter().enumerate() {
+            if piece.points_array().is_some() {
+                ids.push(ArrayId::Points(i));
+            }
+            let point_data = array_names(&piece.point_data).into_iter();
+            ids.extend(point_data.map(|name| ArrayId::PointData(i, name)));
+            let cell_data = array_names(&piece.cell_data).into_iter();
+            ids.extend(cell_data.map(|name| ArrayId::CellData(i, name)));
+        }
+        ids
+    }
+
+    /// Decodes the given arrays into the cache ahead of time.
+    ///
+    /// Arrays that are already cached are skipped. This has no effect if caching is disabled.
+    pub fn preload(&self, ids: &[ArrayId]) -> Result<(), Error> {
+        if self.cache.is_none() {
+            return Ok(());
+        }
+        for id in ids.iter().filter(|id| !self.is_cached(id)) {
+            match id {
+                ArrayId::Points(piece) => {
+                    self.points(*piece)?;
+                }
+                ArrayId::PointData(piece, name) => {
+                    self.point_array(*piece, name)?;
+                }
+                ArrayId::CellData(piece, name) => {
+                    self.cell_array(*piece, name)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the point attribute with the given name from the given piece.
+    ///
+    /// Returns `None` if there is no such piece or attribute.
+    pub fn point_array(&self, piece: usize, name: &str) -> Result<Option<model::DataArray>, Error> {
+        let (num_points, _) = match self.piece_size(piece) {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let point_data = &self.file.data_set.pieces()[piece].point_data;
+        let id = ArrayId::PointData(piece, name.to_string());
+        self.attribute(id, point_data, name, num_points)
+    }
+
+    /// Loads the cell attribute with the given name from the given piece.
+    ///
+    /// Returns `None` if there is no such piece or attribute.
+    pub fn cell_array(&self, piece: usize, name: &str) -> Result<Option<model::DataArray>, Error> {
+        let (_, num_cells) = match self.piece_size(piece) {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let cell_data = &self.file.data_set.pieces()[piece].cell_data;
+        let id = ArrayId::CellData(piece, name.to_string());
+        self.attribute(id, cell_data, name, num_cells)
+    }
+
+    /// Loads the points of the given piece.
+    ///
+    /// Returns `None` if there is no such piece or the piece has no explicit points.
+    pub fn points(&self, piece: usize) -> Result<Option<IOBuffer>, Error> {
+        let (num_points, _) = match self.piece_size(piece) {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let data_array = match self.file.data_set.pieces()[piece].points_array() {
+            Some(data_array) => data_array,
+            None => return Ok(None),
+        };
+        let id = ArrayId::Points(piece);
+        if let Some(CachedArray::Buffer(buf)) = self.cache_get(&id) {
+            return Ok(Some(buf));
+        }
+        let (data_array, appended) = self.prepare(data_array)?;
+        let buf = data_array
+            .into_io_buffer(num_points, appended.as_ref(), self.file.encoding_info())
+            .map_err(xml::Error::from)?;
+        self.cache_insert(id, || CachedArray::Buffer(buf.clone()));
+        Ok(Some(buf))
+    }
+
+    /// Loads the entire data set.
+    ///
+    /// This reads the whole appended data section at once.
+    pub fn load(&self) -> Result<Vtk, Error> {
+        let mut file = self.file.clone();
+        if let Some(appended) = &self.appended {
+            let mut data = {
+                let mut source = self.source.lock().unwrap();
+                source.seek(SeekFrom::Start(appended.start))?;
+                let mut data = Vec::new();
+                source.read_to_end(&mut data)?;
+                data
+            };
+            // Searching from the end, since raw data may contain the closing tag.
+            if let Some(end) = data
+                .windows(b"</AppendedData>".len())
+                .rposition(|w| w == b"</AppendedData>")
+            {
+                data.truncate(end);
+            }
+            if appended.encoding == Encoding::Base64 {
+                while data.last().is_some_and(u8::is_ascii_whitespace) {
+                    data.pop();
+                }
+            }
+            file.appended_data = Some(AppendedData::new(appended.encoding, data));
+        }
+        Ok(Vtk::try_from(file)?)
+    }
+
+    /// Returns the XML structure of the file without any appended data.
+    pub(crate) fn xml_file(&self) -> &xml::VTKFile {
+        &self.file
+    }
+
+    /// Consumes this reader, returning the underlying source.
+    pub fn into_inner(self) -> R {
+        self.source
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn attribute(
+        &self,
+        id: ArrayId,
+        attribute_data: &xml::AttributeData,
+        name: &str,
+        n: usize,
+    ) -> Result<Option<model::DataArray>, Error> {
+        let data_array = match attribute_data.data_array.iter().find(|a| a.name == name) {
+            Some(data_array) => data_array,
+            None => return Ok(None),
+        };
+        if let Some(CachedArray::Attribute(data_array)) = self.cache_get(&id) {
+            return Ok(Some(data_array));
+        }
+        let info: AttributeInfo = attribute_data.info();
+        let (data_array, appended) = self.prepare(data_array)?;
+        let data_array = data_array
+            .into_model_data_array(n, appended.as_ref(), &info, self.file.encoding_info())
+            .map_err(xml::Error::from)?;
+        self.cache_insert(id, || CachedArray::Attribute(data_array.clone()));
+        Ok(Some(data_array))
+    }
+
+    fn cache_get(&self, id: &ArrayId) -> Option<CachedArray> {
+        self.cache.as_ref()?.lock().unwrap().get(id)
+    }
+
+    /// Inserts the array produced by `array` into the cache if caching is enabled.
+    fn cache_insert(&self, id: ArrayId, array: impl FnOnce() -> CachedArray) {
+        if let Some(cache) = &self.cache {
+            let array = array();
+            cache.lock().unwrap().insert(id, array);
+        }
+    }
+
+    /// Reads the appended data of the given array from the source, if any.
+    ///
+    /// Returns a copy of the array along with the appended data it refers to.
+    fn prepare(&self, data_array: &DataArray) -> Result<(DataArray, Option<AppendedData>), Error> {
+        let appended = match (&self.appended, data_array.format) {
+            (Some(appended), DataArrayFormat::Appended) => appended,
+            _ => return Ok((data_array.clone(), None)),
+        };
+        let offset = data_array.offset.unwrap_or(0);
+        let next = appended.offsets.iter().copied().find(|&next| next > offset);
+
+        let bytes = {
+            let mut source = self.source.lock().unwrap();
+            let start = appended.start + offset;
+            source.seek(SeekFrom::Start(start))?;
+            let mut bytes = Vec::new();
+            match next {
+                Some(next) => {
+                    bytes.resize((next - offset) as usize, 0);
+                    source.read_exact(&mut bytes)?;
+                }
+                None => {
+                    source.read_to_end(&mut bytes)?;
+                }
+            }
+            bytes
+        };
+
+        let mut data_array = data_array.clone();
+        data_array.offset = Some(0);
+        Ok((
+            data_array,
+            Some(AppendedData::new(appended.encoding, bytes)),
+        ))
+    }
+}
+
+/// The default capacity in bytes of the cache of each time step in a [`LazyTimeSeries`].
+pub const DEFAULT_TIME_STEP_CACHE_CAPACITY: usize = 256 * 1024 * 1024;
+
+/// A data set of a time series along with its reader, once opened.
+#[derive(Debug)]
+struct TimeStep {
+    timestep: f64,
+    file: PathBuf,
+    reader: Mutex<Option<Arc<LazyReader<BufReader<File>>>>>,
+}
+
+impl TimeStep {
+    /// Returns the reader of this time step, opening it if necessary.
+    fn reader(&self, cache_capacity: usize) -> Result<Arc<LazyReader<BufReader<File>>>, Error> {
+        let mut reader = self.reader.lock().unwrap();
+        if let Some(reader) = reader.as_ref() {
+            return Ok(Arc::clone(reader));
+        }
+        let opened = Arc::new(LazyReader::open(&self.file)?.with_cache(cache_capacity));
+        *reader = Some(Arc::clone(&opened));
+        Ok(opened)
+    }
+}
+
+/// Lazy readers for the data sets of a `.pvd` time series.
+///
+/// Data sets are indexed by their position in the collection file. Each data set is opened with a
+/// cached [`LazyReader`] the first time it is accessed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vtkio::lazy::LazyTimeSeries;
+///
+/// let series = LazyTimeSeries::open("output/sim.pvd").expect("Failed to open time series");
+/// let mut prefetch = series.prefetch(0..2);
+/// for step in 0..series.len() {
+///     // Wait for the previous prefetch to finish and start decoding the next time steps.
+///     prefetch.wait().expect("Failed to prefetch time step");
+///     prefetch = series.prefetch(step + 1..step + 3);
+///     let reader = series.reader(step).unwrap().expect("Failed to open time step");
+///     let pressure = reader.point_array(0, "pressure").expect("Failed to load array");
+///     // Display `pressure` here.
+///     series.release(step);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LazyTimeSeries {
+    steps: Arc<Vec<TimeStep>>,
+    cache_capacity: usize,
+}
+
+impl LazyTimeSeries {
+    /// Reads the given `.pvd` file without opening any of the referenced data sets.
+    pub fn open(file_path: impl AsRef<Path>) -> Result<Self, Error> {
+        let collection = Collection::import(file_path)?;
+        let steps = collection
+            .data_sets
+            .into_iter()
+            .map(|data_set| TimeStep {
+                timestep: data_set.timestep,
+                file: data_set.file,
+                reader: Mutex::new(None),
+            })
+            .collect();
+        Ok(LazyTimeSeries {
+            steps: Arc::new(steps),
+            cache_capacity: DEFAULT_TIME_STEP_CACHE_CAPACITY,
+        })
+    }
+
+    /// Sets the cache capacity in bytes of each time step opened from now on.
+    ///
+    /// The default capacity is given by [`DEFAULT_TIME_STEP_CACHE_CAPACITY`].
+    pub fn with_cache_capacity(self, cache_capacity: usize) -> Self {
+        LazyTimeSeries {
+            cache_capacity,
+            ..self
+        }
+    }
+
+    /// Returns the number of data sets in this time series.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns `true` if this time series contains no data sets.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns the time step value of the given data set.
+    pub fn timestep(&self, step: usize) -> Option<f64> {
+        self.steps.get(step).map(|step| step.timestep)
+    }
+
+    /// Returns the path to the file of the given data set.
+    pub fn file(&self, step: usize) -> Option<&Path> {
+        self.steps.get(step).map(|step| step.file.as_path())
+    }
+
+    /// Returns the reader of the given data set, opening it if necessary.
+    ///
+    /// Returns `None` if there is no such data set.
+    pub fn reader(&self, step: usize) -> Option<Result<Arc<LazyReader<BufReader<File>>>, Error>> {
+        self.steps
+            .get(step)
+            .map(|step| step.reader(self.cache_capacity))
+    }
+
+    /// Returns `true` if the reader of the given data set has been opened.
+    pub fn is_open(&self, step: usize) -> bool {
+        self.steps
+            .get(step)
+            .is_some_and(|step| step.reader.lock().unwrap().is_some())
+    }
+
+    /// Closes the reader of the given data set, releasing its cached arrays.
+    ///
+    /// Readers previously returned by [`reader`](LazyTimeSeries::reader) remain valid.
+    pub fn release(&self, step: usize) {
+        if let Some(step) = self.steps.get(step) {
+            step.reader.lock().unwrap().take();
+        }
+    }
+
+    /// Decodes all arrays of the given data sets on a background thread.
+    ///
+    /// The decoded arrays are stored in the cache of each data set's reader, so subsequent
+    /// accesses return immediately. Data sets are processed in the given order and nonexistent
+    /// ones are skipped. Arrays which don't fit into the cache are decoded but not retained.
+    pub fn prefetch(&self, steps: impl IntoIterator<Item = usize>) -> Prefetch {
+        let steps_to_load: Vec<usize> = steps.into_iter().collect();
+        let steps = Arc::clone(&self.steps);
+        let cache_capacity = self.cache_capacity;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+        let handle = std::thread::spawn(move || {
+            for step in steps_to_load.into_iter().filter_map(|i| steps.get(i)) {
+                let reader = step.reader(cache_capacity)?;
+                for id in reader.array_ids() {
+                    if thread_cancelled.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    reader.preload(&[id])?;
+                }
+            }
+            Ok(())
+        });
+        Prefetch { handle, cancelled }
+    }
+}
+
+/// A handle to a background prefetch started by [`LazyTimeSeries::prefetch`].
+///
+/// Dropping the handle lets the prefetch run to completion in the background.
+#[derive(Debug)]
+pub struct Prefetch {
+    handle: JoinHandle<Result<(), Error>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Prefetch {
+    /// Requests the prefetch to stop before decoding the next array.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the prefetch has finished.
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Waits for the prefetch to finish, returning the first error encountered, if any.
+    pub fn wait(self) -> Result<(), Error> {
+        self.handle.join().unwrap_or(Err(Error::Unknown))
+    }
+}
+
+fn array_names(attribute_data: &xml::AttributeData) -> Vec<String> {
+    attribute_data
+        .data_array
+        .iter()
+        .map(|data_array| data_array.name.clone())
+        .collect()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Attribute, DataSet, Piece};
+    use std::sync::Arc;
+
+    /// Loads the given file eagerly and returns its first inline piece's attributes.
+    fn eager_attributes(file: &str) -> model::Attributes {
+        fn attributes<P: Clone>(
+            pieces: &[Piece<P>],
+            data: impl Fn(&P) -> &model::Attributes,
+        ) -> model::Attributes {
+            match &pieces[0] {
+                Piece::Inline(piece) => data(piece).clone(),
+                _ => panic!("expected an inline piece"),
+            }
+        }
+        match Vtk::import(file).unwrap().data {
+            DataSet::UnstructuredGrid { pieces, .. } => attributes(&pieces, |p| &p.data),
+            DataSet::RectilinearGrid { pieces, .. } => attributes(&pieces, |p| &p.data),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn lazy_reader_is_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LazyReader<BufReader<File>>>();
+        assert_send_sync::<LazyReader<std::io::Cursor<Vec<u8>>>>();
+    }
+
+    #[test]
+    fn lazy_arrays_match_eager_import() -> Result<(), Error> {
+        for file in &[
+            "assets/hexahedron.vtu",
+            "assets/hexahedron_zlib.vtu",
+            "assets/hexahedron_inline_binary.vtu",
+            "assets/RectilinearGridAppendedBase64.vtr",
+            "assets/RectilinearGridRawBinary.vtr",
+            "assets/RectilinearGridCompressed.vtr",
+        ] {
+            let reader = LazyReader::open(file)?;
+            assert_eq!(reader.num_pieces(), 1);
+            let expected = eager_attributes(file);
+            let names = reader.point_array_names(0);
+            assert_eq!(names.len(), expected.point.len());
+            for (name, attrib) in names.iter().zip(expected.point.iter()) {
+                let array = reader.point_array(0, name)?.unwrap();
+                assert_eq!(&Attribute::DataArray(array), attrib, "{}", file);
+            }
+            let names = reader.cell_array_names(0);
+            assert_eq!(names.len(), expected.cell.len());
+            for (name, attrib) in names.iter().zip(expected.cell.iter()) {
+                let array = reader.cell_array(0, name)?.unwrap();
+                assert_eq!(&Attribute::DataArray(array), attrib, "{}", file);
+            }
+            assert!(reader.cell_array(0, "missing")?.is_none());
+            assert!(reader.points(1)?.is_none());
+
+            let mut vtk = Vtk::import(file)?;
+            vtk.file_path = None;
+            if let DataSet::UnstructuredGrid { pieces, .. } = &vtk.data {
+                let points = pieces[0].load_piece_data(None)?.points;
+                assert_eq!(reader.points(0)?, Some(points), "{}", file);
+            }
+            assert_eq!(reader.load()?, vtk, "{}", file);
+        }
+        Ok(())
+    }
+
+    /// A source counting the number of reads performed on it.
+    struct CountingReader {
+        inner: std::io::Cursor<Vec<u8>>,
+        reads: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn cached_arrays() -> Result<(), Error> {
+        use std::sync::atomic::Ordering;
+
+        let file = "assets/RectilinearGridCompressed.vtr";
+        let reads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = CountingReader {
+            inner: std::io::Cursor::new(std::fs::read(file)?),
+            reads: Arc::clone(&reads),
+        };
+        let expected = eager_attributes(file);
+        let reader = LazyReader::new(source)?;
+        let names = reader.cell_array_names(0);
+        let size = reader.cell_array(0, &names[0])?.unwrap().data.num_bytes();
+
+        // Without a cache every access reads from the source.
+        let count = reads.load(Ordering::SeqCst);
+        reader.cell_array(0, &names[0])?;
+        assert!(reads.load(Ordering::SeqCst) > count);
+        assert_eq!(reader.cached_bytes(), 0);
+
+        // Make room for exactly two arrays.
+        let reader = reader.with_cache(2 * size);
+        let first = ArrayId::CellData(0, names[0].clone());
+        let second = ArrayId::CellData(0, names[1].clone());
+        let third = ArrayId::CellData(0, names[2].clone());
+        reader.cell_array(0, &names[0])?;
+        assert!(reader.is_cached(&first));
+        assert_eq!(reader.cached_bytes(), size);
+        let count = reads.load(Ordering::SeqCst);
+        let array = reader.cell_array(0, &names[0])?.unwrap();
+        assert_eq!(reads.load(Ordering::SeqCst), count);
+        assert_eq!(Attribute::DataArray(array), expected.cell[0]);
+
+        // The least recently used array is evicted first.
+        reader.cell_array(0, &names[1])?;
+        reader.cell_array(0, &names[0])?;
+        reader.cell_array(0, &names[2])?;
+        assert!(reader.is_cached(&first));
+        assert!(!reader.is_cached(&second));
+        assert!(reader.is_cached(&third));
+        assert_eq!(reader.cached_bytes(), 2 * size);
+
+        reader.clear_cache();
+        assert!(!reader.is_cached(&first));
+        assert_eq!(reader.cached_bytes(), 0);
+
+        // Arrays larger than the capacity are never cached.
+        let reader = reader.with_cache(size - 1);
+        reader.cell_array(0, &names[0])?;
+        assert!(!reader.is_cached(&first));
+        Ok(())
+    }
+
+    #[test]
+    fn prefetch_time_steps() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join("vtkio_lazy_prefetch");
+        std::fs::create_dir_all(&dir)?;
+        let mut pvd = String::from("<VTKFile type=\"Collection\" version=\"0.1\">\n<Collection>\n");
+        for step in 0..3 {
+            let file = format!("step_{}.vtr", step);
+            std::fs::copy("assets/RectilinearGridCompressed.vtr", dir.join(&file))?;
+            pvd.push_str(&format!(
+                "<DataSet timestep=\"{}\" file=\"{}\"/>\n",
+                step, file
+            ));
+        }
+        pvd.push_str("</Collection>\n</VTKFile>\n");
+        std::fs::write(dir.join("series.pvd"), pvd)?;
+
+        let series = LazyTimeSeries::open(dir.join("series.pvd"))?;
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.timestep(2), Some(2.0));
+        series.prefetch(vec![1, 2, 5]).wait()?;
+        assert!(!series.is_open(0));
+        let expected = eager_attributes("assets/RectilinearGridCompressed.vtr");
+        for step in 1..3 {
+            let reader = series.reader(step).unwrap()?;
+            let ids = reader.array_ids();
+            assert!(!ids.is_empty());
+            assert!(ids.iter().all(|id| reader.is_cached(id)));
+            let names = reader.cell_array_names(0);
+            let array = reader.cell_array(0, &names[0])?.unwrap();
+            assert_eq!(Attribute::DataArray(array), expected.cell[0]);
+        }
+
+        // Cancelled prefetches stop early.
+        let prefetch = series.prefetch(0..1);
+        prefetch.cancel();
+        prefetch.wait()?;
+
+        series.release(1);
+        assert!(!series.is_open(1));
+        assert_eq!(series.reader(1).unwrap()?.cached_bytes(), 0);
+        assert!(series.reader(3).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_reads() -> Result<(), Error> {
+        let file = "assets/RectilinearGridRawBinary.vtr";
+        let reader = Arc::new(LazyReader::open(file)?);
+        let expected = eager_attributes(file);
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let reader = Arc::clone(&reader);
+                std::thread::spawn(move || {
+                    let names = reader.cell_array_names(0);
+                    let name = &names[i % names.len()];
+                    (i, reader.cell_array(0, name).map(|a| a.unwrap()))
+                })
+            })
+            .collect();
+        for handle in handles {
+            let (i, array) = handle.join().unwrap();
+            let expected = &expected.cell[i % expected.cell.len()];
+            assert_eq!(&Attribute::DataArray(array?), expected);
+        }
+        Ok(())
+    }
+}
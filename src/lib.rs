@@ -89,44 +89,669 @@
 //!
 //! assert_eq!(data.as_slice(), &[0.0]);
 //! ```
+//!
+//! # Thread safety
+//!
+//! All model types, including [`Vtk`] and [`IOBuffer`], as well as the error types are `Send` and
+//! `Sync`, so loaded files can be moved to or shared with worker threads. The
+//! [`AsciiWriter`](writer::AsciiWriter), [`BinaryWriter`](writer::BinaryWriter) and
+//! [`IoWriter`](writer::IoWriter) types are `Send` and `Sync` whenever the wrapped writer is. Readers in the `lazy` module synchronize
+//! access to their source internally and can be shared between threads behind an `Arc`.
 #[macro_use]
 extern crate nom;
 
+#[cfg(feature = "xml")]
+pub mod amr;
+pub mod anonymize;
 #[macro_use]
 pub mod basic;
+#[cfg(feature = "xml")]
+pub mod checkpoint;
+#[cfg(feature = "exodus")]
+pub mod exodus;
+pub mod format;
+#[cfg(feature = "gmsh")]
+pub mod gmsh;
+pub mod graph;
+pub mod index;
+#[cfg(feature = "las")]
+pub mod las;
 
+#[cfg(feature = "xml")]
+pub mod lazy;
+#[cfg(feature = "medical")]
+pub mod medical;
 #[macro_use]
 pub mod model;
+pub mod normalize;
+#[cfg(feature = "openfoam")]
+pub mod openfoam;
 pub mod parser;
+#[cfg(feature = "plot3d")]
+pub mod plot3d;
+pub mod point_cloud;
+#[cfg(feature = "xml")]
+pub mod pvd;
+pub mod render;
+pub mod scene;
+pub mod stack;
+pub mod stream;
+pub mod summary;
+#[cfg(feature = "tecplot")]
+pub mod tecplot;
+pub mod terrain;
+pub mod trailer;
+pub mod trajectories;
+pub mod vtkjs;
+#[cfg(feature = "xml")]
+pub mod vtm;
 pub mod writer;
 #[cfg(feature = "xml")]
 pub mod xml;
 
+use std::collections::BTreeSet;
 #[cfg(feature = "xml")]
 use std::convert::{TryFrom, TryInto};
 use std::fs::File;
+use std::io::{self, Read};
 #[cfg(feature = "xml")]
-use std::io::BufRead;
-use std::io::{self, BufWriter, Read, Write};
+use std::io::{BufRead, Write};
 use std::path::Path;
 
-use crate::writer::{AsciiWriter, BinaryWriter, WriteVtk};
+use crate::writer::{AsciiWriter, BinaryWriter, OutputFormat, WriteVtk, WriterConfig};
 
 pub use model::IOBuffer;
 
 /// The primary `vtkio` API is provided through the `Vtk` struct.
 pub use model::Vtk;
 
+/// Options controlling how strictly files are validated when they are read.
+///
+/// By default, inconsistent files are rejected with an error describing the problem. Tolerant
+/// options instead attempt to salvage as much data as possible.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::{ReadOptions, Vtk};
+///
+/// // DIMENSIONS don't match the number of POINTS.
+/// let vtk_ascii: &[u8] = b"
+/// ## vtk DataFile Version 2.0
+/// Mismatched grid
+/// ASCII
+/// DATASET STRUCTURED_GRID
+/// DIMENSIONS 2 2 2
+/// POINTS 4 float
+/// 0 0 0 1 0 0 0 1 0 1 1 0
+/// ";
+///
+/// assert!(Vtk::parse_legacy_be(vtk_ascii).is_err());
+/// let vtk = Vtk::parse_legacy_be_with_options(vtk_ascii, ReadOptions::tolerant())
+///     .expect("Failed to parse vtk file");
+/// assert_eq!(vtk.data.extent().unwrap().clone().into_dims(), [2, 2, 1]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ReadOptions {
+    tolerant: bool,
+    length_policy: LengthPolicy,
+    float_color_scalars: bool,
+    byte_order: Option<model::ByteOrder>,
+    skip_unknown_sections: bool,
+    strict_syntax: bool,
+    array_filter: Option<BTreeSet<String>>,
+    max_array_size: Option<u64>,
+    max_total_size: Option<u64>,
+}
+
+impl ReadOptions {
+    /// Options rejecting any inconsistencies in the input. This is the default.
+    pub fn strict() -> Self {
+        ReadOptions::default()
+    }
+
+    /// Options recovering from inconsistencies in the input where possible.
+    ///
+    /// Mismatched `DIMENSIONS` in legacy structured and rectilinear grids are recomputed from the
+    /// number of points given, and attribute arrays of the wrong length are handled according to
+    /// the [`LengthPolicy`] set with [`with_length_policy`](ReadOptions::with_length_policy).
+    pub fn tolerant() -> Self {
+        ReadOptions {
+            tolerant: true,
+            ..ReadOptions::default()
+        }
+    }
+
+    /// Sets how attribute arrays of the wrong length are handled in tolerant mode.
+    ///
+    /// Strict options always discard such arrays.
+    pub fn with_length_policy(self, length_policy: LengthPolicy) -> Self {
+        ReadOptions {
+            length_policy,
+            ..self
+        }
+    }
+
+    /// Sets whether `COLOR_SCALARS` data in binary legacy files is converted to floats.
+    ///
+    /// Legacy files store colors as floats in `[0, 1]` in ASCII format, but as unsigned chars in
+    /// binary format. By default color scalars are kept as stored. If enabled, binary colors are
+    /// divided by 255 so that they are represented the same way regardless of the file format.
+    /// `LOOKUP_TABLE` data is always converted to floats, since writers convert it back.
+    pub fn with_float_color_scalars(self, float_color_scalars: bool) -> Self {
+        ReadOptions {
+            float_color_scalars,
+            ..self
+        }
+    }
+
+    /// Sets the byte order of binary data in legacy files.
+    ///
+    /// By default the byte order is detected from the data, see
+    /// [`parse_legacy`](Vtk::parse_legacy). Files written by VTK are big endian.
+    pub fn with_byte_order(self, byte_order: model::ByteOrder) -> Self {
+        ReadOptions {
+            byte_order: Some(byte_order),
+            ..self
+        }
+    }
+
+    /// Sets whether unrecognized sections in legacy files are skipped instead of rejected.
+    ///
+    /// Some exporters write sections that aren't part of the file format or not supported by
+    /// `vtkio`, such as `GLOBAL_IDS` attributes. With this option, such sections are skipped up to
+    /// the next recognized keyword, and a [`Warning::SectionSkipped`] is reported for each, so
+    /// that the geometry and remaining attributes can still be read. Skipping is reliable in
+    /// ASCII files, but the extent of unknown binary data can only be guessed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::{ReadOptions, Vtk, Warning};
+    ///
+    /// let vtk_ascii: &[u8] = b"
+    /// ## vtk DataFile Version 2.0
+    /// Exotic exporter
+    /// ASCII
+    /// DATASET POLYDATA
+    /// POINTS 2 float
+    /// 0 0 0 1 0 0
+    /// POINT_DATA 2
+    /// GLOBAL_IDS ids vtkIdType
+    /// 7 8
+    /// SCALARS pressure float
+    /// LOOKUP_TABLE default
+    /// 1 2
+    /// ";
+    ///
+    /// let strict = ReadOptions::default().with_strict_syntax(true);
+    /// assert!(Vtk::parse_legacy_with_options(vtk_ascii, strict).is_err());
+    /// let options = ReadOptions::default().with_skip_unknown_sections(true);
+    /// let vtk = Vtk::parse_legacy_with_options(vtk_ascii, options).expect("Failed to parse file");
+    /// assert_eq!(vtk.data.num_pieces(), 1);
+    /// ```
+    pub fn with_skip_unknown_sections(self, skip_unknown_sections: bool) -> Self {
+        ReadOptions {
+            skip_unknown_sections,
+            ..self
+        }
+    }
+
+    /// Sets whether legacy files with content that fails to parse are rejected.
+    ///
+    /// The legacy parser stops at the first section it doesn't recognize, and by default the
+    /// rest of the file is ignored with a [`Warning::ContentIgnored`], so that files with notes
+    /// appended by some tools can still be read. With this option, such files are rejected with
+    /// an [`Error::ParseAt`] locating the offending input instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::{Error, ReadOptions, Vtk};
+    ///
+    /// let vtk_ascii: &[u8] = b"
+    /// ## vtk DataFile Version 2.0
+    /// Annotated
+    /// ASCII
+    /// DATASET POLYDATA
+    /// POINTS 2 float
+    /// 0 0 0 1 0 0
+    /// Written by exporter v1.2
+    /// ";
+    ///
+    /// assert!(Vtk::parse_legacy(vtk_ascii).is_ok());
+    /// let options = ReadOptions::default().with_strict_syntax(true);
+    /// match Vtk::parse_legacy_with_options(vtk_ascii, options) {
+    ///     Err(Error::ParseAt(e)) => assert_eq!(e.line, 8),
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    /// ```
+    pub fn with_strict_syntax(self, strict_syntax: bool) -> Self {
+        ReadOptions {
+            strict_syntax,
+            ..self
+        }
+    }
+
+    /// Sets the names of the point and cell data arrays to read.
+    ///
+    /// Arrays with other names are dropped. In XML files they are skipped without being decoded, which saves time and
+    /// memory when only a few of many large arrays are needed. Legacy files must still be parsed
+    /// in full to find where each array ends, so there the filter only reduces the memory held by
+    /// the result.
+    ///
+    /// The filter applies to the data stored in the file being read, and not to pieces stored in
+    /// other files that are loaded later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::{ReadOptions, Vtk};
+    ///
+    /// let vtk_ascii: &[u8] = b"
+    /// ## vtk DataFile Version 2.0
+    /// Two arrays
+    /// ASCII
+    /// DATASET POLYDATA
+    /// POINTS 2 float
+    /// 0 0 0 1 0 0
+    /// POINT_DATA 2
+    /// SCALARS pressure float
+    /// LOOKUP_TABLE default
+    /// 1 2
+    /// SCALARS temperature float
+    /// LOOKUP_TABLE default
+    /// 3 4
+    /// ";
+    ///
+    /// let options = ReadOptions::default().with_array_filter(["temperature"]);
+    /// let vtk = Vtk::parse_legacy_with_options(vtk_ascii, options).expect("Failed to parse file");
+    /// if let DataSet::PolyData { pieces, .. } = vtk.data {
+    ///     let piece = pieces[0].load_piece_data(None).expect("Failed to load piece");
+    ///     let names: Vec<_> = piece.data.point.iter().map(Attribute::name).collect();
+    ///     assert_eq!(names, ["temperature"]);
+    /// }
+    /// ```
+    pub fn with_array_filter<S: Into<String>>(self, names: impl IntoIterator<Item = S>) -> Self {
+        ReadOptions {
+            array_filter: Some(names.into_iter().map(Into::into).collect()),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of bytes allocated for any single array.
+    ///
+    /// Files declare the sizes of their arrays in headers, such as `POINTS 4000000000 float`,
+    /// which are otherwise trusted when allocating memory for the data. With a limit, arrays
+    /// declared larger than it are rejected with [`Error::LimitExceeded`] before anything is
+    /// allocated, which protects against malicious or truncated files from untrusted sources.
+    ///
+    /// Legacy files are then read with the [stream reader](stream::parse_legacy), which checks
+    /// each array as it is read. In XML files the points and attribute arrays of all pieces are
+    /// checked before any data is decoded. Only the file being read is checked, and not pieces
+    /// stored in other files that are loaded later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::{Error, ReadOptions, Vtk};
+    ///
+    /// let vtk_ascii: &[u8] = b"
+    /// ## vtk DataFile Version 2.0
+    /// Huge header
+    /// ASCII
+    /// DATASET POLYDATA
+    /// POINTS 4000000000 float
+    /// 0 0 0
+    /// ";
+    ///
+    /// let options = ReadOptions::default().with_max_array_size(1 << 30);
+    /// match Vtk::parse_legacy_with_options(vtk_ascii, options) {
+    ///     Err(Error::LimitExceeded(e)) => assert_eq!(e.size, 48_000_000_000),
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    /// ```
+    pub fn with_max_array_size(self, max_array_size: u64) -> Self {
+        ReadOptions {
+            max_array_size: Some(max_array_size),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of bytes allocated for all arrays of a file together.
+    ///
+    /// This is checked like the limit set with
+    /// [`with_max_array_size`](ReadOptions::with_max_array_size).
+    pub fn with_max_total_size(self, max_total_size: u64) -> Self {
+        ReadOptions {
+            max_total_size: Some(max_total_size),
+            ..self
+        }
+    }
+
+    /// Returns the maximum number of bytes allocated for any single array, if limited.
+    pub fn max_array_size(&self) -> Option<u64> {
+        self.max_array_size
+    }
+
+    /// Returns the maximum number of bytes allocated for all arrays together, if limited.
+    pub fn max_total_size(&self) -> Option<u64> {
+        self.max_total_size
+    }
+
+    /// Returns `true` if the sizes of arrays are limited.
+    pub(crate) fn has_allocation_limits(&self) -> bool {
+        self.max_array_size.is_some() || self.max_total_size.is_some()
+    }
+
+    /// Returns a budget tracking allocations against the limits of these options.
+    pub(crate) fn allocation_budget(&self) -> AllocationBudget {
+        AllocationBudget {
+            max_array_size: self.max_array_size,
+            remaining: self.max_total_size,
+        }
+    }
+
+    /// Returns the names of the data arrays to read, or `None` if all arrays are read.
+    pub fn array_filter(&self) -> Option<&BTreeSet<String>> {
+        self.array_filter.as_ref()
+    }
+
+    /// Returns `true` if legacy files with content that fails to parse are rejected.
+    pub fn strict_syntax(&self) -> bool {
+        self.strict_syntax
+    }
+
+    /// Returns `true` if unrecognized sections in legacy files are skipped.
+    pub fn skip_unknown_sections(&self) -> bool {
+        self.skip_unknown_sections
+    }
+
+    /// Returns the byte order of binary legacy files, or `None` if it is detected from the data.
+    pub fn byte_order(&self) -> Option<model::ByteOrder> {
+        self.byte_order
+    }
+
+    /// Returns `true` if color scalars are converted to floats when reading legacy files.
+    pub fn float_color_scalars(&self) -> bool {
+        self.float_color_scalars
+    }
+
+    /// Returns `true` if these options recover from inconsistencies in the input.
+    pub fn is_tolerant(&self) -> bool {
+        self.tolerant
+    }
+
+    /// Returns the policy applied to attribute arrays of the wrong length.
+    pub fn length_policy(&self) -> LengthPolicy {
+        if self.tolerant {
+            self.length_policy
+        } else {
+            LengthPolicy::Discard
+        }
+    }
+}
+
+/// Tracks the memory allocated for the arrays of a file against the limits in [`ReadOptions`].
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct AllocationBudget {
+    max_array_size: Option<u64>,
+    /// The number of bytes that may still be allocated, if limited.
+    remaining: Option<u64>,
+}
+
+impl AllocationBudget {
+    /// Records the allocation of an array of `n` elements of the given size in bytes.
+    pub(crate) fn allocate(&mut self, n: u64, elem_size: u64) -> Result<(), LimitExceeded> {
+        let size = n.saturating_mul(elem_size);
+        if let Some(limit) = self.max_array_size.filter(|&limit| size > limit) {
+            return Err(LimitExceeded { size, limit });
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.checked_sub(size).ok_or(LimitExceeded {
+                size,
+                limit: *remaining,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Error produced when a file declares an array larger than allowed by the [`ReadOptions`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LimitExceeded {
+    /// The size of the array in bytes.
+    pub size: u64,
+    /// The number of bytes that were still allowed to be allocated.
+    pub limit: u64,
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Array of {} bytes exceeds the allocation limit of {} bytes",
+            self.size, self.limit
+        )
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// How attribute arrays whose length doesn't match the number of points or cells are handled.
+///
+/// Some exporters produce arrays that are a few values short or long. Since the arrays in XML
+/// files know their own length, such arrays can be salvaged. In legacy files the length of each
+/// array is determined by the `POINT_DATA` and `CELL_DATA` sections, so mismatched arrays can't
+/// be recovered and are reported as parse errors instead.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LengthPolicy {
+    /// Discard mismatched arrays. This is the default.
+    #[default]
+    Discard,
+    /// Truncate arrays that are too long, and discard arrays that are too short.
+    Truncate,
+    /// Pad arrays that are too short by repeating their last tuple, and truncate arrays that are
+    /// too long.
+    RepeatLast,
+    /// Pad arrays that are too short with zeros, and truncate arrays that are too long.
+    Zero,
+}
+
+/// A recoverable inconsistency found while reading a file.
+///
+/// Warnings are reported by [`Vtk::import_with_warnings`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The `DIMENSIONS` of a legacy grid didn't match its points and were recomputed.
+    DimensionsRecomputed {
+        dims: [u32; 3],
+        recomputed: [u32; 3],
+    },
+    /// An attribute array had the wrong number of values.
+    ///
+    /// `action` is the policy that was applied, which is `Discard` if the array was dropped.
+    AttributeLengthMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+        action: LengthPolicy,
+    },
+    /// An unrecognized section of a legacy file starting at the given line was skipped.
+    SectionSkipped { keyword: String, line: usize },
+    /// Content of a legacy file from the given line on failed to parse and was ignored.
+    ContentIgnored { line: usize },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Warning::DimensionsRecomputed { dims, recomputed } => write!(
+                f,
+                "DIMENSIONS {} {} {} don't match the points given; using {} {} {} instead",
+                dims[0], dims[1], dims[2], recomputed[0], recomputed[1], recomputed[2]
+            ),
+            Warning::AttributeLengthMismatch {
+                name,
+                expected,
+                actual,
+                action,
+            } => {
+                write!(
+                    f,
+                    "Attribute {:?} has {} values, but {} were expected; ",
+                    name, actual, expected
+                )?;
+                match action {
+                    LengthPolicy::Discard => write!(f, "the attribute was discarded"),
+                    LengthPolicy::Truncate => write!(f, "the attribute was truncated"),
+                    LengthPolicy::RepeatLast if actual > expected => {
+                        write!(f, "the attribute was truncated")
+                    }
+                    LengthPolicy::RepeatLast => write!(f, "the last tuple was repeated"),
+                    LengthPolicy::Zero if actual > expected => {
+                        write!(f, "the attribute was truncated")
+                    }
+                    LengthPolicy::Zero => write!(f, "the attribute was padded with zeros"),
+                }
+            }
+            Warning::SectionSkipped { keyword, line } => {
+                write!(
+                    f,
+                    "Unknown section {} at line {} was skipped",
+                    keyword, line
+                )
+            }
+            Warning::ContentIgnored { line } => {
+                write!(
+                    f,
+                    "Content from line {} on failed to parse and was ignored",
+                    line
+                )
+            }
+        }
+    }
+}
+
+/// The format version and features of an imported file.
+///
+/// Reported by [`Vtk::import_with_report`], this allows applications to warn users when saving
+/// a file in a different format or version would lose some of its capabilities. For instance
+/// legacy files older than version 5.1 can't store 64-bit cell ids, and no legacy file can be
+/// compressed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FormatReport {
+    /// Whether the file is in the modern XML format rather than the legacy format.
+    pub xml: bool,
+    /// The version declared in the file.
+    pub version: model::Version,
+    /// The features encountered in the file.
+    pub features: BTreeSet<FormatFeature>,
+}
+
+impl FormatReport {
+    fn new(xml: bool, version: model::Version) -> Self {
+        FormatReport {
+            xml,
+            version,
+            features: BTreeSet::new(),
+        }
+    }
+
+    /// Returns `true` if the given feature was encountered in the file.
+    pub fn has(&self, feature: FormatFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    /// Records the features found in the cells of the given data set.
+    ///
+    /// Only inline pieces are inspected, so the cells of pieces stored in other files are not
+    /// reported.
+    fn add_cell_features(&mut self, data: &model::DataSet) {
+        if let model::DataSet::UnstructuredGrid { pieces, .. } = data {
+            let nonlinear = pieces.iter().any(|piece| match piece {
+                model::Piece::Inline(piece) => piece.cells.types.iter().any(|t| !t.is_linear()),
+                _ => false,
+            });
+            if nonlinear {
+                self.features.insert(FormatFeature::HigherOrderCells);
+            }
+        }
+    }
+}
+
+/// A file format feature that is not supported by all formats or versions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum FormatFeature {
+    /// Binary data is compressed. Only supported by XML files.
+    Compression,
+    /// Cell connectivity or offsets are stored as 64-bit integers. Supported by XML files and
+    /// legacy files since version 5.1.
+    Int64Ids,
+    /// Nonlinear cells such as quadratic, Lagrange or Bezier cells are used.
+    HigherOrderCells,
+}
+
+/// A syntax error in a legacy VTK file along with where it was found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    /// The kind of error reported by the parser.
+    pub kind: nom::ErrorKind<u32>,
+    /// The offset in bytes from the start of the file to the offending input.
+    pub offset: usize,
+    /// The line of the offending input, starting at 1.
+    pub line: usize,
+    /// The keyword of the section being parsed, such as `CELL_TYPES`.
+    ///
+    /// This is `None` for errors in the file header, and for errors that couldn't be attributed
+    /// to a section.
+    pub section: Option<String>,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{:?} in {}, line {}", self.kind, section, self.line)?,
+            None => write!(f, "{:?} at line {}", self.kind, self.line)?,
+        }
+        write!(f, " (byte {})", self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 /// Error type for Import/Export operations.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     IO(io::Error),
     Write(writer::Error),
     Parse(nom::ErrorKind<u32>),
+    ParseAt(ParseError),
     #[cfg(feature = "xml")]
     XML(xml::Error),
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+    #[cfg(feature = "gmsh")]
+    Gmsh(gmsh::Error),
+    #[cfg(feature = "exodus")]
+    Exodus(exodus::Error),
+    #[cfg(feature = "openfoam")]
+    OpenFoam(openfoam::Error),
+    #[cfg(feature = "tecplot")]
+    Tecplot(tecplot::Error),
+    #[cfg(feature = "plot3d")]
+    Plot3d(plot3d::Error),
+    #[cfg(feature = "las")]
+    Las(las::Error),
     UnknownFileExtension(Option<String>),
     Load(model::Error),
+    UnsupportedDataSet(&'static str),
+    LimitExceeded(LimitExceeded),
     Unknown,
 }
 
@@ -136,13 +761,32 @@ impl std::fmt::Display for Error {
             Error::IO(source) => write!(f, "IO error: {}", source),
             Error::Write(source) => write!(f, "Write error: {}", source),
             Error::Parse(source) => write!(f, "Parse error: {:?}", source),
+            Error::ParseAt(source) => write!(f, "Parse error: {}", source),
             #[cfg(feature = "xml")]
             Error::XML(source) => write!(f, "XML error: {}", source),
+            #[cfg(feature = "image")]
+            Error::Image(source) => write!(f, "Image error: {}", source),
+            #[cfg(feature = "gmsh")]
+            Error::Gmsh(source) => write!(f, "Gmsh error: {}", source),
+            #[cfg(feature = "exodus")]
+            Error::Exodus(source) => write!(f, "Exodus error: {}", source),
+            #[cfg(feature = "openfoam")]
+            Error::OpenFoam(source) => write!(f, "OpenFOAM error: {}", source),
+            #[cfg(feature = "tecplot")]
+            Error::Tecplot(source) => write!(f, "Tecplot error: {}", source),
+            #[cfg(feature = "plot3d")]
+            Error::Plot3d(source) => write!(f, "Plot3D error: {}", source),
+            #[cfg(feature = "las")]
+            Error::Las(source) => write!(f, "LAS error: {}", source),
             Error::UnknownFileExtension(Some(ext)) => {
                 write!(f, "Unknown file extension: {:?}", ext)
             }
             Error::UnknownFileExtension(None) => write!(f, "Missing file extension"),
             Error::Load(source) => write!(f, "Load error: {}", source),
+            Error::UnsupportedDataSet(data_set) => {
+                write!(f, "Unsupported data set type: {}", data_set)
+            }
+            Error::LimitExceeded(source) => write!(f, "Limit exceeded: {}", source),
             Error::Unknown => write!(f, "Unknown error"),
         }
     }
@@ -154,10 +798,27 @@ impl std::error::Error for Error {
             Error::IO(source) => Some(source),
             Error::Write(source) => Some(source),
             Error::Parse(_) => None,
+            Error::ParseAt(source) => Some(source),
             #[cfg(feature = "xml")]
             Error::XML(source) => Some(source),
+            #[cfg(feature = "image")]
+            Error::Image(source) => Some(source),
+            #[cfg(feature = "gmsh")]
+            Error::Gmsh(source) => Some(source),
+            #[cfg(feature = "exodus")]
+            Error::Exodus(source) => Some(source),
+            #[cfg(feature = "openfoam")]
+            Error::OpenFoam(source) => Some(source),
+            #[cfg(feature = "tecplot")]
+            Error::Tecplot(source) => Some(source),
+            #[cfg(feature = "plot3d")]
+            Error::Plot3d(source) => Some(source),
+            #[cfg(feature = "las")]
+            Error::Las(source) => Some(source),
             Error::UnknownFileExtension(_) => None,
             Error::Load(source) => Some(source),
+            Error::UnsupportedDataSet(_) => None,
+            Error::LimitExceeded(source) => Some(source),
             Error::Unknown => None,
         }
     }
@@ -177,13 +838,82 @@ impl From<model::Error> for Error {
     }
 }
 
+/// Convert a [`LimitExceeded`] error into the top level `vtkio` error.
+impl From<LimitExceeded> for Error {
+    fn from(e: LimitExceeded) -> Error {
+        Error::LimitExceeded(e)
+    }
+}
+
 /// Convert [`xml::Error`] error into the top level `vtkio` error.
 ///
 /// [`xml::Error`]: xml.enum.Error.html
 #[cfg(feature = "xml")]
 impl From<xml::Error> for Error {
     fn from(e: xml::Error) -> Error {
-        Error::XML(e)
+        match e {
+            // Limits are reported the same way for all formats.
+            xml::Error::Validation(xml::ValidationError::LimitExceeded(e)) => {
+                Error::LimitExceeded(e)
+            }
+            e => Error::XML(e),
+        }
+    }
+}
+
+/// Convert an [`image::ImageError`] into the top level `vtkio` error.
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Error {
+        Error::Image(e)
+    }
+}
+
+/// Convert a [`gmsh::Error`] into the top level `vtkio` error.
+#[cfg(feature = "gmsh")]
+impl From<gmsh::Error> for Error {
+    fn from(e: gmsh::Error) -> Error {
+        Error::Gmsh(e)
+    }
+}
+
+/// Convert an [`exodus::Error`] into the top level `vtkio` error.
+#[cfg(feature = "exodus")]
+impl From<exodus::Error> for Error {
+    fn from(e: exodus::Error) -> Error {
+        Error::Exodus(e)
+    }
+}
+
+/// Convert an [`openfoam::Error`] into the top level `vtkio` error.
+#[cfg(feature = "openfoam")]
+impl From<openfoam::Error> for Error {
+    fn from(e: openfoam::Error) -> Error {
+        Error::OpenFoam(e)
+    }
+}
+
+/// Convert a [`tecplot::Error`] into the top level `vtkio` error.
+#[cfg(feature = "tecplot")]
+impl From<tecplot::Error> for Error {
+    fn from(e: tecplot::Error) -> Error {
+        Error::Tecplot(e)
+    }
+}
+
+/// Convert a [`plot3d::Error`] into the top level `vtkio` error.
+#[cfg(feature = "plot3d")]
+impl From<plot3d::Error> for Error {
+    fn from(e: plot3d::Error) -> Error {
+        Error::Plot3d(e)
+    }
+}
+
+/// Convert a [`las::Error`] into the top level `vtkio` error.
+#[cfg(feature = "las")]
+impl From<las::Error> for Error {
+    fn from(e: las::Error) -> Error {
+        Error::Las(e)
     }
 }
 
@@ -203,28 +933,202 @@ impl From<writer::Error> for Error {
     }
 }
 
+/// The flavor of a VTK file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FileFormat {
+    Legacy,
+    Xml,
+}
+
+/// Determines the flavor of the file at the given path from its first bytes.
+///
+/// Returns `None` if the file starts like neither a legacy nor an XML VTK file.
+fn detect_format(path: &Path) -> Result<Option<FileFormat>, Error> {
+    let mut head = Vec::new();
+    File::open(path)?.take(1024).read_to_end(&mut head)?;
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&head);
+    let start = head
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(head.len());
+    let head = &head[start..];
+    let starts_with = |prefix: &[u8]| {
+        head.get(..prefix.len())
+            .is_some_and(|h| h.eq_ignore_ascii_case(prefix))
+    };
+    Ok(if starts_with(b"# vtk DataFile") {
+        Some(FileFormat::Legacy)
+    } else if head.starts_with(b"<") && head.windows(8).any(|w| w == b"<VTKFile") {
+        Some(FileFormat::Xml)
+    } else {
+        None
+    })
+}
+
 impl Vtk {
-    /// Helper for parsing legacy VTK files.
-    fn parse_vtk<F>(mut reader: impl Read, parse: F, buf: &mut Vec<u8>) -> Result<Vtk, Error>
-    where
-        F: Fn(&[u8]) -> nom::IResult<&[u8], Vtk>,
-    {
-        use nom::IResult;
+    /// Helper for parsing legacy VTK files with binary data in the given byte order.
+    fn parse_vtk(
+        mut reader: impl Read,
+        byte_order: model::ByteOrder,
+        buf: &mut Vec<u8>,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vtk, Error> {
         reader.read_to_end(buf)?;
-        match parse(buf) {
-            IResult::Done(_, vtk) => Ok(vtk),
-            IResult::Error(e) => Err(Error::Parse(e.into_error_kind())),
-            IResult::Incomplete(_) => Err(Error::Unknown),
+        let (vtk, _) = Vtk::parse_vtk_buf(buf, byte_order, options, warnings, None)?;
+        Ok(vtk)
+    }
+
+    /// Helper for parsing legacy VTK files whose byte order is detected unless given in the
+    /// options.
+    ///
+    /// Also returns whether the cells are stored with 64-bit ids.
+    fn parse_vtk_detect(
+        mut reader: impl Read,
+        buf: &mut Vec<u8>,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(Vtk, bool), Error> {
+        reader.read_to_end(buf)?;
+        let byte_order = options
+            .byte_order
+            .unwrap_or_else(|| parser::detect_byte_order(buf));
+        Vtk::parse_vtk_buf(buf, byte_order, options, warnings, None)
+    }
+
+    /// Parses a legacy VTK file like [`parse_legacy_with_options`](Vtk::parse_legacy_with_options),
+    /// returning any content following the data set instead of ignoring it.
+    ///
+    /// The content is returned even with `strict_syntax`, which would otherwise reject it.
+    pub(crate) fn parse_legacy_with_trailer(
+        mut reader: impl Read,
+        options: ReadOptions,
+    ) -> Result<(Vtk, Vec<u8>), Error> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let byte_order = options
+            .byte_order
+            .unwrap_or_else(|| parser::detect_byte_order(&buf));
+        let mut trailer = Vec::new();
+        let (vtk, _) = Vtk::parse_vtk_buf(
+            &buf,
+            byte_order,
+            options,
+            &mut Vec::new(),
+            Some(&mut trailer),
+        )?;
+        Ok((vtk, trailer))
+    }
+
+    /// Parses the legacy VTK file stored in the given buffer.
+    ///
+    /// Content following the data set is stored in `trailer` if given. Otherwise it is ignored
+    /// with a [`Warning::ContentIgnored`], or rejected with `strict_syntax`.
+    ///
+    /// Also returns whether any cell offsets or connectivity are stored as 64-bit integers.
+    fn parse_vtk_buf(
+        buf: &[u8],
+        byte_order: model::ByteOrder,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+        trailer: Option<&mut Vec<u8>>,
+    ) -> Result<(Vtk, bool), Error> {
+        use nom::IResult;
+        // Unknown sections are skipped by the stream reader, which matches sections by keyword.
+        let parse_stream = |warnings: &mut Vec<Warning>| {
+            stream::parse_sections(buf, byte_order, &options, warnings)
+        };
+        // The slice parsers allocate arrays of the declared sizes up front, so files are read by
+        // the stream reader instead if allocations are limited, which checks each size first.
+        let (mut vtk, int64_ids) = if options.has_allocation_limits() && trailer.is_none() {
+            parse_stream(warnings)?
+        } else {
+            match parser::parse_with_id_width(buf, byte_order) {
+                IResult::Done(rest, parsed) => {
+                    let start = rest.iter().position(|b| !b.is_ascii_whitespace());
+                    match (start, trailer) {
+                        (None, _) => parsed,
+                        (Some(start), Some(trailer)) => {
+                            trailer.extend_from_slice(&rest[start..]);
+                            parsed
+                        }
+                        (Some(_), None) if options.skip_unknown_sections => parse_stream(warnings)?,
+                        (Some(_), None) if options.strict_syntax => {
+                            let e = nom::Err::Position(nom::ErrorKind::Eof, rest);
+                            return Err(stream::locate_error(buf, byte_order, e));
+                        }
+                        // Sections that fail to parse are otherwise skipped along with the rest
+                        // of the file.
+                        (Some(start), None) => {
+                            let offset = buf.len() - rest.len() + start;
+                            let line = 1 + buf[..offset].iter().filter(|&&b| b == b'\n').count();
+                            warnings.push(Warning::ContentIgnored { line });
+                            parsed
+                        }
+                    }
+                }
+                IResult::Error(_) if options.skip_unknown_sections => parse_stream(warnings)?,
+                IResult::Error(e) => return Err(stream::locate_error(buf, byte_order, e)),
+                IResult::Incomplete(_) => return Err(Error::Unknown),
+            }
+        };
+        vtk.check_dimensions(options.tolerant, warnings)?;
+        if options.float_color_scalars {
+            vtk.normalize_color_scalars();
         }
+        if let Some(names) = options.array_filter() {
+            vtk.retain_arrays(&|name| names.contains(name));
+        }
+        Ok((vtk, int64_ids))
     }
 
     /// Helper for importing legacy VTK files from the given path.
-    fn import_vtk<F>(file_path: &Path, parse: F) -> Result<Vtk, Error>
-    where
-        F: Fn(&[u8]) -> nom::IResult<&[u8], Vtk>,
-    {
+    fn import_vtk(
+        file_path: &Path,
+        byte_order: model::ByteOrder,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vtk, Error> {
         let file = File::open(file_path)?;
-        Vtk::parse_vtk(file, parse, &mut Vec::new())
+        Vtk::parse_vtk(file, byte_order, &mut Vec::new(), options, warnings)
+    }
+
+    /// Parse a legacy VTK file from the given reader, detecting the byte order of binary data.
+    ///
+    /// The byte order is guessed from the first binary arrays in the file: integers such as cell
+    /// sizes are small and coordinates are of moderate magnitude when decoded in the right byte
+    /// order, but rarely in the other. Big endian is assumed for ASCII files and if the data
+    /// doesn't decide, since it is the byte order written by VTK. Use
+    /// [`ReadOptions::with_byte_order`] to skip detection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// let mut file = b"# vtk DataFile Version 2.0\nLine\nBINARY\nDATASET POLYDATA\n".to_vec();
+    /// file.extend_from_slice(b"POINTS 2 float\n");
+    /// for x in &[0.5f32, 1.0, 1.5, 2.0, 2.5, 3.0] {
+    ///     file.extend_from_slice(&x.to_le_bytes());
+    /// }
+    ///
+    /// let vtk = Vtk::parse_legacy(file.as_slice()).expect("Failed to parse vtk file");
+    /// assert_eq!(vtk.byte_order, ByteOrder::LittleEndian);
+    /// ```
+    pub fn parse_legacy(reader: impl Read) -> Result<Vtk, Error> {
+        Vtk::parse_legacy_with_options(reader, ReadOptions::default())
+    }
+
+    /// Parse a legacy VTK file from the given reader with the given options.
+    ///
+    /// This is the same as [`parse_legacy`](Vtk::parse_legacy), but allows forcing the byte order
+    /// and recovering from inconsistencies in the file with [`ReadOptions::tolerant`].
+    pub fn parse_legacy_with_options(
+        reader: impl Read,
+        options: ReadOptions,
+    ) -> Result<Vtk, Error> {
+        let (vtk, _) = Vtk::parse_vtk_detect(reader, &mut Vec::new(), options, &mut Vec::new())?;
+        Ok(vtk)
     }
 
     /// Parse a legacy VTK file from the given reader.
@@ -234,6 +1138,10 @@ impl Vtk {
     /// Note that this function and [`parse_legacy_le`](Vtk::parse_legacy_le) also work equally well for
     /// parsing VTK files in ASCII format.
     ///
+    /// The entire file is read into memory before it is parsed. Use
+    /// [`stream::parse_legacy`](crate::stream::parse_legacy) to read large files section by
+    /// section instead.
+    ///
     /// # Examples
     ///
     /// Parsing an ASCII file:
@@ -273,7 +1181,13 @@ impl Vtk {
     /// });
     /// ```
     pub fn parse_legacy_be(reader: impl Read) -> Result<Vtk, Error> {
-        Vtk::parse_vtk(reader, parser::parse_be, &mut Vec::new())
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::BigEndian,
+            &mut Vec::new(),
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
     }
 
     /// Parse a legacy VTK file from the given reader.
@@ -321,7 +1235,13 @@ impl Vtk {
     /// });
     /// ```
     pub fn parse_legacy_le(reader: impl Read) -> Result<Vtk, Error> {
-        Vtk::parse_vtk(reader, parser::parse_le, &mut Vec::new())
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::LittleEndian,
+            &mut Vec::new(),
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
     }
 
     /// Parse a legacy VTK file in big endian format from the given reader and a buffer.
@@ -329,7 +1249,13 @@ impl Vtk {
     /// This is the buffered version of [`Vtk::parse_legacy_be`](Vtk::parse_legacy_be), which allows one to reuse the same
     /// heap allocated space when reading many files.
     pub fn parse_legacy_buf_be(reader: impl Read, buf: &mut Vec<u8>) -> Result<Vtk, Error> {
-        Vtk::parse_vtk(reader, parser::parse_be, buf)
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::BigEndian,
+            buf,
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
     }
 
     /// Parse a legacy VTK file in little endian format from the given reader and a buffer.
@@ -337,7 +1263,47 @@ impl Vtk {
     /// This is the buffered version of [`parse_legacy_le`](Vtk::parse_legacy_le), which allows one to reuse the same
     /// heap allocated space when reading many files.
     pub fn parse_legacy_buf_le(reader: impl Read, buf: &mut Vec<u8>) -> Result<Vtk, Error> {
-        Vtk::parse_vtk(reader, parser::parse_le, buf)
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::LittleEndian,
+            buf,
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
+    }
+
+    /// Parse a legacy VTK file in big endian format from the given reader with the given options.
+    ///
+    /// This is the same as [`parse_legacy_be`](Vtk::parse_legacy_be), but allows recovering from
+    /// inconsistencies in the file with [`ReadOptions::tolerant`].
+    pub fn parse_legacy_be_with_options(
+        reader: impl Read,
+        options: ReadOptions,
+    ) -> Result<Vtk, Error> {
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::BigEndian,
+            &mut Vec::new(),
+            options,
+            &mut Vec::new(),
+        )
+    }
+
+    /// Parse a legacy VTK file in little endian format from the given reader with the given options.
+    ///
+    /// This is the same as [`parse_legacy_le`](Vtk::parse_legacy_le), but allows recovering from
+    /// inconsistencies in the file with [`ReadOptions::tolerant`].
+    pub fn parse_legacy_le_with_options(
+        reader: impl Read,
+        options: ReadOptions,
+    ) -> Result<Vtk, Error> {
+        Vtk::parse_vtk(
+            reader,
+            model::ByteOrder::LittleEndian,
+            &mut Vec::new(),
+            options,
+            &mut Vec::new(),
+        )
     }
 
     /// Parse a modern XML style VTK file from a given reader.
@@ -358,12 +1324,12 @@ impl Vtk {
     ///       <CellData/>\
     ///       <Points>\
     ///         <DataArray type=\"Float32\" format=\"binary\" NumberOfComponents=\"3\">\
-    ///           AAAAAAAAAAQAAAAAAAAAAAAAAAA/gAAAAAAAAAAAAAAAAAAAAAAAAL+AAAA=\
+    ///           AAAAAAAAACQAAAAAAAAAAAAAAAA/gAAAAAAAAAAAAAAAAAAAAAAAAL+AAAA=\
     ///         </DataArray>\
     ///       </Points>\
     ///       <Polys>\
     ///         <DataArray type=\"UInt64\" Name=\"connectivity\" format=\"binary\" NumberOfComponents=\"1\">\
-    ///           AAAAAAAAAAgAAAAAAAAAAAAAAAAAAAABAAAAAAAAAAI=\
+    ///           AAAAAAAAABgAAAAAAAAAAAAAAAAAAAABAAAAAAAAAAI=\
     ///         </DataArray>\
     ///         <DataArray type=\"UInt64\" Name=\"offsets\" format=\"binary\" NumberOfComponents=\"1\">\
     ///           AAAAAAAAAAgAAAAAAAAAAw==\
@@ -400,6 +1366,19 @@ impl Vtk {
         Ok(vtk_file.try_into()?)
     }
 
+    /// Parse a modern XML style VTK file from a given reader with the given options.
+    ///
+    /// This is the same as [`parse_xml`](Vtk::parse_xml), but allows recovering attribute arrays
+    /// of the wrong length with [`ReadOptions::tolerant`].
+    #[cfg(feature = "xml")]
+    pub fn parse_xml_with_options(
+        reader: impl BufRead,
+        options: ReadOptions,
+    ) -> Result<Vtk, Error> {
+        let vtk_file = xml::parse(reader)?;
+        Ok(vtk_file.into_model_with_options(options, &mut Vec::new())?)
+    }
+
     #[cfg(feature = "async_blocked")]
     async fn import_vtk_async<F>(file_path: &Path, parse: F) -> Result<Vtk, Error>
     where
@@ -412,11 +1391,13 @@ impl Vtk {
         let mut file = File::open(file_path).await?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf).await?;
-        match parse(&buf) {
-            IResult::Done(_, vtk) => Ok(vtk),
-            IResult::Error(e) => Err(Error::Parse(e.into_error_kind())),
-            IResult::Incomplete(_) => Err(Error::Unknown),
-        }
+        let mut vtk = match parse(&buf) {
+            IResult::Done(_, vtk) => vtk,
+            IResult::Error(e) => return Err(Error::Parse(e.into_error_kind())),
+            IResult::Incomplete(_) => return Err(Error::Unknown),
+        };
+        vtk.check_dimensions(false, &mut Vec::new())?;
+        Ok(vtk)
     }
 
     /// Import a VTK file at the specified path.
@@ -436,6 +1417,13 @@ impl Vtk {
     ///  - PStructuredGrid (`.pvts`) -- Parallel vtkStructuredGrid (structured)
     ///  - PUnstructuredGrid (`.pvtu`) -- Parallel vtkUnstructuredGrid (unstructured)
     ///
+    /// The byte order of binary legacy files is detected as in [`parse_legacy`](Vtk::parse_legacy).
+    ///
+    /// Files without one of these extensions are recognized by their first line, which is
+    /// `# vtk DataFile Version` for legacy files and the `<VTKFile` element (optionally preceded
+    /// by an XML declaration) for XML files. The contents of `.vtk` files are checked as well,
+    /// since some exporters use this extension for XML files.
+    ///
     /// # Examples
     ///
     /// The following example imports a legacy `.vtk` file called `tet.vtk`, and panics with an
@@ -451,36 +1439,179 @@ impl Vtk {
     ///     .expect(&format!("Failed to load file: {:?}", file_path));
     /// ```
     pub fn import(file_path: impl AsRef<Path>) -> Result<Vtk, Error> {
-        Vtk::import_impl(file_path.as_ref())
+        Vtk::import_impl(
+            file_path.as_ref(),
+            ReadOptions::default(),
+            &mut Vec::new(),
+            None,
+        )
+    }
+
+    /// Import a VTK file at the specified path with the given options.
+    ///
+    /// This is the same as [`import`](Vtk::import), but allows recovering from inconsistencies in
+    /// the file with [`ReadOptions::tolerant`].
+    pub fn import_with_options(
+        file_path: impl AsRef<Path>,
+        options: ReadOptions,
+    ) -> Result<Vtk, Error> {
+        Vtk::import_impl(file_path.as_ref(), options, &mut Vec::new(), None)
+    }
+
+    /// Import a VTK file at the specified path with the given options, and report any
+    /// inconsistencies found in the file.
+    ///
+    /// This is the same as [`import_with_options`](Vtk::import_with_options), but also returns
+    /// the [`Warning`]s collected while reading, such as attribute arrays that were padded,
+    /// truncated or discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use vtkio::{LengthPolicy, ReadOptions, Vtk};
+    ///
+    /// let options = ReadOptions::tolerant().with_length_policy(LengthPolicy::RepeatLast);
+    /// let (vtk, warnings) = Vtk::import_with_warnings("broken.vtu", options)
+    ///     .expect("Failed to load file");
+    /// for warning in warnings {
+    ///     eprintln!("Warning: {}", warning);
+    /// }
+    /// ```
+    pub fn import_with_warnings(
+        file_path: impl AsRef<Path>,
+        options: ReadOptions,
+    ) -> Result<(Vtk, Vec<Warning>), Error> {
+        let mut warnings = Vec::new();
+        let vtk = Vtk::import_impl(file_path.as_ref(), options, &mut warnings, None)?;
+        Ok((vtk, warnings))
+    }
+
+    /// Import a VTK file at the specified path, and report the format version and features
+    /// used by the file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use vtkio::{FormatFeature, Vtk};
+    ///
+    /// let (vtk, report) = Vtk::import_with_report("mesh.vtu").expect("Failed to load file");
+    /// if report.has(FormatFeature::HigherOrderCells) {
+    ///     eprintln!("Warning: higher order cells may not be supported by older readers");
+    /// }
+    /// ```
+    pub fn import_with_report(file_path: impl AsRef<Path>) -> Result<(Vtk, FormatReport), Error> {
+        let mut report = FormatReport::new(false, model::Version::new((0, 0)));
+        let vtk = Vtk::import_impl(
+            file_path.as_ref(),
+            ReadOptions::default(),
+            &mut Vec::new(),
+            Some(&mut report),
+        )?;
+        Ok((vtk, report))
     }
 
     /// A non-generic helper for the `import` function.
-    fn import_impl(path: &Path) -> Result<Vtk, Error> {
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .ok_or(Error::UnknownFileExtension(None))?;
-        match ext {
-            "vtk" => Vtk::import_vtk(path, parser::parse_be),
+    ///
+    /// The format report is only filled in if requested.
+    fn import_impl(
+        path: &Path,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+        report: Option<&mut FormatReport>,
+    ) -> Result<Vtk, Error> {
+        let ext = path.extension().and_then(|s| s.to_str());
+        if let Some(deserializer) = ext.and_then(format::deserializer) {
+            let mut reader = std::io::BufReader::new(File::open(path)?);
+            let mut vtk = deserializer.deserialize(&mut reader)?;
+            vtk.file_path.get_or_insert_with(|| path.into());
+            if let Some(report) = report {
+                *report = FormatReport::new(false, vtk.version);
+                report.add_cell_features(&vtk.data);
+            }
+            return Ok(vtk);
+        }
+        let format = match ext {
+            // Some exporters write XML files with a `.vtk` extension.
+            Some("vtk") => detect_format(path)?.unwrap_or(FileFormat::Legacy),
             #[cfg(feature = "xml")]
-            ext => {
-                let ft = xml::FileType::try_from_ext(ext)
-                    .ok_or(Error::UnknownFileExtension(Some(ext.to_string())))?;
+            Some(ext) if xml::FileType::try_from_ext(ext).is_some() => FileFormat::Xml,
+            _ => detect_format(path)?
+                .ok_or_else(|| Error::UnknownFileExtension(ext.map(String::from)))?,
+        };
+        match format {
+            FileFormat::Legacy => {
+                let file = File::open(path)?;
+                let (vtk, int64_ids) =
+                    Vtk::parse_vtk_detect(file, &mut Vec::new(), options, warnings)?;
+                if let Some(report) = report {
+                    *report = FormatReport::new(false, vtk.version);
+                    if int64_ids {
+                        report.features.insert(FormatFeature::Int64Ids);
+                    }
+                    report.add_cell_features(&vtk.data);
+                }
+                Ok(vtk)
+            }
+            #[cfg(feature = "xml")]
+            FileFormat::Xml => {
                 let vtk_file = xml::import(path)?;
-                let exp_ft = xml::FileType::from(vtk_file.data_set_type);
-                if ft != exp_ft {
-                    Err(Error::XML(xml::Error::TypeExtensionMismatch))
-                } else {
-                    let mut vtk: Vtk = vtk_file.try_into()?;
-                    vtk.file_path = Some(path.into());
-                    Ok(vtk)
+                // The extension only needs to match the data set type if it names one.
+                let ft = ext.and_then(xml::FileType::try_from_ext);
+                if ft.is_some_and(|ft| ft != xml::FileType::from(vtk_file.data_set_type)) {
+                    return Err(Error::XML(xml::Error::TypeExtensionMismatch));
                 }
+                let mut xml_report = FormatReport::new(true, vtk_file.version);
+                if vtk_file.compressor != xml::Compressor::None {
+                    xml_report.features.insert(FormatFeature::Compression);
+                }
+                if vtk_file.has_int64_ids() {
+                    xml_report.features.insert(FormatFeature::Int64Ids);
+                }
+                let mut vtk = vtk_file.into_model_with_options(options, warnings)?;
+                vtk.file_path = Some(path.into());
+                if let Some(report) = report {
+                    xml_report.add_cell_features(&vtk.data);
+                    *report = xml_report;
+                }
+                Ok(vtk)
             }
             #[cfg(not(feature = "xml"))]
-            _ => Err(Error::UnknownFileExtension(None)),
+            FileFormat::Xml => Err(Error::UnknownFileExtension(ext.map(String::from))),
         }
     }
 
+    /// Attach the attributes stored in the file at the specified path to this data set.
+    ///
+    /// This is useful for long time series over a static geometry, where each step is stored in
+    /// a compact field only file (see [`checkpoint`](crate::checkpoint)). All point and cell
+    /// attributes in this data set are replaced by the ones stored in the given file, while
+    /// any geometry stored in the file is ignored.
+    ///
+    /// The file must contain a data set of the same type with the same number of pieces, and
+    /// the size of each attribute must match the number of points or cells in the corresponding
+    /// piece of this data set. Otherwise an error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use vtkio::Vtk;
+    ///
+    /// let mesh = Vtk::import("mesh.vtu").expect("Failed to load mesh");
+    /// let step = mesh
+    ///     .clone()
+    ///     .with_fields_from("fields_0.vtu")
+    ///     .expect("Failed to load fields");
+    /// ```
+    pub fn with_fields_from(self, file_path: impl AsRef<Path>) -> Result<Vtk, Error> {
+        let fields = Vtk::import(file_path)?;
+        let data = self.data.with_attributes_from(
+            fields.data,
+            self.file_path.as_deref(),
+            fields.file_path.as_deref(),
+        )?;
+        Ok(Vtk { data, ..self })
+    }
+
     /// Import a VTK file at the specified path.
     ///
     /// This is the async version of [`import`](Vtk::import).
@@ -572,7 +1703,12 @@ impl Vtk {
     /// [`import`]: fn.import.html
     /// [`import_legacy_be`]: fn.import_legacy_be.html
     pub fn import_legacy_le(file_path: impl AsRef<Path>) -> Result<Vtk, Error> {
-        Vtk::import_vtk(file_path.as_ref(), parser::parse_le)
+        Vtk::import_vtk(
+            file_path.as_ref(),
+            model::ByteOrder::LittleEndian,
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
     }
 
     #[deprecated(since = "0.6.2", note = "Please use Vtk::import_legacy_le instead")]
@@ -588,7 +1724,12 @@ impl Vtk {
     ///
     /// [`import`]: fn.import.html
     pub fn import_legacy_be(file_path: impl AsRef<Path>) -> Result<Vtk, Error> {
-        Vtk::import_vtk(file_path.as_ref(), parser::parse_be)
+        Vtk::import_vtk(
+            file_path.as_ref(),
+            model::ByteOrder::BigEndian,
+            ReadOptions::default(),
+            &mut Vec::new(),
+        )
     }
 
     #[deprecated(since = "0.6.2", note = "Please use Vtk::import_legacy_be instead")]
@@ -642,12 +1783,15 @@ impl Vtk {
             .extension()
             .and_then(|s| s.to_str())
             .ok_or(Error::UnknownFileExtension(None))?;
+        if let Some(serializer) = format::serializer(ext) {
+            let mut writer = std::io::BufWriter::new(File::create(path)?);
+            serializer.serialize(self, &mut writer)?;
+            // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+            io::Write::flush(&mut writer)?;
+            return Ok(());
+        }
         match ext {
-            "vtk" => {
-                let file = File::create(path)?;
-                BinaryWriter(BufWriter::new(file)).write_vtk(self)?;
-                Ok(())
-            }
+            "vtk" => WriterConfig::new().export(self, path),
             #[cfg(feature = "xml")]
             ext => {
                 let ft = xml::FileType::try_from_ext(ext)
@@ -809,15 +1953,102 @@ impl Vtk {
         Ok(())
     }
 
+    /// Write the given VTK file in modern XML format to the specified [`Write`](std::io::Write)r
+    /// with all binary data stored in a raw `AppendedData` section.
+    ///
+    /// Large arrays are written as raw bytes after the XML body instead of being inlined as
+    /// base64, which produces smaller files that are faster to read and write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*; // import model definition of a VTK file
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((2,0)),
+    ///     byte_order: ByteOrder::LittleEndian,
+    ///     title: String::new(),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+    ///         polys: Some(VertexNumbers::XML {
+    ///             connectivity: vec![0, 1, 2],
+    ///             offsets: vec![3],
+    ///         }),
+    ///         data: Attributes::new(),
+    ///         ..Default::default()
+    ///     })
+    /// };
+    ///
+    /// let mut vtk_bytes = Vec::<u8>::new();
+    /// vtk.clone().write_xml_appended(&mut vtk_bytes).expect("Failed to write file");
+    ///
+    /// let round_trip = Vtk::parse_xml(vtk_bytes.as_slice()).expect("Failed to parse file");
+    /// assert_eq!(round_trip, vtk);
+    /// ```
+    #[cfg(feature = "xml")]
+    pub fn write_xml_appended(self, writer: impl Write) -> Result<(), Error> {
+        let vtk_file = self.try_into_xml_appended(xml::Compressor::None, 0)?;
+        xml::write(&vtk_file, writer)?;
+        Ok(())
+    }
+
+    /// Write the given VTK file in modern XML format to the specified [`Write`](std::io::Write)r
+    /// with all binary data compressed by the given compressor.
+    ///
+    /// The compression level ranges from 0 (no compression) to 9 (slowest but smallest file
+    /// size) and is ignored for LZ4 compression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*; // import model definition of a VTK file
+    /// use vtkio::xml::Compressor;
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((2,0)),
+    ///     byte_order: ByteOrder::LittleEndian,
+    ///     title: String::new(),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+    ///         polys: Some(VertexNumbers::XML {
+    ///             connectivity: vec![0, 1, 2],
+    ///             offsets: vec![3],
+    ///         }),
+    ///         data: Attributes::new(),
+    ///         ..Default::default()
+    ///     })
+    /// };
+    ///
+    /// let mut vtk_bytes = Vec::<u8>::new();
+    /// vtk.clone().write_xml_compressed(&mut vtk_bytes, Compressor::ZLib, 5).expect("Failed to write file");
+    /// assert!(String::from_utf8_lossy(&vtk_bytes).contains("vtkZLibDataCompressor"));
+    ///
+    /// let round_trip = Vtk::parse_xml(vtk_bytes.as_slice()).expect("Failed to parse file");
+    /// assert_eq!(round_trip, vtk);
+    /// ```
+    #[cfg(feature = "xml")]
+    pub fn write_xml_compressed(
+        self,
+        writer: impl Write,
+        compressor: xml::Compressor,
+        compression_level: u32,
+    ) -> Result<(), Error> {
+        let vtk_file = self.try_into_xml_format(compressor, compression_level)?;
+        xml::write(&vtk_file, writer)?;
+        Ok(())
+    }
+
     /// Export the VTK data to the specified path in little endian binary format.
     ///
     /// This function is used as [`export`] but overrides endiannes.
     ///
     /// [`export`]: fn.export.html
     pub fn export_le(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
-        let file = File::create(file_path.as_ref())?;
-        BinaryWriter(BufWriter::new(file)).write_vtk_le(self)?;
-        Ok(())
+        WriterConfig::new()
+            .with_byte_order(model::ByteOrder::LittleEndian)
+            .export(self, file_path)
     }
 
     /// Export the VTK data to the specified path in big endian binary format.
@@ -826,9 +2057,9 @@ impl Vtk {
     ///
     /// [`export`]: fn.export.html
     pub fn export_be(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
-        let file = File::create(file_path.as_ref())?;
-        BinaryWriter(BufWriter::new(file)).write_vtk_be(self)?;
-        Ok(())
+        WriterConfig::new()
+            .with_byte_order(model::ByteOrder::BigEndian)
+            .export(self, file_path)
     }
 
     /// Export VTK data to the specified file in ASCII format.
@@ -858,12 +2089,143 @@ impl Vtk {
     /// vtk.export_ascii("test.vtk");
     /// ```
     pub fn export_ascii(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
-        // Ascii formats are typically used for small files, so it makes sense to make the write
-        // in-memory first.
-        let mut out_str = AsciiWriter(String::new());
-        out_str.write_vtk(self)?;
-        let mut file = File::create(file_path.as_ref())?;
-        file.write_all(out_str.0.as_bytes())?;
-        Ok(())
+        WriterConfig::new()
+            .with_format(OutputFormat::LegacyAscii)
+            .export(self, file_path)
+    }
+
+    /// Export only the geometry of this data set to the specified file.
+    ///
+    /// All point, cell and field data is dropped as with [`geometry_only`](Vtk::geometry_only)
+    /// before the remaining data set is written with [`export`](Vtk::export).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let vtk = vtkio::import("simulation.vtu").expect("Failed to load file");
+    /// vtk.export_geometry("mesh.vtu").expect("Failed to export mesh");
+    /// ```
+    pub fn export_geometry(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
+        self.into_geometry_only()?.export(file_path)
+    }
+}
+
+/// Export the given [`Vtk`] data set to the specified file.
+///
+/// This is a shorthand for [`Vtk::export`], which chooses the file format from the extension of
+/// `file_path`: legacy binary for `.vtk` files and the matching XML format otherwise.
+///
+/// # Examples
+///
+/// ```no_run
+/// let vtk = vtkio::import("tet.vtk").expect("Failed to load file");
+/// vtkio::export(vtk.clone(), "tet.vtu").expect("Failed to export XML file");
+/// vtkio::export_ascii(vtk, "tet_ascii.vtk").expect("Failed to export ASCII file");
+/// ```
+pub fn export(vtk: Vtk, file_path: impl AsRef<Path>) -> Result<(), Error> {
+    vtk.export(file_path)
+}
+
+/// Export the given [`Vtk`] data set to the specified file in legacy ASCII format.
+///
+/// This is a shorthand for [`Vtk::export_ascii`].
+pub fn export_ascii(vtk: Vtk, file_path: impl AsRef<Path>) -> Result<(), Error> {
+    vtk.export_ascii(file_path)
+}
+
+/// Export the given [`Vtk`] data set to the specified file in legacy little endian binary format.
+///
+/// This is a shorthand for [`Vtk::export_le`].
+pub fn export_le(vtk: Vtk, file_path: impl AsRef<Path>) -> Result<(), Error> {
+    vtk.export_le(file_path)
+}
+
+/// Export the given [`Vtk`] data set to the specified file in legacy big endian binary format.
+///
+/// This is a shorthand for [`Vtk::export_be`].
+pub fn export_be(vtk: Vtk, file_path: impl AsRef<Path>) -> Result<(), Error> {
+    vtk.export_be(file_path)
+}
+
+/// Import a VTK file at the specified path.
+///
+/// This is a shorthand for [`Vtk::import`], which chooses the file format from the extension or
+/// the contents of `file_path`.
+pub fn import(file_path: impl AsRef<Path>) -> Result<Vtk, Error> {
+    Vtk::import(file_path)
+}
+
+/// Import a series of VTK files with the given extension from a directory one file at a time.
+///
+/// Files are ordered by name, with embedded numbers compared by value so that `step_2.vtu` comes
+/// before `step_10.vtu`. Each file is imported using [`Vtk::import`] only when the iterator
+/// advances. A file that fails to import yields an error without ending the iteration, so the
+/// rest of the series can still be processed. An error is returned immediately only if the
+/// directory itself can't be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// for (step, vtk) in vtkio::import_series("output", "vtu").unwrap().enumerate() {
+///     match vtk {
+///         Ok(vtk) => println!("{}: {}", step, vtk.title),
+///         Err(err) => eprintln!("Skipping step {}: {}", step, err),
+///     }
+/// }
+/// ```
+pub fn import_series(
+    dir: impl AsRef<Path>,
+    extension: &str,
+) -> Result<impl ExactSizeIterator<Item = Result<Vtk, Error>>, Error> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
     }
+    files.sort_by(|a, b| cmp_numbered(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(files.into_iter().map(Vtk::import))
+}
+
+/// Compares strings such that runs of digits are ordered by their numeric value.
+fn cmp_numbered(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    fn digits(s: &[u8]) -> usize {
+        s.iter().take_while(|c| c.is_ascii_digit()).count()
+    }
+    fn trim_zeros(s: &[u8]) -> &[u8] {
+        &s[s.iter().take_while(|&&c| c == b'0').count()..]
+    }
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        let (na, nb) = (digits(a), digits(b));
+        let ord = if na > 0 && nb > 0 {
+            let (x, y) = (trim_zeros(&a[..na]), trim_zeros(&b[..nb]));
+            a = &a[na..];
+            b = &b[nb..];
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            match (a.split_first(), b.split_first()) {
+                (Some((x, rest_a)), Some((y, rest_b))) => {
+                    a = rest_a;
+                    b = rest_b;
+                    x.cmp(y)
+                }
+                _ => return a.len().cmp(&b.len()),
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+/// Scan the headers of the VTK file at the specified path without loading its data.
+///
+/// This is a shorthand for [`summary::scan`], which skips over the bulk data to quickly report
+/// the type and size of the data set along with the arrays it stores.
+#[doc(alias = "peek")]
+pub fn scan(file_path: impl AsRef<Path>) -> Result<summary::Summary, Error> {
+    summary::scan(file_path)
 }
@@ -0,0 +1,393 @@
+//! Geometry helpers for exporting medical image volumes.
+//!
+//! Medical volumes, such as those stored in DICOM series or NIfTI files, place voxels in patient
+//! space using an origin, a voxel spacing and a direction matrix (or equivalently a single affine
+//! transform). A [`VolumeGeometry`] captures this mapping and converts a volume into a VTK data
+//! set that preserves voxel positions.
+//!
+//! `ImageData` in VTK files has no orientation, so volumes whose axes are aligned with the world
+//! axes become [`DataSet::ImageData`], while rotated or sheared volumes are written as a
+//! [`DataSet::StructuredGrid`] with explicit point coordinates.
+//!
+//! No coordinate convention is imposed: DICOM geometry is given in LPS (left, posterior,
+//! superior) coordinates while NIfTI uses RAS (right, anterior, superior). Use
+//! [`VolumeGeometry::flip_ras_lps`] to convert between the two.
+//!
+//! This module is available with the `medical` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::medical::VolumeGeometry;
+//! use vtkio::model::*;
+//!
+//! // NIfTI sform with 0.5mm in-plane and 2mm slice spacing.
+//! let geometry = VolumeGeometry::from_affine([
+//!     [0.5, 0.0, 0.0, -10.0],
+//!     [0.0, 0.5, 0.0, -20.0],
+//!     [0.0, 0.0, 2.0, 5.0],
+//!     [0.0, 0.0, 0.0, 1.0],
+//! ])
+//! .unwrap();
+//!
+//! let mut data = Attributes::new();
+//! data.point.push(Attribute::scalars("intensity", 1).with_data(vec![0i16; 8]));
+//! match geometry.into_data_set([2, 2, 2], data) {
+//!     DataSet::ImageData { origin, spacing, .. } => {
+//!         assert_eq!(origin, [-10.0, -20.0, 5.0]);
+//!         assert_eq!(spacing, [0.5, 0.5, 2.0]);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::model::*;
+
+/// Tolerance used to decide whether a direction matrix is the identity.
+const AXIS_ALIGNED_TOLERANCE: f64 = 1e-6;
+
+/// Error produced when volume geometry can't be determined from the given metadata.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GeometryError {
+    /// The direction of the given voxel axis (0, 1 or 2) has zero length.
+    DegenerateAxis(usize),
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeometryError::DegenerateAxis(axis) => {
+                write!(f, "Direction of voxel axis {} has zero length", axis)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+/// The placement of a voxel grid in world space.
+///
+/// The world position of the voxel with indices `(i, j, k)` is
+///
+/// `origin + i * spacing[0] * axis(0) + j * spacing[1] * axis(1) + k * spacing[2] * axis(2)`
+///
+/// where `axis(c)` is the unit length column `c` of the `direction` matrix.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct VolumeGeometry {
+    /// World position of the first voxel.
+    pub origin: [f64; 3],
+    /// Distance between neighbouring voxels along each voxel axis.
+    pub spacing: [f64; 3],
+    /// Row major matrix whose columns are the unit directions of the voxel axes.
+    pub direction: [[f64; 3]; 3],
+}
+
+impl VolumeGeometry {
+    /// Constructs an axis aligned geometry.
+    pub fn new(origin: [f64; 3], spacing: [f64; 3]) -> Self {
+        VolumeGeometry {
+            origin,
+            spacing,
+            direction: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// Constructs a geometry from a row major 4x4 affine transform mapping voxel indices to world
+    /// coordinates, such as the NIfTI `sform` or `qform` matrix.
+    ///
+    /// The last row of the affine is ignored. An error is returned if any of the first three
+    /// columns is zero.
+    pub fn from_affine(affine: [[f64; 4]; 4]) -> Result<Self, GeometryError> {
+        let mut geometry = VolumeGeometry {
+            origin: [affine[0][3], affine[1][3], affine[2][3]],
+            spacing: [1.0; 3],
+            direction: [
+                [affine[0][0], affine[0][1], affine[0][2]],
+                [affine[1][0], affine[1][1], affine[1][2]],
+                [affine[2][0], affine[2][1], affine[2][2]],
+            ],
+        };
+        for axis in 0..3 {
+            let (unit, len) =
+                normalize(geometry.axis(axis)).ok_or(GeometryError::DegenerateAxis(axis))?;
+            geometry.set_axis(axis, unit);
+            geometry.spacing[axis] = len;
+        }
+        Ok(geometry)
+    }
+
+    /// Constructs a geometry from DICOM image plane attributes.
+    ///
+    /// `image_position` and `image_orientation` are the `ImagePositionPatient` and
+    /// `ImageOrientationPatient` attributes of the first slice, and `pixel_spacing` is the
+    /// `PixelSpacing` attribute, given as the distance between rows followed by the distance
+    /// between columns. The slice direction is the cross product of the row and column directions.
+    /// A negative `slice_spacing` indicates that slices are ordered against this direction.
+    pub fn from_dicom(
+        image_position: [f64; 3],
+        image_orientation: [f64; 6],
+        pixel_spacing: [f64; 2],
+        slice_spacing: f64,
+    ) -> Result<Self, GeometryError> {
+        let row = [
+            image_orientation[0],
+            image_orientation[1],
+            image_orientation[2],
+        ];
+        let col = [
+            image_orientation[3],
+            image_orientation[4],
+            image_orientation[5],
+        ];
+        let (row, _) = normalize(row).ok_or(GeometryError::DegenerateAxis(0))?;
+        let (col, _) = normalize(col).ok_or(GeometryError::DegenerateAxis(1))?;
+        let (normal, _) = normalize(cross(row, col)).ok_or(GeometryError::DegenerateAxis(2))?;
+        let normal = if slice_spacing < 0.0 {
+            [-normal[0], -normal[1], -normal[2]]
+        } else {
+            normal
+        };
+        let mut geometry = VolumeGeometry::new(
+            image_position,
+            [pixel_spacing[1], pixel_spacing[0], slice_spacing.abs()],
+        );
+        geometry.set_axis(0, row);
+        geometry.set_axis(1, col);
+        geometry.set_axis(2, normal);
+        Ok(geometry)
+    }
+
+    /// Constructs a geometry from the `ImagePositionPatient` attributes of all slices in a DICOM
+    /// series, in the order the slices are stored.
+    ///
+    /// The slice spacing is the distance between the first two slices along the slice normal. A
+    /// single slice is given unit slice spacing.
+    pub fn from_dicom_slices(
+        image_positions: &[[f64; 3]],
+        image_orientation: [f64; 6],
+        pixel_spacing: [f64; 2],
+    ) -> Result<Self, GeometryError> {
+        let first = image_positions.first().copied().unwrap_or([0.0; 3]);
+        let mut geometry =
+            VolumeGeometry::from_dicom(first, image_orientation, pixel_spacing, 1.0)?;
+        if let Some(second) = image_positions.get(1) {
+            let offset = [
+                second[0] - first[0],
+                second[1] - first[1],
+                second[2] - first[2],
+            ];
+            let slice_spacing = dot(offset, geometry.axis(2));
+            if slice_spacing.abs() > 0.0 {
+                geometry = VolumeGeometry::from_dicom(
+                    first,
+                    image_orientation,
+                    pixel_spacing,
+                    slice_spacing,
+                )?;
+            }
+        }
+        Ok(geometry)
+    }
+
+    /// Converts between RAS and LPS world coordinates by negating the first two world axes.
+    pub fn flip_ras_lps(mut self) -> Self {
+        for row in 0..2 {
+            self.origin[row] = -self.origin[row];
+            for value in self.direction[row].iter_mut() {
+                *value = -*value;
+            }
+        }
+        self
+    }
+
+    /// Returns the unit direction of the given voxel axis in world space.
+    pub fn axis(&self, axis: usize) -> [f64; 3] {
+        [
+            self.direction[0][axis],
+            self.direction[1][axis],
+            self.direction[2][axis],
+        ]
+    }
+
+    /// Returns the row major 4x4 affine transform mapping voxel indices to world coordinates.
+    pub fn affine(&self) -> [[f64; 4]; 4] {
+        let mut affine = [[0.0, 0.0, 0.0, 1.0]; 4];
+        for (row, dir) in self.direction.iter().enumerate() {
+            affine[row] = [
+                dir[0] * self.spacing[0],
+                dir[1] * self.spacing[1],
+                dir[2] * self.spacing[2],
+                self.origin[row],
+            ];
+        }
+        affine
+    }
+
+    /// Returns the world position of the voxel with the given indices.
+    pub fn world_position(&self, index: [f64; 3]) -> [f64; 3] {
+        let offset = [
+            index[0] * self.spacing[0],
+            index[1] * self.spacing[1],
+            index[2] * self.spacing[2],
+        ];
+        let mut pos = self.origin;
+        for (p, dir) in pos.iter_mut().zip(self.direction.iter()) {
+            *p += dot(*dir, offset);
+        }
+        pos
+    }
+
+    /// Returns `true` if the voxel axes coincide with the world axes.
+    pub fn is_axis_aligned(&self) -> bool {
+        (0..3).all(|row| {
+            (0..3).all(|col| {
+                let expected = if row == col { 1.0 } else { 0.0 };
+                (self.direction[row][col] - expected).abs() <= AXIS_ALIGNED_TOLERANCE
+            })
+        })
+    }
+
+    /// Converts a volume with the given number of voxels along each axis into a data set.
+    ///
+    /// Voxel values are expected as point attributes in `data`, with the first index varying
+    /// fastest. Axis aligned volumes produce `ImageData`; all other volumes produce a
+    /// `StructuredGrid` whose points are the world positions of the voxels.
+    pub fn into_data_set(self, dims: [u32; 3], data: Attributes) -> DataSet {
+        let extent = Extent::Ranges([
+            0..=dims[0] as i32 - 1,
+            0..=dims[1] as i32 - 1,
+            0..=dims[2] as i32 - 1,
+        ]);
+        if self.is_axis_aligned() {
+            return DataSet::ImageData {
+                extent: extent.clone(),
+                origin: [
+                    self.origin[0] as f32,
+                    self.origin[1] as f32,
+                    self.origin[2] as f32,
+                ],
+                spacing: [
+                    self.spacing[0] as f32,
+                    self.spacing[1] as f32,
+                    self.spacing[2] as f32,
+                ],
+                meta: None,
+                pieces: vec![Piece::Inline(Box::new(ImageDataPiece { extent, data }))],
+            };
+        }
+        let num_points = dims.iter().map(|&d| d as usize).product::<usize>();
+        let mut points = Vec::with_capacity(3 * num_points);
+        for k in 0..dims[2] {
+            for j in 0..dims[1] {
+                for i in 0..dims[0] {
+                    let pos = self.world_position([f64::from(i), f64::from(j), f64::from(k)]);
+                    points.extend_from_slice(&pos);
+                }
+            }
+        }
+        DataSet::inline(StructuredGridPiece {
+            extent,
+            points: points.into(),
+            data,
+        })
+    }
+
+    fn set_axis(&mut self, axis: usize, dir: [f64; 3]) {
+        for (row, &d) in dir.iter().enumerate() {
+            self.direction[row][axis] = d;
+        }
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Returns the given vector scaled to unit length along with its original length.
+///
+/// `None` is returned for vectors of zero length.
+fn normalize(v: [f64; 3]) -> Option<([f64; 3], f64)> {
+    let len = dot(v, v).sqrt();
+    if len > 0.0 {
+        Some(([v[0] / len, v[1] / len, v[2] / len], len))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f64; 3], b: [f64; 3]) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn affine_round_trip() {
+        let affine = [
+            [0.0, -0.5, 0.0, 10.0],
+            [0.5, 0.0, 0.0, -3.0],
+            [0.0, 0.0, 2.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        let geometry = VolumeGeometry::from_affine(affine).unwrap();
+        assert_eq!(geometry.spacing, [0.5, 0.5, 2.0]);
+        assert!(!geometry.is_axis_aligned());
+        assert_eq!(geometry.affine(), affine);
+        assert_close(geometry.world_position([1.0, 2.0, 3.0]), [9.0, -2.5, 7.0]);
+
+        let mut degenerate = affine;
+        degenerate[0][1] = 0.0;
+        assert_eq!(
+            VolumeGeometry::from_affine(degenerate),
+            Err(GeometryError::DegenerateAxis(1))
+        );
+    }
+
+    #[test]
+    fn dicom_slices() {
+        // Axial slices stored from head to feet.
+        let positions = [[-100.0, -120.0, 50.0], [-100.0, -120.0, 47.5]];
+        let orientation = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let geometry =
+            VolumeGeometry::from_dicom_slices(&positions, orientation, [0.8, 0.6]).unwrap();
+        assert_eq!(geometry.spacing, [0.6, 0.8, 2.5]);
+        assert_close(geometry.axis(2), [0.0, 0.0, -1.0]);
+        assert_close(geometry.world_position([0.0, 0.0, 1.0]), positions[1]);
+
+        let lps = geometry.flip_ras_lps();
+        assert_close(lps.origin, [100.0, 120.0, 50.0]);
+        assert_close(lps.axis(0), [-1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn oriented_volume_is_structured_grid() {
+        let geometry =
+            VolumeGeometry::from_dicom([0.0; 3], [0.0, 1.0, 0.0, 0.0, 0.0, -1.0], [1.0, 1.0], 1.0)
+                .unwrap();
+        match geometry.into_data_set([2, 1, 1], Attributes::new()) {
+            DataSet::StructuredGrid { pieces, .. } => {
+                let piece = pieces[0].load_piece_data(None).unwrap();
+                assert_eq!(
+                    piece.points.into_vec::<f64>().unwrap(),
+                    vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+                );
+            }
+            _ => panic!("expected a structured grid"),
+        }
+    }
+}
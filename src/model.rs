@@ -10,22 +10,44 @@
 //!
 
 use std::any::TypeId;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 
 use bytemuck::{cast_slice, cast_vec};
-use num_derive::FromPrimitive;
 use num_traits::ToPrimitive;
 
 /// Error type describing failure modes of various model processing tasks and validation.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     InvalidCast(std::io::Error),
     FailedToLoadPieceData,
     MissingPieceData,
     PieceDataMismatch,
+    AttributeSizeMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    IncompatiblePieces(String),
+    DimensionsMismatch {
+        dims: [u32; 3],
+        num_points: usize,
+    },
+    TupleSizeMismatch {
+        name: String,
+        num_comp: usize,
+        len: usize,
+    },
+    InvalidTCoordsDim {
+        name: String,
+        dim: u32,
+    },
+    InvalidCells(CellIssue),
     IO(std::io::Error),
     VTKIO(Box<crate::Error>),
 }
@@ -36,6 +58,44 @@ impl std::fmt::Display for Error {
             Error::InvalidCast(source) => write!(f, "Invalid cast error: {:?}", source),
             Error::MissingPieceData => write!(f, "Missing piece data"),
             Error::PieceDataMismatch => write!(f, "Piece type doesn't match data set type"),
+            Error::AttributeSizeMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Attribute \"{}\" has {} values, but should have {}",
+                name, actual, expected
+            ),
+            Error::IncompatiblePieces(name) => write!(
+                f,
+                "Data array \"{}\" is missing or has a different type in some pieces",
+                name
+            ),
+            Error::DimensionsMismatch { dims, num_points } => write!(
+                f,
+                "DIMENSIONS {} {} {} describe {} points, but {} points were given",
+                dims[0],
+                dims[1],
+                dims[2],
+                u64::from(dims[0]) * u64::from(dims[1]) * u64::from(dims[2]),
+                num_points
+            ),
+            Error::TupleSizeMismatch {
+                name,
+                num_comp,
+                len,
+            } => write!(
+                f,
+                "Data array \"{}\" has {} values, which is not a multiple of {} components",
+                name, len, num_comp
+            ),
+            Error::InvalidTCoordsDim { name, dim } => write!(
+                f,
+                "Texture coordinates \"{}\" have {} dimensions, but only 1, 2 or 3 are supported",
+                name, dim
+            ),
+            Error::InvalidCells(issue) => write!(f, "Invalid cells: {}", issue),
             Error::IO(source) => write!(f, "IO error: {:?}", source),
             Error::VTKIO(source) => write!(f, "VTK IO error: {:?}", source),
             Error::FailedToLoadPieceData => write!(f, "Failed to load piece data"),
@@ -164,10 +224,410 @@ impl Vtk {
         }
         Ok(())
     }
+
+    /// Loads the piece at the given index if it references another file.
+    ///
+    /// Unlike [`load_all_pieces`](Vtk::load_all_pieces), the loaded piece is stored as a
+    /// [`Piece::Loaded`] data set in place, so the indices of all pieces remain unchanged. This
+    /// allows loading only the pieces that are needed from large "Parallel" XML files.
+    ///
+    /// Loaded and inline pieces are left untouched. An error is returned if there is no piece
+    /// with the given index.
+    pub fn load_piece(&mut self, index: usize) -> Result<(), Error> {
+        fn load<P: PieceData>(
+            pieces: &mut [Piece<P>],
+            index: usize,
+            file_path: Option<&Path>,
+        ) -> Result<(), Error> {
+            pieces
+                .get_mut(index)
+                .ok_or(Error::MissingPieceData)?
+                .load_piece_in_place_recursive(file_path)
+        }
+        let file_path = self.file_path.as_deref();
+        match &mut self.data {
+            DataSet::ImageData { pieces, .. } => load(pieces, index, file_path),
+            DataSet::StructuredGrid { pieces, .. } => load(pieces, index, file_path),
+            DataSet::RectilinearGrid { pieces, .. } => load(pieces, index, file_path),
+            DataSet::UnstructuredGrid { pieces, .. } => load(pieces, index, file_path),
+            DataSet::PolyData { pieces, .. } => load(pieces, index, file_path),
+            DataSet::Field { .. } => Err(Error::MissingPieceData),
+        }
+    }
+
+    /// Consumes this data set and returns an iterator over its pieces, each as its own `Vtk`.
+    ///
+    /// Pieces stored in other files are loaded one at a time as the iterator advances, so only a
+    /// single piece of a large "Parallel" XML file needs to be kept in memory. A piece that fails
+    /// to load yields an error without ending the iteration, and the remaining pieces can still
+    /// be processed.
+    ///
+    /// Each yielded data set keeps the header, whole extent and meta data of this one. Field data
+    /// sets have no pieces, so nothing is yielded for them.
+    pub fn into_piece_iter(self) -> impl ExactSizeIterator<Item = Result<Vtk, Error>> {
+        let Vtk {
+            version,
+            title,
+            byte_order,
+            data,
+            file_path,
+        } = self;
+        data.into_pieces().into_iter().map(move |data| {
+            let mut vtk = Vtk {
+                version,
+                title: title.clone(),
+                byte_order,
+                data,
+                file_path: file_path.clone(),
+            };
+            vtk.load_all_pieces()?;
+            Ok(vtk)
+        })
+    }
+
+    /// Checks that the dimensions of structured data sets match the number of points given.
+    ///
+    /// This is used to validate legacy files, where the `DIMENSIONS` are specified independently
+    /// from the `POINTS` of a `STRUCTURED_GRID` and the coordinates of a `RECTILINEAR_GRID`.
+    ///
+    /// If `tolerant` is `true`, mismatched dimensions are recomputed from the number of points
+    /// instead of producing an error. For rectilinear grids, the dimensions are simply given by the
+    /// number of coordinates along each axis. For structured grids, the last dimension is
+    /// recomputed if the number of points is divisible by the first two, otherwise the grid is
+    /// treated as a line of points. A warning is reported for each recomputed extent.
+    pub(crate) fn check_dimensions(
+        &mut self,
+        tolerant: bool,
+        warnings: &mut Vec<crate::Warning>,
+    ) -> Result<(), Error> {
+        let (extent, pieces_extent, dims) = match &mut self.data {
+            DataSet::StructuredGrid { extent, pieces, .. } => match pieces.as_mut_slice() {
+                [Piece::Inline(piece)] => {
+                    let num_points = piece.points.len() / 3;
+                    let [nx, ny, nz] = extent.clone().into_dims();
+                    if extent.num_points() == num_points as u64 {
+                        return Ok(());
+                    }
+                    if !tolerant {
+                        return Err(Error::DimensionsMismatch {
+                            dims: [nx, ny, nz],
+                            num_points,
+                        });
+                    }
+                    let plane = nx as usize * ny as usize;
+                    let dims = if plane > 0 && num_points / plane * plane == num_points {
+                        [nx, ny, (num_points / plane) as u32]
+                    } else {
+                        [num_points as u32, 1, 1]
+                    };
+                    (extent, &mut piece.extent, dims)
+                }
+                _ => return Ok(()),
+            },
+            DataSet::RectilinearGrid { extent, pieces, .. } => match pieces.as_mut_slice() {
+                [Piece::Inline(piece)] => {
+                    let coords = &piece.coords;
+                    let dims = [coords.x.len(), coords.y.len(), coords.z.len()];
+                    let dims = [dims[0] as u32, dims[1] as u32, dims[2] as u32];
+                    if extent.clone().into_dims() == dims {
+                        return Ok(());
+                    }
+                    if !tolerant {
+                        return Err(Error::DimensionsMismatch {
+                            dims: extent.clone().into_dims(),
+                            num_points: dims.iter().map(|&n| n as usize).product(),
+                        });
+                    }
+                    (extent, &mut piece.extent, dims)
+                }
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        warnings.push(crate::Warning::DimensionsRecomputed {
+            dims: extent.clone().into_dims(),
+            recomputed: dims,
+        });
+        *extent = Extent::Dims(dims);
+        *pieces_extent = Extent::Dims(dims);
+        Ok(())
+    }
+
+    /// Converts the color scalars in all inline pieces to floats in `[0, 1]`.
+    ///
+    /// This is used to read binary legacy files, which store colors as unsigned chars.
+    pub(crate) fn normalize_color_scalars(&mut self) {
+        fn normalize<P>(pieces: &mut [Piece<P>], data: impl Fn(&mut P) -> &mut Attributes) {
+            for piece in pieces.iter_mut() {
+                if let Piece::Inline(piece) = piece {
+                    let data = data(piece);
+                    for attrib in data.point.iter_mut().chain(data.cell.iter_mut()) {
+                        if let Attribute::DataArray(DataArray {
+                            elem: ElementType::ColorScalars(_),
+                            data,
+                            ..
+                        }) = attrib
+                        {
+                            if let Cow::Owned(floats) = color_scalars_to_f32(data) {
+                                *data = floats;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        match &mut self.data {
+            DataSet::ImageData { pieces, .. } => normalize(pieces, |p| &mut p.data),
+            DataSet::StructuredGrid { pieces, .. } => normalize(pieces, |p| &mut p.data),
+            DataSet::RectilinearGrid { pieces, .. } => normalize(pieces, |p| &mut p.data),
+            DataSet::UnstructuredGrid { pieces, .. } => normalize(pieces, |p| &mut p.data),
+            DataSet::PolyData { pieces, .. } => normalize(pieces, |p| &mut p.data),
+            DataSet::Field { .. } => {}
+        }
+    }
+
+    /// Removes the point and cell data arrays of inline pieces whose names are rejected by `keep`.
+    ///
+    /// Arrays of field attributes are filtered individually, and fields left empty are removed.
+    pub(crate) fn retain_arrays(&mut self, keep: &dyn Fn(&str) -> bool) {
+        fn retain<P>(
+            pieces: &mut [Piece<P>],
+            keep: &dyn Fn(&str) -> bool,
+            data: impl Fn(&mut P) -> &mut Attributes,
+        ) {
+            for piece in pieces.iter_mut() {
+                if let Piece::Inline(piece) = piece {
+                    let data = data(piece);
+                    for attribs in [&mut data.point, &mut data.cell] {
+                        attribs.retain_mut(|attrib| match attrib {
+                            Attribute::DataArray(array) => keep(&array.name),
+                            Attribute::Field { data_array, .. } => {
+                                data_array.retain(|array| keep(&array.name));
+                                !data_array.is_empty()
+                            }
+                            Attribute::Unknown { name, .. } => keep(name),
+                        });
+                    }
+                }
+            }
+        }
+        match &mut self.data {
+            DataSet::ImageData { pieces, .. } => retain(pieces, keep, |p| &mut p.data),
+            DataSet::StructuredGrid { pieces, .. } => retain(pieces, keep, |p| &mut p.data),
+            DataSet::RectilinearGrid { pieces, .. } => retain(pieces, keep, |p| &mut p.data),
+            DataSet::UnstructuredGrid { pieces, .. } => retain(pieces, keep, |p| &mut p.data),
+            DataSet::PolyData { pieces, .. } => retain(pieces, keep, |p| &mut p.data),
+            DataSet::Field { .. } => {}
+        }
+    }
+
+    /// Returns a copy of this data set without any point, cell or field data.
+    ///
+    /// Only the geometry and topology are kept, which is useful for sharing meshes without the
+    /// simulation results attached to them. Pieces stored in other files are loaded first, so the
+    /// copy no longer references any files whose attributes could be read back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((4, 2)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Points"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0].into(),
+    ///         data: Attributes {
+    ///             point: vec![Attribute::scalars("pressure", 1).with_data(vec![1.0f32])],
+    ///             ..Attributes::new()
+    ///         },
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let geometry = vtk.geometry_only().expect("Failed to load pieces");
+    /// assert_eq!(
+    ///     geometry.data,
+    ///     DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0].into(),
+    ///         ..Default::default()
+    ///     })
+    /// );
+    /// ```
+    pub fn geometry_only(&self) -> Result<Vtk, Error> {
+        self.clone().into_geometry_only()
+    }
+
+    /// Consuming version of [`geometry_only`](Vtk::geometry_only).
+    pub(crate) fn into_geometry_only(mut self) -> Result<Vtk, Error> {
+        self.load_all_pieces()?;
+        self.data.strip_attributes();
+        Ok(self)
+    }
+
+    /// Checks that the sizes of the geometry and attributes in all inline pieces are consistent.
+    ///
+    /// This verifies that
+    ///  - points are given as triples of coordinates,
+    ///  - structured and rectilinear grids have as many points as described by their extent,
+    ///  - cell vertex numbers describe exactly the given number of cells, and unstructured grids
+    ///    have one cell type per cell,
+    ///  - each point and cell attribute has exactly one tuple per point and cell respectively.
+    ///
+    /// Pieces stored in other files are not checked. Writers don't check any of the above, so
+    /// writing an inconsistent model produces a corrupt file unless validation is enabled with
+    /// [`WriterConfig::with_validation`](crate::writer::WriterConfig::with_validation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// let mut vtk = Vtk {
+    ///     version: Version::new((4, 2)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Triangle"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+    ///         polys: Some(VertexNumbers::Legacy {
+    ///             num_cells: 1,
+    ///             vertices: vec![3, 0, 1, 2],
+    ///         }),
+    ///         data: Attributes {
+    ///             point: vec![Attribute::scalars("id", 1).with_data(vec![0u8, 1])],
+    ///             ..Attributes::new()
+    ///         },
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// assert!(matches!(vtk.validate(), Err(Error::AttributeSizeMismatch { expected: 3, .. })));
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        fn validate<P>(
+            pieces: &[Piece<P>],
+            check: impl Fn(&P) -> Result<(), Error>,
+        ) -> Result<(), Error> {
+            pieces.iter().try_for_each(|piece| match piece {
+                Piece::Inline(piece) => check(piece),
+                _ => Ok(()),
+            })
+        }
+        fn validate_points(points: &IOBuffer) -> Result<(), Error> {
+            if !points.len().is_multiple_of(3) {
+                return Err(Error::TupleSizeMismatch {
+                    name: String::from("points"),
+                    num_comp: 3,
+                    len: points.len(),
+                });
+            }
+            Ok(())
+        }
+        fn validate_extent(extent: &Extent, num_points: usize) -> Result<(), Error> {
+            if extent.num_points() != num_points as u64 {
+                return Err(Error::DimensionsMismatch {
+                    dims: extent.clone().into_dims(),
+                    num_points,
+                });
+            }
+            Ok(())
+        }
+        fn validate_cells(cell_verts: &VertexNumbers) -> Result<(), Error> {
+            match split_cells(cell_verts) {
+                (_, Some(cell)) => Err(Error::InvalidCells(CellIssue::MalformedConnectivity {
+                    cell,
+                })),
+                (_, None) => Ok(()),
+            }
+        }
+        match &self.data {
+            DataSet::ImageData { pieces, .. } => validate(pieces, |p| {
+                let num_points = p.extent.num_points() as usize;
+                validate_attributes(&p.data, num_points, p.extent.num_cells() as usize)
+            }),
+            DataSet::StructuredGrid { pieces, .. } => validate(pieces, |p| {
+                validate_points(&p.points)?;
+                validate_extent(&p.extent, p.num_points())?;
+                validate_attributes(&p.data, p.num_points(), p.extent.num_cells() as usize)
+            }),
+            DataSet::RectilinearGrid { pieces, .. } => validate(pieces, |p| {
+                let coords = &p.coords;
+                let num_points = coords.x.len() * coords.y.len() * coords.z.len();
+                validate_extent(&p.extent, num_points)?;
+                validate_attributes(&p.data, num_points, p.extent.num_cells() as usize)
+            }),
+            DataSet::UnstructuredGrid { pieces, .. } => validate(pieces, |p| {
+                validate_points(&p.points)?;
+                validate_cells(&p.cells.cell_verts)?;
+                let num_types = p.cells.types.len();
+                let num_cells = p.cells.cell_verts.num_cells();
+                if num_types != num_cells {
+                    return Err(Error::InvalidCells(CellIssue::CellCountMismatch {
+                        num_types,
+                        num_cells,
+                    }));
+                }
+                validate_attributes(&p.data, p.num_points(), num_cells)
+            }),
+            DataSet::PolyData { pieces, .. } => validate(pieces, |p| {
+                validate_points(&p.points)?;
+                for topo in [&p.verts, &p.lines, &p.polys, &p.strips]
+                    .iter()
+                    .copied()
+                    .flatten()
+                {
+                    validate_cells(topo)?;
+                }
+                validate_attributes(&p.data, p.num_points(), p.num_cells())
+            }),
+            DataSet::Field { .. } => Ok(()),
+        }
+    }
+
+    /// Loads all pieces of an unstructured grid.
+    ///
+    /// This is useful for reading parallel `.pvtu` files, where each piece is stored in a
+    /// separate file. Piece files are resolved relative to `file_path`.
+    ///
+    /// An error is returned if this file is not an unstructured grid.
+    pub fn into_unstructured_grid_pieces(mut self) -> Result<Vec<UnstructuredGridPiece>, Error> {
+        self.load_all_pieces()?;
+        let Vtk {
+            data, file_path, ..
+        } = self;
+        match data {
+            DataSet::UnstructuredGrid { pieces, .. } => pieces
+                .into_iter()
+                .map(|piece| piece.into_loaded_piece_data(file_path.as_deref()))
+                .collect(),
+            _ => Err(Error::PieceDataMismatch),
+        }
+    }
+
+    /// Loads all pieces of an unstructured grid and merges them into a single inline piece.
+    ///
+    /// See [`UnstructuredGridPiece::merge`] for how pieces are combined.
+    pub fn into_merged_unstructured_grid(self) -> Result<Vtk, Error> {
+        let version = self.version;
+        let title = self.title.clone();
+        let byte_order = self.byte_order;
+        let file_path = self.file_path.clone();
+        let piece = UnstructuredGridPiece::merge(self.into_unstructured_grid_pieces()?)?;
+        Ok(Vtk {
+            version,
+            title,
+            byte_order,
+            data: DataSet::inline(piece),
+            file_path,
+        })
+    }
 }
 
 /// Version number (e.g. `4.1 => Version { major: 4, minor: 1 }`)
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Eq, Hash, PartialOrd, Ord)]
 pub struct Version {
     pub major: u8,
     pub minor: u8,
@@ -180,6 +640,12 @@ impl Version {
             minor: pair.1,
         }
     }
+
+    /// Returns `true` if legacy files of this version store cells as separate `OFFSETS` and
+    /// `CONNECTIVITY` arrays, which is the layout written by VTK since file version 5.1.
+    pub fn has_cell_arrays(self) -> bool {
+        self >= Version::new((5, 1))
+    }
 }
 
 impl From<(u8, u8)> for Version {
@@ -194,7 +660,7 @@ impl fmt::Display for Version {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ByteOrder {
     BigEndian,
@@ -357,6 +823,67 @@ macro_rules! impl_bytes_constructor {
     }};
 }
 
+/// Size of each block of uncompressed data when writing compressed data arrays.
+///
+/// This is the default block size used by VTK.
+#[cfg(feature = "xml")]
+const COMPRESSION_BLOCK_SIZE: usize = 1 << 15;
+
+/// Compresses a single block of data with the given compressor.
+///
+/// If support for the given compressor is disabled, the block is left uncompressed.
+// Rustfmt removes the extra layer of curly braces, which breaks the feature attribute
+// specifications.
+#[rustfmt::skip]
+#[cfg(feature = "xml")]
+#[allow(unused_variables)]
+fn compress_block(
+    block: &[u8],
+    compressor: crate::xml::Compressor,
+    compression_level: u32,
+) -> Vec<u8> {
+    use crate::xml::Compressor;
+    match compressor {
+        Compressor::ZLib => {
+            #[cfg(feature = "flate2")]
+            {
+                use flate2::{write::ZlibEncoder, Compression};
+                use std::io::Write;
+                let mut e = ZlibEncoder::new(Vec::new(), Compression::new(compression_level));
+                e.write_all(block).unwrap();
+                return e.finish().unwrap();
+            }
+        }
+        Compressor::LZMA => {
+            #[cfg(feature = "xz2")]
+            {
+                use std::io::Write;
+                let mut e = xz2::write::XzEncoder::new(Vec::new(), compression_level);
+                e.write_all(block).unwrap();
+                return e.finish().unwrap();
+            }
+        }
+        Compressor::LZ4 => {
+            #[cfg(feature = "lz4")]
+            {
+                // VTK expects data compressed in the LZ4 block format.
+                return lz4::compress(block);
+            }
+        }
+        Compressor::Zstd => {
+            #[cfg(feature = "zstd")]
+            {
+                // Zstd levels range from 1 to 22, but levels beyond 19 require more memory to
+                // decompress, so we map the 0-9 scale onto 1-19.
+                let level = 1 + 2 * compression_level.min(9) as i32;
+                return zstd::bulk::compress(block, level).unwrap();
+            }
+        }
+        Compressor::None => {}
+    }
+    block.to_vec()
+}
+
 impl IOBuffer {
     /// Returns the scalar type represented by this buffer.
     pub fn scalar_type(&self) -> ScalarType {
@@ -399,7 +926,7 @@ impl IOBuffer {
 
     /// Converts this `IOBuffer` into an array of bytes with a 64-bit size prefix.
     ///
-    /// The size of the scalar type in bytes is stored as a 64-bit integer at the very beginning.
+    /// The size of the data in bytes is stored as a 64-bit integer at the very beginning.
     ///
     /// This is how VTK data arrays store data in the XML files.
     #[cfg(feature = "xml")]
@@ -421,7 +948,7 @@ impl IOBuffer {
 
     /// Converts this `IOBuffer` into an array of bytes with a 32-bit size prefix.
     ///
-    /// The size of the scalar type in bytes is stored as a 32-bit integer at the very beginning.
+    /// The size of the data in bytes is stored as a 32-bit integer at the very beginning.
     ///
     /// This is how VTK data arrays store data in the XML files.
     #[cfg(feature = "xml")]
@@ -441,9 +968,6 @@ impl IOBuffer {
         })
     }
 
-    // Rustfmt removes the extra layer of curly braces, which breaks the feature attribute
-    // specifications.
-    #[rustfmt::skip]
     #[cfg(feature = "xml")]
     fn into_bytes_with_size_impl(
         self,
@@ -455,96 +979,59 @@ impl IOBuffer {
     ) -> Vec<u8> {
         use crate::xml::Compressor;
 
+        let num_uncompressed_bytes = self.num_bytes();
+
         // Allocate enough bytes for the prefix.
-        // We will know what exactly to put there after compression.
+        // We will know what exactly to put there after writing the data.
         let mut out = vec![0u8; prefix_size];
 
-        let num_uncompressed_bytes = self.num_bytes();
-
         // Reserve the number of bytes of the uncompressed data.
         out.reserve(num_uncompressed_bytes);
 
         // Handle fast pass cases where we can just do a memcpy.
-        if compressor == Compressor::None || compression_level == 0 {
-            match self {
-                IOBuffer::Bit(mut v) | IOBuffer::U8(mut v) => {
-                    out.append(&mut v);
-                    write_size(out.as_mut_slice(), num_uncompressed_bytes);
-                    return out;
-                }
-                IOBuffer::I8(v) => {
-                    out.append(&mut cast_vec(v));
-                    write_size(out.as_mut_slice(), num_uncompressed_bytes);
-                    return out;
-                }
-                // Can't just copy the bytes, so we will do a conversion.
-                _ => {}
+        match self {
+            IOBuffer::Bit(mut v) | IOBuffer::U8(mut v) => out.append(&mut v),
+            IOBuffer::I8(v) => out.append(&mut cast_vec(v)),
+            // Data in native byte order is already laid out as required.
+            buf if bo == ByteOrder::native() => {
+                match_buf!(&buf, v => out.extend_from_slice(bytemuck::cast_slice(v)))
             }
+            // Can't just copy the bytes, so we will do a conversion.
+            buf => buf.write_bytes(&mut out, bo),
         }
 
-        {
-            match compressor {
-                Compressor::ZLib => {
-                    #[cfg(feature = "flate2")]
-                    {
-                        use flate2::{write::ZlibEncoder, Compression};
-                        let mut e = ZlibEncoder::new(out, Compression::new(compression_level));
-                        self.write_bytes(&mut e, bo);
-                        let mut out = e.finish().unwrap();
-                        let num_compressed_bytes = out.len() - prefix_size;
-                        write_size(out.as_mut_slice(), num_compressed_bytes);
-                        return out;
-                    }
-                }
-                Compressor::LZMA => {
-                    #[cfg(feature = "xz2")]
-                    {
-                        let mut e = xz2::write::XzEncoder::new(out, compression_level);
-                        self.write_bytes(&mut e, bo);
-                        let mut out = e.finish().unwrap();
-                        let num_compressed_bytes = out.len() - prefix_size;
-                        write_size(out.as_mut_slice(), num_compressed_bytes);
-                        return out;
-                    }
-                }
-                Compressor::LZ4 => {
-                    #[cfg(feature = "lz4")]
-                    {
-                        // The following commented out code is a snippet for how to do this encoding
-                        // using the lz4 crate, although at the time of this writing it does not
-                        // support lz4 block format.
-                        //let mut e = lz4::EncoderBuilder::new()
-                        //    .level(compression_level)
-                        //    .checksum(lz4::ContentChecksum::NoChecksum)
-                        //    .build(out)
-                        //    .unwrap();
-                        //self.write_bytes(&mut e, bo);
-                        //let mut out = e.finish().0;
-
-                        // Initially write raw bytes to out.
-                        self.write_bytes(&mut out, bo);
-
-                        // Then compress them.
-                        // This should be done using a writer, but lz4_flex does not implement this at
-                        // this time, and it seems like the lz4 crate doesn't support lz4's block format.
-                        let mut out = lz4::compress(&out);
-
-                        let num_compressed_bytes = out.len() - prefix_size;
-                        write_size(out.as_mut_slice(), num_compressed_bytes);
-                        return out;
-                    }
-                }
-                Compressor::None => {}
-            }
+        if compressor == Compressor::None {
+            write_size(out.as_mut_slice(), num_uncompressed_bytes);
+            return out;
         }
 
-        self.write_bytes(&mut out, bo);
-        write_size(out.as_mut_slice(), num_uncompressed_bytes);
-
-        // Remove excess bytes.
-        out.shrink_to_fit();
-
-        out
+        // Compressed data is split into blocks, which are compressed independently.
+        // The data is then organized as [nb][nu][np][nc_1]...[nc_nb][Data]
+        // Where
+        //   [nb] = Number of blocks in the data array
+        //   [nu] = Block size before compression
+        //   [np] = Size of the last partial block before compression (zero if it is not needed)
+        //   [nc_i] = Size in bytes of block i after compression
+        // See https://vtk.org/Wiki/VTK_XML_Formats for details.
+        let blocks: Vec<Vec<u8>> = out[prefix_size..]
+            .chunks(COMPRESSION_BLOCK_SIZE)
+            .map(|block| compress_block(block, compressor, compression_level))
+            .collect();
+
+        let num_blocks = blocks.len();
+        let mut compressed = vec![0u8; prefix_size * (3 + num_blocks)];
+        compressed.reserve(blocks.iter().map(|b| b.len()).sum());
+        write_size(&mut compressed[..], num_blocks);
+        write_size(&mut compressed[prefix_size..], COMPRESSION_BLOCK_SIZE);
+        write_size(
+            &mut compressed[2 * prefix_size..],
+            num_uncompressed_bytes % COMPRESSION_BLOCK_SIZE,
+        );
+        for (i, block) in blocks.into_iter().enumerate() {
+            write_size(&mut compressed[(3 + i) * prefix_size..], block.len());
+            compressed.extend_from_slice(&block);
+        }
+        compressed
     }
 
     #[cfg(feature = "xml")]
@@ -1074,6 +1561,191 @@ impl DataArray {
     pub fn num_comp(&self) -> usize {
         self.elem.num_comp() as usize
     }
+
+    /// Returns an iterator over the tuples of `num_comp` values stored in this array.
+    ///
+    /// If `T` doesn't match the scalar type of the underlying buffer or the array has no
+    /// components, then `None` is returned. Trailing values that don't form a complete tuple are
+    /// skipped; use [`validate_tuples`](Self::validate_tuples) to detect them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::DataArray;
+    ///
+    /// let uvs = DataArray::tcoords("uv", 2).with_data(vec![0.0f32, 0.5, 1.0, 0.25]);
+    /// let tuples: Vec<_> = uvs.tuples::<f32>().unwrap().collect();
+    /// assert_eq!(tuples, vec![&[0.0, 0.5][..], &[1.0, 0.25][..]]);
+    /// ```
+    pub fn tuples<T: Scalar>(&self) -> Option<std::slice::ChunksExact<'_, T>> {
+        let num_comp = self.num_comp();
+        if num_comp == 0 {
+            return None;
+        }
+        self.data
+            .iter::<T>()
+            .map(|v| v.as_slice().chunks_exact(num_comp))
+    }
+
+    /// Checks that the data in this array forms a whole number of tuples.
+    ///
+    /// Texture coordinates are additionally required to have 1, 2 or 3 dimensions.
+    pub fn validate_tuples(&self) -> Result<(), Error> {
+        if let ElementType::TCoords(dim) = self.elem {
+            if !(1..=3).contains(&dim) {
+                return Err(Error::InvalidTCoordsDim {
+                    name: self.name.clone(),
+                    dim,
+                });
+            }
+        }
+        let num_comp = self.num_comp();
+        let len = self.len();
+        match len.checked_rem(num_comp) {
+            Some(0) => Ok(()),
+            None if len == 0 => Ok(()),
+            _ => Err(Error::TupleSizeMismatch {
+                name: self.name.clone(),
+                num_comp,
+                len,
+            }),
+        }
+    }
+
+    /// Converts this array into texture coordinates with `dim` dimensions.
+    ///
+    /// Each tuple is truncated or padded with zeros to `dim` components, so for instance 3D
+    /// coordinates can be turned into 2D UVs by dropping the last component. The number of
+    /// components of the original array is preserved regardless of its element type.
+    ///
+    /// An error is returned if `dim` is not 1, 2 or 3, or if the data doesn't form a whole
+    /// number of tuples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::{DataArray, ElementType};
+    ///
+    /// let uvw = DataArray::tcoords("uv", 3).with_data(vec![0.0f32, 0.5, 1.0, 1.0, 0.25, 0.0]);
+    /// let uv = uvw.with_tcoords_dim(2).unwrap();
+    /// assert_eq!(uv.elem, ElementType::TCoords(2));
+    /// assert_eq!(uv.data.into_vec::<f32>().unwrap(), vec![0.0, 0.5, 1.0, 0.25]);
+    /// ```
+    pub fn with_tcoords_dim(mut self, dim: u32) -> Result<Self, Error> {
+        if !(1..=3).contains(&dim) {
+            return Err(Error::InvalidTCoordsDim {
+                name: self.name,
+                dim,
+            });
+        }
+        self.validate_tuples()?;
+        let from = self.num_comp();
+        let to = dim as usize;
+        if from != to {
+            let num_elem = self.len().checked_div(from).unwrap_or(0);
+            match_buf!(&mut self.data, v => {
+                let old = std::mem::take(v);
+                v.reserve(num_elem * to);
+                for tuple in old.chunks_exact(from.max(1)) {
+                    let n = from.min(to);
+                    v.extend_from_slice(&tuple[..n]);
+                    v.resize(v.len() + to - n, Default::default());
+                }
+            });
+        }
+        self.elem = ElementType::TCoords(dim);
+        Ok(self)
+    }
+
+    /// Returns the data of this array in the given layout.
+    ///
+    /// The data is borrowed if it's already in the requested layout, which is always the case
+    /// for [`Layout::Interleaved`] and for arrays with a single component. Trailing values that
+    /// don't form a complete tuple are dropped when converting. Bit arrays are packed and thus
+    /// always borrowed unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::{DataArray, Layout};
+    ///
+    /// let vectors = DataArray::vectors("v").with_data(vec![1, 2, 3, 4, 5, 6]);
+    /// let planar = vectors.data_with_layout(Layout::Planar);
+    /// assert_eq!(planar.into_owned().into_vec::<i32>().unwrap(), vec![1, 4, 2, 5, 3, 6]);
+    /// ```
+    pub fn data_with_layout(&self, layout: Layout) -> Cow<'_, IOBuffer> {
+        let num_comp = self.num_comp();
+        if layout == Layout::Interleaved || num_comp <= 1 || matches!(self.data, IOBuffer::Bit(_)) {
+            return Cow::Borrowed(&self.data);
+        }
+        let num_elem = self.len() / num_comp;
+        Cow::Owned(match_buf!(&self.data, v => transpose(v, num_elem, num_comp).into()))
+    }
+
+    /// Returns the values of each component of this array in a separate buffer.
+    ///
+    /// This is useful for exporting arrays to column based formats.
+    pub fn split_components(&self) -> Vec<IOBuffer> {
+        let num_comp = self.num_comp();
+        let num_elem = self.len().checked_div(num_comp).unwrap_or(0);
+        let planar = self.data_with_layout(Layout::Planar);
+        (0..num_comp)
+            .map(|i| buffer_slice(&planar, i * num_elem..(i + 1) * num_elem))
+            .collect()
+    }
+
+    /// Replaces the data of this array with `data` given in the specified layout.
+    ///
+    /// Planar data is interleaved to match the layout of `DataArray`s. Data given in the
+    /// interleaved layout or with a single component is stored without copying, as is bit data.
+    ///
+    /// An error is returned if the data doesn't form a whole number of tuples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::{DataArray, Layout};
+    ///
+    /// let x = vec![1.0f32, 2.0];
+    /// let y = vec![3.0f32, 4.0];
+    /// let planar: Vec<f32> = x.into_iter().chain(y).collect();
+    /// let uvs = DataArray::tcoords("uv", 2).with_layout_data(planar, Layout::Planar).unwrap();
+    /// assert_eq!(uvs.data.into_vec::<f32>().unwrap(), vec![1.0, 3.0, 2.0, 4.0]);
+    /// ```
+    pub fn with_layout_data(
+        mut self,
+        data: impl Into<IOBuffer>,
+        layout: Layout,
+    ) -> Result<Self, Error> {
+        self.data = data.into();
+        self.validate_tuples()?;
+        let num_comp = self.num_comp();
+        if layout == Layout::Planar && num_comp > 1 && !matches!(self.data, IOBuffer::Bit(_)) {
+            let num_elem = self.len() / num_comp;
+            self.data = match_buf!(&self.data, v => transpose(v, num_comp, num_elem).into());
+        }
+        Ok(self)
+    }
+}
+
+/// The order in which the components of a multi-component array are stored.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// Components of each element are stored together, e.g. `x0 y0 z0 x1 y1 z1`.
+    ///
+    /// This is the array of structures (AoS) layout used by `DataArray`s.
+    Interleaved,
+    /// Each component is stored contiguously for all elements, e.g. `x0 x1 y0 y1 z0 z1`.
+    ///
+    /// This is the structure of arrays (SoA) layout.
+    Planar,
+}
+
+/// Transposes the row major `rows x cols` matrix stored in the beginning of `v`.
+fn transpose<T: Copy>(v: &[T], rows: usize, cols: usize) -> Vec<T> {
+    (0..cols)
+        .flat_map(|c| (0..rows).map(move |r| v[r * cols + c]))
+        .collect()
 }
 
 impl FieldArray {
@@ -1103,7 +1775,11 @@ impl FieldArray {
 /// If an XML `DataArray` is marked tagged by any variant other than `Generic` (or Legacy only types
 /// like `ColorScalars` and `LookupTable`) then it is considered active. If there is more than one
 /// tagged attribute with the same type, then the first one is considered active.
-#[derive(Clone, PartialEq, Debug)]
+///
+/// More element types may be added in the future. Arrays with an unrecognized element type are
+/// represented by the `Generic` variant, which preserves the number of components.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
+#[non_exhaustive]
 pub enum ElementType {
     /// Color Scalars represent floats in the range 0 to 1.
     ///
@@ -1180,7 +1856,11 @@ impl ElementType {
 }
 
 /// Data structure that stores a VTK attribute.
+///
+/// More attribute kinds may be added in the future. Use accessors like [`Attribute::name`] and
+/// [`Attribute::data_array`] to avoid depending on the exact set of variants.
 #[derive(Clone, PartialEq, Debug)]
+#[non_exhaustive]
 pub enum Attribute {
     /// A data array with any number of components.
     ///
@@ -1203,16 +1883,47 @@ pub enum Attribute {
         name: String,
         data_array: Vec<FieldArray>,
     },
+    /// An attribute section of a legacy file that `vtkio` doesn't recognize.
+    ///
+    /// Sections skipped with
+    /// [`ReadOptions::with_skip_unknown_sections`](crate::ReadOptions::with_skip_unknown_sections),
+    /// such as `GLOBAL_IDS` written by newer versions of VTK, are kept here so that they are
+    /// written back unchanged to legacy files. Other formats can't store them, so they are
+    /// dropped there. The content is not updated by operations that remove points or cells.
+    ///
+    /// This is a Legacy only attribute type.
+    Unknown {
+        /// The keyword starting the section, such as `GLOBAL_IDS`.
+        keyword: String,
+        /// The name following the keyword, which may be empty.
+        name: String,
+        /// The lines of the section, starting with its header.
+        raw: String,
+    },
 }
 
 impl Attribute {
     /// Get the name of this attribute.
     pub fn name(&self) -> &str {
         match self {
-            Attribute::Field { name, .. } => name.as_str(),
+            Attribute::Field { name, .. } | Attribute::Unknown { name, .. } => name.as_str(),
             Attribute::DataArray(data_array) => data_array.name.as_str(),
         }
     }
+    /// Returns the data array stored in this attribute, if any.
+    pub fn data_array(&self) -> Option<&DataArray> {
+        match self {
+            Attribute::DataArray(data_array) => Some(data_array),
+            Attribute::Field { .. } | Attribute::Unknown { .. } => None,
+        }
+    }
+    /// Returns the field arrays stored in this attribute if it is a field attribute.
+    pub fn field_arrays(&self) -> Option<&[FieldArray]> {
+        match self {
+            Attribute::Field { data_array, .. } => Some(data_array),
+            Attribute::DataArray(_) | Attribute::Unknown { .. } => None,
+        }
+    }
     /// Constructs a new scalars attribute with an associated lookup table.
     pub fn scalars_with_lookup(
         name: impl Into<String>,
@@ -1326,11 +2037,16 @@ impl Attribute {
     }
 }
 
-/// Point and cell attributes.
+/// Point and cell attributes, along with field data describing the data set as a whole.
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct Attributes {
     pub point: Vec<Attribute>,
     pub cell: Vec<Attribute>,
+    /// Global field data such as the `TimeValue` of the data set.
+    ///
+    /// In legacy files this corresponds to the `FIELD FieldData` block following the `DATASET`
+    /// keyword. It is currently ignored by the XML reader and writer.
+    pub field: Vec<FieldArray>,
 }
 
 impl Attributes {
@@ -1359,15 +2075,15 @@ impl Attributes {
 /// different cells, a secondary array of offsets is given to indicate the ends of each cell as an
 /// index into the vertex array. This struct represents a portion of the `Cells` element or one of
 /// `Verts`, `Lines`, `Strips` or `Polys`.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
 pub enum VertexNumbers {
     /// Specifies the vertex numbers for cells using a contiguous array of cell sizes and vertex
     /// indices.
     Legacy {
         /// Total number of cells contained in the `vertices` vector.
-        num_cells: u32,
+        num_cells: u64,
         /// Each cell in `vertices` is of the form: `n i_1 ... i_n`.
-        vertices: Vec<u32>,
+        vertices: Vec<u64>,
     },
     /// Specifies the vertex numbers for cells using an array of offsets into a connectivity array
     /// giving the actual vertex indices.
@@ -1376,9 +2092,8 @@ pub enum VertexNumbers {
         connectivity: Vec<u64>,
         /// The offsets into the connectivity array indicating the end of each cell.
         ///
-        /// This array may or may not have a leading zero. This depends on the input VTK file.
-        /// Some file (in particular Legacy type VTK files version 5 and higher) include a leading zero,
-        /// however, XML files typically do not.
+        /// Unlike the `OFFSETS` array in Legacy files of version 5.1 and higher, this array has no
+        /// leading zero.
         offsets: Vec<u64>,
     },
 }
@@ -1417,11 +2132,7 @@ impl VertexNumbers {
     /// Converts `self` into `Legacy` format.
     ///
     /// Returns a number of cells and vertices array pair as in the `Legacy` variant.
-    ///
-    /// # Panic
-    ///
-    /// Panics when the topology representation doesn't fit into 32-bit integer representation.
-    pub fn into_legacy(self) -> (u32, Vec<u32>) {
+    pub fn into_legacy(self) -> (u64, Vec<u64>) {
         match self {
             VertexNumbers::Legacy {
                 num_cells,
@@ -1434,16 +2145,15 @@ impl VertexNumbers {
                 let num_cells = offsets.len();
                 let num_verts = connectivity.len();
                 let mut vertices = Vec::with_capacity(num_verts + num_cells);
-                let mut i = 0u32;
+                let mut i = 0u64;
                 for off in offsets.into_iter() {
-                    let off = u32::try_from(off).unwrap();
                     vertices.push(off - i);
                     while i < off {
-                        vertices.push(u32::try_from(connectivity[i as usize]).unwrap());
+                        vertices.push(connectivity[i as usize]);
                         i += 1;
                     }
                 }
-                (u32::try_from(num_cells).unwrap(), vertices)
+                (num_cells as u64, vertices)
             }
         }
     }
@@ -1463,7 +2173,6 @@ impl VertexNumbers {
                 let mut n = -1i64;
                 let mut prev_off = 0;
                 for v in vertices {
-                    let v = u64::from(v);
                     if n > 0 {
                         connectivity.push(v);
                         n -= 1;
@@ -1489,7 +2198,7 @@ impl VertexNumbers {
 ///
 /// This struct corresponds to the `Cells` XML element or the CELLS and CELL_TYPES entries in the
 /// legacy VTK format.
-#[derive(Clone, PartialEq, Debug, Default)]
+#[derive(Clone, PartialEq, Debug, Default, Eq, Hash)]
 pub struct Cells {
     /// Cell vertices specified through offsets or simply as a contiguous array.
     ///
@@ -1514,110 +2223,280 @@ impl Cells {
     }
 }
 
+/// Defines the `CellType` enum along with the mapping between variants, VTK ids and names.
+macro_rules! impl_cell_types {
+    ($(#[$enum_attr:meta])* pub enum CellType {
+        $($variant:ident = $id:literal => $vtk_name:literal,)*
+    }) => {
+        $(#[$enum_attr])*
+        pub enum CellType {
+            $($variant,)*
+            /// A cell type with an id not known to `vtkio`, for instance one introduced in a newer
+            /// version of VTK.
+            Unknown(u32),
+        }
+
+        impl CellType {
+            /// Returns the VTK id of this cell type.
+            pub fn id(self) -> u32 {
+                match self {
+                    $(CellType::$variant => $id,)*
+                    CellType::Unknown(id) => id,
+                }
+            }
+
+            /// Returns the cell type with the given VTK id, or `CellType::Unknown` if the id is
+            /// not known.
+            pub fn from_id(id: u32) -> CellType {
+                match id {
+                    $($id => CellType::$variant,)*
+                    id => CellType::Unknown(id),
+                }
+            }
+
+            /// Returns the name of the constant identifying this cell type in VTK, for instance
+            /// `VTK_TETRA`.
+            pub fn vtk_name(self) -> Option<&'static str> {
+                match self {
+                    $(CellType::$variant => Some($vtk_name),)*
+                    CellType::Unknown(_) => None,
+                }
+            }
+        }
+    };
+}
+
+impl_cell_types! {
 /// This enum describes the types of Cells representable by VTK files.
 ///
 /// These are explicitly written in `UnstructuredGrid`s and some are referred to in `PolyData`
 /// datasets.  For more details on each of these types see, the [VTK file
 /// formats](https://lorensen.github.io/VTKExamples/site/VTKFileFormats/) documentation.
-#[derive(Copy, Clone, PartialEq, Debug, FromPrimitive)]
+///
+/// More cell types may be added in the future as they are introduced in VTK. Until then, cells
+/// with unrecognized ids are represented by `CellType::Unknown`.
+#[derive(Copy, Clone, PartialEq, Debug, Eq, Hash)]
+#[non_exhaustive]
 pub enum CellType {
     // Linear cells
-    Vertex = 1,
-    PolyVertex = 2,
-    Line = 3,
-    PolyLine = 4,
-    Triangle = 5,
-    TriangleStrip = 6,
-    Polygon = 7,
-    Pixel = 8,
-    Quad = 9,
-    Tetra = 10,
-    Voxel = 11,
-    Hexahedron = 12,
-    Wedge = 13,
-    Pyramid = 14,
+    EmptyCell = 0 => "VTK_EMPTY_CELL",
+    Vertex = 1 => "VTK_VERTEX",
+    PolyVertex = 2 => "VTK_POLY_VERTEX",
+    Line = 3 => "VTK_LINE",
+    PolyLine = 4 => "VTK_POLY_LINE",
+    Triangle = 5 => "VTK_TRIANGLE",
+    TriangleStrip = 6 => "VTK_TRIANGLE_STRIP",
+    Polygon = 7 => "VTK_POLYGON",
+    Pixel = 8 => "VTK_PIXEL",
+    Quad = 9 => "VTK_QUAD",
+    Tetra = 10 => "VTK_TETRA",
+    Voxel = 11 => "VTK_VOXEL",
+    Hexahedron = 12 => "VTK_HEXAHEDRON",
+    Wedge = 13 => "VTK_WEDGE",
+    Pyramid = 14 => "VTK_PYRAMID",
 
     // Quadratic, isoparametric cells
-    QuadraticEdge = 21,
-    QuadraticTriangle = 22,
-    QuadraticQuad = 23,
-    QuadraticTetra = 24,
-    QuadraticHexahedron = 25,
-    QuadraticWedge = 26,
-    QuadraticPyramid = 27,
-    BiquadraticQuad = 28,
-    TriquadraticHexahedron = 29,
-    QuadraticLinearQuad = 30,
-    QuadraticLinearWedge = 31,
-    BiquadraticQuadraticWedge = 32,
-    BiquadraticQuadraticHexahedron = 33,
-    BiquadraticTriangle = 34,
+    QuadraticEdge = 21 => "VTK_QUADRATIC_EDGE",
+    QuadraticTriangle = 22 => "VTK_QUADRATIC_TRIANGLE",
+    QuadraticQuad = 23 => "VTK_QUADRATIC_QUAD",
+    QuadraticTetra = 24 => "VTK_QUADRATIC_TETRA",
+    QuadraticHexahedron = 25 => "VTK_QUADRATIC_HEXAHEDRON",
+    QuadraticWedge = 26 => "VTK_QUADRATIC_WEDGE",
+    QuadraticPyramid = 27 => "VTK_QUADRATIC_PYRAMID",
+    BiquadraticQuad = 28 => "VTK_BIQUADRATIC_QUAD",
+    TriquadraticHexahedron = 29 => "VTK_TRIQUADRATIC_HEXAHEDRON",
+    QuadraticLinearQuad = 30 => "VTK_QUADRATIC_LINEAR_QUAD",
+    QuadraticLinearWedge = 31 => "VTK_QUADRATIC_LINEAR_WEDGE",
+    BiquadraticQuadraticWedge = 32 => "VTK_BIQUADRATIC_QUADRATIC_WEDGE",
+    BiquadraticQuadraticHexahedron = 33 => "VTK_BIQUADRATIC_QUADRATIC_HEXAHEDRON",
+    BiquadraticTriangle = 34 => "VTK_BIQUADRATIC_TRIANGLE",
 
     // Cubic, isoparametric cell
-    CubicLine = 35,
+    CubicLine = 35 => "VTK_CUBIC_LINE",
+
+    // Quadratic cells
+    QuadraticPolygon = 36 => "VTK_QUADRATIC_POLYGON",
+    TriquadraticPyramid = 37 => "VTK_TRIQUADRATIC_PYRAMID",
 
     // Special class of cells formed by convex group of points
-    ConvexPointSet = 41,
+    ConvexPointSet = 41 => "VTK_CONVEX_POINT_SET",
 
     // Polyhedron cell (consisting of polygonal faces)
-    Polyhedron = 42,
+    Polyhedron = 42 => "VTK_POLYHEDRON",
 
     // Higher order cells in parametric form
-    ParametricCurve = 51,
-    ParametricSurface = 52,
-    ParametricTriSurface = 53,
-    ParametricQuadSurface = 54,
-    ParametricTetraRegion = 55,
-    ParametricHexRegion = 56,
+    ParametricCurve = 51 => "VTK_PARAMETRIC_CURVE",
+    ParametricSurface = 52 => "VTK_PARAMETRIC_SURFACE",
+    ParametricTriSurface = 53 => "VTK_PARAMETRIC_TRI_SURFACE",
+    ParametricQuadSurface = 54 => "VTK_PARAMETRIC_QUAD_SURFACE",
+    ParametricTetraRegion = 55 => "VTK_PARAMETRIC_TETRA_REGION",
+    ParametricHexRegion = 56 => "VTK_PARAMETRIC_HEX_REGION",
 
     // Higher order cells
-    HigherOrderEdge = 60,
-    HigherOrderTriangle = 61,
-    HigherOrderQuad = 62,
-    HigherOrderPolygon = 63,
-    HigherOrderTetrahedron = 64,
-    HigherOrderWedge = 65,
-    HigherOrderPyramid = 66,
-    HigherOrderHexahedron = 67,
+    HigherOrderEdge = 60 => "VTK_HIGHER_ORDER_EDGE",
+    HigherOrderTriangle = 61 => "VTK_HIGHER_ORDER_TRIANGLE",
+    HigherOrderQuad = 62 => "VTK_HIGHER_ORDER_QUAD",
+    HigherOrderPolygon = 63 => "VTK_HIGHER_ORDER_POLYGON",
+    HigherOrderTetrahedron = 64 => "VTK_HIGHER_ORDER_TETRAHEDRON",
+    HigherOrderWedge = 65 => "VTK_HIGHER_ORDER_WEDGE",
+    HigherOrderPyramid = 66 => "VTK_HIGHER_ORDER_PYRAMID",
+    HigherOrderHexahedron = 67 => "VTK_HIGHER_ORDER_HEXAHEDRON",
 
     // Arbitrary order lagrange elements (formulated separated from generic higher order cells)
-    LagrangeCurve = 68,
-    LagrangeTriangle = 69,
-    LagrangeQuadrilateral = 70,
-    LagrangeTetrahedron = 71,
-    LagrangeHexahedron = 72,
-    LagrangeWedge = 73,
-    LagrangePyramid = 74,
+    LagrangeCurve = 68 => "VTK_LAGRANGE_CURVE",
+    LagrangeTriangle = 69 => "VTK_LAGRANGE_TRIANGLE",
+    LagrangeQuadrilateral = 70 => "VTK_LAGRANGE_QUADRILATERAL",
+    LagrangeTetrahedron = 71 => "VTK_LAGRANGE_TETRAHEDRON",
+    LagrangeHexahedron = 72 => "VTK_LAGRANGE_HEXAHEDRON",
+    LagrangeWedge = 73 => "VTK_LAGRANGE_WEDGE",
+    LagrangePyramid = 74 => "VTK_LAGRANGE_PYRAMID",
 
     // Arbitrary order bezier elements (formulated separated from generic higher order cells)
-    BezierCurve = 75,
-    BezierTriangle = 76,
-    BezierQuadrilateral = 77,
-    BezierTetrahedron = 78,
-    BezierHexahedron = 79,
-    BezierWedge = 80,
-    BezierPyramid = 81,
+    BezierCurve = 75 => "VTK_BEZIER_CURVE",
+    BezierTriangle = 76 => "VTK_BEZIER_TRIANGLE",
+    BezierQuadrilateral = 77 => "VTK_BEZIER_QUADRILATERAL",
+    BezierTetrahedron = 78 => "VTK_BEZIER_TETRAHEDRON",
+    BezierHexahedron = 79 => "VTK_BEZIER_HEXAHEDRON",
+    BezierWedge = 80 => "VTK_BEZIER_WEDGE",
+    BezierPyramid = 81 => "VTK_BEZIER_PYRAMID",
 }
-
-/// Point coordinates on a `RectilinearGrid` corresponding to `x`, `y` and `z` axes.
-///
-/// Coordinates for an extent are specified by the ordinate along each axis for each integer value
-/// in the extent’s range. This contains three `IOBuffer`s describing the ordinates along
-/// the x-y-z axes, respectively.
-///
-/// This struct corresponds to the `Coordinates` element in XML formats.
-#[derive(Clone, Debug, PartialEq, Default)]
-pub struct Coordinates {
-    /// Point coordinates along the `x` axis.
-    pub x: IOBuffer,
-    /// Point coordinates along the `y` axis.
-    pub y: IOBuffer,
-    /// Point coordinates along the `z` axis.
-    pub z: IOBuffer,
 }
 
-/// The extent of the structured object being represented in 3D space.
-#[derive(Clone, PartialEq, Debug)]
+/// Error returned when converting an id that doesn't correspond to a known cell type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownCellType(pub u32);
+
+impl fmt::Display for UnknownCellType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown cell type id: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCellType {}
+
+impl TryFrom<u32> for CellType {
+    type Error = UnknownCellType;
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        match CellType::from_id(id) {
+            CellType::Unknown(id) => Err(UnknownCellType(id)),
+            cell_type => Ok(cell_type),
+        }
+    }
+}
+
+impl TryFrom<u8> for CellType {
+    type Error = UnknownCellType;
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        CellType::try_from(u32::from(id))
+    }
+}
+
+impl From<CellType> for u32 {
+    fn from(cell_type: CellType) -> u32 {
+        cell_type.id()
+    }
+}
+
+/// Displays the name of the VTK constant for this cell type, e.g. `VTK_TETRA`.
+impl fmt::Display for CellType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.vtk_name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "VTK_UNKNOWN_CELL({})", self.id()),
+        }
+    }
+}
+
+impl CellType {
+    /// Returns the range of vertex counts valid for a cell of this type.
+    ///
+    /// Cells with a fixed number of vertices produce a range with a single value, while cells
+    /// with a variable number of vertices are only bounded from below.
+    pub fn num_verts_range(&self) -> RangeInclusive<usize> {
+        use CellType::*;
+        let exactly = |n| n..=n;
+        let at_least = |n| n..=usize::MAX;
+        match self {
+            EmptyCell => exactly(0),
+            Vertex => exactly(1),
+            PolyVertex => at_least(1),
+            Line => exactly(2),
+            PolyLine => at_least(2),
+            Triangle => exactly(3),
+            TriangleStrip | Polygon => at_least(3),
+            Pixel | Quad | Tetra => exactly(4),
+            Voxel | Hexahedron => exactly(8),
+            Wedge => exactly(6),
+            Pyramid => exactly(5),
+            QuadraticEdge => exactly(3),
+            QuadraticTriangle => exactly(6),
+            QuadraticQuad => exactly(8),
+            QuadraticTetra => exactly(10),
+            QuadraticHexahedron => exactly(20),
+            QuadraticWedge => exactly(15),
+            QuadraticPyramid => exactly(13),
+            BiquadraticQuad => exactly(9),
+            TriquadraticHexahedron => exactly(27),
+            QuadraticLinearQuad => exactly(6),
+            QuadraticLinearWedge => exactly(12),
+            BiquadraticQuadraticWedge => exactly(18),
+            BiquadraticQuadraticHexahedron => exactly(24),
+            BiquadraticTriangle => exactly(7),
+            CubicLine => exactly(4),
+            QuadraticPolygon => at_least(6),
+            TriquadraticPyramid => exactly(19),
+            ConvexPointSet | Polyhedron => at_least(1),
+            ParametricCurve
+            | ParametricSurface
+            | ParametricTriSurface
+            | ParametricQuadSurface
+            | ParametricTetraRegion
+            | ParametricHexRegion => at_least(1),
+            HigherOrderEdge | LagrangeCurve | BezierCurve => at_least(2),
+            HigherOrderTriangle | HigherOrderPolygon | LagrangeTriangle | BezierTriangle => {
+                at_least(3)
+            }
+            HigherOrderQuad
+            | HigherOrderTetrahedron
+            | LagrangeQuadrilateral
+            | LagrangeTetrahedron
+            | BezierQuadrilateral
+            | BezierTetrahedron => at_least(4),
+            HigherOrderPyramid | LagrangePyramid | BezierPyramid => at_least(5),
+            HigherOrderWedge | LagrangeWedge | BezierWedge => at_least(6),
+            HigherOrderHexahedron | LagrangeHexahedron | BezierHexahedron => at_least(8),
+            Unknown(_) => at_least(0),
+        }
+    }
+
+    /// Returns `true` if this is a linear cell type.
+    ///
+    /// Following VTK, all cell types other than the quadratic, cubic, parametric and higher order
+    /// cells are considered linear. Unknown cell types are assumed to be nonlinear.
+    pub fn is_linear(&self) -> bool {
+        matches!(self.id(), 0..=20 | 41 | 42)
+    }
+}
+
+/// Point coordinates on a `RectilinearGrid` corresponding to `x`, `y` and `z` axes.
+///
+/// Coordinates for an extent are specified by the ordinate along each axis for each integer value
+/// in the extent’s range. This contains three `IOBuffer`s describing the ordinates along
+/// the x-y-z axes, respectively.
+///
+/// This struct corresponds to the `Coordinates` element in XML formats.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Coordinates {
+    /// Point coordinates along the `x` axis.
+    pub x: IOBuffer,
+    /// Point coordinates along the `y` axis.
+    pub y: IOBuffer,
+    /// Point coordinates along the `z` axis.
+    pub z: IOBuffer,
+}
+
+/// The extent of the structured object being represented in 3D space.
+#[derive(Clone, PartialEq, Debug, Eq, Hash)]
 pub enum Extent {
     /// Legacy formats use dimensions to indicate the extent of a grid.
     Dims([u32; 3]),
@@ -1669,14 +2548,37 @@ impl Extent {
     ///
     /// then the equivalent extent in XML format is returned:
     ///
-    /// `[0..=nx, 0..=ny, 0..=nz]`
+    /// `[0..=nx-1, 0..=ny-1, 0..=nz-1]`
+    ///
+    /// since XML extents specify inclusive ranges of point indices.
     pub fn into_ranges(self) -> [RangeInclusive<i32>; 3] {
         match self {
-            Extent::Dims([nx, ny, nz]) => [0..=nx as i32, 0..=ny as i32, 0..=nz as i32],
+            Extent::Dims([nx, ny, nz]) => {
+                let rng = |n: u32| 0..=(n as i32 - 1);
+                [rng(nx), rng(ny), rng(nz)]
+            }
             Extent::Ranges(rng) => rng,
         }
     }
 
+    /// Extends this extent to also span `other`.
+    ///
+    /// The result is given as `Ranges` unless both extents are given as `Dims`.
+    pub fn extend(&mut self, other: Extent) {
+        *self = match (self.clone(), other) {
+            (Extent::Dims(a), Extent::Dims(b)) => {
+                Extent::Dims([a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])])
+            }
+            (a, b) => {
+                let a = a.into_ranges();
+                let b = b.into_ranges();
+                let union =
+                    |i: usize| (*a[i].start()).min(*b[i].start())..=(*a[i].end()).max(*b[i].end());
+                Extent::Ranges([union(0), union(1), union(2)])
+            }
+        };
+    }
+
     /// Compute the total number of points represented by this extent.
     pub fn num_points(&self) -> u64 {
         let [nx, ny, nz] = self.clone().into_dims();
@@ -1684,9 +2586,17 @@ impl Extent {
     }
 
     /// Compute the total number of cells represented by this extent.
+    ///
+    /// As in VTK, dimensions with a single point don't contribute to the number of cells, so
+    /// planar and linear extents produce 2D and 1D cells respectively. An empty extent has no
+    /// cells.
     pub fn num_cells(&self) -> u64 {
         let [nx, ny, nz] = self.clone().into_dims();
-        (nx as u64 - 1) * (ny as u64 - 1) * (nz as u64 - 1)
+        if nx == 0 || ny == 0 || nz == 0 {
+            return 0;
+        }
+        let cells = |n: u32| (n as u64 - 1).max(1);
+        cells(nx) * cells(ny) * cells(nz)
     }
 }
 
@@ -1738,6 +2648,11 @@ fn build_piece_path(path: impl AsRef<Path>, source_path: Option<&Path>) -> PathB
 }
 
 impl<P: PieceData> Piece<P> {
+    /// Returns `true` if this piece is stored inline or has been loaded from its source file.
+    pub fn is_loaded(&self) -> bool {
+        !matches!(self, Piece::Source(..))
+    }
+
     /// Converts `self` into a loaded piece if the current piece is only a `Source`.
     ///
     /// This function recursively loads any referenced pieces down the hierarchy.
@@ -1823,6 +2738,59 @@ pub struct ImageDataPiece {
     pub data: Attributes,
 }
 
+impl ImageDataPiece {
+    /// Returns `true` if the point at the given index is blanked.
+    ///
+    /// Blanking is stored in the `vtkGhostType` point attribute, so points are visible unless
+    /// that array is present and has the [`HIDDEN_POINT`] flag set.
+    pub fn is_point_blanked(&self, index: usize) -> bool {
+        is_flagged(&self.data.point, index, HIDDEN_POINT)
+    }
+
+    /// Returns `true` if the cell at the given index is blanked.
+    ///
+    /// Only the [`HIDDEN_CELL`] flag is checked here. Use `is_cell_visible` to also account for
+    /// blanked points.
+    pub fn is_cell_blanked(&self, index: usize) -> bool {
+        is_flagged(&self.data.cell, index, HIDDEN_CELL)
+    }
+
+    /// Returns `true` if the cell at the given index is neither blanked itself nor references
+    /// any blanked points.
+    pub fn is_cell_visible(&self, index: usize) -> bool {
+        is_cell_visible(&self.extent, &self.data, index)
+    }
+
+    /// Returns an iterator over the indices of all visible cells in this piece.
+    pub fn visible_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.extent.num_cells() as usize).filter(move |&i| self.is_cell_visible(i))
+    }
+
+    /// Blanks or unblanks the point at the given index.
+    ///
+    /// The `vtkGhostType` point attribute is added if it doesn't exist yet.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is not smaller than the number of points in the extent.
+    pub fn set_point_blanked(&mut self, index: usize, blanked: bool) {
+        let n = self.extent.num_points() as usize;
+        set_flag(&mut self.data.point, n, index, HIDDEN_POINT, blanked);
+    }
+
+    /// Blanks or unblanks the cell at the given index.
+    ///
+    /// The `vtkGhostType` cell attribute is added if it doesn't exist yet.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is not smaller than the number of cells in the extent.
+    pub fn set_cell_blanked(&mut self, index: usize, blanked: bool) {
+        let n = self.extent.num_cells() as usize;
+        set_flag(&mut self.data.cell, n, index, HIDDEN_CELL, blanked);
+    }
+}
+
 /// RectilinearGrid piece data.
 #[derive(Clone, Debug, PartialEq)]
 pub struct RectilinearGridPiece {
@@ -1847,6 +2815,252 @@ impl StructuredGridPiece {
     pub fn num_points(&self) -> usize {
         self.points.len() / 3
     }
+
+    /// Returns `true` if the point at the given index is blanked.
+    ///
+    /// Blanking is stored in the `vtkGhostType` point attribute, so points are visible unless
+    /// that array is present and has the [`HIDDEN_POINT`] flag set.
+    pub fn is_point_blanked(&self, index: usize) -> bool {
+        is_flagged(&self.data.point, index, HIDDEN_POINT)
+    }
+
+    /// Returns `true` if the cell at the given index is blanked.
+    ///
+    /// Only the [`HIDDEN_CELL`] flag is checked here. Use `is_cell_visible` to also account for
+    /// blanked points.
+    pub fn is_cell_blanked(&self, index: usize) -> bool {
+        is_flagged(&self.data.cell, index, HIDDEN_CELL)
+    }
+
+    /// Returns `true` if the cell at the given index is neither blanked itself nor references
+    /// any blanked points.
+    pub fn is_cell_visible(&self, index: usize) -> bool {
+        is_cell_visible(&self.extent, &self.data, index)
+    }
+
+    /// Returns an iterator over the indices of all visible cells in this piece.
+    pub fn visible_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.extent.num_cells() as usize).filter(move |&i| self.is_cell_visible(i))
+    }
+
+    /// Blanks or unblanks the point at the given index.
+    ///
+    /// The `vtkGhostType` point attribute is added if it doesn't exist yet.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is not smaller than the number of points in the extent.
+    pub fn set_point_blanked(&mut self, index: usize, blanked: bool) {
+        let n = self.extent.num_points() as usize;
+        set_flag(&mut self.data.point, n, index, HIDDEN_POINT, blanked);
+    }
+
+    /// Blanks or unblanks the cell at the given index.
+    ///
+    /// The `vtkGhostType` cell attribute is added if it doesn't exist yet.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `index` is not smaller than the number of cells in the extent.
+    pub fn set_cell_blanked(&mut self, index: usize, blanked: bool) {
+        let n = self.extent.num_cells() as usize;
+        set_flag(&mut self.data.cell, n, index, HIDDEN_CELL, blanked);
+    }
+}
+
+/// Name of the `u8` point and cell attribute VTK uses to flag ghost and blanked entities.
+pub const GHOST_ARRAY_NAME: &str = "vtkGhostType";
+/// Flag in the `vtkGhostType` point attribute marking a blanked point.
+pub const HIDDEN_POINT: u8 = 0x02;
+/// Flag in the `vtkGhostType` cell attribute marking a blanked cell.
+pub const HIDDEN_CELL: u8 = 0x08;
+
+/// Returns the flags in the ghost array among the given attributes, if any.
+pub(crate) fn ghost_flags(attribs: &[Attribute]) -> Option<&[u8]> {
+    attribs.iter().find_map(|attrib| match attrib {
+        Attribute::DataArray(DataArray {
+            name,
+            data: IOBuffer::U8(flags),
+            ..
+        }) if name == GHOST_ARRAY_NAME => Some(flags.as_slice()),
+        _ => None,
+    })
+}
+
+fn is_flagged(attribs: &[Attribute], index: usize, flag: u8) -> bool {
+    ghost_flags(attribs)
+        .and_then(|flags| flags.get(index))
+        .is_some_and(|&f| f & flag != 0)
+}
+
+/// Sets or clears `flag` for the entity at `index` in the ghost array, which is created or
+/// converted to `u8` and sized to `n` entries as needed.
+fn set_flag(attribs: &mut Vec<Attribute>, n: usize, index: usize, flag: u8, value: bool) {
+    assert!(
+        index < n,
+        "index {} out of bounds for {} entities",
+        index,
+        n
+    );
+    let pos = attribs
+        .iter()
+        .position(|attrib| matches!(attrib, Attribute::DataArray(a) if a.name == GHOST_ARRAY_NAME));
+    let pos = pos.unwrap_or_else(|| {
+        attribs.push(Attribute::scalars(GHOST_ARRAY_NAME, 1).with_data(Vec::<u8>::new()));
+        attribs.len() - 1
+    });
+    if let Attribute::DataArray(DataArray { data, .. }) = &mut attribs[pos] {
+        let mut flags = match std::mem::take(data) {
+            IOBuffer::U8(flags) => flags,
+            other => other.cast_into::<u8>().unwrap_or_default(),
+        };
+        flags.resize(flags.len().max(n), 0);
+        if value {
+            flags[index] |= flag;
+        } else {
+            flags[index] &= !flag;
+        }
+        *data = IOBuffer::U8(flags);
+    }
+}
+
+/// Checks the cell flag of a structured cell along with the flags of all of its points.
+fn is_cell_visible(extent: &Extent, data: &Attributes, index: usize) -> bool {
+    if is_flagged(&data.cell, index, HIDDEN_CELL) {
+        return false;
+    }
+    let point_flags = match ghost_flags(&data.point) {
+        Some(flags) => flags,
+        None => return true,
+    };
+    let dims = extent.clone().into_dims();
+    !structured_cell_points(dims, index)
+        .any(|p| point_flags.get(p).is_some_and(|&f| f & HIDDEN_POINT != 0))
+}
+
+/// Name of the point attribute recording the index each point had when provenance tracking was
+/// enabled.
+pub const ORIGINAL_POINT_IDS: &str = "vtkOriginalPointIds";
+/// Name of the cell attribute recording the index each cell had when provenance tracking was
+/// enabled.
+pub const ORIGINAL_CELL_IDS: &str = "vtkOriginalCellIds";
+/// Name of the point and cell attribute recording the index of the piece each entity came from
+/// in the most recent merge.
+pub const ORIGINAL_PIECE_IDS: &str = "vtkOriginalPieceIds";
+/// Name of the global `u8` field array holding the newline separated log of applied filters.
+pub const PROCESSING_LOG: &str = "ProcessingLog";
+
+impl Attributes {
+    /// Returns `true` if filters record their operations in the processing log.
+    ///
+    /// Tracking is enabled by `track_provenance` on `PolyDataPiece` and `UnstructuredGridPiece`.
+    pub fn is_tracking_provenance(&self) -> bool {
+        self.field.iter().any(|array| array.name == PROCESSING_LOG)
+    }
+
+    /// Returns the entries of the processing log, oldest first.
+    ///
+    /// The log is empty if provenance is not tracked.
+    pub fn processing_log(&self) -> Vec<String> {
+        let log = self.field.iter().find_map(|array| match &array.data {
+            IOBuffer::U8(log) if array.name == PROCESSING_LOG => Some(log),
+            _ => None,
+        });
+        match log {
+            Some(log) if !log.is_empty() => String::from_utf8_lossy(log)
+                .split('\n')
+                .map(String::from)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Appends an entry to the processing log if provenance is tracked.
+    ///
+    /// Line breaks in the entry are replaced by spaces.
+    pub fn log_operation(&mut self, entry: &str) {
+        let log = self
+            .field
+            .iter_mut()
+            .find_map(|array| match &mut array.data {
+                IOBuffer::U8(log) if array.name == PROCESSING_LOG => Some(log),
+                _ => None,
+            });
+        if let Some(log) = log {
+            if !log.is_empty() {
+                log.push(b'\n');
+            }
+            log.extend(entry.bytes().map(|b| if b == b'\n' { b' ' } else { b }));
+        }
+    }
+}
+
+/// Adds origin ids for points and cells that don't have them yet and starts the processing log.
+fn track_provenance(data: &mut Attributes, num_points: usize, num_cells: usize) {
+    let has_ids = |attribs: &[Attribute], name: &str| attribs.iter().any(|a| a.name() == name);
+    if !has_ids(&data.point, ORIGINAL_POINT_IDS) {
+        set_ids(&mut data.point, ORIGINAL_POINT_IDS, num_points, |i| {
+            i as i64
+        });
+    }
+    if !has_ids(&data.cell, ORIGINAL_CELL_IDS) {
+        set_ids(&mut data.cell, ORIGINAL_CELL_IDS, num_cells, |i| i as i64);
+    }
+    if !data.is_tracking_provenance() {
+        data.field
+            .push(FieldArray::new(PROCESSING_LOG, 1).with_data(Vec::<u8>::new()));
+    }
+}
+
+/// Sets the `i64` scalar attribute with the given name to `n` ids, replacing any existing one.
+fn set_ids(attribs: &mut Vec<Attribute>, name: &str, n: usize, id: impl Fn(usize) -> i64) {
+    attribs.retain(|attrib| attrib.name() != name);
+    attribs.push(Attribute::scalars(name, 1).with_data((0..n).map(id).collect::<Vec<i64>>()));
+}
+
+/// Records the piece of origin of all points and cells in pieces about to be merged, provided
+/// any of them tracks provenance, and logs the merge in the first piece.
+///
+/// `counts` gives the number of points and cells of a piece.
+fn track_merge_provenance<P>(
+    pieces: &mut [P],
+    counts: impl Fn(&P) -> (usize, usize),
+    data: impl Fn(&mut P) -> &mut Attributes,
+) {
+    if !pieces.iter_mut().any(|p| data(p).is_tracking_provenance()) {
+        return;
+    }
+    let num_pieces = pieces.len();
+    for (k, piece) in pieces.iter_mut().enumerate() {
+        let (num_points, num_cells) = counts(piece);
+        let data = data(piece);
+        track_provenance(data, num_points, num_cells);
+        set_ids(&mut data.point, ORIGINAL_PIECE_IDS, num_points, |_| {
+            k as i64
+        });
+        set_ids(&mut data.cell, ORIGINAL_PIECE_IDS, num_cells, |_| k as i64);
+    }
+    if let Some(first) = pieces.first_mut() {
+        data(first).log_operation(&format!("merge {} pieces", num_pieces));
+    }
+}
+
+/// Returns the indices of the points of the cell at `index` in a structured grid with the given
+/// point dimensions.
+///
+/// Dimensions with a single point don't contribute cells, so planar and linear grids give 2D and
+/// 1D cells with four and two points respectively.
+pub(crate) fn structured_cell_points(dims: [u32; 3], index: usize) -> impl Iterator<Item = usize> {
+    let [nx, ny, nz] = dims.map(|n| n as usize);
+    let cx = nx.saturating_sub(1).max(1);
+    let cy = ny.saturating_sub(1).max(1);
+    let (i, j, k) = (index % cx, index / cx % cy, index / (cx * cy));
+    let corners = |n: usize| if n > 1 { 1 } else { 0 };
+    (0..=corners(nz)).flat_map(move |dk| {
+        (0..=corners(ny)).flat_map(move |dj| {
+            (0..=corners(nx)).map(move |di| (i + di) + nx * ((j + dj) + ny * (k + dk)))
+        })
+    })
 }
 
 /// PolyData piece data.
@@ -1923,6 +3137,203 @@ impl PolyDataPiece {
     pub fn num_cells(&self) -> usize {
         self.num_verts() + self.num_lines() + self.num_polys() + self.num_strips()
     }
+
+    /// Returns `true` if the topology of this piece consists of vertices only.
+    ///
+    /// Such pieces can be converted into a [`PointCloud`](crate::point_cloud::PointCloud).
+    pub fn is_point_cloud(&self) -> bool {
+        self.verts.is_some() && self.num_lines() + self.num_polys() + self.num_strips() == 0
+    }
+
+    /// Merges the given pieces into a single piece.
+    ///
+    /// Points and point attributes are concatenated in order. Each of the vertex, line, polygon
+    /// and triangle strip topologies are concatenated separately, with vertex indices of each
+    /// piece shifted past the points of all preceding pieces, and cell attributes are reordered
+    /// accordingly. Merged topologies are given in `XML` format. Points shared between pieces are
+    /// not deduplicated, use [`merge_duplicate_points`](PolyDataPiece::merge_duplicate_points)
+    /// for that.
+    ///
+    /// Attributes are matched by name, and must be present in all pieces with the same element
+    /// and scalar types, otherwise an [`Error::IncompatiblePieces`] error is returned.
+    /// Global field data is taken from the first piece.
+    ///
+    /// If any piece [tracks provenance](PolyDataPiece::track_provenance), it is enabled for all
+    /// pieces, the index of the source piece is recorded in the [`ORIGINAL_PIECE_IDS`] point and
+    /// cell attributes, and the merge is appended to the log of the first piece.
+    pub fn merge(pieces: impl IntoIterator<Item = PolyDataPiece>) -> Result<PolyDataPiece, Error> {
+        let mut pieces: Vec<_> = pieces.into_iter().collect();
+        if pieces.len() < 2 {
+            return Ok(pieces.pop().unwrap_or_default());
+        }
+        track_merge_provenance(
+            &mut pieces,
+            |p| (p.num_points(), p.num_cells()),
+            |p| &mut p.data,
+        );
+
+        // Cell attributes are ordered by topology, so split them into one section per topology.
+        let mut cell_sections = Vec::new();
+        for topo in 0..4 {
+            for piece in pieces.iter() {
+                let counts = [
+                    piece.num_verts(),
+                    piece.num_lines(),
+                    piece.num_polys(),
+                    piece.num_strips(),
+                ];
+                let start: usize = counts[..topo].iter().sum();
+                let range = start..start + counts[topo];
+                cell_sections.push(slice_attributes(&piece.data.cell, range));
+            }
+        }
+        let mut cell_sections = cell_sections.into_iter();
+        let mut cell = cell_sections.next().unwrap_or_default();
+        for section in cell_sections {
+            append_attributes(&mut cell, section)?;
+        }
+
+        let mut topologies: [Vec<(Option<VertexNumbers>, u64)>; 4] = Default::default();
+        let mut pieces = pieces.into_iter();
+        let mut merged = pieces.next().unwrap();
+        let mut push_topologies = |piece: &mut PolyDataPiece, num_points: u64| {
+            topologies[0].push((piece.verts.take(), num_points));
+            topologies[1].push((piece.lines.take(), num_points));
+            topologies[2].push((piece.polys.take(), num_points));
+            topologies[3].push((piece.strips.take(), num_points));
+        };
+        push_topologies(&mut merged, 0);
+        for mut piece in pieces {
+            push_topologies(&mut piece, merged.num_points() as u64);
+            append_buffer(&mut merged.points, piece.points, "Points")?;
+            append_attributes(&mut merged.data.point, piece.data.point)?;
+        }
+        let [verts, lines, polys, strips] = topologies;
+        merged.verts = merge_topology(verts);
+        merged.lines = merge_topology(lines);
+        merged.polys = merge_topology(polys);
+        merged.strips = merge_topology(strips);
+        merged.data.cell = cell;
+        Ok(merged)
+    }
+
+    /// Merges points that are duplicates of each other according to the given tolerance.
+    ///
+    /// Each point is merged into the first point that it duplicates, so the result doesn't
+    /// depend on anything but the order of the points. Merged points are removed along with
+    /// their point attributes, and all topologies are updated to refer to the remaining points.
+    /// See [`Tolerance`] for details.
+    ///
+    /// Returns the number of removed points. Pieces whose points can't be converted to `f64`
+    /// are left untouched.
+    pub fn merge_duplicate_points(&mut self, tolerance: Tolerance) -> usize {
+        let PolyDataPiece {
+            points,
+            verts,
+            lines,
+            polys,
+            strips,
+            data,
+        } = self;
+        let topologies = verts
+            .iter_mut()
+            .chain(lines.iter_mut())
+            .chain(polys.iter_mut())
+            .chain(strips.iter_mut())
+            .map(|topo| (topo, &[][..]));
+        let removed = merge_duplicate_points(points, topologies, &mut data.point, tolerance);
+        data.log_operation(&format!(
+            "merge_duplicate_points {:?}: removed {} points",
+            tolerance, removed
+        ));
+        removed
+    }
+
+    /// Enables provenance tracking for this piece.
+    ///
+    /// Points and cells are numbered in the [`ORIGINAL_POINT_IDS`] and [`ORIGINAL_CELL_IDS`]
+    /// attributes unless already present, and an empty [`PROCESSING_LOG`] is added to the global
+    /// field data. Filters carry the ids along with the other attributes of the entities they
+    /// keep, and record their operations in the log, see [`Attributes::processing_log`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// let mut piece = PolyDataPiece {
+    ///     points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+    ///     ..Default::default()
+    /// };
+    /// piece.track_provenance();
+    /// piece.merge_duplicate_points(Tolerance::Absolute(0.0));
+    ///
+    /// let ids = piece.data.point.iter().find(|a| a.name() == ORIGINAL_POINT_IDS);
+    /// assert_eq!(ids.unwrap().data_array().unwrap().data, vec![0i64, 1].into());
+    /// assert_eq!(
+    ///     piece.data.processing_log(),
+    ///     vec!["merge_duplicate_points Absolute(0.0): removed 1 points"]
+    /// );
+    /// ```
+    pub fn track_provenance(&mut self) {
+        let (num_points, num_cells) = (self.num_points(), self.num_cells());
+        track_provenance(&mut self.data, num_points, num_cells);
+    }
+}
+
+/// Concatenates the given topologies, shifting each by the given number of points.
+///
+/// Returns `None` if none of the topologies are present.
+fn merge_topology(topologies: Vec<(Option<VertexNumbers>, u64)>) -> Option<VertexNumbers> {
+    if topologies.iter().all(|(topo, _)| topo.is_none()) {
+        return None;
+    }
+    let mut connectivity = Vec::new();
+    let mut offsets = Vec::new();
+    for (topo, num_points) in topologies {
+        if let Some(topo) = topo {
+            let num_verts = connectivity.len() as u64;
+            let (topo_connectivity, topo_offsets) = topo.into_xml();
+            connectivity.extend(topo_connectivity.into_iter().map(|v| v + num_points));
+            offsets.extend(topo_offsets.into_iter().map(|o| o + num_verts));
+        }
+    }
+    Some(VertexNumbers::XML {
+        connectivity,
+        offsets,
+    })
+}
+
+/// Returns copies of the given attributes restricted to the given range of elements.
+fn slice_attributes(attribs: &[Attribute], range: std::ops::Range<usize>) -> Vec<Attribute> {
+    let slice = |buf: &IOBuffer, num_comp: usize| {
+        let end = (range.end * num_comp).min(buf.len());
+        let start = (range.start * num_comp).min(end);
+        buffer_slice(buf, start..end)
+    };
+    // Unknown attributes can't be sliced, so they are dropped.
+    attribs
+        .iter()
+        .filter_map(|attrib| match attrib {
+            Attribute::DataArray(array) => Some(Attribute::DataArray(DataArray {
+                name: array.name.clone(),
+                elem: array.elem.clone(),
+                data: slice(&array.data, array.num_comp()),
+            })),
+            Attribute::Field { name, data_array } => Some(Attribute::Field {
+                name: name.clone(),
+                data_array: data_array
+                    .iter()
+                    .map(|array| FieldArray {
+                        name: array.name.clone(),
+                        elem: array.elem,
+                        data: slice(&array.data, array.num_comp()),
+                    })
+                    .collect(),
+            }),
+            Attribute::Unknown { .. } => None,
+        })
+        .collect()
 }
 
 /// UnstructuredGrid piece data.
@@ -1942,54 +3353,821 @@ impl UnstructuredGridPiece {
     pub fn num_points(&self) -> usize {
         self.points.len() / 3
     }
-}
 
-macro_rules! impl_piece_data {
-    ($data_set:ident, $piece:ident) => {
-        impl TryFrom<DataSet> for $piece {
-            type Error = Error;
-            fn try_from(data_set: DataSet) -> Result<Self, Error> {
-                Self::from_data_set(data_set, None)
+    /// Checks the cells of this piece for consistency.
+    ///
+    /// This cross-validates the number of cell types against the number of cells in
+    /// `cells.cell_verts`, the number of vertices of each cell against its type (see
+    /// [`CellType::num_verts_range`]), and that all vertex indices refer to existing points.
+    ///
+    /// An empty vector is returned if no issues were found. Vertex indices of `Polyhedron` cells
+    /// are not checked since the legacy format stores a face stream in their place.
+    pub fn check_cells(&self) -> Vec<CellIssue> {
+        self.check_cells_impl().0
+    }
+
+    /// Removes all cells reported by [`check_cells`](Self::check_cells).
+    ///
+    /// Cells that can't be decoded, cells without a type and types without a cell are dropped
+    /// along with any cell with an invalid number of vertices or out of bounds vertex indices.
+    /// The corresponding values of cell attributes are removed as well, provided they had one
+    /// value per cell to begin with. The representation of `cells.cell_verts` is preserved.
+    ///
+    /// Returns the issues that caused cells to be removed.
+    pub fn remove_invalid_cells(&mut self) -> Vec<CellIssue> {
+        let num_cells = self.cells.types.len();
+        let issues = self.remove_invalid_cells_impl();
+        let removed = num_cells.saturating_sub(self.cells.types.len());
+        self.data
+            .log_operation(&format!("remove_invalid_cells: removed {} cells", removed));
+        issues
+    }
+
+    fn remove_invalid_cells_impl(&mut self) -> Vec<CellIssue> {
+        let (issues, cells) = self.check_cells_impl();
+        if issues.is_empty() {
+            return issues;
+        }
+
+        let num_types = self.cells.types.len();
+        let num_cells = self.cells.cell_verts.num_cells();
+        let num_decoded = cells.len().min(num_types);
+        let mut keep: Vec<bool> = (0..num_cells.max(num_types))
+            .map(|i| i < num_decoded)
+            .collect();
+        for issue in issues.iter() {
+            match *issue {
+                CellIssue::VertexCountMismatch { cell, .. }
+                | CellIssue::IndexOutOfBounds { cell, .. } => keep[cell] = false,
+                _ => {}
             }
         }
-        impl PieceData for $piece {
-            fn from_data_set(data_set: DataSet, source_path: Option<&Path>) -> Result<Self, Error> {
-                match data_set {
-                    DataSet::$data_set { pieces, .. } => pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(Error::MissingPieceData)?
-                        .into_loaded_piece_data(source_path),
-                    _ => Err(Error::PieceDataMismatch),
+
+        let mut types = Vec::new();
+        let mut connectivity = Vec::new();
+        let mut offsets = Vec::new();
+        for ((verts, &cell_type), _) in cells
+            .into_iter()
+            .zip(self.cells.types.iter())
+            .zip(keep.iter())
+            .filter(|(_, &k)| k)
+        {
+            types.push(cell_type);
+            connectivity.extend_from_slice(&verts);
+            offsets.push(connectivity.len() as u64);
+        }
+
+        let cell_verts = VertexNumbers::XML {
+            connectivity,
+            offsets,
+        };
+        self.cells.cell_verts = match self.cells.cell_verts {
+            VertexNumbers::Legacy { .. } => {
+                let (num_cells, vertices) = cell_verts.into_legacy();
+                VertexNumbers::Legacy {
+                    num_cells,
+                    vertices,
+                }
+            }
+            VertexNumbers::XML { .. } => cell_verts,
+        };
+        self.cells.types = types;
+
+        // Cell attributes may be sized according to either count if they disagree.
+        let retain = |buf: &mut IOBuffer, num_comp: usize| {
+            let n = buf.len().checked_div(num_comp).unwrap_or(0);
+            if n * num_comp == buf.len() && (n == num_types || n == num_cells) {
+                retain_tuples(buf, num_comp, &keep[..n]);
+            }
+        };
+        for attrib in self.data.cell.iter_mut() {
+            match attrib {
+                Attribute::DataArray(array) => {
+                    let num_comp = array.num_comp();
+                    retain(&mut array.data, num_comp);
+                }
+                Attribute::Field { data_array, .. } => {
+                    for array in data_array.iter_mut() {
+                        let num_comp = array.num_comp();
+                        retain(&mut array.data, num_comp);
+                    }
                 }
+                Attribute::Unknown { .. } => {}
             }
         }
-    };
-}
 
-impl_piece_data!(ImageData, ImageDataPiece);
-impl_piece_data!(RectilinearGrid, RectilinearGridPiece);
-impl_piece_data!(StructuredGrid, StructuredGridPiece);
-impl_piece_data!(PolyData, PolyDataPiece);
-impl_piece_data!(UnstructuredGrid, UnstructuredGridPiece);
+        issues
+    }
 
-/// Dataset described in the file.
-///
-/// For 2D objects, `dims[2]` will be set to `1`. For 1D objects, `dims[1]` will also be `1`.
-/// This enum is designed to closely represent the data as it is stored in the vtk file.
-///
-/// The `extent` specified in the enum variants corresponds to the `WholeExtent` attribute.
-///
-/// Each `DataSet` is split into pieces for compatibility with XML formats. Legacy formats
-/// correspond to a data set with a single inline piece.
-#[derive(Clone, PartialEq, Debug)]
-pub enum DataSet {
-    /// Also referred to as `StructuredPoints` in Legacy format.
-    ImageData {
-        extent: Extent,
-        origin: [f32; 3],
-        spacing: [f32; 3],
-        meta: Option<Box<MetaData>>,
+    /// Merges the given pieces into a single piece.
+    ///
+    /// Points, cells and attributes are concatenated in order, with vertex indices of each piece
+    /// shifted past the points of all preceding pieces. The merged cells are given in `XML`
+    /// format. Points shared between pieces are not deduplicated, use
+    /// [`merge_duplicate_points`](UnstructuredGridPiece::merge_duplicate_points) for that.
+    ///
+    /// Attributes are matched by name, and must be present in all pieces with the same element
+    /// and scalar types, otherwise an [`Error::IncompatiblePieces`] error is returned.
+    /// Global field data is taken from the first piece.
+    ///
+    /// If any piece [tracks provenance](UnstructuredGridPiece::track_provenance), it is enabled
+    /// for all pieces, the index of the source piece is recorded in the [`ORIGINAL_PIECE_IDS`]
+    /// point and cell attributes, and the merge is appended to the log of the first piece.
+    pub fn merge(
+        pieces: impl IntoIterator<Item = UnstructuredGridPiece>,
+    ) -> Result<UnstructuredGridPiece, Error> {
+        let mut pieces: Vec<_> = pieces.into_iter().collect();
+        if pieces.len() > 1 {
+            track_merge_provenance(
+                &mut pieces,
+                |p| (p.num_points(), p.cells.types.len()),
+                |p| &mut p.data,
+            );
+        }
+        let mut pieces = pieces.into_iter();
+        let mut merged = match pieces.next() {
+            Some(piece) => piece,
+            None => {
+                return Ok(UnstructuredGridPiece {
+                    points: IOBuffer::default(),
+                    cells: Cells::default(),
+                    data: Attributes::new(),
+                })
+            }
+        };
+        let (mut connectivity, mut offsets) =
+            std::mem::take(&mut merged.cells.cell_verts).into_xml();
+        for piece in pieces {
+            let num_points = merged.num_points() as u64;
+            let num_verts = connectivity.len() as u64;
+            append_buffer(&mut merged.points, piece.points, "Points")?;
+            let (piece_connectivity, piece_offsets) = piece.cells.cell_verts.into_xml();
+            connectivity.extend(piece_connectivity.into_iter().map(|v| v + num_points));
+            offsets.extend(piece_offsets.into_iter().map(|o| o + num_verts));
+            merged.cells.types.extend(piece.cells.types);
+            append_attributes(&mut merged.data.point, piece.data.point)?;
+            append_attributes(&mut merged.data.cell, piece.data.cell)?;
+        }
+        merged.cells.cell_verts = VertexNumbers::XML {
+            connectivity,
+            offsets,
+        };
+        Ok(merged)
+    }
+
+    /// Merges points that are duplicates of each other according to the given tolerance.
+    ///
+    /// Each point is merged into the first point that it duplicates, so the result doesn't
+    /// depend on anything but the order of the points. Merged points are removed along with
+    /// their point attributes, and cells are updated to refer to the remaining points. See
+    /// [`Tolerance`] for details.
+    ///
+    /// Cells are not checked, so invalid cells should be removed first with
+    /// [`remove_invalid_cells`](UnstructuredGridPiece::remove_invalid_cells). The vertices of
+    /// `Polyhedron` cells are taken to be face streams, as in legacy files.
+    ///
+    /// Returns the number of removed points. Pieces whose points can't be converted to `f64`
+    /// are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// // Two triangles sharing an edge, with the shared points stored twice.
+    /// let mut piece = UnstructuredGridPiece {
+    ///     points: vec![
+    ///         0.0f64, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+    ///         1.0, 1e-9, 0.0, 1.0, 1.0, 0.0, 1e-9, 1.0, 0.0,
+    ///     ].into(),
+    ///     cells: Cells {
+    ///         cell_verts: VertexNumbers::XML {
+    ///             connectivity: vec![0, 1, 2, 3, 4, 5],
+    ///             offsets: vec![3, 6],
+    ///         },
+    ///         types: vec![CellType::Triangle; 2],
+    ///     },
+    ///     data: Attributes::new(),
+    /// };
+    ///
+    /// assert_eq!(piece.merge_duplicate_points(Tolerance::Absolute(1e-6)), 2);
+    /// assert_eq!(piece.num_points(), 4);
+    /// assert_eq!(piece.cells.cell_verts.into_xml().0, vec![0, 1, 2, 1, 3, 2]);
+    /// ```
+    pub fn merge_duplicate_points(&mut self, tolerance: Tolerance) -> usize {
+        let removed = merge_duplicate_points(
+            &mut self.points,
+            std::iter::once((&mut self.cells.cell_verts, self.cells.types.as_slice())),
+            &mut self.data.point,
+            tolerance,
+        );
+        self.data.log_operation(&format!(
+            "merge_duplicate_points {:?}: removed {} points",
+            tolerance, removed
+        ));
+        removed
+    }
+
+    /// Enables provenance tracking for this piece.
+    ///
+    /// Points and cells are numbered in the [`ORIGINAL_POINT_IDS`] and [`ORIGINAL_CELL_IDS`]
+    /// attributes unless already present, and an empty [`PROCESSING_LOG`] is added to the global
+    /// field data. Filters carry the ids along with the other attributes of the entities they
+    /// keep, and record their operations in the log, see [`Attributes::processing_log`].
+    pub fn track_provenance(&mut self) {
+        let (num_points, num_cells) = (self.num_points(), self.cells.types.len());
+        track_provenance(&mut self.data, num_points, num_cells);
+    }
+
+    /// Checks the cells of this piece, additionally returning the decoded vertices of each cell.
+    fn check_cells_impl(&self) -> (Vec<CellIssue>, Vec<Vec<u64>>) {
+        let mut issues = Vec::new();
+        let num_points = self.num_points();
+        let num_types = self.cells.types.len();
+        let num_cells = self.cells.cell_verts.num_cells();
+        if num_types != num_cells {
+            issues.push(CellIssue::CellCountMismatch {
+                num_types,
+                num_cells,
+            });
+        }
+
+        let (cells, malformed) = split_cells(&self.cells.cell_verts);
+        if let Some(cell) = malformed {
+            issues.push(CellIssue::MalformedConnectivity { cell });
+        }
+
+        for (cell, (verts, &cell_type)) in cells.iter().zip(self.cells.types.iter()).enumerate() {
+            let expected = cell_type.num_verts_range();
+            if !expected.contains(&verts.len()) {
+                issues.push(CellIssue::VertexCountMismatch {
+                    cell,
+                    cell_type,
+                    expected,
+                    found: verts.len(),
+                });
+            }
+            if cell_type == CellType::Polyhedron {
+                continue;
+            }
+            if let Some(&index) = verts.iter().find(|&&v| v >= num_points as u64) {
+                issues.push(CellIssue::IndexOutOfBounds {
+                    cell,
+                    index,
+                    num_points,
+                });
+            }
+        }
+        (issues, cells)
+    }
+}
+
+/// Returns a copy of the values of `buf` in the given range.
+fn buffer_slice(buf: &IOBuffer, range: std::ops::Range<usize>) -> IOBuffer {
+    use IOBuffer::*;
+    match buf {
+        Bit(v) => Bit(v[range].to_vec()),
+        U8(v) => U8(v[range].to_vec()),
+        I8(v) => I8(v[range].to_vec()),
+        U16(v) => U16(v[range].to_vec()),
+        I16(v) => I16(v[range].to_vec()),
+        U32(v) => U32(v[range].to_vec()),
+        I32(v) => I32(v[range].to_vec()),
+        U64(v) => U64(v[range].to_vec()),
+        I64(v) => I64(v[range].to_vec()),
+        F32(v) => F32(v[range].to_vec()),
+        F64(v) => F64(v[range].to_vec()),
+    }
+}
+
+/// Appends the contents of `src` to `dst`.
+///
+/// Returns an error naming the array if the buffers have different scalar types.
+pub(crate) fn append_buffer(dst: &mut IOBuffer, src: IOBuffer, name: &str) -> Result<(), Error> {
+    use IOBuffer::*;
+    match (dst, src) {
+        (Bit(a), Bit(b)) | (U8(a), U8(b)) => a.extend(b),
+        (I8(a), I8(b)) => a.extend(b),
+        (U16(a), U16(b)) => a.extend(b),
+        (I16(a), I16(b)) => a.extend(b),
+        (U32(a), U32(b)) => a.extend(b),
+        (I32(a), I32(b)) => a.extend(b),
+        (U64(a), U64(b)) => a.extend(b),
+        (I64(a), I64(b)) => a.extend(b),
+        (F32(a), F32(b)) => a.extend(b),
+        (F64(a), F64(b)) => a.extend(b),
+        _ => return Err(Error::IncompatiblePieces(name.to_string())),
+    }
+    Ok(())
+}
+
+/// Appends the attributes in `src` to the matching attributes in `dst`.
+///
+/// Attributes are matched by name and must have the same element and scalar types.
+fn append_attributes(dst: &mut [Attribute], mut src: Vec<Attribute>) -> Result<(), Error> {
+    for attrib in dst.iter_mut() {
+        let pos = src
+            .iter()
+            .position(|other| other.name() == attrib.name())
+            .ok_or_else(|| Error::IncompatiblePieces(attrib.name().to_string()))?;
+        match (attrib, src.swap_remove(pos)) {
+            (Attribute::DataArray(a), Attribute::DataArray(b)) if a.elem == b.elem => {
+                append_buffer(&mut a.data, b.data, &a.name)?
+            }
+            (
+                Attribute::Field { data_array: a, .. },
+                Attribute::Field {
+                    data_array: mut b, ..
+                },
+            ) => {
+                for array in a.iter_mut() {
+                    let pos = b
+                        .iter()
+                        .position(|other| other.name == array.name && other.elem == array.elem)
+                        .ok_or_else(|| Error::IncompatiblePieces(array.name.clone()))?;
+                    append_buffer(&mut array.data, b.swap_remove(pos).data, &array.name)?;
+                }
+                if let Some(array) = b.first() {
+                    return Err(Error::IncompatiblePieces(array.name.clone()));
+                }
+            }
+            (attrib, _) => return Err(Error::IncompatiblePieces(attrib.name().to_string())),
+        }
+    }
+    match src.first() {
+        Some(attrib) => Err(Error::IncompatiblePieces(attrib.name().to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Splits the given vertex numbers into the vertex indices of each cell.
+///
+/// Decoding stops at the first cell that is inconsistent with the rest of the vertex numbers, in
+/// which case the index of that cell is also returned.
+pub(crate) fn split_cells(cell_verts: &VertexNumbers) -> (Vec<Vec<u64>>, Option<usize>) {
+    let mut cells = Vec::new();
+    match cell_verts {
+        VertexNumbers::Legacy {
+            num_cells,
+            vertices,
+        } => {
+            let mut rest = vertices.as_slice();
+            while let Some((&n, tail)) = rest.split_first() {
+                if cells.len() == *num_cells as usize || tail.len() < n as usize {
+                    return (cells.clone(), Some(cells.len()));
+                }
+                let (verts, tail) = tail.split_at(n as usize);
+                cells.push(verts.to_vec());
+                rest = tail;
+            }
+            if cells.len() < *num_cells as usize {
+                return (cells.clone(), Some(cells.len()));
+            }
+        }
+        VertexNumbers::XML {
+            connectivity,
+            offsets,
+        } => {
+            let mut begin = 0;
+            for &end in offsets.iter() {
+                if end < begin || end > connectivity.len() as u64 {
+                    return (cells.clone(), Some(cells.len()));
+                }
+                cells.push(connectivity[begin as usize..end as usize].to_vec());
+                begin = end;
+            }
+            if begin != connectivity.len() as u64 {
+                return (cells.clone(), Some(cells.len()));
+            }
+        }
+    }
+    (cells, None)
+}
+
+/// Retains only the tuples of `num_comp` values in `buf` whose entry in `keep` is `true`.
+///
+/// `buf` must contain exactly one tuple per entry in `keep`.
+fn retain_tuples(buf: &mut IOBuffer, num_comp: usize, keep: &[bool]) {
+    debug_assert_eq!(buf.len(), num_comp * keep.len());
+    match_buf!(buf, v => {
+        let mut i = 0;
+        v.retain(|_| {
+            i += 1;
+            keep[(i - 1) / num_comp]
+        });
+    });
+}
+
+/// Merges duplicate points, updating the given topologies and point attributes.
+///
+/// Each topology is given along with the types of its cells, which may be empty if the cells
+/// have no face streams. Returns the number of removed points.
+fn merge_duplicate_points<'a>(
+    points: &mut IOBuffer,
+    topologies: impl IntoIterator<Item = (&'a mut VertexNumbers, &'a [CellType])>,
+    point_data: &mut [Attribute],
+    tolerance: Tolerance,
+) -> usize {
+    let coords = match points.cast_into::<f64>() {
+        Some(coords) => coords,
+        None => return 0,
+    };
+    let num_points = coords.len() / 3;
+    let representatives = tolerance.representatives(&coords[..3 * num_points]);
+
+    // Map each point to the new index of its representative.
+    let keep: Vec<bool> = representatives
+        .iter()
+        .enumerate()
+        .map(|(i, &rep)| i == rep)
+        .collect();
+    let mut new_index = vec![0; num_points];
+    let mut num_kept = 0;
+    for (i, &rep) in representatives.iter().enumerate() {
+        if i == rep {
+            new_index[i] = num_kept;
+            num_kept += 1;
+        } else {
+            new_index[i] = new_index[rep];
+        }
+    }
+    if num_kept == num_points {
+        return 0;
+    }
+
+    let remap = |v: &mut u64| {
+        if let Some(&index) = new_index.get(*v as usize) {
+            *v = index as u64;
+        }
+    };
+    // Polyhedra are given as the number of faces followed by the size and vertices of each face.
+    let remap_cell = |verts: &mut [u64], cell_type: Option<&CellType>| {
+        if cell_type != Some(&CellType::Polyhedron) {
+            verts.iter_mut().for_each(remap);
+            return;
+        }
+        let mut faces = verts
+            .split_first_mut()
+            .map_or(&mut [][..], |(_, faces)| faces);
+        while let Some((&mut n, tail)) = faces.split_first_mut() {
+            let n = (n as usize).min(tail.len());
+            let (face, tail) = tail.split_at_mut(n);
+            face.iter_mut().for_each(remap);
+            faces = tail;
+        }
+    };
+    for (topo, types) in topologies {
+        match topo {
+            VertexNumbers::Legacy { vertices, .. } => {
+                let mut rest = vertices.as_mut_slice();
+                let mut cell = 0;
+                while let Some((&mut n, tail)) = rest.split_first_mut() {
+                    let n = (n as usize).min(tail.len());
+                    let (verts, tail) = tail.split_at_mut(n);
+                    remap_cell(verts, types.get(cell));
+                    rest = tail;
+                    cell += 1;
+                }
+            }
+            VertexNumbers::XML {
+                connectivity,
+                offsets,
+            } => {
+                let mut begin = 0;
+                for (cell, &end) in offsets.iter().enumerate() {
+                    let end = (end as usize).clamp(begin, connectivity.len());
+                    remap_cell(&mut connectivity[begin..end], types.get(cell));
+                    begin = end;
+                }
+                connectivity[begin..].iter_mut().for_each(remap);
+            }
+        }
+    }
+
+    if points.len() == 3 * num_points {
+        retain_tuples(points, 3, &keep);
+    }
+    let retain = |buf: &mut IOBuffer, num_comp: usize| {
+        if buf.len() == num_comp * num_points {
+            retain_tuples(buf, num_comp, &keep);
+        }
+    };
+    for attrib in point_data.iter_mut() {
+        match attrib {
+            Attribute::DataArray(array) => {
+                let num_comp = array.num_comp();
+                retain(&mut array.data, num_comp);
+            }
+            Attribute::Field { data_array, .. } => {
+                for array in data_array.iter_mut() {
+                    let num_comp = array.num_comp();
+                    retain(&mut array.data, num_comp);
+                }
+            }
+            Attribute::Unknown { .. } => {}
+        }
+    }
+    num_points - num_kept
+}
+
+/// Converts color scalars to floats in `[0, 1]`, as stored in ASCII legacy files.
+///
+/// Integer values are mapped from the range `[0, 255]`, while floats are kept as is. The buffer is
+/// borrowed if no conversion is needed.
+pub(crate) fn color_scalars_to_f32(buf: &IOBuffer) -> Cow<'_, IOBuffer> {
+    match buf {
+        IOBuffer::F32(_) => Cow::Borrowed(buf),
+        IOBuffer::F64(v) => Cow::Owned(v.iter().map(|&x| x as f32).collect()),
+        _ => match buf.cast_into::<f64>() {
+            Some(v) => Cow::Owned(v.into_iter().map(|x| (x / 255.0) as f32).collect()),
+            None => Cow::Borrowed(buf),
+        },
+    }
+}
+
+/// Converts the colors of a lookup table to floats in `[0, 1]`, as used in the model.
+///
+/// Binary legacy files store lookup tables as unsigned chars, which are converted when read.
+pub(crate) fn lookup_table_to_f32(mut buf: IOBuffer) -> IOBuffer {
+    if let Cow::Owned(floats) = color_scalars_to_f32(&buf) {
+        buf = floats;
+    }
+    buf
+}
+
+/// Converts color scalars to unsigned chars, as stored in binary legacy files.
+///
+/// Floats are mapped from the range `[0, 1]` and integers are clamped to `[0, 255]`. The buffer is
+/// borrowed if no conversion is needed.
+pub(crate) fn color_scalars_to_u8(buf: &IOBuffer) -> Cow<'_, IOBuffer> {
+    let clamp = |x: f64| x.clamp(0.0, 255.0).round() as u8;
+    match buf {
+        IOBuffer::U8(_) => Cow::Borrowed(buf),
+        IOBuffer::F32(v) => Cow::Owned(v.iter().map(|&x| clamp(f64::from(x) * 255.0)).collect()),
+        IOBuffer::F64(v) => Cow::Owned(v.iter().map(|&x| clamp(x * 255.0)).collect()),
+        _ => match buf.cast_into::<f64>() {
+            Some(v) => Cow::Owned(v.into_iter().map(clamp).collect()),
+            None => Cow::Borrowed(buf),
+        },
+    }
+}
+
+/// Resizes `buf` to hold exactly `len` values.
+///
+/// Extra values are removed from the end. Missing values are filled in by repeating the last
+/// complete tuple of `num_comp` values if `repeat_last` is `true`, or with zeros otherwise.
+pub(crate) fn resize_tuples(buf: &mut IOBuffer, len: usize, num_comp: usize, repeat_last: bool) {
+    match_buf!(buf, v => {
+        if v.len() >= len {
+            v.truncate(len);
+            return;
+        }
+        let num_comp = num_comp.max(1);
+        v.truncate(v.len() / num_comp * num_comp);
+        if repeat_last && !v.is_empty() {
+            let last = v[v.len() - num_comp..].to_vec();
+            while v.len() < len {
+                let n = (len - v.len()).min(num_comp);
+                v.extend_from_slice(&last[..n]);
+            }
+        } else {
+            v.resize(len, Default::default());
+        }
+    });
+}
+
+/// The criterion used to decide whether two points are duplicates when merging points.
+///
+/// The right choice depends on the scale of the data: a fixed distance suits data in known units,
+/// such as CAD models in millimeters, while a relative tolerance adapts to the extent of the data,
+/// for instance at the nanometer scale. Grid quantization produces the same result regardless of
+/// the order of the points, but may keep points that are very close on opposite sides of a grid
+/// plane.
+///
+/// Points are processed in order, and each point is merged into the first preceding point that
+/// it duplicates. Points that have been merged don't absorb any further points, so a chain of
+/// points closer than the tolerance is not collapsed into a single point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tolerance {
+    /// Points at most the given distance apart are duplicates.
+    Absolute(f64),
+    /// Points at most the given fraction of the diagonal of the bounding box of all points apart
+    /// are duplicates.
+    Relative(f64),
+    /// Points in the same cell of a grid with the given spacing and a vertex at the origin are
+    /// duplicates.
+    Grid(f64),
+}
+
+impl Tolerance {
+    /// Returns the index of the point each of the given points is merged into.
+    ///
+    /// Points that are kept are mapped to themselves, and no point is mapped to a point that is
+    /// itself merged into another point. A tolerance or spacing of zero only merges identical
+    /// points.
+    fn representatives(self, coords: &[f64]) -> Vec<usize> {
+        let points: Vec<[f64; 3]> = coords.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+        let (distance, grid) = match self {
+            Tolerance::Absolute(distance) => (Some(distance), distance),
+            Tolerance::Relative(fraction) => {
+                let distance = fraction * bounding_box_diagonal(&points);
+                (Some(distance), distance)
+            }
+            Tolerance::Grid(spacing) => (None, spacing),
+        };
+
+        // Points are binned into grid cells. Exact coordinates are used as keys for zero or
+        // invalid spacings, where normalizing -0.0 lets it match 0.0.
+        let key = |p: &[f64; 3]| -> [i64; 3] {
+            if grid > 0.0 {
+                p.map(|x| (x / grid).floor() as i64)
+            } else {
+                p.map(|x| (x + 0.0).to_bits() as i64)
+            }
+        };
+        let mut bins: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut representatives = Vec::with_capacity(points.len());
+        for (i, p) in points.iter().enumerate() {
+            let k = key(p);
+            let found = match distance {
+                // Duplicates are at most one cell away when the spacing matches the distance.
+                Some(distance) if grid > 0.0 => NEIGHBOR_OFFSETS
+                    .iter()
+                    .flat_map(|offset| {
+                        let neighbor = [0, 1, 2].map(|d| k[d].wrapping_add(offset[d]));
+                        bins.get(&neighbor).into_iter().flatten().copied()
+                    })
+                    .filter(|&j| distance_sq(p, &points[j]) <= distance * distance)
+                    .min(),
+                _ => bins.get(&k).and_then(|bin| bin.first().copied()),
+            };
+            match found {
+                Some(j) => representatives.push(j),
+                None => {
+                    bins.entry(k).or_default().push(i);
+                    representatives.push(i);
+                }
+            }
+        }
+        representatives
+    }
+}
+
+/// Offsets of a grid cell and its 26 neighbors.
+const NEIGHBOR_OFFSETS: [[i64; 3]; 27] = {
+    let mut offsets = [[0; 3]; 27];
+    let mut i = 0;
+    while i < 27 {
+        offsets[i] = [
+            (i % 3) as i64 - 1,
+            ((i / 3) % 3) as i64 - 1,
+            (i / 9) as i64 - 1,
+        ];
+        i += 1;
+    }
+    offsets
+};
+
+fn distance_sq(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+/// Returns the length of the diagonal of the bounding box of the given points.
+fn bounding_box_diagonal(points: &[[f64; 3]]) -> f64 {
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for p in points {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    if points.is_empty() {
+        return 0.0;
+    }
+    distance_sq(&min, &max).sqrt()
+}
+
+/// An inconsistency in the cells of an unstructured grid.
+///
+/// These are reported by [`UnstructuredGridPiece::check_cells`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CellIssue {
+    /// The number of cell types doesn't match the number of cells in the vertex numbers.
+    CellCountMismatch { num_types: usize, num_cells: usize },
+    /// The vertex numbers are inconsistent starting with the given cell.
+    ///
+    /// This cell and all subsequent cells can't be decoded.
+    MalformedConnectivity { cell: usize },
+    /// The number of vertices in a cell is not valid for its type.
+    VertexCountMismatch {
+        cell: usize,
+        cell_type: CellType,
+        expected: RangeInclusive<usize>,
+        found: usize,
+    },
+    /// A cell refers to a point that doesn't exist.
+    IndexOutOfBounds {
+        cell: usize,
+        index: u64,
+        num_points: usize,
+    },
+}
+
+impl fmt::Display for CellIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CellIssue::CellCountMismatch {
+                num_types,
+                num_cells,
+            } => write!(
+                f,
+                "Found {} cell types for {} cells; remove the extra entries or add the missing ones",
+                num_types, num_cells
+            ),
+            CellIssue::MalformedConnectivity { cell } => write!(
+                f,
+                "Vertex numbers are malformed starting at cell {}; subsequent cells are ignored",
+                cell
+            ),
+            CellIssue::VertexCountMismatch {
+                cell,
+                cell_type,
+                expected,
+                found,
+            } => {
+                write!(f, "Cell {} of type {:?} has {} vertices, but expected ", cell, cell_type, found)?;
+                if expected.start() == expected.end() {
+                    write!(f, "{}", expected.start())
+                } else {
+                    write!(f, "at least {}", expected.start())
+                }
+            }
+            CellIssue::IndexOutOfBounds {
+                cell,
+                index,
+                num_points,
+            } => write!(
+                f,
+                "Cell {} refers to point {}, but there are only {} points",
+                cell, index, num_points
+            ),
+        }
+    }
+}
+
+macro_rules! impl_piece_data {
+    ($data_set:ident, $piece:ident) => {
+        impl TryFrom<DataSet> for $piece {
+            type Error = Error;
+            fn try_from(data_set: DataSet) -> Result<Self, Error> {
+                Self::from_data_set(data_set, None)
+            }
+        }
+        impl PieceData for $piece {
+            fn from_data_set(data_set: DataSet, source_path: Option<&Path>) -> Result<Self, Error> {
+                match data_set {
+                    DataSet::$data_set { pieces, .. } => pieces
+                        .into_iter()
+                        .next()
+                        .ok_or(Error::MissingPieceData)?
+                        .into_loaded_piece_data(source_path),
+                    _ => Err(Error::PieceDataMismatch),
+                }
+            }
+        }
+    };
+}
+
+impl_piece_data!(ImageData, ImageDataPiece);
+impl_piece_data!(RectilinearGrid, RectilinearGridPiece);
+impl_piece_data!(StructuredGrid, StructuredGridPiece);
+impl_piece_data!(PolyData, PolyDataPiece);
+impl_piece_data!(UnstructuredGrid, UnstructuredGridPiece);
+
+/// Dataset described in the file.
+///
+/// For 2D objects, `dims[2]` will be set to `1`. For 1D objects, `dims[1]` will also be `1`.
+/// This enum is designed to closely represent the data as it is stored in the vtk file.
+///
+/// The `extent` specified in the enum variants corresponds to the `WholeExtent` attribute.
+///
+/// Each `DataSet` is split into pieces for compatibility with XML formats. Legacy formats
+/// correspond to a data set with a single inline piece.
+///
+/// More data set types may be added in the future. Use [`DataSet::accept`] with a
+/// [`DataSetVisitor`] to handle every type of data set, or accessors like
+/// [`DataSet::num_pieces`] to avoid depending on the exact set of variants.
+#[derive(Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum DataSet {
+    /// Also referred to as `StructuredPoints` in Legacy format.
+    ImageData {
+        extent: Extent,
+        origin: [f32; 3],
+        spacing: [f32; 3],
+        meta: Option<Box<MetaData>>,
         pieces: Vec<Piece<ImageDataPiece>>,
     },
     StructuredGrid {
@@ -2027,6 +4205,581 @@ impl DataSet {
     pub fn inline(p: impl Into<DataSet>) -> DataSet {
         p.into()
     }
+
+    /// Construct a data set with multiple inline pieces.
+    ///
+    /// The whole extent of structured data sets spans the extents of all pieces. An error is
+    /// returned if no pieces are given, or if the pieces are of different types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// let triangle = |z: f32| PolyDataPiece {
+    ///     points: vec![0.0, 0.0, z, 1.0, 0.0, z, 0.0, 1.0, z].into(),
+    ///     polys: Some(VertexNumbers::XML {
+    ///         connectivity: vec![0, 1, 2],
+    ///         offsets: vec![3],
+    ///     }),
+    ///     ..Default::default()
+    /// };
+    /// let data_set = DataSet::from_pieces(vec![triangle(0.0), triangle(1.0)]).unwrap();
+    /// assert_eq!(data_set.num_pieces(), 2);
+    /// ```
+    pub fn from_pieces<P: Into<DataSet>>(
+        pieces: impl IntoIterator<Item = P>,
+    ) -> Result<DataSet, Error> {
+        let mut pieces = pieces.into_iter();
+        let mut data_set = pieces.next().ok_or(Error::MissingPieceData)?.into();
+        for piece in pieces {
+            data_set.append_pieces(piece.into())?;
+        }
+        Ok(data_set)
+    }
+
+    /// Appends all pieces of `other` to this data set.
+    ///
+    /// Both data sets must have the same type, and image data must have the same origin and
+    /// spacing, otherwise [`Error::PieceDataMismatch`] is returned. The whole extent of structured
+    /// data sets is extended to span both data sets. The meta data of `other` is discarded.
+    pub fn append_pieces(&mut self, other: DataSet) -> Result<(), Error> {
+        match (self, other) {
+            (
+                DataSet::ImageData {
+                    extent,
+                    origin,
+                    spacing,
+                    pieces,
+                    ..
+                },
+                DataSet::ImageData {
+                    extent: other_extent,
+                    origin: other_origin,
+                    spacing: other_spacing,
+                    pieces: other_pieces,
+                    ..
+                },
+            ) if *origin == other_origin && *spacing == other_spacing => {
+                extent.extend(other_extent);
+                pieces.extend(other_pieces);
+            }
+            (
+                DataSet::StructuredGrid { extent, pieces, .. },
+                DataSet::StructuredGrid {
+                    extent: other_extent,
+                    pieces: other_pieces,
+                    ..
+                },
+            ) => {
+                extent.extend(other_extent);
+                pieces.extend(other_pieces);
+            }
+            (
+                DataSet::RectilinearGrid { extent, pieces, .. },
+                DataSet::RectilinearGrid {
+                    extent: other_extent,
+                    pieces: other_pieces,
+                    ..
+                },
+            ) => {
+                extent.extend(other_extent);
+                pieces.extend(other_pieces);
+            }
+            (
+                DataSet::UnstructuredGrid { pieces, .. },
+                DataSet::UnstructuredGrid {
+                    pieces: other_pieces,
+                    ..
+                },
+            ) => pieces.extend(other_pieces),
+            (
+                DataSet::PolyData { pieces, .. },
+                DataSet::PolyData {
+                    pieces: other_pieces,
+                    ..
+                },
+            ) => pieces.extend(other_pieces),
+            _ => return Err(Error::PieceDataMismatch),
+        }
+        Ok(())
+    }
+
+    /// Returns the number of pieces in this data set.
+    ///
+    /// Field data sets have no pieces.
+    pub fn num_pieces(&self) -> usize {
+        match self {
+            DataSet::ImageData { pieces, .. } => pieces.len(),
+            DataSet::StructuredGrid { pieces, .. } => pieces.len(),
+            DataSet::RectilinearGrid { pieces, .. } => pieces.len(),
+            DataSet::UnstructuredGrid { pieces, .. } => pieces.len(),
+            DataSet::PolyData { pieces, .. } => pieces.len(),
+            DataSet::Field { .. } => 0,
+        }
+    }
+
+    /// Splits this data set into data sets holding a single piece each.
+    ///
+    /// The whole extent, origin, spacing and meta data are copied to each data set. Field data
+    /// sets have no pieces, so an empty `Vec` is returned for them.
+    pub fn into_pieces(self) -> Vec<DataSet> {
+        match self {
+            DataSet::ImageData {
+                extent,
+                origin,
+                spacing,
+                meta,
+                pieces,
+            } => pieces
+                .into_iter()
+                .map(|piece| DataSet::ImageData {
+                    extent: extent.clone(),
+                    origin,
+                    spacing,
+                    meta: meta.clone(),
+                    pieces: vec![piece],
+                })
+                .collect(),
+            DataSet::StructuredGrid {
+                extent,
+                meta,
+                pieces,
+            } => pieces
+                .into_iter()
+                .map(|piece| DataSet::StructuredGrid {
+                    extent: extent.clone(),
+                    meta: meta.clone(),
+                    pieces: vec![piece],
+                })
+                .collect(),
+            DataSet::RectilinearGrid {
+                extent,
+                meta,
+                pieces,
+            } => pieces
+                .into_iter()
+                .map(|piece| DataSet::RectilinearGrid {
+                    extent: extent.clone(),
+                    meta: meta.clone(),
+                    pieces: vec![piece],
+                })
+                .collect(),
+            DataSet::UnstructuredGrid { meta, pieces } => pieces
+                .into_iter()
+                .map(|piece| DataSet::UnstructuredGrid {
+                    meta: meta.clone(),
+                    pieces: vec![piece],
+                })
+                .collect(),
+            DataSet::PolyData { meta, pieces } => pieces
+                .into_iter()
+                .map(|piece| DataSet::PolyData {
+                    meta: meta.clone(),
+                    pieces: vec![piece],
+                })
+                .collect(),
+            DataSet::Field { .. } => Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the piece at the given index is stored inline or has been loaded.
+    ///
+    /// Returns `None` if there is no piece with the given index.
+    pub fn is_piece_loaded(&self, index: usize) -> Option<bool> {
+        match self {
+            DataSet::ImageData { pieces, .. } => pieces.get(index).map(Piece::is_loaded),
+            DataSet::StructuredGrid { pieces, .. } => pieces.get(index).map(Piece::is_loaded),
+            DataSet::RectilinearGrid { pieces, .. } => pieces.get(index).map(Piece::is_loaded),
+            DataSet::UnstructuredGrid { pieces, .. } => pieces.get(index).map(Piece::is_loaded),
+            DataSet::PolyData { pieces, .. } => pieces.get(index).map(Piece::is_loaded),
+            DataSet::Field { .. } => None,
+        }
+    }
+
+    /// Returns the whole extent of this data set if it is a structured data set.
+    pub fn extent(&self) -> Option<&Extent> {
+        match self {
+            DataSet::ImageData { extent, .. }
+            | DataSet::StructuredGrid { extent, .. }
+            | DataSet::RectilinearGrid { extent, .. } => Some(extent),
+            _ => None,
+        }
+    }
+
+    /// Returns the meta data for lazily loaded pieces of this data set, if any.
+    pub fn meta(&self) -> Option<&MetaData> {
+        match self {
+            DataSet::ImageData { meta, .. }
+            | DataSet::StructuredGrid { meta, .. }
+            | DataSet::RectilinearGrid { meta, .. }
+            | DataSet::UnstructuredGrid { meta, .. }
+            | DataSet::PolyData { meta, .. } => meta.as_deref(),
+            DataSet::Field { .. } => None,
+        }
+    }
+
+    /// Returns the extension of serial XML files storing this data set.
+    ///
+    /// Field data sets have no XML format.
+    pub(crate) fn xml_extension(&self) -> Option<&'static str> {
+        match self {
+            DataSet::ImageData { .. } => Some("vti"),
+            DataSet::StructuredGrid { .. } => Some("vts"),
+            DataSet::RectilinearGrid { .. } => Some("vtr"),
+            DataSet::UnstructuredGrid { .. } => Some("vtu"),
+            DataSet::PolyData { .. } => Some("vtp"),
+            DataSet::Field { .. } => None,
+        }
+    }
+
+    /// Calls the method of `visitor` corresponding to the type of this data set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    ///
+    /// // Counts the number of pieces in a data set.
+    /// struct PieceCounter;
+    ///
+    /// impl DataSetVisitor for PieceCounter {
+    ///     type Output = usize;
+    ///     fn visit_image_data(
+    ///         &mut self,
+    ///         _: &Extent,
+    ///         _: [f32; 3],
+    ///         _: [f32; 3],
+    ///         _: Option<&MetaData>,
+    ///         pieces: &[Piece<ImageDataPiece>],
+    ///     ) -> usize {
+    ///         pieces.len()
+    ///     }
+    ///     fn visit_structured_grid(
+    ///         &mut self,
+    ///         _: &Extent,
+    ///         _: Option<&MetaData>,
+    ///         pieces: &[Piece<StructuredGridPiece>],
+    ///     ) -> usize {
+    ///         pieces.len()
+    ///     }
+    ///     fn visit_rectilinear_grid(
+    ///         &mut self,
+    ///         _: &Extent,
+    ///         _: Option<&MetaData>,
+    ///         pieces: &[Piece<RectilinearGridPiece>],
+    ///     ) -> usize {
+    ///         pieces.len()
+    ///     }
+    ///     fn visit_unstructured_grid(
+    ///         &mut self,
+    ///         _: Option<&MetaData>,
+    ///         pieces: &[Piece<UnstructuredGridPiece>],
+    ///     ) -> usize {
+    ///         pieces.len()
+    ///     }
+    ///     fn visit_poly_data(&mut self, _: Option<&MetaData>, pieces: &[Piece<PolyDataPiece>]) -> usize {
+    ///         pieces.len()
+    ///     }
+    ///     fn visit_field(&mut self, _: &str, _: &[FieldArray]) -> usize {
+    ///         0
+    ///     }
+    /// }
+    ///
+    /// let data = DataSet::inline(PolyDataPiece::default());
+    /// assert_eq!(data.accept(&mut PieceCounter), 1);
+    /// ```
+    pub fn accept<V: DataSetVisitor>(&self, visitor: &mut V) -> V::Output {
+        match self {
+            DataSet::ImageData {
+                extent,
+                origin,
+                spacing,
+                meta,
+                pieces,
+            } => visitor.visit_image_data(extent, *origin, *spacing, meta.as_deref(), pieces),
+            DataSet::StructuredGrid {
+                extent,
+                meta,
+                pieces,
+            } => visitor.visit_structured_grid(extent, meta.as_deref(), pieces),
+            DataSet::RectilinearGrid {
+                extent,
+                meta,
+                pieces,
+            } => visitor.visit_rectilinear_grid(extent, meta.as_deref(), pieces),
+            DataSet::UnstructuredGrid { meta, pieces } => {
+                visitor.visit_unstructured_grid(meta.as_deref(), pieces)
+            }
+            DataSet::PolyData { meta, pieces } => visitor.visit_poly_data(meta.as_deref(), pieces),
+            DataSet::Field { name, data_array } => visitor.visit_field(name, data_array),
+        }
+    }
+}
+
+/// A visitor over the different types of data sets.
+///
+/// Implementing this trait instead of matching on [`DataSet`] directly ensures that every type of
+/// data set is handled, since each method must be implemented. Use [`DataSet::accept`] to dispatch
+/// to the appropriate method.
+pub trait DataSetVisitor {
+    /// The value produced by visiting a data set.
+    type Output;
+
+    fn visit_image_data(
+        &mut self,
+        extent: &Extent,
+        origin: [f32; 3],
+        spacing: [f32; 3],
+        meta: Option<&MetaData>,
+        pieces: &[Piece<ImageDataPiece>],
+    ) -> Self::Output;
+    fn visit_structured_grid(
+        &mut self,
+        extent: &Extent,
+        meta: Option<&MetaData>,
+        pieces: &[Piece<StructuredGridPiece>],
+    ) -> Self::Output;
+    fn visit_rectilinear_grid(
+        &mut self,
+        extent: &Extent,
+        meta: Option<&MetaData>,
+        pieces: &[Piece<RectilinearGridPiece>],
+    ) -> Self::Output;
+    fn visit_unstructured_grid(
+        &mut self,
+        meta: Option<&MetaData>,
+        pieces: &[Piece<UnstructuredGridPiece>],
+    ) -> Self::Output;
+    fn visit_poly_data(
+        &mut self,
+        meta: Option<&MetaData>,
+        pieces: &[Piece<PolyDataPiece>],
+    ) -> Self::Output;
+    fn visit_field(&mut self, name: &str, data_array: &[FieldArray]) -> Self::Output;
+}
+
+/// Checks that each attribute has the expected number of values.
+///
+/// Field attributes are not checked since field arrays don't necessarily correspond to points or
+/// cells.
+fn validate_attributes(
+    data: &Attributes,
+    num_points: usize,
+    num_cells: usize,
+) -> Result<(), Error> {
+    validate_attribute_sizes(&data.point, num_points)?;
+    validate_attribute_sizes(&data.cell, num_cells)
+}
+
+/// Checks that each of the given attributes has exactly one tuple per element.
+pub(crate) fn validate_attribute_sizes(attribs: &[Attribute], n: usize) -> Result<(), Error> {
+    for attrib in attribs {
+        if let Attribute::DataArray(array) = attrib {
+            array.validate_tuples()?;
+            let expected = n * array.num_comp();
+            if array.data.len() != expected {
+                return Err(Error::AttributeSizeMismatch {
+                    name: array.name.clone(),
+                    expected,
+                    actual: array.data.len(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the attributes in each of `pieces` with the attributes in the corresponding piece of
+/// `fields`.
+fn replace_piece_attributes<P: PieceData>(
+    pieces: Vec<Piece<P>>,
+    fields: Vec<Piece<P>>,
+    source_path: Option<&Path>,
+    fields_path: Option<&Path>,
+    counts: impl Fn(&P) -> (usize, usize),
+    attributes: impl Fn(&mut P) -> &mut Attributes,
+) -> Result<Vec<Piece<P>>, Error> {
+    if pieces.len() != fields.len() {
+        return Err(Error::PieceDataMismatch);
+    }
+    pieces
+        .into_iter()
+        .zip(fields)
+        .map(|(piece, field_piece)| {
+            let mut piece = piece.into_loaded_piece_data(source_path)?;
+            let mut field_piece = field_piece.into_loaded_piece_data(fields_path)?;
+            let (num_points, num_cells) = counts(&piece);
+            let data = std::mem::take(attributes(&mut field_piece));
+            validate_attributes(&data, num_points, num_cells)?;
+            *attributes(&mut piece) = data;
+            Ok(Piece::Inline(Box::new(piece)))
+        })
+        .collect()
+}
+
+impl DataSet {
+    /// Removes all point, cell and field data, keeping only the geometry of this data set.
+    ///
+    /// Attributes are removed from inline and loaded pieces, along with their descriptions in the
+    /// meta data. Pieces stored in other files are left untouched, use
+    /// [`Vtk::geometry_only`] to load and strip them as well. Field data sets are left empty.
+    pub fn strip_attributes(&mut self) {
+        fn strip<P>(pieces: &mut [Piece<P>], data: impl Fn(&mut P) -> &mut Attributes) {
+            for piece in pieces.iter_mut() {
+                match piece {
+                    Piece::Inline(piece) => *data(piece) = Attributes::new(),
+                    Piece::Loaded(data_set) => data_set.strip_attributes(),
+                    Piece::Source(..) => {}
+                }
+            }
+        }
+        fn strip_meta(meta: &mut Option<Box<MetaData>>) {
+            if let Some(meta) = meta {
+                let attributes = match &mut **meta {
+                    MetaData::ImageData { attributes, .. }
+                    | MetaData::RectilinearGrid { attributes, .. }
+                    | MetaData::StructuredGrid { attributes, .. }
+                    | MetaData::UnstructuredGrid { attributes, .. }
+                    | MetaData::PolyData { attributes, .. } => attributes,
+                };
+                attributes.point_data.clear();
+                attributes.cell_data.clear();
+            }
+        }
+        match self {
+            DataSet::ImageData { meta, pieces, .. } => {
+                strip_meta(meta);
+                strip(pieces, |p| &mut p.data);
+            }
+            DataSet::StructuredGrid { meta, pieces, .. } => {
+                strip_meta(meta);
+                strip(pieces, |p| &mut p.data);
+            }
+            DataSet::RectilinearGrid { meta, pieces, .. } => {
+                strip_meta(meta);
+                strip(pieces, |p| &mut p.data);
+            }
+            DataSet::UnstructuredGrid { meta, pieces } => {
+                strip_meta(meta);
+                strip(pieces, |p| &mut p.data);
+            }
+            DataSet::PolyData { meta, pieces } => {
+                strip_meta(meta);
+                strip(pieces, |p| &mut p.data);
+            }
+            DataSet::Field { data_array, .. } => data_array.clear(),
+        }
+    }
+
+    /// Replaces all attributes in this data set with the attributes stored in `fields`.
+    ///
+    /// The geometry stored in `fields` is ignored. Both data sets must be of the same type and
+    /// have the same number of pieces. The size of each new attribute is validated against the
+    /// number of points and cells in the corresponding piece of this data set.
+    ///
+    /// `source_path` and `fields_path` are the paths to the files containing each data set, which
+    /// are used to load any referenced pieces.
+    pub(crate) fn with_attributes_from(
+        self,
+        fields: DataSet,
+        source_path: Option<&Path>,
+        fields_path: Option<&Path>,
+    ) -> Result<DataSet, Error> {
+        let extent_counts =
+            |extent: &Extent| (extent.num_points() as usize, extent.num_cells() as usize);
+        Ok(match (self, fields) {
+            (
+                DataSet::ImageData {
+                    extent,
+                    origin,
+                    spacing,
+                    meta,
+                    pieces,
+                },
+                DataSet::ImageData { pieces: fields, .. },
+            ) => DataSet::ImageData {
+                extent,
+                origin,
+                spacing,
+                meta,
+                pieces: replace_piece_attributes(
+                    pieces,
+                    fields,
+                    source_path,
+                    fields_path,
+                    |p: &ImageDataPiece| extent_counts(&p.extent),
+                    |p| &mut p.data,
+                )?,
+            },
+            (
+                DataSet::StructuredGrid {
+                    extent,
+                    meta,
+                    pieces,
+                },
+                DataSet::StructuredGrid { pieces: fields, .. },
+            ) => DataSet::StructuredGrid {
+                extent,
+                meta,
+                pieces: replace_piece_attributes(
+                    pieces,
+                    fields,
+                    source_path,
+                    fields_path,
+                    |p: &StructuredGridPiece| extent_counts(&p.extent),
+                    |p| &mut p.data,
+                )?,
+            },
+            (
+                DataSet::RectilinearGrid {
+                    extent,
+                    meta,
+                    pieces,
+                },
+                DataSet::RectilinearGrid { pieces: fields, .. },
+            ) => DataSet::RectilinearGrid {
+                extent,
+                meta,
+                pieces: replace_piece_attributes(
+                    pieces,
+                    fields,
+                    source_path,
+                    fields_path,
+                    |p: &RectilinearGridPiece| extent_counts(&p.extent),
+                    |p| &mut p.data,
+                )?,
+            },
+            (
+                DataSet::UnstructuredGrid { meta, pieces },
+                DataSet::UnstructuredGrid { pieces: fields, .. },
+            ) => DataSet::UnstructuredGrid {
+                meta,
+                pieces: replace_piece_attributes(
+                    pieces,
+                    fields,
+                    source_path,
+                    fields_path,
+                    |p: &UnstructuredGridPiece| (p.num_points(), p.cells.num_cells()),
+                    |p| &mut p.data,
+                )?,
+            },
+            (DataSet::PolyData { meta, pieces }, DataSet::PolyData { pieces: fields, .. }) => {
+                DataSet::PolyData {
+                    meta,
+                    pieces: replace_piece_attributes(
+                        pieces,
+                        fields,
+                        source_path,
+                        fields_path,
+                        |p: &PolyDataPiece| (p.num_points(), p.num_cells()),
+                        |p| &mut p.data,
+                    )?,
+                }
+            }
+            _ => return Err(Error::PieceDataMismatch),
+        })
+    }
 }
 
 impl From<ImageDataPiece> for DataSet {
@@ -2128,7 +4881,7 @@ pub struct ArrayMetaData {
 
 /// Types of data that can be recognized by the parser. Not all data types are supported for all
 /// classes.
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Eq, Hash)]
 pub enum ScalarType {
     /// Data is interpreted as `u8` (unsigned 8 bit) chunks.
     Bit,
@@ -2216,6 +4969,136 @@ impl From<TypeId> for ScalarType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn cell_type_ids() {
+        assert_eq!(CellType::try_from(10u8), Ok(CellType::Tetra));
+        assert_eq!(CellType::try_from(72u32), Ok(CellType::LagrangeHexahedron));
+        assert_eq!(CellType::try_from(90u32), Err(UnknownCellType(90)));
+        assert_eq!(UnknownCellType(90).to_string(), "Unknown cell type id: 90");
+        assert_eq!(CellType::from_id(90), CellType::Unknown(90));
+        assert_eq!(u32::from(CellType::Unknown(90)), 90);
+        assert_eq!(u32::from(CellType::BezierPyramid), 81);
+        assert_eq!(CellType::Tetra.to_string(), "VTK_TETRA");
+        assert_eq!(CellType::Unknown(90).to_string(), "VTK_UNKNOWN_CELL(90)");
+        for id in 0..=81 {
+            assert_eq!(CellType::from_id(id).id(), id);
+        }
+    }
+
+    #[test]
+    fn common_traits() {
+        use std::collections::HashSet;
+        use std::hash::Hash;
+
+        fn assert_hashable<T: Clone + fmt::Debug + Eq + Hash>() {}
+        assert_hashable::<Version>();
+        assert_hashable::<ByteOrder>();
+        assert_hashable::<ElementType>();
+        assert_hashable::<VertexNumbers>();
+        assert_hashable::<Cells>();
+        assert_hashable::<CellType>();
+        assert_hashable::<Extent>();
+        assert_hashable::<ScalarType>();
+        assert_hashable::<crate::ReadOptions>();
+        assert_hashable::<crate::Warning>();
+
+        let topologies: HashSet<_> = vec![
+            VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            },
+            VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            },
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(topologies.len(), 1);
+        assert!(Version::new((4, 2)) < Version::new((5, 1)));
+    }
+
+    #[test]
+    fn poly_data_merge() -> Result<(), Error> {
+        let piece = |z: f32, id: i32| PolyDataPiece {
+            points: vec![0.0, 0.0, z, 1.0, 0.0, z, 0.0, 1.0, z].into(),
+            verts: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![1, 0],
+            }),
+            polys: Some(VertexNumbers::XML {
+                connectivity: vec![0, 1, 2],
+                offsets: vec![3],
+            }),
+            data: Attributes {
+                point: vec![Attribute::generic("z", 1).with_data(vec![z; 3])],
+                cell: vec![Attribute::generic("id", 1).with_data(vec![id, id + 1])],
+                field: Vec::new(),
+            },
+            ..Default::default()
+        };
+        let merged = PolyDataPiece::merge(vec![piece(0.0, 10), piece(1.0, 20)])?;
+        assert_eq!(merged.num_points(), 6);
+        assert_eq!(
+            merged.verts,
+            Some(VertexNumbers::XML {
+                connectivity: vec![0, 3],
+                offsets: vec![1, 2],
+            })
+        );
+        assert_eq!(
+            merged.polys,
+            Some(VertexNumbers::XML {
+                connectivity: vec![0, 1, 2, 3, 4, 5],
+                offsets: vec![3, 6],
+            })
+        );
+        assert_eq!(merged.lines, None);
+        assert_eq!(
+            merged.data.point,
+            vec![Attribute::generic("z", 1).with_data(vec![0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0])]
+        );
+        // Cell data follows the order of the merged topologies.
+        assert_eq!(
+            merged.data.cell,
+            vec![Attribute::generic("id", 1).with_data(vec![10, 20, 11, 21])]
+        );
+
+        let mut other = piece(2.0, 30);
+        other.data.cell.clear();
+        assert!(PolyDataPiece::merge(vec![piece(0.0, 10), other]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn append_pieces() -> Result<(), Error> {
+        let image = |extent: [RangeInclusive<i32>; 3]| ImageDataPiece {
+            extent: Extent::Ranges(extent),
+            data: Attributes::new(),
+        };
+        let mut data_set = DataSet::from_pieces(vec![
+            image([0..=2, 0..=2, 0..=0]),
+            image([2..=4, 0..=2, 0..=0]),
+        ])?;
+        assert_eq!(data_set.num_pieces(), 2);
+        assert_eq!(
+            data_set.extent(),
+            Some(&Extent::Ranges([0..=4, 0..=2, 0..=0]))
+        );
+
+        assert!(data_set
+            .append_pieces(DataSet::inline(PolyDataPiece::default()))
+            .is_err());
+        let mut shifted = DataSet::inline(image([0..=2, 2..=4, 0..=0]));
+        if let DataSet::ImageData { origin, .. } = &mut shifted {
+            *origin = [1.0; 3];
+        }
+        assert!(data_set.append_pieces(shifted).is_err());
+        assert_eq!(data_set.num_pieces(), 2);
+        assert!(DataSet::from_pieces(Vec::<PolyDataPiece>::new()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn io_buffer_iter() {
         let v = vec![1, 2, 3, 4];
@@ -2231,4 +5114,446 @@ mod tests {
         assert!(buf.clone().into_vec::<f32>().is_none());
         assert_eq!(buf.into_vec::<u32>(), Some(v));
     }
+
+    #[test]
+    fn empty_extent() {
+        let extent = Extent::Ranges(Extent::Dims([0, 0, 0]).into_ranges());
+        assert_eq!(extent.num_points(), 0);
+        assert_eq!(extent.num_cells(), 0);
+        assert_eq!(Extent::Dims([0, 0, 0]).num_cells(), 0);
+        assert_eq!(Extent::Dims([3, 3, 3]).num_cells(), 8);
+        assert_eq!(Extent::Dims([3, 0, 3]).num_cells(), 0);
+    }
+
+    #[test]
+    fn degenerate_extent() {
+        assert_eq!(Extent::Dims([3, 4, 1]).num_cells(), 6);
+        assert_eq!(Extent::Dims([1, 4, 1]).num_cells(), 3);
+        assert_eq!(Extent::Dims([1, 1, 1]).num_cells(), 1);
+        assert_eq!(Extent::Ranges([0..=2, 0..=3, 5..=5]).num_cells(), 6);
+    }
+
+    fn two_triangles(cell_verts: VertexNumbers) -> UnstructuredGridPiece {
+        UnstructuredGridPiece {
+            points: vec![0.0f32; 12].into(),
+            cells: Cells {
+                cell_verts,
+                types: vec![CellType::Triangle; 2],
+            },
+            data: Attributes {
+                point: Vec::new(),
+                cell: vec![
+                    Attribute::scalars("id", 1).with_data(vec![1i32, 2]),
+                    Attribute::field("fields").add_field_data(
+                        FieldArray::new("vel", 2).with_data(vec![1.0f32, 1.0, 2.0, 2.0]),
+                    ),
+                ],
+                field: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn check_valid_cells() {
+        let piece = two_triangles(VertexNumbers::XML {
+            connectivity: vec![0, 1, 2, 1, 2, 3],
+            offsets: vec![3, 6],
+        });
+        assert!(piece.check_cells().is_empty());
+        let piece = two_triangles(VertexNumbers::Legacy {
+            num_cells: 2,
+            vertices: vec![3, 0, 1, 2, 3, 1, 2, 3],
+        });
+        assert!(piece.check_cells().is_empty());
+    }
+
+    #[test]
+    fn check_invalid_cells() {
+        let piece = two_triangles(VertexNumbers::XML {
+            connectivity: vec![0, 1, 2, 3, 1, 2, 4],
+            offsets: vec![4, 7],
+        });
+        assert_eq!(
+            piece.check_cells(),
+            vec![
+                CellIssue::VertexCountMismatch {
+                    cell: 0,
+                    cell_type: CellType::Triangle,
+                    expected: 3..=3,
+                    found: 4,
+                },
+                CellIssue::IndexOutOfBounds {
+                    cell: 1,
+                    index: 4,
+                    num_points: 4,
+                },
+            ]
+        );
+
+        let mut piece = two_triangles(VertexNumbers::Legacy {
+            num_cells: 3,
+            vertices: vec![3, 0, 1, 2, 3, 1, 2],
+        });
+        piece.cells.types.push(CellType::Vertex);
+        assert_eq!(
+            piece.check_cells(),
+            vec![CellIssue::MalformedConnectivity { cell: 1 }]
+        );
+    }
+
+    #[test]
+    fn tcoords_tuples() {
+        let uvw = DataArray::tcoords("uv", 3).with_data(vec![0.0f32, 0.5, 1.0, 1.0, 0.25, 0.0]);
+        assert!(uvw.validate_tuples().is_ok());
+        assert!(uvw.tuples::<f64>().is_none());
+        let tuples: Vec<_> = uvw.tuples::<f32>().unwrap().collect();
+        assert_eq!(tuples, vec![&[0.0, 0.5, 1.0][..], &[1.0, 0.25, 0.0][..]]);
+
+        let u = uvw.clone().with_tcoords_dim(1).unwrap();
+        assert_eq!(u.elem, ElementType::TCoords(1));
+        assert_eq!(u.data.clone().into_vec::<f32>().unwrap(), vec![0.0, 1.0]);
+        let uv = u.with_tcoords_dim(2).unwrap();
+        assert_eq!(uv.data.into_vec::<f32>().unwrap(), vec![0.0, 0.0, 1.0, 0.0]);
+
+        assert!(matches!(
+            uvw.clone().with_tcoords_dim(4),
+            Err(Error::InvalidTCoordsDim { dim: 4, .. })
+        ));
+        let partial = uvw.with_data(vec![0.0f32, 0.5, 1.0, 1.0]);
+        assert!(matches!(
+            partial.validate_tuples(),
+            Err(Error::TupleSizeMismatch {
+                num_comp: 3,
+                len: 4,
+                ..
+            })
+        ));
+        assert!(partial.with_tcoords_dim(2).is_err());
+        assert!(DataArray::tcoords("uv", 4).validate_tuples().is_err());
+    }
+
+    #[test]
+    fn color_scalars_conversion() {
+        let floats = IOBuffer::from(vec![0.0f32, 0.5, 1.0, 1.5, -0.5]);
+        let chars = color_scalars_to_u8(&floats).into_owned();
+        assert_eq!(chars, IOBuffer::from(vec![0u8, 128, 255, 255, 0]));
+        assert_eq!(
+            *color_scalars_to_f32(&IOBuffer::from(vec![0u8, 51, 255])),
+            IOBuffer::from(vec![0.0f32, 0.2, 1.0])
+        );
+        assert_eq!(
+            *color_scalars_to_u8(&IOBuffer::from(vec![-1i32, 100, 300])),
+            IOBuffer::from(vec![0u8, 100, 255])
+        );
+        assert!(matches!(color_scalars_to_u8(&chars), Cow::Borrowed(_)));
+        assert!(matches!(color_scalars_to_f32(&floats), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn resize_tuples_pads_and_truncates() {
+        let mut buf = IOBuffer::from(vec![1i32, 2, 3, 4, 5]);
+        resize_tuples(&mut buf, 8, 2, true);
+        assert_eq!(buf, IOBuffer::from(vec![1i32, 2, 3, 4, 3, 4, 3, 4]));
+        resize_tuples(&mut buf, 3, 2, true);
+        assert_eq!(buf, IOBuffer::from(vec![1i32, 2, 3]));
+        resize_tuples(&mut buf, 4, 1, false);
+        assert_eq!(buf, IOBuffer::from(vec![1i32, 2, 3, 0]));
+
+        // Empty buffers can only be padded with zeros.
+        let mut buf = IOBuffer::from(Vec::<f32>::new());
+        resize_tuples(&mut buf, 2, 1, true);
+        assert_eq!(buf, IOBuffer::from(vec![0.0f32; 2]));
+    }
+
+    #[test]
+    fn component_layouts() {
+        let tensors = DataArray::tensors("t").with_data((0..18).collect::<Vec<u16>>());
+        let planar = tensors.data_with_layout(Layout::Planar).into_owned();
+        assert_eq!(
+            planar.clone().into_vec::<u16>().unwrap()[..4],
+            [0, 9, 1, 10]
+        );
+        let components = tensors.split_components();
+        assert_eq!(components.len(), 9);
+        assert_eq!(components[8], IOBuffer::from(vec![8u16, 17]));
+
+        let restored = DataArray::tensors("t")
+            .with_layout_data(planar, Layout::Planar)
+            .unwrap();
+        assert_eq!(restored, tensors);
+        assert!(matches!(
+            tensors.data_with_layout(Layout::Interleaved),
+            Cow::Borrowed(_)
+        ));
+
+        // Single component arrays have the same data in both layouts.
+        let scalars = DataArray::scalars("s", 1).with_data(vec![1.0f64, 2.0]);
+        assert!(matches!(
+            scalars.data_with_layout(Layout::Planar),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(scalars.split_components(), vec![scalars.data.clone()]);
+
+        assert!(DataArray::vectors("v")
+            .with_layout_data(vec![1.0f32; 4], Layout::Planar)
+            .is_err());
+    }
+
+    #[test]
+    fn remove_invalid_cells() {
+        let mut piece = two_triangles(VertexNumbers::Legacy {
+            num_cells: 2,
+            vertices: vec![3, 0, 1, 5, 3, 1, 2, 3],
+        });
+        piece.cells.types.push(CellType::Vertex);
+        let issues = piece.remove_invalid_cells();
+        assert_eq!(
+            issues,
+            vec![
+                CellIssue::CellCountMismatch {
+                    num_types: 3,
+                    num_cells: 2,
+                },
+                CellIssue::IndexOutOfBounds {
+                    cell: 0,
+                    index: 5,
+                    num_points: 4,
+                },
+            ]
+        );
+        assert!(piece.check_cells().is_empty());
+        assert_eq!(
+            piece.cells,
+            Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 1,
+                    vertices: vec![3, 1, 2, 3],
+                },
+                types: vec![CellType::Triangle],
+            }
+        );
+        assert_eq!(
+            piece.data.cell[0].data_array().unwrap().data,
+            IOBuffer::from(vec![2i32])
+        );
+        assert_eq!(
+            piece.data.cell[1].field_arrays().unwrap()[0].data,
+            IOBuffer::from(vec![2.0f32, 2.0])
+        );
+    }
+
+    #[test]
+    fn merge_duplicate_points_tolerances() {
+        // Points spaced 0.4 apart along x, followed by a copy of the first point.
+        let coords = vec![
+            0.0, 0.0, 0.0, 0.4, 0.0, 0.0, 0.8, 0.0, 0.0, 1.2, 0.0, 0.0, 0.0, 0.0, 0.0,
+        ];
+        assert_eq!(
+            Tolerance::Absolute(0.0).representatives(&coords),
+            vec![0, 1, 2, 3, 0]
+        );
+        // Merged points don't absorb their neighbors, so chains are not collapsed.
+        assert_eq!(
+            Tolerance::Absolute(0.5).representatives(&coords),
+            vec![0, 0, 2, 2, 0]
+        );
+        // The bounding box diagonal is 1.2.
+        assert_eq!(
+            Tolerance::Relative(0.7).representatives(&coords),
+            vec![0, 0, 0, 3, 0]
+        );
+        assert_eq!(
+            Tolerance::Grid(1.0).representatives(&coords),
+            vec![0, 0, 0, 3, 0]
+        );
+        assert_eq!(
+            Tolerance::Grid(0.5).representatives(&coords),
+            vec![0, 0, 2, 3, 0]
+        );
+    }
+
+    #[test]
+    fn merge_duplicate_points_updates_piece() {
+        let mut piece = PolyDataPiece {
+            points: vec![
+                0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+            ]
+            .into(),
+            verts: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![1, 3],
+            }),
+            lines: Some(VertexNumbers::XML {
+                connectivity: vec![0, 1, 2, 3],
+                offsets: vec![2, 4],
+            }),
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data(vec![0u8, 1, 2, 3])],
+                cell: vec![],
+                field: vec![],
+            },
+            ..Default::default()
+        };
+        assert_eq!(piece.merge_duplicate_points(Tolerance::Absolute(0.0)), 2);
+        assert_eq!(
+            piece.points,
+            IOBuffer::from(vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0])
+        );
+        assert_eq!(
+            piece.verts,
+            Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![1, 0],
+            })
+        );
+        assert_eq!(piece.lines.unwrap().into_xml().0, vec![0, 1, 1, 0]);
+        assert_eq!(
+            piece.data.point[0].data_array().unwrap().data,
+            IOBuffer::from(vec![0u8, 1])
+        );
+    }
+
+    #[test]
+    fn merge_duplicate_points_in_face_streams() {
+        // A tetrahedron given as a polyhedron whose second point duplicates the first, followed
+        // by a triangle.
+        let stream = vec![4, 3, 0, 3, 2, 3, 0, 3, 4, 3, 3, 2, 4, 3, 2, 0, 4];
+        let mut vertices = vec![stream.len() as u64];
+        vertices.extend_from_slice(&stream);
+        vertices.extend_from_slice(&[3, 3, 4, 2]);
+        let legacy = UnstructuredGridPiece {
+            points: vec![
+                0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ]
+            .into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 2,
+                    vertices,
+                },
+                types: vec![CellType::Polyhedron, CellType::Triangle],
+            },
+            data: Attributes::new(),
+        };
+        let (connectivity, offsets) = legacy.cells.cell_verts.clone().into_xml();
+        let mut xml = legacy.clone();
+        xml.cells.cell_verts = VertexNumbers::XML {
+            connectivity,
+            offsets,
+        };
+        for mut piece in [legacy, xml] {
+            assert_eq!(piece.merge_duplicate_points(Tolerance::Absolute(0.0)), 1);
+            assert_eq!(
+                piece.cells.cell_verts.into_xml().0,
+                vec![4, 3, 0, 1, 2, 3, 0, 1, 3, 3, 1, 2, 3, 3, 2, 0, 3, 1, 3, 2]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn bytes_with_size_in_both_byte_orders() {
+        use crate::xml::Compressor;
+        let buf = IOBuffer::from(vec![1.5f32, -2.0]);
+        let le = buf
+            .clone()
+            .into_bytes_with_size32(ByteOrder::LittleEndian, Compressor::None, 0);
+        let be = buf.into_bytes_with_size32(ByteOrder::BigEndian, Compressor::None, 0);
+        let mut expected_le = 8u32.to_le_bytes().to_vec();
+        let mut expected_be = 8u32.to_be_bytes().to_vec();
+        for x in [1.5f32, -2.0].iter() {
+            expected_le.extend_from_slice(&x.to_le_bytes());
+            expected_be.extend_from_slice(&x.to_be_bytes());
+        }
+        assert_eq!(le, expected_le);
+        assert_eq!(be, expected_be);
+    }
+
+    #[test]
+    fn provenance_tracking() {
+        let triangle = |offset: f32| UnstructuredGridPiece {
+            points: vec![offset, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 0, 1, 7],
+                    offsets: vec![3, 6],
+                },
+                types: vec![CellType::Triangle; 2],
+            },
+            data: Attributes::new(),
+        };
+        let untracked = UnstructuredGridPiece::merge(vec![triangle(0.0), triangle(0.0)]).unwrap();
+        assert!(!untracked.data.is_tracking_provenance());
+        assert!(untracked.data.point.is_empty());
+
+        // Tracking in one piece enables it for all merged pieces.
+        let mut first = triangle(0.0);
+        first.track_provenance();
+        let mut merged = UnstructuredGridPiece::merge(vec![first, triangle(0.5)]).unwrap();
+        merged.merge_duplicate_points(Tolerance::Absolute(0.0));
+        merged.remove_invalid_cells();
+
+        let ids = |attribs: &[Attribute], name: &str| {
+            let attrib = attribs.iter().find(|a| a.name() == name).unwrap();
+            attrib.data_array().unwrap().data.clone()
+        };
+        let point = &merged.data.point;
+        assert_eq!(ids(point, ORIGINAL_POINT_IDS), vec![0i64, 1, 2, 0].into());
+        assert_eq!(ids(point, ORIGINAL_PIECE_IDS), vec![0i64, 0, 0, 1].into());
+        let cell = &merged.data.cell;
+        assert_eq!(ids(cell, ORIGINAL_CELL_IDS), vec![0i64, 0].into());
+        assert_eq!(ids(cell, ORIGINAL_PIECE_IDS), vec![0i64, 1].into());
+        assert_eq!(
+            merged.data.processing_log(),
+            vec![
+                "merge 2 pieces",
+                "merge_duplicate_points Absolute(0.0): removed 2 points",
+                "remove_invalid_cells: removed 2 cells",
+            ]
+        );
+    }
+    #[test]
+    fn structured_blanking() {
+        let mut piece = StructuredGridPiece {
+            extent: Extent::Dims([3, 3, 1]),
+            points: IOBuffer::F32(vec![0.0; 27]),
+            data: Attributes::new(),
+        };
+        assert!(!piece.is_point_blanked(4));
+        assert_eq!(piece.visible_cells().count(), 4);
+
+        piece.set_cell_blanked(1, true);
+        assert!(piece.is_cell_blanked(1));
+        assert_eq!(piece.visible_cells().collect::<Vec<_>>(), vec![0, 2, 3]);
+
+        // Point 6 is the top left corner, so it only hides cell 2.
+        piece.set_point_blanked(6, true);
+        assert!(piece.is_point_blanked(6));
+        assert!(!piece.is_cell_blanked(2));
+        assert!(!piece.is_cell_visible(2));
+        assert_eq!(piece.visible_cells().collect::<Vec<_>>(), vec![0, 3]);
+
+        piece.set_cell_blanked(1, false);
+        piece.set_point_blanked(6, false);
+        assert_eq!(piece.visible_cells().count(), 4);
+        assert_eq!(
+            piece.data.cell[0].data_array().unwrap().data,
+            IOBuffer::U8(vec![0; 4])
+        );
+
+        // Existing ghost arrays of other types are converted to `u8`.
+        let mut image = ImageDataPiece {
+            extent: Extent::Ranges([0..=2, 0..=1, 0..=1]),
+            data: Attributes {
+                cell: vec![Attribute::scalars(GHOST_ARRAY_NAME, 1).with_data(vec![0i32, 1])],
+                ..Attributes::new()
+            },
+        };
+        image.set_cell_blanked(0, true);
+        assert_eq!(
+            image.data.cell[0].data_array().unwrap().data,
+            IOBuffer::U8(vec![HIDDEN_CELL, 1])
+        );
+        assert_eq!(image.visible_cells().collect::<Vec<_>>(), vec![1]);
+    }
 }
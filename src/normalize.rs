@@ -0,0 +1,202 @@
+//! Canonical text output for version controlled data sets.
+//!
+//! ASCII VTK files checked into a repository as test fixtures tend to produce noisy diffs when
+//! they are regenerated, since the order of attributes, the formatting of floats and the way
+//! values are broken into lines depend on the tool and library version that wrote them.
+//! [`normalize`] rewrites a data set into a canonical legacy ASCII file determined only by the
+//! data itself and the chosen [`Profile`].
+//!
+//! Normalization is lossless for the data: floats are written with the fewest digits that parse
+//! back to the same value, and points, cells and attribute values are written unchanged.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::normalize::{normalize, Profile};
+//!
+//! let vtk = Vtk {
+//!     version: Version::new((4, 2)),
+//!     byte_order: ByteOrder::BigEndian,
+//!     title: String::from("Points"),
+//!     file_path: None,
+//!     data: DataSet::inline(PolyDataPiece {
+//!         points: vec![0.1f64, 0.2, 0.3].into(),
+//!         data: Attributes {
+//!             point: vec![
+//!                 Attribute::scalars("b", 1).with_data(vec![2.0f32]),
+//!                 Attribute::scalars("a", 1).with_data(vec![1.0f32]),
+//!             ],
+//!             ..Attributes::new()
+//!         },
+//!         ..Default::default()
+//!     }),
+//! };
+//!
+//! let text = normalize(vtk, &Profile::new()).expect("Failed to normalize");
+//! assert!(text.contains("POINTS 1 double\n0.1 0.2 0.3\n"));
+//! assert!(text.find("SCALARS a").unwrap() < text.find("SCALARS b").unwrap());
+//! ```
+
+use crate::model::*;
+use crate::writer::{FloatFormat, LegacyVersion, LineWrap, OutputFormat, WriterConfig};
+use crate::Error;
+
+/// Options determining the canonical form produced by [`normalize`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Profile {
+    sort_attributes: bool,
+    float_format: FloatFormat,
+    line_wrap: LineWrap,
+    version: Option<LegacyVersion>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile::new()
+    }
+}
+
+impl Profile {
+    /// Creates the default profile.
+    ///
+    /// Attributes are sorted by name, floats are written in the shortest round trip format and
+    /// lines hold at most 9 values like in files written by VTK. The version of the data set is
+    /// kept.
+    pub fn new() -> Self {
+        Profile {
+            sort_attributes: true,
+            float_format: FloatFormat::RoundTrip,
+            line_wrap: LineWrap::Values(9),
+            version: None,
+        }
+    }
+
+    /// Sets whether point, cell and field attributes are sorted by name.
+    ///
+    /// Readers like VTK treat the first `SCALARS` or `VECTORS` attribute of a section as the
+    /// active one, so sorting can change which attribute is active when the file is loaded.
+    pub fn with_sorted_attributes(self, sort_attributes: bool) -> Self {
+        Profile {
+            sort_attributes,
+            ..self
+        }
+    }
+
+    /// Sets how floats are written.
+    ///
+    /// Note that `FloatFormat::Significant` may round values, making normalization lossy.
+    pub fn with_float_format(self, float_format: FloatFormat) -> Self {
+        Profile {
+            float_format,
+            ..self
+        }
+    }
+
+    /// Sets how long lines of values are broken up.
+    pub fn with_line_wrap(self, line_wrap: LineWrap) -> Self {
+        Profile { line_wrap, ..self }
+    }
+
+    /// Writes all files with the given version instead of the version of the data set.
+    pub fn with_version(self, version: LegacyVersion) -> Self {
+        Profile {
+            version: Some(version),
+            ..self
+        }
+    }
+
+    /// Returns the configuration used to write normalized files.
+    pub fn writer_config(&self) -> WriterConfig {
+        let config = WriterConfig::new()
+            .with_format(OutputFormat::LegacyAscii)
+            .with_float_format(self.float_format)
+            .with_line_wrap(self.line_wrap);
+        match self.version {
+            Some(version) => config.with_legacy_version(version),
+            None => config,
+        }
+    }
+}
+
+/// Rewrites the given data set into its canonical form without writing it.
+///
+/// Pieces stored in other files are loaded, and attributes are sorted by name if requested by the
+/// profile.
+pub fn canonicalize(mut vtk: Vtk, profile: &Profile) -> Result<Vtk, Error> {
+    vtk.load_all_pieces()?;
+    if profile.sort_attributes {
+        sort_data_set(&mut vtk.data);
+    }
+    Ok(vtk)
+}
+
+/// Writes the given data set as a canonical legacy ASCII file.
+///
+/// Writing the same data with the same profile always produces identical text.
+pub fn normalize(vtk: Vtk, profile: &Profile) -> Result<String, Error> {
+    let vtk = canonicalize(vtk, profile)?;
+    let mut output = Vec::new();
+    profile.writer_config().write(vtk, &mut output)?;
+    // The ASCII writer only produces UTF-8, given that the title and names are valid strings.
+    Ok(String::from_utf8(output).expect("ASCII output is not valid UTF-8"))
+}
+
+fn sort_attributes(data: &mut Attributes) {
+    for attribs in [&mut data.point, &mut data.cell].iter_mut() {
+        attribs.sort_by(|a, b| a.name().cmp(b.name()));
+        for attrib in attribs.iter_mut() {
+            if let Attribute::Field { data_array, .. } = attrib {
+                data_array.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+        }
+    }
+    data.field.sort_by(|a, b| a.name.cmp(&b.name));
+}
+
+fn sort_pieces<P>(pieces: &mut [Piece<P>], data: impl Fn(&mut P) -> &mut Attributes) {
+    for piece in pieces {
+        match piece {
+            Piece::Inline(piece) => sort_attributes(data(piece)),
+            Piece::Loaded(data_set) => sort_data_set(data_set),
+            Piece::Source(..) => {}
+        }
+    }
+}
+
+fn sort_data_set(data: &mut DataSet) {
+    match data {
+        DataSet::ImageData { pieces, .. } => sort_pieces(pieces, |p| &mut p.data),
+        DataSet::StructuredGrid { pieces, .. } => sort_pieces(pieces, |p| &mut p.data),
+        DataSet::RectilinearGrid { pieces, .. } => sort_pieces(pieces, |p| &mut p.data),
+        DataSet::UnstructuredGrid { pieces, .. } => sort_pieces(pieces, |p| &mut p.data),
+        DataSet::PolyData { pieces, .. } => sort_pieces(pieces, |p| &mut p.data),
+        DataSet::Field { data_array, .. } => data_array.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_files_are_stable() -> Result<(), Error> {
+        let vtk = crate::import("assets/cube_complex.vtk")?;
+        let text = normalize(vtk.clone(), &Profile::new())?;
+
+        // Normalization is idempotent and lossless.
+        let reparsed = Vtk::parse_legacy_be(text.as_bytes())?;
+        assert_eq!(normalize(reparsed.clone(), &Profile::new())?, text);
+        let unsorted = Profile::new().with_sorted_attributes(false);
+        assert_eq!(
+            canonicalize(reparsed, &unsorted)?,
+            canonicalize(vtk.clone(), &Profile::new())?
+        );
+
+        // Cell normals are sorted before the cell scalars given first in the original file.
+        let position = |s: &str| text.find(s).unwrap();
+        assert!(position("NORMALS cell_normals") < position("SCALARS cell_scalars"));
+        assert!(text.lines().all(|line| line.split(' ').count() <= 9));
+        Ok(())
+    }
+}
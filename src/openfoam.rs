@@ -0,0 +1,948 @@
+//! Reading OpenFOAM cases.
+//!
+//! [OpenFOAM](https://openfoam.org) stores meshes as arbitrary polyhedra in the
+//! `constant/polyMesh` directory of a case: a list of `points`, a list of polygonal `faces`,
+//! the cell that `owner`s each face, the `neighbour` cell of each internal face and the
+//! `boundary` patches grouping the remaining faces. A [`PolyMesh`] reads these files and
+//! converts them into an unstructured grid of `Polyhedron` cells, whose vertices are given as
+//! face streams like in legacy files, and the faces of each [`Patch`] into poly data.
+//!
+//! Results are stored in one directory per time step, holding one file per field. A [`Case`]
+//! lists the time steps of a case and reads the volume and point fields of a time step as cell
+//! and point attributes with [`Case::time_step`]. Surface fields, boundary values and
+//! decomposed (`processor*`) cases are not read.
+//!
+//! Both the ASCII and binary formats are supported. Compressed (`.gz`) files can be read when
+//! the `flate2` feature is enabled.
+//!
+//! This module is available with the `openfoam` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::openfoam::Case;
+//!
+//! let case = Case::open("cavity").expect("Failed to read mesh");
+//! println!("{} cells", case.mesh.num_cells());
+//! for time in &case.times {
+//!     let vtk = case.time_step(time).expect("Failed to read fields");
+//!     vtk.export(format!("cavity_{}.vtk", time)).expect("Failed to export time step");
+//! }
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::model::{self, *};
+
+/// Error produced when reading OpenFOAM files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The given file is malformed at the given line (starting at 1).
+    Syntax { file: String, line: usize },
+    /// The given file is only available compressed, but the `flate2` feature is disabled.
+    Compressed(PathBuf),
+    /// The mesh is inconsistent, e.g. a face refers to a point that doesn't exist.
+    InvalidMesh(String),
+    /// A field has a class other than a volume or point field of scalars, vectors or tensors.
+    UnsupportedClass(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax { file, line } => write!(f, "Syntax error in {} at line {}", file, line),
+            Error::Compressed(path) => write!(
+                f,
+                "Compressed file {} requires the flate2 feature",
+                path.display()
+            ),
+            Error::InvalidMesh(msg) => write!(f, "Invalid mesh: {}", msg),
+            Error::UnsupportedClass(class) => write!(f, "Unsupported field class: {}", class),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A boundary patch, which is a contiguous range of faces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    pub name: String,
+    /// The type of the patch, such as `patch`, `wall`, `symmetryPlane` or `empty`.
+    pub kind: String,
+    /// Index of the first face of the patch.
+    pub start_face: usize,
+    pub num_faces: usize,
+}
+
+/// The mesh of an OpenFOAM case.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PolyMesh {
+    /// The coordinates of the points, three per point.
+    pub points: Vec<f64>,
+    /// The point indices of each face, ordered counter-clockwise when viewed from outside the
+    /// owner cell.
+    pub faces: Vec<Vec<u64>>,
+    /// The cell each face belongs to.
+    pub owner: Vec<u64>,
+    /// The other cell of each internal face. Internal faces come first, so boundary faces are
+    /// the ones without a neighbour.
+    pub neighbour: Vec<u64>,
+    pub patches: Vec<Patch>,
+}
+
+impl PolyMesh {
+    /// Reads the mesh from the given `polyMesh` directory.
+    pub fn import(dir: impl AsRef<Path>) -> Result<PolyMesh, crate::Error> {
+        let dir = dir.as_ref();
+        let mesh = PolyMesh {
+            points: FoamFile::read(&dir.join("points"), |f| f.points())?,
+            faces: FoamFile::read(&dir.join("faces"), |f| f.faces())?,
+            owner: FoamFile::read(&dir.join("owner"), |f| f.labels())?,
+            neighbour: FoamFile::read(&dir.join("neighbour"), |f| f.labels())?,
+            patches: FoamFile::read(&dir.join("boundary"), |f| f.patches())?,
+        };
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    /// Returns the number of points.
+    pub fn num_points(&self) -> usize {
+        self.points.len() / 3
+    }
+
+    /// Returns the number of cells, which is one more than the largest owner or neighbour.
+    pub fn num_cells(&self) -> usize {
+        self.owner
+            .iter()
+            .chain(&self.neighbour)
+            .max()
+            .map_or(0, |&c| c as usize + 1)
+    }
+
+    /// Returns the cells as an unstructured grid of `Polyhedron` cells.
+    ///
+    /// Each cell consists of the faces it owns followed by the faces it neighbours, which are
+    /// reversed so that all faces point outward.
+    pub fn internal_mesh(&self) -> UnstructuredGridPiece {
+        let num_cells = self.num_cells();
+        let mut cell_faces = vec![Vec::new(); num_cells];
+        for (face, &cell) in self.owner.iter().enumerate() {
+            cell_faces[cell as usize].push((face, false));
+        }
+        for (face, &cell) in self.neighbour.iter().enumerate() {
+            cell_faces[cell as usize].push((face, true));
+        }
+
+        let mut vertices = Vec::new();
+        for faces in &cell_faces {
+            let len: usize = faces.iter().map(|&(f, _)| self.faces[f].len() + 1).sum();
+            vertices.push(len as u64 + 1);
+            vertices.push(faces.len() as u64);
+            for &(f, reversed) in faces {
+                let face = &self.faces[f];
+                vertices.push(face.len() as u64);
+                if reversed {
+                    vertices.extend(face.iter().rev());
+                } else {
+                    vertices.extend(face);
+                }
+            }
+        }
+
+        UnstructuredGridPiece {
+            points: self.points.clone().into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: num_cells as u64,
+                    vertices,
+                },
+                types: vec![CellType::Polyhedron; num_cells],
+            },
+            data: Attributes::new(),
+        }
+    }
+
+    /// Returns the faces of the given patch as polygons.
+    ///
+    /// All points of the mesh are kept, so point attributes apply to patches unchanged.
+    pub fn patch(&self, patch: &Patch) -> PolyDataPiece {
+        let faces = self
+            .faces
+            .iter()
+            .skip(patch.start_face)
+            .take(patch.num_faces);
+        let mut vertices = Vec::new();
+        for face in faces {
+            vertices.push(face.len() as u64);
+            vertices.extend(face);
+        }
+        PolyDataPiece {
+            points: self.points.clone().into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: patch.num_faces as u64,
+                vertices,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |msg: String| Err(Error::InvalidMesh(msg));
+        if self.owner.len() != self.faces.len() {
+            return invalid(format!(
+                "{} faces but {} owners",
+                self.faces.len(),
+                self.owner.len()
+            ));
+        }
+        if self.neighbour.len() > self.faces.len() {
+            return invalid(format!(
+                "{} faces but {} neighbours",
+                self.faces.len(),
+                self.neighbour.len()
+            ));
+        }
+        let num_points = self.num_points() as u64;
+        if let Some(face) = self
+            .faces
+            .iter()
+            .position(|f| f.iter().any(|&p| p >= num_points))
+        {
+            return invalid(format!("face {} refers to a missing point", face));
+        }
+        if let Some(patch) = self
+            .patches
+            .iter()
+            .find(|p| p.start_face + p.num_faces > self.faces.len())
+        {
+            return invalid(format!("patch {} refers to missing faces", patch.name));
+        }
+        Ok(())
+    }
+}
+
+/// The values of a field in the internal mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValues {
+    /// A single value shared by all cells or points.
+    Uniform(Vec<f64>),
+    /// The concatenated values of all cells or points.
+    NonUniform(Vec<f64>),
+}
+
+/// A volume or point field of a time step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    /// The class of the field, such as `volScalarField` or `pointVectorField`.
+    pub class: String,
+    /// The number of components of each value. Symmetric tensors have six components, stored in
+    /// the order `xx xy xz yy yz zz`.
+    pub num_comp: usize,
+    pub values: FieldValues,
+}
+
+impl Field {
+    /// Reads the field stored in the given file, named after the file.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<Field, crate::Error> {
+        FoamFile::read(file_path.as_ref(), |f| f.field())
+    }
+
+    /// Returns `true` if the field holds a value per point rather than per cell.
+    pub fn is_point_field(&self) -> bool {
+        self.class.starts_with("point")
+    }
+
+    /// Converts the field into an attribute with the given number of values.
+    ///
+    /// Uniform values are repeated, and symmetric tensors are expanded to full tensors.
+    pub fn into_attribute(self, len: usize) -> Attribute {
+        let mut values = match self.values {
+            FieldValues::Uniform(value) => value.repeat(len),
+            FieldValues::NonUniform(values) => values,
+        };
+        if self.num_comp == 6 {
+            values = values
+                .chunks(6)
+                .flat_map(|t| vec![t[0], t[1], t[2], t[1], t[3], t[4], t[2], t[4], t[5]])
+                .collect();
+        }
+        match self.num_comp {
+            3 => Attribute::vectors(self.name),
+            6 | 9 => Attribute::tensors(self.name),
+            n => Attribute::scalars(self.name, n as u32),
+        }
+        .with_data(values)
+    }
+}
+
+/// An OpenFOAM case directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub path: PathBuf,
+    pub mesh: PolyMesh,
+    /// The names of the time step directories, ordered by time.
+    pub times: Vec<String>,
+}
+
+impl Case {
+    /// Reads the mesh and lists the time steps of the case in the given directory.
+    pub fn open(path: impl AsRef<Path>) -> Result<Case, crate::Error> {
+        let path = path.as_ref().to_path_buf();
+        let mesh = PolyMesh::import(path.join("constant").join("polyMesh"))?;
+        let mut times = Vec::new();
+        for entry in fs::read_dir(&path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                if let Ok(time) = name.parse::<f64>() {
+                    times.push((time, name.to_string()));
+                }
+            }
+        }
+        times.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Case {
+            path,
+            mesh,
+            times: times.into_iter().map(|(_, name)| name).collect(),
+        })
+    }
+
+    /// Returns the internal mesh with all volume and point fields of the given time step.
+    ///
+    /// Fields are ordered by name, and files holding other kinds of data are skipped. Points
+    /// written to the `polyMesh` directory of the time step by moving mesh solvers replace the
+    /// points of the mesh.
+    pub fn time_step(&self, time: &str) -> Result<Vtk, crate::Error> {
+        let dir = self.path.join(time);
+        let mut piece = self.mesh.internal_mesh();
+        let moved_points = dir.join("polyMesh").join("points");
+        if moved_points.exists() || gz_path(&moved_points).exists() {
+            let points = FoamFile::read(&moved_points, |f| f.points())?;
+            if points.len() != self.mesh.points.len() {
+                return Err(Error::InvalidMesh(format!(
+                    "moved points of time {} don't match the mesh",
+                    time
+                ))
+                .into());
+            }
+            piece.points = points.into();
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                let path = entry.path();
+                files.push(match path.extension() {
+                    Some(ext) if ext == "gz" => path.with_extension(""),
+                    _ => path,
+                });
+            }
+        }
+        files.sort();
+        files.dedup();
+
+        let num_cells = self.mesh.num_cells();
+        let num_points = self.mesh.num_points();
+        for file in files {
+            let field = match Field::import(&file) {
+                Ok(field) => field,
+                Err(crate::Error::OpenFoam(Error::UnsupportedClass(_))) => continue,
+                Err(err) => return Err(err),
+            };
+            if field.is_point_field() {
+                piece.data.point.push(field.into_attribute(num_points));
+            } else {
+                piece.data.cell.push(field.into_attribute(num_cells));
+            }
+        }
+
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(Vtk {
+            version: Version::new((4, 2)),
+            byte_order: model::ByteOrder::BigEndian,
+            title: format!("{} at time {}", name, time),
+            file_path: None,
+            data: DataSet::inline(piece),
+        })
+    }
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".gz");
+    path.with_file_name(name)
+}
+
+/// Reads the given file, or its compressed version if it doesn't exist.
+fn read_file(path: &Path) -> Result<Vec<u8>, crate::Error> {
+    match fs::read(path) {
+        Ok(data) => Ok(data),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && gz_path(path).exists() => {
+            read_compressed(&gz_path(path))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(feature = "flate2")]
+fn read_compressed(path: &Path) -> Result<Vec<u8>, crate::Error> {
+    use std::io::Read;
+    let mut data = Vec::new();
+    flate2::read::GzDecoder::new(fs::File::open(path)?).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "flate2"))]
+fn read_compressed(path: &Path) -> Result<Vec<u8>, crate::Error> {
+    Err(Error::Compressed(path.to_path_buf()).into())
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Punct(u8),
+    Word(&'a str),
+    Str(&'a str),
+}
+
+/// The contents of a file following its `FoamFile` header.
+struct FoamFile<'a> {
+    name: String,
+    data: &'a [u8],
+    pos: usize,
+    class: String,
+    binary: bool,
+    big_endian: bool,
+    label_size: usize,
+    scalar_size: usize,
+}
+
+impl<'a> FoamFile<'a> {
+    /// Reads the given file and its header, and then its contents using `read`.
+    fn read<T>(
+        path: &Path,
+        read: impl FnOnce(FoamFile) -> Result<T, Error>,
+    ) -> Result<T, crate::Error> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let data = read_file(path)?;
+        Ok(read(FoamFile::parse(&name, &data)?)?)
+    }
+
+    fn parse(name: &str, data: &'a [u8]) -> Result<FoamFile<'a>, Error> {
+        let mut file = FoamFile {
+            name: name.to_string(),
+            data,
+            pos: 0,
+            class: String::new(),
+            binary: false,
+            big_endian: false,
+            label_size: 4,
+            scalar_size: 8,
+        };
+        if file.next()? != Token::Word("FoamFile") {
+            return Err(file.error());
+        }
+        file.expect(b'{')?;
+        loop {
+            let key = match file.next()? {
+                Token::Punct(b'}') => break,
+                Token::Word(key) => key.to_string(),
+                _ => return Err(file.error()),
+            };
+            let value = match file.next()? {
+                Token::Word(value) | Token::Str(value) => value.to_string(),
+                _ => return Err(file.error()),
+            };
+            file.expect(b';')?;
+            match key.as_str() {
+                "format" => file.binary = value == "binary",
+                "class" => file.class = value,
+                "arch" => {
+                    for part in value.split(';') {
+                        match part.split_once('=') {
+                            Some(("label", bits)) => file.label_size = file.size(bits)?,
+                            Some(("scalar", bits)) => file.scalar_size = file.size(bits)?,
+                            _ => file.big_endian |= part == "MSB",
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(file)
+    }
+
+    fn size(&self, bits: &str) -> Result<usize, Error> {
+        match bits {
+            "32" => Ok(4),
+            "64" => Ok(8),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn error(&self) -> Error {
+        let end = self.pos.min(self.data.len());
+        Error::Syntax {
+            file: self.name.clone(),
+            line: self.data[..end].iter().filter(|&&b| b == b'\n').count() + 1,
+        }
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_space(&mut self) {
+        loop {
+            let rest = &self.data[self.pos.min(self.data.len())..];
+            if rest.first().is_some_and(u8::is_ascii_whitespace) {
+                self.pos += 1;
+            } else if rest.starts_with(b"//") {
+                let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                self.pos += len;
+            } else if rest.starts_with(b"/*") {
+                let len = rest
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(rest.len(), |i| i + 2);
+                self.pos += len;
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_space();
+        let start = self.pos;
+        let first = *self.data.get(start)?;
+        let token = match first {
+            b'(' | b')' | b'{' | b'}' | b'[' | b']' | b';' => {
+                self.pos += 1;
+                Token::Punct(first)
+            }
+            b'"' => {
+                let len = self.data[start + 1..].iter().position(|&b| b == b'"')?;
+                self.pos += len + 2;
+                Token::Str(std::str::from_utf8(&self.data[start + 1..start + 1 + len]).ok()?)
+            }
+            _ => {
+                let len = self.data[start..]
+                    .iter()
+                    .position(|&b| b.is_ascii_whitespace() || b"(){}[];\"".contains(&b))
+                    .unwrap_or(self.data.len() - start);
+                self.pos += len;
+                Token::Word(std::str::from_utf8(&self.data[start..start + len]).ok()?)
+            }
+        };
+        Some(token)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, Error> {
+        self.next_token().ok_or_else(|| self.error())
+    }
+
+    fn peek(&mut self) -> Result<Token<'a>, Error> {
+        let pos = self.pos;
+        let token = self.next_token();
+        self.pos = pos;
+        token.ok_or_else(|| self.error())
+    }
+
+    fn expect(&mut self, punct: u8) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(p) if p == punct => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        match self.next()? {
+            Token::Word(word) => word.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| self.error())
+    }
+
+    /// Skips the value of a dictionary entry whose key has been read.
+    fn skip_value(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punct(b'(' | b'{' | b'[') => depth += 1,
+                Token::Punct(b')' | b']') => depth -= 1,
+                Token::Punct(b'}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        // Dictionaries aren't terminated by a semicolon.
+                        return Ok(());
+                    }
+                }
+                Token::Punct(b';') if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads a list of values with `num_comp` components each, concatenating their components.
+    ///
+    /// The list is given by its size followed by its values in parentheses or a single value
+    /// in braces, and values of several components are enclosed in parentheses in ASCII files.
+    fn values<T: Copy + std::str::FromStr>(
+        &mut self,
+        num_comp: usize,
+        size: usize,
+        decode: impl Fn(&[u8]) -> T,
+    ) -> Result<Vec<T>, Error> {
+        let len = match self.peek()? {
+            Token::Punct(b'(') => None,
+            _ => Some(self.number::<usize>()?),
+        };
+        match self.next()? {
+            Token::Punct(b'{') => {
+                let value = self.value(num_comp)?;
+                self.expect(b'}')?;
+                Ok(value.repeat(len.ok_or_else(|| self.error())?))
+            }
+            Token::Punct(b'(') => match len {
+                Some(len) if self.binary => {
+                    let end = self.pos + len * num_comp * size;
+                    let bytes = self.data.get(self.pos..end).ok_or_else(|| self.error())?;
+                    let values = bytes.chunks_exact(size).map(decode).collect();
+                    self.pos = end;
+                    self.expect(b')')?;
+                    Ok(values)
+                }
+                _ => {
+                    let mut values = Vec::with_capacity(len.unwrap_or(0) * num_comp);
+                    while self.peek()? != Token::Punct(b')') {
+                        values.extend(self.value::<T>(num_comp)?);
+                    }
+                    self.expect(b')')?;
+                    match len {
+                        Some(len) if len * num_comp != values.len() => Err(self.error()),
+                        _ => Ok(values),
+                    }
+                }
+            },
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Reads a single ASCII value with `num_comp` components.
+    fn value<T: std::str::FromStr>(&mut self, num_comp: usize) -> Result<Vec<T>, Error> {
+        if num_comp == 1 {
+            return Ok(vec![self.number()?]);
+        }
+        self.expect(b'(')?;
+        let value = (0..num_comp)
+            .map(|_| self.number())
+            .collect::<Result<_, _>>()?;
+        self.expect(b')')?;
+        Ok(value)
+    }
+
+    fn scalars(&mut self, num_comp: usize) -> Result<Vec<f64>, Error> {
+        let (size, big_endian) = (self.scalar_size, self.big_endian);
+        self.values(num_comp, size, |b| match (size, big_endian) {
+            (4, false) => f64::from(LittleEndian::read_f32(b)),
+            (4, true) => f64::from(BigEndian::read_f32(b)),
+            (_, false) => LittleEndian::read_f64(b),
+            (_, true) => BigEndian::read_f64(b),
+        })
+    }
+
+    fn label_list(&mut self) -> Result<Vec<u64>, Error> {
+        let (size, big_endian) = (self.label_size, self.big_endian);
+        self.values(1, size, |b| {
+            if big_endian {
+                BigEndian::read_int(b, size) as u64
+            } else {
+                LittleEndian::read_int(b, size) as u64
+            }
+        })
+    }
+
+    fn points(mut self) -> Result<Vec<f64>, Error> {
+        self.scalars(3)
+    }
+
+    fn labels(mut self) -> Result<Vec<u64>, Error> {
+        self.label_list()
+    }
+
+    fn faces(mut self) -> Result<Vec<Vec<u64>>, Error> {
+        if self.class == "faceCompactList" {
+            // Offsets into the concatenated point indices, starting at zero.
+            let offsets = self.label_list()?;
+            let indices = self.label_list()?;
+            return offsets
+                .windows(2)
+                .map(|w| {
+                    indices
+                        .get(w[0] as usize..w[1] as usize)
+                        .map(<[u64]>::to_vec)
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(|| self.error());
+        }
+        let len = self.number::<usize>()?;
+        self.expect(b'(')?;
+        let faces = (0..len)
+            .map(|_| self.label_list())
+            .collect::<Result<_, _>>()?;
+        self.expect(b')')?;
+        Ok(faces)
+    }
+
+    fn patches(mut self) -> Result<Vec<Patch>, Error> {
+        let len = self.number::<usize>()?;
+        self.expect(b'(')?;
+        let mut patches = Vec::with_capacity(len);
+        for _ in 0..len {
+            let name = match self.next()? {
+                Token::Word(name) => name.to_string(),
+                _ => return Err(self.error()),
+            };
+            self.expect(b'{')?;
+            let mut patch = Patch {
+                name,
+                kind: String::new(),
+                start_face: 0,
+                num_faces: 0,
+            };
+            loop {
+                match self.next()? {
+                    Token::Punct(b'}') => break,
+                    Token::Word("type") => {
+                        patch.kind = match self.next()? {
+                            Token::Word(kind) => kind.to_string(),
+                            _ => return Err(self.error()),
+                        };
+                        self.expect(b';')?;
+                    }
+                    Token::Word("nFaces") => {
+                        patch.num_faces = self.number()?;
+                        self.expect(b';')?;
+                    }
+                    Token::Word("startFace") => {
+                        patch.start_face = self.number()?;
+                        self.expect(b';')?;
+                    }
+                    Token::Word(_) => self.skip_value()?,
+                    _ => return Err(self.error()),
+                }
+            }
+            patches.push(patch);
+        }
+        self.expect(b')')?;
+        Ok(patches)
+    }
+
+    fn field(mut self) -> Result<Field, Error> {
+        let kind = self
+            .class
+            .strip_prefix("vol")
+            .or_else(|| self.class.strip_prefix("point"));
+        let num_comp = match kind {
+            Some("ScalarField" | "SphericalTensorField") => 1,
+            Some("VectorField") => 3,
+            Some("SymmTensorField") => 6,
+            Some("TensorField") => 9,
+            _ => return Err(Error::UnsupportedClass(self.class)),
+        };
+        loop {
+            match self.next()? {
+                Token::Word("internalField") => break,
+                Token::Word(_) => self.skip_value()?,
+                _ => return Err(self.error()),
+            }
+        }
+        let values = match self.next()? {
+            Token::Word("uniform") => FieldValues::Uniform(self.value(num_comp)?),
+            Token::Word("nonuniform") => {
+                // Skip the type of the list, e.g. `List<scalar>`.
+                if let Token::Word(word) = self.peek()? {
+                    if word.parse::<usize>().is_err() {
+                        self.next()?;
+                    }
+                }
+                FieldValues::NonUniform(self.scalars(num_comp)?)
+            }
+            _ => return Err(self.error()),
+        };
+        self.expect(b';')?;
+        Ok(Field {
+            name: self.name,
+            class: self.class,
+            num_comp,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(class: &str, format: &str) -> String {
+        format!(
+            "/* Generated for testing */\nFoamFile\n{{\n    version 2.0;\n    format {};\n    \
+             class {};\n    arch \"LSB;label=32;scalar=64\";\n}}\n// * * * //\n\n",
+            format, class
+        )
+    }
+
+    /// Writes a case of two unit cubes side by side along the x axis.
+    fn write_case(dir: &Path) -> std::io::Result<()> {
+        let mesh = dir.join("constant").join("polyMesh");
+        fs::create_dir_all(&mesh)?;
+        fs::create_dir_all(dir.join("0"))?;
+        fs::create_dir_all(dir.join("0.5").join("uniform"))?;
+
+        let points = (0..12)
+            .map(|i| format!("({} {} {})", i % 3, i / 3 % 2, i / 6))
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(
+            mesh.join("points"),
+            format!("{}12\n(\n{}\n)\n", header("vectorField", "ascii"), points),
+        )?;
+        fs::write(
+            mesh.join("faces"),
+            header("faceList", "ascii")
+                + "11\n(\n4(1 4 10 7)\n4(0 6 9 3)\n4(2 5 11 8)\n4(0 3 4 1)\n4(1 4 5 2)\n\
+                   4(6 7 10 9)\n4(7 8 11 10)\n4(0 1 7 6)\n4(1 2 8 7)\n4(3 9 10 4)\n4(4 10 11 5)\n)\n",
+        )?;
+        // Owners are stored in binary to cover both formats.
+        let mut owner = header("labelList", "binary").into_bytes();
+        owner.extend_from_slice(b"11\n(");
+        for label in [0u32, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1] {
+            owner.extend_from_slice(&label.to_le_bytes());
+        }
+        owner.extend_from_slice(b")\n");
+        fs::write(mesh.join("owner"), owner)?;
+        fs::write(
+            mesh.join("neighbour"),
+            header("labelList", "ascii") + "1(1)\n",
+        )?;
+        fs::write(
+            mesh.join("boundary"),
+            header("polyBoundaryMesh", "ascii")
+                + "3\n(\n    inlet\n    {\n        type patch;\n        nFaces 1;\n        \
+                   startFace 1;\n    }\n    outlet { type patch; nFaces 1; startFace 2; }\n    \
+                   walls\n    {\n        type wall;\n        inGroups List<word> 1(wall);\n        \
+                   nFaces 8;\n        startFace 3;\n    }\n)\n",
+        )?;
+
+        let boundary = "boundaryField\n{\n    inlet { type fixedValue; value uniform 1; }\n    \
+                        \".*\" { type zeroGradient; }\n}\n";
+        fs::write(
+            dir.join("0.5").join("p"),
+            header("volScalarField", "ascii")
+                + "dimensions [0 2 -2 0 0 0 0];\n\ninternalField nonuniform List<scalar> 2(1.5 \
+                   -2);\n\n"
+                + boundary,
+        )?;
+        fs::write(
+            dir.join("0.5").join("U"),
+            header("volVectorField", "ascii")
+                + "dimensions [0 1 -1 0 0 0 0];\ninternalField uniform (1 0 0);\n"
+                + boundary,
+        )?;
+        fs::write(
+            dir.join("0.5").join("phi"),
+            header("surfaceScalarField", "ascii") + "internalField uniform 0;\n",
+        )?;
+        fs::write(
+            dir.join("0.5").join("uniform").join("time"),
+            header("dictionary", "ascii") + "value 0.5;\n",
+        )
+    }
+
+    #[test]
+    fn case() -> Result<(), crate::Error> {
+        let dir = std::env::temp_dir().join("vtkio_openfoam_test");
+        write_case(&dir)?;
+
+        let case = Case::open(&dir)?;
+        assert_eq!(case.times, vec!["0", "0.5"]);
+        assert_eq!(case.mesh.num_cells(), 2);
+        assert_eq!(case.mesh.owner, vec![0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1]);
+        let names: Vec<_> = case.mesh.patches.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["inlet", "outlet", "walls"]);
+        assert_eq!(case.mesh.patches[2].kind, "wall");
+        assert_eq!(case.mesh.patches[2].start_face, 3);
+
+        let piece = case.mesh.internal_mesh();
+        assert_eq!(piece.cells.types, vec![CellType::Polyhedron; 2]);
+        assert!(piece.check_cells().is_empty());
+        let (_, vertices) = piece.cells.cell_verts.clone().into_legacy();
+        // The second cell starts with the faces it owns, followed by the reversed internal face.
+        assert_eq!(&vertices[..8], &[31, 6, 4, 1, 4, 10, 7, 4]);
+        assert_eq!(&vertices[32..34], &[31, 6]);
+        assert_eq!(&vertices[vertices.len() - 5..], &[4, 7, 10, 4, 1]);
+
+        let outlet = case.mesh.patch(&case.mesh.patches[1]);
+        assert_eq!(
+            outlet.polys,
+            Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![4, 2, 5, 11, 8],
+            })
+        );
+
+        let vtk = case.time_step("0.5")?;
+        let mut legacy = Vec::new();
+        vtk.clone().write_legacy(&mut legacy)?;
+        assert_eq!(Vtk::parse_legacy_be(legacy.as_slice())?.data, vtk.data);
+        let piece = UnstructuredGridPiece::from_data_set(vtk.data, None)?;
+        let cell = &piece.data.cell;
+        assert_eq!(cell.len(), 2);
+        assert_eq!(cell[0].name(), "U");
+        assert_eq!(
+            cell[0].data_array().unwrap().data,
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, 0.0].into()
+        );
+        assert_eq!(cell[1].name(), "p");
+        assert_eq!(cell[1].data_array().unwrap().data, vec![1.5, -2.0].into());
+        Ok(())
+    }
+
+    #[test]
+    fn fields() -> Result<(), Error> {
+        let input = header("volSymmTensorField", "ascii")
+            + "internalField nonuniform List<symmTensor> 1((1 2 3 4 5 6));\n";
+        let field = FoamFile::parse("sigma", input.as_bytes())?.field()?;
+        assert_eq!(field.num_comp, 6);
+        let attrib = field.into_attribute(1);
+        assert_eq!(
+            attrib.data_array().unwrap().data,
+            vec![1.0, 2.0, 3.0, 2.0, 4.0, 5.0, 3.0, 5.0, 6.0].into()
+        );
+
+        let input = header("surfaceScalarField", "ascii") + "internalField uniform 0;\n";
+        assert_eq!(
+            FoamFile::parse("phi", input.as_bytes())?
+                .field()
+                .unwrap_err(),
+            Error::UnsupportedClass(String::from("surfaceScalarField"))
+        );
+
+        let input = header("volScalarField", "ascii") + "internalField nonuniform 2(1);\n";
+        assert_eq!(
+            FoamFile::parse("p", input.as_bytes())?.field().unwrap_err(),
+            Error::Syntax {
+                file: String::from("p"),
+                line: 11,
+            }
+        );
+        Ok(())
+    }
+}
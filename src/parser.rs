@@ -2,12 +2,12 @@
 // caused by macros.
 #![allow(clippy::cognitive_complexity)]
 
+use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::str;
 
 use byteorder::{BigEndian, ByteOrder, LittleEndian, NativeEndian};
-use nom::{self, eol, ErrorKind, IResult};
-use num_traits::FromPrimitive;
+use nom::{self, eol, multispace, ErrorKind, IResult};
 
 pub use crate::basic::*;
 use crate::model::*;
@@ -66,7 +66,11 @@ named!(data_type< &[u8], ScalarType >, alt!(
         tag_no_case!("unsigned_int")   => { |_| ScalarType::U32 } |
         tag_no_case!("unsigned_char")  => { |_| ScalarType::U8 } |
         tag_no_case!("unsigned_long")  => { |_| ScalarType::U64 } |
-        tag_no_case!("unsigned_short") => { |_| ScalarType::U16 } ));
+        tag_no_case!("unsigned_short") => { |_| ScalarType::U16 } |
+        // 64-bit ids written by VTK and exporters mimicking it.
+        tag_no_case!("vtkIdType")      => { |_| ScalarType::I64 } |
+        tag_no_case!("vtktypeint64")   => { |_| ScalarType::I64 } |
+        tag_no_case!("vtktypeuint64")  => { |_| ScalarType::U64 } ));
 
 named!(pub usize_b<&[u8], usize>, call!(integer) );
 named!(pub u32_b<&[u8], u32>, call!(integer) );
@@ -75,6 +79,20 @@ named!(pub f32_b<&[u8], f32>, call!(real::<f32>) );
 
 named!(name, take_until_either!(" \t\n\r"));
 
+/// Consume the remainder of a header line preceding a data section.
+///
+/// Binary data starts right after the line ending, which may be `\r\n`, so no whitespace
+/// beyond it is skipped. ASCII data may be separated from the header by any whitespace.
+fn data_start(input: &[u8], ft: FileType) -> IResult<&[u8], ()> {
+    match ft {
+        FileType::ASCII => map!(input, opt!(complete!(multispace)), |_| ()),
+        FileType::Binary => do_parse!(
+            input,
+            opt!(complete!(whitespace)) >> alt_complete!(eol | eof!()) >> (())
+        ),
+    }
+}
+
 enum Axis {
     X,
     Y,
@@ -104,7 +122,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                 >> vec: switch!(
                        do_parse!(
                            dt: sp!( data_type ) >>
-                           eol >>
+                           call!( data_start, ft ) >>
                            (dt) ),
                                 ScalarType::F32 => call!( parse_data_buffer::<f32, BO>, 3*n as usize, ft ) |
                                 ScalarType::F64 => call!( parse_data_buffer::<f64, BO>, 3*n as usize, ft ) )
@@ -115,6 +133,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
     /// Parse cell topology indices in the legacy way.
     ///
     /// Cells are stored as a single contiguous array with the format `n v0 v1 ... vn` for each cell.
+    /// Binary files store these as 32-bit integers.
     fn legacy_cell_topo<'a>(
         input: &'a [u8],
         n: u32,
@@ -123,10 +142,16 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
     ) -> IResult<&'a [u8], VertexNumbers> {
         do_parse!(
             input,
-            data: call!(parse_data_vec::<u32, BO>, size as usize, ft)
+            data: switch!(value!(ft),
+                FileType::ASCII => call!(parse_data_vec::<u64, BO>, size as usize, ft) |
+                FileType::Binary => map!(
+                    call!(parse_data_vec::<u32, BO>, size as usize, ft),
+                    |v| v.into_iter().map(u64::from).collect()
+                )
+            )
                 >> ({
                     VertexNumbers::Legacy {
-                        num_cells: n,
+                        num_cells: u64::from(n),
                         vertices: data,
                     }
                 })
@@ -136,38 +161,67 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
     /// Parse cell topology indices in modern way using offsets and connectivity arras.
     ///
     /// Cells are stored as two arrays: OFFSETS and CONNECTIVITY, which are specified separately.
+    /// Following VTK 5.1, the OFFSETS array has `n` entries starting with a leading zero, which
+    /// is dropped here since offsets in `VertexNumbers::XML` mark the end of each cell.
+    ///
+    /// Also returns whether either array is stored as 64-bit integers.
     fn modern_cell_topo<'a>(
         input: &'a [u8],
         n: u32,
         size: u32,
         ft: FileType,
-    ) -> IResult<&'a [u8], VertexNumbers> {
+    ) -> IResult<&'a [u8], (VertexNumbers, bool)> {
         complete!(
             input,
             do_parse!(
                 offsets: call!(Self::topo, "OFFSETS", n, ft)
                     >> connectivity: call!(Self::topo, "CONNECTIVITY", size, ft)
-                    >> (VertexNumbers::XML {
-                        offsets,
-                        connectivity,
+                    >> ({
+                        let (mut offsets, offsets_type) = offsets;
+                        let (connectivity, connectivity_type) = connectivity;
+                        if offsets.first() == Some(&0) {
+                            offsets.remove(0);
+                        }
+                        let int64_ids = offsets_type == ScalarType::I64
+                            || connectivity_type == ScalarType::I64;
+                        (
+                            VertexNumbers::XML {
+                                offsets,
+                                connectivity,
+                            },
+                            int64_ids,
+                        )
                     })
             )
         )
     }
 
-    /// Parse either a CONNECTIVITY or OFFSETS array.
+    /// Parse either a CONNECTIVITY or OFFSETS array along with its data type.
+    ///
+    /// The array data type is one of `vtktypeint64` or `vtktypeint32`.
     fn topo<'a>(
         input: &'a [u8],
         tag: &'static str,
         n: u32,
         ft: FileType,
-    ) -> IResult<&'a [u8], Vec<u64>> {
+    ) -> IResult<&'a [u8], (Vec<u64>, ScalarType)> {
         do_parse!(
             input,
             ws!(tag_no_case!(tag))
-                >> take_until_either!("\n\r") // Skip data type, parse everything as u64
-                >> eol
-                >> data: call!(parse_data_vec::<u64, BO>, n as usize, ft)
+                >> data: switch!(
+                       do_parse!(
+                           dt: sp!(alt_complete!(
+                               tag_no_case!("vtktypeint64") => { |_| ScalarType::I64 } |
+                               tag_no_case!("vtktypeint32") => { |_| ScalarType::I32 }
+                           )) >>
+                           call!(data_start, ft) >>
+                           (dt) ),
+                                ScalarType::I64 => map!(
+                                    call!( parse_data_vec::<u64, BO>, n as usize, ft ),
+                                    |v| (v, ScalarType::I64) ) |
+                                ScalarType::I32 => map!(
+                                    call!( parse_data_vec::<u32, BO>, n as usize, ft ),
+                                    |v| (v.into_iter().map(u64::from).collect(), ScalarType::I32) ) )
                 >> (data)
         )
     }
@@ -178,20 +232,22 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
     ///  * "LINES"
     ///  * "POLYGONS"
     ///  * "TRIANGLE_STRIPS"
+    ///
+    /// Also returns whether the cells are stored with 64-bit offsets or connectivity.
     fn cell_verts<'a>(
         input: &'a [u8],
         tag: &'static str,
         ft: FileType,
-    ) -> IResult<&'a [u8], VertexNumbers> {
+    ) -> IResult<&'a [u8], (VertexNumbers, bool)> {
         do_parse!(
             input,
             n: ws!(do_parse!(tag_no_case!(tag) >> n: u32_b >> (n)))
                 >> size: sp!(u32_b)
-                >> eol
+                >> call!(data_start, ft)
                 >> vertex_numbers:
                     alt!(
                         call!(Self::modern_cell_topo, n, size, ft)
-                            | call!(Self::legacy_cell_topo, n, size, ft)
+                            | map!(call!(Self::legacy_cell_topo, n, size, ft), |v| (v, false))
                     )
                 >> (vertex_numbers)
         )
@@ -210,22 +266,37 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                 >> vec: switch!(
                        do_parse!(
                            dt: sp!( data_type ) >>
-                           eol >>
+                           call!( data_start, ft ) >>
                            (dt) ),
                                 ScalarType::F32 => call!( parse_data_buffer::<f32, BO>, n as usize, ft ) |
                                 ScalarType::F64 => call!( parse_data_buffer::<f64, BO>, n as usize, ft ) )
+                >> opt!(Self::meta)
                 >> (vec)
         )
     }
 
-    /// Recognize and throw away `METADATA` block. Metadata is separated by an empty line.
+    /// Recognize and throw away `METADATA` block.
+    ///
+    /// Metadata may contain `COMPONENT_NAMES` and `INFORMATION` entries consisting of
+    /// `NAME ... LOCATION ...` and `DATA ...` lines. The block is terminated by an empty line or
+    /// the end of the file.
     fn meta(input: &[u8]) -> IResult<&[u8], ()> {
-        complete!(
-            input,
-            ws!(do_parse!(
-                tag_no_case!("METADATA") >> alt!(take_until!("\n\n") | take_until!("\r\n\r\n")) >> ()
-            ))
-        )
+        let mut rest = match complete!(input, ws!(tag_no_case!("METADATA"))) {
+            IResult::Done(rest, _) => rest,
+            IResult::Incomplete(e) => return IResult::Incomplete(e),
+            IResult::Error(e) => return IResult::Error(e),
+        };
+        // Skip the remainder of the METADATA line, then each line up to the first empty one.
+        let mut first = true;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            let line = &rest[..end];
+            rest = &rest[end + 1..];
+            if !first && line.iter().all(u8::is_ascii_whitespace) {
+                return IResult::Done(rest, ());
+            }
+            first = false;
+        }
+        IResult::Done(&rest[rest.len()..], ())
     }
 
     /**
@@ -253,76 +324,93 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         }
     }
 
-    named!(
-        lookup_table,
-        alt_complete!(
-        sp!( do_parse!( tag_no_case!("LOOKUP_TABLE") >> n: name >> (n) ) ) |
-        eof!() => { |_| &b""[..] } |
-        eol
+    /// Parse the optional `LOOKUP_TABLE` line following a `SCALARS` header.
+    fn lookup_table(input: &[u8], ft: FileType) -> IResult<&[u8], &str> {
+        do_parse!(
+            input,
+            name: sp!(do_parse!(
+                tag_no_case!("LOOKUP_TABLE") >> name: map_res!(name, str::from_utf8) >> (name)
+            )) >> call!(data_start, ft)
+                >> (name)
         )
-    );
+    }
+
+    /// Parse the name, data type and optional number of components following `SCALARS`.
+    ///
+    /// These must be on a single line, since the first value of the data could otherwise be
+    /// mistaken for the number of components when it is omitted.
+    fn attribute_scalars_header(input: &[u8]) -> IResult<&[u8], (&str, ScalarType, Option<u32>)> {
+        sp!(
+            input,
+            do_parse!(
+                name: map_res!(name, str::from_utf8)
+                    >> dt: data_type
+                    >> num_comp: opt!(complete!(u32_b))
+                    >> (name, dt, num_comp)
+            )
+        )
+    }
 
     fn attribute_scalars(
         input: &[u8],
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        do_parse!(
             input,
-            do_parse!(
-                tag_no_case!("SCALARS")
-                    >> name: map_res!(name, str::from_utf8)
-                    >> dt: data_type
-                    >> num_comp: opt!(u32_b)
-                    >> lookup_tbl_name: opt!(map_res!(Self::lookup_table, str::from_utf8))
-                    >> data: call!(
-                        Self::attribute_data,
-                        num_comp.unwrap_or(1) as usize * num_elements,
-                        dt,
-                        ft
-                    )
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::Scalars {
-                            num_comp: num_comp.unwrap_or(1),
-                            lookup_table: lookup_tbl_name.and_then(|x| if x == "default" {
-                                None
-                            } else {
-                                Some(String::from(x))
-                            }),
-                        },
-                        data
-                    }))
-            )
+            sp!(tag_no_case!("SCALARS"))
+                >> header: call!(Self::attribute_scalars_header)
+                >> call!(data_start, ft)
+                >> lookup_tbl_name: opt!(complete!(call!(Self::lookup_table, ft)))
+                >> data: call!(
+                    Self::attribute_data,
+                    header.2.unwrap_or(1) as usize * num_elements,
+                    header.1,
+                    ft
+                )
+                >> opt!(Self::meta)
+                >> (Attribute::DataArray(DataArray {
+                    name: String::from(header.0),
+                    elem: ElementType::Scalars {
+                        num_comp: header.2.unwrap_or(1),
+                        lookup_table: lookup_tbl_name.and_then(|x| if x == "default" {
+                            None
+                        } else {
+                            Some(String::from(x))
+                        }),
+                    },
+                    data
+                }))
         )
     }
 
     fn attribute_lookup_table(input: &[u8], ft: FileType) -> IResult<&[u8], Attribute> {
-        ws!(
+        do_parse!(
             input,
-            do_parse!(
+            header: sp!(do_parse!(
                 tag_no_case!("LOOKUP_TABLE")
                     >> name: map_res!(name, str::from_utf8)
                     >> num_elements: u32_b
-                    >> data: call!(
-                        Self::attribute_data,
-                        4 * num_elements as usize,
-                        ScalarType::F32,
-                        ft
-                    )
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::LookupTable,
-                        data
-                    }))
-            )
+                    >> (name, num_elements)
+            ))
+                >> call!(data_start, ft)
+                >> data: call!(
+                    Self::attribute_color_scalars_data,
+                    4 * header.1 as usize,
+                    ft
+                )
+                >> opt!(Self::meta)
+                >> (Attribute::DataArray(DataArray {
+                    name: String::from(header.0),
+                    elem: ElementType::LookupTable,
+                    data: lookup_table_to_f32(data)
+                }))
         )
     }
 
-    /// Helper to `attribute_color_scalars`. This function calls the appropriate data parser for color
-    /// scalars.
+    /// Helper to `attribute_color_scalars` and `attribute_lookup_table`. This function calls the
+    /// appropriate data parser for color values, which are floats in ASCII files and unsigned chars
+    /// in binary files.
     fn attribute_color_scalars_data(
         input: &[u8],
         n: usize,
@@ -339,24 +427,50 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        do_parse!(
             input,
-            do_parse!(
+            header: sp!(do_parse!(
                 tag_no_case!("COLOR_SCALARS")
                     >> name: map_res!(name, str::from_utf8)
                     >> num_comp: u32_b
-                    >> data: call!(
-                        Self::attribute_color_scalars_data,
-                        num_comp as usize * num_elements,
-                        ft
-                    )
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::ColorScalars(num_comp),
-                        data
-                    }))
-            )
+                    >> (name, num_comp)
+            ))
+                >> call!(data_start, ft)
+                >> data: call!(
+                    Self::attribute_color_scalars_data,
+                    header.1 as usize * num_elements,
+                    ft
+                )
+                >> opt!(Self::meta)
+                >> (Attribute::DataArray(DataArray {
+                    name: String::from(header.0),
+                    elem: ElementType::ColorScalars(header.1),
+                    data
+                }))
+        )
+    }
+
+    /// Parse an attribute whose header consists of the given keyword, a name and a data type,
+    /// followed by `num_comp` values per element.
+    fn attribute_typed<'a>(
+        input: &'a [u8],
+        keyword: &'static str,
+        num_comp: usize,
+        num_elements: usize,
+        ft: FileType,
+    ) -> IResult<&'a [u8], (&'a str, IOBuffer)> {
+        do_parse!(
+            input,
+            header: sp!(do_parse!(
+                tag_no_case!(keyword)
+                    >> name: map_res!(name, str::from_utf8)
+                    >> dt: data_type
+                    >> (name, dt)
+            ))
+                >> call!(data_start, ft)
+                >> data: call!(Self::attribute_data, num_comp * num_elements, header.1, ft)
+                >> opt!(Self::meta)
+                >> ((header.0, data))
         )
     }
 
@@ -365,20 +479,14 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        map!(
             input,
-            do_parse!(
-                tag_no_case!("VECTORS")
-                    >> name: map_res!(name, str::from_utf8)
-                    >> dt: data_type
-                    >> data: call!(Self::attribute_data, 3 * num_elements, dt, ft)
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::Vectors,
-                        data
-                    }))
-            )
+            call!(Self::attribute_typed, "VECTORS", 3, num_elements, ft),
+            |(name, data)| Attribute::DataArray(DataArray {
+                name: String::from(name),
+                elem: ElementType::Vectors,
+                data
+            })
         )
     }
 
@@ -387,20 +495,14 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        map!(
             input,
-            do_parse!(
-                tag_no_case!("NORMALS")
-                    >> name: map_res!(name, str::from_utf8)
-                    >> dt: data_type
-                    >> data: call!(Self::attribute_data, 3 * num_elements, dt, ft)
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::Normals,
-                        data
-                    }))
-            )
+            call!(Self::attribute_typed, "NORMALS", 3, num_elements, ft),
+            |(name, data)| Attribute::DataArray(DataArray {
+                name: String::from(name),
+                elem: ElementType::Normals,
+                data
+            })
         )
     }
 
@@ -409,21 +511,28 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        do_parse!(
             input,
-            do_parse!(
+            header: sp!(do_parse!(
                 tag_no_case!("TEXTURE_COORDINATES")
                     >> name: map_res!(name, str::from_utf8)
                     >> dim: u32_b
                     >> dt: data_type
-                    >> data: call!(Self::attribute_data, dim as usize * num_elements, dt, ft)
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::TCoords(dim),
-                        data
-                    }))
-            )
+                    >> (name, dim, dt)
+            ))
+                >> call!(data_start, ft)
+                >> data: call!(
+                    Self::attribute_data,
+                    header.1 as usize * num_elements,
+                    header.2,
+                    ft
+                )
+                >> opt!(Self::meta)
+                >> (Attribute::DataArray(DataArray {
+                    name: String::from(header.0),
+                    elem: ElementType::TCoords(header.1),
+                    data
+                }))
         )
     }
 
@@ -432,44 +541,41 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         num_elements: usize,
         ft: FileType,
     ) -> IResult<&[u8], Attribute> {
-        ws!(
+        map!(
             input,
-            do_parse!(
-                tag_no_case!("TENSORS")
-                    >> name: map_res!(name, str::from_utf8)
-                    >> dt: data_type
-                    >> data: call!(Self::attribute_data, 9 * num_elements, dt, ft)
-                    >> opt!(Self::meta)
-                    >> (Attribute::DataArray(DataArray {
-                        name: String::from(name),
-                        elem: ElementType::Tensors,
-                        data
-                    }))
-            )
+            call!(Self::attribute_typed, "TENSORS", 9, num_elements, ft),
+            |(name, data)| Attribute::DataArray(DataArray {
+                name: String::from(name),
+                elem: ElementType::Tensors,
+                data
+            })
         )
     }
 
     fn attribute_field_array(input: &[u8], ft: FileType) -> IResult<&[u8], FieldArray> {
-        ws!(
+        do_parse!(
             input,
-            do_parse!(
-                name: map_res!(name, str::from_utf8)
-                    >> num_comp: u32_b
-                    >> num_tuples: u32_b
-                    >> dt: data_type
-                    >> data: call!(
-                        Self::attribute_data,
-                        (num_comp * num_tuples) as usize,
-                        dt,
-                        ft
-                    )
-                    >> opt!(Self::meta)
-                    >> (FieldArray {
-                        name: String::from(name),
-                        elem: num_comp,
-                        data
-                    })
-            )
+            opt!(complete!(multispace))
+                >> header: sp!(do_parse!(
+                    name: map_res!(name, str::from_utf8)
+                        >> num_comp: u32_b
+                        >> num_tuples: u32_b
+                        >> dt: data_type
+                        >> (name, num_comp, num_tuples, dt)
+                ))
+                >> call!(data_start, ft)
+                >> data: call!(
+                    Self::attribute_data,
+                    (header.1 * header.2) as usize,
+                    header.3,
+                    ft
+                )
+                >> opt!(Self::meta)
+                >> (FieldArray {
+                    name: String::from(header.0),
+                    elem: header.1,
+                    data
+                })
         )
     }
 
@@ -494,6 +600,18 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         )
     }
 
+    /// Parse the global field data following the `DATASET` keyword.
+    fn dataset_field(input: &[u8], ft: FileType) -> IResult<&[u8], Vec<FieldArray>> {
+        map_opt!(
+            input,
+            call!(Self::attribute_field, ft),
+            |attrib| match attrib {
+                Attribute::Field { data_array, .. } => Some(data_array),
+                _ => None,
+            }
+        )
+    }
+
     fn attribute(input: &[u8], num_elements: usize, ft: FileType) -> IResult<&[u8], Attribute> {
         ws!(
             input,
@@ -553,7 +671,8 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                             c
                         } else {
                             c2.unwrap_or_default()
-                        }
+                        },
+                        field: Vec::new(),
                     })
             )
         )
@@ -565,6 +684,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
             input,
             do_parse!(
                 tag_no_case!("STRUCTURED_POINTS")
+                    >> field: opt!(complete!(call!(Self::dataset_field, ft)))
                     >> parms:
                         permutation!(
                             do_parse!(
@@ -598,7 +718,10 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                         meta: None,
                         pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
                             extent: Extent::Dims(parms.0),
-                            data
+                            data: Attributes {
+                                field: field.unwrap_or_default(),
+                                ..data
+                            }
                         }))]
                     })
             )
@@ -611,6 +734,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
             input,
             do_parse!(
                 tag_no_case!("STRUCTURED_GRID")
+                    >> field: opt!(complete!(call!(Self::dataset_field, ft)))
                     >> dims: do_parse!(
                         tag_no_case!("DIMENSIONS")
                             >> nx: u32_b
@@ -624,7 +748,10 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                     >> (DataSet::inline(StructuredGridPiece {
                         extent: Extent::Dims(dims),
                         points,
-                        data
+                        data: Attributes {
+                            field: field.unwrap_or_default(),
+                            ..data
+                        }
                     }))
             )
         )
@@ -636,6 +763,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
             input,
             do_parse!(
                 tag_no_case!("RECTILINEAR_GRID")
+                    >> field: opt!(complete!(call!(Self::dataset_field, ft)))
                     >> dims: do_parse!(
                         tag_no_case!("DIMENSIONS")
                             >> nx: u32_b
@@ -651,7 +779,10 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                     >> (DataSet::inline(RectilinearGridPiece {
                         extent: Extent::Dims(dims),
                         coords: Coordinates { x, y, z },
-                        data
+                        data: Attributes {
+                            field: field.unwrap_or_default(),
+                            ..data
+                        }
                     }))
             )
         )
@@ -673,13 +804,13 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         }
     }
 
-    // Parse a single cell type. Essentially a byte converted to` CellType` enum.
+    // Parse a single cell type. Unknown ids are kept as `CellType::Unknown`.
     named!(pub cell_type<&[u8], CellType>,
-    map_opt!( u8_b, |x| CellType::from_u8(x) )
+    map!( u32_b, CellType::from_id )
     );
 
     named!(pub cell_type_binary<&[u8], CellType>,
-    map_opt!( i32::from_binary::<BO>, |x| CellType::from_u8(x as u8) )
+    map_opt!( i32::from_binary::<BO>, |x| u32::try_from(x).ok().map(CellType::from_id) )
     );
 
     fn cell_type_data(input: &[u8], n: usize, ft: FileType) -> IResult<&[u8], Vec<CellType>> {
@@ -695,28 +826,49 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
             input,
             ws!(tag_no_case!("CELL_TYPES"))
                 >> n: sp!(usize_b)
-                >> eol
+                >> call!(data_start, ft)
                 >> data: dbg!(call!(Self::cell_type_data, n, ft))
                 >> (data)
         )
     }
 
-    /// Parse UNSTRUCTURED_GRID type dataset
-    fn unstructured_grid(input: &[u8], ft: FileType) -> IResult<&[u8], DataSet> {
+    /// Parse UNSTRUCTURED_GRID type dataset, along with whether its ids are 64-bit integers.
+    fn unstructured_grid(input: &[u8], ft: FileType) -> IResult<&[u8], (DataSet, bool)> {
         ws!(
             input,
             do_parse!(
                 tag_no_case!("UNSTRUCTURED_GRID")
+                    >> field: opt!(complete!(call!(Self::dataset_field, ft)))
                     >> p: call!(Self::points, ft)
                     >> opt!(Self::meta)
-                    >> cell_verts: call!(Self::cell_verts, "CELLS", ft)
-                    >> types: call!(Self::cell_types, ft)
+                    >> cells: opt!(complete!(do_parse!(
+                        cell_verts: call!(Self::cell_verts, "CELLS", ft)
+                            >> types: call!(Self::cell_types, ft)
+                            >> ((Cells { cell_verts: cell_verts.0, types }, cell_verts.1))
+                    )))
                     >> data: call!(Self::attributes, ft)
-                    >> (DataSet::inline(UnstructuredGridPiece {
-                        points: p,
-                        cells: Cells { cell_verts, types },
-                        data
-                    }))
+                    >> ({
+                        // A grid without cells may omit the cell sections entirely.
+                        let (cells, int64_ids) = cells.unwrap_or_else(|| {
+                            let cells = Cells {
+                                cell_verts: VertexNumbers::Legacy {
+                                    num_cells: 0,
+                                    vertices: Vec::new(),
+                                },
+                                types: Vec::new(),
+                            };
+                            (cells, false)
+                        });
+                        let data_set = DataSet::inline(UnstructuredGridPiece {
+                            points: p,
+                            cells,
+                            data: Attributes {
+                                field: field.unwrap_or_default(),
+                                ..data
+                            }
+                        });
+                        (data_set, int64_ids)
+                    })
             )
         )
     }
@@ -725,7 +877,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
     fn poly_data_topo(
         input: &[u8],
         ft: FileType,
-    ) -> IResult<&[u8], (PolyDataTopology, VertexNumbers)> {
+    ) -> IResult<&[u8], (PolyDataTopology, (VertexNumbers, bool))> {
         alt_complete!(
             input,
             map!(call!(Self::cell_verts, "LINES", ft), |x| {
@@ -740,12 +892,13 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
         )
     }
 
-    /// Parse POLYDATA type dataset
+    /// Parse POLYDATA type dataset, along with whether its ids are 64-bit integers.
     #[allow(unused_comparisons)] // Suppress the warning of using 0 in many_m_n!(..)
-    fn poly_data(input: &[u8], ft: FileType) -> IResult<&[u8], DataSet> {
+    fn poly_data(input: &[u8], ft: FileType) -> IResult<&[u8], (DataSet, bool)> {
         do_parse!(
             input,
             tag_no_case!("POLYDATA")
+                >> field: opt!(complete!(call!(Self::dataset_field, ft)))
                 >> points: call!(Self::points, ft)
                 >> opt!(Self::meta)
                 >> topo1: opt!(call!(Self::poly_data_topo, ft))
@@ -757,6 +910,7 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                     // The following algorithm is just to avoid unnecessary cloning.
                     // There may be a simpler way to do this.
                     let mut topos = [topo1, topo2, topo3, topo4];
+                    let int64_ids = topos.iter().flatten().any(|topo| (topo.1).1);
                     let vertsi = topos
                         .iter()
                         .position(|x| x.as_ref().map(|x| x.0) == Some(PolyDataTopology::Verts));
@@ -793,19 +947,24 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
 
                     let [verts, lines, polys, strips] = topos;
 
-                    DataSet::inline(PolyDataPiece {
+                    let data_set = DataSet::inline(PolyDataPiece {
                         points,
-                        verts: verts.map(|x| x.1),
-                        lines: lines.map(|x| x.1),
-                        polys: polys.map(|x| x.1),
-                        strips: strips.map(|x| x.1),
-                        data,
-                    })
+                        verts: verts.map(|x| (x.1).0),
+                        lines: lines.map(|x| (x.1).0),
+                        polys: polys.map(|x| (x.1).0),
+                        strips: strips.map(|x| (x.1).0),
+                        data: Attributes {
+                            field: field.unwrap_or_default(),
+                            ..data
+                        },
+                    });
+                    (data_set, int64_ids)
                 })
         )
     }
 
-    fn dataset(input: &[u8], file_type: FileType) -> IResult<&[u8], DataSet> {
+    /// Parse a data set, along with whether its cells are stored with 64-bit ids.
+    fn dataset(input: &[u8], file_type: FileType) -> IResult<&[u8], (DataSet, bool)> {
         alt_complete!(
             input,
             do_parse!(
@@ -813,31 +972,31 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
                     >> whitespace
                     >> tn: alt!(
                         call!(Self::poly_data, file_type)
-                            | call!(Self::structured_grid, file_type)
-                            | call!(Self::rectilinear_grid, file_type)
-                            | call!(Self::structured_points, file_type)
+                            | map!(call!(Self::structured_grid, file_type), |d| (d, false))
+                            | map!(call!(Self::rectilinear_grid, file_type), |d| (d, false))
+                            | map!(call!(Self::structured_points, file_type), |d| (d, false))
                             | call!(Self::unstructured_grid, file_type)
                     )
                     >> (tn)
-            ) | call!(Self::field_data, file_type)
+            ) | map!(call!(Self::field_data, file_type), |d| (d, false))
         )
     }
 
-    /// Parse the entire vtk file
-    fn vtk(input: &[u8]) -> IResult<&[u8], Vtk> {
+    /// Parse the entire vtk file, along with whether its cells are stored with 64-bit ids.
+    fn vtk(input: &[u8]) -> IResult<&[u8], (Vtk, bool)> {
         complete!(
             input,
             ws!(do_parse!(
                 h: header
                     >> d: call!(Self::dataset, h.2)
-                    >> (Vtk {
+                    >> ((Vtk {
                         version: h.0,
                         // This is ignored in Legacy formats
                         byte_order: ByteOrderTag::new::<BO>(),
                         title: h.1,
-                        data: d,
+                        data: d.0,
                         file_path: None,
-                    })
+                    }, d.1))
             ))
         )
     }
@@ -845,12 +1004,12 @@ impl<BO: ByteOrder + 'static> VtkParser<BO> {
 
 /// Parse the entire VTK file using native endian byte order.
 pub fn parse_ne(input: &[u8]) -> IResult<&[u8], Vtk> {
-    VtkParser::<NativeEndian>::vtk(input)
+    VtkParser::<NativeEndian>::vtk(input).map(|(vtk, _)| vtk)
 }
 
 /// Parse the entire VTK file using little endian byte order.
 pub fn parse_le(input: &[u8]) -> IResult<&[u8], Vtk> {
-    VtkParser::<LittleEndian>::vtk(input)
+    VtkParser::<LittleEndian>::vtk(input).map(|(vtk, _)| vtk)
 }
 
 /// Parse the entire VTK file using big endian byte order.
@@ -858,7 +1017,213 @@ pub fn parse_le(input: &[u8]) -> IResult<&[u8], Vtk> {
 /// This is the default VTK byte order. Binary `.vtk` files produced by ParaView are in big endian
 /// form.
 pub fn parse_be(input: &[u8]) -> IResult<&[u8], Vtk> {
-    VtkParser::<BigEndian>::vtk(input)
+    VtkParser::<BigEndian>::vtk(input).map(|(vtk, _)| vtk)
+}
+
+/// Parse the entire VTK file in the given byte order, along with whether any cell offsets or
+/// connectivity are stored as 64-bit integers.
+pub(crate) fn parse_with_id_width(
+    input: &[u8],
+    byte_order: ByteOrderTag,
+) -> IResult<&[u8], (Vtk, bool)> {
+    match byte_order {
+        ByteOrderTag::BigEndian => VtkParser::<BigEndian>::vtk(input),
+        ByteOrderTag::LittleEndian => VtkParser::<LittleEndian>::vtk(input),
+    }
+}
+
+/// Returns the offset of the furthest position in `input` reported by the given error.
+///
+/// Errors without any position are reported at the start of the input.
+pub(crate) fn error_offset(input: &[u8], err: &nom::Err<&[u8]>) -> usize {
+    let (rest, next): (Option<&[u8]>, &[nom::Err<&[u8]>]) = match err {
+        nom::Err::Code(_) => (None, &[]),
+        nom::Err::Node(_, next) => (None, next),
+        nom::Err::Position(_, rest) => (Some(rest), &[]),
+        nom::Err::NodePosition(_, rest, next) => (Some(rest), next),
+    };
+    let offset = rest.map_or(0, |rest| input.len() - rest.len());
+    next.iter()
+        .map(|err| error_offset(input, err))
+        .fold(offset, usize::max)
+}
+
+/// Returns the scalar type with the given name as it appears in the headers of legacy files.
+pub(crate) fn scalar_type_from_name(name: &str) -> Option<ScalarType> {
+    match data_type(name.as_bytes()) {
+        IResult::Done([], scalar_type) => Some(scalar_type),
+        _ => None,
+    }
+}
+
+/// Parses `n` values of the given type stored in big endian binary format.
+pub(crate) fn parse_binary_data(
+    input: &[u8],
+    n: usize,
+    scalar_type: ScalarType,
+) -> Option<IOBuffer> {
+    match VtkParser::<BigEndian>::attribute_data(input, n, scalar_type, FileType::Binary) {
+        IResult::Done(_, data) => Some(data),
+        _ => None,
+    }
+}
+
+/// Guesses the byte order of the binary data in a legacy file.
+///
+/// The headers are followed up to the first binary array that can tell the byte orders apart.
+/// Its leading values are decoded in both byte orders, and the order producing more plausible
+/// values is chosen: integers are plausible if they are small, and floats if they are zero or of
+/// moderate magnitude. Arrays of bytes are skipped. Big endian, the byte order written by VTK, is
+/// returned for ASCII files and when no array decides.
+pub(crate) fn detect_byte_order(input: &[u8]) -> ByteOrderTag {
+    let header = |line: &[u8]| String::from_utf8_lossy(line).trim().to_ascii_uppercase();
+    // Skip the version and title lines, and check the file type.
+    let start = input.iter().position(|&b| b == b'#').unwrap_or(0);
+    let mut rest = match next_line(&input[start..]).and_then(|(_, rest)| next_line(rest)) {
+        Some((_, rest)) => rest,
+        None => return ByteOrderTag::BigEndian,
+    };
+    while let Some((line, tail)) = next_line(rest) {
+        rest = tail;
+        match header(line).as_str() {
+            "" => continue,
+            "BINARY" => break,
+            _ => return ByteOrderTag::BigEndian,
+        }
+    }
+
+    // Counts are taken from the file, so sizes saturate instead of overflowing.
+    let mut num_elements = 0usize;
+    while let Some((line, tail)) = next_line(rest) {
+        rest = tail;
+        let line = header(line);
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let num = |word: Option<&str>| word.and_then(|w| w.parse::<usize>().ok()).unwrap_or(0);
+        let data_type = |word: Option<&str>| word.and_then(scalar_type_from_name);
+        let (n, scalar_type) = match keyword {
+            "POINTS" => (num(words.next()).saturating_mul(3), data_type(words.next())),
+            "X_COORDINATES" | "Y_COORDINATES" | "Z_COORDINATES" => {
+                (num(words.next()), data_type(words.next()))
+            }
+            "CELLS" | "VERTICES" | "LINES" | "POLYGONS" | "TRIANGLE_STRIPS" => {
+                let size = num(words.nth(1));
+                match next_line(rest) {
+                    // Offsets and connectivity of version 5.1 are typed by their own headers.
+                    Some((line, _)) if header(line).starts_with("OFFSETS") => continue,
+                    _ => (size, Some(ScalarType::I32)),
+                }
+            }
+            "OFFSETS" | "CONNECTIVITY" => match words.next() {
+                Some("VTKTYPEINT64") => (usize::MAX, Some(ScalarType::I64)),
+                _ => (usize::MAX, Some(ScalarType::I32)),
+            },
+            "CELL_TYPES" => (num(words.next()), Some(ScalarType::I32)),
+            "POINT_DATA" | "CELL_DATA" => {
+                num_elements = num(words.next());
+                continue;
+            }
+            "SCALARS" => {
+                let scalar_type = data_type(words.nth(1));
+                let num_comp = words.next().map_or(1, |w| num(Some(w)));
+                if let Some((line, tail)) = next_line(rest) {
+                    if header(line).starts_with("LOOKUP_TABLE") {
+                        rest = tail;
+                    }
+                }
+                (num_comp.saturating_mul(num_elements), scalar_type)
+            }
+            "VECTORS" | "NORMALS" => (num_elements.saturating_mul(3), data_type(words.nth(1))),
+            "TENSORS" => (num_elements.saturating_mul(9), data_type(words.nth(1))),
+            "TEXTURE_COORDINATES" => {
+                let dim = num(words.nth(1));
+                (dim.saturating_mul(num_elements), data_type(words.next()))
+            }
+            "COLOR_SCALARS" => (
+                num(words.nth(1)).saturating_mul(num_elements),
+                Some(ScalarType::U8),
+            ),
+            "LOOKUP_TABLE" => (num(words.nth(1)).saturating_mul(4), Some(ScalarType::U8)),
+            "METADATA" => {
+                // The block is terminated by an empty line.
+                while let Some((line, tail)) = next_line(rest) {
+                    rest = tail;
+                    if header(line).is_empty() {
+                        break;
+                    }
+                }
+                continue;
+            }
+            "DATASET" | "DIMENSIONS" | "ORIGIN" | "SPACING" | "ASPECT_RATIO" | "FIELD" => continue,
+            // Field arrays are declared as `name num_comp num_tuples type`.
+            _ => {
+                let num_comp = num(words.next());
+                let num_tuples = num(words.next());
+                (num_comp.saturating_mul(num_tuples), data_type(words.next()))
+            }
+        };
+        let scalar_type = match scalar_type {
+            Some(scalar_type) => scalar_type,
+            None => break,
+        };
+        if let Some(byte_order) = compare_byte_orders(rest, n, scalar_type) {
+            return byte_order;
+        }
+        let size = match scalar_type {
+            ScalarType::Bit => n.div_ceil(8),
+            _ => n.saturating_mul(scalar_type.size()),
+        };
+        rest = &rest[size.min(rest.len())..];
+    }
+    ByteOrderTag::BigEndian
+}
+
+/// Splits off the next line, without its line ending.
+fn next_line(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    if input.is_empty() {
+        return None;
+    }
+    Some(match input.iter().position(|&b| b == b'\n') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => (input, &input[input.len()..]),
+    })
+}
+
+/// Decodes up to 256 leading values of the given array in both byte orders, and returns the one
+/// producing more plausible values, if any.
+fn compare_byte_orders(input: &[u8], n: usize, scalar_type: ScalarType) -> Option<ByteOrderTag> {
+    fn score<BO: ByteOrder>(input: &[u8], size: usize, scalar_type: ScalarType) -> usize {
+        let small = |x: i64| x.unsigned_abs() < 1 << 20;
+        let moderate = |x: f64| x == 0.0 || (1e-20..1e20).contains(&x.abs());
+        input
+            .chunks_exact(size)
+            .filter(|v| match scalar_type {
+                ScalarType::I16 => small(i64::from(BO::read_i16(v))),
+                ScalarType::U16 => small(i64::from(BO::read_u16(v))),
+                ScalarType::I32 => small(i64::from(BO::read_i32(v))),
+                ScalarType::U32 => small(i64::from(BO::read_u32(v))),
+                ScalarType::I64 | ScalarType::U64 => small(BO::read_i64(v)),
+                ScalarType::F32 => moderate(f64::from(BO::read_f32(v))),
+                ScalarType::F64 => moderate(BO::read_f64(v)),
+                ScalarType::Bit | ScalarType::U8 | ScalarType::I8 => true,
+            })
+            .count()
+    }
+    let size = scalar_type.size();
+    if size < 2 {
+        return None;
+    }
+    let input = &input[..input.len().min(n.min(256).saturating_mul(size))];
+    let be = score::<BigEndian>(input, size, scalar_type);
+    let le = score::<LittleEndian>(input, size, scalar_type);
+    match be.cmp(&le) {
+        std::cmp::Ordering::Greater => Some(ByteOrderTag::BigEndian),
+        std::cmp::Ordering::Less => Some(ByteOrderTag::LittleEndian),
+        std::cmp::Ordering::Equal => None,
+    }
 }
 
 #[cfg(test)]
@@ -908,10 +1273,13 @@ mod tests {
             f,
             IResult::Done(
                 "".as_bytes(),
-                VertexNumbers::Legacy {
-                    num_cells: 0,
-                    vertices: vec![]
-                }
+                (
+                    VertexNumbers::Legacy {
+                        num_cells: 0,
+                        vertices: vec![]
+                    },
+                    false
+                )
             )
         );
         let f = VtkParser::<NativeEndian>::cell_verts(in2.as_bytes(), "CELLS", FileType::ASCII);
@@ -919,10 +1287,56 @@ mod tests {
             f,
             IResult::Done(
                 "other".as_bytes(),
-                VertexNumbers::Legacy {
-                    num_cells: 1,
-                    vertices: vec![2, 1, 2]
-                }
+                (
+                    VertexNumbers::Legacy {
+                        num_cells: 1,
+                        vertices: vec![2, 1, 2]
+                    },
+                    false
+                )
+            )
+        );
+    }
+    #[test]
+    fn modern_cells_test() {
+        let in1 =
+            "CELLS 3 5\nOFFSETS vtktypeint64\n0 3 5\nCONNECTIVITY vtktypeint64\n0 1 2 2 3\nother";
+        let f = VtkParser::<NativeEndian>::cell_verts(in1.as_bytes(), "CELLS", FileType::ASCII);
+        assert_eq!(
+            f,
+            IResult::Done(
+                "other".as_bytes(),
+                (
+                    VertexNumbers::XML {
+                        offsets: vec![3, 5],
+                        connectivity: vec![0, 1, 2, 2, 3]
+                    },
+                    true
+                )
+            )
+        );
+
+        // Binary data with 32-bit indices.
+        let mut in2 = b"CELLS 2 2\nOFFSETS vtktypeint32\n".to_vec();
+        for i in &[0i32, 2] {
+            in2.extend_from_slice(&i.to_be_bytes());
+        }
+        in2.extend_from_slice(b"\nCONNECTIVITY vtktypeint32\n");
+        for i in &[4i32, 7] {
+            in2.extend_from_slice(&i.to_be_bytes());
+        }
+        let f = VtkParser::<BigEndian>::cell_verts(&in2, "CELLS", FileType::Binary);
+        assert_eq!(
+            f,
+            IResult::Done(
+                &[][..],
+                (
+                    VertexNumbers::XML {
+                        offsets: vec![2],
+                        connectivity: vec![4, 7]
+                    },
+                    false
+                )
             )
         );
     }
@@ -932,6 +1346,24 @@ mod tests {
         assert_eq!(f, IResult::Done("".as_bytes(), CellType::PolyVertex));
         let f = VtkParser::<NativeEndian>::cell_type("10".as_bytes());
         assert_eq!(f, IResult::Done("".as_bytes(), CellType::Tetra));
+        let f = VtkParser::<NativeEndian>::cell_type("0".as_bytes());
+        assert_eq!(f, IResult::Done("".as_bytes(), CellType::EmptyCell));
+        let f = VtkParser::<NativeEndian>::cell_type("37".as_bytes());
+        assert_eq!(
+            f,
+            IResult::Done("".as_bytes(), CellType::TriquadraticPyramid)
+        );
+        let f = VtkParser::<NativeEndian>::cell_type("72".as_bytes());
+        assert_eq!(
+            f,
+            IResult::Done("".as_bytes(), CellType::LagrangeHexahedron)
+        );
+        let f = VtkParser::<NativeEndian>::cell_type("300".as_bytes());
+        assert_eq!(f, IResult::Done("".as_bytes(), CellType::Unknown(300)));
+        let f = VtkParser::<BigEndian>::cell_type_binary(&[0, 0, 1, 10]);
+        assert_eq!(f, IResult::Done(&[][..], CellType::Unknown(266)));
+        let f = VtkParser::<BigEndian>::cell_type_binary(&[255, 255, 255, 255]);
+        assert!(f.is_err());
     }
 
     macro_rules! test {
@@ -976,7 +1408,7 @@ mod tests {
             data: Attributes::new(),
         });
 
-        test!(unstructured_grid(in1, FileType::ASCII) => ("other", out1));
+        test!(unstructured_grid(in1, FileType::ASCII) => ("other", (out1, false)));
     }
     #[test]
     fn attribute_test() {
@@ -1029,6 +1461,7 @@ mod tests {
         let out2 = Attributes {
             point: pt_res,
             cell: cl_res,
+            field: Vec::new(),
         };
         test!(attributes(in2, FileType::ASCII) => out2);
     }
@@ -1046,7 +1479,7 @@ mod tests {
             },
             data: Attributes::new(),
         });
-        test!(dataset(in1, FileType::ASCII) => out1);
+        test!(dataset(in1, FileType::ASCII) => ("", (out1, false)));
     }
     #[test]
     fn dataset_test() {
@@ -1063,7 +1496,7 @@ mod tests {
             },
             data: Attributes::new(),
         });
-        test!(dataset(in1, FileType::ASCII) => out1);
+        test!(dataset(in1, FileType::ASCII) => ("", (out1, false)));
     }
     #[test]
     fn dataset_crlf_test() {
@@ -1080,6 +1513,34 @@ mod tests {
             },
             data: Attributes::new(),
         });
-        test!(dataset(in1, FileType::ASCII) => out1);
+        test!(dataset(in1, FileType::ASCII) => ("", (out1, false)));
+    }
+    #[test]
+    fn int64_ids_test() {
+        let ids = |body: &str| {
+            let file = format!("# vtk DataFile Version 5.1\nids\nASCII\n{}", body);
+            match parse_with_id_width(file.as_bytes(), ByteOrderTag::BigEndian) {
+                IResult::Done(_, (_, int64_ids)) => int64_ids,
+                result => panic!("Failed to parse {:?}: {:?}", body, result),
+            }
+        };
+        let points = "DATASET POLYDATA\nPOINTS 3 float\n0 0 0 1 0 0 0 1 0\n";
+        assert!(ids(&format!(
+            "{}POLYGONS 2 3\nOFFSETS vtktypeint64\n0 3\nCONNECTIVITY vtktypeint64\n0 1 2\n",
+            points
+        )));
+        assert!(ids(&format!(
+            "{}LINES 2 2\noffsets \tVTKTYPEINT64\n0 2\nCONNECTIVITY vtktypeint32\n0 1\n",
+            points
+        )));
+        assert!(!ids(&format!(
+            "{}POLYGONS 2 3\nOFFSETS vtktypeint32\n0 3\nCONNECTIVITY vtktypeint32\n0 1 2\n",
+            points
+        )));
+        // Field arrays named like the type don't count.
+        assert!(!ids("FIELD FieldData 1\nvtktypeint64 1 1 long\n0\n"));
+        assert!(ids("DATASET UNSTRUCTURED_GRID\nPOINTS 1 float\n0 0 0\n\
+             CELLS 2 1\nOFFSETS vtktypeint64\n0 1\nCONNECTIVITY vtktypeint64\n0\n\
+             CELL_TYPES 1\n1\n"));
     }
 }
@@ -0,0 +1,615 @@
+//! Reading Plot3D grid and solution files.
+//!
+//! Plot3D is a structured grid format from NASA that is still common in CFD archives. A grid
+//! file (`.xyz`) stores the coordinates of one or more blocks, and a solution file (`.q`) the
+//! flow variables at the points of each block. A [`Grid`] reads both and converts each block into
+//! a structured grid piece, where the solution is stored in the point attributes named
+//! [`DENSITY`], [`MOMENTUM`] and [`STAGNATION_ENERGY`] and the free stream conditions in the field
+//! arrays [`FREE_STREAM_MACH`], [`ANGLE_OF_ATTACK`], [`REYNOLDS_NUMBER`] and [`TIME`], as named by
+//! VTK's own reader. Blanking values are stored in the point attribute [`IBLANK`].
+//!
+//! Plot3D files come in many variants: ASCII, Fortran unformatted or plain binary, little or big
+//! endian, in single or double precision, with one or several blocks, in two or three dimensions
+//! and with or without blanking. The [`Layout`] of a file is detected by trying all variants
+//! until one accounts for the whole file, or can be given explicitly to avoid ambiguities.
+//! Function files (`.f`) are not supported.
+//!
+//! This module is available with the `plot3d` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::plot3d::{self, Encoding, Grid};
+//!
+//! // A single block of 2 x 2 x 1 points.
+//! let grid = b"1\n2 2 1\n0 1 0 1\n0 0 1 1\n0 0 0 0\n";
+//! let solution = b"1\n2 2 1\n0.5 2 1e6 0\n1 1 1 1\n0.5 0.5 0.5 0.5\n0 0 0 0\n0 0 0 0\n2.5 2.5 2.5 2.5\n";
+//!
+//! let mut grid = Grid::parse(grid).expect("Failed to parse grid");
+//! assert_eq!(grid.layout.encoding, Encoding::Ascii);
+//! grid.parse_solution(solution).expect("Failed to parse solution");
+//!
+//! let block = &grid.blocks[0];
+//! assert_eq!(block.extent, Extent::Dims([2, 2, 1]));
+//! let momentum = block.data.point.iter().find(|a| a.name() == plot3d::MOMENTUM).unwrap();
+//! assert_eq!(momentum.data_array().unwrap().data.len(), 12);
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::model::{self, *};
+
+/// Name of the point attribute holding the density.
+pub const DENSITY: &str = "Density";
+/// Name of the point attribute holding the momentum vectors.
+pub const MOMENTUM: &str = "Momentum";
+/// Name of the point attribute holding the stagnation energy per unit volume.
+pub const STAGNATION_ENERGY: &str = "StagnationEnergy";
+/// Name of the point attribute holding the blanking values.
+pub const IBLANK: &str = "IBlank";
+/// Name of the field array holding the free stream Mach number.
+pub const FREE_STREAM_MACH: &str = "FreeStreamMach";
+/// Name of the field array holding the angle of attack.
+pub const ANGLE_OF_ATTACK: &str = "AngleOfAttack";
+/// Name of the field array holding the Reynolds number.
+pub const REYNOLDS_NUMBER: &str = "ReynoldsNumber";
+/// Name of the field array holding the time.
+pub const TIME: &str = "Time";
+
+/// Error produced when reading Plot3D files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// No layout accounts for the contents of the file, or the file doesn't match the given
+    /// layout.
+    UnknownLayout,
+    /// The blocks of a solution have different sizes than the blocks of the grid.
+    SolutionMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownLayout => write!(f, "Unrecognized Plot3D file layout"),
+            Error::SolutionMismatch => write!(f, "Solution blocks don't match the grid"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The encoding of numbers in a Plot3D file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// Whitespace separated numbers.
+    Ascii,
+    /// Fortran unformatted records, each preceded and followed by its size in bytes.
+    Fortran,
+    /// Numbers stored back to back.
+    Binary,
+}
+
+/// The variant of the Plot3D format used by a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Layout {
+    pub encoding: Encoding,
+    /// The byte order of binary files. This is ignored for ASCII files.
+    pub byte_order: model::ByteOrder,
+    /// Whether real numbers of binary files are stored in double precision. This is ignored for
+    /// ASCII files.
+    pub double_precision: bool,
+    /// Whether the file starts with the number of blocks.
+    pub multi_block: bool,
+    /// Whether the file stores two dimensional blocks, without `z` coordinates.
+    pub planar: bool,
+    /// Whether grid files store a blanking value for each point.
+    pub iblanks: bool,
+}
+
+impl Layout {
+    /// Returns all layouts that can be used to read the given data, in order of preference.
+    fn candidates(data: &[u8]) -> Vec<Layout> {
+        let ascii = data
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+        let encodings: &[_] = if ascii {
+            &[Encoding::Ascii]
+        } else {
+            &[Encoding::Fortran, Encoding::Binary]
+        };
+        let binary_variants: &[_] = if ascii {
+            &[(model::ByteOrder::BigEndian, false)]
+        } else {
+            &[
+                (model::ByteOrder::LittleEndian, false),
+                (model::ByteOrder::LittleEndian, true),
+                (model::ByteOrder::BigEndian, false),
+                (model::ByteOrder::BigEndian, true),
+            ]
+        };
+        let mut layouts = Vec::new();
+        for &encoding in encodings {
+            for &(byte_order, double_precision) in binary_variants {
+                for &multi_block in &[true, false] {
+                    for &planar in &[false, true] {
+                        for &iblanks in &[false, true] {
+                            layouts.push(Layout {
+                                encoding,
+                                byte_order,
+                                double_precision,
+                                multi_block,
+                                planar,
+                                iblanks,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        layouts
+    }
+}
+
+/// The blocks of a Plot3D grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    /// The layout of the grid file.
+    pub layout: Layout,
+    pub blocks: Vec<StructuredGridPiece>,
+}
+
+impl Grid {
+    /// Imports the grid file at the given path, detecting its layout.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<Grid, crate::Error> {
+        Ok(Grid::parse(&std::fs::read(file_path)?)?)
+    }
+
+    /// Parses a grid file, detecting its layout.
+    pub fn parse(data: &[u8]) -> Result<Grid, Error> {
+        Layout::candidates(data)
+            .into_iter()
+            .find_map(|layout| Grid::parse_with_layout(data, layout).ok())
+            .ok_or(Error::UnknownLayout)
+    }
+
+    /// Parses a grid file with the given layout.
+    pub fn parse_with_layout(data: &[u8], layout: Layout) -> Result<Grid, Error> {
+        let blocks = read_grid(&mut Reader::new(data, layout)).ok_or(Error::UnknownLayout)?;
+        Ok(Grid { layout, blocks })
+    }
+
+    /// Imports the solution file at the given path into the blocks of this grid.
+    ///
+    /// The layout of the solution is detected, preferring that of the grid.
+    pub fn import_solution(&mut self, file_path: impl AsRef<Path>) -> Result<(), crate::Error> {
+        Ok(self.parse_solution(&std::fs::read(file_path)?)?)
+    }
+
+    /// Parses a solution file into the blocks of this grid, detecting its layout.
+    ///
+    /// Solutions store no blanking values, so [`Layout::iblanks`] is ignored.
+    pub fn parse_solution(&mut self, data: &[u8]) -> Result<(), Error> {
+        let preferred = Layout {
+            iblanks: false,
+            ..self.layout
+        };
+        let mut layouts = vec![preferred];
+        layouts.extend(
+            Layout::candidates(data)
+                .into_iter()
+                .filter(|l| !l.iblanks && *l != preferred),
+        );
+        let blocks = layouts
+            .into_iter()
+            .find_map(|layout| read_solution(&mut Reader::new(data, layout)))
+            .ok_or(Error::UnknownLayout)?;
+        self.add_solution(blocks)
+    }
+
+    /// Parses a solution file with the given layout into the blocks of this grid.
+    pub fn parse_solution_with_layout(&mut self, data: &[u8], layout: Layout) -> Result<(), Error> {
+        let blocks = read_solution(&mut Reader::new(data, layout)).ok_or(Error::UnknownLayout)?;
+        self.add_solution(blocks)
+    }
+
+    fn add_solution(&mut self, solution: Vec<SolutionBlock>) -> Result<(), Error> {
+        let matches = solution.len() == self.blocks.len()
+            && solution
+                .iter()
+                .zip(&self.blocks)
+                .all(|(s, b)| Extent::Dims(s.dims) == b.extent);
+        if !matches {
+            return Err(Error::SolutionMismatch);
+        }
+        for (solution, block) in solution.into_iter().zip(&mut self.blocks) {
+            let n = solution.q.len() / if solution.planar { 4 } else { 5 };
+            let q = |var: usize| &solution.q[var * n..(var + 1) * n];
+            let mut momentum = Vec::with_capacity(3 * n);
+            for i in 0..n {
+                momentum.push(q(1)[i]);
+                momentum.push(q(2)[i]);
+                momentum.push(if solution.planar { 0.0 } else { q(3)[i] });
+            }
+            let energy = if solution.planar { q(3) } else { q(4) };
+            let point = &mut block.data.point;
+            point.push(Attribute::scalars(DENSITY, 1).with_data(q(0).to_vec()));
+            point.push(Attribute::vectors(MOMENTUM).with_data(momentum));
+            point.push(Attribute::scalars(STAGNATION_ENERGY, 1).with_data(energy.to_vec()));
+            let names = [FREE_STREAM_MACH, ANGLE_OF_ATTACK, REYNOLDS_NUMBER, TIME];
+            for (name, &value) in names.iter().zip(&solution.conditions) {
+                block
+                    .data
+                    .field
+                    .push(FieldArray::new(*name, 1).with_data(vec![value]));
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts each block into a model with a single structured grid piece.
+    pub fn into_vtk_blocks(self) -> Vec<Vtk> {
+        self.blocks
+            .into_iter()
+            .enumerate()
+            .map(|(i, piece)| Vtk {
+                version: Version::new((4, 2)),
+                byte_order: model::ByteOrder::BigEndian,
+                title: format!("Block {}", i + 1),
+                file_path: None,
+                data: DataSet::inline(piece),
+            })
+            .collect()
+    }
+
+    /// Converts the blocks into a multiblock data set with blocks named `Block 1`, `Block 2`
+    /// and so on.
+    #[cfg(feature = "xml")]
+    pub fn into_multi_block(self) -> crate::vtm::MultiBlockDataSet {
+        use crate::vtm::{Block, BlockDataSet, MultiBlockDataSet};
+        MultiBlockDataSet {
+            blocks: self
+                .into_vtk_blocks()
+                .into_iter()
+                .map(|vtk| Block::DataSet(BlockDataSet::inline(vtk.title.clone(), vtk)))
+                .collect(),
+        }
+    }
+}
+
+/// The solution of a single block.
+struct SolutionBlock {
+    dims: [u32; 3],
+    planar: bool,
+    /// The free stream Mach number, angle of attack, Reynolds number and time.
+    conditions: [f64; 4],
+    /// The values of each variable for all points, one variable after the other.
+    q: Vec<f64>,
+}
+
+/// Reads the numbers of a Plot3D file.
+///
+/// All methods return `None` if the data doesn't match the layout.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    layout: Layout,
+    /// The end of the current Fortran record.
+    record_end: usize,
+    /// The size of the current Fortran record.
+    record_len: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], layout: Layout) -> Self {
+        Reader {
+            data,
+            pos: 0,
+            layout,
+            record_end: 0,
+            record_len: 0,
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn token(&mut self) -> Option<&'a str> {
+        let is_separator = |b: &u8| b.is_ascii_whitespace() || *b == b',';
+        while self.data.get(self.pos).is_some_and(is_separator) {
+            self.pos += 1;
+        }
+        let len = self.data[self.pos..]
+            .iter()
+            .position(is_separator)
+            .unwrap_or(self.data.len() - self.pos);
+        let token = self.bytes(len).filter(|t| !t.is_empty())?;
+        std::str::from_utf8(token).ok()
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.bytes(4)?;
+        Some(match self.layout.byte_order {
+            model::ByteOrder::BigEndian => BigEndian::read_u32(bytes),
+            model::ByteOrder::LittleEndian => LittleEndian::read_u32(bytes),
+        })
+    }
+
+    fn begin_record(&mut self) -> Option<()> {
+        if self.layout.encoding == Encoding::Fortran {
+            let len = self.u32()? as usize;
+            self.record_end = self
+                .pos
+                .checked_add(len)
+                .filter(|&end| end <= self.data.len())?;
+            self.record_len = len;
+        }
+        Some(())
+    }
+
+    fn end_record(&mut self) -> Option<()> {
+        if self.layout.encoding == Encoding::Fortran {
+            let len = self.record_len;
+            if self.pos != self.record_end || self.u32()? as usize != len {
+                return None;
+            }
+        }
+        Some(())
+    }
+
+    fn int(&mut self) -> Option<i32> {
+        match self.layout.encoding {
+            Encoding::Ascii => self.token()?.parse().ok(),
+            _ => self.u32().map(|i| i as i32),
+        }
+    }
+
+    /// Reads `n` values using `read`, checking first that the data can hold them.
+    fn values<T>(
+        &mut self,
+        n: usize,
+        mut read: impl FnMut(&mut Self) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        self.can_hold(n)?;
+        (0..n).map(|_| read(self)).collect()
+    }
+
+    /// Checks that the remaining data can hold `n` values, to avoid huge allocations when trying
+    /// the wrong layout.
+    fn can_hold(&self, n: usize) -> Option<()> {
+        // ASCII values take at least two bytes including the separator.
+        let min_size = match self.layout.encoding {
+            Encoding::Ascii => 2,
+            _ => 4,
+        };
+        (n.checked_mul(min_size)? <= self.data.len() - self.pos + 1).then_some(())
+    }
+
+    fn real(&mut self) -> Option<f64> {
+        match self.layout.encoding {
+            // Fortran writes exponents of double precision values with `D`.
+            Encoding::Ascii => self.token()?.replace(['D', 'd'], "e").parse().ok(),
+            _ => {
+                let size = if self.layout.double_precision { 8 } else { 4 };
+                let bytes = self.bytes(size)?;
+                Some(match (self.layout.byte_order, size) {
+                    (model::ByteOrder::BigEndian, 4) => f64::from(BigEndian::read_f32(bytes)),
+                    (model::ByteOrder::LittleEndian, 4) => f64::from(LittleEndian::read_f32(bytes)),
+                    (model::ByteOrder::BigEndian, _) => BigEndian::read_f64(bytes),
+                    (model::ByteOrder::LittleEndian, _) => LittleEndian::read_f64(bytes),
+                })
+            }
+        }
+    }
+
+    /// Checks that all data has been read.
+    fn finish(&mut self) -> Option<()> {
+        match self.layout.encoding {
+            Encoding::Ascii => self.data[self.pos..]
+                .iter()
+                .all(u8::is_ascii_whitespace)
+                .then_some(()),
+            _ => (self.pos == self.data.len()).then_some(()),
+        }
+    }
+
+    /// Reads the number of blocks and their dimensions.
+    fn header(&mut self) -> Option<Vec<[u32; 3]>> {
+        let num_blocks = if self.layout.multi_block {
+            self.begin_record()?;
+            let n = usize::try_from(self.int()?).ok()?;
+            self.end_record()?;
+            n
+        } else {
+            1
+        };
+        let num_dims = if self.layout.planar { 2 } else { 3 };
+        self.begin_record()?;
+        let dims = self.values(num_blocks * num_dims, |r| {
+            r.int()
+                .and_then(|d| u32::try_from(d).ok())
+                .filter(|&d| d > 0)
+        })?;
+        self.end_record()?;
+        Some(
+            dims.chunks(num_dims)
+                .map(|d| [d[0], d[1], d.get(2).copied().unwrap_or(1)])
+                .collect(),
+        )
+    }
+}
+
+fn num_points(dims: [u32; 3]) -> Option<usize> {
+    dims.iter()
+        .try_fold(1usize, |n, &d| n.checked_mul(d as usize))
+}
+
+fn read_grid(r: &mut Reader) -> Option<Vec<StructuredGridPiece>> {
+    let mut blocks = Vec::new();
+    for dims in r.header()? {
+        let n = num_points(dims)?;
+        r.begin_record()?;
+        r.can_hold(n)?;
+        let mut points = vec![0.0; 3 * n];
+        for axis in 0..if r.layout.planar { 2 } else { 3 } {
+            for (i, x) in r.values(n, Reader::real)?.into_iter().enumerate() {
+                points[3 * i + axis] = x;
+            }
+        }
+        let mut data = Attributes::new();
+        if r.layout.iblanks {
+            let iblanks = r.values(n, Reader::int)?;
+            data.point
+                .push(Attribute::scalars(IBLANK, 1).with_data(iblanks));
+        }
+        r.end_record()?;
+        blocks.push(StructuredGridPiece {
+            extent: Extent::Dims(dims),
+            points: points.into(),
+            data,
+        });
+    }
+    r.finish()?;
+    Some(blocks)
+}
+
+fn read_solution(r: &mut Reader) -> Option<Vec<SolutionBlock>> {
+    let planar = r.layout.planar;
+    let mut blocks = Vec::new();
+    for dims in r.header()? {
+        let n = num_points(dims)?;
+        r.begin_record()?;
+        let mut conditions = [0.0; 4];
+        for c in conditions.iter_mut() {
+            *c = r.real()?;
+        }
+        r.end_record()?;
+        r.begin_record()?;
+        let q = r.values(n.checked_mul(if planar { 4 } else { 5 })?, Reader::real)?;
+        r.end_record()?;
+        blocks.push(SolutionBlock {
+            dims,
+            planar,
+            conditions,
+            q,
+        });
+    }
+    r.finish()?;
+    Some(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps the given bytes into a little endian Fortran record.
+    fn record(bytes: &[u8]) -> Vec<u8> {
+        let len = (bytes.len() as u32).to_le_bytes();
+        [&len[..], bytes, &len[..]].concat()
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn reals(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn fortran_planar_multi_block() {
+        let mut grid = record(&ints(&[2]));
+        grid.extend(record(&ints(&[2, 1, 1, 2])));
+        grid.extend(record(
+            &[reals(&[0.0, 1.0, 0.0, 0.0]), ints(&[1, 0])].concat(),
+        ));
+        grid.extend(record(
+            &[reals(&[2.0, 2.0, 0.0, 1.0]), ints(&[1, 1])].concat(),
+        ));
+
+        let mut grid = Grid::parse(&grid).unwrap();
+        assert_eq!(
+            grid.layout,
+            Layout {
+                encoding: Encoding::Fortran,
+                byte_order: model::ByteOrder::LittleEndian,
+                double_precision: false,
+                multi_block: true,
+                planar: true,
+                iblanks: true,
+            }
+        );
+        assert_eq!(grid.blocks.len(), 2);
+        assert_eq!(grid.blocks[1].extent, Extent::Dims([1, 2, 1]));
+        assert_eq!(
+            grid.blocks[1].points,
+            vec![2.0f64, 0.0, 0.0, 2.0, 1.0, 0.0].into()
+        );
+        assert_eq!(
+            grid.blocks[0].data.point,
+            vec![Attribute::scalars(IBLANK, 1).with_data(vec![1i32, 0])]
+        );
+
+        let mut solution = record(&ints(&[2]));
+        solution.extend(record(&ints(&[2, 1, 1, 2])));
+        for _ in 0..2 {
+            solution.extend(record(&reals(&[0.8, 1.0, 1e5, 0.5])));
+            solution.extend(record(&reals(&[1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0])));
+        }
+        grid.parse_solution(&solution).unwrap();
+
+        let data = &grid.blocks[0].data;
+        assert_eq!(
+            data.point[2],
+            Attribute::vectors(MOMENTUM).with_data(vec![2.0f64, 3.0, 0.0, 2.0, 3.0, 0.0])
+        );
+        assert_eq!(
+            data.point[3],
+            Attribute::scalars(STAGNATION_ENERGY, 1).with_data(vec![4.0f64, 4.0])
+        );
+        assert_eq!(
+            data.field[1],
+            FieldArray::new(ANGLE_OF_ATTACK, 1).with_data(vec![1.0f64])
+        );
+    }
+
+    #[test]
+    fn binary_double_precision() {
+        let mut grid: Vec<u8> = [1u32, 1, 2].iter().flat_map(|v| v.to_be_bytes()).collect();
+        for x in &[0.0f64, 0.0, 0.0, 0.0, 0.5, 1.0] {
+            grid.extend(&x.to_be_bytes());
+        }
+        let grid = Grid::parse(&grid).unwrap();
+        assert_eq!(grid.layout.encoding, Encoding::Binary);
+        assert_eq!(grid.layout.byte_order, model::ByteOrder::BigEndian);
+        assert!(grid.layout.double_precision);
+        assert!(!grid.layout.multi_block);
+
+        let vtk = grid.into_vtk_blocks().remove(0);
+        match vtk.data {
+            DataSet::StructuredGrid { pieces, .. } => match &pieces[0] {
+                Piece::Inline(piece) => {
+                    assert_eq!(piece.points, vec![0.0f64, 0.0, 0.5, 0.0, 0.0, 1.0].into())
+                }
+                piece => panic!("Unexpected piece: {:?}", piece),
+            },
+            data => panic!("Unexpected data set: {:?}", data),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Grid::parse(b"1\n2 2 1\n0 1 0"), Err(Error::UnknownLayout));
+        let mut grid = Grid::parse(b"2 1 1\n0 1\n0 0\n0 0\n").unwrap();
+        let solution = b"1 1 1\n0 0 0 0\n1 1 1 1 1\n";
+        assert_eq!(grid.parse_solution(solution), Err(Error::SolutionMismatch));
+        assert!(grid.blocks[0].data.point.is_empty());
+    }
+}
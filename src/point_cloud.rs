@@ -0,0 +1,187 @@
+//! Point clouds with per-point attributes.
+//!
+//! VTK has no dedicated point cloud data set. Instead point clouds are stored as `PolyData` whose
+//! only topology is a `VERTICES` section with one vertex per point. A [`PointCloud`] builds such
+//! data sets from a list of positions, and recognizes them when reading files back in.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::point_cloud::PointCloud;
+//!
+//! let cloud = PointCloud::new(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+//!     .with_attribute(Attribute::scalars("intensity", 1).with_data(vec![0.1f32, 0.5, 0.9]))
+//!     .unwrap();
+//!
+//! let piece = cloud.into_piece();
+//! assert!(piece.is_point_cloud());
+//! assert_eq!(piece.num_verts(), 3);
+//!
+//! let cloud = PointCloud::from_piece(piece).unwrap();
+//! assert_eq!(cloud.points[1], [1.0, 0.0, 0.0]);
+//! assert!(cloud.attribute("intensity").is_some());
+//! ```
+
+use crate::model::{self, *};
+
+/// A set of points with per-point attributes.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct PointCloud {
+    /// Positions of the points.
+    pub points: Vec<[f64; 3]>,
+    /// Point attributes, each with one tuple per point.
+    pub attributes: Vec<Attribute>,
+}
+
+impl PointCloud {
+    /// Constructs a point cloud from the given positions without any attributes.
+    pub fn new(points: Vec<[f64; 3]>) -> Self {
+        PointCloud {
+            points,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Returns the number of points in this point cloud.
+    pub fn num_points(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Adds an attribute to this point cloud.
+    ///
+    /// An error is returned if the attribute doesn't have exactly one tuple per point.
+    pub fn push_attribute(&mut self, attribute: Attribute) -> Result<(), Error> {
+        model::validate_attribute_sizes(std::slice::from_ref(&attribute), self.num_points())?;
+        self.attributes.push(attribute);
+        Ok(())
+    }
+
+    /// Adds an attribute to this point cloud.
+    ///
+    /// This is the builder variant of [`push_attribute`](Self::push_attribute).
+    pub fn with_attribute(mut self, attribute: Attribute) -> Result<Self, Error> {
+        self.push_attribute(attribute)?;
+        Ok(self)
+    }
+
+    /// Returns the attribute with the given name if any.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attrib| attrib.name() == name)
+    }
+
+    /// Converts this point cloud into a poly data piece with a single vertex per point.
+    pub fn into_piece(self) -> PolyDataPiece {
+        let num_points = self.points.len() as u64;
+        let points: Vec<f64> = self.points.iter().flatten().copied().collect();
+        PolyDataPiece {
+            points: points.into(),
+            verts: Some(VertexNumbers::XML {
+                connectivity: (0..num_points).collect(),
+                offsets: (1..=num_points).collect(),
+            }),
+            data: Attributes {
+                point: self.attributes,
+                cell: Vec::new(),
+                field: Vec::new(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Converts this point cloud into a one piece poly data set.
+    pub fn into_data_set(self) -> DataSet {
+        DataSet::inline(self.into_piece())
+    }
+
+    /// Extracts a point cloud from the given piece.
+    ///
+    /// Returns `None` if the piece contains any topology other than vertices (see
+    /// [`PolyDataPiece::is_point_cloud`]) or if its points are not floating point values.
+    /// All points of the piece are kept, and cell attributes are discarded.
+    pub fn from_piece(piece: PolyDataPiece) -> Option<Self> {
+        if !piece.is_point_cloud() {
+            return None;
+        }
+        let points = match piece.points {
+            IOBuffer::F64(points) => points,
+            IOBuffer::F32(points) => points.into_iter().map(f64::from).collect(),
+            _ => return None,
+        };
+        Some(PointCloud {
+            points: points.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect(),
+            attributes: piece.data.point,
+        })
+    }
+
+    /// Extracts a point cloud from a poly data set.
+    ///
+    /// Multiple pieces are merged into one. Returns `None` if the data set is not poly data, if any
+    /// of its pieces cannot be loaded or merged, or if the merged piece is not a point cloud.
+    pub fn from_data_set(data_set: DataSet) -> Option<Self> {
+        match data_set {
+            DataSet::PolyData { pieces, .. } => {
+                let pieces = pieces
+                    .into_iter()
+                    .map(|piece| piece.into_loaded_piece_data(None))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()?;
+                PointCloud::from_piece(PolyDataPiece::merge(pieces).ok()?)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<PointCloud> for PolyDataPiece {
+    fn from(cloud: PointCloud) -> PolyDataPiece {
+        cloud.into_piece()
+    }
+}
+
+impl From<PointCloud> for DataSet {
+    fn from(cloud: PointCloud) -> DataSet {
+        cloud.into_data_set()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_sizes() {
+        let mut cloud = PointCloud::new(vec![[0.0; 3]; 4]);
+        cloud
+            .push_attribute(Attribute::vectors("velocity").with_data(vec![0.0f32; 12]))
+            .unwrap();
+        assert!(matches!(
+            cloud.push_attribute(Attribute::scalars("mass", 1).with_data(vec![1.0f32; 3])),
+            Err(Error::AttributeSizeMismatch {
+                expected: 4,
+                actual: 3,
+                ..
+            })
+        ));
+        assert_eq!(cloud.attributes.len(), 1);
+    }
+
+    #[test]
+    fn recognize_point_cloud() {
+        let cloud = PointCloud::new(vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let data_set = DataSet::from_pieces(vec![cloud.clone(), cloud.clone()]).unwrap();
+        let merged = PointCloud::from_data_set(data_set).unwrap();
+        assert_eq!(merged.num_points(), 4);
+        assert_eq!(merged.points[2], [1.0, 2.0, 3.0]);
+
+        // Pieces with other topology are not point clouds.
+        let mut piece = cloud.into_piece();
+        piece.lines = Some(VertexNumbers::XML {
+            connectivity: vec![0, 1],
+            offsets: vec![2],
+        });
+        assert!(!piece.is_point_cloud());
+        assert_eq!(PointCloud::from_piece(piece), None);
+        assert!(!PolyDataPiece::default().is_point_cloud());
+    }
+}
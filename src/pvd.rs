@@ -0,0 +1,365 @@
+//! Reading and writing ParaView collection (`.pvd`) files.
+//!
+//! A `.pvd` file lists a number of data sets stored in separate VTK files, each tagged with a time
+//! step value, a group name and a part number. These are commonly used to describe time series,
+//! where each time step is stored in its own file.
+//!
+//! A [`Collection`] can be imported lazily, where only the collection file itself is read, or
+//! eagerly, where all referenced data sets are loaded immediately. Long series can also be loaded
+//! one data set at a time using [`Collection::into_vtk_iter`]. Time series can be written along
+//! with their collection file using a [`TimeSeriesWriter`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::pvd::Collection;
+//!
+//! let mut collection = Collection::import("output/sim.pvd").expect("Failed to load collection");
+//! for data_set in collection.data_sets.iter_mut() {
+//!     println!("t = {}: {}", data_set.timestep, data_set.file.display());
+//!     let vtk = data_set.load().expect("Failed to load data set");
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::model::{DataSet, Vtk};
+use crate::xml;
+use crate::Error;
+
+/// A data set referenced by a `.pvd` collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectionEntry {
+    /// The time step value of this data set.
+    pub timestep: f64,
+    /// The group this data set belongs to.
+    pub group: String,
+    /// The part number of this data set within its time step and group.
+    pub part: u32,
+    /// The path to the referenced file.
+    ///
+    /// Relative paths in the collection file are resolved against the directory containing it.
+    pub file: PathBuf,
+    /// The loaded data set, if any.
+    pub data: Option<Vtk>,
+}
+
+impl CollectionEntry {
+    /// Loads the referenced data set if it hasn't been loaded already.
+    pub fn load(&mut self) -> Result<&Vtk, Error> {
+        if self.data.is_none() {
+            self.data = Some(Vtk::import(&self.file)?);
+        }
+        Ok(self.data.as_ref().unwrap())
+    }
+
+    /// Consumes this entry and returns the referenced data set, loading it if necessary.
+    pub fn into_vtk(self) -> Result<Vtk, Error> {
+        match self.data {
+            Some(vtk) => Ok(vtk),
+            None => Vtk::import(&self.file),
+        }
+    }
+}
+
+/// Writes a series of snapshots along with a `.pvd` collection file indexing them.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vtkio::model::*;
+/// use vtkio::pvd::TimeSeriesWriter;
+///
+/// let vtk = Vtk::import("assets/hexahedron.vtu").expect("Failed to load data set");
+/// let mut writer = TimeSeriesWriter::new("output", "sim").unwrap();
+/// for step in 0..10 {
+///     // Update `vtk` here.
+///     writer.write(0.1 * step as f64, vtk.clone()).unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeSeriesWriter {
+    dir: PathBuf,
+    base_name: String,
+    data_sets: Vec<PvdDataSet>,
+}
+
+impl TimeSeriesWriter {
+    /// Creates a new writer for a series of files in `dir` named after `base_name`.
+    ///
+    /// The directory is created if it doesn't already exist.
+    pub fn new(
+        dir: impl AsRef<Path>,
+        base_name: impl Into<String>,
+    ) -> Result<TimeSeriesWriter, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(TimeSeriesWriter {
+            dir,
+            base_name: base_name.into(),
+            data_sets: Vec::new(),
+        })
+    }
+
+    /// Writes a snapshot of the given data set at time `time`.
+    ///
+    /// The snapshot is written in XML format to `<base_name>_<n>.<ext>`, where `n` is the number of
+    /// previously written snapshots and the extension is determined by the type of data set.
+    /// The `<base_name>.pvd` collection file is then replaced by one including the new snapshot,
+    /// so it stays readable if writing is interrupted.
+    ///
+    /// Returns the path to the written snapshot.
+    pub fn write(&mut self, time: f64, vtk: Vtk) -> Result<PathBuf, Error> {
+        let file = format!(
+            "{}_{}.{}",
+            self.base_name,
+            self.data_sets.len(),
+            data_set_extension(&vtk.data)?
+        );
+        let path = self.dir.join(&file);
+        vtk.export(&path)?;
+        self.data_sets.push(PvdDataSet {
+            timestep: time,
+            group: String::new(),
+            part: 0,
+            file,
+            mesh: None,
+        });
+        write(&self.pvd_path(), &self.data_sets)?;
+        Ok(path)
+    }
+
+    /// The path to the `.pvd` collection file listing all written snapshots.
+    pub fn pvd_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.pvd", self.base_name))
+    }
+}
+
+/// The contents of a `.pvd` collection file.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Collection {
+    /// Data sets in the order they are listed in the collection file.
+    pub data_sets: Vec<CollectionEntry>,
+}
+
+impl Collection {
+    /// Reads the given `.pvd` file without loading any of the referenced data sets.
+    ///
+    /// Data sets can be loaded later using [`CollectionEntry::load`] or [`Collection::load_all`].
+    pub fn import(file_path: impl AsRef<Path>) -> Result<Collection, Error> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let data_sets = parse(file_path)?
+            .into_iter()
+            .map(|data_set| CollectionEntry {
+                timestep: data_set.timestep,
+                group: data_set.group,
+                part: data_set.part,
+                file: dir.join(data_set.file),
+                data: None,
+            })
+            .collect();
+        Ok(Collection { data_sets })
+    }
+
+    /// Reads the given `.pvd` file and loads all of the referenced data sets.
+    pub fn import_loaded(file_path: impl AsRef<Path>) -> Result<Collection, Error> {
+        let mut collection = Collection::import(file_path)?;
+        collection.load_all()?;
+        Ok(collection)
+    }
+
+    /// Loads all data sets that haven't been loaded yet.
+    pub fn load_all(&mut self) -> Result<(), Error> {
+        for data_set in self.data_sets.iter_mut() {
+            data_set.load()?;
+        }
+        Ok(())
+    }
+
+    /// Consumes this collection and returns an iterator loading its data sets one at a time.
+    ///
+    /// Data sets are yielded in the order they are listed, and those already loaded are yielded as
+    /// is. A data set that fails to load yields an error without ending the iteration, so the rest
+    /// of a long time series can still be processed.
+    pub fn into_vtk_iter(self) -> impl ExactSizeIterator<Item = Result<Vtk, Error>> {
+        self.data_sets.into_iter().map(CollectionEntry::into_vtk)
+    }
+
+    /// Returns the distinct time step values in this collection in increasing order.
+    pub fn timesteps(&self) -> Vec<f64> {
+        let mut timesteps: Vec<f64> = self.data_sets.iter().map(|d| d.timestep).collect();
+        timesteps.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        timesteps.dedup();
+        timesteps
+    }
+
+    /// Returns all data sets with the given time step value.
+    pub fn at_timestep(&self, timestep: f64) -> impl Iterator<Item = &CollectionEntry> {
+        self.data_sets
+            .iter()
+            .filter(move |data_set| data_set.timestep == timestep)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PvdFile {
+    #[serde(rename = "Collection")]
+    collection: PvdCollection,
+}
+
+#[derive(Debug, Deserialize)]
+struct PvdCollection {
+    #[serde(rename = "DataSet", default)]
+    data_sets: Vec<PvdDataSet>,
+}
+
+/// A raw `DataSet` entry of a `.pvd` file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub(crate) struct PvdDataSet {
+    #[serde(default)]
+    pub timestep: f64,
+    #[serde(default)]
+    pub group: String,
+    #[serde(default)]
+    pub part: u32,
+    pub file: String,
+    /// The mesh file used by field only data sets written by a
+    /// [`CheckpointWriter`](crate::checkpoint::CheckpointWriter).
+    pub mesh: Option<String>,
+}
+
+/// Parses the `DataSet` entries of the given `.pvd` file.
+pub(crate) fn parse(file_path: &Path) -> Result<Vec<PvdDataSet>, Error> {
+    let f = std::io::BufReader::new(std::fs::File::open(file_path)?);
+    let mut reader = quick_xml::Reader::from_reader(f);
+    reader.expand_empty_elements(true).trim_text(true);
+    let pvd = PvdFile::deserialize(&mut quick_xml::de::Deserializer::new(reader))
+        .map_err(xml::Error::from)?;
+    Ok(pvd.collection.data_sets)
+}
+
+/// Writes a `.pvd` collection file listing the given data sets.
+///
+/// The contents are written to a temporary file next to `file_path`, which then replaces it, so
+/// readers never see a partially written collection.
+pub(crate) fn write(file_path: &Path, data_sets: &[PvdDataSet]) -> Result<(), Error> {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\"?>\n");
+    out.push_str("<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">\n");
+    out.push_str("  <Collection>\n");
+    for data_set in data_sets {
+        out.push_str(&format!(
+            "    <DataSet timestep=\"{}\" group=\"{}\" part=\"{}\" file=\"{}\"",
+            data_set.timestep,
+            escape(&data_set.group),
+            data_set.part,
+            escape(&data_set.file)
+        ));
+        if let Some(mesh) = &data_set.mesh {
+            out.push_str(&format!(" mesh=\"{}\"", escape(mesh)));
+        }
+        out.push_str("/>\n");
+    }
+    out.push_str("  </Collection>\n");
+    out.push_str("</VTKFile>\n");
+    let mut tmp_path = file_path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, out)?;
+    std::fs::rename(&tmp_path, file_path)?;
+    Ok(())
+}
+
+/// Chooses the serial XML file extension for the given data set.
+pub(crate) fn data_set_extension(data: &DataSet) -> Result<&'static str, Error> {
+    data.xml_extension()
+        .ok_or(Error::UnknownFileExtension(None))
+}
+
+/// Escapes special characters in an XML attribute value.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Attribute, Attributes, ByteOrder, PolyDataPiece, Version, VertexNumbers};
+
+    fn triangle(pressure: f32) -> Vtk {
+        Vtk {
+            version: Version::new((1, 0)),
+            byte_order: ByteOrder::LittleEndian,
+            title: String::new(),
+            file_path: None,
+            data: DataSet::inline(PolyDataPiece {
+                points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+                polys: Some(VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2],
+                    offsets: vec![3],
+                }),
+                data: Attributes {
+                    point: vec![Attribute::scalars("pressure", 1).with_data(vec![pressure; 3])],
+                    cell: vec![],
+                    field: Vec::new(),
+                },
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn time_series_round_trip() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(format!(
+            "vtkio_pvd_time_series_round_trip_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut writer = TimeSeriesWriter::new(&dir, "a&b")?;
+        let first = writer.write(0.0, triangle(1.0))?;
+        assert_eq!(first, dir.join("a&b_0.vtp"));
+        writer.write(0.25, triangle(2.0))?;
+        let mut files: Vec<_> = std::fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        assert_eq!(files, ["a&b.pvd", "a&b_0.vtp", "a&b_1.vtp"]);
+
+        let collection = Collection::import_loaded(writer.pvd_path())?;
+        assert_eq!(collection.timesteps(), vec![0.0, 0.25]);
+        for (data_set, pressure) in collection.data_sets.into_iter().zip([1.0, 2.0]) {
+            assert_eq!(data_set.into_vtk()?.data, triangle(pressure).data);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_iteration_continues_after_errors() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(format!(
+            "vtkio_pvd_lazy_iteration_continues_after_errors_{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut writer = TimeSeriesWriter::new(&dir, "sim")?;
+        for step in 0..3 {
+            writer.write(step as f64, triangle(step as f32))?;
+        }
+        std::fs::remove_file(dir.join("sim_1.vtp"))?;
+
+        let results: Vec<_> = Collection::import(writer.pvd_path())?
+            .into_vtk_iter()
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[0].as_ref().unwrap().data, triangle(0.0).data);
+        assert_eq!(results[2].as_ref().unwrap().data, triangle(2.0).data);
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+//! Utilities for flattening VTK data into buffers suitable for rendering.
+//!
+//! Viewers built on top of APIs like `wgpu` or `glium` typically expect a single interleaved
+//! vertex buffer along with a triangle index buffer. The [`MeshBuffers`] type produces exactly
+//! that from a [`PolyDataPiece`], triangulating polygons and expanding triangle strips along the
+//! way.
+//!
+//! Meshes with fewer than 65535 vertices can be drawn with 16-bit indices, halving the size of
+//! the index buffer. [`MeshBuffers::compact_index_bytes`] picks the smallest [`IndexFormat`] able
+//! to address all vertices.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::render::{IndexFormat, MeshBuffers};
+//!
+//! let quad = PolyDataPiece {
+//!     points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0].into(),
+//!     polys: Some(VertexNumbers::Legacy {
+//!         num_cells: 1,
+//!         vertices: vec![4, 0, 1, 2, 3],
+//!     }),
+//!     ..Default::default()
+//! };
+//!
+//! let buffers = MeshBuffers::from_polydata(&quad);
+//! assert_eq!(buffers.vertices.len(), 4);
+//! assert_eq!(buffers.indices, vec![0, 1, 2, 0, 2, 3]);
+//! assert_eq!(buffers.vertices[0].normal, [0.0, 0.0, 1.0]);
+//!
+//! let (format, bytes) = buffers.compact_index_bytes();
+//! assert_eq!(format, IndexFormat::Uint16);
+//! assert_eq!(bytes.len(), 6 * 2);
+//! ```
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use crate::model::{Attribute, DataArray, ElementType, PolyDataPiece, VertexNumbers};
+
+/// A single interleaved vertex consisting of a position, normal and texture coordinate.
+///
+/// The layout is `#[repr(C)]` so a slice of vertices can be uploaded to the GPU directly (see
+/// [`MeshBuffers::vertex_bytes`]).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+// SAFETY: `Vertex` is `#[repr(C)]`, contains only `f32`s and has no padding.
+unsafe impl bytemuck::Zeroable for Vertex {}
+unsafe impl bytemuck::Pod for Vertex {}
+
+/// The type of the values stored in an index buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IndexFormat {
+    Uint16,
+    Uint32,
+}
+
+impl IndexFormat {
+    /// Returns the size of a single index in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            IndexFormat::Uint16 => 2,
+            IndexFormat::Uint32 => 4,
+        }
+    }
+}
+
+/// Interleaved vertex and triangle index buffers ready for upload to the GPU.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshBuffers {
+    /// One vertex per point in the source data set.
+    pub vertices: Vec<Vertex>,
+    /// Triangle list indices into `vertices`, three per triangle.
+    pub indices: Vec<u32>,
+}
+
+impl MeshBuffers {
+    /// Flattens the given poly data piece into vertex and index buffers.
+    ///
+    /// Polygons are fan triangulated and triangle strips are expanded into individual triangles
+    /// with consistent winding. Vertices and lines are ignored since they produce no triangles.
+    ///
+    /// Normals are taken from the point attribute of type `NORMALS` if one is present,
+    /// otherwise area weighted vertex normals are computed from the generated triangles.
+    /// Texture coordinates are taken from the first point attribute of type `TEXTURE_COORDINATES`,
+    /// and are zero if none is present.
+    ///
+    /// Points that cannot be represented as `f32` (e.g. bit buffers) produce empty buffers.
+    pub fn from_polydata(pd: &PolyDataPiece) -> MeshBuffers {
+        let positions = match pd.points.cast_into::<f32>() {
+            Some(positions) => positions,
+            None => return MeshBuffers::default(),
+        };
+
+        let num_points = positions.len() / 3;
+        let mut vertices: Vec<Vertex> = positions
+            .chunks_exact(3)
+            .map(|p| Vertex {
+                position: [p[0], p[1], p[2]],
+                ..Default::default()
+            })
+            .collect();
+
+        let mut indices = Vec::new();
+        if let Some(polys) = pd.polys.as_ref() {
+            triangulate_polys(polys, &mut indices);
+        }
+        if let Some(strips) = pd.strips.as_ref() {
+            expand_strips(strips, &mut indices);
+        }
+
+        // Drop any triangles referencing points out of bounds.
+        let mut valid = Vec::with_capacity(indices.len());
+        for tri in indices.chunks_exact(3) {
+            if tri.iter().all(|&i| (i as usize) < num_points) {
+                valid.extend_from_slice(tri);
+            }
+        }
+        let indices = valid;
+
+        match point_attrib(pd, num_points, |elem| *elem == ElementType::Normals) {
+            Some((normals, _)) => {
+                for (v, n) in vertices.iter_mut().zip(normals.chunks_exact(3)) {
+                    v.normal = [n[0], n[1], n[2]];
+                }
+            }
+            None => compute_normals(&mut vertices, &indices),
+        }
+
+        if let Some((uvs, num_comp)) = point_attrib(pd, num_points, |elem| {
+            matches!(elem, ElementType::TCoords(_))
+        }) {
+            for (v, uv) in vertices.iter_mut().zip(uvs.chunks_exact(num_comp)) {
+                v.uv = [uv[0], if num_comp > 1 { uv[1] } else { 0.0 }];
+            }
+        }
+
+        MeshBuffers { vertices, indices }
+    }
+
+    /// Returns the number of triangles in the index buffer.
+    pub fn num_triangles(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Returns the vertex buffer as raw bytes.
+    pub fn vertex_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.vertices)
+    }
+
+    /// Returns the index buffer as raw bytes.
+    pub fn index_bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.indices)
+    }
+
+    /// Returns the smallest index format able to address all vertices.
+    ///
+    /// The maximum 16-bit index is excluded, since it is reserved to restart strips in most
+    /// graphics APIs.
+    pub fn index_format(&self) -> IndexFormat {
+        if self.vertices.len() < usize::from(u16::MAX) {
+            IndexFormat::Uint16
+        } else {
+            IndexFormat::Uint32
+        }
+    }
+
+    /// Returns the index buffer converted to 16-bit indices.
+    ///
+    /// Returns `None` if the mesh needs 32-bit indices according to
+    /// [`index_format`](MeshBuffers::index_format), or if any index is out of range.
+    pub fn indices_u16(&self) -> Option<Vec<u16>> {
+        if self.index_format() != IndexFormat::Uint16 {
+            return None;
+        }
+        self.indices
+            .iter()
+            .map(|&i| u16::try_from(i).ok().filter(|&i| i < u16::MAX))
+            .collect()
+    }
+
+    /// Returns the index buffer as raw bytes in the smallest format able to address all
+    /// vertices, along with that format.
+    ///
+    /// Falls back to the 32-bit [`index_bytes`](MeshBuffers::index_bytes) if any index doesn't
+    /// fit in 16 bits.
+    pub fn compact_index_bytes(&self) -> (IndexFormat, Cow<'_, [u8]>) {
+        match self.indices_u16() {
+            Some(indices) => (
+                IndexFormat::Uint16,
+                Cow::Owned(bytemuck::cast_slice(&indices).to_vec()),
+            ),
+            None => (IndexFormat::Uint32, Cow::Borrowed(self.index_bytes())),
+        }
+    }
+}
+
+/// Finds the first point attribute satisfying the given predicate whose size matches the number
+/// of points, and returns its values converted to `f32` along with the number of components.
+fn point_attrib(
+    pd: &PolyDataPiece,
+    num_points: usize,
+    pred: impl Fn(&ElementType) -> bool,
+) -> Option<(Vec<f32>, usize)> {
+    pd.data.point.iter().find_map(|attrib| match attrib {
+        Attribute::DataArray(DataArray { elem, data, .. }) if pred(elem) => {
+            let num_comp = elem.num_comp() as usize;
+            if num_comp == 0 || data.len() != num_comp * num_points {
+                return None;
+            }
+            data.cast_into::<f32>().map(|v| (v, num_comp))
+        }
+        _ => None,
+    })
+}
+
+/// Calls `f` on each cell of the given topology with the slice of its vertex indices.
+fn for_each_cell(topo: &VertexNumbers, mut f: impl FnMut(&[u32])) {
+    let mut cell = Vec::new();
+    let mut push_cell = |indices: &[u64]| {
+        cell.clear();
+        cell.extend(
+            indices
+                .iter()
+                .map(|&i| u32::try_from(i).unwrap_or(u32::MAX)),
+        );
+        f(&cell);
+    };
+    match topo {
+        VertexNumbers::Legacy { vertices, .. } => {
+            let mut rest = vertices.as_slice();
+            while let Some((&n, tail)) = rest.split_first() {
+                let n = (n as usize).min(tail.len());
+                push_cell(&tail[..n]);
+                rest = &tail[n..];
+            }
+        }
+        VertexNumbers::XML {
+            connectivity,
+            offsets,
+        } => {
+            let mut begin = 0;
+            for &end in offsets.iter() {
+                let end = (end as usize).min(connectivity.len());
+                push_cell(&connectivity[begin.min(end)..end]);
+                begin = end;
+            }
+        }
+    }
+}
+
+/// Fan triangulates each polygon in `polys`.
+fn triangulate_polys(polys: &VertexNumbers, indices: &mut Vec<u32>) {
+    for_each_cell(polys, |poly| {
+        for i in 2..poly.len() {
+            indices.extend_from_slice(&[poly[0], poly[i - 1], poly[i]]);
+        }
+    });
+}
+
+/// Expands triangle strips into individual triangles, flipping every other triangle to preserve
+/// winding order. Degenerate triangles are skipped.
+fn expand_strips(strips: &VertexNumbers, indices: &mut Vec<u32>) {
+    for_each_cell(strips, |strip| {
+        for (i, tri) in strip.windows(3).enumerate() {
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+                continue;
+            }
+            if i % 2 == 0 {
+                indices.extend_from_slice(&[tri[0], tri[1], tri[2]]);
+            } else {
+                indices.extend_from_slice(&[tri[1], tri[0], tri[2]]);
+            }
+        }
+    });
+}
+
+/// Computes area weighted vertex normals from the given triangles.
+fn compute_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let sub = |a: [f32; 3], b: [f32; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        let u = sub(vertices[b].position, vertices[a].position);
+        let v = sub(vertices[c].position, vertices[a].position);
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        for &i in &[a, b, c] {
+            for (vn, n) in vertices[i].normal.iter_mut().zip(n.iter()) {
+                *vn += n;
+            }
+        }
+    }
+    for v in vertices.iter_mut() {
+        let len = v.normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if len > 0.0 {
+            v.normal.iter_mut().for_each(|x| *x /= len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+
+    #[test]
+    fn strip_expansion() {
+        let pd = PolyDataPiece {
+            points: vec![
+                0.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0,
+            ]
+            .into(),
+            strips: Some(VertexNumbers::XML {
+                connectivity: vec![0, 2, 1, 3],
+                offsets: vec![4],
+            }),
+            ..Default::default()
+        };
+        let buffers = MeshBuffers::from_polydata(&pd);
+        assert_eq!(buffers.num_triangles(), 2);
+        assert_eq!(buffers.indices, vec![0, 2, 1, 1, 2, 3]);
+        for v in buffers.vertices.iter() {
+            assert_eq!(v.normal, [0.0, 0.0, 1.0]);
+        }
+        assert_eq!(buffers.vertex_bytes().len(), 4 * 8 * 4);
+        assert_eq!(buffers.index_bytes().len(), 6 * 4);
+        assert_eq!(buffers.indices_u16(), Some(vec![0, 2, 1, 1, 2, 3]));
+    }
+
+    #[test]
+    fn compact_indices() {
+        let mut buffers = MeshBuffers {
+            vertices: vec![Vertex::default(); 3],
+            indices: vec![0, 1, 2],
+        };
+        let (format, bytes) = buffers.compact_index_bytes();
+        assert_eq!(format, IndexFormat::Uint16);
+        assert_eq!(bytes.len(), 3 * format.size());
+
+        // Indices out of range are never truncated.
+        buffers.indices[2] = 70000;
+        assert_eq!(buffers.indices_u16(), None);
+        let (format, bytes) = buffers.compact_index_bytes();
+        assert_eq!(format, IndexFormat::Uint32);
+        assert_eq!(bytes, buffers.index_bytes());
+
+        buffers
+            .vertices
+            .resize(usize::from(u16::MAX), Vertex::default());
+        buffers.indices[2] = 2;
+        assert_eq!(buffers.index_format(), IndexFormat::Uint32);
+        assert_eq!(buffers.indices_u16(), None);
+    }
+
+    #[test]
+    fn normals_and_tcoords_from_attributes() {
+        let pd = PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            }),
+            data: Attributes {
+                point: vec![
+                    Attribute::normals("N").with_data([0.0f32, 0.0, -1.0].repeat(3)),
+                    Attribute::tcoords("uv", 2).with_data(vec![0.0f32, 0.0, 1.0, 0.0, 0.0, 1.0]),
+                ],
+                cell: Vec::new(),
+                field: Vec::new(),
+            },
+            ..Default::default()
+        };
+        let buffers = MeshBuffers::from_polydata(&pd);
+        assert_eq!(buffers.indices, vec![0, 1, 2]);
+        assert_eq!(buffers.vertices[1].normal, [0.0, 0.0, -1.0]);
+        assert_eq!(buffers.vertices[1].uv, [1.0, 0.0]);
+        assert_eq!(buffers.vertices[2].uv, [0.0, 1.0]);
+    }
+}
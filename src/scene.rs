@@ -0,0 +1,436 @@
+//! A lightweight scene description for bundling multiple data sets with display hints.
+//!
+//! A [`Scene`] groups a number of named [`Vtk`] data sets (blocks), each with its own
+//! [`DisplayHints`]. Exporting a scene writes every block to its own data file inside a target
+//! directory along with a `scene.json` file describing how each block should be displayed. The
+//! JSON uses ParaView naming for representations and color map presets, so the bundle can be
+//! easily reconstructed in ParaView or any other viewer.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::model::*;
+//! use vtkio::scene::*;
+//!
+//! let vtk = Vtk::import("assets/tet.vtk").expect("Failed to load file");
+//! let scene = Scene::new().with_block(
+//!     "tet",
+//!     vtk,
+//!     DisplayHints::new()
+//!         .with_representation(Representation::SurfaceWithEdges)
+//!         .with_color_by("pressure", "Cool to Warm")
+//!         .with_opacity(0.5),
+//! );
+//! scene.export("tet_bundle").expect("Failed to export scene");
+//! ```
+
+use std::collections::HashSet;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::path::Path;
+
+use crate::model::{DataSet, Vtk};
+use crate::Error;
+
+/// Name of the scene description file written into the bundle directory.
+pub const SCENE_FILE_NAME: &str = "scene.json";
+
+/// The way a block should be rendered.
+///
+/// These correspond to the representation types available in ParaView.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Representation {
+    #[default]
+    Surface,
+    SurfaceWithEdges,
+    Wireframe,
+    Points,
+    Outline,
+    Volume,
+}
+
+impl std::fmt::Display for Representation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Representation::Surface => "Surface",
+            Representation::SurfaceWithEdges => "Surface With Edges",
+            Representation::Wireframe => "Wireframe",
+            Representation::Points => "Points",
+            Representation::Outline => "Outline",
+            Representation::Volume => "Volume",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Per-block display hints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayHints {
+    /// How the block should be rendered.
+    pub representation: Representation,
+    /// Name of the attribute used for coloring, if any.
+    pub color_by: Option<String>,
+    /// Name of the color map preset (e.g. "Cool to Warm" or "Viridis (matplotlib)").
+    pub colormap: Option<String>,
+    /// Opacity in the range `[0, 1]`.
+    ///
+    /// Scenes with a non-finite opacity fail to export, since JSON can't represent it.
+    pub opacity: f32,
+    /// Whether the block is initially visible.
+    pub visible: bool,
+}
+
+impl Default for DisplayHints {
+    fn default() -> DisplayHints {
+        DisplayHints {
+            representation: Representation::default(),
+            color_by: None,
+            colormap: None,
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+}
+
+impl DisplayHints {
+    /// Constructs default display hints: a visible, opaque surface with solid coloring.
+    pub fn new() -> DisplayHints {
+        DisplayHints::default()
+    }
+    /// Sets the representation of the block.
+    pub fn with_representation(self, representation: Representation) -> DisplayHints {
+        DisplayHints {
+            representation,
+            ..self
+        }
+    }
+    /// Colors the block by the given attribute using the given color map preset.
+    pub fn with_color_by(
+        self,
+        attribute: impl Into<String>,
+        colormap: impl Into<String>,
+    ) -> DisplayHints {
+        DisplayHints {
+            color_by: Some(attribute.into()),
+            colormap: Some(colormap.into()),
+            ..self
+        }
+    }
+    /// Sets the opacity of the block, clamped to `[0, 1]`.
+    ///
+    /// A `NaN` opacity is kept as is, and rejected when the scene is exported.
+    pub fn with_opacity(self, opacity: f32) -> DisplayHints {
+        DisplayHints {
+            opacity: opacity.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+    /// Sets the initial visibility of the block.
+    pub fn with_visibility(self, visible: bool) -> DisplayHints {
+        DisplayHints { visible, ..self }
+    }
+}
+
+/// A named data set with associated display hints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub name: String,
+    pub vtk: Vtk,
+    pub hints: DisplayHints,
+}
+
+impl Block {
+    /// The block name with all characters other than alphanumerics, `-` and `_` replaced by `_`.
+    pub(crate) fn sanitized_name(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+/// A collection of blocks forming a shareable visualization bundle.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scene {
+    pub blocks: Vec<Block>,
+}
+
+impl Scene {
+    /// Constructs an empty scene.
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// Adds a block to this scene and returns the result.
+    pub fn with_block(mut self, name: impl Into<String>, vtk: Vtk, hints: DisplayHints) -> Scene {
+        self.add_block(name, vtk, hints);
+        self
+    }
+
+    /// Adds a block to this scene.
+    pub fn add_block(&mut self, name: impl Into<String>, vtk: Vtk, hints: DisplayHints) {
+        self.blocks.push(Block {
+            name: name.into(),
+            vtk,
+            hints,
+        });
+    }
+
+    /// The names of the data files the blocks are written to within the bundle, in order.
+    ///
+    /// Block names are sanitized to only contain alphanumeric characters, `-` and `_`, and the
+    /// extension is chosen according to the stored data set type. Blocks whose names sanitize to
+    /// the same file name are told apart by a numeric suffix.
+    pub fn file_names(&self) -> Vec<String> {
+        let mut taken = HashSet::new();
+        self.blocks
+            .iter()
+            .map(|block| {
+                let ext = data_set_extension(&block.vtk.data);
+                let base = block.sanitized_name();
+                let mut name = format!("{}.{}", base, ext);
+                let mut n = 1;
+                // Compare names ignoring case for case insensitive file systems.
+                while !taken.insert(name.to_lowercase()) {
+                    name = format!("{}_{}.{}", base, n, ext);
+                    n += 1;
+                }
+                name
+            })
+            .collect()
+    }
+
+    /// Produces the JSON scene description referencing the data file of each block.
+    ///
+    /// An error of kind `InvalidInput` is returned if the opacity of any block is not finite.
+    pub fn to_json(&self) -> Result<String, Error> {
+        self.check_hints()?;
+        let mut out = String::new();
+        // Writing to a `String` never fails.
+        self.write_json(&mut out).unwrap();
+        Ok(out)
+    }
+
+    /// Checks that the display hints of all blocks can be written to JSON.
+    pub(crate) fn check_hints(&self) -> io::Result<()> {
+        match self.blocks.iter().find(|b| !b.hints.opacity.is_finite()) {
+            Some(block) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "opacity of block {:?} is not finite: {}",
+                    block.name, block.hints.opacity
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn write_json(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"version\": 1,")?;
+        writeln!(out, "  \"blocks\": [")?;
+        let file_names = self.file_names();
+        for (i, (block, file_name)) in self.blocks.iter().zip(&file_names).enumerate() {
+            let DisplayHints {
+                representation,
+                color_by,
+                colormap,
+                opacity,
+                visible,
+            } = &block.hints;
+            writeln!(out, "    {{")?;
+            writeln!(out, "      \"name\": {},", json_string(&block.name))?;
+            writeln!(out, "      \"file\": {},", json_string(file_name))?;
+            writeln!(
+                out,
+                "      \"representation\": {},",
+                json_string(&representation.to_string())
+            )?;
+            writeln!(out, "      \"colorBy\": {},", json_opt_string(color_by))?;
+            writeln!(out, "      \"colorMap\": {},", json_opt_string(colormap))?;
+            writeln!(out, "      \"opacity\": {},", opacity)?;
+            writeln!(out, "      \"visible\": {}", visible)?;
+            let sep = if i + 1 < self.blocks.len() { "," } else { "" };
+            writeln!(out, "    }}{}", sep)?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    /// Exports this scene into the given directory.
+    ///
+    /// The directory is created if it doesn't already exist. Each block is written to its own
+    /// data file (see [`Scene::file_names`]) and the scene description is written to
+    /// [`SCENE_FILE_NAME`]. Nothing is written if the display hints are invalid, see
+    /// [`Scene::to_json`].
+    pub fn export(self, dir: impl AsRef<Path>) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        let json = self.to_json()?;
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(SCENE_FILE_NAME), json)?;
+        let file_names = self.file_names();
+        for (block, file_name) in self.blocks.into_iter().zip(file_names) {
+            block.vtk.export(dir.join(file_name))?;
+        }
+        Ok(())
+    }
+
+    /// Exports this scene into a `.vtkjs` bundle, which can be opened in vtk.js web viewers.
+    ///
+    /// See the [`vtkjs`](crate::vtkjs) module for details.
+    pub fn export_vtkjs(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        crate::vtkjs::export(self, path)
+    }
+}
+
+/// Chooses the file extension for the given data set.
+///
+/// XML formats are preferred when the `xml` feature is enabled.
+fn data_set_extension(data: &DataSet) -> &'static str {
+    if cfg!(feature = "xml") {
+        data.xml_extension().unwrap_or("vtk")
+    } else {
+        "vtk"
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_opt_string(s: &Option<String>) -> String {
+    s.as_ref()
+        .map(|s| json_string(s))
+        .unwrap_or_else(|| String::from("null"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+
+    fn triangle() -> Vtk {
+        Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::from("Triangle"),
+            file_path: None,
+            data: DataSet::inline(PolyDataPiece {
+                points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+                polys: Some(VertexNumbers::Legacy {
+                    num_cells: 1,
+                    vertices: vec![3, 0, 1, 2],
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn scene_json() {
+        let scene = Scene::new()
+            .with_block(
+                "my \"tri\"",
+                triangle(),
+                DisplayHints::new()
+                    .with_representation(Representation::SurfaceWithEdges)
+                    .with_color_by("pressure", "Cool to Warm")
+                    .with_opacity(0.5),
+            )
+            .with_block(
+                "hidden",
+                triangle(),
+                DisplayHints::new().with_visibility(false),
+            );
+
+        let ext = if cfg!(feature = "xml") { "vtp" } else { "vtk" };
+        let expected = format!(
+            r#"{{
+  "version": 1,
+  "blocks": [
+    {{
+      "name": "my \"tri\"",
+      "file": "my__tri_.{ext}",
+      "representation": "Surface With Edges",
+      "colorBy": "pressure",
+      "colorMap": "Cool to Warm",
+      "opacity": 0.5,
+      "visible": true
+    }},
+    {{
+      "name": "hidden",
+      "file": "hidden.{ext}",
+      "representation": "Surface",
+      "colorBy": null,
+      "colorMap": null,
+      "opacity": 1,
+      "visible": false
+    }}
+  ]
+}}
+"#,
+            ext = ext
+        );
+        assert_eq!(scene.to_json().unwrap(), expected);
+    }
+
+    #[test]
+    fn unique_file_names() {
+        let hints = DisplayHints::new();
+        let scene = Scene::new()
+            .with_block("a b", triangle(), hints.clone())
+            .with_block("a/b", triangle(), hints.clone())
+            .with_block("A_b", triangle(), hints.clone())
+            .with_block("a_b_1", triangle(), hints);
+        let ext = if cfg!(feature = "xml") { "vtp" } else { "vtk" };
+        let expected: Vec<_> = ["a_b", "a_b_1", "A_b_2", "a_b_1_1"]
+            .iter()
+            .map(|name| format!("{}.{}", name, ext))
+            .collect();
+        assert_eq!(scene.file_names(), expected);
+    }
+
+    #[test]
+    fn non_finite_opacity() {
+        let invalid_input = |scene: &Scene| match scene.to_json() {
+            Err(crate::Error::IO(e)) => e.kind() == io::ErrorKind::InvalidInput,
+            _ => false,
+        };
+        let hints = DisplayHints::new().with_opacity(f32::INFINITY);
+        assert_eq!(hints.opacity, 1.0);
+        let scene = Scene::new().with_block("nan", triangle(), hints.with_opacity(f32::NAN));
+        assert!(invalid_input(&scene));
+        let mut hints = DisplayHints::new();
+        hints.opacity = f32::NEG_INFINITY;
+        let scene = Scene::new().with_block("inf", triangle(), hints);
+        assert!(invalid_input(&scene));
+
+        let dir = std::env::temp_dir().join(format!(
+            "vtkio_scene_non_finite_opacity_{}",
+            std::process::id()
+        ));
+        assert!(crate::vtkjs::write(&scene, Vec::new()).is_err());
+        assert!(scene.export(&dir).is_err());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,328 @@
+//! Building volumetric image data from stacks of 2D slices.
+//!
+//! Tomography and microscopy data is commonly stored as a sequence of 2D images, one per slice.
+//! An [`ImageStack`] collects such slices, either as raw buffers or (with the `image` feature)
+//! as PNG or TIFF files, and turns them into a [`DataSet::ImageData`] volume that can be exported
+//! directly, for instance to a `.vti` file.
+//!
+//! Pixel values are stored as point data in the order they appear in each slice, so pixels within
+//! a row run along the `x` axis, rows along `y` and slices along `z`.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::stack::ImageStack;
+//!
+//! let volume = ImageStack::new(2, 2)
+//!     .with_name("density")
+//!     .with_spacing([0.5, 0.5, 2.0])
+//!     .with_slice(vec![0u8, 1, 2, 3])
+//!     .unwrap()
+//!     .with_slice(vec![4u8, 5, 6, 7])
+//!     .unwrap()
+//!     .into_data_set();
+//!
+//! match volume {
+//!     DataSet::ImageData { extent, spacing, .. } => {
+//!         assert_eq!(extent, Extent::Ranges([0..=1, 0..=1, 0..=1]));
+//!         assert_eq!(spacing, [0.5, 0.5, 2.0]);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use crate::model::{self, *};
+use crate::Error;
+
+/// A stack of equally sized 2D slices forming a 3D image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageStack {
+    width: u32,
+    height: u32,
+    num_comp: u32,
+    num_slices: u32,
+    name: String,
+    origin: [f32; 3],
+    spacing: [f32; 3],
+    data: Option<IOBuffer>,
+}
+
+impl ImageStack {
+    /// Constructs an empty stack of slices with the given width and height in pixels.
+    ///
+    /// Each pixel has a single component by default, the origin is at zero and the spacing is one
+    /// in every direction.
+    pub fn new(width: u32, height: u32) -> Self {
+        ImageStack {
+            width,
+            height,
+            num_comp: 1,
+            num_slices: 0,
+            name: String::from("image"),
+            origin: [0.0; 3],
+            spacing: [1.0; 3],
+            data: None,
+        }
+    }
+
+    /// Sets the number of components per pixel, for instance 3 for RGB images.
+    ///
+    /// This should be set before any slices are added.
+    pub fn with_num_comp(mut self, num_comp: u32) -> Self {
+        self.num_comp = num_comp;
+        self
+    }
+
+    /// Sets the name of the point data array holding the pixel values.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the position of the first pixel of the first slice.
+    pub fn with_origin(mut self, origin: [f32; 3]) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Sets the distance between neighbouring pixels, with the slice spacing given in `z`.
+    pub fn with_spacing(mut self, spacing: [f32; 3]) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Returns the number of slices added so far.
+    pub fn num_slices(&self) -> usize {
+        self.num_slices as usize
+    }
+
+    /// Adds a slice given by its pixel values in row major order.
+    ///
+    /// An error is returned if the slice doesn't contain exactly `width * height * num_comp`
+    /// values, or if its scalar type differs from the slices added before.
+    pub fn push_slice(&mut self, slice: impl Into<IOBuffer>) -> Result<(), Error> {
+        let slice = slice.into();
+        let expected = self.width as usize * self.height as usize * self.num_comp as usize;
+        if slice.len() != expected {
+            return Err(model::Error::AttributeSizeMismatch {
+                name: self.name.clone(),
+                expected,
+                actual: slice.len(),
+            }
+            .into());
+        }
+        match &mut self.data {
+            Some(data) => model::append_buffer(data, slice, &self.name)?,
+            None => self.data = Some(slice),
+        }
+        self.num_slices += 1;
+        Ok(())
+    }
+
+    /// Adds a slice given by its pixel values in row major order.
+    ///
+    /// This is the builder variant of [`push_slice`](Self::push_slice).
+    pub fn with_slice(mut self, slice: impl Into<IOBuffer>) -> Result<Self, Error> {
+        self.push_slice(slice)?;
+        Ok(self)
+    }
+
+    /// Loads an image file and adds it as the next slice.
+    ///
+    /// If this is the first slice, the number of components is taken from the image. Otherwise
+    /// the image must have the same size, number of channels and bit depth as the previous slices.
+    #[cfg(feature = "image")]
+    pub fn push_image_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let (width, height, num_comp, slice) = read_image(path.as_ref())?;
+        if self.data.is_none() {
+            self.num_comp = num_comp;
+        }
+        if (width, height, num_comp) != (self.width, self.height, self.num_comp) {
+            return Err(model::Error::AttributeSizeMismatch {
+                name: self.name.clone(),
+                expected: self.width as usize * self.height as usize * self.num_comp as usize,
+                actual: slice.len(),
+            }
+            .into());
+        }
+        self.push_slice(slice)
+    }
+
+    /// Builds a stack from a sequence of image files, one per slice.
+    ///
+    /// The size and number of components are taken from the first image. Supported formats are
+    /// PNG and TIFF.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use vtkio::model::*;
+    /// use vtkio::stack::ImageStack;
+    ///
+    /// let paths = (0..100).map(|i| format!("scan/slice_{:03}.png", i));
+    /// let vtk = Vtk {
+    ///     version: Version::new((1, 0)),
+    ///     byte_order: ByteOrder::LittleEndian,
+    ///     title: String::from("Scan"),
+    ///     file_path: None,
+    ///     data: ImageStack::from_image_files(paths)
+    ///         .expect("Failed to load slices")
+    ///         .with_spacing([0.1, 0.1, 0.3])
+    ///         .into_data_set(),
+    /// };
+    /// vtk.export("scan.vti").expect("Failed to export volume");
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn from_image_files<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Self, Error> {
+        let mut paths = paths.into_iter();
+        let first = match paths.next() {
+            Some(path) => path,
+            None => return Ok(ImageStack::new(0, 0)),
+        };
+        let (width, height, num_comp, slice) = read_image(first.as_ref())?;
+        let mut stack = ImageStack::new(width, height)
+            .with_num_comp(num_comp)
+            .with_slice(slice)?;
+        for path in paths {
+            stack.push_image_file(path)?;
+        }
+        Ok(stack)
+    }
+
+    /// Converts this stack into an image data set with the pixel values stored as point data.
+    pub fn into_data_set(self) -> DataSet {
+        let extent = Extent::Ranges([
+            0..=self.width as i32 - 1,
+            0..=self.height as i32 - 1,
+            0..=self.num_slices as i32 - 1,
+        ]);
+        let data =
+            DataArray::scalars(self.name, self.num_comp).with_buf(self.data.unwrap_or_default());
+        DataSet::ImageData {
+            extent: extent.clone(),
+            origin: self.origin,
+            spacing: self.spacing,
+            meta: None,
+            pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                extent,
+                data: Attributes {
+                    point: vec![Attribute::DataArray(data)],
+                    cell: Vec::new(),
+                    field: Vec::new(),
+                },
+            }))],
+        }
+    }
+}
+
+impl From<ImageStack> for DataSet {
+    fn from(stack: ImageStack) -> DataSet {
+        stack.into_data_set()
+    }
+}
+
+/// Reads an image file, returning its width, height, number of channels and pixel values.
+///
+/// 8 and 16 bit images keep their bit depth, while all other images are converted to 32 bit
+/// floats.
+#[cfg(feature = "image")]
+fn read_image(path: &Path) -> Result<(u32, u32, u32, IOBuffer), Error> {
+    use image::DynamicImage;
+    let img = image::open(path)?;
+    let (width, height) = (img.width(), img.height());
+    let num_comp = u32::from(img.color().channel_count());
+    let data = match img {
+        DynamicImage::ImageLuma8(img) => img.into_raw().into(),
+        DynamicImage::ImageLumaA8(img) => img.into_raw().into(),
+        DynamicImage::ImageRgb8(img) => img.into_raw().into(),
+        DynamicImage::ImageRgba8(img) => img.into_raw().into(),
+        DynamicImage::ImageLuma16(img) => img.into_raw().into(),
+        DynamicImage::ImageLumaA16(img) => img.into_raw().into(),
+        DynamicImage::ImageRgb16(img) => img.into_raw().into(),
+        DynamicImage::ImageRgba16(img) => img.into_raw().into(),
+        DynamicImage::ImageRgb32F(img) => img.into_raw().into(),
+        img => {
+            let img = img.into_rgba32f();
+            return Ok((width, height, 4, img.into_raw().into()));
+        }
+    };
+    Ok((width, height, num_comp, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_slices() {
+        let mut stack = ImageStack::new(3, 2)
+            .with_num_comp(2)
+            .with_origin([1.0, 2.0, 3.0]);
+        stack.push_slice(vec![0.0f32; 12]).unwrap();
+        stack.push_slice(vec![1.0f32; 12]).unwrap();
+        stack.push_slice(vec![2.0f32; 12]).unwrap();
+        assert_eq!(stack.num_slices(), 3);
+
+        // Wrong size and wrong scalar type.
+        assert!(stack.push_slice(vec![0.0f32; 6]).is_err());
+        assert!(stack.push_slice(vec![0.0f64; 12]).is_err());
+        assert_eq!(stack.num_slices(), 3);
+
+        match stack.into_data_set() {
+            DataSet::ImageData {
+                extent,
+                origin,
+                spacing,
+                pieces,
+                ..
+            } => {
+                assert_eq!(extent, Extent::Ranges([0..=2, 0..=1, 0..=2]));
+                assert_eq!(origin, [1.0, 2.0, 3.0]);
+                assert_eq!(spacing, [1.0; 3]);
+                let piece = pieces[0].load_piece_data(None).unwrap();
+                let array = piece.data.point[0].data_array().unwrap();
+                assert_eq!(array.name, "image");
+                assert_eq!(array.num_comp(), 2);
+                assert_eq!(array.len(), 36);
+            }
+            _ => panic!("expected image data"),
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_files() {
+        let dir = std::env::temp_dir().join("vtkio_image_stack_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<_> = (0..2u8)
+            .map(|i| {
+                let path = dir.join(format!("slice_{}.png", i));
+                image::GrayImage::from_raw(2, 3, vec![i; 6])
+                    .unwrap()
+                    .save(&path)
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let stack = ImageStack::from_image_files(&paths).unwrap();
+        assert_eq!(stack.num_slices(), 2);
+        assert_eq!(
+            stack.data,
+            Some(IOBuffer::U8(vec![0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1]))
+        );
+
+        // Slices with a different number of channels are rejected.
+        let path = dir.join("rgb.png");
+        image::RgbImage::new(2, 3).save(&path).unwrap();
+        let mut stack = stack;
+        assert!(stack.push_image_file(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1011 @@
+//! Parsing of legacy VTK files from a stream.
+//!
+//! The parsers in [`parser`](crate::parser) operate on a slice holding the entire file, so
+//! reading a file keeps both its raw contents and the parsed data in memory. [`parse_legacy`]
+//! instead reads the file section by section from any [`BufRead`], decoding each array as it is
+//! read. Binary arrays are read directly into their final buffers, while ASCII arrays are parsed
+//! value by value, so only the parsed data set is kept in memory.
+//!
+//! The result is the same as that of [`Vtk::parse_legacy_be`] and friends. Sections are matched
+//! by their keywords rather than their order, so the stream reader is somewhat more lenient
+//! about the layout of the file.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::fs::File;
+//! use std::io::BufReader;
+//! use vtkio::model::*;
+//! use vtkio::ReadOptions;
+//!
+//! let file = BufReader::new(File::open("assets/cube.vtk").expect("Failed to open file"));
+//! let vtk = vtkio::stream::parse_legacy(file, ByteOrder::BigEndian, ReadOptions::default())
+//!     .expect("Failed to parse file");
+//! assert_eq!(vtk, vtkio::import("assets/cube.vtk").expect("Failed to import file"));
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{self, BufRead};
+use std::marker::PhantomData;
+
+use byteorder::{BigEndian, LittleEndian};
+
+use crate::basic::FromAscii;
+use crate::model::*;
+use crate::{parser, summary, AllocationBudget, Error, ParseError, ReadOptions, Warning};
+
+/// Parses a legacy VTK file from the given reader.
+///
+/// Binary data is interpreted in the given byte order. As with
+/// [`Vtk::parse_legacy_be_with_options`], the options determine how inconsistencies in the file
+/// are handled. Syntax errors are reported as [`Error::ParseAt`] along with the line and section
+/// where they occurred.
+pub fn parse_legacy(
+    reader: impl BufRead,
+    byte_order: ByteOrder,
+    options: ReadOptions,
+) -> Result<Vtk, Error> {
+    let mut warnings = Vec::new();
+    let (mut vtk, _) = parse_sections(reader, byte_order, &options, &mut warnings)?;
+    vtk.check_dimensions(options.tolerant, &mut warnings)?;
+    if options.float_color_scalars {
+        vtk.normalize_color_scalars();
+    }
+    Ok(vtk)
+}
+
+/// Parses the sections of a legacy file without checking their consistency.
+///
+/// Unknown sections are skipped if enabled in the options, recording a warning for each. Also
+/// returns whether any cell offsets or connectivity are declared as 64-bit integers.
+pub(crate) fn parse_sections(
+    reader: impl BufRead,
+    byte_order: ByteOrder,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<(Vtk, bool), Error> {
+    let skip_unknown = options.skip_unknown_sections;
+    let budget = options.allocation_budget();
+    match byte_order {
+        ByteOrder::BigEndian => {
+            let mut stream = LegacyStream::<_, BigEndian>::new(reader);
+            stream.skip_unknown = skip_unknown;
+            stream.budget = budget;
+            stream.parse(byte_order, warnings)
+        }
+        ByteOrder::LittleEndian => {
+            let mut stream = LegacyStream::<_, LittleEndian>::new(reader);
+            stream.skip_unknown = skip_unknown;
+            stream.budget = budget;
+            stream.parse(byte_order, warnings)
+        }
+    }
+}
+
+/// Locates the error reported by the slice parsers in the given legacy file.
+///
+/// The slice parsers backtrack on failure, so their error usually points to the start of the
+/// data set rather than the offending input. The file is therefore parsed again section by
+/// section to find where exactly it is malformed. If that succeeds, since the stream reader is
+/// more lenient, the furthest position reported by the slice parser is used instead.
+pub(crate) fn locate_error(input: &[u8], byte_order: ByteOrder, err: nom::Err<&[u8]>) -> Error {
+    let located = parse_sections(input, byte_order, &ReadOptions::default(), &mut Vec::new());
+    if let Err(err @ Error::ParseAt(_)) = located {
+        return err;
+    }
+    let offset = parser::error_offset(input, &err);
+    Error::ParseAt(ParseError {
+        kind: err.into_error_kind(),
+        offset,
+        line: 1 + input[..offset].iter().filter(|&&b| b == b'\n').count(),
+        section: None,
+    })
+}
+
+/// The number of values arrays initially have room for.
+///
+/// Arrays grow as their data is read, since the counts in headers are not trusted.
+const INITIAL_CAPACITY: usize = 4096;
+
+/// The keywords starting the sections of a data set.
+const KEYWORDS: &[&str] = &[
+    "DIMENSIONS",
+    "ORIGIN",
+    "SPACING",
+    "ASPECT_RATIO",
+    "POINTS",
+    "X_COORDINATES",
+    "Y_COORDINATES",
+    "Z_COORDINATES",
+    "CELLS",
+    "VERTICES",
+    "LINES",
+    "POLYGONS",
+    "TRIANGLE_STRIPS",
+    "CELL_TYPES",
+    "POINT_DATA",
+    "CELL_DATA",
+    "FIELD",
+    "SCALARS",
+    "COLOR_SCALARS",
+    "LOOKUP_TABLE",
+    "VECTORS",
+    "NORMALS",
+    "TENSORS",
+    "TEXTURE_COORDINATES",
+];
+
+fn eof() -> Error {
+    Error::Parse(nom::ErrorKind::Eof)
+}
+
+fn tag() -> Error {
+    Error::Parse(nom::ErrorKind::Tag)
+}
+
+fn word(word: Option<&str>) -> Result<&str, Error> {
+    word.ok_or_else(eof)
+}
+
+fn number<T: std::str::FromStr>(word: Option<&str>) -> Result<T, Error> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or(Error::Parse(nom::ErrorKind::Digit))
+}
+
+fn data_type(word: Option<&str>) -> Result<ScalarType, Error> {
+    word.and_then(parser::scalar_type_from_name).ok_or_else(tag)
+}
+
+/// Parses the type of points and coordinates, which must be `float` or `double`.
+fn float_type(word: Option<&str>) -> Result<ScalarType, Error> {
+    match data_type(word)? {
+        scalar_type @ (ScalarType::F32 | ScalarType::F64) => Ok(scalar_type),
+        _ => Err(tag()),
+    }
+}
+
+/// Parses three numbers following a header keyword.
+fn triple<'a, T: std::str::FromStr>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<[T; 3], Error> {
+    Ok([
+        number(words.next())?,
+        number(words.next())?,
+        number(words.next())?,
+    ])
+}
+
+/// The sections of a data set read so far.
+#[derive(Default)]
+struct Sections {
+    dims: Option<[u32; 3]>,
+    origin: Option<[f32; 3]>,
+    spacing: Option<[f32; 3]>,
+    points: Option<IOBuffer>,
+    coords: [Option<IOBuffer>; 3],
+    cells: Option<VertexNumbers>,
+    types: Option<Vec<CellType>>,
+    /// Vertices, lines, polygons and triangle strips of poly data.
+    topo: [Option<VertexNumbers>; 4],
+    data: Attributes,
+}
+
+/// A reader of legacy files that keeps only the current header in memory.
+struct LegacyStream<R, BO> {
+    source: R,
+    /// Bytes taken from the source to look ahead, which are read before the source.
+    lookahead: Vec<u8>,
+    binary: bool,
+    /// The number of bytes and lines consumed so far.
+    offset: usize,
+    line: usize,
+    /// The offset and line at which the item read last starts, where errors are reported.
+    mark: (usize, usize),
+    /// The keyword of the section being read.
+    section: Option<String>,
+    /// Whether unknown sections are skipped rather than rejected.
+    skip_unknown: bool,
+    /// The memory that may still be allocated for arrays.
+    budget: AllocationBudget,
+    /// Whether any `OFFSETS` or `CONNECTIVITY` array read so far is declared as 64-bit.
+    int64_ids: bool,
+    byte_order: PhantomData<BO>,
+}
+
+impl<R: BufRead, BO: byteorder::ByteOrder> LegacyStream<R, BO> {
+    fn new(source: R) -> Self {
+        LegacyStream {
+            source,
+            lookahead: Vec::new(),
+            binary: false,
+            offset: 0,
+            line: 0,
+            mark: (0, 0),
+            section: None,
+            skip_unknown: false,
+            budget: AllocationBudget::default(),
+            int64_ids: false,
+            byte_order: PhantomData,
+        }
+    }
+
+    /// Parses the whole file, reporting syntax errors along with their location.
+    ///
+    /// Also returns whether the cells are stored with 64-bit ids.
+    fn parse(
+        mut self,
+        byte_order: ByteOrder,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(Vtk, bool), Error> {
+        self.parse_file(byte_order, warnings)
+            .map(|vtk| (vtk, self.int64_ids))
+            .map_err(|err| match err {
+                Error::Parse(kind) => Error::ParseAt(ParseError {
+                    kind,
+                    offset: self.mark.0,
+                    line: self.mark.1 + 1,
+                    section: self.section.take(),
+                }),
+                err => err,
+            })
+    }
+
+    fn parse_file(
+        &mut self,
+        byte_order: ByteOrder,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Vtk, Error> {
+        // Leading blank lines are accepted, as by the slice parsers.
+        let version = self
+            .header()?
+            .as_deref()
+            .and_then(summary::parse_version)
+            .ok_or_else(tag)?;
+        let title = self.line()?.unwrap_or_default();
+        self.binary = match self.header()? {
+            Some(file_type) if file_type.eq_ignore_ascii_case("BINARY") => true,
+            Some(file_type) if file_type.eq_ignore_ascii_case("ASCII") => false,
+            _ => return Err(tag()),
+        };
+
+        let line = self.header()?.ok_or_else(eof)?;
+        let mut words = line.split_whitespace();
+        self.section = words.next().map(str::to_ascii_uppercase);
+        let data = match self.section.as_deref() {
+            Some("DATASET") => {
+                let kind = word(words.next())?.to_ascii_uppercase();
+                let start = self.mark;
+                let sections = self.sections(warnings)?;
+                // Missing sections are reported at the data set header.
+                self.mark = start;
+                self.section = Some(String::from("DATASET"));
+                Self::data_set(&kind, sections)?
+            }
+            Some("FIELD") => {
+                let (name, data_array) = self.field(words)?;
+                DataSet::Field { name, data_array }
+            }
+            _ => return Err(tag()),
+        };
+        Ok(Vtk {
+            version,
+            byte_order,
+            title: title.trim_start_matches(&[' ', '\t'][..]).to_string(),
+            file_path: None,
+            data,
+        })
+    }
+
+    /// Reads all sections following the `DATASET` header.
+    fn sections(&mut self, warnings: &mut Vec<Warning>) -> Result<Sections, Error> {
+        let mut s = Sections::default();
+        // Field data preceding any POINT_DATA or CELL_DATA section belongs to the whole data set.
+        let mut location: Option<(bool, usize)> = None;
+        // The header of the next section, if it has already been read while skipping.
+        let mut next = None;
+        while let Some(line) = match next.take() {
+            Some(line) => Some(line),
+            None => self.header()?,
+        } {
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default().to_ascii_uppercase();
+            self.section = Some(keyword.clone());
+            let header_line = self.mark.1 + 1;
+            let num_elements = location.map_or(0, |(_, n)| n);
+            let attrib = match keyword.as_str() {
+                "DIMENSIONS" => {
+                    s.dims = Some(triple(words)?);
+                    None
+                }
+                "ORIGIN" => {
+                    s.origin = Some(triple(words)?);
+                    None
+                }
+                "SPACING" | "ASPECT_RATIO" => {
+                    s.spacing = Some(triple(words)?);
+                    None
+                }
+                "POINTS" => {
+                    let n: usize = number(words.next())?;
+                    s.points = Some(self.data(n.saturating_mul(3), float_type(words.next())?)?);
+                    None
+                }
+                "X_COORDINATES" | "Y_COORDINATES" | "Z_COORDINATES" => {
+                    let n = number(words.next())?;
+                    let axis = usize::from(keyword.as_bytes()[0] - b'X');
+                    s.coords[axis] = Some(self.data(n, float_type(words.next())?)?);
+                    None
+                }
+                "CELLS" | "VERTICES" | "LINES" | "POLYGONS" | "TRIANGLE_STRIPS" => {
+                    let n = number(words.next())?;
+                    let size = number(words.next())?;
+                    let cells = self.cell_verts(n, size)?;
+                    match keyword.as_str() {
+                        "CELLS" => s.cells = Some(cells),
+                        "VERTICES" => s.topo[0] = Some(cells),
+                        "LINES" => s.topo[1] = Some(cells),
+                        "POLYGONS" => s.topo[2] = Some(cells),
+                        _ => s.topo[3] = Some(cells),
+                    }
+                    None
+                }
+                "CELL_TYPES" => {
+                    let n = number(words.next())?;
+                    s.types = Some(self.cell_types(n)?);
+                    None
+                }
+                "POINT_DATA" | "CELL_DATA" => {
+                    location = Some((keyword == "POINT_DATA", number(words.next())?));
+                    None
+                }
+                "FIELD" => {
+                    let (name, data_array) = self.field(words)?;
+                    if location.is_none() {
+                        s.data.field.extend(data_array);
+                        None
+                    } else {
+                        Some(Attribute::Field { name, data_array })
+                    }
+                }
+                "SCALARS" => {
+                    let name = word(words.next())?.to_string();
+                    let scalar_type = data_type(words.next())?;
+                    let num_comp = match words.next() {
+                        Some(num_comp) => number(Some(num_comp))?,
+                        None => 1,
+                    };
+                    let mut lookup_table = None;
+                    if self.peek_keyword("LOOKUP_TABLE")? {
+                        let line = self.header()?.unwrap_or_default();
+                        lookup_table = line
+                            .split_whitespace()
+                            .nth(1)
+                            .filter(|&name| name != "default")
+                            .map(String::from);
+                    }
+                    let data = self.data(
+                        (num_comp as usize).saturating_mul(num_elements),
+                        scalar_type,
+                    )?;
+                    Some(Attribute::DataArray(DataArray {
+                        name,
+                        elem: ElementType::Scalars {
+                            num_comp,
+                            lookup_table,
+                        },
+                        data,
+                    }))
+                }
+                "COLOR_SCALARS" => {
+                    let name = word(words.next())?.to_string();
+                    let num_comp: u32 = number(words.next())?;
+                    let data = self.color_data((num_comp as usize).saturating_mul(num_elements))?;
+                    Some(Attribute::DataArray(DataArray {
+                        name,
+                        elem: ElementType::ColorScalars(num_comp),
+                        data,
+                    }))
+                }
+                "LOOKUP_TABLE" => {
+                    let name = word(words.next())?.to_string();
+                    let size: usize = number(words.next())?;
+                    let data = lookup_table_to_f32(self.color_data(size.saturating_mul(4))?);
+                    Some(Attribute::DataArray(DataArray {
+                        name,
+                        elem: ElementType::LookupTable,
+                        data,
+                    }))
+                }
+                "VECTORS" | "NORMALS" | "TENSORS" | "TEXTURE_COORDINATES" => {
+                    let name = word(words.next())?.to_string();
+                    let elem = match keyword.as_str() {
+                        "VECTORS" => ElementType::Vectors,
+                        "NORMALS" => ElementType::Normals,
+                        "TENSORS" => ElementType::Tensors,
+                        _ => ElementType::TCoords(number(words.next())?),
+                    };
+                    let n = (elem.num_comp() as usize).saturating_mul(num_elements);
+                    let data = self.data(n, data_type(words.next())?)?;
+                    Some(Attribute::DataArray(DataArray { name, elem, data }))
+                }
+                _ if self.skip_unknown => {
+                    warnings.push(Warning::SectionSkipped {
+                        keyword: keyword.clone(),
+                        line: header_line,
+                    });
+                    let mut lines = vec![line.clone()];
+                    next = self.skip_section(&mut lines)?;
+                    // Attribute sections of ASCII files are kept to be written back unchanged.
+                    if self.binary || location.is_none() {
+                        None
+                    } else {
+                        Some(Attribute::Unknown {
+                            keyword: keyword.clone(),
+                            name: words.next().unwrap_or_default().to_string(),
+                            raw: lines.join("\n"),
+                        })
+                    }
+                }
+                _ => return Err(tag()),
+            };
+            match (attrib, location) {
+                (Some(attrib), Some((true, _))) => s.data.point.push(attrib),
+                (Some(attrib), Some((false, _))) => s.data.cell.push(attrib),
+                // Attributes must follow a POINT_DATA or CELL_DATA header.
+                (Some(_), None) if self.skip_unknown => {
+                    warnings.push(Warning::SectionSkipped {
+                        keyword,
+                        line: header_line,
+                    });
+                }
+                (Some(_), None) => return Err(tag()),
+                (None, _) => {}
+            }
+        }
+        Ok(s)
+    }
+
+    /// Assembles the data set of the given kind from its sections.
+    fn data_set(kind: &str, s: Sections) -> Result<DataSet, Error> {
+        let extent = || s.dims.map(Extent::Dims).ok_or_else(tag);
+        Ok(match kind {
+            "STRUCTURED_POINTS" => {
+                let extent = extent()?;
+                DataSet::ImageData {
+                    extent: extent.clone(),
+                    origin: s.origin.ok_or_else(tag)?,
+                    spacing: s.spacing.ok_or_else(tag)?,
+                    meta: None,
+                    pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                        extent,
+                        data: s.data,
+                    }))],
+                }
+            }
+            "STRUCTURED_GRID" => DataSet::inline(StructuredGridPiece {
+                extent: extent()?,
+                points: s.points.ok_or_else(tag)?,
+                data: s.data,
+            }),
+            "RECTILINEAR_GRID" => {
+                let extent = extent()?;
+                let [x, y, z] = s.coords;
+                DataSet::inline(RectilinearGridPiece {
+                    extent,
+                    coords: Coordinates {
+                        x: x.ok_or_else(tag)?,
+                        y: y.ok_or_else(tag)?,
+                        z: z.ok_or_else(tag)?,
+                    },
+                    data: s.data,
+                })
+            }
+            "UNSTRUCTURED_GRID" => DataSet::inline(UnstructuredGridPiece {
+                points: s.points.ok_or_else(tag)?,
+                // A grid without cells may omit the cell sections entirely.
+                cells: Cells {
+                    cell_verts: s.cells.unwrap_or(VertexNumbers::Legacy {
+                        num_cells: 0,
+                        vertices: Vec::new(),
+                    }),
+                    types: s.types.unwrap_or_default(),
+                },
+                data: s.data,
+            }),
+            "POLYDATA" => {
+                let [verts, lines, polys, strips] = s.topo;
+                DataSet::inline(PolyDataPiece {
+                    points: s.points.ok_or_else(tag)?,
+                    verts,
+                    lines,
+                    polys,
+                    strips,
+                    data: s.data,
+                })
+            }
+            _ => return Err(tag()),
+        })
+    }
+
+    /// Reads the arrays of a `FIELD` section, given the words following the keyword.
+    fn field<'a>(
+        &mut self,
+        mut words: impl Iterator<Item = &'a str>,
+    ) -> Result<(String, Vec<FieldArray>), Error> {
+        let name = word(words.next())?.to_string();
+        let n: usize = number(words.next())?;
+        self.budget
+            .allocate(n as u64, std::mem::size_of::<FieldArray>() as u64)?;
+        let mut data_array = Vec::new();
+        for _ in 0..n {
+            let line = self.header()?.ok_or_else(eof)?;
+            let mut words = line.split_whitespace();
+            let name = word(words.next())?.to_string();
+            let elem: u32 = number(words.next())?;
+            let num_tuples: usize = number(words.next())?;
+            let data = self.data(
+                (elem as usize).saturating_mul(num_tuples),
+                data_type(words.next())?,
+            )?;
+            data_array.push(FieldArray { name, elem, data });
+        }
+        Ok((name, data_array))
+    }
+
+    /// Skips the data of an unknown section, returning the header of the next known section.
+    ///
+    /// The non-empty lines skipped are appended to `skipped`.
+    fn skip_section(&mut self, skipped: &mut Vec<String>) -> Result<Option<String>, Error> {
+        while let Some(line) = self.header()? {
+            let keyword = line.split_whitespace().next().unwrap_or_default();
+            if KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(keyword)) {
+                return Ok(Some(line));
+            }
+            skipped.push(line);
+        }
+        Ok(None)
+    }
+
+    /// Reads cell topology with `n` cells, or `n` offsets in the format of version 5.1, and
+    /// `size` vertex indices.
+    fn cell_verts(&mut self, n: usize, size: usize) -> Result<VertexNumbers, Error> {
+        if !self.peek_keyword("OFFSETS")? {
+            // Binary files store these as 32-bit integers.
+            let vertices = if self.binary {
+                let vertices = self.binary_vec::<u32>(size)?;
+                vertices.into_iter().map(u64::from).collect()
+            } else {
+                self.ascii_vec(size)?
+            };
+            return Ok(VertexNumbers::Legacy {
+                num_cells: n as u64,
+                vertices,
+            });
+        }
+        let mut offsets = self.topo("OFFSETS", n)?;
+        // Offsets in `VertexNumbers::XML` mark the end of each cell.
+        if offsets.first() == Some(&0) {
+            offsets.remove(0);
+        }
+        let connectivity = self.topo("CONNECTIVITY", size)?;
+        Ok(VertexNumbers::XML {
+            offsets,
+            connectivity,
+        })
+    }
+
+    /// Reads an `OFFSETS` or `CONNECTIVITY` array with `n` entries.
+    fn topo(&mut self, keyword: &str, n: usize) -> Result<Vec<u64>, Error> {
+        let line = self.header()?.ok_or_else(eof)?;
+        let mut words = line.split_whitespace();
+        if !word(words.next())?.eq_ignore_ascii_case(keyword) {
+            return Err(tag());
+        }
+        let data_type = words.next();
+        if data_type.is_some_and(|t| t.eq_ignore_ascii_case("vtktypeint64")) {
+            self.int64_ids = true;
+        }
+        match data_type {
+            _ if !self.binary => self.ascii_vec(n),
+            Some(t) if t.eq_ignore_ascii_case("vtktypeint64") => self.binary_vec(n),
+            Some(t) if t.eq_ignore_ascii_case("vtktypeint32") => {
+                let values = self.binary_vec::<u32>(n)?;
+                Ok(values.into_iter().map(u64::from).collect())
+            }
+            _ => Err(tag()),
+        }
+    }
+
+    fn cell_types(&mut self, n: usize) -> Result<Vec<CellType>, Error> {
+        let ids: Vec<u32> = if self.binary {
+            let ids = self.binary_vec::<i32>(n)?;
+            ids.into_iter()
+                .map(u32::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(|_| Error::Parse(nom::ErrorKind::MapOpt))?
+        } else {
+            self.ascii_vec(n)?
+        };
+        Ok(ids.into_iter().map(CellType::from_id).collect())
+    }
+
+    /// Reads color data, which is stored as floats in ASCII files and bytes in binary files.
+    fn color_data(&mut self, n: usize) -> Result<IOBuffer, Error> {
+        let scalar_type = if self.binary {
+            ScalarType::U8
+        } else {
+            ScalarType::F32
+        };
+        self.data(n, scalar_type)
+    }
+
+    /// Reads `n` values of the given type following a header.
+    fn data(&mut self, n: usize, scalar_type: ScalarType) -> Result<IOBuffer, Error> {
+        Ok(if self.binary {
+            match scalar_type {
+                // Bits are kept packed, as in the slice parsers.
+                ScalarType::Bit => self.binary_vec::<u8>(n.div_ceil(8))?.into(),
+                ScalarType::U8 => self.binary_vec::<u8>(n)?.into(),
+                ScalarType::I8 => self.binary_vec::<i8>(n)?.into(),
+                ScalarType::U16 => self.binary_vec::<u16>(n)?.into(),
+                ScalarType::I16 => self.binary_vec::<i16>(n)?.into(),
+                ScalarType::U32 => self.binary_vec::<u32>(n)?.into(),
+                ScalarType::I32 => self.binary_vec::<i32>(n)?.into(),
+                ScalarType::U64 => self.binary_vec::<u64>(n)?.into(),
+                ScalarType::I64 => self.binary_vec::<i64>(n)?.into(),
+                ScalarType::F32 => self.binary_vec::<f32>(n)?.into(),
+                ScalarType::F64 => self.binary_vec::<f64>(n)?.into(),
+            }
+        } else {
+            match scalar_type {
+                ScalarType::Bit | ScalarType::U8 => self.ascii_vec::<u8>(n)?.into(),
+                ScalarType::I8 => self.ascii_vec::<i8>(n)?.into(),
+                ScalarType::U16 => self.ascii_vec::<u16>(n)?.into(),
+                ScalarType::I16 => self.ascii_vec::<i16>(n)?.into(),
+                ScalarType::U32 => self.ascii_vec::<u32>(n)?.into(),
+                ScalarType::I32 => self.ascii_vec::<i32>(n)?.into(),
+                ScalarType::U64 => self.ascii_vec::<u64>(n)?.into(),
+                ScalarType::I64 => self.ascii_vec::<i64>(n)?.into(),
+                ScalarType::F32 => self.ascii_vec::<f32>(n)?.into(),
+                ScalarType::F64 => self.ascii_vec::<f64>(n)?.into(),
+            }
+        })
+    }
+
+    /// Reads `n` binary values directly into a vector and converts them to native byte order.
+    ///
+    /// The vector grows as the values are read, so a count exceeding the data in the file fails
+    /// at its end rather than allocating memory for all values up front.
+    fn binary_vec<T: Binary>(&mut self, n: usize) -> Result<Vec<T>, Error> {
+        self.budget
+            .allocate(n as u64, std::mem::size_of::<T>() as u64)?;
+        self.mark = (self.offset, self.line);
+        let mut values = Vec::new();
+        while values.len() < n {
+            let start = values.len();
+            let len = n.min(start.saturating_mul(2).max(INITIAL_CAPACITY));
+            values.resize(len, T::zeroed());
+            self.read_exact(bytemuck::cast_slice_mut(&mut values[start..]))
+                .map_err(|e| match e.kind() {
+                    io::ErrorKind::UnexpectedEof => eof(),
+                    _ => Error::IO(e),
+                })?;
+        }
+        T::from_byte_order::<BO>(&mut values);
+        Ok(values)
+    }
+
+    /// Reads `n` whitespace separated ASCII values.
+    fn ascii_vec<T: FromAscii>(&mut self, n: usize) -> Result<Vec<T>, Error> {
+        self.budget
+            .allocate(n as u64, std::mem::size_of::<T>() as u64)?;
+        let mut values = Vec::with_capacity(n.min(INITIAL_CAPACITY));
+        let mut value = Vec::new();
+        for _ in 0..n {
+            if !self.word(&mut value)? {
+                return Err(eof());
+            }
+            match T::from_ascii(&value) {
+                nom::IResult::Done([], x) => values.push(x),
+                _ => return Err(Error::Parse(nom::ErrorKind::Digit)),
+            }
+        }
+        Ok(values)
+    }
+
+    /// Returns the bytes to read next, which are empty at the end of the source.
+    fn available(&mut self) -> io::Result<&[u8]> {
+        if self.lookahead.is_empty() {
+            self.source.fill_buf()
+        } else {
+            Ok(&self.lookahead)
+        }
+    }
+
+    /// Consumes `n` of the bytes returned by [`available`](Self::available).
+    fn advance(&mut self, n: usize) {
+        if self.lookahead.is_empty() {
+            self.source.consume(n);
+        } else {
+            self.lookahead.drain(..n);
+        }
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let n = self.lookahead.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.lookahead[..n]);
+        self.lookahead.drain(..n);
+        self.source.read_exact(&mut buf[n..])?;
+        self.offset += buf.len();
+        self.line += buf.iter().filter(|&&b| b == b'\n').count();
+        Ok(())
+    }
+
+    /// Reads the next whitespace separated word into `word`, returning `false` at the end of the
+    /// source.
+    fn word(&mut self, word: &mut Vec<u8>) -> io::Result<bool> {
+        word.clear();
+        loop {
+            let (offset, line) = (self.offset, self.line);
+            let available = self.available()?;
+            if available.is_empty() {
+                return Ok(!word.is_empty());
+            }
+            let mut used = 0;
+            let mut lines = 0;
+            let mut start = None;
+            let mut done = false;
+            for &b in available {
+                if !b.is_ascii_whitespace() {
+                    if word.is_empty() {
+                        start = Some((offset + used, line + lines));
+                    }
+                    word.push(b);
+                } else if !word.is_empty() {
+                    done = true;
+                    break;
+                } else if b == b'\n' {
+                    lines += 1;
+                }
+                used += 1;
+            }
+            self.advance(used);
+            self.offset += used;
+            self.line += lines;
+            if let Some(start) = start {
+                self.mark = start;
+            }
+            if done {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Reads the next line without its line ending, or `None` at the end of the source.
+    fn line(&mut self) -> Result<Option<String>, Error> {
+        self.mark = (self.offset, self.line);
+        let mut line = Vec::new();
+        loop {
+            let available = self.available()?;
+            if available.is_empty() {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            match available.iter().position(|&b| b == b'\n') {
+                Some(i) => {
+                    line.extend_from_slice(&available[..i]);
+                    self.advance(i + 1);
+                    self.offset += i + 1;
+                    self.line += 1;
+                    break;
+                }
+                None => {
+                    line.extend_from_slice(available);
+                    let n = available.len();
+                    self.advance(n);
+                    self.offset += n;
+                }
+            }
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+
+    /// Reads the next non-empty line without surrounding whitespace, skipping any `METADATA`
+    /// blocks.
+    fn header(&mut self) -> Result<Option<String>, Error> {
+        while let Some(line) = self.line()? {
+            let line = line.trim();
+            if line.eq_ignore_ascii_case("METADATA") {
+                // The block is terminated by an empty line or the end of the file.
+                while let Some(line) = self.line()? {
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+            } else if !line.is_empty() {
+                return Ok(Some(line.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns `true` if the next word is the given keyword without consuming any input.
+    fn peek_keyword(&mut self, keyword: &str) -> io::Result<bool> {
+        loop {
+            let start = self.lookahead.iter().position(|b| !b.is_ascii_whitespace());
+            if let Some(start) = start {
+                if self.lookahead.len() >= start + keyword.len() {
+                    let word = &self.lookahead[start..start + keyword.len()];
+                    return Ok(word.eq_ignore_ascii_case(keyword.as_bytes()));
+                }
+            }
+            let available = self.source.fill_buf()?;
+            if available.is_empty() {
+                return Ok(false);
+            }
+            let n = available.len().min(keyword.len());
+            self.lookahead.extend_from_slice(&available[..n]);
+            self.source.consume(n);
+        }
+    }
+}
+
+/// Scalars read directly from binary files.
+trait Binary: bytemuck::Pod {
+    /// Converts values in the byte order `BO` to native byte order in place.
+    fn from_byte_order<BO: byteorder::ByteOrder>(values: &mut [Self]);
+}
+
+macro_rules! impl_binary {
+    ($($type:ty => $from_slice:ident),* $(,)*) => {
+        $(
+            impl Binary for $type {
+                fn from_byte_order<BO: byteorder::ByteOrder>(values: &mut [Self]) {
+                    BO::$from_slice(values);
+                }
+            }
+        )*
+    }
+}
+
+impl_binary!(
+    u16 => from_slice_u16,
+    i16 => from_slice_i16,
+    u32 => from_slice_u32,
+    i32 => from_slice_i32,
+    u64 => from_slice_u64,
+    i64 => from_slice_i64,
+    f32 => from_slice_f32,
+    f64 => from_slice_f64,
+);
+
+impl Binary for u8 {
+    fn from_byte_order<BO: byteorder::ByteOrder>(_: &mut [Self]) {}
+}
+
+impl Binary for i8 {
+    fn from_byte_order<BO: byteorder::ByteOrder>(_: &mut [Self]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn legacy_matches_import() -> Result<(), Error> {
+        let files = std::fs::read_dir("assets")?
+            .chain(std::fs::read_dir("assets/pygmsh")?)
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for file in files
+            .iter()
+            .filter(|f| f.extension().is_some_and(|e| e == "vtk"))
+        {
+            let expected = match crate::import(file) {
+                Ok(vtk) => vtk,
+                Err(_) => continue,
+            };
+            // A tiny buffer splits headers and values across reads.
+            let reader = BufReader::with_capacity(7, File::open(file)?);
+            let vtk = parse_legacy(reader, ByteOrder::BigEndian, ReadOptions::default())?;
+            assert_eq!(vtk, expected, "{}", file.display());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn int64_ids() -> Result<(), Error> {
+        let file = |offsets: &str, connectivity: &str| {
+            format!(
+                "# vtk DataFile Version 5.1\nIds\nASCII\nDATASET POLYDATA\nPOINTS 3 float\n\
+                 0 0 0 1 0 0 0 1 0\nPOLYGONS 2 3\nOFFSETS {}\n0 3\nCONNECTIVITY {}\n0 1 2\n",
+                offsets, connectivity
+            )
+        };
+        let int64_ids = |file: String| {
+            let options = ReadOptions::default();
+            parse_sections(
+                file.as_bytes(),
+                ByteOrder::BigEndian,
+                &options,
+                &mut Vec::new(),
+            )
+            .map(|(_, int64_ids)| int64_ids)
+        };
+        assert!(int64_ids(file("vtktypeint64", "vtktypeint64"))?);
+        assert!(int64_ids(file("vtktypeint32", "VTKTYPEINT64"))?);
+        assert!(!int64_ids(file("vtktypeint32", "vtktypeint32"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_data() {
+        let file: &[u8] = b"# vtk DataFile Version 2.0
+Truncated
+ASCII
+DATASET POLYDATA
+POINTS 2 float
+0 0 0 1 0
+";
+        match parse_legacy(file, ByteOrder::BigEndian, ReadOptions::default()) {
+            Err(Error::ParseAt(err)) => {
+                assert_eq!(err.kind, nom::ErrorKind::Eof);
+                assert_eq!(err.section.as_deref(), Some("POINTS"));
+                assert_eq!(err.line, 6);
+                assert_eq!(&file[err.offset..], b"0\n");
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn huge_counts() {
+        // The arrays are not allocated up front, so this fails at the end of the data.
+        for encoding in &["ASCII", "BINARY"] {
+            let file = format!(
+                "# vtk DataFile Version 2.0\nHuge\n{}\nDATASET POLYDATA\nPOINTS 4000000000 float\n",
+                encoding
+            );
+            let mut file = file.into_bytes();
+            file.extend_from_slice(&[b'0'; 12]);
+            match parse_legacy(
+                file.as_slice(),
+                ByteOrder::BigEndian,
+                ReadOptions::default(),
+            ) {
+                Err(Error::ParseAt(err)) => {
+                    assert_eq!(err.kind, nom::ErrorKind::Eof);
+                    assert_eq!(err.section.as_deref(), Some("POINTS"));
+                }
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        }
+    }
+    #[test]
+    fn overflowing_counts() {
+        // Sizes computed from huge counts saturate, so they are rejected by the limits.
+        for body in &[
+            "POINT_DATA 9223372036854775807\nSCALARS s float 4\n",
+            "POINT_DATA 1\nLOOKUP_TABLE t 9223372036854775807\n",
+            "POINTS 9223372036854775807 float\n",
+        ] {
+            let file = format!(
+                "# vtk DataFile Version 2.0\nHuge\nASCII\nDATASET POLYDATA\n{}",
+                body
+            );
+            let options = ReadOptions::default().with_max_total_size(1 << 20);
+            match parse_legacy(file.as_bytes(), ByteOrder::BigEndian, options) {
+                Err(Error::LimitExceeded(e)) => assert_eq!(e.size, u64::MAX, "{}", body),
+                result => panic!("Unexpected result for {}: {:?}", body, result),
+            }
+        }
+    }
+}
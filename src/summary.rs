@@ -0,0 +1,834 @@
+//! Fast scanning of VTK files without loading their data.
+//!
+//! Cataloging large collections of simulation results typically only requires the type and size
+//! of each data set along with the names of the stored arrays. [`scan`] reads just the headers of
+//! a file and skips over the bulk data using the sizes declared in the headers, so the time spent
+//! on a file hardly depends on the amount of data it contains.
+//!
+//! Binary data in legacy files is skipped by seeking past it, while ASCII data is skipped without
+//! parsing or storing any values. The offsets of binary arrays are recorded along the way, so
+//! individual arrays can later be read with [`read_array`] without touching the rest of the file.
+//!
+//! XML files are read up to their `AppendedData` section, so appended arrays are never touched.
+//! Inline arrays are read along with the XML structure but not decoded.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::summary::{ArrayLocation, DataSetKind};
+//!
+//! let summary = vtkio::scan("assets/cube_complex.vtk").expect("Failed to scan file");
+//! assert_eq!(summary.data_set, DataSetKind::PolyData);
+//! assert_eq!((summary.num_points, summary.num_cells), (8, 6));
+//!
+//! let cell_arrays: Vec<_> = summary
+//!     .arrays
+//!     .iter()
+//!     .filter(|array| array.location == ArrayLocation::Cell)
+//!     .map(|array| array.name.as_str())
+//!     .collect();
+//! assert_eq!(cell_arrays, ["cell_scalars", "cell_normals", "cellIds", "faceAttributes"]);
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::model::{DataArray, ElementType, Extent, ScalarType, Version};
+use crate::{parser, Error};
+
+/// The size of the buffer holding the headers of legacy files.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The type of data set stored in a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DataSetKind {
+    /// Image data, called `STRUCTURED_POINTS` in legacy files.
+    ImageData,
+    StructuredGrid,
+    RectilinearGrid,
+    UnstructuredGrid,
+    PolyData,
+    /// A legacy file storing only field data.
+    Field,
+}
+
+/// The elements a data array is associated with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ArrayLocation {
+    /// One tuple per point.
+    Point,
+    /// One tuple per cell.
+    Cell,
+    /// Field data associated with the data set as a whole.
+    Field,
+}
+
+/// The declaration of a data array.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArraySummary {
+    pub name: String,
+    pub location: ArrayLocation,
+    /// The kind of attribute stored in the array.
+    ///
+    /// Arrays in XML files are always reported as `Generic`, since their attribute kind is only
+    /// determined when the file is loaded.
+    pub elem: ElementType,
+    pub scalar_type: ScalarType,
+    /// The number of tuples stored in the array, summed over all pieces.
+    pub num_tuples: u64,
+    /// The byte offset of the data within a legacy binary file, used by [`read_array`].
+    ///
+    /// This is `None` for ASCII and XML files, whose arrays can't be located without parsing.
+    pub offset: Option<u64>,
+}
+
+/// An overview of the contents of a file, produced by [`scan`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Summary {
+    /// Whether the file is in the modern XML format rather than the legacy format.
+    pub xml: bool,
+    /// The version declared in the file.
+    pub version: Version,
+    /// The title of a legacy file. This is empty for XML files.
+    pub title: String,
+    pub data_set: DataSetKind,
+    /// The extent of image data, structured and rectilinear grids.
+    ///
+    /// This holds the dimensions declared in legacy files and the whole extent of XML files,
+    /// which is also given for parallel XML files.
+    pub extent: Option<Extent>,
+    /// The number of pieces stored in the file.
+    ///
+    /// Parallel XML files only reference pieces stored in other files, which are not scanned, so
+    /// these report no pieces, points, cells or arrays.
+    pub num_pieces: usize,
+    /// The total number of points in all pieces.
+    pub num_points: u64,
+    /// The total number of cells in all pieces.
+    pub num_cells: u64,
+    /// The point, cell and field data arrays in order of their first appearance.
+    ///
+    /// Arrays with the same name and location in different pieces are reported once.
+    pub arrays: Vec<ArraySummary>,
+}
+
+impl Summary {
+    fn new(xml: bool, version: Version, data_set: DataSetKind) -> Self {
+        Summary {
+            xml,
+            version,
+            title: String::new(),
+            data_set,
+            extent: None,
+            num_pieces: 0,
+            num_points: 0,
+            num_cells: 0,
+            arrays: Vec::new(),
+        }
+    }
+
+    /// Returns the array with the given name and location.
+    pub fn array(&self, name: &str, location: ArrayLocation) -> Option<&ArraySummary> {
+        self.arrays
+            .iter()
+            .find(|array| array.name == name && array.location == location)
+    }
+
+    fn add_array(&mut self, array: ArraySummary) {
+        let existing = self
+            .arrays
+            .iter_mut()
+            .find(|a| a.name == array.name && a.location == array.location);
+        match existing {
+            Some(existing) => existing.num_tuples += array.num_tuples,
+            None => self.arrays.push(array),
+        }
+    }
+}
+
+/// Scans the headers of the VTK file at the given path without loading its data.
+#[doc(alias = "peek")]
+///
+/// As with [`import`](crate::import), the format is determined by the file extension. The
+/// contents of the file are not validated beyond what is needed to find the headers.
+pub fn scan(file_path: impl AsRef<Path>) -> Result<Summary, Error> {
+    let path = file_path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .ok_or(Error::UnknownFileExtension(None))?;
+    match ext {
+        "vtk" => scan_legacy(File::open(path)?),
+        #[cfg(feature = "xml")]
+        ext => {
+            crate::xml::FileType::try_from_ext(ext)
+                .ok_or(Error::UnknownFileExtension(Some(ext.to_string())))?;
+            scan_xml(File::open(path)?)
+        }
+        #[cfg(not(feature = "xml"))]
+        _ => Err(Error::UnknownFileExtension(None)),
+    }
+}
+
+/// Scans the headers of a legacy VTK file stored in the given source.
+pub fn scan_legacy(source: impl Read + Seek) -> Result<Summary, Error> {
+    let mut scanner = LegacyScanner {
+        source,
+        buf: vec![0; CHUNK_SIZE],
+        pos: 0,
+        end: 0,
+        start: 0,
+        binary: false,
+    };
+
+    let version = scanner
+        .line()?
+        .as_deref()
+        .and_then(parse_version)
+        .ok_or(Error::Parse(nom::ErrorKind::Tag))?;
+    let title = scanner.line()?.unwrap_or_default();
+    scanner.binary = match scanner.header()? {
+        Some(file_type) if file_type.eq_ignore_ascii_case("BINARY") => true,
+        Some(file_type) if file_type.eq_ignore_ascii_case("ASCII") => false,
+        _ => return Err(Error::Parse(nom::ErrorKind::Tag)),
+    };
+
+    let mut summary = Summary {
+        title,
+        ..Summary::new(false, version, DataSetKind::Field)
+    };
+    let color_type = if scanner.binary {
+        ScalarType::U8
+    } else {
+        ScalarType::F32
+    };
+    // Attributes preceding any POINT_DATA or CELL_DATA section belong to the whole data set.
+    let mut location = ArrayLocation::Field;
+    let mut num_elements = 0;
+    while let Some(line) = scanner.header()? {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default().to_ascii_uppercase();
+        match keyword.as_str() {
+            "DATASET" => {
+                // Legacy files store a single piece, while field data sets have none.
+                summary.num_pieces = 1;
+                summary.data_set = match word(words.next())?.to_ascii_uppercase().as_str() {
+                    "STRUCTURED_POINTS" => DataSetKind::ImageData,
+                    "STRUCTURED_GRID" => DataSetKind::StructuredGrid,
+                    "RECTILINEAR_GRID" => DataSetKind::RectilinearGrid,
+                    "UNSTRUCTURED_GRID" => DataSetKind::UnstructuredGrid,
+                    "POLYDATA" => DataSetKind::PolyData,
+                    _ => return Err(Error::Parse(nom::ErrorKind::Tag)),
+                };
+            }
+            "DIMENSIONS" => {
+                let mut dims = [0; 3];
+                for dim in dims.iter_mut() {
+                    *dim = u32::try_from(number(words.next())?)
+                        .map_err(|_| Error::Parse(nom::ErrorKind::Digit))?;
+                }
+                let extent = Extent::Dims(dims);
+                summary.num_points = extent.num_points();
+                summary.num_cells = extent.num_cells();
+                summary.extent = Some(extent);
+            }
+            "ORIGIN" | "SPACING" | "ASPECT_RATIO" => {}
+            "POINTS" => {
+                let n = number(words.next())?;
+                summary.num_points = n;
+                scanner.skip_data(3 * n, data_type(words.next())?)?;
+            }
+            "X_COORDINATES" | "Y_COORDINATES" | "Z_COORDINATES" => {
+                let n = number(words.next())?;
+                scanner.skip_data(n, data_type(words.next())?)?;
+            }
+            "CELLS" | "VERTICES" | "LINES" | "POLYGONS" | "TRIANGLE_STRIPS" => {
+                let n = number(words.next())?;
+                let size = number(words.next())?;
+                if scanner.peek_keyword("OFFSETS")? {
+                    // Since version 5.1, `n` is the number of offsets including a leading zero.
+                    scanner.skip_topology(n)?;
+                    scanner.skip_topology(size)?;
+                    summary.num_cells += n.saturating_sub(1);
+                } else {
+                    scanner.skip_data(size, ScalarType::I32)?;
+                    summary.num_cells += n;
+                }
+            }
+            "CELL_TYPES" => {
+                let n = number(words.next())?;
+                scanner.skip_data(n, ScalarType::I32)?;
+            }
+            "POINT_DATA" => {
+                location = ArrayLocation::Point;
+                num_elements = number(words.next())?;
+            }
+            "CELL_DATA" => {
+                location = ArrayLocation::Cell;
+                num_elements = number(words.next())?;
+            }
+            "SCALARS" => {
+                let name = word(words.next())?;
+                let scalar_type = data_type(words.next())?;
+                let num_comp = match words.next() {
+                    Some(num_comp) => number(Some(num_comp))?,
+                    None => 1,
+                };
+                let mut lookup_table = None;
+                if scanner.peek_keyword("LOOKUP_TABLE")? {
+                    let line = scanner.header()?.unwrap_or_default();
+                    lookup_table = line
+                        .split_whitespace()
+                        .nth(1)
+                        .filter(|&name| name != "default")
+                        .map(String::from);
+                }
+                let offset = scanner.skip_data(num_comp * num_elements, scalar_type)?;
+                summary.add_array(ArraySummary {
+                    name: name.to_string(),
+                    location,
+                    elem: ElementType::Scalars {
+                        num_comp: u32::try_from(num_comp)
+                            .map_err(|_| Error::Parse(nom::ErrorKind::Digit))?,
+                        lookup_table,
+                    },
+                    scalar_type,
+                    num_tuples: num_elements,
+                    offset,
+                });
+            }
+            "COLOR_SCALARS" => {
+                let name = word(words.next())?;
+                let num_comp = number(words.next())?;
+                let offset = scanner.skip_data(num_comp * num_elements, color_type)?;
+                summary.add_array(ArraySummary {
+                    name: name.to_string(),
+                    location,
+                    elem: ElementType::ColorScalars(
+                        u32::try_from(num_comp).map_err(|_| Error::Parse(nom::ErrorKind::Digit))?,
+                    ),
+                    scalar_type: color_type,
+                    num_tuples: num_elements,
+                    offset,
+                });
+            }
+            "LOOKUP_TABLE" => {
+                let name = word(words.next())?;
+                let size = number(words.next())?;
+                let offset = scanner.skip_data(4 * size, color_type)?;
+                summary.add_array(ArraySummary {
+                    name: name.to_string(),
+                    location,
+                    elem: ElementType::LookupTable,
+                    scalar_type: color_type,
+                    num_tuples: size,
+                    offset,
+                });
+            }
+            "VECTORS" | "NORMALS" | "TENSORS" | "TEXTURE_COORDINATES" => {
+                let name = word(words.next())?;
+                let (elem, num_comp) = match keyword.as_str() {
+                    "VECTORS" => (ElementType::Vectors, 3),
+                    "NORMALS" => (ElementType::Normals, 3),
+                    "TENSORS" => (ElementType::Tensors, 9),
+                    _ => {
+                        let dim = number(words.next())?;
+                        let dim32 =
+                            u32::try_from(dim).map_err(|_| Error::Parse(nom::ErrorKind::Digit))?;
+                        (ElementType::TCoords(dim32), dim)
+                    }
+                };
+                let scalar_type = data_type(words.next())?;
+                let offset = scanner.skip_data(num_comp * num_elements, scalar_type)?;
+                summary.add_array(ArraySummary {
+                    name: name.to_string(),
+                    location,
+                    elem,
+                    scalar_type,
+                    num_tuples: num_elements,
+                    offset,
+                });
+            }
+            "FIELD" => {
+                word(words.next())?;
+                for _ in 0..number(words.next())? {
+                    let line = scanner.header()?.ok_or(Error::Parse(nom::ErrorKind::Eof))?;
+                    let mut words = line.split_whitespace();
+                    let name = word(words.next())?;
+                    let num_comp = number(words.next())?;
+                    let num_tuples = number(words.next())?;
+                    let scalar_type = data_type(words.next())?;
+                    let offset = scanner.skip_data(num_comp * num_tuples, scalar_type)?;
+                    summary.add_array(ArraySummary {
+                        name: name.to_string(),
+                        location,
+                        elem: ElementType::Generic(
+                            u32::try_from(num_comp)
+                                .map_err(|_| Error::Parse(nom::ErrorKind::Digit))?,
+                        ),
+                        scalar_type,
+                        num_tuples,
+                        offset,
+                    });
+                }
+            }
+            _ => return Err(Error::Parse(nom::ErrorKind::Tag)),
+        }
+    }
+    Ok(summary)
+}
+
+/// Scans the XML structure of an XML VTK file stored in the given source.
+///
+/// Reading stops at the `AppendedData` section, see [`LazyReader`](crate::lazy::LazyReader).
+#[cfg(feature = "xml")]
+pub fn scan_xml(source: impl Read + Seek) -> Result<Summary, Error> {
+    use crate::xml::DataSetType;
+
+    let reader = crate::lazy::LazyReader::new(source)?;
+    let file = reader.xml_file();
+    let data_set = match file.data_set_type {
+        DataSetType::ImageData | DataSetType::PImageData => DataSetKind::ImageData,
+        DataSetType::StructuredGrid | DataSetType::PStructuredGrid => DataSetKind::StructuredGrid,
+        DataSetType::RectilinearGrid | DataSetType::PRectilinearGrid => {
+            DataSetKind::RectilinearGrid
+        }
+        DataSetType::UnstructuredGrid | DataSetType::PUnstructuredGrid => {
+            DataSetKind::UnstructuredGrid
+        }
+        DataSetType::PolyData | DataSetType::PPolyData => DataSetKind::PolyData,
+    };
+    let mut summary = Summary {
+        extent: file.whole_extent().map(Extent::from),
+        ..Summary::new(true, file.version, data_set)
+    };
+    for (i, piece) in file.data_set.pieces().iter().enumerate() {
+        let (num_points, num_cells) = file.piece_size(i).unwrap_or_default();
+        summary.num_pieces += 1;
+        summary.num_points += num_points as u64;
+        summary.num_cells += num_cells as u64;
+        let attributes = [
+            (ArrayLocation::Point, &piece.point_data, num_points),
+            (ArrayLocation::Cell, &piece.cell_data, num_cells),
+        ];
+        for &(location, attribute_data, num_tuples) in attributes.iter() {
+            for data_array in &attribute_data.data_array {
+                summary.add_array(ArraySummary {
+                    name: data_array.name.clone(),
+                    location,
+                    elem: ElementType::Generic(data_array.num_comp),
+                    scalar_type: data_array.scalar_type.into(),
+                    num_tuples: num_tuples as u64,
+                    offset: None,
+                });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Reads a single array of a legacy binary file found by [`scan`].
+///
+/// Only the data of the given array is read by seeking to its [`offset`](ArraySummary::offset).
+/// Data is assumed to be stored in big endian byte order, as written by VTK. Returns `None` if
+/// the array has no offset, which is the case for arrays in ASCII and XML files.
+pub fn read_array(
+    mut source: impl Read + Seek,
+    array: &ArraySummary,
+) -> Result<Option<DataArray>, Error> {
+    let offset = match array.offset {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    let n = array.num_tuples * u64::from(array.elem.num_comp());
+    let num_bytes = usize::try_from(binary_size(n, array.scalar_type))
+        .map_err(|_| Error::Parse(nom::ErrorKind::Eof))?;
+    let mut bytes = vec![0; num_bytes];
+    source.seek(SeekFrom::Start(offset))?;
+    source.read_exact(&mut bytes)?;
+    let data = parser::parse_binary_data(&bytes, n as usize, array.scalar_type)
+        .ok_or(Error::Parse(nom::ErrorKind::Eof))?;
+    Ok(Some(DataArray {
+        name: array.name.clone(),
+        elem: array.elem.clone(),
+        data,
+    }))
+}
+
+/// Reads the first array with the given name from the legacy binary file at the given path.
+///
+/// The headers of the file are scanned to find the offset of the array, so no data preceding
+/// the array is read. Use [`scan`] and [`read_array`] to read several arrays from the same file
+/// with a single scan.
+///
+/// Returns `None` if the file contains no array with the given name or it is not a legacy
+/// binary file.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::model::ElementType;
+///
+/// let array = vtkio::summary::read_array_at("assets/tri_attrib_binary.vtk", "tensors")
+///     .expect("Failed to read array")
+///     .expect("Missing array");
+/// assert_eq!(array.elem, ElementType::Tensors);
+/// assert_eq!(array.data.len(), 9);
+/// ```
+pub fn read_array_at(file_path: impl AsRef<Path>, name: &str) -> Result<Option<DataArray>, Error> {
+    let summary = scan(file_path.as_ref())?;
+    match summary.arrays.iter().find(|array| array.name == name) {
+        Some(array) => read_array(File::open(file_path)?, array),
+        None => Ok(None),
+    }
+}
+
+/// Parses the version from the first line of a legacy file, e.g. `# vtk DataFile Version 2.0`.
+pub(crate) fn parse_version(line: &str) -> Option<Version> {
+    if !line.starts_with('#') || !line.to_ascii_lowercase().contains("vtk") {
+        return None;
+    }
+    let (major, minor) = line.split_whitespace().last()?.split_once('.')?;
+    Some(Version::new((major.parse().ok()?, minor.parse().ok()?)))
+}
+
+fn word(word: Option<&str>) -> Result<&str, Error> {
+    word.ok_or(Error::Parse(nom::ErrorKind::Eof))
+}
+
+fn number(word: Option<&str>) -> Result<u64, Error> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or(Error::Parse(nom::ErrorKind::Digit))
+}
+
+fn data_type(word: Option<&str>) -> Result<ScalarType, Error> {
+    word.and_then(parser::scalar_type_from_name)
+        .ok_or(Error::Parse(nom::ErrorKind::Tag))
+}
+
+/// Returns the number of bytes taken up by `n` values of the given type in legacy binary files.
+fn binary_size(n: u64, scalar_type: ScalarType) -> u64 {
+    match scalar_type {
+        ScalarType::Bit => n.div_ceil(8),
+        _ => n * scalar_type.size() as u64,
+    }
+}
+
+/// A reader of legacy files that keeps only the current headers in memory.
+struct LegacyScanner<R> {
+    source: R,
+    buf: Vec<u8>,
+    /// Position of the first unread byte in `buf`.
+    pos: usize,
+    /// End of the valid bytes in `buf`.
+    end: usize,
+    /// Position of the first byte in `buf` within the source.
+    start: u64,
+    binary: bool,
+}
+
+impl<R: Read + Seek> LegacyScanner<R> {
+    /// Makes at least `n` bytes available in the buffer, unless the source ends first, and
+    /// returns the available bytes.
+    fn fill(&mut self, n: usize) -> io::Result<&[u8]> {
+        if self.end - self.pos < n {
+            self.buf.copy_within(self.pos..self.end, 0);
+            self.start += self.pos as u64;
+            self.end -= self.pos;
+            self.pos = 0;
+            while self.end < n.min(self.buf.len()) {
+                let read = self.source.read(&mut self.buf[self.end..])?;
+                if read == 0 {
+                    break;
+                }
+                self.end += read;
+            }
+        }
+        Ok(&self.buf[self.pos..self.end])
+    }
+
+    /// Reads the next line without surrounding whitespace, or `None` at the end of the source.
+    fn line(&mut self) -> Result<Option<String>, Error> {
+        let mut searched = 0;
+        loop {
+            let available = &self.buf[self.pos..self.end];
+            if let Some(i) = available[searched..].iter().position(|&b| b == b'\n') {
+                let line = String::from_utf8_lossy(&available[..searched + i]);
+                let line = line.trim().to_string();
+                self.pos += searched + i + 1;
+                return Ok(Some(line));
+            }
+            searched = available.len();
+            if searched == self.buf.len() {
+                // Headers never come close to the size of the buffer.
+                return Err(Error::Parse(nom::ErrorKind::TakeUntil));
+            }
+            if self.fill(searched + 1)?.len() == searched {
+                if searched == 0 {
+                    return Ok(None);
+                }
+                let line = String::from_utf8_lossy(&self.buf[self.pos..self.end]);
+                let line = line.trim().to_string();
+                self.pos = self.end;
+                return Ok(Some(line));
+            }
+        }
+    }
+
+    /// Reads the next non-empty line, skipping any `METADATA` blocks.
+    fn header(&mut self) -> Result<Option<String>, Error> {
+        while let Some(line) = self.line()? {
+            if line.eq_ignore_ascii_case("METADATA") {
+                // The block is terminated by an empty line or the end of the file.
+                while let Some(line) = self.line()? {
+                    if line.is_empty() {
+                        break;
+                    }
+                }
+            } else if !line.is_empty() {
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns `true` if the next word is the given keyword without consuming any input.
+    fn peek_keyword(&mut self, keyword: &str) -> io::Result<bool> {
+        let available = self.fill(256)?;
+        let start = available
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(available.len());
+        Ok(available[start..]
+            .get(..keyword.len())
+            .is_some_and(|word| word.eq_ignore_ascii_case(keyword.as_bytes())))
+    }
+
+    /// Skips an `OFFSETS` or `CONNECTIVITY` array with `n` entries.
+    fn skip_topology(&mut self, n: u64) -> Result<(), Error> {
+        let line = self.header()?.ok_or(Error::Parse(nom::ErrorKind::Eof))?;
+        let scalar_type = match line.split_whitespace().nth(1) {
+            Some(t) if t.eq_ignore_ascii_case("vtktypeint64") => ScalarType::I64,
+            Some(t) if t.eq_ignore_ascii_case("vtktypeint32") => ScalarType::I32,
+            _ => return Err(Error::Parse(nom::ErrorKind::Tag)),
+        };
+        self.skip_data(n, scalar_type)?;
+        Ok(())
+    }
+
+    /// Skips `n` values of the given type following a header.
+    ///
+    /// Returns the offset of the skipped data within binary files.
+    fn skip_data(&mut self, n: u64, scalar_type: ScalarType) -> Result<Option<u64>, Error> {
+        if !self.binary {
+            self.skip_values(n)?;
+            return Ok(None);
+        }
+        let offset = self.start + self.pos as u64;
+        let num_bytes = binary_size(n, scalar_type);
+        let available = (self.end - self.pos) as u64;
+        if num_bytes <= available {
+            self.pos += num_bytes as usize;
+        } else {
+            let skipped = i64::try_from(num_bytes - available)
+                .map_err(|_| Error::Parse(nom::ErrorKind::Eof))?;
+            self.start = self.source.seek(SeekFrom::Current(skipped))?;
+            self.pos = 0;
+            self.end = 0;
+        }
+        Ok(Some(offset))
+    }
+
+    /// Skips `n` whitespace separated ASCII values.
+    fn skip_values(&mut self, mut n: u64) -> Result<(), Error> {
+        let mut in_value = false;
+        while n > 0 {
+            if self.pos == self.end && self.fill(1)?.is_empty() {
+                // The last value may be terminated by the end of the file.
+                return if in_value && n == 1 {
+                    Ok(())
+                } else {
+                    Err(Error::Parse(nom::ErrorKind::Eof))
+                };
+            }
+            for &b in &self.buf[self.pos..self.end] {
+                self.pos += 1;
+                if !b.is_ascii_whitespace() {
+                    in_value = true;
+                } else if in_value {
+                    in_value = false;
+                    n -= 1;
+                    if n == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Attribute, Attributes, DataSet, IOBuffer, Piece};
+
+    #[test]
+    fn legacy_attributes() -> Result<(), Error> {
+        for file in &["assets/tri_attrib.vtk", "assets/tri_attrib_binary.vtk"] {
+            let summary = scan(file)?;
+            assert!(!summary.xml);
+            assert_eq!(summary.title, "Triangle example");
+            assert_eq!(summary.data_set, DataSetKind::PolyData);
+            assert_eq!((summary.num_points, summary.num_cells), (3, 1));
+            let tensors = summary.array("tensors", ArrayLocation::Cell).unwrap();
+            assert_eq!(tensors.elem, ElementType::Tensors);
+            assert_eq!(tensors.scalar_type, ScalarType::F64);
+            assert_eq!(tensors.num_tuples, 1);
+            let color_scalars = summary.array("scalars", ArrayLocation::Cell).unwrap();
+            assert_eq!(color_scalars.elem, ElementType::ColorScalars(3));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_binary_arrays() -> Result<(), Error> {
+        fn attributes<P: Clone>(
+            pieces: &[Piece<P>],
+            data: impl Fn(&P) -> &Attributes,
+        ) -> Vec<(String, IOBuffer)> {
+            let piece = match &pieces[0] {
+                Piece::Inline(piece) => data(piece),
+                _ => panic!("expected an inline piece"),
+            };
+            let mut arrays = Vec::new();
+            for attrib in piece.point.iter().chain(piece.cell.iter()) {
+                match attrib {
+                    Attribute::DataArray(array) => {
+                        arrays.push((array.name.clone(), array.data.clone()))
+                    }
+                    Attribute::Field { data_array, .. } => arrays.extend(
+                        data_array
+                            .iter()
+                            .map(|array| (array.name.clone(), array.data.clone())),
+                    ),
+                    _ => {}
+                }
+            }
+            arrays
+        }
+
+        for file in &[
+            "assets/tri_attrib_binary.vtk",
+            "assets/rectilinear_grid_binary.vtk",
+        ] {
+            let expected = match crate::import(file)?.data {
+                DataSet::PolyData { pieces, .. } => attributes(&pieces, |p| &p.data),
+                DataSet::RectilinearGrid { pieces, .. } => attributes(&pieces, |p| &p.data),
+                _ => unreachable!(),
+            };
+            let summary = scan(file)?;
+            assert_eq!(summary.arrays.len(), expected.len());
+            for (array, (name, data)) in summary.arrays.iter().zip(expected) {
+                assert_eq!(array.name, name);
+                let read = read_array(File::open(file)?, array)?.unwrap();
+                assert_eq!(read.data, data, "{}: {}", file, name);
+            }
+            assert!(read_array_at(file, "missing")?.is_none());
+        }
+
+        // ASCII files have no offsets.
+        let summary = scan("assets/tri_attrib.vtk")?;
+        assert!(summary.arrays.iter().all(|array| array.offset.is_none()));
+        assert!(read_array_at("assets/tri_attrib.vtk", "tensors")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_matches_import() -> Result<(), Error> {
+        for file in &[
+            "assets/cube_complex.vtk",
+            "assets/field.vtk",
+            "assets/global_field_data.vtk",
+            "assets/para_test.vtk",
+            "assets/pygmsh/ascii.vtk",
+            "assets/pygmsh/binary.vtk",
+            "assets/rectilinear_grid_binary.vtk",
+            "assets/rectilinear_grid_with_meta.vtk",
+            "assets/structured_grid.vtk",
+            "assets/unstructured_grid_complex.vtk",
+            "assets/volume_complex.vtk",
+        ] {
+            let summary = scan(file)?;
+            let vtk = crate::import(file)?;
+            assert_eq!(summary.version, vtk.version, "{}", file);
+            assert_eq!(summary.title, vtk.title, "{}", file);
+            assert_eq!(summary.num_pieces, vtk.data.num_pieces(), "{}", file);
+            let size = match &vtk.data {
+                DataSet::ImageData { extent, .. }
+                | DataSet::StructuredGrid { extent, .. }
+                | DataSet::RectilinearGrid { extent, .. } => {
+                    assert_eq!(summary.extent.as_ref(), Some(extent), "{}", file);
+                    (extent.num_points(), extent.num_cells())
+                }
+                DataSet::UnstructuredGrid { pieces, .. } => {
+                    let piece = pieces[0].load_piece_data(None)?;
+                    (piece.num_points() as u64, piece.cells.num_cells() as u64)
+                }
+                DataSet::PolyData { pieces, .. } => {
+                    let piece = pieces[0].load_piece_data(None)?;
+                    (piece.num_points() as u64, piece.num_cells() as u64)
+                }
+                DataSet::Field { .. } => (0, 0),
+            };
+            assert_eq!((summary.num_points, summary.num_cells), size, "{}", file);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn xml_arrays() -> Result<(), Error> {
+        for file in &[
+            "assets/hexahedron_zlib.vtu",
+            "assets/RectilinearGridAppendedBase64.vtr",
+            "assets/RectilinearGrid_ascii.vtr",
+        ] {
+            let summary = scan(file)?;
+            let reader = crate::lazy::LazyReader::open(file)?;
+            assert!(summary.xml);
+            assert_eq!(summary.num_pieces, reader.num_pieces());
+            let (num_points, num_cells) = reader.piece_size(0).unwrap();
+            assert_eq!(summary.num_points, num_points as u64);
+            assert_eq!(summary.num_cells, num_cells as u64);
+            let names: Vec<_> = summary.arrays.iter().map(|a| a.name.clone()).collect();
+            let mut expected = reader.point_array_names(0);
+            expected.extend(reader.cell_array_names(0));
+            assert_eq!(names, expected, "{}", file);
+            let extent = match crate::import(file)?.data {
+                DataSet::RectilinearGrid { extent, .. } => Some(extent),
+                _ => None,
+            };
+            assert_eq!(summary.extent, extent, "{}", file);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_ascii() {
+        let file: &[u8] = b"# vtk DataFile Version 2.0
+Truncated
+ASCII
+DATASET POLYDATA
+POINTS 2 float
+0 0 0 1 0
+";
+        assert!(matches!(
+            scan_legacy(io::Cursor::new(file)),
+            Err(Error::Parse(nom::ErrorKind::Eof))
+        ));
+    }
+}
@@ -0,0 +1,1001 @@
+//! Conversion between Tecplot ASCII data files and structured or unstructured grids.
+//!
+//! [Tecplot](https://tecplot.com) data files (`.dat`) declare a list of variables followed by a
+//! number of zones holding values for each variable. A [`DataFile`] reads and writes such files
+//! in the ASCII format, mapping
+//!
+//! * ordered zones, whose points are laid out in `I`, `J` and `K` directions, to structured grid
+//!   pieces, and
+//! * finite element zones of line segments, triangles, quadrilaterals, tetrahedra or bricks to
+//!   unstructured grid pieces.
+//!
+//! The variables named `X`, `Y` and `Z` (ignoring case) hold the coordinates of the points, and
+//! missing coordinates are zero. All other variables become `f64` scalar attributes: nodal
+//! variables are stored as point attributes and cell-centered variables as cell attributes.
+//! Attributes with several components are written as one variable per component, named after the
+//! attribute and the index of the component, like `U_0`, `U_1` and `U_2`.
+//!
+//! Both the `POINT` and `BLOCK` data packing, as well as the older `F=FEPOINT` style of zone
+//! headers, are read, while files are written with `BLOCK` packing. Tecplot stores other cells as
+//! degenerate ones with repeated nodes, so triangles are written as quadrilaterals and tetrahedra,
+//! pyramids and wedges as bricks in zones mixing them with other cells. Polygonal and polyhedral
+//! zones, shared variables and connectivity, text and geometry records and cell-centered
+//! variables in ordered zones are not supported.
+//!
+//! This module is available with the `tecplot` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::tecplot::{DataFile, Zone};
+//!
+//! let input: &[u8] = br#"TITLE = "Wing"
+//! VARIABLES = "X", "Y", "Z", "Cp"
+//! ZONE T="surface", NODES=4, ELEMENTS=2, DATAPACKING=POINT, ZONETYPE=FETRIANGLE
+//! 0 0 0 -0.5
+//! 1 0 0 0.25
+//! 1 1 0 0.5
+//! 0 1 0 1
+//! 1 2 3
+//! 1 3 4
+//! "#;
+//!
+//! let file = DataFile::parse(input).expect("Failed to parse file");
+//! assert_eq!(file.zones[0].title(), "surface");
+//!
+//! let vtk = file.zones[0].clone().into_vtk();
+//! let piece = UnstructuredGridPiece::from_data_set(vtk.data, None).unwrap();
+//! assert_eq!(piece.cells.types, vec![CellType::Triangle; 2]);
+//! assert_eq!(piece.data.point[0].name(), "Cp");
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::model::{self, *};
+
+/// Finite element zone types along with the corresponding cell types and nodes per element.
+const ZONE_TYPES: &[(&str, CellType, usize)] = &[
+    ("FELINESEG", CellType::Line, 2),
+    ("FETRIANGLE", CellType::Triangle, 3),
+    ("FEQUADRILATERAL", CellType::Quad, 4),
+    ("FETETRAHEDRON", CellType::Tetra, 4),
+    ("FEBRICK", CellType::Hexahedron, 8),
+];
+
+/// Keywords starting the records of a data file.
+const RECORDS: &[&str] = &[
+    "TITLE",
+    "FILETYPE",
+    "VARIABLES",
+    "ZONE",
+    "TEXT",
+    "GEOMETRY",
+    "AUXDATA",
+    "DATASETAUXDATA",
+    "VARAUXDATA",
+];
+
+/// Error produced when reading or writing Tecplot files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The given line (starting at 1) is malformed or ends prematurely.
+    Syntax(usize),
+    /// The file uses the given feature of the format, which is not supported.
+    Unsupported(String),
+    /// A cell has a type that can't be stored in a finite element zone.
+    UnsupportedCellType(CellType),
+    /// A zone has different variables than the first zone, such as the given one.
+    InconsistentVariables(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax(line) => write!(f, "Syntax error at line {}", line),
+            Error::Unsupported(feature) => write!(f, "Unsupported feature: {}", feature),
+            Error::UnsupportedCellType(ty) => write!(f, "Unsupported cell type: {:?}", ty),
+            Error::InconsistentVariables(name) => {
+                write!(f, "Variable {:?} is not shared by all zones", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A zone of a Tecplot data file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Zone {
+    /// An ordered zone, whose `I`, `J` and `K` sizes are given by the extent of the piece.
+    Ordered {
+        title: String,
+        piece: StructuredGridPiece,
+    },
+    /// A finite element zone.
+    FiniteElement {
+        title: String,
+        piece: UnstructuredGridPiece,
+    },
+}
+
+impl Zone {
+    /// Returns the title of the zone.
+    pub fn title(&self) -> &str {
+        match self {
+            Zone::Ordered { title, .. } | Zone::FiniteElement { title, .. } => title,
+        }
+    }
+
+    /// Converts this zone into a structured or unstructured grid with a single piece.
+    pub fn into_data_set(self) -> DataSet {
+        match self {
+            Zone::Ordered { piece, .. } => DataSet::inline(piece),
+            Zone::FiniteElement { piece, .. } => DataSet::inline(piece),
+        }
+    }
+
+    /// Converts this zone into a model titled after the zone, ready to be exported.
+    pub fn into_vtk(self) -> Vtk {
+        Vtk {
+            version: Version::new((4, 2)),
+            byte_order: model::ByteOrder::BigEndian,
+            title: self.title().to_string(),
+            file_path: None,
+            data: self.into_data_set(),
+        }
+    }
+
+    fn data(&self) -> &Attributes {
+        match self {
+            Zone::Ordered { piece, .. } => &piece.data,
+            Zone::FiniteElement { piece, .. } => &piece.data,
+        }
+    }
+}
+
+/// A Tecplot data file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataFile {
+    pub title: String,
+    pub zones: Vec<Zone>,
+}
+
+impl DataFile {
+    /// Imports the Tecplot file at the given path.
+    pub fn import(file_path: impl AsRef<Path>) -> Result<DataFile, crate::Error> {
+        DataFile::parse(BufReader::new(File::open(file_path)?))
+    }
+
+    /// Parses a Tecplot file from the given reader.
+    pub fn parse(mut reader: impl BufRead) -> Result<DataFile, crate::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Parser::new(&text).parse()?)
+    }
+
+    /// Converts the pieces of a structured or unstructured grid into zones.
+    ///
+    /// Zones are titled after the index of the piece, starting at 1.
+    pub fn from_vtk(vtk: Vtk) -> Result<DataFile, crate::Error> {
+        let source_path = vtk.file_path.as_deref();
+        let zones = match &vtk.data {
+            DataSet::StructuredGrid { pieces, .. } => pieces
+                .iter()
+                .enumerate()
+                .map(|(i, piece)| {
+                    Ok(Zone::Ordered {
+                        title: format!("Zone {}", i + 1),
+                        piece: piece.load_piece_data(source_path)?,
+                    })
+                })
+                .collect::<Result<_, crate::Error>>()?,
+            DataSet::UnstructuredGrid { pieces, .. } => pieces
+                .iter()
+                .enumerate()
+                .map(|(i, piece)| {
+                    Ok(Zone::FiniteElement {
+                        title: format!("Zone {}", i + 1),
+                        piece: piece.load_piece_data(source_path)?,
+                    })
+                })
+                .collect::<Result<_, crate::Error>>()?,
+            DataSet::ImageData { .. } => return Err(crate::Error::UnsupportedDataSet("ImageData")),
+            DataSet::RectilinearGrid { .. } => {
+                return Err(crate::Error::UnsupportedDataSet("RectilinearGrid"))
+            }
+            DataSet::PolyData { .. } => return Err(crate::Error::UnsupportedDataSet("PolyData")),
+            DataSet::Field { .. } => return Err(crate::Error::UnsupportedDataSet("Field")),
+        };
+        Ok(DataFile {
+            title: vtk.title,
+            zones,
+        })
+    }
+
+    /// Writes this file in the ASCII format to the given writer.
+    ///
+    /// All zones must have attributes with the same names, numbers of components and locations.
+    pub fn write(&self, writer: impl Write) -> Result<(), crate::Error> {
+        write_file(self, writer)
+    }
+
+    /// Exports this file in the ASCII format to the given path.
+    pub fn export(&self, file_path: impl AsRef<Path>) -> Result<(), crate::Error> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        self.write(&mut writer)?;
+        // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn is_record(word: &str) -> bool {
+    RECORDS.iter().any(|r| r.eq_ignore_ascii_case(word))
+}
+
+/// Returns `true` if the given word starts a value rather than a name.
+fn is_value(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Str(&'a str),
+    Punct(char),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// The current line, starting at 1.
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Parser {
+            text,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error(&self) -> Error {
+        Error::Syntax(self.line)
+    }
+
+    /// Skips whitespace and comments, which extend from `#` to the end of the line.
+    fn skip_space(&mut self) {
+        let mut comment = false;
+        for c in self.text[self.pos..].chars() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    comment = false;
+                }
+                '#' => comment = true,
+                _ if comment || c.is_whitespace() => {}
+                _ => return,
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        self.skip_space();
+        let rest = &self.text[self.pos..];
+        let first = rest.chars().next()?;
+        let (token, len) = match first {
+            '"' => {
+                let len = rest[1..].find('"')?;
+                let s = &rest[1..1 + len];
+                self.line += s.matches('\n').count();
+                (Token::Str(s), len + 2)
+            }
+            '=' | ',' | '(' | ')' | '[' | ']' => (Token::Punct(first), 1),
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || "=,()[]\"#".contains(c))
+                    .unwrap_or(rest.len());
+                (Token::Word(&rest[..len]), len)
+            }
+        };
+        self.pos += len;
+        Some(token)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, Error> {
+        self.next_token().ok_or_else(|| self.error())
+    }
+
+    fn peek(&mut self) -> Option<Token<'a>> {
+        let (pos, line) = (self.pos, self.line);
+        let token = self.next_token();
+        self.pos = pos;
+        self.line = line;
+        token
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(p) if p == punct => Ok(()),
+            _ => Err(self.error()),
+        }
+    }
+
+    /// Reads a quoted or unquoted string.
+    fn string(&mut self) -> Result<&'a str, Error> {
+        match self.next()? {
+            Token::Str(s) | Token::Word(s) => Ok(s),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn parse(mut self) -> Result<DataFile, Error> {
+        let mut file = DataFile::default();
+        let mut variables = Vec::new();
+        while let Some(token) = self.next_token() {
+            let keyword = match token {
+                Token::Word(word) => word.to_ascii_uppercase(),
+                _ => return Err(self.error()),
+            };
+            match keyword.as_str() {
+                "TITLE" => {
+                    self.expect('=')?;
+                    file.title = self.string()?.to_string();
+                }
+                "FILETYPE" => {
+                    self.expect('=')?;
+                    match self.string()?.to_ascii_uppercase().as_str() {
+                        "FULL" => {}
+                        filetype => return Err(Error::Unsupported(format!("{} files", filetype))),
+                    }
+                }
+                "VARIABLES" => {
+                    self.expect('=')?;
+                    variables = self.variables()?;
+                }
+                "ZONE" => {
+                    let zone = self.zone(&variables)?;
+                    file.zones.push(zone);
+                }
+                "AUXDATA" | "DATASETAUXDATA" => {
+                    self.string()?;
+                    self.expect('=')?;
+                    self.string()?;
+                }
+                "VARAUXDATA" => {
+                    self.string()?;
+                    self.string()?;
+                    self.expect('=')?;
+                    self.string()?;
+                }
+                "TEXT" | "GEOMETRY" => {
+                    return Err(Error::Unsupported(format!("{} records", keyword)))
+                }
+                _ => return Err(self.error()),
+            }
+        }
+        Ok(file)
+    }
+
+    fn variables(&mut self) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Str(name)) => names.push(name.to_string()),
+                Some(Token::Word(name)) if !is_record(name) && !is_value(name) => {
+                    names.push(name.to_string())
+                }
+                Some(Token::Punct(',')) => {}
+                _ => return Ok(names),
+            }
+            self.next()?;
+        }
+    }
+
+    /// Reads the cell-centered variables from a `VARLOCATION` parameter such as
+    /// `([2,4-5]=CELLCENTERED)`.
+    fn var_location(&mut self, cell_centered: &mut [bool]) -> Result<(), Error> {
+        self.expect('(')?;
+        loop {
+            match self.next()? {
+                Token::Punct(')') => return Ok(()),
+                Token::Punct(',') => continue,
+                Token::Punct('[') => {}
+                _ => return Err(self.error()),
+            }
+            let mut vars = Vec::new();
+            loop {
+                match self.next()? {
+                    Token::Punct(']') => break,
+                    Token::Punct(',') => {}
+                    Token::Word(range) => {
+                        let (first, last) = range.split_once('-').unwrap_or((range, range));
+                        let first: usize = first.parse().map_err(|_| self.error())?;
+                        let last: usize = last.parse().map_err(|_| self.error())?;
+                        vars.extend(first..=last);
+                    }
+                    _ => return Err(self.error()),
+                }
+            }
+            self.expect('=')?;
+            let centered = match self.string()?.to_ascii_uppercase().as_str() {
+                "CELLCENTERED" => true,
+                "NODAL" => false,
+                _ => return Err(self.error()),
+            };
+            for var in vars {
+                *cell_centered
+                    .get_mut(var.wrapping_sub(1))
+                    .ok_or_else(|| self.error())? = centered;
+            }
+        }
+    }
+
+    /// Skips a parenthesized parameter value.
+    fn skip_group(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads `n` values, separated by whitespace or commas, where `k*x` stands for `k` values
+    /// of `x`.
+    fn values(&mut self, n: usize, values: &mut Vec<f64>) -> Result<(), Error> {
+        let end = values.len() + n;
+        while values.len() < end {
+            let word = match self.next()? {
+                Token::Word(word) => word,
+                Token::Punct(',') => continue,
+                _ => return Err(self.error()),
+            };
+            match word.split_once('*') {
+                Some((count, value)) => {
+                    let count: usize = count.parse().map_err(|_| self.error())?;
+                    let value: f64 = value.parse().map_err(|_| self.error())?;
+                    if values.len() + count > end {
+                        return Err(self.error());
+                    }
+                    values.extend(std::iter::repeat_n(value, count));
+                }
+                None => values.push(word.parse().map_err(|_| self.error())?),
+            }
+        }
+        Ok(())
+    }
+
+    fn zone(&mut self, variables: &[String]) -> Result<Zone, Error> {
+        let mut params = Vec::new();
+        let mut cell_centered = vec![false; variables.len()];
+        loop {
+            let key = match self.peek() {
+                Some(Token::Punct(',')) => {
+                    self.next()?;
+                    continue;
+                }
+                Some(Token::Word(key)) if !is_record(key) && !is_value(key) => {
+                    key.to_ascii_uppercase()
+                }
+                _ => break,
+            };
+            self.next()?;
+            self.expect('=')?;
+            match key.as_str() {
+                "VARLOCATION" => self.var_location(&mut cell_centered)?,
+                "VARSHARELIST" | "CONNECTIVITYSHAREZONE" | "PASSIVEVARLIST" | "NV" => {
+                    return Err(Error::Unsupported(key))
+                }
+                _ => match self.peek() {
+                    Some(Token::Punct('(')) => self.skip_group()?,
+                    _ => params.push((key, self.string()?)),
+                },
+            }
+        }
+        let param = |key: &str| {
+            params
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_ascii_uppercase())
+        };
+        let size = |keys: &[&str], default: Option<usize>| -> Result<usize, Error> {
+            match keys.iter().find_map(|&key| param(key)) {
+                Some(size) => size.parse().map_err(|_| self.error()),
+                None => default.ok_or_else(|| self.error()),
+            }
+        };
+
+        // Older files give the packing and zone type as `F=FEPOINT, ET=TRIANGLE`.
+        let (finite_element, point_packing) = match param("F") {
+            Some(format) => (format.starts_with("FE"), format.ends_with("POINT")),
+            None => (
+                param("ZONETYPE").is_some_and(|ty| ty != "ORDERED"),
+                param("DATAPACKING").as_deref() == Some("POINT"),
+            ),
+        };
+        let zone_type = if finite_element {
+            let ty = match (param("ZONETYPE"), param("ET")) {
+                (Some(ty), _) => ty,
+                (None, Some(ty)) => format!("FE{}", ty),
+                (None, None) => return Err(self.error()),
+            };
+            let zone_type = ZONE_TYPES.iter().find(|(name, ..)| *name == ty);
+            Some(*zone_type.ok_or(Error::Unsupported(ty))?)
+        } else {
+            None
+        };
+        let dims = match zone_type {
+            Some(_) => [size(&["NODES", "N"], None)?, 1, 1],
+            None => [
+                size(&["I"], Some(1))?,
+                size(&["J"], Some(1))?,
+                size(&["K"], Some(1))?,
+            ],
+        };
+        let num_points = dims[0]
+            .checked_mul(dims[1])
+            .and_then(|n| n.checked_mul(dims[2]))
+            .ok_or_else(|| self.error())?;
+        let num_cells = match zone_type {
+            Some(_) => size(&["ELEMENTS", "E"], None)?,
+            None => 0,
+        };
+        if cell_centered.iter().any(|&c| c) && (zone_type.is_none() || point_packing) {
+            return Err(Error::Unsupported(String::from(
+                "cell-centered variables in ordered zones or with point packing",
+            )));
+        }
+
+        // Sizes come from the file, so storage grows as values are read.
+        let mut columns = Vec::with_capacity(variables.len());
+        if point_packing {
+            let count = num_points.checked_mul(variables.len());
+            let mut values = Vec::new();
+            self.values(count.ok_or_else(|| self.error())?, &mut values)?;
+            for v in 0..variables.len() {
+                columns.push(
+                    values
+                        .iter()
+                        .skip(v)
+                        .step_by(variables.len())
+                        .copied()
+                        .collect(),
+                );
+            }
+        } else {
+            for &centered in &cell_centered {
+                let mut values = Vec::new();
+                self.values(if centered { num_cells } else { num_points }, &mut values)?;
+                columns.push(values);
+            }
+        }
+
+        let mut points = vec![0.0; 3 * num_points];
+        let mut data = Attributes::new();
+        for ((name, values), &centered) in variables.iter().zip(columns).zip(&cell_centered) {
+            match ["X", "Y", "Z"]
+                .iter()
+                .position(|c| c.eq_ignore_ascii_case(name))
+            {
+                Some(axis) => {
+                    for (p, x) in values.into_iter().enumerate() {
+                        points[3 * p + axis] = x;
+                    }
+                }
+                None => {
+                    let attrib = Attribute::scalars(name.as_str(), 1).with_data(values);
+                    if centered {
+                        data.cell.push(attrib);
+                    } else {
+                        data.point.push(attrib);
+                    }
+                }
+            }
+        }
+
+        let title = params
+            .iter()
+            .rev()
+            .find(|(k, _)| k == "T")
+            .map_or_else(String::new, |(_, title)| title.to_string());
+        let (_, cell_type, nodes) = match zone_type {
+            Some(zone_type) => zone_type,
+            None => {
+                let extent = Extent::Dims([dims[0] as u32, dims[1] as u32, dims[2] as u32]);
+                let piece = StructuredGridPiece {
+                    extent,
+                    points: points.into(),
+                    data,
+                };
+                return Ok(Zone::Ordered { title, piece });
+            }
+        };
+        let count = num_cells.checked_mul(nodes).ok_or_else(|| self.error())?;
+        let mut connectivity = Vec::new();
+        for _ in 0..count {
+            let node = match self.next()? {
+                Token::Word(word) => word.parse::<u64>().ok(),
+                _ => None,
+            };
+            match node {
+                Some(node) if node >= 1 && node as usize <= num_points => {
+                    connectivity.push(node - 1)
+                }
+                _ => return Err(self.error()),
+            }
+        }
+        Ok(Zone::FiniteElement {
+            title,
+            piece: UnstructuredGridPiece {
+                points: points.into(),
+                cells: Cells {
+                    cell_verts: VertexNumbers::XML {
+                        connectivity,
+                        offsets: (1..=num_cells as u64).map(|i| i * nodes as u64).collect(),
+                    },
+                    types: vec![cell_type; num_cells],
+                },
+                data,
+            },
+        })
+    }
+}
+
+/// A variable holding the values of one component of an attribute.
+struct Variable {
+    name: String,
+    cell_centered: bool,
+    values: Vec<f64>,
+}
+
+/// Returns the variables of the given zone other than the coordinates.
+fn variables(zone: &Zone) -> Result<Vec<Variable>, Error> {
+    let data = zone.data();
+    if let (Zone::Ordered { .. }, Some(attrib)) = (zone, data.cell.first()) {
+        return Err(Error::Unsupported(format!(
+            "cell attribute {:?} of an ordered zone",
+            attrib.name()
+        )));
+    }
+    let mut variables = Vec::new();
+    let attribs = data.point.iter().map(|a| (a, false));
+    for (attrib, cell_centered) in attribs.chain(data.cell.iter().map(|a| (a, true))) {
+        let array = match attrib.data_array() {
+            Some(array) => array,
+            None => continue,
+        };
+        let values = array.data.cast_into::<f64>().unwrap_or_default();
+        let num_comp = array.num_comp().max(1);
+        for comp in 0..num_comp {
+            variables.push(Variable {
+                name: if num_comp == 1 {
+                    array.name.clone()
+                } else {
+                    format!("{}_{}", array.name, comp)
+                },
+                cell_centered,
+                values: values
+                    .iter()
+                    .skip(comp)
+                    .step_by(num_comp)
+                    .copied()
+                    .collect(),
+            });
+        }
+    }
+    Ok(variables)
+}
+
+/// Returns the finite element zone type storing the given cell types.
+fn zone_type(types: &[CellType]) -> Result<&'static (&'static str, CellType, usize), Error> {
+    use CellType::*;
+    let all = |allowed: &[CellType]| types.iter().all(|ty| allowed.contains(ty));
+    let name = if all(&[Line]) {
+        "FELINESEG"
+    } else if all(&[Triangle]) {
+        "FETRIANGLE"
+    } else if all(&[Triangle, Quad, Pixel]) {
+        "FEQUADRILATERAL"
+    } else if all(&[Tetra]) {
+        "FETETRAHEDRON"
+    } else if all(&[Tetra, Pyramid, Wedge, Hexahedron, Voxel]) {
+        "FEBRICK"
+    } else {
+        let allowed = [
+            Line, Triangle, Quad, Pixel, Tetra, Pyramid, Wedge, Hexahedron, Voxel,
+        ];
+        let ty = types
+            .iter()
+            .find(|ty| !allowed.contains(ty))
+            .unwrap_or(&types[0]);
+        return Err(Error::UnsupportedCellType(*ty));
+    };
+    Ok(ZONE_TYPES.iter().find(|(n, ..)| *n == name).unwrap())
+}
+
+/// Returns the nodes of a cell in a zone with the given number of nodes per element, repeating
+/// nodes of cells with fewer nodes.
+fn element_nodes(cell_type: CellType, verts: &[u64], nodes: usize) -> Vec<u64> {
+    let order: &[usize] = match (cell_type, nodes) {
+        (CellType::Triangle, 4) => &[0, 1, 2, 2],
+        (CellType::Pixel, _) => &[0, 1, 3, 2],
+        (CellType::Tetra, 8) => &[0, 1, 2, 2, 3, 3, 3, 3],
+        (CellType::Pyramid, _) => &[0, 1, 2, 3, 4, 4, 4, 4],
+        (CellType::Wedge, _) => &[0, 1, 2, 2, 3, 4, 5, 5],
+        (CellType::Voxel, _) => &[0, 1, 3, 2, 4, 5, 7, 6],
+        _ => return verts.to_vec(),
+    };
+    order.iter().map(|&i| verts[i]).collect()
+}
+
+fn write_values(w: &mut impl Write, values: impl IntoIterator<Item = f64>) -> std::io::Result<()> {
+    for (i, x) in values.into_iter().enumerate() {
+        if i > 0 {
+            w.write_all(if i % 10 == 0 { b"\n" } else { b" " })?;
+        }
+        write!(w, "{}", x)?;
+    }
+    writeln!(w)
+}
+
+fn write_file(file: &DataFile, mut w: impl Write) -> Result<(), crate::Error> {
+    let first = match file.zones.first() {
+        Some(zone) => variables(zone)?,
+        None => Vec::new(),
+    };
+    writeln!(w, "TITLE = {:?}", file.title)?;
+    write!(w, "VARIABLES = \"X\", \"Y\", \"Z\"")?;
+    for var in &first {
+        write!(w, ", {:?}", var.name)?;
+    }
+    writeln!(w)?;
+
+    for zone in &file.zones {
+        let vars = variables(zone)?;
+        if let Some(var) = vars.iter().find(|v| {
+            !first
+                .iter()
+                .any(|f| f.name == v.name && f.cell_centered == v.cell_centered)
+        }) {
+            return Err(Error::InconsistentVariables(var.name.clone()).into());
+        }
+        if let Some(var) = first
+            .iter()
+            .find(|f| !vars.iter().any(|v| v.name == f.name))
+        {
+            return Err(Error::InconsistentVariables(var.name.clone()).into());
+        }
+
+        write!(w, "ZONE T={:?}", zone.title())?;
+        let (points, cells) = match zone {
+            Zone::Ordered { piece, .. } => {
+                let [i, j, k] = piece.extent.clone().into_dims();
+                write!(w, ", I={}, J={}, K={}", i, j, k)?;
+                (&piece.points, None)
+            }
+            Zone::FiniteElement { piece, .. } => {
+                let (name, _, nodes) = zone_type(&piece.cells.types)?;
+                write!(
+                    w,
+                    ", NODES={}, ELEMENTS={}, ZONETYPE={}",
+                    piece.num_points(),
+                    piece.cells.types.len(),
+                    name
+                )?;
+                (&piece.points, Some((&piece.cells, *nodes)))
+            }
+        };
+        write!(w, ", DATAPACKING=BLOCK")?;
+        let centered: Vec<_> = first
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.cell_centered)
+            .map(|(i, _)| (i + 4).to_string())
+            .collect();
+        if !centered.is_empty() {
+            write!(w, ", VARLOCATION=([{}]=CELLCENTERED)", centered.join(","))?;
+        }
+        writeln!(w)?;
+
+        let points = points.cast_into::<f64>().unwrap_or_default();
+        for axis in 0..3 {
+            write_values(&mut w, points.iter().skip(axis).step_by(3).copied())?;
+        }
+        for var in &first {
+            let values = vars.iter().find(|v| v.name == var.name).map(|v| &v.values);
+            write_values(&mut w, values.into_iter().flatten().copied())?;
+        }
+
+        if let Some((cells, nodes)) = cells {
+            let (connectivity, offsets) = cells.cell_verts.clone().into_xml();
+            let mut start = 0;
+            for (cell, (&cell_type, &end)) in cells.types.iter().zip(&offsets).enumerate() {
+                let verts = connectivity.get(start..end as usize).unwrap_or_default();
+                let expected = cell_type.num_verts_range();
+                if !expected.contains(&verts.len()) {
+                    let issue = CellIssue::VertexCountMismatch {
+                        cell,
+                        cell_type,
+                        expected,
+                        found: verts.len(),
+                    };
+                    return Err(model::Error::InvalidCells(issue).into());
+                }
+                let nodes = element_nodes(cell_type, verts, nodes);
+                let line: Vec<_> = nodes.iter().map(|n| (n + 1).to_string()).collect();
+                writeln!(w, "{}", line.join(" "))?;
+                start = end as usize;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_and_block_zones() -> Result<(), crate::Error> {
+        let input: &[u8] = b"# Two zones in different styles
+TITLE = \"Channel\"
+VARIABLES = X Y
+\"P\"
+ZONE T=\"inflow\", I=3, J=2, F=POINT
+0 0 1.5
+1 0 2.5
+2, 0, 3.5
+0 1 4.5
+1 1 5.5
+2 1 6.5
+ZONE T=\"cells\" N=4 E=1 DATAPACKING=BLOCK ZONETYPE=FEQUADRILATERAL
+VARLOCATION=([3]=CELLCENTERED) STRANDID=1 SOLUTIONTIME=0.5 DT=(SINGLE SINGLE SINGLE)
+0 1 1 0
+2*0 2*1
+7
+1 2 3 4
+";
+        let file = DataFile::parse(input)?;
+        assert_eq!(file.title, "Channel");
+        assert_eq!(file.zones.len(), 2);
+
+        let piece = match &file.zones[0] {
+            Zone::Ordered { title, piece } if title == "inflow" => piece,
+            zone => panic!("unexpected zone: {:?}", zone),
+        };
+        assert_eq!(piece.extent, Extent::Dims([3, 2, 1]));
+        assert_eq!(
+            piece.points,
+            vec![0.0, 0., 0., 1., 0., 0., 2., 0., 0., 0., 1., 0., 1., 1., 0., 2., 1., 0.].into()
+        );
+        assert_eq!(
+            piece.data.point[0].data_array().unwrap().data,
+            vec![1.5, 2.5, 3.5, 4.5, 5.5, 6.5].into()
+        );
+
+        let piece = match &file.zones[1] {
+            Zone::FiniteElement { title, piece } if title == "cells" => piece,
+            zone => panic!("unexpected zone: {:?}", zone),
+        };
+        assert_eq!(piece.cells.types, vec![CellType::Quad]);
+        assert_eq!(
+            piece.points,
+            vec![0.0, 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.].into()
+        );
+        assert!(piece.data.point.is_empty());
+        assert_eq!(piece.data.cell[0].name(), "P");
+        assert_eq!(
+            piece.data.cell[0].data_array().unwrap().data,
+            vec![7.0].into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), crate::Error> {
+        let piece = UnstructuredGridPiece {
+            points: vec![0.0, 0., 0., 1., 0., 0., 0., 1., 0., 0., 0., 1., 1., 1., 1.].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 3, 1, 2, 3, 4],
+                    offsets: vec![4, 8],
+                },
+                types: vec![CellType::Tetra; 2],
+            },
+            data: Attributes {
+                point: vec![Attribute::vectors("U").with_data(vec![0.5f32; 15])],
+                cell: vec![Attribute::scalars("id", 1).with_data(vec![1i32, 2])],
+                field: vec![],
+            },
+        };
+        let file = DataFile::from_vtk(
+            Zone::FiniteElement {
+                title: String::new(),
+                piece: piece.clone(),
+            }
+            .into_vtk(),
+        )?;
+        let mut output = Vec::new();
+        file.write(&mut output)?;
+        let text = String::from_utf8(output.clone()).unwrap();
+        assert!(text.contains("VARIABLES = \"X\", \"Y\", \"Z\", \"U_0\", \"U_1\", \"U_2\", \"id\""));
+        assert!(text
+            .contains("ZONETYPE=FETETRAHEDRON, DATAPACKING=BLOCK, VARLOCATION=([7]=CELLCENTERED)"));
+
+        let read = DataFile::parse(output.as_slice())?;
+        let read_piece = match &read.zones[0] {
+            Zone::FiniteElement { piece, .. } => piece,
+            zone => panic!("unexpected zone: {:?}", zone),
+        };
+        assert_eq!(read_piece.points, piece.points);
+        assert_eq!(read_piece.cells, piece.cells);
+        let names: Vec<_> = read_piece.data.point.iter().map(|a| a.name()).collect();
+        assert_eq!(names, vec!["U_0", "U_1", "U_2"]);
+        assert_eq!(
+            read_piece.data.cell[0].data_array().unwrap().data,
+            vec![1.0, 2.0].into()
+        );
+
+        // Mixing cells turns tetrahedra into degenerate bricks.
+        let mut mixed = piece;
+        mixed.cells.types[1] = CellType::Pyramid;
+        mixed.cells.cell_verts = VertexNumbers::XML {
+            connectivity: vec![0, 1, 2, 3, 0, 1, 4, 2, 3],
+            offsets: vec![4, 9],
+        };
+        let file = DataFile {
+            title: String::from("Mixed"),
+            zones: vec![Zone::FiniteElement {
+                title: String::from("mixed"),
+                piece: mixed,
+            }],
+        };
+        let mut output = Vec::new();
+        file.write(&mut output)?;
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("ZONETYPE=FEBRICK"));
+        assert!(text.ends_with("\n1 2 3 3 4 4 4 4\n1 2 5 3 4 4 4 4\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        let parse = |input: &str| match DataFile::parse(input.as_bytes()) {
+            Err(crate::Error::Tecplot(err)) => err,
+            result => panic!("unexpected result: {:?}", result),
+        };
+        assert_eq!(
+            parse("VARIABLES = X\nZONE ZONETYPE=FEPOLYGON, NODES=1, ELEMENTS=1\n"),
+            Error::Unsupported(String::from("FEPOLYGON"))
+        );
+        assert_eq!(parse("VARIABLES = X\nZONE I=3\n0 1\n"), Error::Syntax(4));
+        assert_eq!(
+            parse("VARIABLES = X\nZONE N=2, E=1, ET=LINESEG, F=FEPOINT\n0 1\n1 3\n"),
+            Error::Syntax(4)
+        );
+        // Huge sizes are not trusted before the values are read.
+        assert_eq!(
+            parse("VARIABLES = X Y\nZONE N=999999999999, E=1, ET=TRIANGLE, F=FEPOINT\n0 1\n"),
+            Error::Syntax(4)
+        );
+        assert_eq!(
+            parse("VARIABLES = X\nZONE I=99999999999, J=99999999999, K=99999999999\n"),
+            Error::Syntax(2)
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Conversion of terrain heightmaps into VTK data sets.
+//!
+//! A heightmap is a regular grid of elevations, as commonly found in digital elevation models or
+//! terrain textures. [`from_heightmap`] turns such a grid into a [`Terrain`], which can then be
+//! exported either as a `StructuredGrid` or as triangulated `PolyData`. In both cases the
+//! elevation is also stored as a point scalar named `elevation`, which is convenient for coloring.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::terrain;
+//!
+//! // A 3x2 grid of elevations, given row by row.
+//! let elevations = vec![0.0f32, 1.0, 0.5, 2.0, 1.5, 0.0];
+//! let terrain = terrain::from_heightmap(3, 2, elevations, [10.0, 10.0]).unwrap();
+//!
+//! match terrain.into_poly_data() {
+//!     DataSet::PolyData { pieces, .. } => {
+//!         let piece = pieces[0].load_piece_data(None).unwrap();
+//!         assert_eq!(piece.num_points(), 6);
+//!         assert_eq!(piece.num_polys(), 4);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use num_traits::Float;
+
+use crate::model::*;
+
+/// Name of the point attribute holding the elevation of each point.
+pub const ELEVATION: &str = "elevation";
+
+/// A regular grid of elevations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terrain<T> {
+    width: u32,
+    height: u32,
+    elevations: Vec<T>,
+    spacing: [T; 2],
+    origin: [T; 2],
+}
+
+/// Builds a terrain from `width * height` elevations given row by row.
+///
+/// The first `width` elevations form the row at the smallest `y` coordinate. Neighbouring samples
+/// are `spacing[0]` apart along `x` and `spacing[1]` apart along `y`.
+///
+/// An error is returned if the number of elevations doesn't match the size of the grid.
+pub fn from_heightmap<T: Float + 'static>(
+    width: u32,
+    height: u32,
+    elevations: Vec<T>,
+    spacing: [T; 2],
+) -> Result<Terrain<T>, Error> {
+    let expected = width as usize * height as usize;
+    if elevations.len() != expected {
+        return Err(Error::AttributeSizeMismatch {
+            name: String::from(ELEVATION),
+            expected,
+            actual: elevations.len(),
+        });
+    }
+    Ok(Terrain {
+        width,
+        height,
+        elevations,
+        spacing,
+        origin: [T::zero(); 2],
+    })
+}
+
+impl<T: Float + 'static> Terrain<T> {
+    /// Sets the `x` and `y` coordinates of the first sample.
+    pub fn with_origin(self, origin: [T; 2]) -> Self {
+        Terrain { origin, ..self }
+    }
+
+    /// Converts this terrain into a structured grid with one point per sample.
+    pub fn into_structured_grid(self) -> DataSet {
+        let extent = Extent::Ranges([0..=self.width as i32 - 1, 0..=self.height as i32 - 1, 0..=0]);
+        let (points, data) = self.into_points_and_attributes();
+        DataSet::inline(StructuredGridPiece {
+            extent,
+            points,
+            data,
+        })
+    }
+
+    /// Converts this terrain into a triangle mesh with one point per sample.
+    ///
+    /// Each grid cell is split into two triangles, oriented counter-clockwise when viewed from
+    /// above.
+    pub fn into_poly_data(self) -> DataSet {
+        let (w, h) = (u64::from(self.width), u64::from(self.height));
+        let num_quads = (w.saturating_sub(1) * h.saturating_sub(1)) as usize;
+        let mut connectivity = Vec::with_capacity(6 * num_quads);
+        for j in 0..h.saturating_sub(1) {
+            for i in 0..w.saturating_sub(1) {
+                let v = j * w + i;
+                connectivity.extend_from_slice(&[v, v + 1, v + w + 1, v, v + w + 1, v + w]);
+            }
+        }
+        let offsets = (1..=2 * num_quads as u64).map(|n| 3 * n).collect();
+        let (points, data) = self.into_points_and_attributes();
+        DataSet::inline(PolyDataPiece {
+            points,
+            polys: Some(VertexNumbers::XML {
+                connectivity,
+                offsets,
+            }),
+            data,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the positions of all samples along with the elevation attribute.
+    fn into_points_and_attributes(self) -> (IOBuffer, Attributes) {
+        let mut points = Vec::with_capacity(3 * self.elevations.len());
+        let width = self.width.max(1) as usize;
+        for (n, &z) in self.elevations.iter().enumerate() {
+            let i = T::from(n % width).unwrap();
+            let j = T::from(n / width).unwrap();
+            points.push(self.origin[0] + i * self.spacing[0]);
+            points.push(self.origin[1] + j * self.spacing[1]);
+            points.push(z);
+        }
+        let mut data = Attributes::new();
+        data.point
+            .push(Attribute::scalars(ELEVATION, 1).with_data(IOBuffer::new(self.elevations)));
+        (IOBuffer::new(points), data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_grid() {
+        let terrain = from_heightmap(2, 2, vec![1.0f64, 2.0, 3.0, 4.0], [0.5, 2.0])
+            .unwrap()
+            .with_origin([10.0, 20.0]);
+        match terrain.into_structured_grid() {
+            DataSet::StructuredGrid { extent, pieces, .. } => {
+                assert_eq!(extent, Extent::Ranges([0..=1, 0..=1, 0..=0]));
+                let piece = pieces[0].load_piece_data(None).unwrap();
+                assert_eq!(
+                    piece.points.into_vec::<f64>().unwrap(),
+                    vec![10.0, 20.0, 1.0, 10.5, 20.0, 2.0, 10.0, 22.0, 3.0, 10.5, 22.0, 4.0]
+                );
+                assert_eq!(piece.data.point[0].name(), ELEVATION);
+            }
+            _ => panic!("expected a structured grid"),
+        }
+    }
+
+    #[test]
+    fn poly_data() {
+        let terrain = from_heightmap(3, 2, vec![0.0f32; 6], [1.0, 1.0]).unwrap();
+        match terrain.into_poly_data() {
+            DataSet::PolyData { pieces, .. } => {
+                let piece = pieces[0].load_piece_data(None).unwrap();
+                assert_eq!(
+                    piece.polys,
+                    Some(VertexNumbers::XML {
+                        connectivity: vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4],
+                        offsets: vec![3, 6, 9, 12],
+                    })
+                );
+            }
+            _ => panic!("expected poly data"),
+        }
+    }
+
+    #[test]
+    fn size_mismatch() {
+        assert!(matches!(
+            from_heightmap(3, 2, vec![0.0f32; 5], [1.0, 1.0]),
+            Err(Error::AttributeSizeMismatch {
+                expected: 6,
+                actual: 5,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Preservation of content following the data set in legacy files.
+//!
+//! Some tools append text, such as provenance information, after the data set of a legacy `.vtk`
+//! file. This isn't part of the file format, so [`Vtk::import`] and friends ignore it with a
+//! [`Warning::ContentIgnored`](crate::Warning::ContentIgnored), or reject it with strict syntax.
+//! A [`LegacyFile`] instead keeps this content as an opaque trailer, which is written back after
+//! the data set. This allows files to be rewritten without losing anything appended to them.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::trailer::LegacyFile;
+//!
+//! let input: &[u8] = b"# vtk DataFile Version 2.0
+//! Points
+//! ASCII
+//! DATASET POLYDATA
+//! POINTS 1 float
+//! 0 0 0
+//!
+//! Created by mesher 1.2
+//! ";
+//!
+//! let file = LegacyFile::parse(input).expect("Failed to parse file");
+//! assert_eq!(file.trailer, b"Created by mesher 1.2\n");
+//!
+//! let mut output = Vec::new();
+//! file.write(&Default::default(), &mut output).expect("Failed to write file");
+//! assert!(output.ends_with(b"\nCreated by mesher 1.2\n"));
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::model::Vtk;
+use crate::writer::{OutputFormat, WriterConfig};
+use crate::{Error, ReadOptions};
+
+/// A legacy VTK file along with any content following its data set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegacyFile {
+    /// The data set stored in the file.
+    pub vtk: Vtk,
+    /// The content following the data set, starting at its first non-whitespace byte.
+    ///
+    /// This is empty if nothing follows the data set.
+    pub trailer: Vec<u8>,
+}
+
+impl LegacyFile {
+    /// Imports the legacy VTK file at the given path.
+    ///
+    /// The byte order of binary data is detected as in [`Vtk::parse_legacy`].
+    pub fn import(file_path: impl AsRef<Path>) -> Result<LegacyFile, Error> {
+        let mut file = LegacyFile::parse(File::open(file_path.as_ref())?)?;
+        file.vtk.file_path = Some(file_path.as_ref().into());
+        Ok(file)
+    }
+
+    /// Parses a legacy VTK file from the given reader.
+    ///
+    /// The byte order of binary data is detected as in [`Vtk::parse_legacy`].
+    pub fn parse(reader: impl Read) -> Result<LegacyFile, Error> {
+        LegacyFile::parse_with_options(reader, ReadOptions::default())
+    }
+
+    /// Parses a legacy VTK file from the given reader with the given options.
+    ///
+    /// Any content that can't be parsed as part of the data set ends up in the trailer, including
+    /// malformed sections of the data set itself. Check the parsed data set if the trailer is
+    /// expected to be empty.
+    pub fn parse_with_options(
+        reader: impl Read,
+        options: ReadOptions,
+    ) -> Result<LegacyFile, Error> {
+        let (vtk, trailer) = Vtk::parse_legacy_with_trailer(reader, options)?;
+        Ok(LegacyFile { vtk, trailer })
+    }
+
+    /// Writes the data set with the given configuration followed by the trailer.
+    ///
+    /// The trailer is only written to legacy files, since XML files have no place for it.
+    pub fn write(self, config: &WriterConfig, mut writer: impl Write) -> Result<(), Error> {
+        config.write(self.vtk, &mut writer)?;
+        #[cfg(feature = "xml")]
+        if config.format() == OutputFormat::Xml {
+            return Ok(());
+        }
+        writer.write_all(&self.trailer)?;
+        Ok(())
+    }
+
+    /// Exports the data set in legacy binary format followed by the trailer to the given path.
+    pub fn export(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
+        self.export_with(&WriterConfig::new(), file_path.as_ref())
+    }
+
+    /// Exports the data set in legacy ASCII format followed by the trailer to the given path.
+    pub fn export_ascii(self, file_path: impl AsRef<Path>) -> Result<(), Error> {
+        let config = WriterConfig::new().with_format(OutputFormat::LegacyAscii);
+        self.export_with(&config, file_path.as_ref())
+    }
+
+    fn export_with(self, config: &WriterConfig, path: &Path) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(config, &mut writer)?;
+        // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_round_trip() -> Result<(), Error> {
+        let vtk = crate::import("assets/tet_test_binary.vtk")?;
+        let mut input = Vec::new();
+        vtk.clone().write_legacy(&mut input)?;
+        input.extend_from_slice(b"\n# Provenance: \xff binary \x00 bytes\n");
+
+        let file = LegacyFile::parse(input.as_slice())?;
+        assert_eq!(file.trailer, b"# Provenance: \xff binary \x00 bytes\n");
+        assert_eq!(file.vtk.data, vtk.data);
+
+        let mut output = Vec::new();
+        file.write(&WriterConfig::new(), &mut output)?;
+        assert_eq!(
+            LegacyFile::parse(output.as_slice())?.trailer,
+            b"# Provenance: \xff binary \x00 bytes\n"
+        );
+
+        // Files without a trailer are unchanged.
+        let file = LegacyFile::parse(std::fs::File::open("assets/tet.vtk")?)?;
+        assert!(file.trailer.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Export of trajectories as poly lines.
+//!
+//! Particle tracks, sensor paths and similar data consist of a number of trajectories, each given
+//! by a sequence of positions. [`to_poly_data`] stores each trajectory as one poly line in the
+//! `LINES` topology of a `PolyData` piece, and [`from_poly_data`] recovers the trajectories from
+//! such a piece.
+//!
+//! # Examples
+//!
+//! ```
+//! use vtkio::model::*;
+//! use vtkio::trajectories;
+//!
+//! let tracks = vec![
+//!     vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 1.0, 0.0]],
+//!     vec![[0.0, 1.0, 0.0], [0.0, 2.0, 0.0]],
+//! ];
+//! let time = Attribute::scalars("time", 1).with_data(vec![0.0f32, 0.5, 1.0, 0.0, 1.0]);
+//! let piece = trajectories::to_poly_data(tracks.clone(), vec![time]).unwrap();
+//! assert_eq!(piece.num_lines(), 2);
+//!
+//! assert_eq!(trajectories::from_poly_data(piece), Some(tracks));
+//! ```
+
+use crate::model::{self, *};
+
+/// A position along a trajectory.
+pub type Point = [f64; 3];
+
+/// Builds a poly data piece with one poly line per trajectory.
+///
+/// Points are stored in the order of the trajectories, so `attributes` must hold one tuple for
+/// each point of the first trajectory, followed by those of the second trajectory and so on.
+/// An error is returned if any attribute doesn't have exactly one tuple per point.
+pub fn to_poly_data(
+    trajectories: Vec<Vec<Point>>,
+    attributes: Vec<Attribute>,
+) -> Result<PolyDataPiece, Error> {
+    let num_points: usize = trajectories.iter().map(Vec::len).sum();
+    model::validate_attribute_sizes(&attributes, num_points)?;
+
+    let mut points = Vec::with_capacity(3 * num_points);
+    let mut offsets = Vec::with_capacity(trajectories.len());
+    for trajectory in trajectories {
+        points.extend(trajectory.into_iter().flatten());
+        offsets.push((points.len() / 3) as u64);
+    }
+    Ok(PolyDataPiece {
+        points: points.into(),
+        lines: Some(VertexNumbers::XML {
+            connectivity: (0..num_points as u64).collect(),
+            offsets,
+        }),
+        data: Attributes {
+            point: attributes,
+            cell: Vec::new(),
+            field: Vec::new(),
+        },
+        ..Default::default()
+    })
+}
+
+/// Extracts the trajectories stored as poly lines in the given piece.
+///
+/// Each line in the `LINES` topology becomes one trajectory, and all other topologies are ignored.
+/// Returns `None` if the piece has no lines, if its points cannot be converted to `f64`, or if a
+/// line refers to a point that doesn't exist.
+pub fn from_poly_data(piece: PolyDataPiece) -> Option<Vec<Vec<Point>>> {
+    let points = piece.points.cast_into::<f64>()?;
+    let (connectivity, offsets) = piece.lines?.into_xml();
+    let mut begin = 0;
+    offsets
+        .into_iter()
+        .map(|end| {
+            let line = connectivity.get(begin as usize..end as usize)?;
+            begin = end;
+            line.iter()
+                .map(|&v| {
+                    let p = points.get(3 * v as usize..3 * v as usize + 3)?;
+                    Some([p[0], p[1], p[2]])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_lines() {
+        let piece = PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0].into(),
+            lines: Some(VertexNumbers::Legacy {
+                num_cells: 2,
+                vertices: vec![2, 0, 1, 3, 2, 1, 0],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            from_poly_data(piece),
+            Some(vec![
+                vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+                vec![[2.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        let tracks = vec![vec![[0.0; 3]; 2], vec![[1.0; 3]; 3]];
+        assert!(matches!(
+            to_poly_data(
+                tracks,
+                vec![Attribute::vectors("v").with_data(vec![0.0f32; 6])]
+            ),
+            Err(Error::AttributeSizeMismatch {
+                expected: 15,
+                actual: 6,
+                ..
+            })
+        ));
+
+        // Out of range vertex indices.
+        let piece = PolyDataPiece {
+            points: vec![0.0f64; 3].into(),
+            lines: Some(VertexNumbers::XML {
+                connectivity: vec![0, 1],
+                offsets: vec![2],
+            }),
+            ..Default::default()
+        };
+        assert_eq!(from_poly_data(piece), None);
+        assert_eq!(from_poly_data(PolyDataPiece::default()), None);
+    }
+}
@@ -0,0 +1,843 @@
+//! Export of scenes into the zipped `.vtkjs` bundle format read by vtk.js web viewers.
+//!
+//! A `.vtkjs` file is a zip archive containing an `index.json` scene description along with a
+//! directory for every data set. Each data set directory holds an `index.json` file describing
+//! the data set structure and a `data` directory with the raw little endian array values. Such
+//! bundles can be opened directly in the vtk.js `SceneExplorer` and `OfflineLocalView` viewers.
+//!
+//! Display hints of each [`Block`] are translated into the corresponding vtk.js actor, mapper
+//! and property settings.
+//!
+//! Only poly data and image data are supported, since these are the data set types rendered by
+//! vtk.js. Each piece of a block is exported as a separate data set sharing the block's display
+//! hints. When the `compression` feature is enabled, the archive entries are deflated.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::model::*;
+//! use vtkio::scene::*;
+//!
+//! let vtk = Vtk::import("assets/cube.vtk").expect("Failed to load file");
+//! let scene = Scene::new().with_block(
+//!     "cube",
+//!     vtk,
+//!     DisplayHints::new().with_representation(Representation::SurfaceWithEdges),
+//! );
+//! scene.export_vtkjs("cube.vtkjs").expect("Failed to export scene");
+//! ```
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::path::Path;
+
+use byteorder::{WriteBytesExt, LE};
+
+use crate::model::*;
+use crate::scene::{json_string, Block, DisplayHints, Representation, Scene};
+use crate::Error;
+
+/// Name of the scene description file at the root of the archive.
+pub const INDEX_FILE_NAME: &str = "index.json";
+
+/// Exports the given scene into a `.vtkjs` file at the given path.
+pub fn export(scene: &Scene, path: impl AsRef<Path>) -> Result<(), Error> {
+    // Check before creating the file, so nothing is left behind.
+    scene.check_hints()?;
+    let file = std::fs::File::create(path.as_ref())?;
+    write(scene, io::BufWriter::new(file))?.flush()?;
+    Ok(())
+}
+
+/// Writes the given scene as a `.vtkjs` archive into the given writer.
+///
+/// The writer is returned once the archive is complete.
+pub fn write<W: Write>(scene: &Scene, writer: W) -> Result<W, Error> {
+    scene.check_hints()?;
+    let mut zip = ZipWriter::new(writer);
+    let mut items = Vec::new();
+    let mut dirs = HashSet::new();
+    for block in scene.blocks.iter() {
+        let pieces = block_pieces(block)?;
+        let num_pieces = pieces.len();
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let mut dir = if num_pieces > 1 {
+                format!("{}_{}", block.sanitized_name(), i)
+            } else {
+                block.sanitized_name()
+            };
+            // Make sure every data set gets its own directory.
+            let base = dir.clone();
+            let mut n = 1;
+            while !dirs.insert(dir.clone()) {
+                dir = format!("{}_{}", base, n);
+                n += 1;
+            }
+            let color_range = block
+                .hints
+                .color_by
+                .as_ref()
+                .and_then(|name| piece.color_range(name));
+            piece.write(&dir, &mut zip)?;
+            items.push(SceneItem {
+                name: if num_pieces > 1 {
+                    format!("{} ({})", block.name, i)
+                } else {
+                    block.name.clone()
+                },
+                dir,
+                hints: &block.hints,
+                color_range,
+            });
+        }
+    }
+    zip.add(INDEX_FILE_NAME, scene_json(&items).as_bytes())?;
+    Ok(zip.finish()?)
+}
+
+/// A single data set ready to be written into the archive.
+enum DataSetPiece {
+    PolyData(PolyDataPiece),
+    ImageData {
+        origin: [f32; 3],
+        spacing: [f32; 3],
+        piece: ImageDataPiece,
+    },
+}
+
+/// Loads all pieces of the given block.
+fn block_pieces(block: &Block) -> Result<Vec<DataSetPiece>, Error> {
+    let source_path = block.vtk.file_path.as_deref();
+    match &block.vtk.data {
+        DataSet::PolyData { pieces, .. } => pieces
+            .iter()
+            .map(|p| Ok(DataSetPiece::PolyData(p.load_piece_data(source_path)?)))
+            .collect(),
+        DataSet::ImageData {
+            origin,
+            spacing,
+            pieces,
+            ..
+        } => pieces
+            .iter()
+            .map(|p| {
+                Ok(DataSetPiece::ImageData {
+                    origin: *origin,
+                    spacing: *spacing,
+                    piece: p.load_piece_data(source_path)?,
+                })
+            })
+            .collect(),
+        DataSet::StructuredGrid { .. } => Err(Error::UnsupportedDataSet("StructuredGrid")),
+        DataSet::RectilinearGrid { .. } => Err(Error::UnsupportedDataSet("RectilinearGrid")),
+        DataSet::UnstructuredGrid { .. } => Err(Error::UnsupportedDataSet("UnstructuredGrid")),
+        DataSet::Field { .. } => Err(Error::UnsupportedDataSet("Field")),
+    }
+}
+
+impl DataSetPiece {
+    fn attributes(&self) -> &Attributes {
+        match self {
+            DataSetPiece::PolyData(piece) => &piece.data,
+            DataSetPiece::ImageData { piece, .. } => &piece.data,
+        }
+    }
+
+    /// Computes the range of the named attribute, which determines the color map range.
+    ///
+    /// The magnitude is used for attributes with multiple components. Points and cells blanked
+    /// in the `vtkGhostType` attribute don't contribute to the range.
+    fn color_range(&self, name: &str) -> Option<(bool, [f64; 2])> {
+        let attribs = self.attributes();
+        let point = attribs.point.iter().map(|a| (true, a));
+        let cell = attribs.cell.iter().map(|a| (false, a));
+        point
+            .chain(cell)
+            .find_map(|(is_point, attrib)| match attrib {
+                Attribute::DataArray(DataArray {
+                    name: n,
+                    elem,
+                    data,
+                }) if n == name => {
+                    let num_comp = (elem.num_comp() as usize).max(1);
+                    let values = data.cast_into::<f64>()?;
+                    let (ghosts, hidden) = if is_point {
+                        (ghost_flags(&attribs.point), HIDDEN_POINT)
+                    } else {
+                        (ghost_flags(&attribs.cell), HIDDEN_CELL)
+                    };
+                    let is_visible = |i: usize| {
+                        ghosts
+                            .and_then(|g| g.get(i))
+                            .is_none_or(|&f| f & hidden == 0)
+                    };
+                    let range = values
+                        .chunks_exact(num_comp)
+                        .enumerate()
+                        .filter(|&(i, _)| is_visible(i))
+                        .map(|(_, v)| {
+                            if num_comp == 1 {
+                                v[0]
+                            } else {
+                                v.iter().map(|x| x * x).sum::<f64>().sqrt()
+                            }
+                        })
+                        .fold([f64::INFINITY, f64::NEG_INFINITY], |[lo, hi], x| {
+                            [lo.min(x), hi.max(x)]
+                        });
+                    Some((is_point, range)).filter(|_| range[0] <= range[1])
+                }
+                _ => None,
+            })
+    }
+
+    /// Writes the data set description and its arrays into the given data set directory.
+    fn write<W: Write>(&self, dir: &str, zip: &mut ZipWriter<W>) -> Result<(), Error> {
+        let mut arrays = ArrayWriter {
+            dir,
+            zip,
+            written: HashSet::new(),
+        };
+        let mut out = String::new();
+        out.push_str("{\n");
+        match self {
+            DataSetPiece::PolyData(piece) => {
+                out.push_str("  \"vtkClass\": \"vtkPolyData\",\n");
+                let points = arrays.write("vtkPoints", "_points", 3, &piece.points, None)?;
+                if let Some(points) = points {
+                    writeln!(out, "  \"points\": {},", points).unwrap();
+                }
+                let topology = [
+                    ("verts", &piece.verts),
+                    ("lines", &piece.lines),
+                    ("polys", &piece.polys),
+                    ("strips", &piece.strips),
+                ];
+                for (name, cells) in topology.iter() {
+                    if let Some(cells) = cells {
+                        let (_, vertices) = cells.clone().into_legacy();
+                        // vtk.js stores cells in 32-bit arrays.
+                        let vertices = vertices
+                            .into_iter()
+                            .map(u32::try_from)
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|_| {
+                                io::Error::new(
+                                    io::ErrorKind::InvalidInput,
+                                    "vertex indices too large for vtk.js",
+                                )
+                            })?;
+                        let buf = IOBuffer::U32(vertices);
+                        let array = format!("_{}", name);
+                        if let Some(cells) = arrays.write("vtkCellArray", &array, 1, &buf, None)? {
+                            writeln!(out, "  \"{}\": {},", name, cells).unwrap();
+                        }
+                    }
+                }
+            }
+            DataSetPiece::ImageData {
+                origin,
+                spacing,
+                piece,
+            } => {
+                let [x, y, z] = piece.extent.clone().into_ranges();
+                out.push_str("  \"vtkClass\": \"vtkImageData\",\n");
+                writeln!(out, "  \"origin\": {},", json_array(origin)).unwrap();
+                writeln!(out, "  \"spacing\": {},", json_array(spacing)).unwrap();
+                let extent = [x.start(), x.end(), y.start(), y.end(), z.start(), z.end()];
+                writeln!(out, "  \"extent\": {},", json_array(&extent)).unwrap();
+            }
+        }
+        let attribs = self.attributes();
+        let point_data = arrays.write_attributes(&attribs.point)?;
+        let cell_data = arrays.write_attributes(&attribs.cell)?;
+        writeln!(out, "  \"pointData\": {},", point_data).unwrap();
+        writeln!(out, "  \"cellData\": {}", cell_data).unwrap();
+        out.push_str("}\n");
+        arrays
+            .zip
+            .add(&format!("{}/{}", dir, INDEX_FILE_NAME), out.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Writes array values into the `data` directory of a data set, skipping duplicate arrays.
+struct ArrayWriter<'a, W> {
+    dir: &'a str,
+    zip: &'a mut ZipWriter<W>,
+    written: HashSet<String>,
+}
+
+impl<'a, W: Write> ArrayWriter<'a, W> {
+    /// Writes the values of the given buffer and returns the JSON description referencing them.
+    ///
+    /// Returns `None` for bit arrays, which are not supported by vtk.js.
+    fn write(
+        &mut self,
+        class: &str,
+        name: &str,
+        num_comp: usize,
+        buf: &IOBuffer,
+        registration: Option<&str>,
+    ) -> Result<Option<String>, Error> {
+        let (data_type, bytes) = match typed_bytes(buf) {
+            Some(typed) => typed,
+            None => return Ok(None),
+        };
+        // Identical arrays are only stored once.
+        let id = format!("{:08x}{:x}", crc32(&bytes), bytes.len());
+        if self.written.insert(id.clone()) {
+            self.zip.add(&format!("{}/data/{}", self.dir, id), &bytes)?;
+        }
+        let mut out = String::new();
+        write!(
+            out,
+            "{{\"vtkClass\": \"{}\", \"name\": {}, \"numberOfComponents\": {}, \
+             \"dataType\": \"{}\", \"size\": {}, \"ref\": {{\"encode\": \"LittleEndian\", \
+             \"basepath\": \"data\", \"id\": \"{}\"",
+            class,
+            json_string(name),
+            num_comp,
+            data_type,
+            buf.len(),
+            id
+        )
+        .unwrap();
+        if let Some(registration) = registration {
+            write!(out, ", \"registration\": \"{}\"", registration).unwrap();
+        }
+        out.push_str("}}");
+        Ok(Some(out))
+    }
+
+    /// Writes the given attributes and returns the JSON description of the `vtkDataSetAttributes`.
+    ///
+    /// The first attribute of each of the scalars, vectors, normals, texture coordinates and
+    /// tensors types is registered as the active attribute of that type.
+    fn write_attributes(&mut self, attributes: &[Attribute]) -> Result<String, Error> {
+        let mut active = HashSet::new();
+        let mut arrays = Vec::new();
+        for attrib in attributes.iter() {
+            match attrib {
+                Attribute::DataArray(DataArray { name, elem, data }) => {
+                    let registration = match elem {
+                        ElementType::Scalars { .. } => "setScalars",
+                        ElementType::Vectors => "setVectors",
+                        ElementType::Normals => "setNormals",
+                        ElementType::TCoords(_) => "setTCoords",
+                        ElementType::Tensors => "setTensors",
+                        _ => "addArray",
+                    };
+                    let registration = if registration == "addArray" || active.insert(registration)
+                    {
+                        registration
+                    } else {
+                        "addArray"
+                    };
+                    let num_comp = elem.num_comp() as usize;
+                    arrays.extend(self.write(
+                        "vtkDataArray",
+                        name,
+                        num_comp,
+                        data,
+                        Some(registration),
+                    )?);
+                }
+                Attribute::Field { data_array, .. } => {
+                    for array in data_array.iter() {
+                        arrays.extend(self.write(
+                            "vtkDataArray",
+                            &array.name,
+                            array.num_comp(),
+                            &array.data,
+                            Some("addArray"),
+                        )?);
+                    }
+                }
+                // Unknown legacy sections can't be represented in vtk.js.
+                Attribute::Unknown { .. } => {}
+            }
+        }
+        let mut out = String::from("{\"vtkClass\": \"vtkDataSetAttributes\", \"arrays\": [");
+        for (i, array) in arrays.iter().enumerate() {
+            let sep = if i > 0 { ", " } else { "" };
+            write!(out, "{}{{\"data\": {}}}", sep, array).unwrap();
+        }
+        out.push_str("]}");
+        Ok(out)
+    }
+}
+
+/// Converts the given buffer into little endian bytes along with the name of the corresponding
+/// JavaScript typed array.
+///
+/// 64-bit integers are converted to doubles since they are not supported by vtk.js.
+fn typed_bytes(buf: &IOBuffer) -> Option<(&'static str, Vec<u8>)> {
+    Some(match buf {
+        IOBuffer::Bit(_) => return None,
+        IOBuffer::U8(v) => ("Uint8Array", v.clone()),
+        IOBuffer::I8(v) => ("Int8Array", v.iter().map(|&x| x as u8).collect()),
+        IOBuffer::U16(v) => (
+            "Uint16Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+        IOBuffer::I16(v) => (
+            "Int16Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+        IOBuffer::U32(v) => (
+            "Uint32Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+        IOBuffer::I32(v) => (
+            "Int32Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+        IOBuffer::U64(v) => (
+            "Float64Array",
+            v.iter().flat_map(|&x| (x as f64).to_le_bytes()).collect(),
+        ),
+        IOBuffer::I64(v) => (
+            "Float64Array",
+            v.iter().flat_map(|&x| (x as f64).to_le_bytes()).collect(),
+        ),
+        IOBuffer::F32(v) => (
+            "Float32Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+        IOBuffer::F64(v) => (
+            "Float64Array",
+            v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+        ),
+    })
+}
+
+/// A data set entry in the scene description.
+struct SceneItem<'a> {
+    name: String,
+    dir: String,
+    hints: &'a DisplayHints,
+    /// Whether the colored attribute is a point attribute along with its range.
+    color_range: Option<(bool, [f64; 2])>,
+}
+
+/// Produces the root `index.json` scene description.
+fn scene_json(items: &[SceneItem]) -> String {
+    let mut out = String::new();
+    out.push_str("{\n");
+    out.push_str("  \"version\": 1,\n");
+    out.push_str("  \"background\": [1, 1, 1],\n");
+    out.push_str("  \"scene\": [\n");
+    for (i, item) in items.iter().enumerate() {
+        let DisplayHints {
+            representation,
+            color_by,
+            colormap,
+            opacity,
+            visible,
+        } = item.hints;
+        let (representation, edge_visibility) = match representation {
+            Representation::Points => (0, 0),
+            Representation::Wireframe | Representation::Outline => (1, 0),
+            Representation::Surface | Representation::Volume => (2, 0),
+            Representation::SurfaceWithEdges => (2, 1),
+        };
+        out.push_str("    {\n");
+        writeln!(out, "      \"name\": {},", json_string(&item.name)).unwrap();
+        out.push_str("      \"type\": \"httpDataSetReader\",\n");
+        writeln!(
+            out,
+            "      \"httpDataSetReader\": {{\"url\": {}}},",
+            json_string(&item.dir)
+        )
+        .unwrap();
+        writeln!(out, "      \"actor\": {{\"visibility\": {}}},", visible).unwrap();
+        match (color_by, item.color_range) {
+            (Some(color_by), Some((is_point, [lo, hi]))) => {
+                // Use point (3) or cell (4) field data for coloring.
+                let scalar_mode = if is_point { 3 } else { 4 };
+                writeln!(
+                    out,
+                    "      \"mapper\": {{\"colorByArrayName\": {}, \"colorMode\": 1, \
+                     \"scalarMode\": {}, \"scalarVisibility\": true}},",
+                    json_string(color_by),
+                    scalar_mode
+                )
+                .unwrap();
+                writeln!(
+                    out,
+                    "      \"lookupTable\": {{\"tableRange\": [{}, {}], \"hueRange\": [0.666, 0]}},",
+                    lo, hi
+                )
+                .unwrap();
+                if let Some(colormap) = colormap {
+                    writeln!(out, "      \"colorMapPreset\": {},", json_string(colormap)).unwrap();
+                }
+            }
+            _ => out.push_str("      \"mapper\": {\"scalarVisibility\": false},\n"),
+        }
+        writeln!(
+            out,
+            "      \"property\": {{\"representation\": {}, \"edgeVisibility\": {}, \
+             \"opacity\": {}}}",
+            representation, edge_visibility, opacity
+        )
+        .unwrap();
+        let sep = if i + 1 < items.len() { "," } else { "" };
+        writeln!(out, "    }}{}", sep).unwrap();
+    }
+    out.push_str("  ],\n");
+    out.push_str("  \"lookupTables\": {}\n");
+    out.push_str("}\n");
+    out
+}
+
+fn json_array<T: std::fmt::Display>(values: &[T]) -> String {
+    let values: Vec<_> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(", "))
+}
+
+/*
+ * Zip archive writing
+ */
+
+/// An entry recorded in the central directory of a zip archive.
+struct ZipEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// A minimal writer for zip archives without zip64 extensions.
+struct ZipWriter<W> {
+    out: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+}
+
+/// The DOS date of the entries: 1980-01-01.
+const ZIP_DATE: u16 = (1 << 5) | 1;
+
+fn too_large() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "data too large for a zip archive",
+    )
+}
+
+impl<W: Write> ZipWriter<W> {
+    fn new(out: W) -> Self {
+        ZipWriter {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let (method, compressed) = compress(data)?;
+        let entry = ZipEntry {
+            name: name.to_string(),
+            method,
+            crc: crc32(data),
+            compressed_size: u32::try_from(compressed.len()).map_err(|_| too_large())?,
+            size: u32::try_from(data.len()).map_err(|_| too_large())?,
+            offset: u32::try_from(self.offset).map_err(|_| too_large())?,
+        };
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.write_u32::<LE>(0x0403_4b50)?;
+        header.write_u16::<LE>(20)?; // Version needed to extract
+        header.write_u16::<LE>(0)?; // Flags
+        header.write_u16::<LE>(entry.method)?;
+        header.write_u16::<LE>(0)?; // Time
+        header.write_u16::<LE>(ZIP_DATE)?;
+        header.write_u32::<LE>(entry.crc)?;
+        header.write_u32::<LE>(entry.compressed_size)?;
+        header.write_u32::<LE>(entry.size)?;
+        header.write_u16::<LE>(u16::try_from(name.len()).map_err(|_| too_large())?)?;
+        header.write_u16::<LE>(0)?; // Extra field length
+        header.extend_from_slice(name.as_bytes());
+        self.out.write_all(&header)?;
+        self.out.write_all(&compressed)?;
+        self.offset += (header.len() + compressed.len()) as u64;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    fn finish(mut self) -> io::Result<W> {
+        let mut dir = Vec::new();
+        for entry in self.entries.iter() {
+            dir.write_u32::<LE>(0x0201_4b50)?;
+            dir.write_u16::<LE>(20)?; // Version made by
+            dir.write_u16::<LE>(20)?; // Version needed to extract
+            dir.write_u16::<LE>(0)?; // Flags
+            dir.write_u16::<LE>(entry.method)?;
+            dir.write_u16::<LE>(0)?; // Time
+            dir.write_u16::<LE>(ZIP_DATE)?;
+            dir.write_u32::<LE>(entry.crc)?;
+            dir.write_u32::<LE>(entry.compressed_size)?;
+            dir.write_u32::<LE>(entry.size)?;
+            dir.write_u16::<LE>(entry.name.len() as u16)?;
+            dir.write_u16::<LE>(0)?; // Extra field length
+            dir.write_u16::<LE>(0)?; // Comment length
+            dir.write_u16::<LE>(0)?; // Disk number
+            dir.write_u16::<LE>(0)?; // Internal attributes
+            dir.write_u32::<LE>(0)?; // External attributes
+            dir.write_u32::<LE>(entry.offset)?;
+            dir.extend_from_slice(entry.name.as_bytes());
+        }
+        let num_entries = u16::try_from(self.entries.len()).map_err(|_| too_large())?;
+        let dir_size = u32::try_from(dir.len()).map_err(|_| too_large())?;
+        let dir_offset = u32::try_from(self.offset).map_err(|_| too_large())?;
+        dir.write_u32::<LE>(0x0605_4b50)?;
+        dir.write_u16::<LE>(0)?; // Disk number
+        dir.write_u16::<LE>(0)?; // Disk with the central directory
+        dir.write_u16::<LE>(num_entries)?;
+        dir.write_u16::<LE>(num_entries)?;
+        dir.write_u32::<LE>(dir_size)?;
+        dir.write_u32::<LE>(dir_offset)?;
+        dir.write_u16::<LE>(0)?; // Comment length
+        self.out.write_all(&dir)?;
+        Ok(self.out)
+    }
+}
+
+/// Deflates the given data, returning the zip compression method along with the result.
+///
+/// The data is stored as is if compression doesn't reduce its size.
+#[cfg(feature = "compression")]
+fn compress(data: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    if compressed.len() < data.len() {
+        Ok((8, compressed))
+    } else {
+        Ok((0, data.to_vec()))
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress(data: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+    Ok((0, data.to_vec()))
+}
+
+/// Computes the CRC-32 checksum used by zip archives.
+#[cfg(feature = "compression")]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Computes the CRC-32 checksum used by zip archives.
+#[cfg(not(feature = "compression"))]
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        CRC32_TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Lookup table of the CRC-32 polynomial used by zip archives.
+#[cfg(not(feature = "compression"))]
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            bit += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::convert::TryInto;
+
+    /// Reads all entries of a zip archive produced by `ZipWriter`.
+    fn read_zip(bytes: &[u8]) -> HashMap<String, Vec<u8>> {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]) as usize;
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap()) as usize;
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(end), 0x0605_4b50);
+        let num_entries = u16_at(end + 10);
+        let mut pos = u32_at(end + 16);
+        let mut entries = HashMap::new();
+        for _ in 0..num_entries {
+            assert_eq!(u32_at(pos), 0x0201_4b50);
+            let method = u16_at(pos + 10);
+            let crc = u32_at(pos + 16) as u32;
+            let compressed_size = u32_at(pos + 20);
+            let name_len = u16_at(pos + 28);
+            let offset = u32_at(pos + 42);
+            let name = String::from_utf8(bytes[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+            let start = offset + 30 + u16_at(offset + 26);
+            let raw = &bytes[start..start + compressed_size];
+            let data = match method {
+                0 => raw.to_vec(),
+                #[cfg(feature = "compression")]
+                8 => {
+                    use std::io::Read;
+                    let mut data = Vec::new();
+                    flate2::read::DeflateDecoder::new(raw)
+                        .read_to_end(&mut data)
+                        .unwrap();
+                    data
+                }
+                _ => panic!("unexpected compression method"),
+            };
+            assert_eq!(crc32(&data), crc);
+            entries.insert(name, data);
+            pos += 46 + name_len;
+        }
+        entries
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn scene_archive() -> Result<(), Error> {
+        let triangle = Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::from("Triangle"),
+            file_path: None,
+            data: DataSet::inline(PolyDataPiece {
+                points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+                polys: Some(VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2],
+                    offsets: vec![3],
+                }),
+                data: Attributes {
+                    point: vec![Attribute::scalars("pressure", 1).with_data(vec![1.0f64, 2.0, 4.0])],
+                    cell: vec![Attribute::generic("id", 1).with_data(vec![7i64])],
+                    field: Vec::new(),
+                },
+                ..Default::default()
+            }),
+        };
+        let image = Vtk {
+            data: DataSet::ImageData {
+                extent: Extent::Dims([2, 2, 1]),
+                origin: [1.0, 2.0, 3.0],
+                spacing: [0.5, 0.5, 1.0],
+                meta: None,
+                pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                    extent: Extent::Dims([2, 2, 1]),
+                    data: Attributes::new(),
+                }))],
+            },
+            ..triangle.clone()
+        };
+        let scene = Scene::new()
+            .with_block(
+                "tri",
+                triangle,
+                DisplayHints::new()
+                    .with_representation(Representation::SurfaceWithEdges)
+                    .with_color_by("pressure", "Cool to Warm")
+                    .with_opacity(0.5),
+            )
+            .with_block("tri", image, DisplayHints::new().with_visibility(false));
+
+        let entries = read_zip(&write(&scene, Vec::new())?);
+        let mut names: Vec<_> = entries.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names.len(), 7);
+        assert!(names.contains(&"index.json"));
+        assert!(names.contains(&"tri/index.json"));
+        assert!(names.contains(&"tri_1/index.json"));
+
+        let index = String::from_utf8(entries["index.json"].clone()).unwrap();
+        assert!(index.contains("\"httpDataSetReader\": {\"url\": \"tri\"}"));
+        assert!(index.contains("\"httpDataSetReader\": {\"url\": \"tri_1\"}"));
+        assert!(index.contains(
+            "\"mapper\": {\"colorByArrayName\": \"pressure\", \"colorMode\": 1, \
+             \"scalarMode\": 3, \"scalarVisibility\": true}"
+        ));
+        assert!(index.contains("\"tableRange\": [1, 4]"));
+        assert!(index.contains(
+            "\"property\": {\"representation\": 2, \"edgeVisibility\": 1, \"opacity\": 0.5}"
+        ));
+        assert!(index.contains("\"actor\": {\"visibility\": false}"));
+
+        let tri = String::from_utf8(entries["tri/index.json"].clone()).unwrap();
+        assert!(tri.contains("\"vtkClass\": \"vtkPolyData\""));
+        assert!(tri.contains("\"registration\": \"setScalars\""));
+        let image = String::from_utf8(entries["tri_1/index.json"].clone()).unwrap();
+        assert!(image.contains("\"extent\": [0, 1, 0, 1, 0, 0]"));
+        assert!(image.contains("\"origin\": [1, 2, 3]"));
+
+        // Polygons are stored in the legacy layout.
+        let polys = [3u32, 0, 1, 2];
+        let polys: Vec<u8> = polys.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let polys_id = format!("{:08x}{:x}", crc32(&polys), polys.len());
+        assert!(tri.contains(&polys_id));
+        assert_eq!(entries[&format!("tri/data/{}", polys_id)], polys);
+
+        // 64-bit integers are converted to doubles.
+        let ids = 7.0f64.to_le_bytes();
+        let ids_id = format!("{:08x}{:x}", crc32(&ids), ids.len());
+        assert_eq!(entries[&format!("tri/data/{}", ids_id)], ids);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_data_set() {
+        let vtk = Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::new(),
+            file_path: None,
+            data: DataSet::UnstructuredGrid {
+                meta: None,
+                pieces: Vec::new(),
+            },
+        };
+        let scene = Scene::new().with_block("grid", vtk, DisplayHints::new());
+        assert!(matches!(
+            write(&scene, Vec::new()),
+            Err(Error::UnsupportedDataSet("UnstructuredGrid"))
+        ));
+    }
+
+    #[test]
+    fn color_range_skips_blanked() {
+        let mut piece = ImageDataPiece {
+            extent: Extent::Dims([3, 1, 1]),
+            data: Attributes {
+                point: vec![Attribute::scalars("t", 1).with_data(vec![1.0f32, 5.0, 9.0])],
+                ..Attributes::new()
+            },
+        };
+        piece.set_point_blanked(2, true);
+        let piece = DataSetPiece::ImageData {
+            origin: [0.0; 3],
+            spacing: [1.0; 3],
+            piece,
+        };
+        assert_eq!(piece.color_range("t"), Some((true, [1.0, 5.0])));
+    }
+}
@@ -0,0 +1,429 @@
+//! Reading and writing multiblock data set (`.vtm`) files.
+//!
+//! A `.vtm` file describes a `vtkMultiBlockDataSet`, which is a tree of named blocks. Each block
+//! is either a group of nested blocks or a single data set. Data sets are not stored in the `.vtm`
+//! file itself, but in separate VTK files referenced by it.
+//!
+//! A [`MultiBlockDataSet`] can be imported lazily, where only the `.vtm` file itself is read, or
+//! eagerly, where all referenced data sets are loaded immediately. The data sets can also be
+//! loaded one at a time using [`MultiBlockDataSet::into_vtk_iter`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use vtkio::model::*;
+//! use vtkio::vtm::*;
+//!
+//! let fluid = Vtk::import("assets/hexahedron.vtu").expect("Failed to load data set");
+//! let multi_block = MultiBlockDataSet {
+//!     blocks: vec![Block::MultiBlock {
+//!         name: Some("Fluid".to_string()),
+//!         blocks: vec![Block::DataSet(BlockDataSet::inline("Cells", fluid))],
+//!     }],
+//! };
+//! multi_block.export("output/sim.vtm").expect("Failed to write multiblock data set");
+//!
+//! let multi_block = MultiBlockDataSet::import_loaded("output/sim.vtm")
+//!     .expect("Failed to load multiblock data set");
+//! assert_eq!(multi_block.data_sets().len(), 1);
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::model::Vtk;
+use crate::pvd::{data_set_extension, escape};
+use crate::xml;
+use crate::Error;
+
+/// A data set leaf of a multiblock data set.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct BlockDataSet {
+    /// The name of this block, if any.
+    pub name: Option<String>,
+    /// The path to the referenced file, if any.
+    ///
+    /// Relative paths in the `.vtm` file are resolved against the directory containing it.
+    /// Blocks without a file and without data are empty.
+    pub file: Option<PathBuf>,
+    /// The loaded or inline data set, if any.
+    pub data: Option<Vtk>,
+}
+
+impl BlockDataSet {
+    /// Constructs a named block holding the given data set.
+    pub fn inline(name: impl Into<String>, vtk: Vtk) -> BlockDataSet {
+        BlockDataSet {
+            name: Some(name.into()),
+            file: None,
+            data: Some(vtk),
+        }
+    }
+
+    /// Constructs a named block referencing the data set stored at the given path.
+    pub fn source(name: impl Into<String>, file: impl Into<PathBuf>) -> BlockDataSet {
+        BlockDataSet {
+            name: Some(name.into()),
+            file: Some(file.into()),
+            data: None,
+        }
+    }
+
+    /// Loads the referenced data set if it hasn't been loaded already.
+    ///
+    /// Returns `None` if this block is empty.
+    pub fn load(&mut self) -> Result<Option<&Vtk>, Error> {
+        if self.data.is_none() {
+            if let Some(file) = &self.file {
+                self.data = Some(Vtk::import(file)?);
+            }
+        }
+        Ok(self.data.as_ref())
+    }
+
+    /// Consumes this block and returns its data set, loading it if necessary.
+    ///
+    /// Returns `None` if this block is empty.
+    pub fn into_vtk(self) -> Result<Option<Vtk>, Error> {
+        match (self.data, self.file) {
+            (Some(vtk), _) => Ok(Some(vtk)),
+            (None, Some(file)) => Vtk::import(file).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// A single block of a multiblock data set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    /// A group of nested blocks.
+    MultiBlock {
+        name: Option<String>,
+        blocks: Vec<Block>,
+    },
+    /// A single data set.
+    DataSet(BlockDataSet),
+}
+
+impl Block {
+    /// The name of this block, if any.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Block::MultiBlock { name, .. } => name.as_deref(),
+            Block::DataSet(data_set) => data_set.name.as_deref(),
+        }
+    }
+}
+
+/// The contents of a `.vtm` multiblock data set file.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct MultiBlockDataSet {
+    /// Top level blocks in the order they are listed in the file.
+    pub blocks: Vec<Block>,
+}
+
+impl MultiBlockDataSet {
+    /// Reads the given `.vtm` file without loading any of the referenced data sets.
+    ///
+    /// Data sets can be loaded later using [`BlockDataSet::load`] or
+    /// [`MultiBlockDataSet::load_all`].
+    pub fn import(file_path: impl AsRef<Path>) -> Result<MultiBlockDataSet, Error> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let f = std::io::BufReader::new(std::fs::File::open(file_path)?);
+        let mut reader = quick_xml::Reader::from_reader(f);
+        reader.expand_empty_elements(true).trim_text(true);
+        let vtm = VtmFile::deserialize(&mut quick_xml::de::Deserializer::new(reader))
+            .map_err(xml::Error::from)?;
+        Ok(MultiBlockDataSet {
+            blocks: into_blocks(vtm.multi_block.children, dir),
+        })
+    }
+
+    /// Reads the given `.vtm` file and loads all of the referenced data sets.
+    pub fn import_loaded(file_path: impl AsRef<Path>) -> Result<MultiBlockDataSet, Error> {
+        let mut multi_block = MultiBlockDataSet::import(file_path)?;
+        multi_block.load_all()?;
+        Ok(multi_block)
+    }
+
+    /// Loads all data sets that haven't been loaded yet.
+    pub fn load_all(&mut self) -> Result<(), Error> {
+        fn load_blocks(blocks: &mut [Block]) -> Result<(), Error> {
+            for block in blocks.iter_mut() {
+                match block {
+                    Block::MultiBlock { blocks, .. } => load_blocks(blocks)?,
+                    Block::DataSet(data_set) => {
+                        data_set.load()?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        load_blocks(&mut self.blocks)
+    }
+
+    /// Returns all data set leaves in depth first order.
+    pub fn data_sets(&self) -> Vec<&BlockDataSet> {
+        fn collect<'a>(blocks: &'a [Block], out: &mut Vec<&'a BlockDataSet>) {
+            for block in blocks {
+                match block {
+                    Block::MultiBlock { blocks, .. } => collect(blocks, out),
+                    Block::DataSet(data_set) => out.push(data_set),
+                }
+            }
+        }
+        let mut data_sets = Vec::new();
+        collect(&self.blocks, &mut data_sets);
+        data_sets
+    }
+
+    /// Consumes this multiblock data set and returns an iterator loading its data set leaves one
+    /// at a time in depth first order.
+    ///
+    /// Empty blocks are skipped. A block that fails to load yields an error without ending the
+    /// iteration, so the remaining blocks can still be processed.
+    pub fn into_vtk_iter(self) -> impl Iterator<Item = Result<Vtk, Error>> {
+        fn collect(blocks: Vec<Block>, out: &mut Vec<BlockDataSet>) {
+            for block in blocks {
+                match block {
+                    Block::MultiBlock { blocks, .. } => collect(blocks, out),
+                    Block::DataSet(data_set) => out.push(data_set),
+                }
+            }
+        }
+        let mut data_sets = Vec::new();
+        collect(self.blocks, &mut data_sets);
+        data_sets
+            .into_iter()
+            .filter_map(|data_set| data_set.into_vtk().transpose())
+    }
+
+    /// Writes this multiblock data set to the given `.vtm` file.
+    ///
+    /// Blocks holding data are written in XML format to `<stem>/<stem>_<n>.<ext>` next to the
+    /// `.vtm` file, where `n` is the index of the block among all data set leaves and the
+    /// extension is determined by the type of data set. Blocks without data only reference their
+    /// file, which is written relative to the `.vtm` file where possible.
+    pub fn export(&self, file_path: impl AsRef<Path>) -> Result<(), Error> {
+        let file_path = file_path.as_ref();
+        let dir = file_path.parent().unwrap_or_else(|| Path::new(""));
+        let stem = file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or(Error::UnknownFileExtension(None))?;
+
+        let mut writer = VtmWriter {
+            dir,
+            stem,
+            num_data_sets: 0,
+            out: String::new(),
+        };
+        writer.out.push_str("<?xml version=\"1.0\"?>\n");
+        writer.out.push_str(
+            "<VTKFile type=\"vtkMultiBlockDataSet\" version=\"1.0\" byte_order=\"LittleEndian\">\n",
+        );
+        writer.out.push_str("  <vtkMultiBlockDataSet>\n");
+        writer.write_blocks(&self.blocks, 2)?;
+        writer.out.push_str("  </vtkMultiBlockDataSet>\n");
+        writer.out.push_str("</VTKFile>\n");
+        std::fs::write(file_path, writer.out)?;
+        Ok(())
+    }
+}
+
+/// State used while writing a `.vtm` file.
+struct VtmWriter<'a> {
+    dir: &'a Path,
+    stem: &'a str,
+    num_data_sets: usize,
+    out: String,
+}
+
+impl VtmWriter<'_> {
+    fn write_blocks(&mut self, blocks: &[Block], depth: usize) -> Result<(), Error> {
+        let indent = "  ".repeat(depth);
+        for (index, block) in blocks.iter().enumerate() {
+            match block {
+                Block::MultiBlock { name, blocks } => {
+                    self.out
+                        .push_str(&format!("{}<Block index=\"{}\"", indent, index));
+                    self.push_name(name);
+                    self.out.push_str(">\n");
+                    self.write_blocks(blocks, depth + 1)?;
+                    self.out.push_str(&format!("{}</Block>\n", indent));
+                }
+                Block::DataSet(data_set) => {
+                    self.out
+                        .push_str(&format!("{}<DataSet index=\"{}\"", indent, index));
+                    self.push_name(&data_set.name);
+                    if let Some(file) = self.data_set_file(data_set)? {
+                        self.out.push_str(&format!(" file=\"{}\"", escape(&file)));
+                    }
+                    self.out.push_str("/>\n");
+                    self.num_data_sets += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_name(&mut self, name: &Option<String>) {
+        if let Some(name) = name {
+            self.out.push_str(&format!(" name=\"{}\"", escape(name)));
+        }
+    }
+
+    /// Writes the data of the given block if necessary and returns the file to reference.
+    fn data_set_file(&self, data_set: &BlockDataSet) -> Result<Option<String>, Error> {
+        if let Some(vtk) = &data_set.data {
+            let file = format!(
+                "{}/{}_{}.{}",
+                self.stem,
+                self.stem,
+                self.num_data_sets,
+                data_set_extension(&vtk.data)?
+            );
+            let path = self.dir.join(&file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            vtk.clone().export(&path)?;
+            Ok(Some(file))
+        } else {
+            Ok(data_set.file.as_ref().map(|file| {
+                file.strip_prefix(self.dir)
+                    .unwrap_or(file)
+                    .to_string_lossy()
+                    .into_owned()
+            }))
+        }
+    }
+}
+
+fn into_blocks(nodes: Vec<VtmNode>, dir: &Path) -> Vec<Block> {
+    nodes
+        .into_iter()
+        .map(|node| match node {
+            VtmNode::Block(block) => Block::MultiBlock {
+                name: block.name,
+                blocks: into_blocks(block.children, dir),
+            },
+            VtmNode::DataSet(data_set) => Block::DataSet(BlockDataSet {
+                name: data_set.name,
+                file: data_set
+                    .file
+                    .filter(|file| !file.is_empty())
+                    .map(|file| dir.join(file)),
+                data: None,
+            }),
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct VtmFile {
+    #[serde(rename = "vtkMultiBlockDataSet")]
+    multi_block: VtmBlock,
+}
+
+#[derive(Debug, Deserialize)]
+struct VtmBlock {
+    name: Option<String>,
+    #[serde(rename = "$value", default)]
+    children: Vec<VtmNode>,
+}
+
+#[derive(Debug, Deserialize)]
+enum VtmNode {
+    Block(VtmBlock),
+    DataSet(VtmDataSet),
+}
+
+#[derive(Debug, Deserialize)]
+struct VtmDataSet {
+    name: Option<String>,
+    file: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_blocks_round_trip() -> Result<(), Error> {
+        let hexahedron = Vtk::import("assets/hexahedron.vtu")?;
+        let point = Vtk::import("assets/point.vtp")?;
+        let multi_block = MultiBlockDataSet {
+            blocks: vec![
+                Block::MultiBlock {
+                    name: Some("Solid & Fluid".to_string()),
+                    blocks: vec![
+                        Block::DataSet(BlockDataSet::inline("hexahedron", hexahedron.clone())),
+                        Block::MultiBlock {
+                            name: None,
+                            blocks: vec![Block::DataSet(BlockDataSet::default())],
+                        },
+                    ],
+                },
+                Block::DataSet(BlockDataSet::inline("point", point.clone())),
+            ],
+        };
+
+        let dir = std::env::temp_dir().join("vtkio_vtm_nested_blocks_round_trip");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("blocks.vtm");
+        multi_block.export(&path)?;
+
+        let mut result = MultiBlockDataSet::import(&path)?;
+        assert_eq!(result.blocks.len(), 2);
+        assert_eq!(result.blocks[0].name(), Some("Solid & Fluid"));
+        assert_eq!(result.blocks[1].name(), Some("point"));
+        let files: Vec<_> = result.data_sets().iter().map(|d| d.file.clone()).collect();
+        assert_eq!(
+            files,
+            vec![
+                Some(dir.join("blocks/blocks_0.vtu")),
+                None,
+                Some(dir.join("blocks/blocks_2.vtp")),
+            ]
+        );
+
+        // Empty blocks are skipped when iterating.
+        let vtks = result
+            .clone()
+            .into_vtk_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let data: Vec<_> = vtks.into_iter().map(|vtk| vtk.data).collect();
+        assert_eq!(data, vec![hexahedron.data.clone(), point.data.clone()]);
+
+        result.load_all()?;
+        let data: Vec<_> = result
+            .data_sets()
+            .iter()
+            .map(|d| d.data.as_ref().map(|vtk| vtk.data.clone()))
+            .collect();
+        assert_eq!(data, vec![Some(hexahedron.data), None, Some(point.data)]);
+
+        // Loaded blocks are written again, while references are kept relative.
+        if let Block::DataSet(data_set) = &mut result.blocks[1] {
+            data_set.data = None;
+        }
+        let copy = dir.join("copy.vtm");
+        result.export(&copy)?;
+        let copy = MultiBlockDataSet::import(&copy)?;
+        let files: Vec<_> = copy.data_sets().iter().map(|d| d.file.clone()).collect();
+        assert_eq!(
+            files,
+            vec![
+                Some(dir.join("copy/copy_0.vtu")),
+                None,
+                Some(dir.join("blocks/blocks_2.vtp")),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -1,22 +1,893 @@
+use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
 use std::fmt::Arguments;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
 
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
-use num_traits::ToPrimitive;
+use num_traits::Float;
 
 use crate::model::ByteOrder as ByteOrderTag;
 use crate::model::*;
 
+use self::write_vtk_impl::error::{DataSetError, DataSetPart, EntryPart};
+use self::write_vtk_impl::{narrow_indices, BinaryScalar, WriteVtkImpl};
+
 /// A `Write` wrapper for writing in ASCII format.
+#[derive(Clone, Debug, Default)]
 pub struct AsciiWriter<W: std::fmt::Write>(pub W);
 
+impl<W: std::fmt::Write> AsciiWriter<W> {
+    /// Writes a line of space separated floats in positional notation.
+    fn write_floats<T>(&mut self, values: &[T]) -> std::fmt::Result
+    where
+        T: Float + ryu::Float + std::fmt::Display + std::fmt::LowerExp,
+    {
+        for (i, &value) in values.iter().enumerate() {
+            if i > 0 {
+                self.0.write_char(' ')?;
+            }
+            FloatFormat::Decimal.write(&mut self.0, value)?;
+        }
+        writeln!(&mut self.0)
+    }
+}
+
 /// A `Write` wrapper for writing in binary format.
+///
+/// Data is encoded straight into the wrapped writer, so writing to a file with
+/// [`create`](BinaryWriter::create) never holds the whole output in memory, unlike writing into a
+/// `Vec<u8>`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use vtkio::model::*;
+/// use vtkio::writer::{BinaryWriter, WriteVtk};
+///
+/// let vtk = Vtk {
+///     version: Version::new((4, 2)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Points"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![0.0f32, 0.0, 0.0].into(),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let mut writer = BinaryWriter::create("points.vtk").expect("Failed to create file");
+/// writer.write_vtk(&vtk).expect("Failed to write file");
+/// writer.finish().expect("Failed to flush file");
+/// ```
+#[derive(Clone, Debug, Default)]
 pub struct BinaryWriter<W: std::io::Write>(pub W);
 
+impl BinaryWriter<BufWriter<File>> {
+    /// Creates a writer streaming binary data into a buffered file at the given path.
+    ///
+    /// Call [`finish`](BinaryWriter::finish) after writing to flush the buffer, since errors
+    /// are discarded when a `BufWriter` is dropped.
+    pub fn create(file_path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(BinaryWriter(BufWriter::new(File::create(file_path)?)))
+    }
+}
+
+impl<W: std::io::Write> BinaryWriter<W> {
+    /// Flushes any buffered data and returns the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.0.flush()?;
+        Ok(self.0)
+    }
+}
+
+/// An adapter for writing ASCII files to any [`io::Write`](std::io::Write)r.
+///
+/// [`AsciiWriter`] and [`FormattedAsciiWriter`] write to a [`fmt::Write`](std::fmt::Write)r. This
+/// adapter allows them to write directly to files, sockets or standard output without building
+/// the whole file in memory first. Binary files can be written to any `io::Write`r with
+/// [`BinaryWriter`].
+///
+/// Since `fmt::Write` cannot report the cause of a failure, the first IO error encountered is
+/// kept and can be retrieved with [`take_error`](IoWriter::take_error).
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::BufWriter;
+/// use vtkio::model::*;
+/// use vtkio::writer::{AsciiWriter, IoWriter, WriteVtk};
+///
+/// let vtk = Vtk {
+///     version: Version::new((4, 2)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Points"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![0.0f32, 0.0, 0.0].into(),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let stdout = std::io::stdout();
+/// let mut writer = AsciiWriter(IoWriter::new(BufWriter::new(stdout.lock())));
+/// if writer.write_vtk(vtk).is_err() {
+///     let err = writer.0.take_error();
+///     panic!("Failed to write file: {:?}", err);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct IoWriter<W: std::io::Write> {
+    /// The underlying writer.
+    pub writer: W,
+    error: Option<std::io::Error>,
+}
+
+impl<W: std::io::Write> IoWriter<W> {
+    /// Wraps the given writer.
+    pub fn new(writer: W) -> Self {
+        IoWriter {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Returns the first IO error encountered while writing, if any, and clears it.
+    pub fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: std::io::Write> std::fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.error.get_or_insert(err);
+            std::fmt::Error
+        })
+    }
+}
+
+/// A `Write` wrapper for writing in ASCII format with configurable number formatting.
+///
+/// # Examples
+///
+/// Writing ASCII files suitable as golden outputs in tests:
+///
+/// ```
+/// use vtkio::model::*;
+/// use vtkio::writer::{FloatFormat, FormattedAsciiWriter, WriteVtk};
+///
+/// let vtk = Vtk {
+///     version: Version::new((2, 0)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Points"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![1e-30_f64, 0.1, 1e300].into(),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let mut writer =
+///     FormattedAsciiWriter::new(String::new()).with_float_format(FloatFormat::RoundTrip);
+/// writer.write_vtk(vtk.clone()).expect("Failed to write file");
+/// assert!(writer.writer.contains("POINTS 1 double\n1e-30 0.1 1e300\n"));
+///
+/// let output = Vtk::parse_legacy_be(writer.writer.as_bytes()).expect("Failed to parse file");
+/// assert_eq!(output, vtk);
+/// ```
+#[derive(Clone, Default)]
+pub struct FormattedAsciiWriter<W: std::fmt::Write> {
+    /// The underlying writer.
+    pub writer: W,
+    /// The format used for floating point numbers.
+    pub float_format: FloatFormat,
+    /// How NaN and infinite values are written.
+    pub non_finite: NonFinite,
+    /// How long lines of data values are broken up.
+    pub line_wrap: LineWrap,
+    array_formats: HashMap<String, NumberFormat>,
+}
+
+/// A function formatting a single floating point value in an ASCII file.
+pub type NumberFormat =
+    Arc<dyn Fn(&mut dyn std::fmt::Write, f64) -> std::fmt::Result + Send + Sync>;
+
+impl<W: std::fmt::Write> FormattedAsciiWriter<W> {
+    /// Creates a new writer using the default number formatting of [`AsciiWriter`].
+    pub fn new(writer: W) -> Self {
+        FormattedAsciiWriter {
+            writer,
+            float_format: FloatFormat::default(),
+            non_finite: NonFinite::default(),
+            line_wrap: LineWrap::default(),
+            array_formats: HashMap::new(),
+        }
+    }
+
+    /// Sets the format used for floating point numbers.
+    pub fn with_float_format(self, float_format: FloatFormat) -> Self {
+        FormattedAsciiWriter {
+            float_format,
+            ..self
+        }
+    }
+
+    /// Sets how NaN and infinite values are written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::writer::{FormattedAsciiWriter, NonFinite, WriteVtk};
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((2, 0)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Pressure"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0_f32, 0.0, 0.0, 1.0, 0.0, 0.0].into(),
+    ///         data: Attributes {
+    ///             point: vec![Attribute::scalars("p", 1).with_data(vec![f32::NAN, 2.5])],
+    ///             cell: vec![],
+    ///             field: vec![],
+    ///         },
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let mut writer =
+    ///     FormattedAsciiWriter::new(String::new()).with_non_finite(NonFinite::Replace(-9999.0));
+    /// writer.write_vtk(vtk).expect("Failed to write file");
+    /// assert!(writer.writer.contains("LOOKUP_TABLE default\n-9999 2.5\n"));
+    /// ```
+    pub fn with_non_finite(self, non_finite: NonFinite) -> Self {
+        FormattedAsciiWriter { non_finite, ..self }
+    }
+
+    /// Sets how long lines of data values are broken up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::writer::{FormattedAsciiWriter, LineWrap, WriteVtk};
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((2, 0)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Points"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.5_f32; 12].into(),
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let mut writer = FormattedAsciiWriter::new(String::new()).with_line_wrap(LineWrap::Values(9));
+    /// writer.write_vtk(vtk).expect("Failed to write file");
+    /// assert!(writer.writer.contains("POINTS 4 float\n0.5 0.5 0.5 0.5 0.5 0.5 0.5 0.5 0.5\n0.5 0.5 0.5\n"));
+    /// ```
+    pub fn with_line_wrap(self, line_wrap: LineWrap) -> Self {
+        FormattedAsciiWriter { line_wrap, ..self }
+    }
+
+    /// Formats each value of the floating point array with the given name using `format`,
+    /// overriding the [`FloatFormat`] for that array.
+    ///
+    /// Attribute and field arrays are identified by their name, while points and coordinates
+    /// are named after their keyword: `POINTS`, `X_COORDINATES`, `Y_COORDINATES` and
+    /// `Z_COORDINATES`. Integer arrays are always written as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::writer::{FormattedAsciiWriter, WriteVtk};
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((2, 0)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Residuals"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.5_f32, 1.0, 0.0].into(),
+    ///         data: Attributes {
+    ///             point: vec![Attribute::scalars("residual", 1).with_data(vec![0.00012_f64])],
+    ///             cell: vec![],
+    ///             field: vec![],
+    ///         },
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let mut writer = FormattedAsciiWriter::new(String::new())
+    ///     .with_array_format("POINTS", |f, x| write!(f, "{:.6}", x))
+    ///     .with_array_format("residual", |f, x| write!(f, "{:.3e}", x));
+    /// writer.write_vtk(vtk).expect("Failed to write file");
+    /// assert!(writer.writer.contains("0.500000 1.000000 0.000000\n"));
+    /// assert!(writer.writer.contains("1.200e-4\n"));
+    /// ```
+    pub fn with_array_format(
+        mut self,
+        name: impl Into<String>,
+        format: impl Fn(&mut dyn std::fmt::Write, f64) -> std::fmt::Result + Send + Sync + 'static,
+    ) -> Self {
+        self.array_formats.insert(name.into(), Arc::new(format));
+        self
+    }
+}
+
+impl<W: std::fmt::Write + std::fmt::Debug> std::fmt::Debug for FormattedAsciiWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("FormattedAsciiWriter")
+            .field("writer", &self.writer)
+            .field("float_format", &self.float_format)
+            .field("non_finite", &self.non_finite)
+            .field("line_wrap", &self.line_wrap)
+            .field("array_formats", &self.array_formats.keys())
+            .finish()
+    }
+}
+
+/// How floating point numbers are formatted in ASCII files.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FloatFormat {
+    /// Positional notation without an exponent, as used by [`AsciiWriter`]. This is the default.
+    #[default]
+    Decimal,
+    /// The shortest representation, either positional or in scientific notation, that parses
+    /// back to an identical value.
+    ///
+    /// Non-finite values are written as `nan`, `inf` and `-inf` like VTK does, so files written
+    /// in this format can be read back bit for bit (up to the payload of NaNs).
+    RoundTrip,
+    /// The given number of significant digits, like the `%g` format of `printf` used by VTK.
+    ///
+    /// Scientific notation is used for exponents below -4 or at least the number of digits, and
+    /// trailing zeros are removed. Non-finite values are written as in `RoundTrip`. Note that too
+    /// few digits can merge distinct points, 9 digits suffice for `f32` and 17 for `f64` values
+    /// to be read back exactly.
+    Significant(u8),
+}
+
+impl FloatFormat {
+    fn write<T: Float + ryu::Float + std::fmt::Display + std::fmt::LowerExp>(
+        self,
+        f: &mut impl std::fmt::Write,
+        value: T,
+    ) -> std::fmt::Result {
+        match self {
+            FloatFormat::Decimal if !value.is_finite() => write!(f, "{}", value),
+            FloatFormat::Decimal => ShortestDigits::new(value).write_decimal(f),
+            _ if value.is_nan() => write!(f, "nan"),
+            _ if value == T::infinity() => write!(f, "inf"),
+            _ if value == T::neg_infinity() => write!(f, "-inf"),
+            FloatFormat::RoundTrip => {
+                // Both notations use the fewest digits needed to parse back identically.
+                let digits = ShortestDigits::new(value);
+                if digits.scientific_len() < digits.decimal_len() {
+                    digits.write_scientific(f)
+                } else {
+                    digits.write_decimal(f)
+                }
+            }
+            FloatFormat::Significant(digits) => {
+                let digits = i32::from(digits.max(1));
+                let trim = |s: &str| {
+                    if s.contains('.') {
+                        s.trim_end_matches('0').trim_end_matches('.').to_string()
+                    } else {
+                        s.to_string()
+                    }
+                };
+                // Rounding to the number of digits first gives the exponent of the result.
+                let scientific = format!("{:.*e}", digits as usize - 1, value);
+                let (mantissa, exp) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+                let exp: i32 = exp.parse().unwrap_or(0);
+                if exp < -4 || exp >= digits {
+                    write!(f, "{}e{}", trim(mantissa), exp)
+                } else {
+                    let decimal = format!("{:.*}", (digits - 1 - exp) as usize, value);
+                    write!(f, "{}", trim(&decimal))
+                }
+            }
+        }
+    }
+}
+
+/// How NaN and infinite values are written in ASCII files.
+///
+/// Such values are always read back, in any capitalization and including `infinity`, but some
+/// tools reading VTK files can't handle them.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum NonFinite {
+    /// Written as is in the notation of the [`FloatFormat`]. This is the default.
+    #[default]
+    AsIs,
+    /// Replaced by the given sentinel value, which is formatted like any other value.
+    ///
+    /// The sentinel is converted to the type of the array, so it should be representable as
+    /// an `f32` when writing single precision data.
+    Replace(f64),
+}
+
+impl NonFinite {
+    fn apply<T: Float>(self, value: T) -> T {
+        match self {
+            NonFinite::Replace(sentinel) if !value.is_finite() => {
+                T::from(sentinel).unwrap_or(value)
+            }
+            _ => value,
+        }
+    }
+}
+
+/// The shortest decimal digits that parse back to a finite float, as computed by `ryu`.
+///
+/// The value is `±0.d₁d₂…dₙ × 10^(exp + 1)`, i.e. `exp` is the exponent of the first digit.
+struct ShortestDigits {
+    negative: bool,
+    digits: [u8; 20],
+    len: usize,
+    exp: i32,
+}
+
+impl ShortestDigits {
+    fn new<T: ryu::Float>(value: T) -> Self {
+        let mut buf = ryu::Buffer::new();
+        let s = buf.format_finite(value);
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (mantissa, exp) = s.split_once('e').unwrap_or((s, "0"));
+        let exp: i32 = exp.parse().unwrap_or(0);
+        let int_len = mantissa.find('.').unwrap_or(mantissa.len()) as i32;
+        let mut result = ShortestDigits {
+            negative,
+            digits: [0; 20],
+            len: 0,
+            exp: 0,
+        };
+        let mut leading_zeros = 0;
+        for &d in mantissa.as_bytes().iter().filter(|&&d| d != b'.') {
+            if d == b'0' && result.len == 0 {
+                leading_zeros += 1;
+            } else {
+                result.digits[result.len] = d;
+                result.len += 1;
+            }
+        }
+        while result.len > 0 && result.digits[result.len - 1] == b'0' {
+            result.len -= 1;
+        }
+        if result.len == 0 {
+            result.digits[0] = b'0';
+            result.len = 1;
+        } else {
+            result.exp = int_len - 1 - leading_zeros + exp;
+        }
+        result
+    }
+
+    fn digits(&self) -> &str {
+        // Only ASCII digits are stored.
+        std::str::from_utf8(&self.digits[..self.len]).unwrap()
+    }
+
+    /// The length of the positional notation written by `write_decimal`.
+    fn decimal_len(&self) -> usize {
+        let len = self.len as i32;
+        let body = if self.exp < 0 {
+            1 - self.exp + len
+        } else if len > self.exp + 1 {
+            len + 1
+        } else {
+            self.exp + 1
+        };
+        self.negative as usize + body as usize
+    }
+
+    /// The length of the scientific notation written by `write_scientific`.
+    fn scientific_len(&self) -> usize {
+        let point = if self.len > 1 { 1 } else { 0 };
+        let exp_len = if self.exp < 0 { 1 } else { 0 } + self.exp.unsigned_abs().to_string().len();
+        self.negative as usize + self.len + point + 1 + exp_len
+    }
+
+    /// Writes the digits in positional notation, which matches `Display` for floats.
+    fn write_decimal(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        if self.negative {
+            f.write_char('-')?;
+        }
+        let digits = self.digits();
+        if self.exp < 0 {
+            f.write_str("0.")?;
+            for _ in 0..-self.exp - 1 {
+                f.write_char('0')?;
+            }
+            f.write_str(digits)
+        } else {
+            let int_len = self.exp as usize + 1;
+            if digits.len() > int_len {
+                f.write_str(&digits[..int_len])?;
+                f.write_char('.')?;
+                f.write_str(&digits[int_len..])
+            } else {
+                f.write_str(digits)?;
+                for _ in digits.len()..int_len {
+                    f.write_char('0')?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the digits in scientific notation, which matches the `LowerExp` format for floats.
+    fn write_scientific(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        if self.negative {
+            f.write_char('-')?;
+        }
+        let digits = self.digits();
+        f.write_str(&digits[..1])?;
+        if digits.len() > 1 {
+            f.write_char('.')?;
+            f.write_str(&digits[1..])?;
+        }
+        write!(f, "e{}", self.exp)
+    }
+}
+
+/// How long lines of data values are broken up in ASCII files.
+///
+/// Values are separated by spaces and the line is broken before the value that would exceed the
+/// limit. A single value longer than the character limit is still written on its own line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LineWrap {
+    /// Each data block is written on a single line. This is the default.
+    #[default]
+    None,
+    /// At most the given number of values per line. VTK itself writes 9 values per line.
+    Values(usize),
+    /// At most the given number of characters per line.
+    Chars(usize),
+}
+
+impl<W: std::fmt::Write> FormattedAsciiWriter<W> {
+    /// Writes the given values followed by a new line, breaking lines according to `line_wrap`.
+    fn write_values<T>(
+        &mut self,
+        values: &[T],
+        mut format: impl FnMut(&mut dyn std::fmt::Write, &T) -> std::fmt::Result,
+    ) -> std::fmt::Result {
+        let mut value = String::new();
+        let mut line_values = 0;
+        let mut line_len = 0;
+        for (i, elem) in values.iter().enumerate() {
+            value.clear();
+            format(&mut value, elem)?;
+            if i > 0 {
+                let wrap = match self.line_wrap {
+                    LineWrap::None => false,
+                    LineWrap::Values(n) => line_values >= n,
+                    LineWrap::Chars(n) => line_len + 1 + value.len() > n,
+                };
+                if wrap {
+                    writeln!(&mut self.writer)?;
+                    line_values = 0;
+                    line_len = 0;
+                } else {
+                    write!(&mut self.writer, " ")?;
+                    line_len += 1;
+                }
+            }
+            self.writer.write_str(&value)?;
+            line_values += 1;
+            line_len += value.len();
+        }
+        writeln!(&mut self.writer)
+    }
+}
+
+/// The stage of the file a [`StreamWriter`] is writing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum StreamStage {
+    /// Nothing has been written yet.
+    Start,
+    /// The header was written and points are expected.
+    Points,
+    /// All points were written and cells are expected.
+    Cells,
+    /// All cells were written, attributes may follow.
+    PointData,
+    /// At least one cell attribute was written, so no more point attributes are accepted.
+    CellData,
+}
+
+/// A writer streaming an unstructured grid into a binary legacy file piece by piece.
+///
+/// Unlike [`WriteVtk`], this writer doesn't need the whole data set in memory at once. Points and
+/// cells can be written in chunks as they are computed, followed by whole point and cell
+/// attributes. Only the cell types are kept in memory until all cells are written.
+///
+/// Entries must be written in the order they appear in the file: first all points with
+/// [`write_points`](StreamWriter::write_points), then all cells with
+/// [`write_cells_chunk`](StreamWriter::write_cells_chunk), then any point attributes followed by
+/// any cell attributes. Writing entries out of order produces an [`Error::OutOfOrder`] error.
+///
+/// Files are written in big endian with version 4.2, so vertex indices must fit into 32 bits.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::model::*;
+/// use vtkio::writer::StreamWriter;
+///
+/// let mut writer = StreamWriter::new(Vec::new());
+/// writer.begin_unstructured_grid("Triangles", 4, 2, 6).expect("Failed to write header");
+/// writer.write_points(&vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0].into()).unwrap();
+/// writer.write_points(&vec![0.0f32, 1.0, 0.0, 1.0, 1.0, 0.0].into()).unwrap();
+/// for cell in [[0, 1, 2], [1, 3, 2]].iter() {
+///     let chunk = Cells {
+///         cell_verts: VertexNumbers::XML {
+///             connectivity: cell.to_vec(),
+///             offsets: vec![3],
+///         },
+///         types: vec![CellType::Triangle],
+///     };
+///     writer.write_cells_chunk(&chunk).unwrap();
+/// }
+/// let pressure = Attribute::scalars("pressure", 1).with_data(vec![1.0f32, 2.0, 3.0, 4.0]);
+/// writer.write_point_attribute(&pressure).unwrap();
+/// let bytes = writer.finish().expect("Failed to finish file");
+///
+/// let vtk = Vtk::parse_legacy_be(bytes.as_slice()).expect("Failed to parse file");
+/// let piece = UnstructuredGridPiece::from_data_set(vtk.data, None).unwrap();
+/// assert_eq!(piece.num_points(), 4);
+/// assert_eq!(piece.cells.types, vec![CellType::Triangle; 2]);
+/// assert_eq!(piece.data.point, vec![pressure]);
+/// ```
+#[derive(Debug)]
+pub struct StreamWriter<W: std::io::Write> {
+    writer: W,
+    stage: StreamStage,
+    num_points: usize,
+    num_cells: usize,
+    num_cell_verts: usize,
+    points_type: Option<ScalarType>,
+    /// Number of point coordinates written so far.
+    coords_written: usize,
+    /// Number of cell vertex indices written so far.
+    verts_written: usize,
+    cell_types: Vec<CellType>,
+    /// Whether the `POINT_DATA` or `CELL_DATA` header of the current stage was written.
+    data_header_written: bool,
+}
+
+impl<W: std::io::Write> StreamWriter<W> {
+    /// Wraps the given writer without writing anything yet.
+    pub fn new(writer: W) -> Self {
+        StreamWriter {
+            writer,
+            stage: StreamStage::Start,
+            num_points: 0,
+            num_cells: 0,
+            num_cell_verts: 0,
+            points_type: None,
+            coords_written: 0,
+            verts_written: 0,
+            cell_types: Vec::new(),
+            data_header_written: false,
+        }
+    }
+
+    /// Writes the file header of an unstructured grid with the given title.
+    ///
+    /// Legacy files store the sizes of arrays before their data, so the total number of points
+    /// and cells, as well as the total number of vertex indices in all cells, must be known
+    /// up front.
+    pub fn begin_unstructured_grid(
+        &mut self,
+        title: &str,
+        num_points: usize,
+        num_cells: usize,
+        num_cell_verts: usize,
+    ) -> Result<(), Error> {
+        self.expect_stage(StreamStage::Start)?;
+        write!(
+            self.writer,
+            "# vtk DataFile Version 4.2\n{}\nBINARY\n\nDATASET UNSTRUCTURED_GRID\n",
+            title
+        )?;
+        self.num_points = num_points;
+        self.num_cells = num_cells;
+        self.num_cell_verts = num_cell_verts;
+        self.cell_types.reserve(num_cells);
+        self.stage = StreamStage::Points;
+        Ok(())
+    }
+
+    /// Writes the next chunk of point coordinates.
+    ///
+    /// Chunks are given as flat arrays of coordinate triples, which must all have the same
+    /// scalar type. The points are complete once `num_points` points have been written.
+    pub fn write_points(&mut self, points: &IOBuffer) -> Result<(), Error> {
+        let size_err = Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Points(
+            EntryPart::Sizes,
+        )));
+        self.expect_stage(StreamStage::Points)?;
+        let scalar_type = points.scalar_type();
+        if self.points_type.is_some_and(|t| t != scalar_type)
+            || self.coords_written + points.len() > 3 * self.num_points
+        {
+            return Err(size_err);
+        }
+        self.write_points_header(scalar_type)?;
+        let writer = &mut self.writer;
+        match_buf!(points, v => BinaryScalar::write_slice::<BigEndian, _>(v, writer)?);
+        self.coords_written += points.len();
+        self.complete_points()
+    }
+
+    /// Writes the next chunk of cells.
+    ///
+    /// Vertex indices refer to all points written, not just the points of any one chunk. The
+    /// cells are complete once `num_cells` cells have been written, at which point the total
+    /// number of vertex indices must match the one given in
+    /// [`begin_unstructured_grid`](StreamWriter::begin_unstructured_grid).
+    pub fn write_cells_chunk(&mut self, cells: &Cells) -> Result<(), Error> {
+        let size_err = Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Cells(
+            EntryPart::Sizes,
+        )));
+        if self.stage == StreamStage::Points {
+            // Points may be omitted entirely if there are none.
+            self.complete_points()?;
+        }
+        self.expect_stage(StreamStage::Cells)?;
+        let num_cells = cells.cell_verts.num_cells();
+        let num_verts = cells.cell_verts.num_verts();
+        if num_cells != cells.types.len()
+            || self.cell_types.len() + num_cells > self.num_cells
+            || self.verts_written + num_verts > self.num_cell_verts
+        {
+            return Err(size_err);
+        }
+        if self.cell_types.is_empty() && num_cells > 0 {
+            writeln!(
+                self.writer,
+                "\nCELLS {} {}",
+                self.num_cells,
+                self.num_cells + self.num_cell_verts
+            )?;
+        }
+        let vertices = narrow_indices(&cells.cell_verts)?;
+        BinaryScalar::write_slice::<BigEndian, _>(&vertices, &mut self.writer)?;
+        self.cell_types.extend_from_slice(&cells.types);
+        self.verts_written += num_verts;
+        self.complete_cells()
+    }
+
+    /// Writes a whole point attribute.
+    ///
+    /// Point attributes must be written after all cells and before any cell attributes.
+    pub fn write_point_attribute(&mut self, attrib: &Attribute) -> Result<(), Error> {
+        self.complete_cells()?;
+        self.expect_stage(StreamStage::PointData)?;
+        self.write_attribute("POINT_DATA", self.num_points, attrib)
+    }
+
+    /// Writes a whole cell attribute.
+    ///
+    /// Cell attributes must be written after all cells and point attributes.
+    pub fn write_cell_attribute(&mut self, attrib: &Attribute) -> Result<(), Error> {
+        self.complete_cells()?;
+        if self.stage == StreamStage::PointData {
+            self.stage = StreamStage::CellData;
+            self.data_header_written = false;
+        }
+        self.expect_stage(StreamStage::CellData)?;
+        self.write_attribute("CELL_DATA", self.num_cells, attrib)
+    }
+
+    /// Checks that all points and cells were written, flushes and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.complete_cells()?;
+        match self.stage {
+            StreamStage::PointData | StreamStage::CellData => {}
+            StreamStage::Points => {
+                return Err(Error::DataSet(DataSetError::UnstructuredGrid(
+                    DataSetPart::Points(EntryPart::Sizes),
+                )))
+            }
+            StreamStage::Cells => {
+                return Err(Error::DataSet(DataSetError::UnstructuredGrid(
+                    DataSetPart::Cells(EntryPart::Sizes),
+                )))
+            }
+            StreamStage::Start => return Err(Error::OutOfOrder),
+        }
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn expect_stage(&self, stage: StreamStage) -> Result<(), Error> {
+        if self.stage == stage {
+            Ok(())
+        } else {
+            Err(Error::OutOfOrder)
+        }
+    }
+
+    fn write_points_header(&mut self, scalar_type: ScalarType) -> Result<(), Error> {
+        if self.points_type.is_none() {
+            writeln!(self.writer, "POINTS {} {}", self.num_points, scalar_type)?;
+            self.points_type = Some(scalar_type);
+        }
+        Ok(())
+    }
+
+    /// Moves on to the cells once all points were written.
+    fn complete_points(&mut self) -> Result<(), Error> {
+        if self.stage == StreamStage::Points && self.coords_written == 3 * self.num_points {
+            self.write_points_header(ScalarType::F32)?;
+            writeln!(self.writer)?;
+            self.stage = StreamStage::Cells;
+        }
+        Ok(())
+    }
+
+    /// Writes the cell types and moves on to the attributes once all cells were written.
+    fn complete_cells(&mut self) -> Result<(), Error> {
+        if self.stage == StreamStage::Points && self.num_points == 0 {
+            self.complete_points()?;
+        }
+        if self.stage != StreamStage::Cells || self.cell_types.len() < self.num_cells {
+            return Ok(());
+        }
+        if self.verts_written != self.num_cell_verts {
+            return Err(Error::DataSet(DataSetError::UnstructuredGrid(
+                DataSetPart::Cells(EntryPart::Sizes),
+            )));
+        }
+        if self.num_cells == 0 {
+            writeln!(self.writer, "\nCELLS 0 0")?;
+        }
+        writeln!(self.writer, "\n\nCELL_TYPES {}", self.num_cells)?;
+        let cell_types = std::mem::take(&mut self.cell_types);
+        BinaryWriter(&mut self.writer).write_cell_types::<BigEndian>(&cell_types)?;
+        self.stage = StreamStage::PointData;
+        Ok(())
+    }
+
+    /// Writes an attribute preceded by the `POINT_DATA` or `CELL_DATA` header if necessary.
+    fn write_attribute(&mut self, header: &str, n: usize, attrib: &Attribute) -> Result<(), Error> {
+        if !self.data_header_written {
+            writeln!(self.writer, "\n{} {}", header, n)?;
+            self.data_header_written = true;
+        }
+        writeln!(self.writer)?;
+        BinaryWriter(&mut self.writer).write_attrib::<BigEndian>(attrib)
+    }
+}
+
 mod write_vtk_impl {
+    use std::convert::TryFrom;
     use std::fmt::Display;
 
     use super::*;
-    use byteorder::WriteBytesExt;
 
     pub mod error {
         #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -59,6 +930,7 @@ mod write_vtk_impl {
             Field(EntryPart),
             FieldArray(EntryPart),
             UnrecognizedAttributeType,
+            Unknown,
         }
 
         impl std::fmt::Display for AttributeError {
@@ -75,6 +947,7 @@ mod write_vtk_impl {
                     Field(part) => write!(f, "Field: {}", part),
                     FieldArray(part) => write!(f, "Field array: {}", part),
                     UnrecognizedAttributeType => write!(f, "Unrecognized attribute type"),
+                    Unknown => write!(f, "Unknown attribute section"),
                 }
             }
         }
@@ -145,6 +1018,11 @@ mod write_vtk_impl {
             PieceDataMismatch,
             /// No piece data found for this data set.
             MissingPieceData,
+            /// Legacy files can't store structured data sets with more than one piece.
+            MultiplePieces,
+            /// Vertex indices don't fit into the 32-bit integers used by legacy files before
+            /// version 5.1 or requested with [`CellIdType::Int32`](crate::writer::CellIdType).
+            IndexOverflow,
         }
 
         impl std::fmt::Display for DataSetError {
@@ -162,11 +1040,14 @@ mod write_vtk_impl {
 
                     PieceDataMismatch => write!(f, "Piece data mismatch"),
                     MissingPieceData => write!(f, "Missing piece data"),
+                    MultiplePieces => write!(f, "Multiple pieces"),
+                    IndexOverflow => write!(f, "Vertex indices exceed 32 bits"),
                 }
             }
         }
 
         #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #[non_exhaustive]
         pub enum Error {
             PointDataHeader,
             CellDataHeader,
@@ -180,6 +1061,9 @@ mod write_vtk_impl {
             FormatError,
             /// Generic IO error originating from [`std::io::Error`].
             IOError(std::io::ErrorKind),
+            /// A [`StreamWriter`](crate::writer::StreamWriter) entry was written before the
+            /// preceding entries were complete.
+            OutOfOrder,
         }
 
         impl std::fmt::Display for Error {
@@ -193,6 +1077,7 @@ mod write_vtk_impl {
                     Error::NewLine => write!(f, "New line"),
                     Error::FormatError => write!(f, "Format error"),
                     Error::IOError(kind) => write!(f, "IO Error: {:?}", kind),
+                    Error::OutOfOrder => write!(f, "Entry written out of order"),
                 }
             }
         }
@@ -234,6 +1119,100 @@ mod write_vtk_impl {
 
     /// A typical result of a write operation.
     type Result = std::result::Result<(), Error>;
+    /// Converts vertex numbers to the 32-bit integers stored by legacy files before version 5.1.
+    pub(super) fn narrow_indices(
+        cell_verts: &VertexNumbers,
+    ) -> std::result::Result<Vec<u32>, Error> {
+        let narrow =
+            |v: u64| u32::try_from(v).map_err(|_| Error::DataSet(DataSetError::IndexOverflow));
+        match cell_verts {
+            VertexNumbers::Legacy { vertices, .. } => vertices.iter().map(|&v| narrow(v)).collect(),
+            VertexNumbers::XML {
+                connectivity,
+                offsets,
+            } => {
+                // Narrow while converting to avoid a second copy of the connectivity in memory.
+                let mut vertices = Vec::with_capacity(connectivity.len() + offsets.len());
+                let mut begin = 0;
+                for &end in offsets.iter() {
+                    vertices.push(narrow(end - begin)?);
+                    for &v in &connectivity[begin as usize..end as usize] {
+                        vertices.push(narrow(v)?);
+                    }
+                    begin = end;
+                }
+                Ok(vertices)
+            }
+        }
+    }
+
+    /// The size in bytes of the scratch buffer used to byte swap binary data.
+    ///
+    /// Swapping in chunks bounds the memory used while writing regardless of the size of the
+    /// written arrays.
+    const SWAP_BUFFER_SIZE: usize = 1 << 16;
+
+    /// Returns `true` if `BO` is the native byte order, in which case data can be written as is.
+    fn is_native<BO: ByteOrder>() -> bool {
+        let mut bytes = [0u8; 2];
+        BO::write_u16(&mut bytes, 1);
+        bytes == 1u16.to_ne_bytes()
+    }
+
+    /// A scalar that can be written to binary files.
+    pub trait BinaryScalar: Copy + bytemuck::Pod {
+        /// Converts `data` to bytes in the given byte order, written to `bytes`.
+        fn to_bytes<BO: ByteOrder>(data: &[Self], bytes: &mut [u8]);
+
+        /// Writes all of `data` in the given byte order.
+        ///
+        /// Data in native byte order is written with a single call to `write_all`, otherwise
+        /// it is byte swapped in chunks through a scratch buffer.
+        fn write_slice<BO: ByteOrder, W: std::io::Write>(
+            data: &[Self],
+            writer: &mut W,
+        ) -> std::io::Result<()> {
+            let size = std::mem::size_of::<Self>();
+            if size == 1 || is_native::<BO>() {
+                return writer.write_all(bytemuck::cast_slice(data));
+            }
+            let mut scratch = vec![0u8; SWAP_BUFFER_SIZE.min(std::mem::size_of_val(data))];
+            for chunk in data.chunks(SWAP_BUFFER_SIZE / size) {
+                let bytes = &mut scratch[..std::mem::size_of_val(chunk)];
+                Self::to_bytes::<BO>(chunk, bytes);
+                writer.write_all(bytes)?;
+            }
+            Ok(())
+        }
+    }
+
+    macro_rules! impl_binary_scalar {
+        ($t:ty, single_byte) => {
+            impl BinaryScalar for $t {
+                fn to_bytes<BO: ByteOrder>(data: &[Self], bytes: &mut [u8]) {
+                    bytes.copy_from_slice(bytemuck::cast_slice(data))
+                }
+            }
+        };
+        ($t:ty, $write_into:ident) => {
+            impl BinaryScalar for $t {
+                fn to_bytes<BO: ByteOrder>(data: &[Self], bytes: &mut [u8]) {
+                    BO::$write_into(data, bytes)
+                }
+            }
+        };
+    }
+
+    impl_binary_scalar!(u8, single_byte);
+    impl_binary_scalar!(i8, single_byte);
+    impl_binary_scalar!(u16, write_u16_into);
+    impl_binary_scalar!(i16, write_i16_into);
+    impl_binary_scalar!(u32, write_u32_into);
+    impl_binary_scalar!(i32, write_i32_into);
+    impl_binary_scalar!(u64, write_u64_into);
+    impl_binary_scalar!(i64, write_i64_into);
+    impl_binary_scalar!(f32, write_f32_into);
+    impl_binary_scalar!(f64, write_f64_into);
 
     pub trait WriteVtkImpl {
         /// This function is called by the `write!` macro used throughout this module.
@@ -241,253 +1220,389 @@ mod write_vtk_impl {
         /// of this method.
         fn write_fmt(&mut self, args: Arguments) -> Result;
         fn write_file_type(&mut self) -> Result;
-        fn write_cell_types<BO: ByteOrder>(&mut self, data: Vec<CellType>) -> Result;
-        fn write_vec<T: Display + ToPrimitive + 'static, BO: ByteOrder>(
-            &mut self,
-            data: Vec<T>,
-        ) -> Result;
-        fn write_buf<BO: ByteOrder>(&mut self, data: IOBuffer) -> Result;
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result;
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result;
+        fn write_buf<BO: ByteOrder>(&mut self, data: &IOBuffer) -> Result;
+
+        /// Converts color scalars to the type required by the file format.
+        ///
+        /// Color scalars are floats in `[0, 1]` in ASCII files and unsigned chars in binary files.
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer>;
+
+        /// Writes the lines of an unknown attribute section.
+        ///
+        /// These are only read from ASCII files, so binary writers skip them.
+        fn write_unknown(&mut self, _raw: &str) -> Result {
+            Ok(())
+        }
+
+        /// Reserves space for writing the given model in writers that buffer the whole output.
+        fn reserve_for(&mut self, _vtk: &Vtk, _cell_ids: CellIdType) {}
+
+        /// An in-memory writer used to encode attributes in parallel.
+        #[cfg(feature = "rayon")]
+        type Fork: WriteVtkImpl + Send;
+
+        /// Creates an empty writer with the same settings as this one.
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> Self::Fork;
+
+        /// Appends the output of a writer created by [`fork`](WriteVtkImpl::fork).
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: Self::Fork) -> Result;
+
+        /// Writes the data of the array with the given name.
+        ///
+        /// Points and coordinates are named after their keyword, e.g. `POINTS` or
+        /// `X_COORDINATES`.
+        fn write_named_buf<BO: ByteOrder>(&mut self, _name: &str, data: &IOBuffer) -> Result {
+            self.write_buf::<BO>(data)
+        }
 
         fn write_attributes<BO: ByteOrder>(
             &mut self,
-            data: Attributes,
+            data: &Attributes,
             num_points: usize,
             num_cells: usize,
+            serial: bool,
         ) -> Result {
-            write!(self, "\nPOINT_DATA {}\n", num_points).map_err(|_| Error::PointDataHeader)?;
-            self.write_attrib_data::<BO>(data.point)?;
+            // Like VTK, skip empty sections since some readers reject them.
+            if num_points > 0 || !data.point.is_empty() {
+                write!(self, "\nPOINT_DATA {}\n", num_points)
+                    .map_err(|_| Error::PointDataHeader)?;
+                self.write_attrib_data::<BO>(&data.point, serial)?;
+            }
 
-            write!(self, "\nCELL_DATA {}\n", num_cells).map_err(|_| Error::CellDataHeader)?;
-            self.write_attrib_data::<BO>(data.cell)
+            if num_cells > 0 || !data.cell.is_empty() {
+                write!(self, "\nCELL_DATA {}\n", num_cells).map_err(|_| Error::CellDataHeader)?;
+                self.write_attrib_data::<BO>(&data.cell, serial)?;
+            }
+            Ok(())
+        }
+
+        /// Writes cell topology as a pair of `OFFSETS` and `CONNECTIVITY` arrays following the
+        /// layout used by legacy files of version 5.1 and later.
+        ///
+        /// `err` wraps errors into the error of the data set being written.
+        fn write_cell_arrays<BO: ByteOrder>(
+            &mut self,
+            cell_verts: &VertexNumbers,
+            title: &str,
+            cell_ids: CellIdType,
+            err: fn(DataSetPart) -> DataSetError,
+        ) -> Result {
+            let (connectivity, offsets) = match cell_verts {
+                VertexNumbers::XML {
+                    connectivity,
+                    offsets,
+                } => (Cow::Borrowed(connectivity), Cow::Borrowed(offsets)),
+                VertexNumbers::Legacy { .. } => {
+                    let (connectivity, offsets) = cell_verts.clone().into_xml();
+                    (Cow::Owned(connectivity), Cow::Owned(offsets))
+                }
+            };
+            // Like VTK, offsets start with a leading zero.
+            let offsets: Vec<u64> = std::iter::once(0).chain(offsets.iter().copied()).collect();
+
+            writeln!(self, "{} {} {}", title, offsets.len(), connectivity.len())
+                .map_err(|_| Error::DataSet(err(DataSetPart::Cells(EntryPart::Header))))?;
+
+            let mut write_array = |prefix: &str, name: &str, data: &[u64]| -> Result {
+                let type_name = match cell_ids {
+                    CellIdType::Int64 => "vtktypeint64",
+                    CellIdType::Int32 => "vtktypeint32",
+                };
+                writeln!(self, "{}{} {}", prefix, name, type_name)
+                    .map_err(|_| Error::DataSet(err(DataSetPart::Cells(EntryPart::Tags))))?;
+                let written = match cell_ids {
+                    CellIdType::Int64 => self.write_vec::<_, BO>(data),
+                    CellIdType::Int32 => {
+                        let data = data
+                            .iter()
+                            .map(|&v| i32::try_from(v))
+                            .collect::<std::result::Result<Vec<_>, _>>()
+                            .map_err(|_| Error::DataSet(DataSetError::IndexOverflow))?;
+                        self.write_vec::<_, BO>(&data)
+                    }
+                };
+                written
+                    .map_err(|e| Error::DataSet(err(DataSetPart::Cells(EntryPart::Data(e.into())))))
+            };
+            write_array("", "OFFSETS", &offsets)?;
+            write_array("\n", "CONNECTIVITY", &connectivity)
         }
 
-        fn write_attrib<BO: ByteOrder>(&mut self, attrib: Attribute) -> Result {
-            // Auxiliary generic attributes that cannot be easily mapped to a standard Legacy type.
-            // These are later written into a separate auxiliary field.
-            let mut auxiliary = Vec::new();
+        fn write_attrib<BO: ByteOrder>(&mut self, attrib: &Attribute) -> Result {
             match attrib {
                 Attribute::DataArray(DataArray { name, elem, data }) => {
-                    match elem {
-                        ElementType::Scalars {
-                            num_comp,
-                            lookup_table,
-                        } => {
-                            writeln!(
-                                self,
-                                "SCALARS {} {} {}",
-                                name,
-                                ScalarType::from(data.scalar_type()),
-                                num_comp
-                            )
-                            .map_err(|_| {
-                                Error::Attribute(AttributeError::Scalars(EntryPart::Header))
-                            })?;
-                            writeln!(
-                                self,
-                                "LOOKUP_TABLE {}",
-                                lookup_table.unwrap_or_else(|| String::from("default"))
-                            )
-                            .map_err(|_| {
-                                Error::Attribute(AttributeError::Scalars(EntryPart::LookupTable))
-                            })?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::Scalars(EntryPart::Data(e.into())))
-                            })?;
-                        }
-                        ElementType::ColorScalars(num_comp) => {
-                            writeln!(self, "COLOR_SCALARS {} {}", name, num_comp).map_err(
-                                |_| {
-                                    Error::Attribute(AttributeError::ColorScalars(
-                                        EntryPart::Header,
-                                    ))
-                                },
-                            )?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::ColorScalars(EntryPart::Data(
-                                    e.into(),
-                                )))
-                            })?;
-                        }
-                        ElementType::LookupTable => {
-                            writeln!(self, "LOOKUP_TABLE {} {}", name, data.len() / 4).map_err(
-                                |_| {
-                                    Error::Attribute(AttributeError::LookupTable(EntryPart::Header))
-                                },
-                            )?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::LookupTable(EntryPart::Data(
-                                    e.into(),
-                                )))
-                            })?;
-                        }
-                        ElementType::Vectors => {
-                            writeln!(self, "VECTORS {} {}", name, data.scalar_type()).map_err(
-                                |_| Error::Attribute(AttributeError::Vectors(EntryPart::Header)),
-                            )?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::Vectors(EntryPart::Data(e.into())))
-                            })?;
-                        }
-                        ElementType::Normals => {
-                            writeln!(self, "NORMALS {} {}", name, data.scalar_type()).map_err(
-                                |_| Error::Attribute(AttributeError::Normals(EntryPart::Header)),
-                            )?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::Normals(EntryPart::Data(e.into())))
-                            })?;
-                        }
-                        ElementType::TCoords(dim) => {
-                            writeln!(
-                                self,
-                                "TEXTURE_COORDINATES {} {} {}",
-                                name,
-                                dim,
-                                data.scalar_type()
-                            )
-                            .map_err(|_| {
-                                Error::Attribute(AttributeError::TextureCoordinates(
-                                    EntryPart::Header,
-                                ))
-                            })?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::TextureCoordinates(
-                                    EntryPart::Data(e.into()),
-                                ))
-                            })?;
-                        }
-                        ElementType::Tensors => {
-                            writeln!(self, "TENSORS {} {}", name, data.scalar_type()).map_err(
-                                |_| Error::Attribute(AttributeError::Tensors(EntryPart::Header)),
-                            )?;
-                            self.write_buf::<BO>(data).map_err(|e| {
-                                Error::Attribute(AttributeError::Tensors(EntryPart::Data(e.into())))
-                            })?;
-                        }
-                        ElementType::Generic(n) => {
-                            // Try to convert into an element type representable in Legacy format.
-                            match n {
-                                3 => self.write_attrib::<BO>(Attribute::DataArray(DataArray {
-                                    name,
-                                    elem: ElementType::Vectors,
-                                    data,
-                                }))?,
-                                1 | 2 | 4 => {
-                                    self.write_attrib::<BO>(Attribute::DataArray(DataArray {
-                                        name,
-                                        elem: ElementType::Scalars {
-                                            num_comp: n,
-                                            lookup_table: None,
-                                        },
-                                        data,
-                                    }))?;
-                                }
-                                // TODO: A more sophisticated scheme could check the values to
-                                // determine if the attribute is a Tensor for 9 component elements.
-                                n => {
-                                    auxiliary.push(FieldArray {
-                                        name,
-                                        elem: n,
-                                        data,
-                                    });
-                                }
-                            }
-                        }
-                    }
+                    self.write_data_array::<BO>(name, elem, data)
                 }
                 Attribute::Field { name, data_array } => {
                     writeln!(self, "FIELD {} {}", name, data_array.len())
                         .map_err(|_| Error::Attribute(AttributeError::Field(EntryPart::Header)))?;
-                    for FieldArray {
-                        name,
-                        elem: num_comp,
-                        data,
-                    } in data_array
-                    {
-                        writeln!(
-                            self,
-                            "{} {} {} {}",
-                            name,
-                            num_comp,
-                            data.len() / num_comp as usize,
-                            data.scalar_type()
-                        )
-                        .map_err(|_| {
-                            Error::Attribute(AttributeError::FieldArray(EntryPart::Header))
+                    for array in data_array {
+                        self.write_field_array::<BO>(array, |part| {
+                            Error::Attribute(AttributeError::FieldArray(part))
                         })?;
-                        self.write_buf::<BO>(data).map_err(|e| {
-                            Error::Attribute(AttributeError::FieldArray(EntryPart::Data(e.into())))
+                    }
+                    Ok(())
+                }
+                Attribute::Unknown { raw, .. } => self
+                    .write_unknown(raw)
+                    .map_err(|_| Error::Attribute(AttributeError::Unknown)),
+            }
+        }
+
+        fn write_data_array<BO: ByteOrder>(
+            &mut self,
+            name: &str,
+            elem: &ElementType,
+            data: &IOBuffer,
+        ) -> Result {
+            match elem {
+                ElementType::Scalars {
+                    num_comp,
+                    lookup_table,
+                } => {
+                    writeln!(self, "SCALARS {} {} {}", name, data.scalar_type(), num_comp)
+                        .map_err(|_| {
+                            Error::Attribute(AttributeError::Scalars(EntryPart::Header))
                         })?;
+                    writeln!(
+                        self,
+                        "LOOKUP_TABLE {}",
+                        lookup_table.as_deref().unwrap_or("default")
+                    )
+                    .map_err(|_| {
+                        Error::Attribute(AttributeError::Scalars(EntryPart::LookupTable))
+                    })?;
+                    self.write_named_buf::<BO>(name, data).map_err(|e| {
+                        Error::Attribute(AttributeError::Scalars(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::ColorScalars(num_comp) => {
+                    writeln!(self, "COLOR_SCALARS {} {}", name, num_comp).map_err(|_| {
+                        Error::Attribute(AttributeError::ColorScalars(EntryPart::Header))
+                    })?;
+                    let data = self.color_scalars(data);
+                    self.write_named_buf::<BO>(name, &data).map_err(|e| {
+                        Error::Attribute(AttributeError::ColorScalars(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::LookupTable => {
+                    if !data.len().is_multiple_of(4) {
+                        return Err(Error::Attribute(AttributeError::LookupTable(
+                            EntryPart::Sizes,
+                        )));
+                    }
+                    writeln!(self, "LOOKUP_TABLE {} {}", name, data.len() / 4).map_err(|_| {
+                        Error::Attribute(AttributeError::LookupTable(EntryPart::Header))
+                    })?;
+                    let data = self.color_scalars(data);
+                    self.write_named_buf::<BO>(name, &data).map_err(|e| {
+                        Error::Attribute(AttributeError::LookupTable(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::Vectors => {
+                    writeln!(self, "VECTORS {} {}", name, data.scalar_type()).map_err(|_| {
+                        Error::Attribute(AttributeError::Vectors(EntryPart::Header))
+                    })?;
+                    self.write_named_buf::<BO>(name, data).map_err(|e| {
+                        Error::Attribute(AttributeError::Vectors(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::Normals => {
+                    writeln!(self, "NORMALS {} {}", name, data.scalar_type()).map_err(|_| {
+                        Error::Attribute(AttributeError::Normals(EntryPart::Header))
+                    })?;
+                    self.write_named_buf::<BO>(name, data).map_err(|e| {
+                        Error::Attribute(AttributeError::Normals(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::TCoords(dim) => {
+                    writeln!(
+                        self,
+                        "TEXTURE_COORDINATES {} {} {}",
+                        name,
+                        dim,
+                        data.scalar_type()
+                    )
+                    .map_err(|_| {
+                        Error::Attribute(AttributeError::TextureCoordinates(EntryPart::Header))
+                    })?;
+                    self.write_named_buf::<BO>(name, data).map_err(|e| {
+                        Error::Attribute(AttributeError::TextureCoordinates(EntryPart::Data(
+                            e.into(),
+                        )))
+                    })?;
+                }
+                ElementType::Tensors => {
+                    writeln!(self, "TENSORS {} {}", name, data.scalar_type()).map_err(|_| {
+                        Error::Attribute(AttributeError::Tensors(EntryPart::Header))
+                    })?;
+                    self.write_named_buf::<BO>(name, data).map_err(|e| {
+                        Error::Attribute(AttributeError::Tensors(EntryPart::Data(e.into())))
+                    })?;
+                }
+                ElementType::Generic(n) => {
+                    // Try to convert into an element type representable in Legacy format.
+                    match *n {
+                        3 => self.write_data_array::<BO>(name, &ElementType::Vectors, data)?,
+                        n @ 1 | n @ 2 | n @ 4 => {
+                            let elem = ElementType::Scalars {
+                                num_comp: n,
+                                lookup_table: None,
+                            };
+                            self.write_data_array::<BO>(name, &elem, data)?;
+                        }
+                        // TODO: A more sophisticated scheme could check the values to
+                        // determine if the attribute is a Tensor for 9 component elements.
+                        n => {
+                            // Auxiliary generic attributes that cannot be easily mapped to a
+                            // standard Legacy type are written into a separate auxiliary field.
+                            writeln!(self, "FIELD vtkio_auxiliary 1").map_err(|_| {
+                                Error::Attribute(AttributeError::Field(EntryPart::Header))
+                            })?;
+                            self.write_field_array_data::<BO>(name, n, data, |part| {
+                                Error::Attribute(AttributeError::FieldArray(part))
+                            })?;
+                        }
                     }
                 }
             }
+            Ok(())
+        }
+
+        /// Writes a single array of a `FIELD` block.
+        ///
+        /// `err` wraps errors into the error of the enclosing field.
+        fn write_field_array<BO: ByteOrder>(
+            &mut self,
+            array: &FieldArray,
+            err: fn(EntryPart) -> Error,
+        ) -> Result {
+            self.write_field_array_data::<BO>(&array.name, array.elem, &array.data, err)
+        }
 
-            if !auxiliary.is_empty() {
-                self.write_attrib::<BO>(Attribute::Field {
-                    name: String::from("vtkio_auxiliary"),
-                    data_array: auxiliary,
+        fn write_field_array_data<BO: ByteOrder>(
+            &mut self,
+            name: &str,
+            num_comp: u32,
+            data: &IOBuffer,
+            err: fn(EntryPart) -> Error,
+        ) -> Result {
+            writeln!(
+                self,
+                "{} {} {} {}",
+                name,
+                num_comp,
+                data.len() / num_comp as usize,
+                data.scalar_type()
+            )
+            .map_err(|_| err(EntryPart::Header))?;
+            self.write_named_buf::<BO>(name, data)
+                .map_err(|e| err(EntryPart::Data(e.into())))
+        }
+
+        /// Writes a `FIELD` block that is not attached to points or cells.
+        ///
+        /// This is used for field data sets as well as global field data of other data sets.
+        fn write_field_data<BO: ByteOrder>(
+            &mut self,
+            name: &str,
+            data_array: &[FieldArray],
+        ) -> Result {
+            writeln!(self, "FIELD {} {}", name, data_array.len())
+                .map_err(|_| Error::DataSet(DataSetError::FieldDataHeader))?;
+            for array in data_array {
+                self.write_field_array::<BO>(array, |part| {
+                    Error::DataSet(DataSetError::FieldArray(part))
                 })?;
             }
             Ok(())
         }
 
-        fn write_attrib_data<BO: ByteOrder>(&mut self, attribs: Vec<Attribute>) -> Result {
+        /// Writes the global field data of a data set, if any.
+        fn write_global_field_data<BO: ByteOrder>(&mut self, data: &Attributes) -> Result {
+            if data.field.is_empty() {
+                return Ok(());
+            }
+            self.write_field_data::<BO>("FieldData", &data.field)
+        }
+
+        /// Writes the given attributes, encoding them in parallel with the `rayon` feature unless
+        /// `serial` is set.
+        fn write_attrib_data<BO: ByteOrder>(
+            &mut self,
+            attribs: &[Attribute],
+            serial: bool,
+        ) -> Result {
+            #[cfg(feature = "rayon")]
+            {
+                if !serial {
+                    return self.write_attrib_data_par::<BO>(attribs);
+                }
+            }
+            #[cfg(not(feature = "rayon"))]
+            let _ = serial;
             for attrib in attribs {
                 writeln!(self).map_err(|_| Error::NewLine)?;
                 self.write_attrib::<BO>(attrib)?;
             }
             Ok(())
         }
+
+        /// Encodes attributes in parallel, each into its own fork, and appends them in order.
+        #[cfg(feature = "rayon")]
+        fn write_attrib_data_par<BO: ByteOrder>(&mut self, attribs: &[Attribute]) -> Result {
+            use rayon::prelude::*;
+            let forks: Vec<_> = attribs.iter().map(|_| self.fork()).collect();
+            let forks = forks
+                .into_par_iter()
+                .zip(attribs.par_iter())
+                .map(|(mut fork, attrib)| {
+                    writeln!(fork).map_err(|_| Error::NewLine)?;
+                    fork.write_attrib::<BO>(attrib)?;
+                    Ok(fork)
+                })
+                .collect::<std::result::Result<Vec<_>, Error>>()?;
+            for fork in forks {
+                self.join(fork)?;
+            }
+            Ok(())
+        }
         fn write_vtk_impl<BO: ByteOrder>(
             &mut self,
-            vtk: Vtk,
+            vtk: &Vtk,
+            cell_ids: CellIdType,
+            serial: bool,
         ) -> std::result::Result<&mut Self, Error> {
             let source_path = vtk.file_path.as_ref().map(|p| p.as_ref());
+            self.reserve_for(vtk, cell_ids);
             writeln!(self, "# vtk DataFile Version {}", vtk.version)
                 .map_err(|_| Error::Header(Header::Version))?;
             writeln!(self, "{}", vtk.title).map_err(|_| Error::Header(Header::Version))?;
             self.write_file_type()?;
-            match vtk.data {
+            match &vtk.data {
                 DataSet::Field { name, data_array } => {
-                    writeln!(self, "FIELD {} {}", name, data_array.len())
-                        .map_err(|_| Error::DataSet(DataSetError::FieldDataHeader))?;
-                    for FieldArray {
-                        name,
-                        elem: num_comp,
-                        data,
-                    } in data_array
-                    {
-                        writeln!(
-                            self,
-                            "{} {} {} {}",
-                            name,
-                            num_comp,
-                            data.len() / num_comp as usize,
-                            data.scalar_type()
-                        )
-                        .map_err(|_| Error::DataSet(DataSetError::FieldArray(EntryPart::Header)))?;
-                        self.write_buf::<BO>(data).map_err(|e| {
-                            Error::DataSet(DataSetError::FieldArray(EntryPart::Data(e.into())))
-                        })?;
-                    }
+                    self.write_field_data::<BO>(name, data_array)?;
                 }
 
                 DataSet::PolyData { pieces, .. } => {
-                    let piece = pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(DataSetError::MissingPieceData)?;
-                    if let Ok(PolyDataPiece {
-                        points,
-                        verts,
-                        lines,
-                        polys,
-                        strips,
-                        data,
-                    }) = piece.into_loaded_piece_data(source_path)
-                    {
+                    let piece = load_merged_piece(pieces, source_path, PolyDataPiece::merge)?;
+                    if let Ok(piece) = piece {
+                        let PolyDataPiece {
+                            points,
+                            verts,
+                            lines,
+                            polys,
+                            strips,
+                            data,
+                        } = &*piece;
                         writeln!(self, "DATASET POLYDATA").map_err(|_| {
                             Error::DataSet(DataSetError::PolyData(DataSetPart::Tags))
                         })?;
+                        self.write_global_field_data::<BO>(data)?;
 
                         writeln!(self, "POINTS {} {}", points.len() / 3, points.scalar_type())
                             .map_err(|_| {
@@ -496,7 +1611,7 @@ mod write_vtk_impl {
                                 )))
                             })?;
                         let num_points = points.len() / 3;
-                        self.write_buf::<BO>(points).map_err(|e| {
+                        self.write_named_buf::<BO>("POINTS", points).map_err(|e| {
                             Error::DataSet(DataSetError::PolyData(DataSetPart::Points(
                                 EntryPart::Data(e.into()),
                             )))
@@ -505,68 +1620,83 @@ mod write_vtk_impl {
                         writeln!(self).map_err(|_| Error::NewLine)?;
 
                         let mut num_cells = 0;
-                        let mut write_topo = |cell_verts: VertexNumbers, title: &str| -> Result {
-                            write!(self, "{}", title).map_err(|_| {
-                                Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
-                                    EntryPart::Tags,
-                                )))
-                            })?;
-
+                        let cell_arrays = vtk.version.has_cell_arrays();
+                        let mut write_topo = |cell_verts: &VertexNumbers, title: &str| -> Result {
                             let cur_num_cells = cell_verts.num_cells();
 
-                            writeln!(
-                                self,
-                                " {} {}",
-                                cur_num_cells,
-                                cur_num_cells + cell_verts.num_verts()
-                            )
-                            .map_err(|_| {
-                                Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
-                                    EntryPart::Sizes,
-                                )))
-                            })?;
+                            if cell_arrays {
+                                self.write_cell_arrays::<BO>(
+                                    cell_verts,
+                                    title,
+                                    cell_ids,
+                                    DataSetError::PolyData,
+                                )?;
+                            } else {
+                                write!(self, "{}", title).map_err(|_| {
+                                    Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
+                                        EntryPart::Tags,
+                                    )))
+                                })?;
 
-                            let (_, vertices) = cell_verts.into_legacy();
+                                writeln!(
+                                    self,
+                                    " {} {}",
+                                    cur_num_cells,
+                                    cur_num_cells + cell_verts.num_verts()
+                                )
+                                .map_err(|_| {
+                                    Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
+                                        EntryPart::Sizes,
+                                    )))
+                                })?;
 
-                            self.write_vec::<u32, BO>(vertices).map_err(|e| {
-                                Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
-                                    EntryPart::Data(e.into()),
-                                )))
-                            })?;
+                                let vertices = narrow_indices(cell_verts)?;
+
+                                self.write_vec::<u32, BO>(&vertices).map_err(|e| {
+                                    Error::DataSet(DataSetError::PolyData(DataSetPart::Cells(
+                                        EntryPart::Data(e.into()),
+                                    )))
+                                })?;
+                            }
 
-                            num_cells += cur_num_cells as usize;
+                            num_cells += cur_num_cells;
                             Ok(())
                         };
 
                         verts
+                            .as_ref()
                             .map(|verts| write_topo(verts, "VERTICES"))
                             .transpose()?;
-                        lines.map(|verts| write_topo(verts, "LINES")).transpose()?;
+                        lines
+                            .as_ref()
+                            .map(|verts| write_topo(verts, "LINES"))
+                            .transpose()?;
                         polys
+                            .as_ref()
                             .map(|verts| write_topo(verts, "POLYGONS"))
                             .transpose()?;
                         strips
+                            .as_ref()
                             .map(|verts| write_topo(verts, "TRIANGLE_STRIPS"))
                             .transpose()?;
 
-                        self.write_attributes::<BO>(data, num_points, num_cells)?;
+                        self.write_attributes::<BO>(data, num_points, num_cells, serial)?;
                     }
                 }
 
                 DataSet::UnstructuredGrid { pieces, .. } => {
-                    let piece = pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(DataSetError::MissingPieceData)?;
-                    if let Ok(UnstructuredGridPiece {
-                        points,
-                        cells,
-                        data,
-                    }) = piece.into_loaded_piece_data(source_path)
-                    {
+                    let piece =
+                        load_merged_piece(pieces, source_path, UnstructuredGridPiece::merge)?;
+                    if let Ok(piece) = piece {
+                        let UnstructuredGridPiece {
+                            points,
+                            cells,
+                            data,
+                        } = &*piece;
                         writeln!(self, "DATASET UNSTRUCTURED_GRID").map_err(|_| {
                             Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Tags))
                         })?;
+                        self.write_global_field_data::<BO>(data)?;
 
                         writeln!(self, "POINTS {} {}", points.len() / 3, points.scalar_type())
                             .map_err(|_| {
@@ -575,7 +1705,7 @@ mod write_vtk_impl {
                                 )))
                             })?;
                         let num_points = points.len() / 3;
-                        self.write_buf::<BO>(points).map_err(|e| {
+                        self.write_named_buf::<BO>("POINTS", points).map_err(|e| {
                             Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Points(
                                 EntryPart::Data(e.into()),
                             )))
@@ -584,30 +1714,14 @@ mod write_vtk_impl {
                         let num_cells = cells.cell_verts.num_cells();
 
                         // Write CELLS structure.
-                        if vtk.version.major >= 5 {
-                            // From version 5 and on the cells are written as an offsets and connectivity pair.
-                            let (connectivity, offsets) = cells.cell_verts.into_xml();
-
-                            writeln!(self, "\nCELLS {} {}", offsets.len(), connectivity.len())
-                                .map_err(|_| {
-                                    Error::DataSet(DataSetError::UnstructuredGrid(
-                                        DataSetPart::Cells(EntryPart::Header),
-                                    ))
-                                })?;
-
-                            writeln!(self, "\nOFFSETS vtktypeint64")?;
-                            self.write_vec::<_, BO>(offsets).map_err(|e| {
-                                Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Cells(
-                                    EntryPart::Data(e.into()),
-                                )))
-                            })?;
-
-                            writeln!(self, "\nCONNECTIVITY vtktypeint64")?;
-                            self.write_vec::<_, BO>(connectivity).map_err(|e| {
-                                Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Cells(
-                                    EntryPart::Data(e.into()),
-                                )))
-                            })?;
+                        if vtk.version.has_cell_arrays() {
+                            writeln!(self).map_err(|_| Error::NewLine)?;
+                            self.write_cell_arrays::<BO>(
+                                &cells.cell_verts,
+                                "CELLS",
+                                cell_ids,
+                                DataSetError::UnstructuredGrid,
+                            )?;
                         } else {
                             let num_verts = cells.cell_verts.num_verts();
 
@@ -618,9 +1732,9 @@ mod write_vtk_impl {
                                     ))
                                 })?;
 
-                            let (_, vertices) = cells.cell_verts.into_legacy();
+                            let vertices = narrow_indices(&cells.cell_verts)?;
 
-                            self.write_vec::<u32, BO>(vertices).map_err(|e| {
+                            self.write_vec::<u32, BO>(&vertices).map_err(|e| {
                                 Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::Cells(
                                     EntryPart::Data(e.into()),
                                 )))
@@ -633,9 +1747,9 @@ mod write_vtk_impl {
                             )))
                         })?;
 
-                        self.write_cell_types::<BO>(cells.types)?;
+                        self.write_cell_types::<BO>(&cells.types)?;
 
-                        self.write_attributes::<BO>(data, num_points, num_cells as usize)?;
+                        self.write_attributes::<BO>(data, num_points, num_cells as usize, serial)?;
                     }
                 }
 
@@ -646,18 +1760,18 @@ mod write_vtk_impl {
                     pieces,
                     ..
                 } => {
-                    let piece = pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(DataSetError::MissingPieceData)?;
-                    if let Ok(ImageDataPiece { data, .. }) =
-                        piece.into_loaded_piece_data(source_path)
-                    {
+                    if pieces.len() > 1 {
+                        return Err(Error::DataSet(DataSetError::MultiplePieces));
+                    }
+                    let piece = pieces.first().ok_or(DataSetError::MissingPieceData)?;
+                    if let Ok(piece) = load_piece(piece, source_path) {
+                        let data = &piece.data;
                         writeln!(self, "DATASET STRUCTURED_POINTS").map_err(|_| {
                             Error::DataSet(DataSetError::StructuredPoints(DataSetPart::Tags))
                         })?;
+                        self.write_global_field_data::<BO>(data)?;
 
-                        let dims = extent.into_dims();
+                        let dims = extent.clone().into_dims();
 
                         writeln!(self, "DIMENSIONS {} {} {}", dims[0], dims[1], dims[2]).map_err(
                             |_| {
@@ -691,23 +1805,31 @@ mod write_vtk_impl {
                         )?;
 
                         let num_points = (dims[0] * dims[1] * dims[2]) as usize;
-                        self.write_attributes::<BO>(data, num_points, 0)?;
+                        let num_cells = Extent::Dims(dims).num_cells() as usize;
+                        self.write_attributes::<BO>(data, num_points, num_cells, serial)?;
                     }
                 }
 
                 DataSet::StructuredGrid { extent, pieces, .. } => {
-                    let piece = pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(DataSetError::MissingPieceData)?;
-                    if let Ok(StructuredGridPiece { points, data, .. }) =
-                        piece.into_loaded_piece_data(source_path)
-                    {
+                    if pieces.len() > 1 {
+                        return Err(Error::DataSet(DataSetError::MultiplePieces));
+                    }
+                    let piece = pieces.first().ok_or(DataSetError::MissingPieceData)?;
+                    if let Ok(piece) = load_piece(piece, source_path) {
+                        let StructuredGridPiece { points, data, .. } = &*piece;
                         writeln!(self, "DATASET STRUCTURED_GRID").map_err(|_| {
                             Error::DataSet(DataSetError::StructuredGrid(DataSetPart::Tags))
                         })?;
+                        self.write_global_field_data::<BO>(data)?;
 
-                        let dims = extent.into_dims();
+                        let dims = extent.clone().into_dims();
+
+                        // Degenerate dimensions are allowed, but must agree with the points.
+                        if Extent::Dims(dims).num_points() != (points.len() / 3) as u64 {
+                            return Err(Error::DataSet(DataSetError::StructuredGrid(
+                                DataSetPart::Dimensions,
+                            )));
+                        }
 
                         writeln!(self, "DIMENSIONS {} {} {}", dims[0], dims[1], dims[2]).map_err(
                             |_| {
@@ -724,30 +1846,39 @@ mod write_vtk_impl {
                                 )))
                             })?;
                         let num_points = points.len() / 3;
-                        self.write_buf::<BO>(points).map_err(|e| {
+                        self.write_named_buf::<BO>("POINTS", points).map_err(|e| {
                             Error::DataSet(DataSetError::StructuredGrid(DataSetPart::Points(
                                 EntryPart::Data(e.into()),
                             )))
                         })?;
 
-                        assert_eq!((dims[0] * dims[1] * dims[2]) as usize, num_points);
-                        self.write_attributes::<BO>(data, num_points, 1)?;
+                        let num_cells = Extent::Dims(dims).num_cells() as usize;
+                        self.write_attributes::<BO>(data, num_points, num_cells, serial)?;
                     }
                 }
 
                 DataSet::RectilinearGrid { extent, pieces, .. } => {
-                    let piece = pieces
-                        .into_iter()
-                        .next()
-                        .ok_or(DataSetError::MissingPieceData)?;
-                    if let Ok(RectilinearGridPiece { coords, data, .. }) =
-                        piece.into_loaded_piece_data(source_path)
-                    {
+                    if pieces.len() > 1 {
+                        return Err(Error::DataSet(DataSetError::MultiplePieces));
+                    }
+                    let piece = pieces.first().ok_or(DataSetError::MissingPieceData)?;
+                    if let Ok(piece) = load_piece(piece, source_path) {
+                        let RectilinearGridPiece { coords, data, .. } = &*piece;
                         writeln!(self, "DATASET RECTILINEAR_GRID").map_err(|_| {
                             Error::DataSet(DataSetError::RectilinearGrid(DataSetPart::Tags))
                         })?;
+                        self.write_global_field_data::<BO>(data)?;
+
+                        let dims = extent.clone().into_dims();
 
-                        let dims = extent.into_dims();
+                        // Degenerate dimensions are allowed, but must agree with the coordinates.
+                        if dims
+                            != [coords.x.len(), coords.y.len(), coords.z.len()].map(|n| n as u32)
+                        {
+                            return Err(Error::DataSet(DataSetError::RectilinearGrid(
+                                DataSetPart::Dimensions,
+                            )));
+                        }
 
                         writeln!(self, "DIMENSIONS {} {} {}", dims[0], dims[1], dims[2]).map_err(
                             |_| {
@@ -768,12 +1899,12 @@ mod write_vtk_impl {
                                 DataSetPart::XCoordinates(EntryPart::Header),
                             ))
                         })?;
-                        let num_x_coords = coords.x.len();
-                        self.write_buf::<BO>(coords.x).map_err(|e| {
-                            Error::DataSet(DataSetError::RectilinearGrid(
-                                DataSetPart::XCoordinates(EntryPart::Data(e.into())),
-                            ))
-                        })?;
+                        self.write_named_buf::<BO>("X_COORDINATES", &coords.x)
+                            .map_err(|e| {
+                                Error::DataSet(DataSetError::RectilinearGrid(
+                                    DataSetPart::XCoordinates(EntryPart::Data(e.into())),
+                                ))
+                            })?;
                         writeln!(
                             self,
                             "Y_COORDINATES {} {}",
@@ -785,12 +1916,12 @@ mod write_vtk_impl {
                                 DataSetPart::YCoordinates(EntryPart::Header),
                             ))
                         })?;
-                        let num_y_coords = coords.y.len();
-                        self.write_buf::<BO>(coords.y).map_err(|e| {
-                            Error::DataSet(DataSetError::RectilinearGrid(
-                                DataSetPart::YCoordinates(EntryPart::Data(e.into())),
-                            ))
-                        })?;
+                        self.write_named_buf::<BO>("Y_COORDINATES", &coords.y)
+                            .map_err(|e| {
+                                Error::DataSet(DataSetError::RectilinearGrid(
+                                    DataSetPart::YCoordinates(EntryPart::Data(e.into())),
+                                ))
+                            })?;
                         writeln!(
                             self,
                             "Z_COORDINATES {} {}",
@@ -802,17 +1933,16 @@ mod write_vtk_impl {
                                 DataSetPart::ZCoordinates(EntryPart::Header),
                             ))
                         })?;
-                        let num_z_coords = coords.z.len();
-                        self.write_buf::<BO>(coords.z).map_err(|e| {
-                            Error::DataSet(DataSetError::RectilinearGrid(
-                                DataSetPart::ZCoordinates(EntryPart::Data(e.into())),
-                            ))
-                        })?;
+                        self.write_named_buf::<BO>("Z_COORDINATES", &coords.z)
+                            .map_err(|e| {
+                                Error::DataSet(DataSetError::RectilinearGrid(
+                                    DataSetPart::ZCoordinates(EntryPart::Data(e.into())),
+                                ))
+                            })?;
 
-                        let num_points = num_x_coords * num_y_coords * num_z_coords;
-                        let num_cells =
-                            (num_x_coords - 1) * (num_y_coords - 1) * (num_z_coords - 1);
-                        self.write_attributes::<BO>(data, num_points, num_cells)?;
+                        let num_points = coords.x.len() * coords.y.len() * coords.z.len();
+                        let num_cells = Extent::Dims(dims).num_cells() as usize;
+                        self.write_attributes::<BO>(data, num_points, num_cells, serial)?;
                     }
                 }
             }
@@ -822,99 +1952,124 @@ mod write_vtk_impl {
         }
     }
 
+    /// Returns the data of the given piece, loading it first if it's not inline.
+    fn load_piece<'a, P: PieceData + Clone>(
+        piece: &'a Piece<P>,
+        source_path: Option<&std::path::Path>,
+    ) -> std::result::Result<Cow<'a, P>, crate::model::Error> {
+        match piece {
+            Piece::Inline(data) => Ok(Cow::Borrowed(&**data)),
+            _ => piece.load_piece_data(source_path).map(Cow::Owned),
+        }
+    }
+
+    /// Loads all pieces of a data set and merges them into one, since legacy files store a single
+    /// piece.
+    ///
+    /// Failing to load a piece is reported by the inner result, while pieces that can't be merged
+    /// produce an error. A single inline piece is borrowed rather than copied.
+    fn load_merged_piece<'a, P: PieceData + Clone>(
+        pieces: &'a [Piece<P>],
+        source_path: Option<&std::path::Path>,
+        merge: impl FnOnce(Vec<P>) -> std::result::Result<P, crate::model::Error>,
+    ) -> std::result::Result<std::result::Result<Cow<'a, P>, crate::model::Error>, Error> {
+        match pieces {
+            [] => Err(Error::DataSet(DataSetError::MissingPieceData)),
+            [piece] => Ok(load_piece(piece, source_path)),
+            _ => {
+                let mut loaded = Vec::with_capacity(pieces.len());
+                for piece in pieces {
+                    match piece.load_piece_data(source_path) {
+                        Ok(piece) => loaded.push(piece),
+                        Err(err) => return Ok(Err(err)),
+                    }
+                }
+                merge(loaded)
+                    .map(|piece| Ok(Cow::Owned(piece)))
+                    .map_err(|_| Error::DataSet(DataSetError::PieceDataMismatch))
+            }
+        }
+    }
+
     impl<W: std::io::Write> WriteVtkImpl for BinaryWriter<W> {
         fn write_fmt(&mut self, args: Arguments) -> Result {
             std::io::Write::write_fmt(&mut self.0, args)?;
             Ok(())
         }
+        #[cfg(feature = "rayon")]
+        type Fork = Vec<u8>;
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> Vec<u8> {
+            Vec::new()
+        }
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: Vec<u8>) -> Result {
+            self.0.write_all(&fork)?;
+            Ok(())
+        }
         fn write_file_type(&mut self) -> Result {
             writeln!(&mut self.0, "BINARY\n").map_err(|_| Error::Header(Header::FileType))
         }
-        fn write_cell_types<BO: ByteOrder>(&mut self, data: Vec<CellType>) -> Result {
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result {
             let err_fn = |ek: Option<std::io::ErrorKind>| {
                 Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::CellTypes(
                     EntryPart::Data(ek),
                 )))
             };
             let err = |e: std::io::Error| err_fn(Some(e.kind()));
-            for t in data {
-                self.0.write_i32::<BO>(t as i32).map_err(err)?;
-            }
+            let ids: Vec<i32> = data.iter().map(|t| t.id() as i32).collect();
+            i32::write_slice::<BO, _>(&ids, &mut self.0).map_err(err)?;
             writeln!(&mut self.0).map_err(|_| Error::NewLine)
         }
-        fn write_vec<T: Display + ToPrimitive + 'static, BO: ByteOrder>(
-            &mut self,
-            data: Vec<T>,
-        ) -> Result {
-            let buf = IOBuffer::from(data);
-            self.write_buf::<BO>(buf)
-        }
-        fn write_buf<BO: ByteOrder>(&mut self, buf: IOBuffer) -> Result {
-            fn write_buf_impl<T, W, E>(vec: Vec<T>, writer: &mut W, elem_writer: E) -> Result
-            where
-                W: WriteBytesExt,
-                E: Fn(&mut W, T) -> std::io::Result<()>,
-            {
-                for elem in vec {
-                    elem_writer(writer, elem)?;
-                }
-                Ok(())
-            }
-
-            match buf {
-                IOBuffer::Bit(v) => write_buf_impl(v, &mut self.0, W::write_u8)?,
-                IOBuffer::U8(v) => write_buf_impl(v, &mut self.0, W::write_u8)?,
-                IOBuffer::I8(v) => write_buf_impl(v, &mut self.0, W::write_i8)?,
-                IOBuffer::U16(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_u16::<BO>)?;
-                }
-                IOBuffer::I16(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_i16::<BO>)?;
-                }
-                IOBuffer::U32(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_u32::<BO>)?;
-                }
-                IOBuffer::I32(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_i32::<BO>)?;
-                }
-                IOBuffer::U64(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_u64::<BO>)?;
-                }
-                IOBuffer::I64(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_i64::<BO>)?;
-                }
-                IOBuffer::F32(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_f32::<BO>)?;
-                }
-                IOBuffer::F64(v) => {
-                    write_buf_impl(v, &mut self.0, W::write_f64::<BO>)?;
-                }
-            }
-
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result {
+            T::write_slice::<BO, _>(data, &mut self.0)?;
+            writeln!(&mut self.0)?;
+            Ok(())
+        }
+        fn write_buf<BO: ByteOrder>(&mut self, buf: &IOBuffer) -> Result {
+            match_buf!(buf, v => BinaryScalar::write_slice::<BO, _>(v, &mut self.0)?);
             writeln!(&mut self.0)?;
             Ok(())
         }
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer> {
+            color_scalars_to_u8(data)
+        }
     }
 
     impl WriteVtkImpl for Vec<u8> {
         fn write_fmt(&mut self, args: Arguments) -> Result {
             BinaryWriter(self).write_fmt(args)
         }
+        fn reserve_for(&mut self, vtk: &Vtk, cell_ids: CellIdType) {
+            let config = WriterConfig::new().with_cell_id_type(cell_ids);
+            self.reserve(vtk.estimated_size(&config));
+        }
+        #[cfg(feature = "rayon")]
+        type Fork = Vec<u8>;
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> Vec<u8> {
+            Vec::new()
+        }
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: Vec<u8>) -> Result {
+            self.extend_from_slice(&fork);
+            Ok(())
+        }
         fn write_file_type(&mut self) -> Result {
             BinaryWriter(self).write_file_type()
         }
-        fn write_cell_types<BO: ByteOrder>(&mut self, data: Vec<CellType>) -> Result {
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result {
             BinaryWriter(self).write_cell_types::<BO>(data)
         }
-        fn write_vec<T: Display + ToPrimitive + 'static, BO: ByteOrder>(
-            &mut self,
-            data: Vec<T>,
-        ) -> Result {
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result {
             BinaryWriter(self).write_vec::<T, BO>(data)
         }
-        fn write_buf<BO: ByteOrder>(&mut self, buf: IOBuffer) -> Result {
+        fn write_buf<BO: ByteOrder>(&mut self, buf: &IOBuffer) -> Result {
             BinaryWriter(self).write_buf::<BO>(buf)
         }
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer> {
+            color_scalars_to_u8(data)
+        }
     }
 
     impl<W: std::fmt::Write> WriteVtkImpl for AsciiWriter<W> {
@@ -922,37 +2077,123 @@ mod write_vtk_impl {
             std::fmt::Write::write_fmt(&mut self.0, args)?;
             Ok(())
         }
+        #[cfg(feature = "rayon")]
+        type Fork = String;
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> String {
+            String::new()
+        }
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: String) -> Result {
+            self.0.write_str(&fork)?;
+            Ok(())
+        }
         fn write_file_type(&mut self) -> Result {
             writeln!(&mut self.0, "ASCII\n").map_err(|_| Error::Header(Header::FileType))?;
             Ok(())
         }
-        fn write_cell_types<BO: ByteOrder>(&mut self, data: Vec<CellType>) -> Result {
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result {
             let err = Error::DataSet(DataSetError::UnstructuredGrid(DataSetPart::CellTypes(
                 EntryPart::Data(None),
             )));
             for t in data {
-                writeln!(&mut self.0, "{}", t as u8).map_err(|_| err)?;
+                writeln!(&mut self.0, "{}", t.id()).map_err(|_| err)?;
             }
             writeln!(&mut self.0).map_err(|_| err)?;
             Ok(())
         }
-        fn write_vec<T: Display + ToPrimitive + 'static, BO: ByteOrder>(
-            &mut self,
-            data: Vec<T>,
-        ) -> Result {
-            for i in 0..data.len() {
-                write!(&mut self.0, "{}", data[i])?;
-                if i < data.len() - 1 {
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result {
+            for (i, elem) in data.iter().enumerate() {
+                if i > 0 {
                     // add an extra space between elements
                     write!(&mut self.0, " ")?;
                 }
+                write!(&mut self.0, "{}", elem)?;
             }
             writeln!(&mut self.0)?; // finish with a new line
             Ok(())
         }
 
-        fn write_buf<BO: ByteOrder>(&mut self, data: IOBuffer) -> Result {
-            writeln!(&mut self.0, "{}", data)?;
+        fn write_buf<BO: ByteOrder>(&mut self, data: &IOBuffer) -> Result {
+            match data {
+                IOBuffer::F32(v) => self.write_floats(v)?,
+                IOBuffer::F64(v) => self.write_floats(v)?,
+                _ => writeln!(&mut self.0, "{}", data)?,
+            }
+            Ok(())
+        }
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer> {
+            color_scalars_to_f32(data)
+        }
+        fn write_unknown(&mut self, raw: &str) -> Result {
+            writeln!(self, "{}", raw)
+        }
+    }
+
+    impl<W: std::fmt::Write> WriteVtkImpl for FormattedAsciiWriter<W> {
+        fn write_fmt(&mut self, args: Arguments) -> Result {
+            AsciiWriter(&mut self.writer).write_fmt(args)
+        }
+        #[cfg(feature = "rayon")]
+        type Fork = FormattedAsciiWriter<String>;
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> Self::Fork {
+            FormattedAsciiWriter {
+                writer: String::new(),
+                float_format: self.float_format,
+                non_finite: self.non_finite,
+                line_wrap: self.line_wrap,
+                array_formats: self.array_formats.clone(),
+            }
+        }
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: Self::Fork) -> Result {
+            self.writer.write_str(&fork.writer)?;
+            Ok(())
+        }
+        fn write_file_type(&mut self) -> Result {
+            AsciiWriter(&mut self.writer).write_file_type()
+        }
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result {
+            AsciiWriter(&mut self.writer).write_cell_types::<BO>(data)
+        }
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result {
+            self.write_values(data, |f, x| write!(f, "{}", x))?;
+            Ok(())
+        }
+        fn write_buf<BO: ByteOrder>(&mut self, data: &IOBuffer) -> Result {
+            let float_format = self.float_format;
+            let non_finite = self.non_finite;
+            match data {
+                IOBuffer::F32(v) => self.write_values(v, |mut f, &x| {
+                    float_format.write(&mut f, non_finite.apply(x))
+                })?,
+                IOBuffer::F64(v) => self.write_values(v, |mut f, &x| {
+                    float_format.write(&mut f, non_finite.apply(x))
+                })?,
+                _ => match_buf!(data, v => self.write_values(v, |f, x| write!(f, "{}", x))?),
+            }
+            Ok(())
+        }
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer> {
+            color_scalars_to_f32(data)
+        }
+        fn write_unknown(&mut self, raw: &str) -> Result {
+            writeln!(self, "{}", raw)
+        }
+        fn write_named_buf<BO: ByteOrder>(&mut self, name: &str, data: &IOBuffer) -> Result {
+            let format = match self.array_formats.get(name) {
+                Some(format) => Arc::clone(format),
+                None => return self.write_buf::<BO>(data),
+            };
+            let non_finite = self.non_finite;
+            match data {
+                IOBuffer::F32(v) => {
+                    self.write_values(v, |f, &x| format(f, f64::from(non_finite.apply(x))))?
+                }
+                IOBuffer::F64(v) => self.write_values(v, |f, &x| format(f, non_finite.apply(x)))?,
+                _ => return self.write_buf::<BO>(data),
+            }
             Ok(())
         }
     }
@@ -961,58 +2202,689 @@ mod write_vtk_impl {
         fn write_fmt(&mut self, args: Arguments) -> Result {
             AsciiWriter(self).write_fmt(args)
         }
+        fn reserve_for(&mut self, vtk: &Vtk, cell_ids: CellIdType) {
+            let config = WriterConfig::new()
+                .with_format(OutputFormat::LegacyAscii)
+                .with_cell_id_type(cell_ids);
+            self.reserve(vtk.estimated_size(&config));
+        }
+        #[cfg(feature = "rayon")]
+        type Fork = String;
+        #[cfg(feature = "rayon")]
+        fn fork(&self) -> String {
+            String::new()
+        }
+        #[cfg(feature = "rayon")]
+        fn join(&mut self, fork: String) -> Result {
+            self.push_str(&fork);
+            Ok(())
+        }
         fn write_file_type(&mut self) -> Result {
             AsciiWriter(self).write_file_type()
         }
-        fn write_cell_types<BO: ByteOrder>(&mut self, data: Vec<CellType>) -> Result {
+        fn write_cell_types<BO: ByteOrder>(&mut self, data: &[CellType]) -> Result {
             AsciiWriter(self).write_cell_types::<BO>(data)
         }
-        fn write_vec<T: Display + ToPrimitive + 'static, BO: ByteOrder>(
-            &mut self,
-            data: Vec<T>,
-        ) -> Result {
+        fn write_vec<T: Display + BinaryScalar, BO: ByteOrder>(&mut self, data: &[T]) -> Result {
             AsciiWriter(self).write_vec::<T, BO>(data)
         }
-        fn write_buf<BO: ByteOrder>(&mut self, buf: IOBuffer) -> Result {
+        fn write_buf<BO: ByteOrder>(&mut self, buf: &IOBuffer) -> Result {
             AsciiWriter(self).write_buf::<BO>(buf)
         }
+        fn color_scalars<'a>(&self, data: &'a IOBuffer) -> Cow<'a, IOBuffer> {
+            color_scalars_to_f32(data)
+        }
+        fn write_unknown(&mut self, raw: &str) -> Result {
+            AsciiWriter(self).write_unknown(raw)
+        }
     }
 }
 
 pub use self::write_vtk_impl::Error;
 
+/// The integer type of the `OFFSETS` and `CONNECTIVITY` arrays in legacy files.
+///
+/// Legacy files of version 5.1 and later store cells in these arrays, which VTK writes as 64-bit
+/// integers. Some older consumers only accept 32-bit integers, which can be requested with
+/// [`Int32`](CellIdType::Int32). Writing fails with an `IndexOverflow` error if an offset or
+/// vertex index exceeds `i32::MAX` in that case.
+///
+/// Files of earlier versions always store cells as 32-bit integers.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::model::*;
+/// use vtkio::writer::{CellIdType, WriteVtk};
+///
+/// let vtk = Vtk {
+///     version: Version::new((5, 1)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Triangle"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+///         polys: Some(VertexNumbers::Legacy {
+///             num_cells: 1,
+///             vertices: vec![3, 0, 1, 2],
+///         }),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let mut output = String::new();
+/// output.write_vtk_with_cell_ids(&vtk, CellIdType::Int32).expect("Failed to write file");
+/// assert!(output.contains("OFFSETS vtktypeint32\n0 3\n"));
+/// assert!(output.contains("CONNECTIVITY vtktypeint32\n0 1 2\n"));
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CellIdType {
+    /// 64-bit integers, as written by VTK. This is the default.
+    #[default]
+    Int64,
+    /// 32-bit integers, checked to fit when written.
+    Int32,
+}
+
+/// A version of the legacy file format to target when writing.
+///
+/// The legacy writers adjust the output to the `version` of the written model. Only the
+/// following versions are commonly read by other software, and they differ as follows:
+///
+///  - Versions 2.0, 3.0 and 4.2 store cells in a single `CELLS` array of 32-bit integers,
+///    where the vertices of each cell are preceded by their number.
+///  - Version 5.1 stores cells in separate `OFFSETS` and `CONNECTIVITY` arrays of 64-bit
+///    integers by default, see [`CellIdType`].
+///
+/// Structured points use the `SPACING` keyword in all of these versions, unlike the
+/// `ASPECT_RATIO` keyword of version 1.0.
+///
+/// Use [`WriterConfig::with_legacy_version`] to write a model with a specific version.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LegacyVersion {
+    V2_0,
+    V3_0,
+    V4_2,
+    V5_1,
+}
+
+impl LegacyVersion {
+    /// Returns the version number written to the file header.
+    pub fn version(self) -> Version {
+        match self {
+            LegacyVersion::V2_0 => Version::new((2, 0)),
+            LegacyVersion::V3_0 => Version::new((3, 0)),
+            LegacyVersion::V4_2 => Version::new((4, 2)),
+            LegacyVersion::V5_1 => Version::new((5, 1)),
+        }
+    }
+}
+
+impl From<LegacyVersion> for Version {
+    fn from(version: LegacyVersion) -> Version {
+        version.version()
+    }
+}
+
+/// Writes [`Vtk`] models in the legacy file format.
+///
+/// The model is only borrowed, so the same model can be written to multiple destinations without
+/// copying its data. Both owned and borrowed models are accepted:
+///
+/// ```
+/// use vtkio::model::*;
+/// use vtkio::writer::WriteVtk;
+///
+/// let vtk = Vtk {
+///     version: Version::new((4, 2)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Points"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![0.0f32, 0.0, 0.0].into(),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let mut ascii = String::new();
+/// ascii.write_vtk(&vtk).expect("Failed to write ASCII file");
+/// let mut binary = Vec::new();
+/// binary.write_vtk(vtk).expect("Failed to write binary file");
+/// ```
 pub trait WriteVtk: write_vtk_impl::WriteVtkImpl {
-    fn write_vtk(&mut self, vtk: Vtk) -> Result<&mut Self, Error> {
+    fn write_vtk(&mut self, vtk: impl Borrow<Vtk>) -> Result<&mut Self, Error> {
+        self.write_vtk_with_cell_ids(vtk, CellIdType::default())
+    }
+    /// Same as `write_vtk` but stores cell offsets and connectivity with the given integer type.
+    ///
+    /// This only affects files of version 5.1 and later, see [`CellIdType`].
+    fn write_vtk_with_cell_ids(
+        &mut self,
+        vtk: impl Borrow<Vtk>,
+        cell_ids: CellIdType,
+    ) -> Result<&mut Self, Error> {
+        let vtk = vtk.borrow();
         match vtk.byte_order {
-            ByteOrderTag::LittleEndian => self.write_vtk_impl::<LittleEndian>(vtk),
-            ByteOrderTag::BigEndian => self.write_vtk_impl::<BigEndian>(vtk),
+            ByteOrderTag::LittleEndian => self.write_vtk_impl::<LittleEndian>(vtk, cell_ids, false),
+            ByteOrderTag::BigEndian => self.write_vtk_impl::<BigEndian>(vtk, cell_ids, false),
         }
     }
-    /// Same as `write_vtk` but overrides the `byte_order` field to write in little endian format.
-    fn write_vtk_le(&mut self, mut vtk: Vtk) -> Result<&mut Self, Error> {
-        // Make sure the written file is consistent
-        vtk.byte_order = ByteOrderTag::LittleEndian;
-        self.write_vtk_impl::<LittleEndian>(vtk)
+    /// Same as `write_vtk` but ignores the `byte_order` field to write in little endian format.
+    fn write_vtk_le(&mut self, vtk: impl Borrow<Vtk>) -> Result<&mut Self, Error> {
+        self.write_vtk_impl::<LittleEndian>(vtk.borrow(), CellIdType::default(), false)
     }
-    /// Same as `write_vtk` but overrides the `byte_order` field to write in big endian format.
-    fn write_vtk_be(&mut self, mut vtk: Vtk) -> Result<&mut Self, Error> {
-        // Make sure the written file is consistent
-        vtk.byte_order = ByteOrderTag::BigEndian;
-        self.write_vtk_impl::<BigEndian>(vtk)
+    /// Same as `write_vtk` but ignores the `byte_order` field to write in big endian format.
+    fn write_vtk_be(&mut self, vtk: impl Borrow<Vtk>) -> Result<&mut Self, Error> {
+        self.write_vtk_impl::<BigEndian>(vtk.borrow(), CellIdType::default(), false)
     }
-    /// Same as `write_vtk` but overrides the `byte_order` field to write in native endian format.
+    /// Same as `write_vtk` but ignores the `byte_order` field to write in native endian format.
     #[cfg(target_endian = "little")]
-    fn write_vtk_ne(&mut self, vtk: Vtk) -> Result<&mut Self, Error> {
+    fn write_vtk_ne(&mut self, vtk: impl Borrow<Vtk>) -> Result<&mut Self, Error> {
         self.write_vtk_le(vtk)
     }
-    /// Same as `write_vtk` but overrides the `byte_order` field to write in native endian format.
+    /// Same as `write_vtk` but ignores the `byte_order` field to write in native endian format.
     #[cfg(target_endian = "big")]
-    fn write_vtk_ne(&mut self, vtk: Vtk) -> Result<&mut Self, Error> {
+    fn write_vtk_ne(&mut self, vtk: impl Borrow<Vtk>) -> Result<&mut Self, Error> {
         self.write_vtk_be(vtk)
     }
 }
 
 impl<W: std::fmt::Write> WriteVtk for AsciiWriter<W> {}
 impl<W: std::io::Write> WriteVtk for BinaryWriter<W> {}
+impl<W: std::fmt::Write> WriteVtk for FormattedAsciiWriter<W> {}
 impl WriteVtk for String {}
 impl WriteVtk for Vec<u8> {}
+
+/// The kind of file produced by a [`WriterConfig`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// A legacy `.vtk` file with data written as ASCII text.
+    LegacyAscii,
+    /// A legacy `.vtk` file with binary data. This is the default.
+    #[default]
+    LegacyBinary,
+    /// A modern XML file with base64 encoded binary data.
+    #[cfg(feature = "xml")]
+    Xml,
+}
+
+/// Options controlling how [`Vtk`] models are written.
+///
+/// The writers in this module pick the output format from the type being written to, for
+/// instance a `String` always receives an ASCII file and a `Vec<u8>` a binary one. A
+/// `WriterConfig` instead collects all output options in one place and writes to any
+/// [`io::Write`](std::io::Write)r or file.
+///
+/// Output is deterministic: writing the same model with the same configuration always produces
+/// identical bytes. With the `rayon` feature, arrays are encoded and compressed in parallel, but
+/// the results are joined in the order of the model before anything is written. Use
+/// [`with_deterministic`](WriterConfig::with_deterministic) to encode all arrays serially on the
+/// calling thread instead.
+///
+/// # Examples
+///
+/// ```
+/// use vtkio::model::*;
+/// use vtkio::writer::{FloatFormat, OutputFormat, WriterConfig};
+///
+/// let vtk = Vtk {
+///     version: Version::new((2, 0)),
+///     byte_order: ByteOrder::BigEndian,
+///     title: String::from("Points"),
+///     file_path: None,
+///     data: DataSet::inline(PolyDataPiece {
+///         points: vec![1e-30_f64, 0.1, 1e300].into(),
+///         ..Default::default()
+///     }),
+/// };
+///
+/// let config = WriterConfig::new()
+///     .with_format(OutputFormat::LegacyAscii)
+///     .with_float_format(FloatFormat::RoundTrip)
+///     .with_version(Version::new((4, 2)));
+///
+/// let mut output = Vec::new();
+/// config.write(vtk, &mut output).expect("Failed to write file");
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.starts_with("# vtk DataFile Version 4.2\n"));
+/// assert!(output.contains("1e-30 0.1 1e300\n"));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WriterConfig {
+    format: OutputFormat,
+    byte_order: Option<ByteOrderTag>,
+    #[cfg(feature = "xml")]
+    compression: (crate::xml::Compressor, u32),
+    float_format: FloatFormat,
+    non_finite: NonFinite,
+    line_wrap: LineWrap,
+    cell_id_type: CellIdType,
+    validate: bool,
+    deterministic: bool,
+    version: Option<Version>,
+    legacy_version: Option<LegacyVersion>,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        WriterConfig::new()
+    }
+}
+
+impl WriterConfig {
+    /// Creates a configuration for writing legacy binary files without overriding any of the
+    /// properties of the written model.
+    pub fn new() -> Self {
+        WriterConfig {
+            format: OutputFormat::default(),
+            byte_order: None,
+            #[cfg(feature = "xml")]
+            compression: (crate::xml::Compressor::None, 0),
+            float_format: FloatFormat::default(),
+            non_finite: NonFinite::default(),
+            line_wrap: LineWrap::default(),
+            cell_id_type: CellIdType::default(),
+            validate: false,
+            deterministic: false,
+            version: None,
+            legacy_version: None,
+        }
+    }
+
+    /// Sets the kind of file to write.
+    pub fn with_format(self, format: OutputFormat) -> Self {
+        WriterConfig { format, ..self }
+    }
+
+    /// Writes binary data in the given byte order instead of the one given by the model.
+    ///
+    /// This has no effect on ASCII files.
+    pub fn with_byte_order(self, byte_order: ByteOrderTag) -> Self {
+        WriterConfig {
+            byte_order: Some(byte_order),
+            ..self
+        }
+    }
+
+    /// Compresses the data in XML files with the given compressor.
+    ///
+    /// The compression level ranges from 0 (no compression) to 9 (slowest but smallest file
+    /// size) and is ignored for LZ4 compression. Legacy files are never compressed.
+    #[cfg(feature = "xml")]
+    pub fn with_compression(self, compressor: crate::xml::Compressor, level: u32) -> Self {
+        WriterConfig {
+            compression: (compressor, level),
+            ..self
+        }
+    }
+
+    /// Sets the format of floating point numbers in ASCII files.
+    pub fn with_float_format(self, float_format: FloatFormat) -> Self {
+        WriterConfig {
+            float_format,
+            ..self
+        }
+    }
+
+    /// Sets how NaN and infinite values are written in ASCII files.
+    pub fn with_non_finite(self, non_finite: NonFinite) -> Self {
+        WriterConfig { non_finite, ..self }
+    }
+
+    /// Sets how long lines of data values are broken up in ASCII files.
+    pub fn with_line_wrap(self, line_wrap: LineWrap) -> Self {
+        WriterConfig { line_wrap, ..self }
+    }
+
+    /// Sets the integer type of cell offsets and connectivity in legacy files.
+    ///
+    /// This only affects files of version 5.1 and later, see [`CellIdType`].
+    pub fn with_cell_id_type(self, cell_id_type: CellIdType) -> Self {
+        WriterConfig {
+            cell_id_type,
+            ..self
+        }
+    }
+
+    /// Checks the sizes of the geometry and attributes of the model before writing it.
+    ///
+    /// With validation enabled, an inconsistent model produces an error instead of a corrupt
+    /// file. See [`Vtk::validate`] for the checks performed.
+    pub fn with_validation(self, validate: bool) -> Self {
+        WriterConfig { validate, ..self }
+    }
+
+    /// Encodes and compresses all arrays serially on the calling thread.
+    ///
+    /// Without the `rayon` feature arrays are always encoded serially. With it, they are
+    /// encoded in parallel unless this is set. Both produce the same bytes, but the serial path
+    /// avoids the thread pool entirely, for instance when profiling or when writing from within
+    /// another rayon task.
+    pub fn with_deterministic(self, deterministic: bool) -> Self {
+        WriterConfig {
+            deterministic,
+            ..self
+        }
+    }
+
+    /// Writes the file with the given version instead of the one given by the model.
+    ///
+    /// For legacy files this also determines how cells are stored: versions 5.1 and above use
+    /// offset and connectivity arrays.
+    pub fn with_version(self, version: Version) -> Self {
+        WriterConfig {
+            version: Some(version),
+            ..self
+        }
+    }
+
+    /// Writes legacy files with the given version.
+    ///
+    /// This takes precedence over [`with_version`](WriterConfig::with_version) for legacy files
+    /// and has no effect on XML files. See [`LegacyVersion`] for how the output is adjusted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::writer::{LegacyVersion, OutputFormat, WriterConfig};
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((5, 1)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Triangle"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+    ///         polys: Some(VertexNumbers::Legacy {
+    ///             num_cells: 1,
+    ///             vertices: vec![3, 0, 1, 2],
+    ///         }),
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let config = WriterConfig::new()
+    ///     .with_format(OutputFormat::LegacyAscii)
+    ///     .with_legacy_version(LegacyVersion::V4_2);
+    ///
+    /// let mut output = Vec::new();
+    /// config.write(vtk, &mut output).expect("Failed to write file");
+    /// let output = String::from_utf8(output).unwrap();
+    /// assert!(output.starts_with("# vtk DataFile Version 4.2\n"));
+    /// assert!(output.contains("POLYGONS 1 4\n3 0 1 2\n"));
+    /// ```
+    pub fn with_legacy_version(self, legacy_version: LegacyVersion) -> Self {
+        WriterConfig {
+            legacy_version: Some(legacy_version),
+            ..self
+        }
+    }
+
+    /// Returns the kind of file written with this configuration.
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Writes the given model to the specified [`Write`](std::io::Write)r.
+    ///
+    /// The writer is not buffered, see [`export`](WriterConfig::export) for writing to files.
+    pub fn write(&self, mut vtk: Vtk, writer: impl std::io::Write) -> Result<(), crate::Error> {
+        if self.validate {
+            vtk.validate()?;
+        }
+        if let Some(version) = self.version {
+            vtk.version = version;
+        }
+        if let Some(byte_order) = self.byte_order {
+            vtk.byte_order = byte_order;
+        }
+        if let (Some(version), OutputFormat::LegacyAscii | OutputFormat::LegacyBinary) =
+            (self.legacy_version, self.format)
+        {
+            vtk.version = version.version();
+        }
+        match self.format {
+            OutputFormat::LegacyAscii => {
+                let mut ascii = FormattedAsciiWriter::new(IoWriter::new(writer))
+                    .with_float_format(self.float_format)
+                    .with_non_finite(self.non_finite)
+                    .with_line_wrap(self.line_wrap);
+                if let Err(err) = self.write_legacy(&mut ascii, &vtk) {
+                    // Report the underlying IO error rather than the failed formatting step.
+                    return Err(ascii
+                        .writer
+                        .take_error()
+                        .map_or(crate::Error::Write(err), crate::Error::IO));
+                }
+            }
+            OutputFormat::LegacyBinary => {
+                self.write_legacy(&mut BinaryWriter(writer), &vtk)?;
+            }
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => {
+                let (compressor, level) = self.compression;
+                let vtk_file = vtk.into_xml(compressor, level, false, self.deterministic)?;
+                crate::xml::write(&vtk_file, writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a legacy file with the byte order of the model.
+    fn write_legacy(&self, writer: &mut impl WriteVtkImpl, vtk: &Vtk) -> Result<(), Error> {
+        let (cell_ids, serial) = (self.cell_id_type, self.deterministic);
+        match vtk.byte_order {
+            ByteOrderTag::LittleEndian => {
+                writer.write_vtk_impl::<LittleEndian>(vtk, cell_ids, serial)?
+            }
+            ByteOrderTag::BigEndian => writer.write_vtk_impl::<BigEndian>(vtk, cell_ids, serial)?,
+        };
+        Ok(())
+    }
+
+    /// Writes the given model to the specified file through a buffer.
+    ///
+    /// The file extension is not checked, so it is up to the caller to choose one matching the
+    /// format and data set type.
+    pub fn export(&self, vtk: Vtk, file_path: impl AsRef<Path>) -> Result<(), crate::Error> {
+        let file = File::create(file_path.as_ref())?;
+        let mut writer = BufWriter::new(file);
+        self.write(vtk, &mut writer)?;
+        // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Vtk {
+    /// Estimates the number of bytes written for this model with the given configuration.
+    ///
+    /// This is intended for reserving buffer capacity up front, so the estimate is only
+    /// approximate. ASCII values are assumed to take a typical number of characters for their
+    /// type and compression is ignored. Pieces stored in other files don't contribute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vtkio::model::*;
+    /// use vtkio::writer::{WriteVtk, WriterConfig};
+    ///
+    /// let vtk = Vtk {
+    ///     version: Version::new((4, 2)),
+    ///     byte_order: ByteOrder::BigEndian,
+    ///     title: String::from("Points"),
+    ///     file_path: None,
+    ///     data: DataSet::inline(PolyDataPiece {
+    ///         points: vec![0.0f32; 3000].into(),
+    ///         ..Default::default()
+    ///     }),
+    /// };
+    ///
+    /// let mut output = Vec::with_capacity(vtk.estimated_size(&WriterConfig::new()));
+    /// output.write_vtk(&vtk).expect("Failed to write file");
+    /// assert!(output.len() <= output.capacity());
+    /// ```
+    pub fn estimated_size(&self, config: &WriterConfig) -> usize {
+        let mut estimate = SizeEstimate::default();
+        estimate.add_data_set(&self.data);
+        let version = match config.format {
+            OutputFormat::LegacyAscii | OutputFormat::LegacyBinary => config
+                .legacy_version
+                .map(LegacyVersion::version)
+                .or(config.version)
+                .unwrap_or(self.version),
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => config.version.unwrap_or(self.version),
+        };
+        estimate.total(config, version)
+    }
+}
+
+/// Counts the values written for a model, used by [`Vtk::estimated_size`].
+#[derive(Default)]
+struct SizeEstimate {
+    /// The number of values of each scalar type stored in arrays.
+    values: Vec<(ScalarType, usize)>,
+    /// The number of arrays, each of which adds some markup.
+    arrays: usize,
+    /// The number of topology blocks such as `POLYGONS` or `CELLS`.
+    cell_blocks: usize,
+    cells: usize,
+    cell_verts: usize,
+    cell_types: usize,
+}
+
+impl SizeEstimate {
+    /// Rough bytes of markup for the file header and each array.
+    const HEADER_SIZE: usize = 256;
+    const ARRAY_HEADER_SIZE: usize = 64;
+
+    fn add_buf(&mut self, buf: &IOBuffer) {
+        self.arrays += 1;
+        self.values.push((buf.scalar_type(), buf.len()));
+    }
+
+    fn add_cells(&mut self, cells: &VertexNumbers) {
+        self.cell_blocks += 1;
+        self.cells += cells.num_cells();
+        self.cell_verts += cells.num_verts();
+    }
+
+    fn add_attributes(&mut self, data: &Attributes) {
+        for attrib in data.point.iter().chain(data.cell.iter()) {
+            match attrib {
+                Attribute::DataArray(array) => self.add_buf(&array.data),
+                Attribute::Field { data_array, .. } => data_array
+                    .iter()
+                    .for_each(|array| self.add_buf(&array.data)),
+                // Counting each character as a byte overestimates the size of the text.
+                Attribute::Unknown { raw, .. } => {
+                    self.arrays += 1;
+                    self.values.push((ScalarType::U8, raw.len()));
+                }
+            }
+        }
+        data.field
+            .iter()
+            .for_each(|array| self.add_buf(&array.data));
+    }
+
+    fn add_pieces<P>(&mut self, pieces: &[Piece<P>], add_piece: impl Fn(&mut Self, &P)) {
+        for piece in pieces {
+            match piece {
+                Piece::Inline(piece) => add_piece(self, piece),
+                Piece::Loaded(data_set) => self.add_data_set(data_set),
+                Piece::Source(..) => {}
+            }
+        }
+    }
+
+    fn add_data_set(&mut self, data: &DataSet) {
+        match data {
+            DataSet::ImageData { pieces, .. } => {
+                self.add_pieces(pieces, |e, piece| e.add_attributes(&piece.data))
+            }
+            DataSet::StructuredGrid { pieces, .. } => self.add_pieces(pieces, |e, piece| {
+                e.add_buf(&piece.points);
+                e.add_attributes(&piece.data);
+            }),
+            DataSet::RectilinearGrid { pieces, .. } => self.add_pieces(pieces, |e, piece| {
+                e.add_buf(&piece.coords.x);
+                e.add_buf(&piece.coords.y);
+                e.add_buf(&piece.coords.z);
+                e.add_attributes(&piece.data);
+            }),
+            DataSet::UnstructuredGrid { pieces, .. } => self.add_pieces(pieces, |e, piece| {
+                e.add_buf(&piece.points);
+                e.add_cells(&piece.cells.cell_verts);
+                e.cell_types += piece.cells.types.len();
+                e.add_attributes(&piece.data);
+            }),
+            DataSet::PolyData { pieces, .. } => self.add_pieces(pieces, |e, piece| {
+                e.add_buf(&piece.points);
+                let topologies = [&piece.verts, &piece.lines, &piece.polys, &piece.strips];
+                for cells in topologies.iter().copied().flatten() {
+                    e.add_cells(cells);
+                }
+                e.add_attributes(&piece.data);
+            }),
+            DataSet::Field { data_array, .. } => data_array
+                .iter()
+                .for_each(|array| self.add_buf(&array.data)),
+        }
+    }
+
+    /// Typical number of characters of an ASCII value including its separator.
+    fn ascii_width(scalar_type: ScalarType, float_format: FloatFormat) -> usize {
+        match (scalar_type, float_format) {
+            (ScalarType::F32 | ScalarType::F64, FloatFormat::Significant(digits)) => {
+                usize::from(digits) + 7
+            }
+            (ScalarType::F32, _) => 12,
+            (ScalarType::F64, _) => 20,
+            (ScalarType::Bit | ScalarType::U8 | ScalarType::I8, _) => 4,
+            (ScalarType::U16 | ScalarType::I16, _) => 6,
+            _ => 8,
+        }
+    }
+
+    fn total(&self, config: &WriterConfig, version: Version) -> usize {
+        let markup = Self::HEADER_SIZE + (self.arrays + self.cell_blocks) * Self::ARRAY_HEADER_SIZE;
+        // Legacy files before 5.1 prefix each cell with its size, later ones store an offset per
+        // cell along with a leading zero.
+        let cell_arrays = version.has_cell_arrays();
+        let cell_ids =
+            self.cell_verts + self.cells + if cell_arrays { self.cell_blocks } else { 0 };
+        let id_size = match config.cell_id_type {
+            CellIdType::Int64 if cell_arrays => 8,
+            _ => 4,
+        };
+        let data = match config.format {
+            OutputFormat::LegacyBinary => {
+                let values: usize = self.values.iter().map(|&(t, n)| t.size() * n).sum();
+                values + cell_ids * id_size + self.cell_types * 4
+            }
+            OutputFormat::LegacyAscii => {
+                let float_format = config.float_format;
+                let values: usize = self
+                    .values
+                    .iter()
+                    .map(|&(t, n)| Self::ascii_width(t, float_format) * n)
+                    .sum();
+                values + cell_ids * 8 + self.cell_types * 3
+            }
+            #[cfg(feature = "xml")]
+            OutputFormat::Xml => {
+                // Cells are written as 64-bit offsets and connectivity along with 8-bit types,
+                // and all data is base64 encoded.
+                let values: usize = self.values.iter().map(|&(t, n)| t.size() * n).sum();
+                let cells = (self.cells + self.cell_verts) * 8 + self.cell_types;
+                (values + cells) / 3 * 4
+            }
+        };
+        markup + data
+    }
+}
@@ -9,17 +9,19 @@
 mod se;
 
 use quick_xml::de;
-use std::convert::{TryFrom, TryInto};
-use std::io::{BufRead, Write};
+use std::convert::TryFrom;
+use std::io::{BufRead, Read, Write};
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
 use crate::model;
+use crate::{LengthPolicy, ReadOptions, Warning};
 
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     XML(quick_xml::Error),
     Base64Decode(base64::DecodeError),
@@ -140,6 +142,7 @@ mod compressor {
                 "vtkZLibDataCompressor" => Compressor::ZLib,
                 "vtkLZ4DataCompressor" => Compressor::LZ4,
                 "vtkLZMADataCompressor" => Compressor::LZMA,
+                "vtkZstdDataCompressor" => Compressor::Zstd,
                 _ => Compressor::None,
             })
         }
@@ -154,6 +157,7 @@ mod compressor {
                 Compressor::ZLib => "vtkZLibDataCompressor",
                 Compressor::LZ4 => "vtkLZ4DataCompressor",
                 Compressor::LZMA => "vtkLZMADataCompressor",
+                Compressor::Zstd => "vtkZstdDataCompressor",
                 Compressor::None => return s.serialize_none(),
             };
             s.serialize_str(compressor)
@@ -229,7 +233,7 @@ mod extent {
 }
 
 /// Module used to serialize and deserialize whitespace separated sequences of 3 floats.
-mod vector3 {
+pub(crate) mod vector3 {
     use serde::de::{self, Deserialize, Deserializer, Visitor};
     use serde::ser::{Serialize, Serializer};
     use std::fmt;
@@ -939,22 +943,22 @@ mod piece {
                         piece.extent = map.next_value::<Option<Extent>>()?;
                     }
                     Field::NumberOfPoints => {
-                        piece.number_of_points = map.next_value::<u32>()?;
+                        piece.number_of_points = map.next_value::<u64>()?;
                     }
                     Field::NumberOfCells => {
-                        piece.number_of_cells = map.next_value::<u32>()?;
+                        piece.number_of_cells = map.next_value::<u64>()?;
                     }
                     Field::NumberOfLines => {
-                        piece.number_of_lines = map.next_value::<u32>()?;
+                        piece.number_of_lines = map.next_value::<u64>()?;
                     }
                     Field::NumberOfStrips => {
-                        piece.number_of_strips = map.next_value::<u32>()?;
+                        piece.number_of_strips = map.next_value::<u64>()?;
                     }
                     Field::NumberOfPolys => {
-                        piece.number_of_polys = map.next_value::<u32>()?;
+                        piece.number_of_polys = map.next_value::<u64>()?;
                     }
                     Field::NumberOfVerts => {
-                        piece.number_of_verts = map.next_value::<u32>()?;
+                        piece.number_of_verts = map.next_value::<u64>()?;
                     }
                     Field::PointData => {
                         piece.point_data = map.next_value::<AttributeData>()?;
@@ -1215,11 +1219,195 @@ impl Default for VTKFile {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+impl VTKFile {
+    /// Returns `true` if the cell connectivity or offsets of any inline piece are stored as
+    /// 64-bit integers.
+    pub(crate) fn has_int64_ids(&self) -> bool {
+        self.data_set.pieces().iter().any(|piece| {
+            let topos = [&piece.verts, &piece.lines, &piece.strips, &piece.polys];
+            let topo_arrays = topos
+                .iter()
+                .copied()
+                .flatten()
+                .flat_map(|topo| [&topo.connectivity, &topo.offsets]);
+            let cell_arrays = piece
+                .cells
+                .iter()
+                .flat_map(|cells| [&cells.connectivity, &cells.offsets]);
+            cell_arrays
+                .chain(topo_arrays)
+                .any(|data| matches!(data.scalar_type, ScalarType::Int64 | ScalarType::UInt64))
+        })
+    }
+
+    /// Moves the contents of all inline binary data arrays into a raw `AppendedData` section.
+    ///
+    /// Each binary `DataArray` is converted to the `appended` format with an `offset` pointing
+    /// into the appended data section, which is written as raw binary after the XML body. This
+    /// avoids the 33% size overhead of base64 encoding for large arrays. The header and
+    /// compression of each array is preserved. ASCII data arrays are left untouched.
+    ///
+    /// If this file already has a raw appended data section, new data is added to the end of it.
+    /// Base64 encoded appended data is not supported and results in an error.
+    ///
+    /// Inline arrays are stored in base64, so they are decoded here. When converting from the
+    /// model, use [`model::Vtk::try_into_xml_appended`] instead, which stores the data directly.
+    pub fn into_appended_raw(mut self) -> Result<VTKFile> {
+        let mut appended = match self.appended_data.take() {
+            None => Vec::new(),
+            Some(AppendedData {
+                encoding: Encoding::Raw,
+                data: RawData(data),
+            }) => data,
+            Some(AppendedData {
+                encoding: Encoding::Base64,
+                ..
+            }) => return Err(ValidationError::Unsupported.into()),
+        };
+
+        for piece in self.data_set.pieces_mut() {
+            for data_array in piece.data_arrays_mut() {
+                if data_array.format != DataArrayFormat::Binary {
+                    continue;
+                }
+                let offset = appended.len() as u64;
+                for data in data_array.data.drain(..) {
+                    if let Data::Data(encoded) = data {
+                        let encoded: Vec<u8> = encoded
+                            .bytes()
+                            .filter(|b| !b.is_ascii_whitespace())
+                            .collect();
+                        decode_base64_chunks(&encoded, &mut appended)?;
+                    }
+                }
+                data_array.format = DataArrayFormat::Appended;
+                data_array.offset = Some(offset);
+            }
+        }
+
+        if !appended.is_empty() {
+            self.appended_data = Some(AppendedData {
+                encoding: Encoding::Raw,
+                data: RawData(appended),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Returns the encoding parameters used to decode data arrays stored in this file.
+    pub(crate) fn encoding_info(&self) -> EncodingInfo {
+        EncodingInfo {
+            byte_order: self.byte_order,
+            header_type: self.header_type.unwrap_or(ScalarType::UInt32),
+            compressor: self.compressor,
+            compression_level: 0, // This is meaningless when decoding
+        }
+    }
+
+    /// Returns the whole extent of structured data sets.
+    pub(crate) fn whole_extent(&self) -> Option<Extent> {
+        match &self.data_set {
+            DataSet::ImageData(ImageData { whole_extent, .. })
+            | DataSet::RectilinearGrid(Grid { whole_extent, .. })
+            | DataSet::StructuredGrid(Grid { whole_extent, .. })
+            | DataSet::PImageData(PImageData { whole_extent, .. })
+            | DataSet::PRectilinearGrid(PRectilinearGrid { whole_extent, .. })
+            | DataSet::PStructuredGrid(PStructuredGrid { whole_extent, .. }) => Some(*whole_extent),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of points and cells in the given inline piece.
+    pub(crate) fn piece_size(&self, piece: usize) -> Option<(usize, usize)> {
+        let whole_extent = self.whole_extent();
+        let piece = self.data_set.pieces().get(piece)?;
+        if let Some(whole_extent) = whole_extent {
+            let extent: model::Extent = piece.extent.unwrap_or(whole_extent).into();
+            return Some((
+                usize::try_from(extent.num_points()).ok()?,
+                usize::try_from(extent.num_cells()).ok()?,
+            ));
+        }
+        let num_cells = match self.data_set {
+            DataSet::PolyData(_) => {
+                piece.number_of_lines
+                    + piece.number_of_strips
+                    + piece.number_of_polys
+                    + piece.number_of_verts
+            }
+            _ => piece.number_of_cells,
+        };
+        Some((
+            usize::try_from(piece.number_of_points).ok()?,
+            usize::try_from(num_cells).ok()?,
+        ))
+    }
+
+    /// Checks the declared sizes of the points and attribute arrays of all inline pieces against
+    /// the allocation limits of the given options.
+    ///
+    /// Arrays rejected by the array filter of the options are not counted.
+    fn check_allocation_limits(&self, options: &ReadOptions) -> Result<()> {
+        let mut budget = options.allocation_budget();
+        let keep = |array: &&DataArray| {
+            options
+                .array_filter()
+                .is_none_or(|names| names.contains(&array.name))
+        };
+        for (i, piece) in self.data_set.pieces().iter().enumerate() {
+            let (num_points, num_cells) = self
+                .piece_size(i)
+                .ok_or(ValidationError::TooManyElements(u64::MAX))?;
+            let point_arrays = piece.points.iter().map(|points| &points.data);
+            let point_arrays = point_arrays.chain(piece.point_data.data_array.iter().filter(keep));
+            let cell_arrays = piece.cell_data.data_array.iter().filter(keep);
+            for (array, n) in point_arrays
+                .map(|array| (array, num_points))
+                .chain(cell_arrays.map(|array| (array, num_cells)))
+            {
+                let num_values = u64::from(array.num_comp).saturating_mul(n as u64);
+                budget
+                    .allocate(num_values, array.scalar_type.size() as u64)
+                    .map_err(ValidationError::from)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Strips all points and cell topology from this file, keeping only the attribute data.
+    ///
+    /// The point and cell counts of each piece are preserved, so the attributes can still be
+    /// validated when the file is read back. Rectilinear grid coordinates are kept since they are
+    /// required to read the file. This is useful for writing time dependent fields over a static
+    /// mesh stored in a separate file.
+    pub fn into_field_only(mut self) -> VTKFile {
+        for piece in self.data_set.pieces_mut() {
+            piece.points = None;
+            piece.cells = None;
+            piece.verts = None;
+            piece.lines = None;
+            piece.strips = None;
+            piece.polys = None;
+        }
+        self
+    }
+}
+
+/// Compression used for binary data arrays.
+///
+/// Each compressor requires the corresponding feature (`lz4`, `flate2`, `xz2` or `zstd`) to be
+/// enabled. All but `Zstd` are enabled by the `compression` feature.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Compressor {
     LZ4,
     ZLib,
     LZMA,
+    /// Zstandard compression, stored as `vtkZstdDataCompressor`.
+    ///
+    /// This is an extension of `vtkio`: VTK and ParaView have no such compressor, so files written
+    /// with it can't be read by them. Use it only for files read back by `vtkio`.
+    Zstd,
     None,
 }
 
@@ -1243,6 +1431,35 @@ pub enum DataSet {
     PUnstructuredGrid(PUnstructured),
 }
 
+impl DataSet {
+    /// Returns mutable references to all inline pieces in this data set.
+    ///
+    /// Parallel data sets only reference pieces stored in other files, so no pieces are returned
+    /// for those.
+    fn pieces_mut(&mut self) -> std::slice::IterMut<'_, Piece> {
+        match self {
+            DataSet::ImageData(ImageData { pieces, .. })
+            | DataSet::PolyData(Unstructured { pieces })
+            | DataSet::RectilinearGrid(Grid { pieces, .. })
+            | DataSet::StructuredGrid(Grid { pieces, .. })
+            | DataSet::UnstructuredGrid(Unstructured { pieces }) => pieces.iter_mut(),
+            _ => [].iter_mut(),
+        }
+    }
+
+    /// Returns all inline pieces in this data set.
+    pub(crate) fn pieces(&self) -> &[Piece] {
+        match self {
+            DataSet::ImageData(ImageData { pieces, .. })
+            | DataSet::PolyData(Unstructured { pieces })
+            | DataSet::RectilinearGrid(Grid { pieces, .. })
+            | DataSet::StructuredGrid(Grid { pieces, .. })
+            | DataSet::UnstructuredGrid(Unstructured { pieces }) => pieces,
+            _ => &[],
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct ImageData {
     #[serde(rename = "WholeExtent")]
@@ -1251,7 +1468,7 @@ pub struct ImageData {
     origin: [f32; 3],
     #[serde(rename = "Spacing", deserialize_with = "vector3::deserialize")]
     spacing: [f32; 3],
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<Piece>,
 }
 
@@ -1259,13 +1476,13 @@ pub struct ImageData {
 pub struct Grid {
     #[serde(rename = "WholeExtent")]
     whole_extent: Extent,
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<Piece>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
 pub struct Unstructured {
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<Piece>,
 }
 
@@ -1283,7 +1500,7 @@ pub struct PImageData {
     point_data: Option<PAttributeData>,
     #[serde(rename = "PCellData")]
     cell_data: Option<PAttributeData>,
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<PieceSource>,
 }
 
@@ -1299,7 +1516,7 @@ pub struct PRectilinearGrid {
     cell_data: Option<PAttributeData>,
     #[serde(rename = "PCoordinates")]
     coords: PCoordinates,
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<PieceSource>,
 }
 
@@ -1315,7 +1532,7 @@ pub struct PStructuredGrid {
     cell_data: Option<PAttributeData>,
     #[serde(rename = "PPoints")]
     points: PPoints,
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<PieceSource>,
 }
 
@@ -1329,7 +1546,7 @@ pub struct PUnstructured {
     cell_data: Option<PAttributeData>,
     #[serde(rename = "PPoints")]
     points: PPoints,
-    #[serde(rename = "Piece")]
+    #[serde(rename = "Piece", default)]
     pieces: Vec<PieceSource>,
 }
 
@@ -1416,7 +1633,7 @@ impl Default for PPoints {
 }
 
 /// The attribute on VTKFile indicating the contained data set type.
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub enum DataSetType {
     ImageData,
     PolyData,
@@ -1430,7 +1647,7 @@ pub enum DataSetType {
     PUnstructuredGrid,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Default)]
+#[derive(Copy, Clone, Debug, PartialEq, Default, Eq, Hash)]
 pub struct Extent([i32; 6]);
 
 impl From<model::Extent> for Extent {
@@ -1455,7 +1672,7 @@ impl From<Extent> for model::Extent {
 }
 
 // Helper for serializing number_of_cells
-fn is_zero(n: &u32) -> bool {
+fn is_zero(n: &u64) -> bool {
     *n == 0
 }
 
@@ -1464,17 +1681,17 @@ fn is_zero(n: &u32) -> bool {
 pub struct Piece {
     pub extent: Option<Extent>,
     #[serde(default)]
-    pub number_of_points: u32,
+    pub number_of_points: u64,
     #[serde(default, skip_serializing_if = "is_zero")]
-    pub number_of_cells: u32,
+    pub number_of_cells: u64,
     #[serde(default)]
-    pub number_of_lines: u32,
+    pub number_of_lines: u64,
     #[serde(default)]
-    pub number_of_strips: u32,
+    pub number_of_strips: u64,
     #[serde(default)]
-    pub number_of_polys: u32,
+    pub number_of_polys: u64,
     #[serde(default)]
-    pub number_of_verts: u32,
+    pub number_of_verts: u64,
     pub point_data: AttributeData,
     pub cell_data: AttributeData,
     pub points: Option<Points>,
@@ -1486,6 +1703,75 @@ pub struct Piece {
     pub coordinates: Option<Coordinates>,
 }
 
+impl Piece {
+    /// Returns mutable references to all data arrays stored in this piece.
+    fn data_arrays_mut(&mut self) -> Vec<&mut DataArray> {
+        let Piece {
+            point_data,
+            cell_data,
+            points,
+            cells,
+            verts,
+            lines,
+            strips,
+            polys,
+            coordinates,
+            ..
+        } = self;
+        let mut data_arrays: Vec<&mut DataArray> = Vec::new();
+        data_arrays.extend(point_data.data_array.iter_mut());
+        data_arrays.extend(cell_data.data_array.iter_mut());
+        if let Some(points) = points {
+            data_arrays.push(&mut points.data);
+        }
+        if let Some(cells) = cells {
+            data_arrays.push(&mut cells.connectivity);
+            data_arrays.push(&mut cells.offsets);
+            data_arrays.push(&mut cells.types);
+        }
+        for topo in vec![verts, lines, strips, polys].into_iter().flatten() {
+            data_arrays.push(&mut topo.connectivity);
+            data_arrays.push(&mut topo.offsets);
+        }
+        if let Some(Coordinates(coords)) = coordinates {
+            data_arrays.extend(coords.iter_mut());
+        }
+        data_arrays
+    }
+
+    /// Returns references to all data arrays stored in this piece.
+    pub(crate) fn data_arrays(&self) -> Vec<&DataArray> {
+        let mut data_arrays: Vec<&DataArray> = Vec::new();
+        data_arrays.extend(self.point_data.data_array.iter());
+        data_arrays.extend(self.cell_data.data_array.iter());
+        if let Some(points) = &self.points {
+            data_arrays.push(&points.data);
+        }
+        if let Some(cells) = &self.cells {
+            data_arrays.push(&cells.connectivity);
+            data_arrays.push(&cells.offsets);
+            data_arrays.push(&cells.types);
+        }
+        for topo in [&self.verts, &self.lines, &self.strips, &self.polys]
+            .iter()
+            .copied()
+            .flatten()
+        {
+            data_arrays.push(&topo.connectivity);
+            data_arrays.push(&topo.offsets);
+        }
+        if let Some(Coordinates(coords)) = &self.coordinates {
+            data_arrays.extend(coords.iter());
+        }
+        data_arrays
+    }
+
+    /// Returns the data array storing the points of this piece, if any.
+    pub(crate) fn points_array(&self) -> Option<&DataArray> {
+        self.points.as_ref().map(|points| &points.data)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Points {
     #[serde(rename = "DataArray")]
@@ -1494,8 +1780,12 @@ pub struct Points {
 
 impl Points {
     pub fn from_io_buffer(buf: model::IOBuffer, ei: EncodingInfo) -> Points {
+        Points::encode(buf, &mut ArrayEncoder::inline(ei))
+    }
+
+    fn encode(buf: model::IOBuffer, enc: &mut ArrayEncoder) -> Points {
         Points {
-            data: DataArray::from_io_buffer(buf, ei).with_num_comp(3),
+            data: enc.encode(buf).with_num_comp(3),
         }
     }
 }
@@ -1511,21 +1801,26 @@ pub struct Cells {
 }
 
 impl Cells {
-    fn from_model_cells(cells: model::Cells, ei: EncodingInfo) -> Cells {
+    fn from_model_cells(cells: model::Cells, enc: &mut ArrayEncoder) -> Cells {
         let model::Cells { cell_verts, types } = cells;
         let (connectivity, offsets) = cell_verts.into_xml();
         Cells {
-            connectivity: DataArray::from_io_buffer(connectivity.into(), ei)
-                .with_name("connectivity"),
-            offsets: DataArray::from_io_buffer(offsets.into(), ei).with_name("offsets"),
-            types: DataArray::from_io_buffer(
-                types
-                    .into_iter()
-                    .map(|x| x as u8)
-                    .collect::<model::IOBuffer>(),
-                ei,
-            )
-            .with_name("types"),
+            connectivity: enc.encode(connectivity.into()).with_name("connectivity"),
+            offsets: enc.encode(offsets.into()).with_name("offsets"),
+            types: enc
+                .encode(
+                    // Like VTK, types are stored as bytes unless there are unknown cell types with
+                    // larger ids.
+                    match types
+                        .iter()
+                        .map(|x| u8::try_from(x.id()))
+                        .collect::<std::result::Result<Vec<u8>, _>>()
+                    {
+                        Ok(types) => types.into(),
+                        Err(_) => types.into_iter().map(u32::from).collect(),
+                    },
+                )
+                .with_name("types"),
         }
     }
 
@@ -1536,14 +1831,13 @@ impl Cells {
     fn get_type_codes(
         buf: model::IOBuffer,
     ) -> std::result::Result<Vec<model::CellType>, ValidationError> {
-        use num_traits::FromPrimitive;
         let type_codes = buf
-            .cast_into::<u8>()
+            .cast_into::<u32>()
             .ok_or_else(|| ValidationError::InvalidDataFormat)?;
-        type_codes
+        Ok(type_codes
             .into_iter()
-            .map(|x| model::CellType::from_u8(x).ok_or_else(|| ValidationError::InvalidCellType(x)))
-            .collect()
+            .map(model::CellType::from_id)
+            .collect())
     }
 
     /// Given the expected number of elements and an optional appended data,
@@ -1587,12 +1881,11 @@ pub struct Topo {
 
 impl Topo {
     /// Convert model topology type into `Topo`.
-    fn from_model_topo(topo: model::VertexNumbers, ei: EncodingInfo) -> Topo {
+    fn from_model_topo(topo: model::VertexNumbers, enc: &mut ArrayEncoder) -> Topo {
         let (connectivity, offsets) = topo.into_xml();
         Topo {
-            connectivity: DataArray::from_io_buffer(connectivity.into(), ei)
-                .with_name("connectivity"),
-            offsets: DataArray::from_io_buffer(offsets.into(), ei).with_name("offsets"),
+            connectivity: enc.encode(connectivity.into()).with_name("connectivity"),
+            offsets: enc.encode(offsets.into()).with_name("offsets"),
         }
     }
 
@@ -1702,8 +1995,24 @@ impl AttributeInfo {
 }
 
 impl AttributeData {
+    /// Returns the attribute names assigned to the special attribute types of this data.
+    pub(crate) fn info(&self) -> AttributeInfo {
+        AttributeInfo {
+            scalars: self.scalars.clone(),
+            vectors: self.vectors.clone(),
+            normals: self.normals.clone(),
+            tensors: self.tensors.clone(),
+            tcoords: self.tcoords.clone(),
+        }
+    }
+
     pub fn from_model_attributes(attribs: Vec<model::Attribute>, ei: EncodingInfo) -> Self {
+        AttributeData::encode(attribs, &mut ArrayEncoder::inline(ei))
+    }
+
+    fn encode(attribs: Vec<model::Attribute>, enc: &mut ArrayEncoder) -> Self {
         let mut attribute_data = AttributeData::default();
+        let mut arrays = Vec::new();
         for attrib in attribs {
             match attrib {
                 model::Attribute::DataArray(data) => {
@@ -1736,14 +2045,13 @@ impl AttributeData {
                         }
                         _ => {}
                     }
-                    attribute_data
-                        .data_array
-                        .push(DataArray::from_model_data_array(data, ei));
+                    arrays.push(data);
                 }
-                // Field attributes are not supported, they are simply ignored.
+                // Field and unknown legacy attributes are not supported, they are simply ignored.
                 _ => {}
             }
         }
+        attribute_data.data_array = encode_data_arrays(arrays, enc);
         attribute_data
     }
     pub fn into_model_attributes(
@@ -1751,6 +2059,27 @@ impl AttributeData {
         n: usize,
         appended_data: Option<&AppendedData>,
         ei: EncodingInfo,
+    ) -> Vec<model::Attribute> {
+        self.into_model_attributes_with_policy(
+            n,
+            appended_data,
+            ei,
+            LengthPolicy::Discard,
+            &mut Vec::new(),
+        )
+    }
+
+    /// Converts this attribute data into model attributes with `n` tuples each.
+    ///
+    /// Arrays of the wrong length are handled according to `policy`, and a warning is reported
+    /// for each of them. Arrays that fail to load for any other reason are discarded.
+    pub fn into_model_attributes_with_policy(
+        self,
+        n: usize,
+        appended_data: Option<&AppendedData>,
+        ei: EncodingInfo,
+        policy: LengthPolicy,
+        warnings: &mut Vec<Warning>,
     ) -> Vec<model::Attribute> {
         let AttributeData {
             scalars,
@@ -1771,7 +2100,39 @@ impl AttributeData {
 
         data_array
             .into_iter()
-            .filter_map(|x| x.into_attribute(n, appended_data, &info, ei).ok())
+            .filter_map(|x| {
+                let num_elements = usize::try_from(x.num_comp).ok()? * n;
+                let model::FieldArray {
+                    name,
+                    mut data,
+                    elem,
+                } = x.decode_field_array(n, appended_data, ei).ok()?;
+                let actual = data.len();
+                if actual != num_elements {
+                    let action = match policy {
+                        LengthPolicy::Truncate if actual < num_elements => LengthPolicy::Discard,
+                        policy => policy,
+                    };
+                    warnings.push(Warning::AttributeLengthMismatch {
+                        name: name.clone(),
+                        expected: num_elements,
+                        actual,
+                        action,
+                    });
+                    let repeat_last = match action {
+                        LengthPolicy::Discard => return None,
+                        LengthPolicy::RepeatLast => true,
+                        _ => false,
+                    };
+                    model::resize_tuples(&mut data, num_elements, elem as usize, repeat_last);
+                }
+                let elem = info.element_type(&name, elem);
+                Some(model::Attribute::DataArray(model::DataArray {
+                    name,
+                    data,
+                    elem,
+                }))
+            })
             .collect()
     }
 }
@@ -1782,10 +2143,14 @@ pub struct Coordinates([DataArray; 3]);
 impl Coordinates {
     /// Construct `Coordinates` from `model::Coordinates`.
     pub fn from_model_coords(coords: model::Coordinates, ei: EncodingInfo) -> Self {
+        Coordinates::encode(coords, &mut ArrayEncoder::inline(ei))
+    }
+
+    fn encode(coords: model::Coordinates, enc: &mut ArrayEncoder) -> Self {
         Coordinates([
-            DataArray::from_io_buffer(coords.x, ei),
-            DataArray::from_io_buffer(coords.y, ei),
-            DataArray::from_io_buffer(coords.z, ei),
+            enc.encode(coords.x),
+            enc.encode(coords.y),
+            enc.encode(coords.z),
         ])
     }
 
@@ -1806,7 +2171,7 @@ impl Coordinates {
 }
 
 /// A helper struct indicating how to read and write binary data stored in `DataArray`s.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct EncodingInfo {
     byte_order: model::ByteOrder,
     header_type: ScalarType,
@@ -1847,7 +2212,7 @@ pub struct DataArray {
     #[serde(rename = "Name", default)]
     pub name: String,
     pub format: DataArrayFormat,
-    pub offset: Option<u32>,
+    pub offset: Option<u64>,
     #[serde(rename = "NumberOfComponents", default = "default_num_comp")]
     pub num_comp: u32,
     #[serde(rename = "RangeMin")]
@@ -1886,26 +2251,18 @@ impl DataArray {
     }
     /// Construct a binary `DataArray` from a given `model::FieldArray`.
     pub fn from_field_array(field: model::FieldArray, ei: EncodingInfo) -> Self {
+        DataArray::encode_field_array(field, &mut ArrayEncoder::inline(ei))
+    }
+    fn encode_field_array(field: model::FieldArray, enc: &mut ArrayEncoder) -> Self {
         DataArray {
             name: field.name,
             num_comp: field.elem,
-            ..DataArray::from_io_buffer(field.data, ei)
+            ..enc.encode(field.data)
         }
     }
     /// Construct a binary `DataArray` from a given [`model::IOBuffer`].
     pub fn from_io_buffer(buf: model::IOBuffer, ei: EncodingInfo) -> Self {
-        DataArray {
-            scalar_type: buf.scalar_type().into(),
-            data: vec![Data::Data(base64::encode(
-                if ei.header_type == ScalarType::UInt64 {
-                    buf.into_bytes_with_size(ei.byte_order, ei.compressor, ei.compression_level)
-                } else {
-                    buf.into_bytes_with_size32(ei.byte_order, ei.compressor, ei.compression_level)
-                    // Older vtk Versions
-                },
-            ))],
-            ..Default::default()
-        }
+        ArrayEncoder::inline(ei).encode(buf)
     }
 
     /// Returns the given `DataArray` with name set to `name`.
@@ -1930,6 +2287,28 @@ impl DataArray {
         l: usize,
         appended: Option<&AppendedData>,
         ei: EncodingInfo,
+    ) -> std::result::Result<model::FieldArray, ValidationError> {
+        let num_elements = usize::try_from(self.num_comp).unwrap() * l;
+        let field_array = self.decode_field_array(l, appended, ei)?;
+        if field_array.data.len() != num_elements {
+            return Err(ValidationError::DataArraySizeMismatch {
+                name: field_array.name,
+                expected: num_elements,
+                actual: field_array.data.len(),
+            });
+        }
+        Ok(field_array)
+    }
+
+    /// Decodes this data array into a `model::FieldArray` without checking its size.
+    ///
+    /// The expected number of elements `l` is only used to extract uncompressed appended data,
+    /// whose size can't be determined otherwise.
+    fn decode_field_array(
+        self,
+        l: usize,
+        appended: Option<&AppendedData>,
+        ei: EncodingInfo,
     ) -> std::result::Result<model::FieldArray, ValidationError> {
         use model::IOBuffer;
 
@@ -1946,45 +2325,27 @@ impl DataArray {
         //eprintln!("name = {:?}", &name);
 
         let num_elements = usize::try_from(num_comp).unwrap() * l;
-        let header_bytes = ei.header_type.size();
 
         let data = match format {
             DataArrayFormat::Appended => {
                 if let Some(appended) = appended {
-                    let start: usize = offset.unwrap_or(0).try_into().unwrap();
-                    let buf = appended.extract_data(start, num_elements, scalar_type, ei)?;
-                    if buf.len() != num_elements {
-                        return Err(ValidationError::DataArraySizeMismatch {
-                            name,
-                            expected: num_elements,
-                            actual: buf.len(),
-                        });
-                    }
-                    buf
+                    let start = usize::try_from(offset.unwrap_or(0))
+                        .map_err(|_| ValidationError::InvalidDataFormat)?;
+                    appended.extract_data(start, num_elements, scalar_type, ei)?
                 } else {
                     return Err(ValidationError::InvalidDataFormat);
                 }
             }
             DataArrayFormat::Binary => {
-                // First byte gives the bytes
-                let bytes = base64::decode(data[0].clone().into_string())?;
-                // eprintln!("{:?}", &bytes[..header_bytes]);
-                let buf = IOBuffer::from_bytes(
-                    &bytes[header_bytes..],
-                    scalar_type.into(),
-                    ei.byte_order,
-                )?;
-                if buf.len() != num_elements {
-                    return Err(ValidationError::DataArraySizeMismatch {
-                        name,
-                        expected: num_elements,
-                        actual: buf.len(),
-                    });
-                }
-                buf
+                let bytes = decode_inline_binary(&data, ei)?;
+                IOBuffer::from_bytes(&bytes, scalar_type.into(), ei.byte_order)?
             }
             DataArrayFormat::Ascii => {
-                let string = data[0].clone().into_string();
+                // An empty array may have no data at all.
+                let string = data
+                    .first()
+                    .map(|d| d.clone().into_string())
+                    .unwrap_or_default();
                 let slice = string.as_str();
                 fn parse_num_seq<E, T>(s: &str) -> std::result::Result<Vec<T>, ValidationError>
                 where
@@ -1995,7 +2356,7 @@ impl DataArray {
                         .map(|x| x.parse::<T>().map_err(Into::into))
                         .collect()
                 }
-                let buf = match scalar_type {
+                match scalar_type {
                     ScalarType::Int8 => IOBuffer::I8(parse_num_seq(slice)?),
                     ScalarType::UInt8 => IOBuffer::U8(parse_num_seq(slice)?),
                     ScalarType::Int16 => IOBuffer::I16(parse_num_seq(slice)?),
@@ -2006,15 +2367,7 @@ impl DataArray {
                     ScalarType::UInt64 => IOBuffer::U64(parse_num_seq(slice)?),
                     ScalarType::Float32 => IOBuffer::F32(parse_num_seq(slice)?),
                     ScalarType::Float64 => IOBuffer::F64(parse_num_seq(slice)?),
-                };
-                if buf.len() != num_elements {
-                    return Err(ValidationError::DataArraySizeMismatch {
-                        name,
-                        expected: num_elements,
-                        actual: buf.len(),
-                    });
                 }
-                buf
             }
         };
 
@@ -2104,7 +2457,7 @@ impl Default for Data {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 pub enum ScalarType {
     Int8,
     UInt8,
@@ -2172,7 +2525,7 @@ impl From<ScalarType> for model::ScalarType {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum DataArrayFormat {
     Appended,
@@ -2202,14 +2555,398 @@ impl Default for RawData {
 }
 
 /// Supported binary encoding formats.
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Encoding {
     Base64,
     Raw,
 }
 
+/// Reads a single header number, which depends on the encoding parameters.
+fn read_header_num<R: AsRef<[u8]>>(
+    header_buf: &mut std::io::Cursor<R>,
+    ei: EncodingInfo,
+) -> std::result::Result<usize, ValidationError> {
+    use byteorder::ReadBytesExt;
+    use byteorder::{BE, LE};
+    Ok(match ei.byte_order {
+        model::ByteOrder::LittleEndian => {
+            if ei.header_type == ScalarType::UInt64 {
+                header_buf.read_u64::<LE>()? as usize
+            } else {
+                header_buf.read_u32::<LE>()? as usize
+            }
+        }
+        model::ByteOrder::BigEndian => {
+            if ei.header_type == ScalarType::UInt64 {
+                header_buf.read_u64::<BE>()? as usize
+            } else {
+                header_buf.read_u32::<BE>()? as usize
+            }
+        }
+    })
+}
+
+/// Decodes base64 data consisting of one or more independently padded chunks.
+///
+/// VTK encodes the header of binary data separately from the data itself, so padding may appear
+/// in the middle of the encoded string.
+fn decode_base64_chunks(
+    encoded: &[u8],
+    out: &mut Vec<u8>,
+) -> std::result::Result<(), base64::DecodeError> {
+    let config = base64::STANDARD.decode_allow_trailing_bits(true);
+    let mut rest = encoded;
+    while !rest.is_empty() {
+        let end = match rest.iter().position(|&b| b == b'=') {
+            Some(pad) => pad + rest[pad..].iter().take_while(|&&b| b == b'=').count(),
+            None => rest.len(),
+        };
+        base64::decode_config_buf(&rest[..end], config, out)?;
+        rest = &rest[end..];
+    }
+    Ok(())
+}
+
+/// Encodes the given arrays, in parallel if the `rayon` feature is enabled and the encoder isn't
+/// serial.
+///
+/// The encoded arrays are returned, and stored in the appended data, in the order given either
+/// way.
+fn encode_data_arrays(arrays: Vec<model::DataArray>, enc: &mut ArrayEncoder) -> Vec<DataArray> {
+    let ei = enc.ei;
+    let to_bytes = move |data: model::DataArray| {
+        let num_comp = u32::try_from(data.num_comp()).unwrap();
+        let scalar_type = ScalarType::from(data.data.scalar_type());
+        (
+            data.name,
+            num_comp,
+            scalar_type,
+            io_buffer_bytes(data.data, ei),
+        )
+    };
+    let encoded: Vec<_> = if enc.serial {
+        arrays.into_iter().map(to_bytes).collect()
+    } else {
+        par_map(arrays, to_bytes)
+    };
+    encoded
+        .into_iter()
+        .map(|(name, num_comp, scalar_type, bytes)| DataArray {
+            name,
+            num_comp,
+            ..enc.store(scalar_type, bytes)
+        })
+        .collect()
+}
+
+/// Maps the given items in parallel if the `rayon` feature is enabled, and serially otherwise.
+fn par_map<T: Send, U: Send>(items: Vec<T>, f: impl Fn(T) -> U + Send + Sync) -> Vec<U> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    items.into_iter().map(f).collect()
+}
+
+/// Returns the binary contents of a `DataArray` holding the given buffer, prefixed by its header.
+fn io_buffer_bytes(buf: model::IOBuffer, ei: EncodingInfo) -> Vec<u8> {
+    if ei.header_type == ScalarType::UInt64 {
+        buf.into_bytes_with_size(ei.byte_order, ei.compressor, ei.compression_level)
+    } else {
+        // Older vtk Versions
+        buf.into_bytes_with_size32(ei.byte_order, ei.compressor, ei.compression_level)
+    }
+}
+
+/// Encodes `IOBuffer`s into binary `DataArray`s when converting from the model.
+///
+/// Data is either encoded inline in base64, or written to a raw appended data section as is.
+struct ArrayEncoder {
+    ei: EncodingInfo,
+    appended: Option<Vec<u8>>,
+    /// Encode arrays on the calling thread even with the `rayon` feature.
+    serial: bool,
+}
+
+impl ArrayEncoder {
+    fn inline(ei: EncodingInfo) -> Self {
+        ArrayEncoder {
+            ei,
+            appended: None,
+            serial: false,
+        }
+    }
+
+    fn appended(ei: EncodingInfo) -> Self {
+        ArrayEncoder {
+            appended: Some(Vec::new()),
+            ..ArrayEncoder::inline(ei)
+        }
+    }
+
+    fn encode(&mut self, buf: model::IOBuffer) -> DataArray {
+        let scalar_type = buf.scalar_type().into();
+        let bytes = io_buffer_bytes(buf, self.ei);
+        self.store(scalar_type, bytes)
+    }
+
+    /// Stores bytes produced by `io_buffer_bytes` in a new `DataArray`.
+    fn store(&mut self, scalar_type: ScalarType, bytes: Vec<u8>) -> DataArray {
+        let appended = match &mut self.appended {
+            Some(appended) => appended,
+            None => {
+                return DataArray {
+                    scalar_type,
+                    data: vec![Data::Data(encode_inline_binary(&bytes, self.ei))],
+                    ..Default::default()
+                }
+            }
+        };
+        let offset = appended.len() as u64;
+        appended.extend_from_slice(&bytes);
+        DataArray {
+            scalar_type,
+            format: DataArrayFormat::Appended,
+            offset: Some(offset),
+            data: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the appended data section holding the stored arrays, if any.
+    fn finish(self) -> Option<AppendedData> {
+        self.appended
+            .filter(|data| !data.is_empty())
+            .map(|data| AppendedData {
+                encoding: Encoding::Raw,
+                data: RawData(data),
+            })
+    }
+}
+
+/// Encodes the contents of an inline binary `DataArray` given the bytes produced by
+/// `IOBuffer::into_bytes_with_size`.
+///
+/// As in VTK, the header of compressed data is encoded separately from the data.
+fn encode_inline_binary(bytes: &[u8], ei: EncodingInfo) -> String {
+    if ei.compressor == Compressor::None {
+        return base64::encode(bytes);
+    }
+    let header_size = CompressionHeader::read_num_blocks(bytes, ei)
+        .and_then(|num_blocks| CompressionHeader::size(num_blocks, ei))
+        .unwrap_or(0)
+        .min(bytes.len());
+    let (header, data) = bytes.split_at(header_size);
+    let mut out = base64::encode(header);
+    base64::encode_config_buf(data, base64::STANDARD, &mut out);
+    out
+}
+
+/// Decodes the base64 encoded contents of an inline binary `DataArray`.
+///
+/// The decoded data is prefixed by a header specifying the number of bytes that follow, or a
+/// `CompressionHeader` if the data is compressed. Writers like VTK may encode this header
+/// separately from the data, in which case the encoded header is padded on its own. Whitespace
+/// in the encoded data is ignored.
+///
+/// Returns the decompressed data following the header.
+fn decode_inline_binary(
+    data: &[Data],
+    ei: EncodingInfo,
+) -> std::result::Result<Vec<u8>, ValidationError> {
+    let encoded: Vec<u8> = data
+        .iter()
+        .filter_map(|d| match d {
+            Data::Data(s) => Some(s.bytes()),
+            Data::Meta { .. } => None,
+        })
+        .flatten()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+
+    if encoded.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut bytes = Vec::new();
+    decode_base64_chunks(&encoded, &mut bytes)?;
+
+    if ei.compressor != Compressor::None {
+        let header = CompressionHeader::read(&bytes, ei)?;
+        return header.decompress(&bytes[header.size..], ei.compressor);
+    }
+
+    let header_bytes = ei.header_type.size();
+    if bytes.len() < header_bytes {
+        return Err(ValidationError::InvalidDataFormat);
+    }
+    let num_bytes = read_header_num(&mut std::io::Cursor::new(&bytes[..header_bytes]), ei)?;
+    if bytes.len() - header_bytes < num_bytes {
+        return Err(ValidationError::InvalidDataFormat);
+    }
+    bytes.truncate(header_bytes + num_bytes);
+    bytes.drain(..header_bytes);
+    Ok(bytes)
+}
+
+/// The header preceding compressed binary data.
+///
+/// Compressed data is organized as `[nb][nu][np][nc_1]...[nc_nb][Data]` where
+///
+///  - `[nb]` = Number of blocks in the data array
+///  - `[nu]` = Block size before compression
+///  - `[np]` = Size of the last partial block before compression (zero if it is not needed)
+///  - `[nc_i]` = Size in bytes of block i after compression
+///
+/// Each block is compressed independently. See <https://vtk.org/Wiki/VTK_XML_Formats> for details.
+#[derive(Clone, Debug, PartialEq)]
+struct CompressionHeader {
+    /// Size of the header in bytes.
+    size: usize,
+    block_size: usize,
+    last_block_size: usize,
+    compressed_block_sizes: Vec<usize>,
+}
+
+impl CompressionHeader {
+    /// Reads the number of blocks from the beginning of a compression header.
+    fn read_num_blocks(
+        bytes: &[u8],
+        ei: EncodingInfo,
+    ) -> std::result::Result<usize, ValidationError> {
+        read_header_num(&mut std::io::Cursor::new(bytes), ei)
+    }
+
+    /// Computes the size in bytes of a header describing the given number of blocks.
+    fn size(num_blocks: usize, ei: EncodingInfo) -> std::result::Result<usize, ValidationError> {
+        num_blocks
+            .checked_add(3)
+            .and_then(|n| n.checked_mul(ei.header_type.size()))
+            .ok_or(ValidationError::InvalidDataFormat)
+    }
+
+    /// Reads a complete compression header from the beginning of `bytes`.
+    fn read(bytes: &[u8], ei: EncodingInfo) -> std::result::Result<Self, ValidationError> {
+        let num_blocks = Self::read_num_blocks(bytes, ei)?;
+        let size = Self::size(num_blocks, ei)?;
+        if bytes.len() < size {
+            return Err(ValidationError::InvalidDataFormat);
+        }
+        let mut cursor = std::io::Cursor::new(&bytes[ei.header_type.size()..size]);
+        let block_size = read_header_num(&mut cursor, ei)?;
+        let last_block_size = read_header_num(&mut cursor, ei)?;
+        let compressed_block_sizes = (0..num_blocks)
+            .map(|_| read_header_num(&mut cursor, ei))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(CompressionHeader {
+            size,
+            block_size,
+            last_block_size,
+            compressed_block_sizes,
+        })
+    }
+
+    /// Total number of compressed bytes following the header.
+    fn num_compressed_bytes(&self) -> usize {
+        self.compressed_block_sizes.iter().sum()
+    }
+
+    /// Decompresses the blocks stored in `data`, which immediately follows the header.
+    fn decompress(
+        &self,
+        data: &[u8],
+        compressor: Compressor,
+    ) -> std::result::Result<Vec<u8>, ValidationError> {
+        let num_blocks = self.compressed_block_sizes.len();
+        let mut out = Vec::new();
+        let mut rest = data;
+        for (i, &num_compressed_bytes) in self.compressed_block_sizes.iter().enumerate() {
+            if rest.len() < num_compressed_bytes {
+                return Err(ValidationError::InvalidDataFormat);
+            }
+            let (block, tail) = rest.split_at(num_compressed_bytes);
+            let num_bytes = if i + 1 == num_blocks && self.last_block_size > 0 {
+                self.last_block_size
+            } else {
+                self.block_size
+            };
+            decompress_block(block, num_bytes, compressor, &mut out)?;
+            rest = tail;
+        }
+        Ok(out)
+    }
+}
+
+/// Decompresses a single block of data expected to contain `num_bytes` bytes when uncompressed.
+// Allow this warning which is fired when compression is disabled.
+#[allow(unused_variables)]
+fn decompress_block(
+    block: &[u8],
+    num_bytes: usize,
+    compressor: Compressor,
+    out: &mut Vec<u8>,
+) -> std::result::Result<(), ValidationError> {
+    match compressor {
+        Compressor::ZLib => {
+            #[cfg(not(feature = "flate2"))]
+            {
+                return Err(ValidationError::MissingCompressionLibrary(compressor));
+            }
+            #[cfg(feature = "flate2")]
+            {
+                use std::io::Read;
+                flate2::read::ZlibDecoder::new(block).read_to_end(out)?;
+            }
+        }
+        Compressor::LZ4 => {
+            #[cfg(not(feature = "lz4"))]
+            {
+                return Err(ValidationError::MissingCompressionLibrary(compressor));
+            }
+            #[cfg(feature = "lz4")]
+            {
+                out.extend_from_slice(&lz4::decompress(block, num_bytes)?);
+            }
+        }
+        Compressor::LZMA => {
+            #[cfg(not(feature = "xz2"))]
+            {
+                return Err(ValidationError::MissingCompressionLibrary(compressor));
+            }
+            #[cfg(feature = "xz2")]
+            {
+                use std::io::Read;
+                xz2::read::XzDecoder::new(block).read_to_end(out)?;
+            }
+        }
+        Compressor::Zstd => {
+            #[cfg(not(feature = "zstd"))]
+            {
+                return Err(ValidationError::MissingCompressionLibrary(compressor));
+            }
+            #[cfg(feature = "zstd")]
+            {
+                use std::io::Read;
+                zstd::stream::read::Decoder::new(block)?.read_to_end(out)?;
+            }
+        }
+        Compressor::None => out.extend_from_slice(block),
+    }
+    Ok(())
+}
+
 impl AppendedData {
+    /// Constructs appended data from the contents following the leading underscore.
+    pub(crate) fn new(encoding: Encoding, data: Vec<u8>) -> AppendedData {
+        AppendedData {
+            encoding,
+            data: RawData(data),
+        }
+    }
+
     /// Extract the decompressed and unencoded raw bytes from appended data.
     ///
     /// The data is expected to begin at `offset` from the beginning of the stored data array.
@@ -2275,161 +3012,46 @@ impl AppendedData {
             };
         }
 
-        // Compressed data has a more complex header.
-        // The data is organized as [nb][nu][np][nc_1]...[nc_nb][Data]
-        // Where
-        //   [nb] = Number of blocks in the data array
-        //   [nu] = Block size before compression
-        //   [np] = Size of the last partial block before compression (zero if it is not needed)
-        //   [nc_i] = Size in bytes of block i after compression
-        // See https://vtk.org/Wiki/VTK_XML_Formats for details.
+        // Compressed data is preceded by a more complex header describing each compressed block.
         // In this case we dont know how many bytes are in the data array so we must first read
-        // this information from a header.
-
-        // Helper function to read a single header number, which depends on the encoding parameters.
-        fn read_header_num<R: AsRef<[u8]>>(
-            header_buf: &mut std::io::Cursor<R>,
-            ei: EncodingInfo,
-        ) -> std::result::Result<usize, ValidationError> {
-            use byteorder::ReadBytesExt;
-            use byteorder::{BE, LE};
-            Ok(match ei.byte_order {
-                model::ByteOrder::LittleEndian => {
-                    if ei.header_type == ScalarType::UInt64 {
-                        header_buf.read_u64::<LE>()? as usize
-                    } else {
-                        header_buf.read_u32::<LE>()? as usize
-                    }
-                }
-                model::ByteOrder::BigEndian => {
-                    if ei.header_type == ScalarType::UInt64 {
-                        header_buf.read_u64::<BE>()? as usize
-                    } else {
-                        header_buf.read_u32::<BE>()? as usize
-                    }
-                }
-            })
-        }
-
-        // Allow this warning which are fired when compression is disabled.
-        #[allow(unused_variables)]
-        fn get_data_slice<'a, D, B>(
-            buf: &'a mut Vec<u8>,
-            mut decode: D,
-            mut to_b64: B,
-            data: &'a [u8],
-            header_bytes: usize,
-            ei: EncodingInfo,
-        ) -> std::result::Result<Vec<u8>, ValidationError>
-        where
-            D: for<'b> FnMut(
-                &'b [u8],
-                &'b mut Vec<u8>,
-            ) -> std::result::Result<&'b [u8], ValidationError>,
-            B: FnMut(usize) -> usize,
-        {
-            use std::io::Cursor;
-
-            // First we need to determine the number of blocks stored.
-            let num_blocks = {
-                let encoded_header = &data[0..to_b64(header_bytes)];
-                let decoded_header = decode(encoded_header, buf)?;
-                read_header_num(&mut Cursor::new(decoded_header), ei)?
-            };
-
-            let full_header_bytes = header_bytes * (3 + num_blocks); // nb + nu + np + sum_i nc_i
-            buf.clear();
-
-            let encoded_header = &data[0..to_b64(full_header_bytes)];
-            let decoded_header = decode(encoded_header, buf)?;
-            let mut header_cursor = Cursor::new(decoded_header);
-            let _nb = read_header_num(&mut header_cursor, ei); // We already know the number of blocks
-            let _nu = read_header_num(&mut header_cursor, ei);
-            let _np = read_header_num(&mut header_cursor, ei);
-            let nc_total = (0..num_blocks).fold(0, |acc, _| {
-                acc + read_header_num(&mut header_cursor, ei).unwrap_or(0)
-            });
-            let num_data_bytes = to_b64(nc_total);
-            let start = to_b64(full_header_bytes);
-            buf.clear();
-            let encoded_data = &data[start..start + num_data_bytes];
-            let decoded_data = decode(encoded_data, buf)?;
-
-            // Now that the data is decoded, what is left is to decompress it.
-            match ei.compressor {
-                Compressor::ZLib => {
-                    #[cfg(not(feature = "flate2"))]
-                    {
-                        return Err(ValidationError::MissingCompressionLibrary(ei.compressor));
-                    }
-                    #[cfg(feature = "flate2")]
-                    {
-                        use std::io::Read;
-                        let mut out = Vec::new();
-                        let mut decoder = flate2::read::ZlibDecoder::new(decoded_data);
-                        decoder.read_to_end(&mut out)?;
-                        Ok(out)
-                    }
-                }
-                Compressor::LZ4 => {
-                    #[cfg(not(feature = "lz4"))]
-                    {
-                        return Err(ValidationError::MissingCompressionLibrary(ei.compressor));
-                    }
-                    #[cfg(feature = "lz4")]
-                    {
-                        Ok(lz4::decompress(decoded_data, num_data_bytes)?)
-                    }
-                }
-                Compressor::LZMA => {
-                    #[cfg(not(feature = "xz2"))]
-                    {
-                        return Err(ValidationError::MissingCompressionLibrary(ei.compressor));
-                    }
-                    #[cfg(feature = "xz2")]
-                    {
-                        use std::io::Read;
-                        let mut out = Vec::new();
-                        let mut decoder = xz2::read::XzDecoder::new(decoded_data);
-                        decoder.read_to_end(&mut out)?;
-                        Ok(out)
-                    }
-                }
-                _ => {
-                    unreachable!()
-                }
-            }
-        }
-
+        // this information from the header.
+        let data = self
+            .data
+            .0
+            .get(offset..)
+            .ok_or(ValidationError::MissingReferencedAppendedData)?;
         let out = match self.encoding {
             Encoding::Raw => {
-                let mut buf = Vec::new();
-                get_data_slice(
-                    &mut buf,
-                    |header, _| Ok(header),
-                    |x| x,
-                    &self.data.0[offset..],
-                    header_bytes,
-                    ei,
-                )?
+                let header = CompressionHeader::read(data, ei)?;
+                header.decompress(&data[header.size..], ei.compressor)?
             }
             Encoding::Base64 => {
+                // The header is encoded separately from the data, so we first decode the number of
+                // blocks to determine the size of the encoded header.
                 let mut buf = Vec::new();
-                get_data_slice(
-                    &mut buf,
-                    |header, buf| {
-                        base64::decode_config_buf(
-                            header,
-                            base64::STANDARD.decode_allow_trailing_bits(true),
-                            buf,
-                        )?;
-                        Ok(buf.as_slice())
-                    },
-                    to_b64,
-                    &self.data.0[offset..],
-                    header_bytes,
-                    ei,
-                )?
+                let encoded_num_blocks = data
+                    .get(..to_b64(header_bytes))
+                    .ok_or(ValidationError::InvalidDataFormat)?;
+                decode_base64_chunks(encoded_num_blocks, &mut buf)?;
+                let num_blocks = CompressionHeader::read_num_blocks(&buf, ei)?;
+                let encoded_header_size = to_b64(CompressionHeader::size(num_blocks, ei)?);
+
+                buf.clear();
+                let encoded_header = data
+                    .get(..encoded_header_size)
+                    .ok_or(ValidationError::InvalidDataFormat)?;
+                decode_base64_chunks(encoded_header, &mut buf)?;
+                let header = CompressionHeader::read(&buf, ei)?;
+
+                buf.clear();
+                let encoded_data = data
+                    .get(
+                        encoded_header_size
+                            ..encoded_header_size + to_b64(header.num_compressed_bytes()),
+                    )
+                    .ok_or(ValidationError::InvalidDataFormat)?;
+                decode_base64_chunks(encoded_data, &mut buf)?;
+                header.decompress(&buf, ei.compressor)?
             }
         };
         Ok(model::IOBuffer::from_byte_vec(
@@ -2441,7 +3063,7 @@ impl AppendedData {
 }
 
 /// A file type descriptor of a XML VTK data file.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FileType {
     storage: StorageFormat,
     data: DataType,
@@ -2560,7 +3182,7 @@ impl From<DataSetType> for FileType {
 }
 
 /// The storage format of a given XML VTK file.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum StorageFormat {
     Parallel,
     Serial,
@@ -2569,7 +3191,7 @@ pub enum StorageFormat {
 /// A data type representing particular structured or unstructured data.
 ///
 /// Each of these can be stored either in Parallel or Serial format.
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DataType {
     ImageData,
     PolyData,
@@ -2612,6 +3234,7 @@ impl DataType {
  */
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ValidationError {
     MissingDataSet,
     DataSetMismatch,
@@ -2621,7 +3244,7 @@ pub enum ValidationError {
     ParseFloat(std::num::ParseFloatError),
     ParseInt(std::num::ParseIntError),
     InvalidCellType(u8),
-    TooManyElements(u32),
+    TooManyElements(u64),
     UnexpectedBytesInAppendedData(u64, u64),
     MissingTopologyOffsets,
     MissingReferencedAppendedData,
@@ -2637,6 +3260,7 @@ pub enum ValidationError {
     #[cfg(feature = "lz4")]
     LZ4DecompressError(lz4::block::DecompressError),
     Unsupported,
+    LimitExceeded(crate::LimitExceeded),
 }
 
 #[cfg(feature = "lz4")]
@@ -2646,6 +3270,12 @@ impl From<lz4::block::DecompressError> for ValidationError {
     }
 }
 
+impl From<crate::LimitExceeded> for ValidationError {
+    fn from(e: crate::LimitExceeded) -> ValidationError {
+        ValidationError::LimitExceeded(e)
+    }
+}
+
 impl From<std::io::Error> for ValidationError {
     fn from(e: std::io::Error) -> ValidationError {
         ValidationError::IO(e)
@@ -2693,6 +3323,7 @@ impl std::error::Error for ValidationError {
             ValidationError::ParseInt(source) => Some(source),
             #[cfg(feature = "lz4")]
             ValidationError::LZ4DecompressError(source) => Some(source),
+            ValidationError::LimitExceeded(source) => Some(source),
             _ => None,
         }
     }
@@ -2748,6 +3379,7 @@ impl std::fmt::Display for ValidationError {
                 write!(f, "LZ4 deompression error: {}", source)
             }
             ValidationError::Unsupported => write!(f, "Unsupported data set format"),
+            ValidationError::LimitExceeded(source) => write!(f, "{}", source),
         }
     }
 }
@@ -2755,6 +3387,24 @@ impl std::fmt::Display for ValidationError {
 impl TryFrom<VTKFile> for model::Vtk {
     type Error = Error;
     fn try_from(xml: VTKFile) -> std::result::Result<model::Vtk, Self::Error> {
+        xml.into_model_with_options(ReadOptions::default(), &mut Vec::new())
+    }
+}
+
+impl VTKFile {
+    /// Converts this file into a `model::Vtk` using the given options.
+    ///
+    /// Attribute arrays of the wrong length are handled according to the
+    /// [`LengthPolicy`](crate::LengthPolicy) of the given options, and a warning is added to
+    /// `warnings` for each of them.
+    pub fn into_model_with_options(
+        self,
+        options: ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<model::Vtk> {
+        if options.has_allocation_limits() {
+            self.check_allocation_limits(&options)?;
+        }
         let VTKFile {
             version,
             byte_order,
@@ -2764,7 +3414,7 @@ impl TryFrom<VTKFile> for model::Vtk {
             appended_data,
             data_set,
             ..
-        } = xml;
+        } = self;
 
         let encoding_info = EncodingInfo {
             byte_order,
@@ -2781,15 +3431,56 @@ impl TryFrom<VTKFile> for model::Vtk {
             return Err(ValidationError::DataSetMismatch.into());
         }
 
-        // Convenience function to convert u32 to usize without panics.
-        let convert_num = |n: u32| -> std::result::Result<usize, ValidationError> {
+        // Convenience function to convert u64 to usize without panics.
+        let convert_num = |n: u64| -> std::result::Result<usize, ValidationError> {
             usize::try_from(n).map_err(|_| ValidationError::TooManyElements(n))
         };
 
+        let policy = options.length_policy();
+        let array_filter = options.array_filter();
+        let warnings = std::cell::RefCell::new(warnings);
         let attributes =
-            |npts, ncells, point_data: AttributeData, cell_data: AttributeData| model::Attributes {
-                point: point_data.into_model_attributes(npts, appended_data, encoding_info),
-                cell: cell_data.into_model_attributes(ncells, appended_data, encoding_info),
+            |npts, ncells, mut point_data: AttributeData, mut cell_data: AttributeData| {
+                // Drop unwanted arrays before they are decoded.
+                if let Some(names) = array_filter {
+                    point_data
+                        .data_array
+                        .retain(|array| names.contains(&array.name));
+                    cell_data
+                        .data_array
+                        .retain(|array| names.contains(&array.name));
+                }
+                let mut warnings = warnings.borrow_mut();
+                model::Attributes {
+                    point: point_data.into_model_attributes_with_policy(
+                        npts,
+                        appended_data,
+                        encoding_info,
+                        policy,
+                        &mut warnings,
+                    ),
+                    cell: cell_data.into_model_attributes_with_policy(
+                        ncells,
+                        appended_data,
+                        encoding_info,
+                        policy,
+                        &mut warnings,
+                    ),
+                    field: Vec::new(),
+                }
+            };
+
+        // Points may be omitted in files storing only attribute data (see `into_field_only`).
+        let model_points =
+            |npts, points: Option<Points>| -> std::result::Result<_, ValidationError> {
+                points
+                    .map(|points| {
+                        points
+                            .data
+                            .into_io_buffer(npts, appended_data, encoding_info)
+                    })
+                    .transpose()
+                    .map(Option::unwrap_or_default)
             };
 
         let data = match data_set {
@@ -2813,9 +3504,9 @@ impl TryFrom<VTKFile> for model::Vtk {
                              ..
                          }| {
                             let extent: model::Extent = extent.unwrap_or(whole_extent).into();
-                            let number_of_points = extent.num_points().try_into().unwrap();
-                            let number_of_cells = extent.num_cells().try_into().unwrap();
-                            model::Piece::Inline(Box::new(model::ImageDataPiece {
+                            let number_of_points = convert_num(extent.num_points())?;
+                            let number_of_cells = convert_num(extent.num_cells())?;
+                            Ok(model::Piece::Inline(Box::new(model::ImageDataPiece {
                                 extent,
                                 data: attributes(
                                     number_of_points,
@@ -2823,10 +3514,10 @@ impl TryFrom<VTKFile> for model::Vtk {
                                     point_data,
                                     cell_data,
                                 ),
-                            }))
+                            })))
                         },
                     )
-                    .collect(),
+                    .collect::<Result<Vec<model::Piece<model::ImageDataPiece>>>>()?,
             },
             DataSet::PolyData(Unstructured { pieces }) => model::DataSet::PolyData {
                 meta: None,
@@ -2894,11 +3585,7 @@ impl TryFrom<VTKFile> for model::Vtk {
                                 })
                                 .transpose()?;
                             Ok(model::Piece::Inline(Box::new(model::PolyDataPiece {
-                                points: points.unwrap().data.into_io_buffer(
-                                    number_of_points,
-                                    appended_data,
-                                    encoding_info,
-                                )?,
+                                points: model_points(number_of_points, points)?,
                                 verts,
                                 lines,
                                 polys,
@@ -2931,8 +3618,8 @@ impl TryFrom<VTKFile> for model::Vtk {
                              ..
                          }| {
                             let extent: model::Extent = extent.unwrap_or(whole_extent).into();
-                            let number_of_cells = extent.num_cells().try_into().unwrap();
-                            let number_of_points = extent.num_points().try_into().unwrap();
+                            let number_of_cells = convert_num(extent.num_cells())?;
+                            let number_of_points = convert_num(extent.num_points())?;
                             let [nx, ny, nz] = extent.clone().into_dims();
                             let coords =
                                 coordinates.ok_or_else(|| ValidationError::MissingCoordinates)?;
@@ -2974,15 +3661,11 @@ impl TryFrom<VTKFile> for model::Vtk {
                              ..
                          }| {
                             let extent: model::Extent = extent.unwrap_or(whole_extent).into();
-                            let number_of_points = extent.num_points().try_into().unwrap();
-                            let number_of_cells = extent.num_cells().try_into().unwrap();
+                            let number_of_points = convert_num(extent.num_points())?;
+                            let number_of_cells = convert_num(extent.num_cells())?;
                             Ok(model::Piece::Inline(Box::new(model::StructuredGridPiece {
                                 extent,
-                                points: points.unwrap().data.into_io_buffer(
-                                    number_of_points,
-                                    appended_data,
-                                    encoding_info,
-                                )?,
+                                points: model_points(number_of_points, points)?,
                                 data: attributes(
                                     number_of_points,
                                     number_of_cells,
@@ -3023,11 +3706,7 @@ impl TryFrom<VTKFile> for model::Vtk {
                                     .unwrap_or_default();
                                 Ok(model::Piece::Inline(Box::new(
                                     model::UnstructuredGridPiece {
-                                        points: points.unwrap().data.into_io_buffer(
-                                            number_of_points,
-                                            appended_data,
-                                            encoding_info,
-                                        )?,
+                                        points: model_points(number_of_points, points)?,
                                         cells,
                                         data: attributes(
                                             number_of_points,
@@ -3212,6 +3891,33 @@ impl model::Vtk {
         self,
         compressor: Compressor,
         compression_level: u32,
+    ) -> Result<VTKFile> {
+        self.into_xml(compressor, compression_level, false, false)
+    }
+
+    /// Converts the given Vtk model into an XML format with all binary data stored in a raw
+    /// `AppendedData` section.
+    ///
+    /// This is the same as [`try_into_xml_format`](Self::try_into_xml_format), except that each
+    /// data array refers to an offset into the appended data section, which is written as raw
+    /// binary after the XML body. This avoids the 33% size overhead of base64 encoding for large
+    /// arrays.
+    pub fn try_into_xml_appended(
+        self,
+        compressor: Compressor,
+        compression_level: u32,
+    ) -> Result<VTKFile> {
+        self.into_xml(compressor, compression_level, true, false)
+    }
+
+    /// Converts the model, storing binary data in a raw `AppendedData` section if `appended` is
+    /// set, and encoding arrays serially if `serial` is set.
+    pub(crate) fn into_xml(
+        self,
+        compressor: Compressor,
+        compression_level: u32,
+        appended: bool,
+        serial: bool,
     ) -> Result<VTKFile> {
         let model::Vtk {
             version,
@@ -3223,6 +3929,18 @@ impl model::Vtk {
 
         let source_path = file_path.as_ref().map(|p| p.as_ref());
 
+        // Make sure the requested compression is available before producing any data.
+        let compression_supported = match compressor {
+            Compressor::ZLib => cfg!(feature = "flate2"),
+            Compressor::LZ4 => cfg!(feature = "lz4"),
+            Compressor::LZMA => cfg!(feature = "xz2"),
+            Compressor::Zstd => cfg!(feature = "zstd"),
+            Compressor::None => true,
+        };
+        if !compression_supported {
+            return Err(ValidationError::MissingCompressionLibrary(compressor).into());
+        }
+
         let header_type = ScalarType::UInt64;
 
         let encoding_info = EncodingInfo {
@@ -3232,7 +3950,12 @@ impl model::Vtk {
             compression_level,
         };
 
-        let appended_data = Vec::new();
+        let mut enc = if appended {
+            ArrayEncoder::appended(encoding_info)
+        } else {
+            ArrayEncoder::inline(encoding_info)
+        };
+        enc.serial = serial;
 
         let data_set = match data_set {
             model::DataSet::ImageData {
@@ -3253,14 +3976,8 @@ impl model::Vtk {
                         let model::ImageDataPiece { extent, data } = piece_data;
                         Ok(Piece {
                             extent: Some(extent.into()),
-                            point_data: AttributeData::from_model_attributes(
-                                data.point,
-                                encoding_info,
-                            ),
-                            cell_data: AttributeData::from_model_attributes(
-                                data.cell,
-                                encoding_info,
-                            ),
+                            point_data: AttributeData::encode(data.point, &mut enc),
+                            cell_data: AttributeData::encode(data.cell, &mut enc),
                             ..Default::default()
                         })
                     })
@@ -3284,15 +4001,9 @@ impl model::Vtk {
                         } = piece_data;
                         Ok(Piece {
                             extent: Some(extent.into()),
-                            points: Some(Points::from_io_buffer(points, encoding_info)),
-                            point_data: AttributeData::from_model_attributes(
-                                data.point,
-                                encoding_info,
-                            ),
-                            cell_data: AttributeData::from_model_attributes(
-                                data.cell,
-                                encoding_info,
-                            ),
+                            points: Some(Points::encode(points, &mut enc)),
+                            point_data: AttributeData::encode(data.point, &mut enc),
+                            cell_data: AttributeData::encode(data.cell, &mut enc),
                             ..Default::default()
                         })
                     })
@@ -3316,18 +4027,9 @@ impl model::Vtk {
                         } = piece_data;
                         Ok(Piece {
                             extent: Some(extent.into()),
-                            coordinates: Some(Coordinates::from_model_coords(
-                                coords,
-                                encoding_info,
-                            )),
-                            point_data: AttributeData::from_model_attributes(
-                                data.point,
-                                encoding_info,
-                            ),
-                            cell_data: AttributeData::from_model_attributes(
-                                data.cell,
-                                encoding_info,
-                            ),
+                            coordinates: Some(Coordinates::encode(coords, &mut enc)),
+                            point_data: AttributeData::encode(data.point, &mut enc),
+                            cell_data: AttributeData::encode(data.cell, &mut enc),
                             ..Default::default()
                         })
                     })
@@ -3349,18 +4051,12 @@ impl model::Vtk {
                             data,
                         } = piece_data;
                         Ok(Piece {
-                            number_of_points: u32::try_from(num_points).unwrap(),
-                            number_of_cells: u32::try_from(cells.num_cells()).unwrap(),
-                            points: Some(Points::from_io_buffer(points, encoding_info)),
-                            cells: Some(Cells::from_model_cells(cells, encoding_info)),
-                            point_data: AttributeData::from_model_attributes(
-                                data.point,
-                                encoding_info,
-                            ),
-                            cell_data: AttributeData::from_model_attributes(
-                                data.cell,
-                                encoding_info,
-                            ),
+                            number_of_points: num_points as u64,
+                            number_of_cells: cells.num_cells() as u64,
+                            points: Some(Points::encode(points, &mut enc)),
+                            cells: Some(Cells::from_model_cells(cells, &mut enc)),
+                            point_data: AttributeData::encode(data.point, &mut enc),
+                            cell_data: AttributeData::encode(data.cell, &mut enc),
                             ..Default::default()
                         })
                     })
@@ -3389,30 +4085,24 @@ impl model::Vtk {
                             data,
                         } = piece_data;
 
-                        let verts = verts.map(|topo| Topo::from_model_topo(topo, encoding_info));
-                        let lines = lines.map(|topo| Topo::from_model_topo(topo, encoding_info));
-                        let polys = polys.map(|topo| Topo::from_model_topo(topo, encoding_info));
-                        let strips = strips.map(|topo| Topo::from_model_topo(topo, encoding_info));
+                        let verts = verts.map(|topo| Topo::from_model_topo(topo, &mut enc));
+                        let lines = lines.map(|topo| Topo::from_model_topo(topo, &mut enc));
+                        let polys = polys.map(|topo| Topo::from_model_topo(topo, &mut enc));
+                        let strips = strips.map(|topo| Topo::from_model_topo(topo, &mut enc));
 
                         Ok(Piece {
-                            number_of_points: u32::try_from(num_points).unwrap(),
-                            number_of_lines: u32::try_from(number_of_lines).unwrap(),
-                            number_of_verts: u32::try_from(number_of_verts).unwrap(),
-                            number_of_polys: u32::try_from(number_of_polys).unwrap(),
-                            number_of_strips: u32::try_from(number_of_strips).unwrap(),
-                            points: Some(Points::from_io_buffer(points, encoding_info)),
+                            number_of_points: num_points as u64,
+                            number_of_lines: number_of_lines as u64,
+                            number_of_verts: number_of_verts as u64,
+                            number_of_polys: number_of_polys as u64,
+                            number_of_strips: number_of_strips as u64,
+                            points: Some(Points::encode(points, &mut enc)),
                             verts,
                             lines,
                             polys,
                             strips,
-                            point_data: AttributeData::from_model_attributes(
-                                data.point,
-                                encoding_info,
-                            ),
-                            cell_data: AttributeData::from_model_attributes(
-                                data.cell,
-                                encoding_info,
-                            ),
+                            point_data: AttributeData::encode(data.point, &mut enc),
+                            cell_data: AttributeData::encode(data.cell, &mut enc),
                             ..Default::default()
                         })
                     })
@@ -3433,7 +4123,7 @@ impl model::Vtk {
                         .map(|data| Piece {
                             extent: Some(Extent([0, data.len() as i32, 0, 0, 0, 0])),
                             cell_data: AttributeData {
-                                data_array: vec![DataArray::from_field_array(data, encoding_info)],
+                                data_array: vec![DataArray::encode_field_array(data, &mut enc)],
                                 ..Default::default()
                             },
                             ..Default::default()
@@ -3444,14 +4134,7 @@ impl model::Vtk {
         };
 
         let data_set_type = DataSetType::from(&data_set);
-        let appended_data = if appended_data.is_empty() {
-            None
-        } else {
-            Some(AppendedData {
-                encoding: Encoding::Raw,
-                data: RawData(appended_data),
-            })
-        };
+        let appended_data = enc.finish();
 
         Ok(VTKFile {
             data_set_type,
@@ -3479,14 +4162,100 @@ pub(crate) fn import(file_path: impl AsRef<Path>) -> Result<VTKFile> {
 }
 
 fn de_from_reader(reader: impl BufRead) -> Result<VTKFile> {
-    let mut reader = quick_xml::Reader::from_reader(reader);
+    // Raw appended data may contain arbitrary bytes, which cannot be parsed as XML text, so we
+    // read it separately from the rest of the file.
+    let (bytes, raw_appended_data) = split_raw_appended_data(reader)?;
+
+    let mut reader = quick_xml::Reader::from_reader(bytes.as_slice());
     reader
         .expand_empty_elements(true)
         .check_end_names(true)
         .trim_text(true)
         .trim_text_end(false);
     let mut de = de::Deserializer::new(reader);
-    Ok(VTKFile::deserialize(&mut de)?)
+    let mut vtk = VTKFile::deserialize(&mut de)?;
+
+    if let (Some(raw), Some(appended)) = (raw_appended_data, vtk.appended_data.as_mut()) {
+        appended.data = RawData(raw);
+    }
+    Ok(vtk)
+}
+
+/// A reader keeping a copy of all bytes read through it.
+struct CopyingReader<R> {
+    inner: R,
+    copy: Vec<u8>,
+}
+
+impl<R: Read> Read for CopyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.copy.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reads an XML file, separating the contents of a raw `AppendedData` element from the rest.
+///
+/// The file is read as XML up to the start tag of a raw `AppendedData` element. The remaining
+/// bytes are read as is, and the raw data following the leading underscore is cut out up to the
+/// closing tag.
+///
+/// Returns the file without the raw data, and the raw data if there is any.
+fn split_raw_appended_data(reader: impl Read) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    const CLOSING_TAG: &[u8] = b"</AppendedData>";
+
+    let mut xml = quick_xml::Reader::from_reader(std::io::BufReader::new(CopyingReader {
+        inner: reader,
+        copy: Vec::new(),
+    }));
+    let mut buf = Vec::new();
+    loop {
+        match xml.read_event(&mut buf)? {
+            quick_xml::events::Event::Start(tag) if tag.name() == b"AppendedData" => {
+                let is_raw = tag
+                    .attributes()
+                    .filter_map(std::result::Result::ok)
+                    .any(|attr| attr.key == b"encoding" && attr.value.as_ref() == b"raw");
+                if is_raw {
+                    break;
+                }
+            }
+            quick_xml::events::Event::Eof => {
+                let CopyingReader { copy, .. } = xml.into_underlying_reader().into_inner();
+                return Ok((copy, None));
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    // The copy may extend past the start tag, since reads are buffered.
+    let content_start = xml.buffer_position();
+    let CopyingReader {
+        mut inner,
+        copy: mut bytes,
+    } = xml.into_underlying_reader().into_inner();
+    let mut rest = bytes.split_off(content_start);
+    inner.read_to_end(&mut rest)?;
+
+    // Search from the end since the raw data itself may contain the closing tag.
+    let end = rest
+        .windows(CLOSING_TAG.len())
+        .rposition(|w| w == CLOSING_TAG);
+    let underscore = end.and_then(|end| rest[..end].iter().position(|&b| b == b'_'));
+    match (underscore, end) {
+        (Some(underscore), Some(end)) => {
+            bytes.extend_from_slice(&rest[end..]);
+            rest.truncate(end);
+            rest.drain(..=underscore);
+            Ok((bytes, Some(rest)))
+        }
+        _ => {
+            bytes.append(&mut rest);
+            Ok((bytes, None))
+        }
+    }
 }
 
 /// Parse an XML VTK file from the given reader.
@@ -3505,7 +4274,11 @@ pub(crate) async fn import_async(file_path: impl AsRef<Path>) -> Result<VTKFile>
 /// Export an XML VTK file to the specified path.
 pub(crate) fn export(vtk: &VTKFile, file_path: impl AsRef<Path>) -> Result<()> {
     let f = std::fs::File::create(file_path)?;
-    write(vtk, std::io::BufWriter::new(f))
+    let mut writer = std::io::BufWriter::new(f);
+    write(vtk, &mut writer)?;
+    // Flush explicitly, since errors are discarded when a `BufWriter` is dropped.
+    writer.flush()?;
+    Ok(())
 }
 
 /// Write an XML VTK file to the specified writer.
@@ -3522,6 +4295,7 @@ impl std::fmt::Display for VTKFile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::convert::TryInto;
 
     // Verify that xmls with default empty meshes files work
     #[test]
@@ -3648,6 +4422,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn large_piece_counts() -> Result<()> {
+        // Counts are not limited to 32 bits.
+        let piece = r#"<Piece NumberOfPoints="4294967296" NumberOfCells="5000000000"></Piece>"#;
+        let piece: Piece = de::from_str(piece)?;
+        assert_eq!(piece.number_of_points, 1 << 32);
+        assert_eq!(piece.number_of_cells, 5_000_000_000);
+        let as_str = se::to_string(&piece)?;
+        assert!(as_str.contains(r#"NumberOfCells="5000000000""#));
+        Ok(())
+    }
+
     #[test]
     fn rectilinear_grid_ascii() -> Result<()> {
         let vtk = import("assets/RectilinearGrid_ascii.vtr")?;
@@ -3692,6 +4478,21 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn appended_raw_binary_after_comment() -> Result<()> {
+        use model::*;
+        let vtk: Vtk = import("assets/hexahedron_ascii.vtu")?.try_into()?;
+        let xml = vtk.clone().try_into_xml_appended(Compressor::None, 0)?;
+        let as_bytes = se::to_bytes(&xml)?;
+        // A comment mentioning the appended data must not be mistaken for the element.
+        let body = as_bytes.iter().position(|&b| b == b'>').unwrap() + 1;
+        let mut commented = as_bytes[..body].to_vec();
+        commented.extend_from_slice(b"<!-- <AppendedData encoding=\"raw\">_ -->");
+        commented.extend_from_slice(&as_bytes[body..]);
+        assert_eq!(de_from_reader(commented.as_slice())?, xml);
+        Ok(())
+    }
+
     #[test]
     fn rectilinear_grid_inline_binary() -> Result<()> {
         let vtk = import("assets/RectilinearGridInlineBinary.vtr")?;
@@ -3824,6 +4625,17 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn data_array_appended_64bit_offset() -> Result<()> {
+        let appended_xml = "<DataArray type=\"Float64\" Name=\"scalars\" \
+                            format=\"appended\" offset=\"6000000000\"/>";
+        let appended: DataArray = de::from_str(appended_xml)?;
+        assert_eq!(appended.offset, Some(6_000_000_000));
+        let appended_round_trip: DataArray = de::from_str(&se::to_string(&appended)?)?;
+        assert_eq!(appended_round_trip, appended);
+        Ok(())
+    }
+
     #[test]
     fn data_array_binary() -> Result<()> {
         let binary_xml = "<DataArray type=\"Float32\" Name=\"scalars\" format=\"binary\"> \
@@ -3911,7 +4723,8 @@ mod tests {
                             Attribute::generic("X Velocity", 1).with_data(vec![0.0f32, 0.0, 1.4]),
                             Attribute::generic("Y Velocity", 1).with_data(vec![0.0f32, 0.0, 1.0]),
                             Attribute::generic("Z Velocity", 1).with_data(vec![0.0f32, 0.5, 0.0]),
-                        ]
+                        ],
+                        field: Vec::new(),
                     }
                 }),
                 file_path: None,
@@ -3920,6 +4733,139 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn appended_raw_conversion_round_trip() -> Result<()> {
+        use model::*;
+        let vtk: Vtk = import("assets/hexahedron_ascii.vtu")?.try_into()?;
+        let xml = vtk.clone().try_into_xml_appended(Compressor::None, 0)?;
+        assert!(xml.appended_data.is_some());
+        let converted: Vtk = VTKFile::try_from(vtk.clone())?
+            .into_appended_raw()?
+            .try_into()?;
+        assert_eq!(vtk, converted);
+        let as_bytes = se::to_bytes(&xml)?;
+        let xml_round_trip = de_from_reader(as_bytes.as_slice())?;
+        assert_eq!(xml, xml_round_trip);
+        let vtk_round_trip: Vtk = xml_round_trip.try_into()?;
+        assert_eq!(vtk, vtk_round_trip);
+        Ok(())
+    }
+
+    #[test]
+    fn inline_binary_round_trip() -> Result<()> {
+        let ei = EncodingInfo {
+            byte_order: model::ByteOrder::LittleEndian,
+            header_type: ScalarType::UInt64,
+            compressor: Compressor::None,
+            compression_level: 0,
+        };
+        let buf = model::IOBuffer::from(vec![1.0f32, 2.0, 3.0]);
+        let data_array = DataArray::from_io_buffer(buf.clone(), ei);
+        assert_eq!(data_array.format, DataArrayFormat::Binary);
+        assert_eq!(data_array.into_io_buffer(3, None, ei)?, buf);
+        Ok(())
+    }
+
+    #[test]
+    fn inline_binary_separate_header() -> Result<()> {
+        // VTK may encode the header separately from the data and break lines in the output.
+        let ei = EncodingInfo {
+            byte_order: model::ByteOrder::LittleEndian,
+            header_type: ScalarType::UInt32,
+            compressor: Compressor::None,
+            compression_level: 0,
+        };
+        let values = [1.0f32, 2.0, 3.0];
+        let header = base64::encode(12u32.to_le_bytes());
+        assert_eq!(header, "DAAAAA==");
+        let data = base64::encode(bytemuck::cast_slice::<f32, u8>(&values));
+        let data_array = DataArray {
+            scalar_type: ScalarType::Float32,
+            format: DataArrayFormat::Binary,
+            data: vec![Data::Data(format!("\n  {}{}\n  ", header, data))],
+            ..Default::default()
+        };
+        assert_eq!(
+            data_array.into_io_buffer(3, None, ei)?,
+            model::IOBuffer::from(values.to_vec())
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn inline_binary_zlib() -> Result<()> {
+        // Compressed data with the header encoded separately as written by VTK.
+        let ei = EncodingInfo {
+            byte_order: model::ByteOrder::LittleEndian,
+            header_type: ScalarType::UInt32,
+            compressor: Compressor::ZLib,
+            compression_level: 0,
+        };
+        let data_array = DataArray {
+            scalar_type: ScalarType::Float32,
+            format: DataArrayFormat::Binary,
+            data: vec![Data::Data(String::from(
+                "AQAAAACAAAAQAAAAFQAAAA==eJxjYGiwZ2BgcAAiIG5wAAAQgwJA",
+            ))],
+            ..Default::default()
+        };
+        assert_eq!(
+            data_array.into_io_buffer(4, None, ei)?,
+            model::IOBuffer::from(vec![1.0f32, 2.0, 3.0, 4.0])
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_round_trip() -> Result<()> {
+        use model::*;
+        // Large enough to span multiple compression blocks.
+        let points: Vec<f64> = (0..30_000).map(|i| i as f64).collect();
+        let vtk = Vtk {
+            version: Version::new((1, 0)),
+            byte_order: ByteOrder::LittleEndian,
+            title: String::new(),
+            file_path: None,
+            data: DataSet::inline(PolyDataPiece {
+                points: points.into(),
+                verts: Some(VertexNumbers::XML {
+                    connectivity: (0..10_000).collect(),
+                    offsets: (1..=10_000).collect(),
+                }),
+                ..Default::default()
+            }),
+        };
+        let mut compressors = vec![
+            Compressor::None,
+            Compressor::ZLib,
+            Compressor::LZ4,
+            Compressor::LZMA,
+        ];
+        if cfg!(feature = "zstd") {
+            compressors.push(Compressor::Zstd);
+        }
+        for compressor in compressors {
+            let xml = vtk.clone().try_into_xml_format(compressor, 5)?;
+            assert_eq!(xml.compressor, compressor);
+
+            let as_bytes = se::to_bytes(&xml)?;
+            let vtk_round_trip: Vtk = de_from_reader(as_bytes.as_slice())?.try_into()?;
+            assert_eq!(vtk_round_trip, vtk);
+
+            let appended = vtk.clone().try_into_xml_appended(compressor, 5)?;
+            let as_bytes = se::to_bytes(&appended)?;
+            let vtk_round_trip: Vtk = de_from_reader(as_bytes.as_slice())?.try_into()?;
+            assert_eq!(vtk_round_trip, vtk);
+
+            let appended = se::to_bytes(&xml.into_appended_raw()?)?;
+            let vtk_round_trip: Vtk = de_from_reader(appended.as_slice())?.try_into()?;
+            assert_eq!(vtk_round_trip, vtk);
+        }
+        Ok(())
+    }
+
     #[test]
     fn vtk_xml_conversion_round_trip() -> Result<()> {
         use model::*;
@@ -2,7 +2,7 @@ use nom::IResult;
 use vtkio::model::*;
 use vtkio::parser::*;
 use vtkio::writer::*;
-use vtkio::Error;
+use vtkio::{Error, FormatFeature, ReadOptions, Warning};
 
 macro_rules! test {
     ($fn:ident ($in:expr, $($args:expr),*) => ($rem:expr, $out:expr)) => {
@@ -89,6 +89,7 @@ fn para_tet_test() -> Result {
                 point: vec![],
                 cell: vec![Attribute::field("FieldData")
                     .add_field_data(FieldArray::new("FloatValue", 1).with_data(vec![0.0f32]))],
+                field: Vec::new(),
             },
         }),
     };
@@ -162,6 +163,7 @@ fn para_tets_test() -> Result {
                 cell: vec![Attribute::field("FieldData")
                     .add_field_data(FieldArray::new("Ones", 1).with_data(vec![1.0f32; 3]))
                     .add_field_data(FieldArray::new("Zeros", 1).with_data(vec![0.0f32; 3]))],
+                field: Vec::new(),
             },
         }),
     };
@@ -268,6 +270,7 @@ fn tri_attrib_ascii_test() -> Result {
                         data: vec![1.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
                     }),
                 ],
+                field: Vec::new(),
             },
             ..Default::default()
         }),
@@ -276,6 +279,19 @@ fn tri_attrib_ascii_test() -> Result {
     test_b!(parse_ne(String::new().write_vtk_ne(out1.clone())?.as_bytes()) => ne(&out1));
     // Color scalars are floats only in the ASCII vtk file format. Thus we have another test for
     // The same file but in binary in tri_attrib_binary_test.
+
+    // Writing in binary converts color scalars to unsigned chars.
+    let binary = Vec::<u8>::new().write_vtk_ne(out1.clone())?.clone();
+    match Vtk::parse_legacy_be(binary.as_slice())?.data {
+        DataSet::PolyData { pieces, .. } => {
+            let piece = pieces[0].load_piece_data(None)?;
+            assert_eq!(
+                piece.data.cell[0].data_array().unwrap().data,
+                vec![255u8, 0, 0].into()
+            );
+        }
+        _ => panic!("expected poly data"),
+    }
     Ok(())
 }
 
@@ -312,14 +328,32 @@ fn tri_attrib_binary_test() -> Result {
                         data: vec![1.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
                     }),
                 ],
+                field: Vec::new(),
             },
             ..Default::default()
         }),
     };
     test_ignore_rem!(parse_be(in1) => out1);
-    //test_b!(parse_ne(Vec::<u8>::new().write_vtk_ne(out1.clone())?) => out1);
+    test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
     // Color scalars are u8 only in the Binary vtk file format. ASCII style color scalars are
     // stored as floats and are tested in tri_attrib_ascii_test.
+
+    // Writing in ASCII converts color scalars to floats, which is how they are also read from
+    // binary files when requested.
+    let mut out_float = out1.clone();
+    if let DataSet::PolyData { pieces, .. } = &mut out_float.data {
+        if let Piece::Inline(piece) = &mut pieces[0] {
+            if let Attribute::DataArray(array) = &mut piece.data.cell[0] {
+                array.data = vec![1.0f32, 0.0, 0.0].into();
+            }
+        }
+    }
+    test_b!(parse_be(String::new().write_vtk_be(out1.clone())?.as_bytes()) => out_float);
+    let options = ReadOptions::default().with_float_color_scalars(true);
+    assert_eq!(
+        Vtk::parse_legacy_be_with_options(&in1[..], options)?,
+        out_float
+    );
     Ok(())
 }
 
@@ -431,6 +465,7 @@ fn structured_grid_test() -> Result {
                         data: vec![0.6f32, 0.7, 0.5].into(),
                     }),
                 ],
+                field: Vec::new(),
             },
         }),
     };
@@ -468,6 +503,7 @@ fn rectilinear_grid_test() -> Result {
                         data: vec![1.1_f32, 7.5, 1.2, 1.5, 2.6, 8.1].into(),
                     }],
                 }],
+                field: Vec::new(),
             },
         }),
     };
@@ -584,6 +620,7 @@ fn cube_complex_test() -> Result {
                 ],
             },
         ],
+        field: Vec::new(),
     };
     let points: IOBuffer = vec![
         0.0, 0.0, 0.0, 1.0, 0.0, 0.0f32, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0,
@@ -673,6 +710,56 @@ fn cube_complex_test() -> Result {
     Ok(())
 }
 
+#[test]
+fn binary_lookup_table_test() -> Result {
+    let table = |data: IOBuffer| Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Lookup table"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![0.0f32; 6].into(),
+            verts: Some(VertexNumbers::Legacy {
+                num_cells: 2,
+                vertices: vec![1, 0, 1, 1],
+            }),
+            data: Attributes {
+                point: vec![
+                    Attribute::scalars_with_lookup("scalars", 1, "table")
+                        .with_data(vec![0.0f32, 1.0]),
+                    Attribute::lookup_table("table").with_data(data),
+                ],
+                cell: vec![],
+                field: Vec::new(),
+            },
+            ..Default::default()
+        }),
+    };
+
+    // Each entry is written as four unsigned chars.
+    let mut out = Vec::new();
+    out.write_vtk_be(table(vec![0.0f32, 0.5, 1.0, 1.0].into()))?;
+    let header = b"LOOKUP_TABLE table 1\n";
+    let start = out
+        .windows(header.len())
+        .position(|w| w == header)
+        .expect("missing lookup table")
+        + header.len();
+    assert_eq!(&out[start..start + 4], &[0u8, 128, 255, 255]);
+    // The entries are read back as floats.
+    let entries: Vec<f32> = [0u8, 128, 255, 255]
+        .iter()
+        .map(|&c| c as f32 / 255.0)
+        .collect();
+    test_b!(parse_be(&out) => table(entries.into()));
+
+    // Tables must have four components per entry.
+    assert!(Vec::<u8>::new()
+        .write_vtk_be(table(vec![0.0f32, 0.5, 1.0].into()))
+        .is_err());
+    Ok(())
+}
+
 #[test]
 fn unstructured_grid_complex_test() -> Result {
     let in1 = include_str!("../assets/unstructured_grid_complex.vtk");
@@ -743,6 +830,7 @@ fn unstructured_grid_complex_test() -> Result {
                     }),
                 ],
                 cell: vec![],
+                field: Vec::new(),
             },
         }),
     };
@@ -781,6 +869,7 @@ fn volume_complex_test() -> Result {
                     .into(),
                 })],
                 cell: vec![],
+                field: Vec::new(),
             },
         }),
     };
@@ -1053,3 +1142,1759 @@ fn binary_dodecagon_test() {
     test_b!(parse_be(in1) => out1);
     test_b!(parse_be(in2) => out1);
 }
+
+fn empty_data_sets() -> Vec<Vtk> {
+    let vtk = |data| Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("empty"),
+        file_path: None,
+        data,
+    };
+    vec![
+        vtk(DataSet::inline(UnstructuredGridPiece {
+            points: Vec::<f32>::new().into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 0,
+                    vertices: vec![],
+                },
+                types: vec![],
+            },
+            data: Attributes::new(),
+        })),
+        vtk(DataSet::inline(PolyDataPiece {
+            points: Vec::<f32>::new().into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 0,
+                vertices: vec![],
+            }),
+            ..Default::default()
+        })),
+        vtk(DataSet::inline(ImageDataPiece {
+            extent: Extent::Dims([0, 0, 0]),
+            data: Attributes::new(),
+        })),
+        vtk(DataSet::inline(StructuredGridPiece {
+            extent: Extent::Dims([0, 0, 0]),
+            points: Vec::<f32>::new().into(),
+            data: Attributes::new(),
+        })),
+        vtk(DataSet::inline(RectilinearGridPiece {
+            extent: Extent::Dims([0, 0, 0]),
+            coords: Coordinates {
+                x: Vec::<f32>::new().into(),
+                y: Vec::<f32>::new().into(),
+                z: Vec::<f32>::new().into(),
+            },
+            data: Attributes::new(),
+        })),
+    ]
+}
+
+#[test]
+fn empty_data_sets_round_trip() -> Result {
+    for out1 in empty_data_sets() {
+        let mut ascii = String::new();
+        ascii.write_vtk_ne(out1.clone())?;
+        // Empty attribute sections are omitted.
+        assert!(!ascii.contains("POINT_DATA"));
+        assert!(!ascii.contains("CELL_DATA"));
+        test_b!(parse_ne(ascii.as_bytes()) => ne(&out1));
+        test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    }
+    Ok(())
+}
+
+#[test]
+fn empty_data_sets_with_attributes() -> Result {
+    let out1 = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("empty"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: Vec::<f32>::new().into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 0,
+                    vertices: vec![],
+                },
+                types: vec![],
+            },
+            data: Attributes {
+                point: vec![Attribute::scalars("p", 1).with_data(Vec::<f32>::new())],
+                cell: vec![Attribute::scalars("c", 1).with_data(Vec::<i32>::new())],
+                field: Vec::new(),
+            },
+        }),
+    };
+    // Declared attributes are preserved even if they are empty.
+    test_b!(parse_ne(String::new().write_vtk_ne(out1.clone())?.as_bytes()) => ne(&out1));
+    test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    Ok(())
+}
+
+#[test]
+fn unstructured_grid_without_cells() -> Result {
+    let in1 = "# vtk DataFile Version 2.0\nno cells\nASCII\n\
+               DATASET UNSTRUCTURED_GRID\nPOINTS 1 float\n0 0 0\n";
+    let out1 = Vtk {
+        version: Version::new((2, 0)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("no cells"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32; 3].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 0,
+                    vertices: vec![],
+                },
+                types: vec![],
+            },
+            data: Attributes::new(),
+        }),
+    };
+    test!(parse_be(in1) => out1);
+    Ok(())
+}
+
+fn rectilinear_grid(x: Vec<f32>, y: Vec<f32>, z: Vec<f32>, num_cells: usize) -> Vtk {
+    let extent = Extent::Dims([x.len() as u32, y.len() as u32, z.len() as u32]);
+    let num_points = x.len() * y.len() * z.len();
+    Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("degenerate"),
+        file_path: None,
+        data: DataSet::inline(RectilinearGridPiece {
+            extent,
+            coords: Coordinates {
+                x: x.into(),
+                y: y.into(),
+                z: z.into(),
+            },
+            data: Attributes {
+                point: vec![Attribute::scalars("p", 1).with_data(vec![1.0f32; num_points])],
+                cell: vec![Attribute::scalars("c", 1).with_data(vec![2i32; num_cells])],
+                field: Vec::new(),
+            },
+        }),
+    }
+}
+
+#[test]
+fn degenerate_rectilinear_grids() -> Result {
+    let grids = vec![
+        // Planar
+        (
+            rectilinear_grid(vec![0., 1., 2.], vec![0., 1., 2., 3.], vec![0.], 6),
+            "CELL_DATA 6",
+        ),
+        // Linear
+        (
+            rectilinear_grid(vec![0.], vec![0., 1., 2.], vec![0.], 2),
+            "CELL_DATA 2",
+        ),
+        // Single point
+        (
+            rectilinear_grid(vec![0.], vec![0.], vec![0.], 1),
+            "CELL_DATA 1",
+        ),
+    ];
+    for (out1, cell_data) in grids {
+        let mut ascii = String::new();
+        ascii.write_vtk_ne(out1.clone())?;
+        assert!(ascii.contains(cell_data));
+        test_b!(parse_ne(ascii.as_bytes()) => ne(&out1));
+        test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    }
+    Ok(())
+}
+
+#[test]
+fn degenerate_structured_data() -> Result {
+    for dims in [[3, 4, 1], [1, 3, 1], [3, 1, 2], [1, 1, 1]] {
+        let extent = Extent::Dims(dims);
+        let num_points = extent.num_points() as usize;
+        let attributes = Attributes {
+            point: vec![Attribute::scalars("p", 1).with_data(vec![1.0f32; num_points])],
+            cell: vec![
+                Attribute::scalars("c", 1).with_data(vec![2i32; extent.num_cells() as usize])
+            ],
+            field: Vec::new(),
+        };
+        let vtk = |data| Vtk {
+            version: Version::new((4, 2)),
+            byte_order: ByteOrder::BigEndian,
+            title: String::from("degenerate"),
+            file_path: None,
+            data,
+        };
+        let outputs = vec![
+            vtk(DataSet::ImageData {
+                extent: extent.clone(),
+                origin: [0.0; 3],
+                spacing: [1.0; 3],
+                meta: None,
+                pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                    extent: extent.clone(),
+                    data: attributes.clone(),
+                }))],
+            }),
+            vtk(DataSet::inline(StructuredGridPiece {
+                extent: extent.clone(),
+                points: vec![0.0f32; 3 * num_points].into(),
+                data: attributes.clone(),
+            })),
+        ];
+        for out1 in outputs {
+            test_b!(parse_ne(String::new().write_vtk_ne(out1.clone())?.as_bytes()) => ne(&out1));
+            test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn mismatched_structured_dimensions() {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("mismatched"),
+        file_path: None,
+        data: DataSet::inline(StructuredGridPiece {
+            extent: Extent::Dims([3, 4, 1]),
+            points: vec![0.0f32; 3 * 4].into(),
+            data: Attributes::new(),
+        }),
+    };
+    assert!(Vec::<u8>::new().write_vtk_be(vtk).is_err());
+
+    let vtk = rectilinear_grid(vec![0., 1., 2.], vec![0.], vec![0.], 2);
+    let vtk = Vtk {
+        data: match vtk.data {
+            DataSet::RectilinearGrid { pieces, meta, .. } => DataSet::RectilinearGrid {
+                extent: Extent::Dims([3, 2, 1]),
+                pieces,
+                meta,
+            },
+            _ => unreachable!(),
+        },
+        ..vtk
+    };
+    assert!(Vec::<u8>::new().write_vtk_be(vtk).is_err());
+}
+
+#[test]
+fn dimensions_mismatch_on_read() -> Result {
+    let structured: &[u8] = b"# vtk DataFile Version 2.0
+mismatched
+ASCII
+DATASET STRUCTURED_GRID
+DIMENSIONS 2 2 2
+POINTS 4 float
+0 0 0 1 0 0 0 1 0 1 1 0
+";
+    match Vtk::parse_legacy_be(structured) {
+        Err(Error::Load(e @ vtkio::model::Error::DimensionsMismatch { .. })) => {
+            assert_eq!(
+                e.to_string(),
+                "DIMENSIONS 2 2 2 describe 8 points, but 4 points were given"
+            );
+        }
+        result => panic!("expected a dimensions mismatch, got {:?}", result),
+    }
+    let vtk = Vtk::parse_legacy_be_with_options(structured, ReadOptions::tolerant())?;
+    assert_eq!(vtk.data.extent().unwrap().clone().into_dims(), [2, 2, 1]);
+    match vtk.data {
+        DataSet::StructuredGrid { pieces, .. } => match &pieces[0] {
+            Piece::Inline(piece) => assert_eq!(piece.extent, Extent::Dims([2, 2, 1])),
+            _ => panic!("expected an inline piece"),
+        },
+        _ => panic!("expected a structured grid"),
+    }
+
+    // Points not divisible by the first two dimensions are treated as a line.
+    let line: &[u8] = b"# vtk DataFile Version 2.0
+mismatched
+ASCII
+DATASET STRUCTURED_GRID
+DIMENSIONS 2 2 1
+POINTS 3 float
+0 0 0 1 0 0 2 0 0
+";
+    assert!(Vtk::parse_legacy_be(line).is_err());
+    let vtk = Vtk::parse_legacy_be_with_options(line, ReadOptions::tolerant())?;
+    assert_eq!(vtk.data.extent().unwrap().clone().into_dims(), [3, 1, 1]);
+
+    let rectilinear: &[u8] = b"# vtk DataFile Version 2.0
+mismatched
+ASCII
+DATASET RECTILINEAR_GRID
+DIMENSIONS 3 2 1
+X_COORDINATES 3 float
+0 1 2
+Y_COORDINATES 1 float
+0
+Z_COORDINATES 1 float
+0
+";
+    match Vtk::parse_legacy_be(rectilinear) {
+        Err(Error::Load(vtkio::model::Error::DimensionsMismatch { dims, num_points })) => {
+            assert_eq!(dims, [3, 2, 1]);
+            assert_eq!(num_points, 3);
+        }
+        result => panic!("expected a dimensions mismatch, got {:?}", result),
+    }
+    let vtk = Vtk::parse_legacy_be_with_options(rectilinear, ReadOptions::tolerant())?;
+    assert_eq!(vtk.data.extent().unwrap().clone().into_dims(), [3, 1, 1]);
+    Ok(())
+}
+
+#[test]
+fn multi_piece_write() -> Result {
+    let triangle = |z: f32| PolyDataPiece {
+        points: vec![0.0, 0.0, z, 1.0, 0.0, z, 0.0, 1.0, z].into(),
+        polys: Some(VertexNumbers::Legacy {
+            num_cells: 1,
+            vertices: vec![3, 0, 1, 2],
+        }),
+        ..Default::default()
+    };
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Two triangles"),
+        file_path: None,
+        data: DataSet::from_pieces(vec![triangle(0.0), triangle(1.0)])?,
+    };
+
+    // Legacy files store a single piece, so poly data pieces are merged.
+    let mut output = String::new();
+    vtk.clone().write_legacy_ascii(&mut output)?;
+    let merged = Vtk::parse_legacy_be(output.as_bytes())?;
+    assert_eq!(merged.data.num_pieces(), 1);
+    match merged.data {
+        DataSet::PolyData { pieces, .. } => {
+            let piece = pieces[0].load_piece_data(None)?;
+            assert_eq!(piece.num_points(), 6);
+            assert_eq!(
+                piece.polys.unwrap().into_legacy(),
+                (2, vec![3, 0, 1, 2, 3, 3, 4, 5])
+            );
+        }
+        _ => panic!("expected poly data"),
+    }
+
+    // Structured data sets with multiple pieces can't be written.
+    let image = |x: i32| ImageDataPiece {
+        extent: Extent::Ranges([x..=x + 1, 0..=1, 0..=0]),
+        data: Attributes::new(),
+    };
+    let vtk = Vtk {
+        data: DataSet::from_pieces(vec![image(0), image(1)])?,
+        ..vtk
+    };
+    let mut output = String::new();
+    assert!(vtk.write_legacy_ascii(&mut output).is_err());
+    Ok(())
+}
+
+#[test]
+fn legacy_5_1_poly_data() -> Result {
+    let out1 = Vtk {
+        version: Version::new((5, 1)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Square example"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![
+                0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, -1.0,
+            ]
+            .into(),
+            verts: Some(VertexNumbers::XML {
+                offsets: vec![1, 2],
+                connectivity: vec![0, 3],
+            }),
+            polys: Some(VertexNumbers::XML {
+                offsets: vec![4],
+                connectivity: vec![0, 1, 2, 3],
+            }),
+            data: Attributes::new(),
+            ..Default::default()
+        }),
+    };
+
+    // Like VTK 5.1, cells are written as OFFSETS and CONNECTIVITY arrays.
+    let output = String::new().write_vtk_be(out1.clone())?.clone();
+    assert!(output
+        .contains("VERTICES 3 2\nOFFSETS vtktypeint64\n0 1 2\n\nCONNECTIVITY vtktypeint64\n0 3\n"));
+    assert!(output.contains("POLYGONS 2 4\nOFFSETS vtktypeint64\n0 4\n"));
+
+    test_b!(parse_be(output.as_bytes()) => out1);
+    test_b!(parse_ne(Vec::<u8>::new().write_vtk_ne(out1.clone())?) => ne(&out1));
+    test_b!(parse_le(Vec::<u8>::new().write_vtk_le(out1.clone())?) => le(&out1));
+    test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    Ok(())
+}
+
+#[test]
+fn round_trip_ascii_floats() -> Result {
+    let values = vec![
+        0.1f32,
+        -0.0,
+        1.0e-30,
+        f32::MAX,
+        f32::MIN_POSITIVE,
+        f32::EPSILON,
+        1.0 / 3.0,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NAN,
+    ];
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Round trip"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: [0.0f64, 1.0e300, -std::f64::consts::PI]
+                .repeat(values.len())
+                .into(),
+            data: Attributes {
+                point: vec![Attribute::scalars("values", 1).with_data(values.clone())],
+                cell: vec![],
+                field: Vec::new(),
+            },
+            ..Default::default()
+        }),
+    };
+
+    let mut writer =
+        FormattedAsciiWriter::new(String::new()).with_float_format(FloatFormat::RoundTrip);
+    writer.write_vtk(vtk)?;
+    assert!(writer.writer.contains("0 1e300 -3.141592653589793 0 1e300"));
+    assert!(writer.writer.contains("0.1 -0 1e-30 3.4028235e38"));
+    assert!(writer.writer.contains("inf -inf nan\n"));
+
+    let output = Vtk::parse_legacy_be(writer.writer.as_bytes())?;
+    let piece = match output.data {
+        DataSet::PolyData { pieces, .. } => pieces[0].load_piece_data(None)?,
+        _ => panic!("expected poly data"),
+    };
+    let output_values = piece.data.point[0]
+        .data_array()
+        .unwrap()
+        .data
+        .cast_into::<f32>()
+        .unwrap();
+    let bits = |v: &[f32]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+    assert_eq!(bits(&output_values[..9]), bits(&values[..9]));
+    assert!(output_values[9].is_nan());
+    Ok(())
+}
+
+#[test]
+fn significant_digits_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Significant digits"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![
+                0.0f64,
+                -std::f64::consts::PI,
+                123456.0,
+                9.9999,
+                1.5e-5,
+                0.00012,
+                1e300,
+                f64::NAN,
+                100.0,
+            ]
+            .into(),
+            ..Default::default()
+        }),
+    };
+
+    let write = |digits| -> std::result::Result<String, Error> {
+        let mut writer = FormattedAsciiWriter::new(String::new())
+            .with_float_format(FloatFormat::Significant(digits));
+        writer.write_vtk(&vtk)?;
+        Ok(writer.writer)
+    };
+    assert!(write(3)?.contains("\n0 -3.14 1.23e5 10 1.5e-5 0.00012 1e300 nan 100\n"));
+    assert!(write(6)?.contains("\n0 -3.14159 123456 9.9999 1.5e-5 0.00012 1e300 nan 100\n"));
+
+    // Enough digits to read back every value exactly.
+    let output = Vtk::parse_legacy_be(write(17)?.as_bytes())?;
+    let points = |vtk: &Vtk| match &vtk.data {
+        DataSet::PolyData { pieces, .. } => pieces[0].load_piece_data(None).unwrap().points,
+        _ => panic!("expected poly data"),
+    };
+    let bits = |buf: IOBuffer| {
+        buf.cast_into::<f64>()
+            .unwrap()
+            .iter()
+            .map(|x| x.to_bits())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(bits(points(&output))[..7], bits(points(&vtk))[..7]);
+    Ok(())
+}
+
+#[test]
+fn non_finite_test() -> Result {
+    let input = b"# vtk DataFile Version 2.0
+Residuals
+ASCII
+DATASET POLYDATA
+POINTS 2 double
+0 0 0 1 0 0
+POINT_DATA 2
+SCALARS r double 2
+LOOKUP_TABLE default
+nan Infinity -inf 0.5
+";
+    let vtk = Vtk::parse_legacy_be(&input[..])?;
+
+    let write = |non_finite| -> std::result::Result<String, Error> {
+        let mut output = Vec::new();
+        WriterConfig::new()
+            .with_format(OutputFormat::LegacyAscii)
+            .with_non_finite(non_finite)
+            .write(vtk.clone(), &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    };
+    assert!(write(NonFinite::AsIs)?.contains("\nNaN inf -inf 0.5\n"));
+    let replaced = write(NonFinite::Replace(-9999.0))?;
+    assert!(replaced.contains("\n-9999 -9999 -9999 0.5\n"));
+    assert!(replaced.contains("\n0 0 0 1 0 0\n"));
+    Ok(())
+}
+
+#[test]
+fn line_wrap_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((5, 1)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Line wrap"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: (0..15).map(|i| i as f32 * 0.25).collect::<Vec<_>>().into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 3, 4],
+                    offsets: vec![5],
+                },
+                types: vec![CellType::PolyVertex],
+            },
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data((0..5).collect::<Vec<i32>>())],
+                cell: vec![],
+                field: vec![],
+            },
+        }),
+    };
+
+    let write = |line_wrap| -> std::result::Result<String, Error> {
+        let mut writer = FormattedAsciiWriter::new(String::new()).with_line_wrap(line_wrap);
+        writer.write_vtk(&vtk)?;
+        Ok(writer.writer)
+    };
+
+    let output = write(LineWrap::Values(9))?;
+    assert!(output
+        .contains("POINTS 5 float\n0 0.25 0.5 0.75 1 1.25 1.5 1.75 2\n2.25 2.5 2.75 3 3.25 3.5\n"));
+    assert_eq!(Vtk::parse_legacy_be(output.as_bytes())?, vtk);
+
+    let output = write(LineWrap::Chars(12))?;
+    assert!(output.contains("POINTS 5 float\n0 0.25 0.5\n0.75 1 1.25\n1.5 1.75 2\n"));
+    assert_eq!(Vtk::parse_legacy_be(output.as_bytes())?, vtk);
+
+    // Every value on its own line.
+    let output = write(LineWrap::Values(1))?;
+    assert!(output.contains("LOOKUP_TABLE default\n0\n1\n2\n3\n4\n"));
+    assert_eq!(Vtk::parse_legacy_be(output.as_bytes())?, vtk);
+    Ok(())
+}
+
+#[test]
+fn cell_id_type_test() -> Result {
+    let mut vtk = Vtk {
+        version: Version::new((5, 1)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Cell ids"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 2, 0],
+                    offsets: vec![3, 5],
+                },
+                types: vec![CellType::Triangle, CellType::Line],
+            },
+            data: Attributes::new(),
+        }),
+    };
+
+    let mut output = String::new();
+    output.write_vtk_with_cell_ids(&vtk, CellIdType::Int32)?;
+    assert!(output.contains("CELLS 3 5\nOFFSETS vtktypeint32\n0 3 5\n"));
+    assert!(output.contains("CONNECTIVITY vtktypeint32\n0 1 2 2 0\n"));
+    assert_eq!(Vtk::parse_legacy_be(output.as_bytes())?, vtk);
+
+    let mut output = Vec::new();
+    BinaryWriter(&mut output).write_vtk_with_cell_ids(&vtk, CellIdType::Int32)?;
+    assert_eq!(Vtk::parse_legacy_be(output.as_slice())?, vtk);
+
+    let mut output = Vec::new();
+    WriterConfig::new()
+        .with_cell_id_type(CellIdType::Int32)
+        .write(vtk.clone(), &mut output)?;
+    assert_eq!(Vtk::parse_legacy_be(output.as_slice())?, vtk);
+
+    // Indices that don't fit into 32 bits are rejected rather than truncated.
+    if let DataSet::UnstructuredGrid { pieces, .. } = &mut vtk.data {
+        if let Piece::Inline(piece) = &mut pieces[0] {
+            piece.cells.cell_verts = VertexNumbers::XML {
+                connectivity: vec![0, 1, i32::MAX as u64 + 1],
+                offsets: vec![3],
+            };
+            piece.cells.types = vec![CellType::Triangle];
+        }
+    }
+    let mut output = String::new();
+    assert!(output
+        .write_vtk_with_cell_ids(&vtk, CellIdType::Int32)
+        .is_err());
+    let mut output = String::new();
+    assert!(output.write_vtk(&vtk)?.contains("vtktypeint64"));
+    Ok(())
+}
+
+#[test]
+fn writer_validation_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Validation"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 2, 0],
+                    offsets: vec![3, 5],
+                },
+                types: vec![CellType::Triangle, CellType::Line],
+            },
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data(vec![0u8, 1, 2])],
+                cell: vec![Attribute::vectors("dir").with_data(vec![0.0f32; 6])],
+                field: vec![],
+            },
+        }),
+    };
+    vtk.validate()?;
+
+    let config = WriterConfig::new().with_validation(true);
+    config.write(vtk.clone(), Vec::new())?;
+
+    let modified = |f: &dyn Fn(&mut UnstructuredGridPiece)| {
+        let mut vtk = vtk.clone();
+        if let DataSet::UnstructuredGrid { pieces, .. } = &mut vtk.data {
+            if let Piece::Inline(piece) = &mut pieces[0] {
+                f(piece);
+            }
+        }
+        vtk
+    };
+    let validation_error = |vtk: Vtk| match config.write(vtk.clone(), Vec::new()) {
+        Err(Error::Load(err)) => {
+            // Without validation the corrupt file is written anyway.
+            WriterConfig::new().write(vtk, Vec::new()).unwrap();
+            err
+        }
+        result => panic!("expected validation error, got {:?}", result),
+    };
+
+    let err = validation_error(modified(&|p| p.points = vec![0.0f32; 10].into()));
+    assert!(matches!(
+        err,
+        vtkio::model::Error::TupleSizeMismatch {
+            num_comp: 3,
+            len: 10,
+            ..
+        }
+    ));
+
+    let err = validation_error(modified(&|p| {
+        p.data.point[0] = Attribute::scalars("id", 1).with_data(vec![0u8, 1])
+    }));
+    assert_eq!(
+        err.to_string(),
+        "Attribute \"id\" has 2 values, but should have 3"
+    );
+
+    let err = validation_error(modified(&|p| {
+        p.data.cell[0] = Attribute::vectors("dir").with_data(vec![0.0f32; 3])
+    }));
+    assert!(matches!(
+        err,
+        vtkio::model::Error::AttributeSizeMismatch {
+            expected: 6,
+            actual: 3,
+            ..
+        }
+    ));
+
+    let err = validation_error(modified(&|p| {
+        p.cells.types.pop();
+    }));
+    assert!(matches!(
+        err,
+        vtkio::model::Error::InvalidCells(CellIssue::CellCountMismatch {
+            num_types: 1,
+            num_cells: 2
+        })
+    ));
+
+    let err = validation_error(modified(&|p| {
+        p.cells.cell_verts = VertexNumbers::Legacy {
+            num_cells: 2,
+            vertices: vec![3, 0, 1, 2, 2, 2],
+        }
+    }));
+    assert!(matches!(
+        err,
+        vtkio::model::Error::InvalidCells(CellIssue::MalformedConnectivity { cell: 1 })
+    ));
+    Ok(())
+}
+
+#[test]
+fn legacy_version_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((1, 0)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Legacy version"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 2,
+                    vertices: vec![3, 0, 1, 2, 2, 2, 0],
+                },
+                types: vec![CellType::Triangle, CellType::Line],
+            },
+            data: Attributes::new(),
+        }),
+    };
+
+    for &(legacy_version, cells) in &[
+        (LegacyVersion::V2_0, "CELLS 2 7\n3 0 1 2 2 2 0\n"),
+        (LegacyVersion::V3_0, "CELLS 2 7\n3 0 1 2 2 2 0\n"),
+        (LegacyVersion::V4_2, "CELLS 2 7\n3 0 1 2 2 2 0\n"),
+        (
+            LegacyVersion::V5_1,
+            "CELLS 3 5\nOFFSETS vtktypeint64\n0 3 5\n",
+        ),
+    ] {
+        let config = WriterConfig::new()
+            .with_version(Version::new((1, 0)))
+            .with_legacy_version(legacy_version);
+
+        let mut output = Vec::new();
+        config
+            .with_format(OutputFormat::LegacyAscii)
+            .write(vtk.clone(), &mut output)?;
+        let output = String::from_utf8(output).unwrap();
+        let header = format!("# vtk DataFile Version {}\n", legacy_version.version());
+        assert!(output.starts_with(&header));
+        assert!(output.contains(cells));
+
+        let mut output = Vec::new();
+        config.write(vtk.clone(), &mut output)?;
+        let parsed = Vtk::parse_legacy_be(output.as_slice())?;
+        assert_eq!(parsed.version, legacy_version.into());
+        let piece = UnstructuredGridPiece::from_data_set(parsed.data, None)?;
+        assert_eq!(
+            piece.cells.cell_verts.into_xml(),
+            (vec![0, 1, 2, 2, 0], vec![3, 5])
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn binary_writer_create_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::LittleEndian,
+        title: String::from("Streaming"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f64, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: vec![0, 1, 2, 2, 0],
+                    offsets: vec![3, 5],
+                },
+                types: vec![CellType::Triangle, CellType::Line],
+            },
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data(vec![0i32, 1, 2])],
+                cell: vec![],
+                field: vec![],
+            },
+        }),
+    };
+
+    let dir = std::env::temp_dir().join("vtkio_binary_writer_create");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("streamed.vtk");
+    let mut writer = BinaryWriter::create(&path)?;
+    writer.write_vtk(&vtk)?;
+    writer.finish()?;
+
+    // The streamed file is identical to the one written in memory.
+    let mut expected = Vec::new();
+    BinaryWriter(&mut expected).write_vtk(&vtk)?;
+    assert_eq!(std::fs::read(&path)?, expected);
+    let parsed = Vtk::parse_legacy_le(expected.as_slice())?;
+    let piece = UnstructuredGridPiece::from_data_set(parsed.data, None)?;
+    assert_eq!(
+        piece.cells.cell_verts,
+        VertexNumbers::Legacy {
+            num_cells: 2,
+            vertices: vec![3, 0, 1, 2, 2, 2, 0],
+        }
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn large_binary_arrays_test() -> Result {
+    // Arrays larger than the scratch buffer used for byte swapping are written in chunks.
+    let n = 30_000;
+    let points: Vec<f64> = (0..3 * n).map(|i| i as f64 * 0.5).collect();
+    let ids: Vec<i16> = (0..n).map(|i| (i % 1000) as i16 - 500).collect();
+    let mut vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Large arrays"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: points.into(),
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data(ids)],
+                ..Attributes::new()
+            },
+            ..Default::default()
+        }),
+    };
+
+    let mut output = Vec::new();
+    BinaryWriter(&mut output).write_vtk(&vtk)?;
+    let data_start = output
+        .windows(11)
+        .position(|w| w == b"POINTS 3000")
+        .unwrap();
+    let header_len = "POINTS 30000 double\n".len();
+    assert_eq!(
+        &output[data_start + header_len + 8..][..8],
+        &0.5f64.to_be_bytes()
+    );
+    assert_eq!(Vtk::parse_legacy_be(output.as_slice())?, vtk);
+
+    vtk.byte_order = ByteOrder::LittleEndian;
+    let mut output = Vec::new();
+    BinaryWriter(&mut output).write_vtk(&vtk)?;
+    assert_eq!(Vtk::parse_legacy_le(output.as_slice())?, vtk);
+    Ok(())
+}
+
+#[test]
+fn fast_float_format_test() -> Result {
+    // Floats are written with the shortest digits that parse back to the same value.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut random_bits = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut values = vec![
+        0.0,
+        -0.0,
+        1.0,
+        0.1,
+        -2.5,
+        1e-7,
+        1e-5,
+        123456789.0,
+        1e16,
+        1e300,
+        5e-324,
+    ];
+    values.extend((0..2000).map(|_| f64::from_bits(random_bits())));
+    values.extend((0..2000).map(|_| f64::from(f32::from_bits(random_bits() as u32))));
+    values.retain(|x| x.is_finite());
+    let floats: Vec<f32> = values.iter().map(|&x| x as f32).collect();
+
+    let data_line =
+        |data: IOBuffer, config: Option<WriterConfig>| -> std::result::Result<String, Error> {
+            let vtk = Vtk {
+                version: Version::new((4, 2)),
+                byte_order: ByteOrder::BigEndian,
+                title: String::from("Floats"),
+                file_path: None,
+                data: DataSet::Field {
+                    name: String::from("FieldData"),
+                    data_array: vec![FieldArray::new("values", 1).with_data(data)],
+                },
+            };
+            let output = match config {
+                Some(config) => {
+                    let mut output = Vec::new();
+                    config.write(vtk, &mut output)?;
+                    String::from_utf8(output).unwrap()
+                }
+                None => {
+                    let mut output = String::new();
+                    output.write_vtk(vtk)?;
+                    output
+                }
+            };
+            Ok(output.lines().nth(6).unwrap().to_string())
+        };
+    fn shortest<T: std::fmt::Display + std::fmt::LowerExp>(x: &T) -> String {
+        let (decimal, scientific) = (format!("{}", x), format!("{:e}", x));
+        if scientific.len() < decimal.len() {
+            scientific
+        } else {
+            decimal
+        }
+    }
+    // Values are read back exactly and have as many characters as the standard formats. The
+    // last digit may differ when two shortest representations are equally close.
+    fn check<T>(line: &str, values: &[T], format: impl Fn(&T) -> String)
+    where
+        T: std::str::FromStr + PartialEq + std::fmt::Debug,
+        T::Err: std::fmt::Debug,
+    {
+        let tokens: Vec<&str> = line.split(' ').collect();
+        assert_eq!(tokens.len(), values.len());
+        for (token, value) in tokens.iter().zip(values) {
+            assert_eq!(token.parse::<T>().unwrap(), *value);
+            assert_eq!(token.len(), format(value).len(), "{}", token);
+        }
+    }
+
+    check(&data_line(values.clone().into(), None)?, &values, |x| {
+        x.to_string()
+    });
+    check(&data_line(floats.clone().into(), None)?, &floats, |x| {
+        x.to_string()
+    });
+    let config = WriterConfig::new()
+        .with_format(OutputFormat::LegacyAscii)
+        .with_float_format(FloatFormat::RoundTrip);
+    check(
+        &data_line(values.clone().into(), Some(config))?,
+        &values,
+        shortest,
+    );
+    check(
+        &data_line(floats.clone().into(), Some(config))?,
+        &floats,
+        shortest,
+    );
+    Ok(())
+}
+
+#[test]
+fn estimated_size_test() -> Result {
+    let n = 10_000u64;
+    let points: Vec<f32> = (0..3 * n).map(|i| (i as f32).sqrt()).collect();
+    let values: Vec<f64> = (0..n).map(|i| i as f64 / 7.0).collect();
+    let vertices = (0..n - 2).flat_map(|i| vec![3, i, i + 1, i + 2]).collect();
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Strip"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: points.into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: n - 2,
+                vertices,
+            }),
+            data: Attributes {
+                point: vec![Attribute::scalars("t", 1).with_data(values)],
+                ..Attributes::new()
+            },
+            ..Default::default()
+        }),
+    };
+
+    // The estimate is close to the actual size, and the buffers don't grow while writing.
+    let close = |estimate: usize, actual: usize| estimate >= actual / 2 && estimate <= actual * 2;
+    let mut binary = Vec::new();
+    binary.write_vtk(&vtk)?;
+    let estimate = vtk.estimated_size(&WriterConfig::new());
+    assert!(close(estimate, binary.len()));
+    let mut binary = Vec::new();
+    binary.write_vtk(&vtk)?;
+    assert!(binary.capacity() >= estimate);
+
+    let mut ascii = String::new();
+    ascii.write_vtk(&vtk)?;
+    let config = WriterConfig::new().with_format(OutputFormat::LegacyAscii);
+    assert!(close(vtk.estimated_size(&config), ascii.len()));
+
+    let config = WriterConfig::new().with_legacy_version(LegacyVersion::V5_1);
+    let mut output = Vec::new();
+    config.write(vtk.clone(), &mut output)?;
+    assert!(close(vtk.estimated_size(&config), output.len()));
+    Ok(())
+}
+
+#[test]
+fn rectilinear_grid_with_meta_test() -> Result {
+    // ParaView writes METADATA blocks after each coordinate and field array. The last block
+    // isn't terminated by an empty line.
+    let in1 = include_bytes!("../assets/rectilinear_grid_with_meta.vtk");
+    let out1 = Vtk {
+        version: Version::new((5, 1)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("vtk output"),
+        file_path: None,
+        data: DataSet::inline(RectilinearGridPiece {
+            extent: Extent::Dims([3, 4, 1]),
+            coords: Coordinates {
+                x: vec![0_f32, 2.0, 4.0].into(),
+                y: vec![1_f32, 2.0, 3.0, 4.0].into(),
+                z: vec![0_f32].into(),
+            },
+            data: Attributes {
+                point: vec![],
+                cell: vec![Attribute::Field {
+                    name: String::from("FieldData"),
+                    data_array: vec![FieldArray {
+                        name: String::from("cellscalar"),
+                        elem: 1,
+                        data: vec![1.1_f32, 7.5, 1.2, 1.5, 2.6, 8.1].into(),
+                    }],
+                }],
+                field: Vec::new(),
+            },
+        }),
+    };
+    test_b!(parse_be(in1) => out1);
+
+    let in1_crlf = String::from_utf8(in1.to_vec())
+        .unwrap()
+        .replace('\n', "\r\n");
+    test_b!(parse_be(in1_crlf.as_bytes()) => out1);
+    Ok(())
+}
+
+#[test]
+fn global_field_data_test() -> Result {
+    // VisIt and VTK write data set wide field data right after the DATASET keyword.
+    let in1 = include_bytes!("../assets/global_field_data.vtk");
+    let out1 = Vtk {
+        version: Version::new((3, 0)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Time stamped grid"),
+        file_path: None,
+        data: DataSet::inline(StructuredGridPiece {
+            extent: Extent::Dims([2, 1, 1]),
+            points: vec![0_f32, 0.0, 0.0, 1.0, 0.0, 0.0].into(),
+            data: Attributes {
+                point: vec![Attribute::DataArray(DataArray {
+                    name: String::from("s"),
+                    elem: ElementType::Scalars {
+                        num_comp: 1,
+                        lookup_table: None,
+                    },
+                    data: vec![0_f32, 1.0].into(),
+                })],
+                cell: vec![],
+                field: vec![
+                    FieldArray::new("TIME", 1).with_data(vec![1.5_f64]),
+                    FieldArray::new("CYCLE", 1).with_data(vec![3_i32]),
+                ],
+            },
+        }),
+    };
+    test_b!(parse_be(in1) => out1);
+    test_b!(parse_be(String::new().write_vtk_be(out1.clone())?.as_bytes()) => out1);
+    test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    Ok(())
+}
+
+#[test]
+fn scalars_optional_header_test() -> Result {
+    // Exporters often omit LOOKUP_TABLE lines and the number of components of SCALARS.
+    let in1 = include_bytes!("../assets/scalars_optional_header.vtk");
+    let scalars = |name: &str, data: IOBuffer| {
+        Attribute::DataArray(DataArray {
+            name: String::from(name),
+            elem: ElementType::Scalars {
+                num_comp: 1,
+                lookup_table: None,
+            },
+            data,
+        })
+    };
+    let out1 = Vtk {
+        version: Version::new((3, 0)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Scalars without optional header fields"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            }),
+            data: Attributes {
+                point: vec![
+                    scalars("no_lookup_table", vec![1.5f32, 2.5, 3.5].into()),
+                    scalars("no_num_comp", vec![7i32, 8, 9].into()),
+                    scalars("bare", vec![0.25f64, 0.5, 0.75].into()),
+                    scalars("ids", vec![4i64, 5, 6].into()),
+                ],
+                cell: vec![scalars("cell_bare", vec![42u8].into())],
+                field: Vec::new(),
+            },
+            ..Default::default()
+        }),
+    };
+    test_b!(parse_be(in1) => out1);
+    test_b!(parse_be(String::new().write_vtk_be(out1.clone())?.as_bytes()) => out1);
+    test_b!(parse_be(Vec::<u8>::new().write_vtk_be(out1.clone())?) => out1);
+    Ok(())
+}
+
+#[test]
+fn large_vertex_indices() -> Result {
+    // Indices beyond 32 bits can only be stored in legacy files of version 5.1 and later.
+    let big = u64::from(u32::MAX) + 2;
+    let vtk = Vtk {
+        version: Version::new((5, 1)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Large indices"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32, 0.0, 0.0].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::Legacy {
+                    num_cells: 1,
+                    vertices: vec![2, 0, big],
+                },
+                types: vec![CellType::Line],
+            },
+            data: Attributes::new(),
+        }),
+    };
+
+    let expected = VertexNumbers::XML {
+        offsets: vec![2],
+        connectivity: vec![0, big],
+    };
+    for output in [
+        String::new().write_vtk_be(vtk.clone())?.as_bytes().to_vec(),
+        Vec::<u8>::new().write_vtk_be(vtk.clone())?.clone(),
+    ] {
+        match Vtk::parse_legacy_be(&output[..])?.data {
+            DataSet::UnstructuredGrid { pieces, .. } => {
+                let piece = pieces[0].load_piece_data(None)?;
+                assert_eq!(piece.cells.cell_verts, expected);
+            }
+            _ => panic!("expected an unstructured grid"),
+        }
+    }
+
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        ..vtk
+    };
+    let err = String::new().write_vtk(vtk).unwrap_err();
+    assert!(err.to_string().contains("Vertex indices exceed 32 bits"));
+    Ok(())
+}
+
+#[test]
+fn io_writer_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Triangle example"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            }),
+            ..Default::default()
+        }),
+    };
+
+    // Writing through the adapter produces the same output as writing to a string.
+    let mut writer = AsciiWriter(IoWriter::new(Vec::new()));
+    writer.write_vtk(vtk.clone())?;
+    let expected = String::new().write_vtk(vtk.clone())?.clone();
+    assert_eq!(writer.0.into_inner(), expected.into_bytes());
+
+    // IO errors are kept by the adapter.
+    struct Full;
+    impl std::io::Write for Full {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WriteZero.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut writer = AsciiWriter(IoWriter::new(Full));
+    assert!(writer.write_vtk(vtk).is_err());
+    assert_eq!(
+        writer.0.take_error().map(|err| err.kind()),
+        Some(std::io::ErrorKind::WriteZero)
+    );
+    assert!(writer.0.take_error().is_none());
+    Ok(())
+}
+
+#[test]
+fn export_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Export"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let dir = std::env::temp_dir().join("vtkio_export_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    vtkio::export(vtk.clone(), dir.join("binary.vtk"))?;
+    vtkio::export_ascii(vtk.clone(), dir.join("ascii.vtk"))?;
+    vtkio::export_le(vtk.clone(), dir.join("le.vtk"))?;
+    vtkio::export_be(vtk.clone(), dir.join("be.vtk"))?;
+
+    for name in &["binary.vtk", "ascii.vtk", "be.vtk"] {
+        assert_eq!(vtkio::import(dir.join(name))?.data, vtk.data);
+    }
+    let options = ReadOptions::default().with_byte_order(ByteOrder::LittleEndian);
+    assert_eq!(
+        Vtk::import_with_options(dir.join("le.vtk"), options)?.data,
+        vtk.data
+    );
+    assert_eq!(vtkio::import(dir.join("le.vtk"))?.data, vtk.data);
+    assert!(std::fs::read_to_string(dir.join("ascii.vtk"))?.contains("ASCII\n"));
+
+    assert!(matches!(
+        vtkio::export(vtk, dir.join("triangle")),
+        Err(Error::UnknownFileExtension(None))
+    ));
+    Ok(())
+}
+
+#[test]
+fn format_report_test() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_format_report_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let tet = |version, cell_type| Vtk {
+        version: Version::new(version),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Tetrahedron"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: vec![0.0f32; 30].into(),
+            cells: Cells {
+                cell_verts: VertexNumbers::XML {
+                    connectivity: (0..10).collect(),
+                    offsets: vec![10],
+                },
+                types: vec![cell_type],
+            },
+            data: Attributes::new(),
+        }),
+    };
+
+    tet((5, 1), CellType::QuadraticTetra).export(dir.join("modern.vtk"))?;
+    let (_, report) = Vtk::import_with_report(dir.join("modern.vtk"))?;
+    assert!(!report.xml);
+    assert_eq!(report.version, Version::new((5, 1)));
+    assert!(report.has(FormatFeature::Int64Ids));
+    assert!(report.has(FormatFeature::HigherOrderCells));
+    assert!(!report.has(FormatFeature::Compression));
+
+    tet((4, 2), CellType::Tetra).export_ascii(dir.join("old.vtk"))?;
+    let (_, report) = Vtk::import_with_report(dir.join("old.vtk"))?;
+    assert_eq!(report.version, Version::new((4, 2)));
+    assert!(report.features.is_empty());
+    Ok(())
+}
+
+#[test]
+fn write_borrowed_test() -> Result {
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::LittleEndian,
+        title: String::from("Borrowed"),
+        file_path: None,
+        data: DataSet::inline(PolyDataPiece {
+            points: vec![0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0].into(),
+            polys: Some(VertexNumbers::Legacy {
+                num_cells: 1,
+                vertices: vec![3, 0, 1, 2],
+            }),
+            data: Attributes {
+                point: vec![Attribute::scalars("id", 1).with_data(vec![0u8, 1, 2])],
+                cell: vec![],
+                field: vec![],
+            },
+            ..Default::default()
+        }),
+    };
+
+    // Borrowed and owned models produce identical files.
+    let mut ascii = String::new();
+    ascii.write_vtk(&vtk)?;
+    assert_eq!(ascii, String::new().write_vtk(vtk.clone())?.clone());
+
+    // Writing in a given byte order leaves the model untouched.
+    let mut binary = Vec::new();
+    binary.write_vtk_be(&vtk)?;
+    assert_eq!(vtk.byte_order, ByteOrder::LittleEndian);
+    let mut expected = Vec::new();
+    expected.write_vtk_be(Vtk {
+        byte_order: ByteOrder::BigEndian,
+        ..vtk.clone()
+    })?;
+    assert_eq!(binary, expected);
+
+    let output = Vtk::parse_legacy_be(binary.as_slice())?;
+    assert_eq!(output.data, vtk.data);
+    Ok(())
+}
+
+#[test]
+fn export_geometry_test() -> Result {
+    let vtk = vtkio::import("./assets/cube_complex.vtk")?;
+    let dir = std::env::temp_dir().join("vtkio_export_geometry_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("cube.vtk");
+    vtk.clone().export_geometry(&path)?;
+
+    // Everything but the attributes is preserved.
+    let geometry = vtkio::import(&path)?;
+    let mut expected = PolyDataPiece::from_data_set(vtk.data, None)?;
+    expected.data = Attributes::new();
+    assert_eq!(PolyDataPiece::from_data_set(geometry.data, None)?, expected);
+    let bytes = std::fs::read(&path)?;
+    assert!(!bytes.windows(7).any(|w| w == b"SCALARS"));
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn stream_writer_test() -> Result {
+    let points: Vec<f64> = (0..18).map(|i| f64::from(i) / 7.0).collect();
+    let cells = Cells {
+        cell_verts: VertexNumbers::XML {
+            connectivity: vec![0, 1, 2, 3, 1, 3, 4, 5, 2, 4],
+            offsets: vec![4, 8, 10],
+        },
+        types: vec![CellType::Tetra, CellType::Quad, CellType::Line],
+    };
+    let pressure = Attribute::scalars("pressure", 1).with_data(vec![1.0f32; 6]);
+    let ids = Attribute::scalars("ids", 1).with_data(vec![0i32, 1, 2]);
+    let vtk = Vtk {
+        version: Version::new((4, 2)),
+        byte_order: ByteOrder::BigEndian,
+        title: String::from("Stream"),
+        file_path: None,
+        data: DataSet::inline(UnstructuredGridPiece {
+            points: points.clone().into(),
+            cells,
+            data: Attributes {
+                point: vec![pressure.clone()],
+                cell: vec![ids.clone()],
+                field: vec![],
+            },
+        }),
+    };
+
+    let mut writer = StreamWriter::new(Vec::new());
+    writer.begin_unstructured_grid("Stream", 6, 3, 10)?;
+    writer.write_points(&points[..10].to_vec().into())?;
+    writer.write_points(&points[10..].to_vec().into())?;
+    writer.write_cells_chunk(&Cells {
+        cell_verts: VertexNumbers::Legacy {
+            num_cells: 2,
+            vertices: vec![4, 0, 1, 2, 3, 4, 1, 3, 4, 5],
+        },
+        types: vec![CellType::Tetra, CellType::Quad],
+    })?;
+    // Attributes can't be written before all cells.
+    assert!(matches!(
+        writer.write_point_attribute(&pressure),
+        Err(vtkio::writer::Error::OutOfOrder)
+    ));
+    writer.write_cells_chunk(&Cells {
+        cell_verts: VertexNumbers::XML {
+            connectivity: vec![2, 4],
+            offsets: vec![2],
+        },
+        types: vec![CellType::Line],
+    })?;
+    writer.write_point_attribute(&pressure)?;
+    writer.write_cell_attribute(&ids)?;
+    assert!(matches!(
+        writer.write_point_attribute(&pressure),
+        Err(vtkio::writer::Error::OutOfOrder)
+    ));
+    let streamed = writer.finish()?;
+
+    // The streamed file is identical to the one written all at once.
+    let mut expected = Vec::new();
+    BinaryWriter(&mut expected).write_vtk(&vtk)?;
+    assert_eq!(streamed, expected);
+
+    // Files with missing cells are rejected.
+    let mut writer = StreamWriter::new(Vec::new());
+    writer.begin_unstructured_grid("Stream", 0, 1, 1)?;
+    assert!(writer.finish().is_err());
+    Ok(())
+}
+
+#[test]
+fn detect_byte_order_test() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_detect_byte_order_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    for file in &[
+        "assets/dodecagon.vtk",
+        "assets/para_test.vtk",
+        "assets/para_tet.vtk",
+        "assets/rectilinear_grid_binary.vtk",
+        "assets/tet_test_binary.vtk",
+        "assets/tri_attrib_binary.vtk",
+        "assets/pygmsh/binary.vtk",
+    ] {
+        let options = ReadOptions::default().with_byte_order(ByteOrder::BigEndian);
+        let vtk = Vtk::import_with_options(file, options)?;
+        assert_eq!(vtkio::import(file)?, vtk, "{}", file);
+
+        let le = dir.join("le.vtk");
+        vtk.clone().export_le(&le)?;
+        let detected = vtkio::import(&le)?;
+        assert_eq!(detected.byte_order, ByteOrder::LittleEndian, "{}", file);
+        assert_eq!(detected.data, vtk.data, "{}", file);
+
+        // The byte order can be forced.
+        let options = ReadOptions::default().with_byte_order(ByteOrder::LittleEndian);
+        let forced = Vtk::parse_legacy_with_options(std::fs::File::open(&le)?, options)?;
+        assert_eq!(forced.data, vtk.data, "{}", file);
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn detect_byte_order_huge_counts_test() {
+    // Sizes computed from huge counts must not overflow while guessing the byte order.
+    for body in &[
+        "POINTS 18446744073709551615 float\n",
+        "POINTS 1 float\n\0\0\0\0\0\0\0\0\0\0\0\0\nPOINT_DATA 18446744073709551615\nVECTORS v float\n",
+        "POINTS 1 float\n\0\0\0\0\0\0\0\0\0\0\0\0\nPOINT_DATA 2\nLOOKUP_TABLE t 18446744073709551615\n",
+    ] {
+        let input = format!(
+            "# vtk DataFile Version 2.0\nHuge\nBINARY\nDATASET POLYDATA\n{}",
+            body
+        );
+        let strict = ReadOptions::default().with_strict_syntax(true);
+        let result = Vtk::parse_legacy_with_options(input.as_bytes(), strict);
+        assert!(result.is_err(), "{}", body);
+    }
+}
+
+#[test]
+fn import_series_test() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_import_series_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let tet = Vtk::import("assets/tet.vtk")?;
+    for step in &[10, 2, 1] {
+        let mut vtk = tet.clone();
+        vtk.title = format!("Step {}", step);
+        vtk.export_ascii(dir.join(format!("step_{}.vtk", step)))?;
+    }
+    std::fs::write(
+        dir.join("step_3.vtk"),
+        "# vtk DataFile Version 2.0\nBroken\n",
+    )?;
+    std::fs::write(dir.join("notes.txt"), "Not a VTK file")?;
+
+    // A corrupt file doesn't prevent loading the rest of the series.
+    let series: Vec<_> = vtkio::import_series(&dir, "vtk")?.collect();
+    assert_eq!(series.len(), 4);
+    assert!(series[2].is_err());
+    let titles: Vec<_> = series
+        .into_iter()
+        .filter_map(|vtk| vtk.ok())
+        .map(|vtk| vtk.title)
+        .collect();
+    assert_eq!(titles, vec!["Step 1", "Step 2", "Step 10"]);
+
+    assert!(vtkio::import_series(dir.join("missing"), "vtk").is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn parse_error_location_test() -> Result {
+    let header = "# vtk DataFile Version 2.0\nBroken\nASCII\nDATASET UNSTRUCTURED_GRID\n\
+                  POINTS 3 float\n0 0 0\n1 0 0\n0 1 0\nCELLS 1 4\n3 0 1 2\nCELL_TYPES 1\n5\n";
+    for &(body, section, line, token) in &[
+        (
+            "POINT_DATA 3\nSCALARS p float\nLOOKUP_TABLE default\n1 2 x\n",
+            "SCALARS",
+            16,
+            "x",
+        ),
+        ("CELL_DATA 1\nVECTORS v float\n1 2\n", "VECTORS", 15, "2"),
+        ("CELL_DATA 1\nSCALAR p float\n1\n", "SCALAR", 14, "SCALAR"),
+    ] {
+        let file = format!("{}{}", header, body);
+        let options = ReadOptions::default().with_strict_syntax(true);
+        match Vtk::parse_legacy_be_with_options(file.as_bytes(), options) {
+            Err(Error::ParseAt(err)) => {
+                assert_eq!(err.section.as_deref(), Some(section), "{}", body);
+                assert_eq!(err.line, line, "{}", body);
+                assert!(file[err.offset..].starts_with(token), "{}", body);
+            }
+            result => panic!("Unexpected result for {:?}: {:?}", body, result.map(|_| ())),
+        }
+    }
+
+    // Errors in the header have no section.
+    let file = header.replace("ASCII", "ASCI");
+    match Vtk::parse_legacy(file.as_bytes()) {
+        Err(err @ Error::ParseAt(_)) => {
+            assert_eq!(err.to_string(), "Parse error: Tag at line 3 (byte 34)")
+        }
+        result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+    }
+    Ok(())
+}
+
+#[test]
+fn ignored_content_test() -> Result {
+    // Notes appended by exporters are ignored unless the syntax is strict.
+    let cube = std::fs::read_to_string("assets/cube_complex.vtk")?;
+    let annotated = format!("{}Written by exporter v1.2\n", cube);
+    let lines = cube.lines().count();
+    let dir = std::env::temp_dir().join(format!("vtkio_ignored_content_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("annotated.vtk");
+    std::fs::write(&path, &annotated)?;
+    let (vtk, warnings) = Vtk::import_with_warnings(&path, ReadOptions::default())?;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(vtk, Vtk::parse_legacy_be(cube.as_bytes())?);
+    assert_eq!(warnings, vec![Warning::ContentIgnored { line: lines + 1 }]);
+    let strict = ReadOptions::default().with_strict_syntax(true);
+    match Vtk::parse_legacy_with_options(annotated.as_bytes(), strict.clone()) {
+        Err(Error::ParseAt(err)) => assert_eq!(err.line, lines + 1),
+        result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+    }
+
+    // Locating the error doesn't allocate memory for the sizes given in headers.
+    let huge = cube.replace("POLYGONS 6 30", "POLYGONS 6 400000000000");
+    assert!(Vtk::parse_legacy(huge.as_bytes()).is_ok());
+    match Vtk::parse_legacy_with_options(huge.as_bytes(), strict) {
+        Err(Error::ParseAt(err)) => assert_eq!(err.section.as_deref(), Some("POLYGONS")),
+        result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+    }
+    Ok(())
+}
+
+#[test]
+fn skip_unknown_sections_test() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_skip_unknown_sections_test");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("exotic.vtk");
+    std::fs::write(
+        &path,
+        "# vtk DataFile Version 2.0\nExotic\nASCII\nDATASET UNSTRUCTURED_GRID\n\
+         POINTS 3 float\n0 0 0\n1 0 0\n0 1 0\nCELLS 1 4\n3 0 1 2\nCELL_TYPES 1\n5\n\
+         EDGE_FLAGS 1\n0 1 1\nPOINT_DATA 3\nGLOBAL_IDS ids vtkIdType\n4 5 6\n\
+         SCALARS pressure float\nLOOKUP_TABLE default\n1 2 3\n",
+    )?;
+
+    // By default the parser stops at the first unknown section.
+    let (_, warnings) = Vtk::import_with_warnings(&path, ReadOptions::default())?;
+    assert_eq!(warnings, vec![Warning::ContentIgnored { line: 13 }]);
+    let strict = ReadOptions::default().with_strict_syntax(true);
+    assert!(matches!(
+        Vtk::import_with_options(&path, strict),
+        Err(Error::ParseAt(_))
+    ));
+    let options = ReadOptions::default().with_skip_unknown_sections(true);
+    let (vtk, warnings) = Vtk::import_with_warnings(&path, options.clone())?;
+    assert_eq!(
+        warnings,
+        vec![
+            Warning::SectionSkipped {
+                keyword: String::from("EDGE_FLAGS"),
+                line: 13,
+            },
+            Warning::SectionSkipped {
+                keyword: String::from("GLOBAL_IDS"),
+                line: 16,
+            },
+        ]
+    );
+    let piece = UnstructuredGridPiece::from_data_set(vtk.clone().data, None)?;
+    assert_eq!(piece.cells.types, vec![CellType::Triangle]);
+    assert_eq!(piece.data.point.len(), 2);
+    assert_eq!(
+        piece.data.point[1].data_array().unwrap().data,
+        vec![1.0f32, 2.0, 3.0].into()
+    );
+
+    // Unknown attribute sections are kept and written back unchanged.
+    assert_eq!(
+        piece.data.point[0],
+        Attribute::Unknown {
+            keyword: String::from("GLOBAL_IDS"),
+            name: String::from("ids"),
+            raw: String::from("GLOBAL_IDS ids vtkIdType\n4 5 6"),
+        }
+    );
+    let output = String::new().write_vtk_be(vtk.clone())?.clone();
+    assert!(output.contains("\nGLOBAL_IDS ids vtkIdType\n4 5 6\n"));
+    let reparsed = Vtk::parse_legacy_be_with_options(output.as_bytes(), options)?;
+    assert_eq!(reparsed.data, vtk.data);
+
+    // Binary files can't keep them, since the extent of binary data is only guessed.
+    let binary = Vec::<u8>::new().write_vtk_be(vtk)?.clone();
+    let reparsed = Vtk::parse_legacy_be(binary.as_slice())?;
+    let piece = UnstructuredGridPiece::from_data_set(reparsed.data, None)?;
+    assert_eq!(piece.data.point.len(), 1);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn crlf_line_endings_test() -> Result {
+    // ASCII files with Windows line endings parse like the original.
+    let input = std::fs::read_to_string("assets/cube_complex.vtk")?;
+    let crlf = input.replace('\n', "\r\n");
+    assert_eq!(
+        Vtk::parse_legacy_be(crlf.as_bytes())?,
+        Vtk::parse_legacy_be(input.as_bytes())?
+    );
+
+    // Binary data starts right after the line ending, even if its first bytes look like
+    // whitespace. Line endings may be mixed within a file.
+    let mut input = Vec::new();
+    input.extend_from_slice(
+        b"# vtk DataFile Version 2.0\r\nMixed\nBINARY\r\nDATASET POLYDATA\nPOINTS 2 float\r\n",
+    );
+    for &x in [f32::from_bits(0x0d0a_2009), 0.0, 0.0, 1.0, 0.0, 0.0].iter() {
+        input.extend_from_slice(&x.to_be_bytes());
+    }
+    input.extend_from_slice(b"\r\nVERTICES 1 3\n");
+    for &i in [2i32, 0, 1].iter() {
+        input.extend_from_slice(&i.to_be_bytes());
+    }
+    input.extend_from_slice(b"\nPOINT_DATA 2\r\nSCALARS s int\r\nLOOKUP_TABLE default\r\n");
+    for &i in [0x0d0a_0000i32, 5].iter() {
+        input.extend_from_slice(&i.to_be_bytes());
+    }
+    input.extend_from_slice(b"\r\n");
+
+    let vtk = Vtk::parse_legacy_be(input.as_slice())?;
+    let options = ReadOptions::default().with_skip_unknown_sections(true);
+    assert_eq!(
+        Vtk::parse_legacy_with_options(input.as_slice(), options)?,
+        vtk
+    );
+    let piece = PolyDataPiece::from_data_set(vtk.data, None)?;
+    assert_eq!(
+        piece.points,
+        vec![f32::from_bits(0x0d0a_2009), 0.0, 0.0, 1.0, 0.0, 0.0].into()
+    );
+    assert_eq!(
+        piece.data.point[0].data_array().unwrap().data,
+        vec![0x0d0a_0000i32, 5].into()
+    );
+    Ok(())
+}
+
+#[test]
+fn allocation_limits_test() -> Result {
+    use vtkio::LimitExceeded;
+
+    // Files within the limits are read as usual.
+    let vtk = Vtk::import("./assets/cube_complex.vtk")?;
+    let options = ReadOptions::default()
+        .with_max_array_size(1024)
+        .with_max_total_size(4096);
+    assert_eq!(
+        Vtk::import_with_options("./assets/cube_complex.vtk", options)?,
+        vtk
+    );
+
+    // A truncated binary file declaring a huge array is rejected before allocating it.
+    let mut input = b"# vtk DataFile Version 2.0\nHuge\nBINARY\nDATASET POLYDATA\n".to_vec();
+    input.extend_from_slice(b"POINTS 4000000000 double\n");
+    input.extend_from_slice(&[0; 24]);
+    let options = ReadOptions::default().with_max_array_size(1 << 30);
+    match Vtk::parse_legacy_with_options(input.as_slice(), options) {
+        Err(Error::LimitExceeded(e)) => assert_eq!(
+            e,
+            LimitExceeded {
+                size: 96_000_000_000,
+                limit: 1 << 30
+            }
+        ),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    // Arrays are counted together against the total limit.
+    let options = ReadOptions::default().with_max_total_size(100);
+    match Vtk::import_with_options("./assets/cube_complex.vtk", options) {
+        Err(Error::LimitExceeded(e)) => assert!(e.size > e.limit),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    Ok(())
+}
@@ -50,7 +50,7 @@ fn le(vtk: &Vtk) -> Vtk {
     }
 }
 
-fn make_test_file(leading_zero_offset: bool) -> Vtk {
+fn make_test_file() -> Vtk {
     Vtk {
         version: Version::new((5, 1)),
         byte_order: ByteOrder::BigEndian,
@@ -116,17 +116,10 @@ fn make_test_file(leading_zero_offset: bool) -> Vtk {
             .into(),
             cells: Cells {
                 cell_verts: VertexNumbers::XML {
-                    offsets: {
-                        let mut offsets = vec![
-                            2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 27, 30, 33, 36, 39, 42, 45,
-                            48, 51, 54, 57, 60, 63, 66, 69, 72, 75, 78, 81, 84, 87, 90, 91, 92, 93,
-                            94,
-                        ];
-                        if leading_zero_offset {
-                            offsets.insert(0, 0);
-                        }
-                        offsets
-                    },
+                    offsets: vec![
+                        2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 27, 30, 33, 36, 39, 42, 45, 48,
+                        51, 54, 57, 60, 63, 66, 69, 72, 75, 78, 81, 84, 87, 90, 91, 92, 93, 94,
+                    ],
                     connectivity: vec![
                         0, 4, 4, 5, 5, 1, 1, 6, 6, 7, 7, 8, 8, 2, 2, 9, 9, 10, 10, 3, 3, 11, 11, 0,
                         10, 13, 14, 13, 12, 14, 10, 3, 13, 8, 2, 9, 4, 5, 15, 9, 10, 14, 3, 11, 13,
@@ -147,6 +140,7 @@ fn make_test_file(leading_zero_offset: bool) -> Vtk {
             data: Attributes {
                 point: vec![],
                 cell: vec![],
+                field: Vec::new(),
             },
         }),
     }
@@ -155,7 +149,7 @@ fn make_test_file(leading_zero_offset: bool) -> Vtk {
 #[test]
 fn legacy_ascii() -> Result {
     let input = include_str!("../assets/pygmsh/ascii.vtk").as_bytes();
-    let out1 = make_test_file(true);
+    let out1 = make_test_file();
     assert!(parse_be(input).is_done());
     test_ignore_rem!(parse_be(input) => out1);
     let mut outtest = String::new();
@@ -171,7 +165,7 @@ fn legacy_ascii() -> Result {
 #[test]
 fn legacy_binary() -> Result {
     let input = include_bytes!("../assets/pygmsh/binary.vtk");
-    let out1 = make_test_file(true);
+    let out1 = make_test_file();
     assert!(parse_be(input).is_done());
     test_ignore_rem!(parse_be(input) => out1);
     let mut outtest = String::new();
@@ -242,7 +236,7 @@ fn assert_and_fix_xml_vtu(vtu: &mut Vtk) {
 fn xml_ascii() -> Result {
     let mut vtu = Vtk::import("./assets/pygmsh/ascii.vtu")?;
     assert_and_fix_xml_vtu(&mut vtu);
-    let expected = make_test_file(false);
+    let expected = make_test_file();
     compare_points_in_float_and_overwrite(&mut vtu, &expected);
     assert_eq!(vtu, expected);
     Ok(())
@@ -255,7 +249,7 @@ fn xml_ascii() -> Result {
 fn xml_lzma() -> Result {
     let mut vtu = Vtk::import("./assets/pygmsh/lzma.vtu")?;
     assert_and_fix_xml_vtu(&mut vtu);
-    let expected = make_test_file(false);
+    let expected = make_test_file();
     compare_points_in_float_and_overwrite(&mut vtu, &expected);
     assert_eq!(vtu, expected);
     Ok(())
@@ -266,7 +260,7 @@ fn xml_lzma() -> Result {
 fn xml_no_compression() -> Result {
     let mut vtu = Vtk::import("./assets/pygmsh/no-compression.vtu")?;
     assert_and_fix_xml_vtu(&mut vtu);
-    let expected = make_test_file(false);
+    let expected = make_test_file();
     compare_points_in_float_and_overwrite(&mut vtu, &expected);
     assert_eq!(vtu, expected);
     Ok(())
@@ -279,7 +273,7 @@ fn xml_no_compression() -> Result {
 fn xml_zlib() -> Result {
     let mut vtu = Vtk::import("./assets/pygmsh/zlib.vtu")?;
     assert_and_fix_xml_vtu(&mut vtu);
-    let expected = make_test_file(false);
+    let expected = make_test_file();
     compare_points_in_float_and_overwrite(&mut vtu, &expected);
     assert_eq!(vtu, expected);
     Ok(())
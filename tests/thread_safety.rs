@@ -0,0 +1,59 @@
+//! Compile time checks that public types can be shared and sent between threads.
+use vtkio::model::*;
+use vtkio::writer::{AsciiWriter, BinaryWriter, FormattedAsciiWriter, IoWriter, WriterConfig};
+use vtkio::{Error, IOBuffer, ReadOptions, Vtk};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn model_is_send_sync() {
+    assert_send_sync::<Vtk>();
+    assert_send_sync::<IOBuffer>();
+    assert_send_sync::<DataSet>();
+    assert_send_sync::<Piece<UnstructuredGridPiece>>();
+    assert_send_sync::<Attribute>();
+    assert_send_sync::<Attributes>();
+    assert_send_sync::<MetaData>();
+    assert_send_sync::<ReadOptions>();
+}
+
+#[test]
+fn errors_are_send_sync() {
+    assert_send_sync::<Error>();
+    assert_send_sync::<vtkio::model::Error>();
+    assert_send_sync::<vtkio::writer::Error>();
+    #[cfg(feature = "xml")]
+    assert_send_sync::<vtkio::xml::Error>();
+}
+
+#[test]
+fn writers_are_send_sync() {
+    assert_send_sync::<AsciiWriter<String>>();
+    assert_send_sync::<BinaryWriter<Vec<u8>>>();
+    assert_send_sync::<FormattedAsciiWriter<String>>();
+    assert_send_sync::<AsciiWriter<IoWriter<Vec<u8>>>>();
+    assert_send_sync::<WriterConfig>();
+    assert_send_sync::<vtkio::scene::Scene>();
+    assert_send_sync::<vtkio::graph::Graph>();
+    assert_send_sync::<vtkio::stack::ImageStack>();
+    assert_send_sync::<vtkio::point_cloud::PointCloud>();
+    assert_send_sync::<vtkio::render::MeshBuffers>();
+}
+
+#[cfg(feature = "xml")]
+#[test]
+fn xml_types_are_send_sync() {
+    use std::fs::File;
+    use std::io::BufReader;
+    use vtkio::lazy::{LazyReader, LazyTimeSeries, Prefetch};
+
+    assert_send_sync::<vtkio::xml::VTKFile>();
+    assert_send_sync::<vtkio::pvd::Collection>();
+    assert_send_sync::<vtkio::pvd::TimeSeriesWriter>();
+    assert_send_sync::<vtkio::checkpoint::CheckpointWriter>();
+    assert_send_sync::<vtkio::vtm::MultiBlockDataSet>();
+    assert_send_sync::<vtkio::amr::OverlappingAmr>();
+    assert_send_sync::<LazyReader<BufReader<File>>>();
+    assert_send_sync::<LazyTimeSeries>();
+    assert_send_sync::<Prefetch>();
+}
@@ -1,6 +1,7 @@
 #![cfg(feature = "xml")]
+use std::convert::TryFrom;
 use std::io::BufReader;
-use vtkio::{model::*, Error};
+use vtkio::{model::*, Error, FormatFeature};
 
 type Result = std::result::Result<(), Error>;
 
@@ -78,6 +79,7 @@ fn make_box_vtu() -> Vtk {
                     },
                     data: IOBuffer::I32(vec![0, 0, 0, 0, 0, 0]),
                 })],
+                field: Vec::new(),
             },
         }),
     }
@@ -165,6 +167,7 @@ fn make_box_para_vtu() -> Vtk {
                     },
                     data: IOBuffer::I32(vec![0, 0, 0, 0, 0, 0]),
                 })],
+                field: Vec::new(),
             },
         }),
     }
@@ -206,6 +209,7 @@ fn make_hexahedron_vtu() -> Vtk {
             data: Attributes {
                 point: vec![],
                 cell: vec![],
+                field: Vec::new(),
             },
         }),
     }
@@ -228,6 +232,54 @@ fn hexahedron_pvtu() -> Result {
     Ok(())
 }
 
+#[test]
+fn cube_pvtp_load_single_piece() -> Result {
+    let mut vtp = Vtk::import("./assets/cube.pvtp")?;
+    assert_eq!(vtp.data.num_pieces(), 2);
+    assert_eq!(vtp.data.is_piece_loaded(0), Some(false));
+    assert_eq!(vtp.data.is_piece_loaded(1), Some(false));
+    assert_eq!(vtp.data.is_piece_loaded(2), None);
+
+    vtp.load_piece(1)?;
+    assert_eq!(vtp.data.is_piece_loaded(0), Some(false));
+    assert_eq!(vtp.data.is_piece_loaded(1), Some(true));
+    assert!(vtp.load_piece(2).is_err());
+
+    let source = Vtk::import("./assets/polyEx0.vtp")?;
+    match vtp.data {
+        DataSet::PolyData { pieces, .. } => {
+            assert_eq!(pieces[1], Piece::Loaded(Box::new(source.data)));
+        }
+        _ => panic!("Expected poly data"),
+    }
+    Ok(())
+}
+
+#[test]
+fn cube_pvtp_piece_iter() -> Result {
+    let source = Vtk::import("./assets/polyEx0.vtp")?;
+    let vtp = Vtk::import("./assets/cube.pvtp")?;
+    let pieces = vtp.clone().into_piece_iter();
+    assert_eq!(pieces.len(), 2);
+    for piece in pieces {
+        let piece = piece?;
+        assert_eq!(piece.data.num_pieces(), 1);
+        assert_eq!(piece.data.is_piece_loaded(0), Some(true));
+        assert_eq!(
+            PolyDataPiece::try_from(piece.data).unwrap(),
+            PolyDataPiece::try_from(source.data.clone()).unwrap()
+        );
+    }
+
+    // Pieces that fail to load are reported individually.
+    let mut missing = vtp;
+    missing.file_path = Some(std::env::temp_dir().join("vtkio_missing/cube.pvtp"));
+    let results: Vec<_> = missing.into_piece_iter().collect();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|piece| piece.is_err()));
+    Ok(())
+}
+
 #[cfg(feature = "compression")]
 #[test]
 fn hexahedron_lzma_pvtu() -> Result {
@@ -247,6 +299,96 @@ fn hexahedron_zlib() -> Result {
     Ok(())
 }
 
+#[cfg(feature = "compression")]
+#[test]
+fn writer_config_xml() -> Result {
+    use vtkio::writer::{OutputFormat, WriterConfig};
+    use vtkio::xml::Compressor;
+
+    let vtk = make_hexahedron_vtu();
+    let config = WriterConfig::new()
+        .with_format(OutputFormat::Xml)
+        .with_compression(Compressor::ZLib, 5)
+        .with_byte_order(ByteOrder::BigEndian);
+    let mut output = Vec::new();
+    config.write(vtk.clone(), &mut output)?;
+    assert!(String::from_utf8_lossy(&output).contains("vtkZLibDataCompressor"));
+
+    let round_trip = Vtk::parse_xml(output.as_slice())?;
+    assert_eq!(round_trip.byte_order, ByteOrder::BigEndian);
+    assert_eq!(round_trip.data, vtk.data);
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn writer_config_deterministic() -> Result {
+    use vtkio::writer::{OutputFormat, WriterConfig};
+    use vtkio::xml::Compressor;
+
+    let mut configs = vec![
+        WriterConfig::new(),
+        WriterConfig::new().with_format(OutputFormat::LegacyAscii),
+    ];
+    let mut compressors = vec![
+        Compressor::None,
+        Compressor::ZLib,
+        Compressor::LZ4,
+        Compressor::LZMA,
+    ];
+    if cfg!(feature = "zstd") {
+        compressors.push(Compressor::Zstd);
+    }
+    for compressor in compressors {
+        configs.push(
+            WriterConfig::new()
+                .with_format(OutputFormat::Xml)
+                .with_compression(compressor, 9),
+        );
+    }
+
+    // Several attributes, so they are encoded in parallel with the `rayon` feature.
+    let mut vtk = make_hexahedron_vtu();
+    if let DataSet::UnstructuredGrid { pieces, .. } = &mut vtk.data {
+        if let Piece::Inline(piece) = &mut pieces[0] {
+            for i in 0..8 {
+                let values: Vec<f64> = (0..8).map(|j| f64::from(i * j) / 3.0).collect();
+                let point = Attribute::scalars(format!("p{}", i), 1).with_data(values);
+                let cell = Attribute::scalars(format!("c{}", i), 1).with_data(vec![i]);
+                piece.data.point.push(point);
+                piece.data.cell.push(cell);
+            }
+        }
+    }
+
+    // Repeated writes of equal models produce identical bytes, matching the serial output.
+    for config in configs {
+        let mut first = Vec::new();
+        config.write(vtk.clone(), &mut first)?;
+        let mut second = Vec::new();
+        config.write(vtk.clone(), &mut second)?;
+        assert_eq!(first, second, "{:?}", config);
+        let mut serial = Vec::new();
+        config
+            .with_deterministic(true)
+            .write(vtk.clone(), &mut serial)?;
+        assert_eq!(first, serial, "{:?}", config);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn hexahedron_zlib_format_report() -> Result {
+    let (vtu, report) = Vtk::import_with_report("./assets/hexahedron_zlib.vtu")?;
+    assert!(report.xml);
+    assert_eq!(report.version, vtu.version);
+    assert!(report.has(FormatFeature::Compression));
+    assert!(report.has(FormatFeature::Int64Ids));
+    assert!(!report.has(FormatFeature::HigherOrderCells));
+    Ok(())
+}
+
 #[cfg(feature = "compression")]
 #[test]
 fn hexahedron_zlib_binary() -> Result {
@@ -311,6 +453,7 @@ fn make_tet_vtu() -> Vtk {
                     },
                     data: IOBuffer::I32(vec![1]),
                 })],
+                field: Vec::new(),
             },
         }),
     }
@@ -323,3 +466,636 @@ fn single_tet_vtu() -> Result {
     assert_eq!(vtu, make_tet_vtu());
     Ok(())
 }
+
+#[test]
+fn rectilinear_grid_vtr_round_trip() -> Result {
+    let mut vtr = Vtk::import("./assets/RectilinearGrid_ascii.vtr")?;
+    vtr.file_path = None; // Reset file path to satisfy comparison
+    let mut output: Vec<u8> = Vec::new();
+    vtr.clone().write_xml(&mut output)?;
+    let round_trip = Vtk::parse_xml(BufReader::new(output.as_slice()))?;
+    assert_eq!(round_trip, vtr);
+    Ok(())
+}
+
+#[test]
+fn rectilinear_grid_legacy_to_vtr() -> Result {
+    let mut vtk = Vtk::import("./assets/rectilinear_grid.vtk")?;
+    vtk.file_path = None; // Reset file path to satisfy comparison
+    let mut output: Vec<u8> = Vec::new();
+    vtk.clone().write_xml(&mut output)?;
+    let vtr = Vtk::parse_xml(BufReader::new(output.as_slice()))?;
+
+    let coords = |vtk: Vtk| match vtk.data {
+        DataSet::RectilinearGrid { extent, pieces, .. } => {
+            let piece = pieces[0].load_piece_data(None).unwrap();
+            (extent.into_dims(), piece.coords)
+        }
+        _ => panic!("Expected a rectilinear grid"),
+    };
+
+    // Legacy dimensions are converted into inclusive XML extents.
+    assert_eq!(coords(vtr), coords(vtk));
+    Ok(())
+}
+
+fn make_structured_grid_vts() -> Vtk {
+    Vtk {
+        version: Version { major: 1, minor: 0 },
+        title: String::new(),
+        byte_order: ByteOrder::LittleEndian,
+        file_path: None,
+        data: DataSet::inline(StructuredGridPiece {
+            extent: Extent::Ranges([0..=1, 0..=1, 0..=1]),
+            points: vec![
+                0_f32, 0.2, 0., 0.1, 0.184843, 0., 0., 0.25, 0., 0.1, 0.234843, 0., 0., 0.2,
+                0.333333, 0.1, 0.184843, 0.333333, 0., 0.25, 0.333333, 0.1, 0.234843, 0.333333,
+            ]
+            .into(),
+            data: Attributes {
+                point: vec![
+                    Attribute::scalars("ptval", 1)
+                        .with_data(vec![0f32, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]),
+                    Attribute::vectors("ptvec").with_data(vec![
+                        0_f32, 0.0287671, 0., 0., 0.0258604, 0., 0., 0.0287671, 0., 0., 0.0258604,
+                        0., 0., 0.0287671, 0., 0., 0.0258604, 0., 0., 0.0287671, 0., 0., 0.0258604,
+                        0.,
+                    ]),
+                ],
+                cell: vec![
+                    Attribute::scalars("cellval", 1).with_data(vec![1489.0f32]),
+                    Attribute::vectors("cellvec").with_data(vec![0.6f32, 0.7, 0.5]),
+                ],
+                field: Vec::new(),
+            },
+        }),
+    }
+}
+
+#[test]
+fn structured_grid_vts() -> Result {
+    let mut vts = Vtk::import("./assets/structured_grid.vts")?;
+    vts.file_path = None; // Reset file path to satisfy comparison
+    assert_eq!(vts, make_structured_grid_vts());
+
+    // Round trip through the XML writer.
+    let mut output: Vec<u8> = Vec::new();
+    vts.clone().write_xml(&mut output)?;
+    let round_trip = Vtk::parse_xml(BufReader::new(output.as_slice()))?;
+    assert_eq!(round_trip, vts);
+    Ok(())
+}
+
+#[test]
+fn structured_grid_legacy_to_vts() -> Result {
+    let vtk = Vtk::import("./assets/structured_grid.vtk")?;
+    let mut output: Vec<u8> = Vec::new();
+    vtk.write_xml(&mut output)?;
+    let vts = Vtk::parse_xml(BufReader::new(output.as_slice()))?;
+
+    // Legacy files carry a title and version which are not transferred here.
+    let expected = Vtk {
+        version: Version { major: 3, minor: 0 },
+        byte_order: ByteOrder::BigEndian,
+        ..make_structured_grid_vts()
+    };
+    assert_eq!(vts, expected);
+    Ok(())
+}
+
+#[test]
+fn hexahedron_appended_raw_round_trip() -> Result {
+    let vtu = make_hexahedron_vtu();
+    let mut output: Vec<u8> = Vec::new();
+    vtu.clone().write_xml_appended(&mut output)?;
+    let xml = String::from_utf8_lossy(&output);
+    assert!(xml.contains("<AppendedData encoding=\"raw\">_"));
+    assert!(!xml.contains("format=\"binary\""));
+    let round_trip = Vtk::parse_xml(BufReader::new(output.as_slice()))?;
+    assert_eq!(round_trip, vtu);
+    Ok(())
+}
+
+#[test]
+fn hexahedron_with_fields_from() -> Result {
+    let expected = Vtk::import("./assets/hexahedron_inline_binary.vtu")?;
+
+    // Strip attributes from the geometry.
+    let mut mesh = expected.clone();
+    if let DataSet::UnstructuredGrid { pieces, .. } = &mut mesh.data {
+        for piece in pieces.iter_mut() {
+            if let Piece::Inline(piece) = piece {
+                piece.data = Attributes::new();
+            }
+        }
+    }
+    assert_ne!(mesh, expected);
+
+    let vtu = mesh.with_fields_from("./assets/hexahedron_inline_binary.vtu")?;
+    assert_eq!(vtu, expected);
+
+    // Fields from a data set with a different number of points are rejected.
+    let result = make_box_vtu().with_fields_from("./assets/hexahedron_inline_binary.vtu");
+    assert!(matches!(
+        result,
+        Err(Error::Load(
+            vtkio::model::Error::AttributeSizeMismatch { .. }
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn box_pvtu_pieces() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_box_pvtu_pieces");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("pieces")).unwrap();
+    for i in 0..2 {
+        make_box_vtu().export(dir.join(format!("pieces/box_{}.vtu", i)))?;
+    }
+    std::fs::write(
+        dir.join("box.pvtu"),
+        r#"<VTKFile type="PUnstructuredGrid" version="1.0" byte_order="LittleEndian" header_type="UInt64">
+  <PUnstructuredGrid GhostLevel="0">
+    <PPointData>
+      <PDataArray type="Float32" Name="pressure" NumberOfComponents="1"/>
+    </PPointData>
+    <PPoints>
+      <PDataArray type="Float64" Name="Points" NumberOfComponents="3"/>
+    </PPoints>
+    <Piece Source="pieces/box_0.vtu"/>
+    <Piece Source="pieces/box_1.vtu"/>
+  </PUnstructuredGrid>
+</VTKFile>"#,
+    )
+    .unwrap();
+
+    let piece =
+        UnstructuredGridPiece::try_from(Vtk::import(dir.join("pieces/box_0.vtu"))?.data).unwrap();
+    let pvtu = Vtk::import(dir.join("box.pvtu"))?;
+    let pieces = pvtu.clone().into_unstructured_grid_pieces().unwrap();
+    assert_eq!(pieces, vec![piece.clone(), piece.clone()]);
+
+    let merged = pvtu.into_merged_unstructured_grid().unwrap();
+    let merged = UnstructuredGridPiece::try_from(merged.data).unwrap();
+    assert_eq!(merged.num_points(), 2 * piece.num_points());
+    assert_eq!(merged.cells.num_cells(), 2 * piece.cells.num_cells());
+    assert!(merged.check_cells().is_empty());
+    let (connectivity, offsets) = piece.cells.cell_verts.clone().into_xml();
+    let expected_connectivity: Vec<u64> = connectivity
+        .iter()
+        .copied()
+        .chain(connectivity.iter().map(|&v| v + 8))
+        .collect();
+    assert_eq!(
+        merged.cells.cell_verts,
+        VertexNumbers::XML {
+            connectivity: expected_connectivity,
+            offsets: offsets
+                .iter()
+                .copied()
+                .chain(offsets.iter().map(|&o| o + 24))
+                .collect(),
+        }
+    );
+    for (attrib, expected) in merged.data.point.iter().zip(piece.data.point.iter()) {
+        let len = expected.data_array().unwrap().len();
+        assert_eq!(attrib.name(), expected.name());
+        assert_eq!(attrib.data_array().unwrap().len(), 2 * len);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn empty_data_sets_round_trip() -> Result {
+    let empty = || Extent::Ranges(Extent::Dims([0, 0, 0]).into_ranges());
+    let vtk = |data| Vtk {
+        version: Version::new((1, 0)),
+        byte_order: ByteOrder::LittleEndian,
+        title: String::new(),
+        file_path: None,
+        data,
+    };
+    let data_sets = vec![
+        DataSet::inline(UnstructuredGridPiece {
+            points: Vec::<f32>::new().into(),
+            cells: Cells::default(),
+            data: Attributes {
+                point: vec![Attribute::scalars("p", 1).with_data(Vec::<f32>::new())],
+                cell: vec![Attribute::scalars("c", 1).with_data(Vec::<i32>::new())],
+                field: Vec::new(),
+            },
+        }),
+        DataSet::inline(PolyDataPiece {
+            points: Vec::<f32>::new().into(),
+            ..Default::default()
+        }),
+        DataSet::ImageData {
+            extent: empty(),
+            origin: [0.0; 3],
+            spacing: [1.0; 3],
+            meta: None,
+            pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                extent: empty(),
+                data: Attributes::new(),
+            }))],
+        },
+        DataSet::inline(StructuredGridPiece {
+            extent: empty(),
+            points: Vec::<f32>::new().into(),
+            data: Attributes::new(),
+        }),
+        DataSet::inline(RectilinearGridPiece {
+            extent: empty(),
+            coords: Coordinates {
+                x: Vec::<f32>::new().into(),
+                y: Vec::<f32>::new().into(),
+                z: Vec::<f32>::new().into(),
+            },
+            data: Attributes::new(),
+        }),
+    ];
+    for data in data_sets {
+        let mut output: Vec<u8> = Vec::new();
+        vtk(data.clone()).write_xml(&mut output)?;
+        assert_eq!(Vtk::parse_xml(output.as_slice())?, vtk(data));
+    }
+    Ok(())
+}
+
+#[test]
+fn empty_pieces() -> Result {
+    // Empty pieces may omit points and cells.
+    let vtu = Vtk::parse_xml(
+        r#"<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian">
+  <UnstructuredGrid>
+    <Piece NumberOfPoints="0" NumberOfCells="0">
+      <PointData>
+        <DataArray type="Float32" Name="p" format="ascii"></DataArray>
+      </PointData>
+    </Piece>
+  </UnstructuredGrid>
+</VTKFile>"#
+            .as_bytes(),
+    )?;
+    let piece = UnstructuredGridPiece::try_from(vtu.data).unwrap();
+    assert_eq!(piece.num_points(), 0);
+    assert_eq!(piece.cells.num_cells(), 0);
+    assert_eq!(
+        piece.data.point,
+        vec![Attribute::generic("p", 1).with_data(Vec::<f32>::new())]
+    );
+
+    // Files may also have no pieces at all.
+    let vtu = Vtk::parse_xml(
+        r#"<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian">
+  <UnstructuredGrid/>
+</VTKFile>"#
+            .as_bytes(),
+    )?;
+    assert_eq!(vtu.data.num_pieces(), 0);
+    Ok(())
+}
+
+#[test]
+fn mismatched_attribute_lengths() -> Result {
+    use vtkio::{LengthPolicy, ReadOptions, Warning};
+
+    // The vector attribute is one tuple short and the scalar attribute has one extra value.
+    let input = r#"<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian">
+  <UnstructuredGrid>
+    <Piece NumberOfPoints="3" NumberOfCells="0">
+      <PointData>
+        <DataArray type="Float32" Name="v" NumberOfComponents="3" format="ascii">
+          1 2 3 4 5 6
+        </DataArray>
+        <DataArray type="Int32" Name="s" format="ascii">1 2 3 4</DataArray>
+      </PointData>
+    </Piece>
+  </UnstructuredGrid>
+</VTKFile>"#;
+
+    let dir = std::env::temp_dir().join("vtkio_mismatched_attribute_lengths");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("mismatched.vtu");
+    std::fs::write(&path, input)?;
+    let read = |options: ReadOptions| -> std::result::Result<_, Error> {
+        let (vtk, warnings) = Vtk::import_with_warnings(&path, options)?;
+        let piece = UnstructuredGridPiece::try_from(vtk.data).unwrap();
+        Ok((piece.data.point, warnings))
+    };
+    let v = |data: Vec<f32>| Attribute::generic("v", 3).with_data(data);
+    let s = Attribute::generic("s", 1).with_data(vec![1i32, 2, 3]);
+    let warning = |name: &str, expected, actual, action| Warning::AttributeLengthMismatch {
+        name: name.to_string(),
+        expected,
+        actual,
+        action,
+    };
+
+    // Strict options discard mismatched arrays regardless of the policy.
+    let strict = ReadOptions::strict().with_length_policy(LengthPolicy::Zero);
+    let (point, warnings) = read(strict)?;
+    assert!(point.is_empty());
+    assert_eq!(
+        warnings,
+        vec![
+            warning("v", 9, 6, LengthPolicy::Discard),
+            warning("s", 3, 4, LengthPolicy::Discard)
+        ]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "Attribute \"v\" has 6 values, but 9 were expected; the attribute was discarded"
+    );
+    assert_eq!(Vtk::parse_xml(input.as_bytes())?.data, {
+        let vtk = Vtk::parse_xml_with_options(input.as_bytes(), ReadOptions::tolerant())?;
+        vtk.data
+    });
+
+    let tolerant = ReadOptions::tolerant();
+    let (point, warnings) = read(tolerant.clone().with_length_policy(LengthPolicy::Truncate))?;
+    assert_eq!(point, vec![s.clone()]);
+    assert_eq!(
+        warnings,
+        vec![
+            warning("v", 9, 6, LengthPolicy::Discard),
+            warning("s", 3, 4, LengthPolicy::Truncate)
+        ]
+    );
+
+    let (point, warnings) = read(
+        tolerant
+            .clone()
+            .with_length_policy(LengthPolicy::RepeatLast),
+    )?;
+    assert_eq!(
+        point,
+        vec![v(vec![1., 2., 3., 4., 5., 6., 4., 5., 6.]), s.clone()]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "Attribute \"v\" has 6 values, but 9 were expected; the last tuple was repeated"
+    );
+
+    let (point, _) = read(tolerant.with_length_policy(LengthPolicy::Zero))?;
+    assert_eq!(point, vec![v(vec![1., 2., 3., 4., 5., 6., 0., 0., 0.]), s]);
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[test]
+fn array_filter() -> Result {
+    use vtkio::ReadOptions;
+
+    // The skipped array has the wrong length, but isn't decoded, so no warning is reported.
+    let input = r#"<VTKFile type="UnstructuredGrid" version="1.0" byte_order="LittleEndian">
+  <UnstructuredGrid>
+    <Piece NumberOfPoints="2" NumberOfCells="1">
+      <PointData>
+        <DataArray type="Float32" Name="pressure" format="ascii">1 2 3</DataArray>
+        <DataArray type="Float32" Name="temperature" format="ascii">4 5</DataArray>
+      </PointData>
+      <CellData>
+        <DataArray type="Int32" Name="id" format="ascii">7</DataArray>
+      </CellData>
+    </Piece>
+  </UnstructuredGrid>
+</VTKFile>"#;
+
+    let options = ReadOptions::default()
+        .with_array_filter(vec![String::from("temperature"), String::from("id")]);
+    let dir = std::env::temp_dir().join("vtkio_array_filter");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("filtered.vtu");
+    std::fs::write(&path, input)?;
+    let (vtk, warnings) = Vtk::import_with_warnings(&path, options)?;
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(warnings.is_empty());
+    let piece = UnstructuredGridPiece::try_from(vtk.data).unwrap();
+    assert_eq!(
+        piece.data.point,
+        vec![Attribute::generic("temperature", 1).with_data(vec![4.0f32, 5.0])]
+    );
+    assert_eq!(
+        piece.data.cell,
+        vec![Attribute::generic("id", 1).with_data(vec![7i32])]
+    );
+    Ok(())
+}
+
+#[test]
+fn allocation_limits() -> Result {
+    use vtkio::ReadOptions;
+
+    let input = r#"<VTKFile type="PolyData" version="1.0" byte_order="LittleEndian">
+  <PolyData>
+    <Piece NumberOfPoints="4000000000">
+      <Points>
+        <DataArray type="Float64" NumberOfComponents="3" format="ascii">0 0 0</DataArray>
+      </Points>
+    </Piece>
+  </PolyData>
+</VTKFile>"#;
+
+    let options = ReadOptions::default().with_max_total_size(1 << 30);
+    match Vtk::parse_xml_with_options(input.as_bytes(), options) {
+        Err(Error::LimitExceeded(e)) => assert_eq!(e.size, 96_000_000_000),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let options = ReadOptions::default().with_max_array_size(1024);
+    assert_eq!(
+        Vtk::import_with_options("./assets/hexahedron_zlib.vtu", options)?,
+        Vtk::import("./assets/hexahedron_zlib.vtu")?
+    );
+    Ok(())
+}
+
+#[test]
+fn hexahedron_pvd() -> Result {
+    use std::path::Path;
+    use vtkio::pvd::Collection;
+
+    let mut collection = Collection::import("./assets/hexahedron_series.pvd")?;
+    assert_eq!(collection.data_sets.len(), 3);
+    assert_eq!(collection.timesteps(), vec![0.0, 0.5]);
+    assert_eq!(collection.at_timestep(0.5).count(), 2);
+    for data_set in collection.data_sets.iter() {
+        assert!(data_set.data.is_none());
+        assert_eq!(data_set.group, "");
+    }
+    let second = &collection.data_sets[2];
+    assert_eq!(second.part, 1);
+    assert_eq!(second.file, Path::new("./assets/hexahedron_parallel.pvtu"));
+
+    // Lazy loading.
+    let mut vtu = collection.data_sets[0].load()?.clone();
+    vtu.file_path = None;
+    assert_eq!(vtu, make_hexahedron_vtu());
+    assert!(collection.data_sets[0].data.is_some());
+    assert!(collection.data_sets[1].data.is_none());
+
+    // Eager loading.
+    let collection = Collection::import_loaded("./assets/hexahedron_series.pvd")?;
+    assert!(collection.data_sets.iter().all(|d| d.data.is_some()));
+    let mut vtu = collection.data_sets[0].clone().into_vtk()?;
+    vtu.file_path = None;
+    assert_eq!(vtu, make_hexahedron_vtu());
+    Ok(())
+}
+
+#[test]
+fn hexahedron_vtm() -> Result {
+    use std::path::Path;
+    use vtkio::vtm::{Block, MultiBlockDataSet};
+
+    let mut multi_block = MultiBlockDataSet::import("./assets/hexahedron_blocks.vtm")?;
+    assert_eq!(multi_block.blocks.len(), 2);
+    assert_eq!(multi_block.blocks[0].name(), Some("Solid"));
+    assert_eq!(multi_block.blocks[1].name(), None);
+    match &multi_block.blocks[0] {
+        Block::MultiBlock { blocks, .. } => {
+            assert_eq!(blocks.len(), 2);
+            assert_eq!(blocks[1].name(), Some("Boundary"));
+        }
+        _ => panic!("expected a nested multiblock"),
+    }
+    let names: Vec<_> = multi_block
+        .data_sets()
+        .iter()
+        .map(|d| d.name.as_deref())
+        .collect();
+    assert_eq!(
+        names,
+        vec![Some("hexahedron"), Some("empty"), Some("point"), None]
+    );
+    let data_sets = multi_block.data_sets();
+    assert_eq!(
+        data_sets[0].file.as_deref(),
+        Some(Path::new("./assets/hexahedron.vtu"))
+    );
+    assert!(data_sets[1].file.is_none());
+    assert!(data_sets.iter().all(|d| d.data.is_none()));
+
+    multi_block.load_all()?;
+    let data_sets = multi_block.data_sets();
+    assert!(data_sets[1].data.is_none());
+    let mut vtu = data_sets[0].clone().into_vtk()?.unwrap();
+    vtu.file_path = None;
+    assert_eq!(vtu, make_hexahedron_vtu());
+    assert!(data_sets[2].data.is_some());
+    assert!(data_sets[3].data.is_some());
+    Ok(())
+}
+
+#[test]
+fn volume_amr() -> Result {
+    use std::path::Path;
+    use vtkio::amr::OverlappingAmr;
+
+    let amr = OverlappingAmr::import_loaded("./assets/volume_amr.vthb")?;
+    assert_eq!(amr.origin, [0.0; 3]);
+    assert_eq!(amr.grid_description, "XYZ");
+
+    // Levels are sorted from coarsest to finest.
+    assert_eq!(amr.levels.len(), 2);
+    assert_eq!(amr.levels[0].spacing, [1.0; 3]);
+    assert_eq!(amr.refinement_ratio(1), Some(4));
+    let coarse = &amr.levels[0].boxes[0];
+    assert_eq!(coarse.amr_box, [0, 1, 0, 2, 0, 4]);
+    assert_eq!(
+        coarse.file.as_deref(),
+        Some(Path::new("./assets/volume_complex.vti"))
+    );
+    let mut vti = coarse.data.clone().unwrap();
+    vti.file_path = None;
+    assert_eq!(vti.data, Vtk::import("./assets/volume_complex.vti")?.data);
+
+    // Empty boxes have no data.
+    let fine = &amr.levels[1].boxes[0];
+    assert_eq!(fine.amr_box, [4, 7, 4, 7, 4, 7]);
+    assert!(fine.file.is_none());
+    assert!(fine.data.is_none());
+    Ok(())
+}
+
+#[test]
+fn degenerate_structured_data_round_trip() -> Result {
+    for dims in [[3, 4, 1], [1, 3, 1], [3, 1, 2], [1, 1, 1]] {
+        let extent = Extent::Ranges(Extent::Dims(dims).into_ranges());
+        let num_points = extent.num_points() as usize;
+        let attributes = Attributes {
+            point: vec![Attribute::scalars("p", 1).with_data(vec![1.0f32; num_points])],
+            cell: vec![
+                Attribute::scalars("c", 1).with_data(vec![2i32; extent.num_cells() as usize])
+            ],
+            field: Vec::new(),
+        };
+        let data_sets = vec![
+            DataSet::ImageData {
+                extent: extent.clone(),
+                origin: [0.0; 3],
+                spacing: [1.0; 3],
+                meta: None,
+                pieces: vec![Piece::Inline(Box::new(ImageDataPiece {
+                    extent: extent.clone(),
+                    data: attributes.clone(),
+                }))],
+            },
+            DataSet::inline(StructuredGridPiece {
+                extent: extent.clone(),
+                points: vec![0.0f32; 3 * num_points].into(),
+                data: attributes.clone(),
+            }),
+            DataSet::inline(RectilinearGridPiece {
+                extent: extent.clone(),
+                coords: Coordinates {
+                    x: vec![0.0f32; dims[0] as usize].into(),
+                    y: vec![0.0f32; dims[1] as usize].into(),
+                    z: vec![0.0f32; dims[2] as usize].into(),
+                },
+                data: attributes.clone(),
+            }),
+        ];
+        for data in data_sets {
+            let vtk = Vtk {
+                version: Version::new((1, 0)),
+                byte_order: ByteOrder::LittleEndian,
+                title: String::new(),
+                file_path: None,
+                data,
+            };
+            let mut output: Vec<u8> = Vec::new();
+            vtk.clone().write_xml(&mut output)?;
+            assert_eq!(Vtk::parse_xml(output.as_slice())?, vtk);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn import_detects_format() -> Result {
+    let dir = std::env::temp_dir().join("vtkio_import_detects_format");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+
+    // Files without a known extension are recognized by their contents.
+    let legacy = vtkio::import("assets/tet.vtk")?;
+    std::fs::copy("assets/tet.vtk", dir.join("tet"))?;
+    assert_eq!(vtkio::import(dir.join("tet"))?, legacy);
+    let xml = vtkio::import("assets/hexahedron.vtu")?;
+    for name in &["hexahedron.xml", "hexahedron.vtk"] {
+        std::fs::copy("assets/hexahedron.vtu", dir.join(name))?;
+        assert_eq!(vtkio::import(dir.join(name))?.data, xml.data);
+    }
+
+    std::fs::write(dir.join("notes.txt"), "Not a VTK file")?;
+    assert!(matches!(
+        vtkio::import(dir.join("notes.txt")),
+        Err(Error::UnknownFileExtension(Some(ext))) if ext == "txt"
+    ));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}